@@ -2,11 +2,11 @@ namespace rs rpc.raft
 
 
 struct RaftRequest {
-    1: required string data,
+    1: required binary data,
 }
 
 struct RaftReply {
-    1: required string data,
+    1: required binary data,
     2: required string error
 }
 
@@ -0,0 +1,356 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::conditional;
+use crate::config;
+use crate::config::RestrictedOperation;
+use crate::durability;
+use crate::err::AppError;
+use crate::fs;
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::standby;
+use crate::HandlerResponse;
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::{Query, State};
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// --- 扩展：访问记录。开启 --track-access 后累计每个对象的读取次数和最后读取时间，
+// 定期批量通过 raft 写入桶目录下的 .access.json；--expire-unread 按最后读取时间删除冷对象
+
+const ACCESS_FILE: &str = ".access.json";
+// 累计的访问记录写入日志的间隔
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+// 检查过期对象的间隔
+const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+// 管理接口默认返回的条目数
+const DEFAULT_LIMIT: usize = 100;
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/buckets/{bucket}/access", web::get().to(list_access));
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessRecord {
+    pub count: u64,
+    pub last_access: DateTime<Utc>,
+}
+
+// 一个桶的访问记录，键为对象键
+pub type AccessRecords = BTreeMap<String, AccessRecord>;
+
+// 尚未写入日志的访问：桶 -> 对象键 -> 记录
+static PENDING: Mutex<BTreeMap<String, HashMap<String, AccessRecord>>> =
+    Mutex::new(BTreeMap::new());
+// 应用日志和清理过期记录时串行读写记录文件
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+fn records_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, ACCESS_FILE)
+}
+
+// 读取桶的访问记录，文件不存在或无法解析时为空
+pub(crate) fn load(bucket: &str) -> AccessRecords {
+    std::fs::read(records_path(bucket))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+// 记录一次对象读取
+pub(crate) fn record(bucket: &str, key: &str) {
+    if !config::get().access_tracking {
+        return;
+    }
+    let now = Utc::now();
+    let mut pending = PENDING.lock().unwrap();
+    let entry = pending
+        .entry(bucket.to_string())
+        .or_default()
+        .entry(key.to_string())
+        .or_insert(AccessRecord {
+            count: 0,
+            last_access: now,
+        });
+    entry.count += 1;
+    entry.last_access = now;
+}
+
+// 把新的访问合并进已有的记录：次数累加，最后读取时间取较晚者
+pub fn merge(records: &mut AccessRecords, updates: Vec<(String, AccessRecord)>) {
+    for (key, update) in updates {
+        match records.get_mut(&key) {
+            Some(record) => {
+                record.count += update.count;
+                record.last_access = record.last_access.max(update.last_access);
+            }
+            None => {
+                records.insert(key, update);
+            }
+        }
+    }
+}
+
+fn save(bucket: &str, records: &AccessRecords) -> anyhow::Result<()> {
+    let path = records_path(bucket);
+    if records.is_empty() {
+        let _ = std::fs::remove_file(&path);
+        return Ok(());
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(records)?).context("写入访问记录失败")?;
+    std::fs::rename(&tmp, &path).context("写入访问记录失败")?;
+    durability::enqueue(path);
+    Ok(())
+}
+
+// 应用日志中的一批访问记录；桶已删除时丢弃
+pub(crate) fn apply(bucket: &str, updates: Vec<(String, AccessRecord)>) -> anyhow::Result<()> {
+    let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket);
+    if !bucket_dir.is_dir() {
+        return Ok(());
+    }
+    let _guard = FILE_LOCK.lock().unwrap();
+    let mut records = load(bucket);
+    merge(&mut records, updates);
+    save(bucket, &records)
+}
+
+// 应用删除对象的日志时一并删除其访问记录
+pub(crate) fn forget(meta_file_path: &str) -> anyhow::Result<()> {
+    let Some((bucket, key)) = fs::object_path_from_meta(meta_file_path).and_then(|path| {
+        path.split_once('/')
+            .map(|(b, k)| (b.to_string(), k.to_string()))
+    }) else {
+        return Ok(());
+    };
+    if !records_path(&bucket).exists() {
+        return Ok(());
+    }
+    let _guard = FILE_LOCK.lock().unwrap();
+    let mut records = load(&bucket);
+    if records.remove(&key).is_some() {
+        save(&bucket, &records)?;
+    }
+    Ok(())
+}
+
+// 把累计的访问记录写入日志；写入失败（如当前节点不是主节点）时丢弃这批记录
+pub(crate) async fn flush(app: &App) {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+    for (bucket_name, updates) in pending {
+        let updates: Vec<(String, AccessRecord)> = updates.into_iter().collect();
+        let count = updates.len();
+        if let Err(err) = app
+            .client_write(Request::RecordAccess {
+                bucket_name: bucket_name.clone(),
+                updates,
+            })
+            .await
+        {
+            warn!(
+                "写入桶 {} 的 {} 条访问记录失败: {:#}",
+                bucket_name, count, err
+            );
+        }
+    }
+}
+
+// 对象是否已超过 days 天未被读取，从未读取的对象以最后修改时间计算
+pub fn is_unread_for(
+    last_access: Option<DateTime<Utc>>,
+    modified: DateTime<Utc>,
+    days: u64,
+    now: DateTime<Utc>,
+) -> bool {
+    let since = last_access.map_or(modified, |last| last.max(modified));
+    now - since > Duration::days(days as i64)
+}
+
+// 按 --expire-unread 删除冷对象，只在主节点上执行
+async fn sweep(app: &App) -> anyhow::Result<()> {
+    let cfg = config::get();
+    let now = Utc::now();
+    for rule in &cfg.unread_expirations {
+        let bucket = rule.prefix.split('/').next().unwrap_or_default();
+        if cfg.is_denied(bucket, RestrictedOperation::Delete) {
+            warn!("桶 {} 禁止删除，跳过过期规则 {}", bucket, rule.prefix);
+            continue;
+        }
+        let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+            .join(BASIC_PATH_SUFFIX)
+            .join(bucket);
+        if !bucket_dir.is_dir() {
+            continue;
+        }
+        let records = load(bucket);
+        let mut meta_files = vec![];
+        fs::walk_meta_files(&bucket_dir, &mut meta_files).context("遍历桶目录失败")?;
+        let mut expired = 0;
+        for meta_file in meta_files {
+            let Some(object_path) = fs::object_path_from_meta(&meta_file) else {
+                continue;
+            };
+            if !config::prefix_matches(&rule.prefix, &object_path) {
+                continue;
+            }
+            let Ok(metadata) = fs::load_metadata(&meta_file) else {
+                continue;
+            };
+            let key = &object_path[bucket.len() + 1..];
+            let last_access = records.get(key).map(|r| r.last_access);
+            if is_unread_for(last_access, metadata.time, rule.days, now) {
+                // 仅删除扫描时的版本，期间被覆盖的对象留给下一轮判断
+                let res = app
+                    .client_write(Request::DeleteFileV2 {
+                        file_path: meta_file.to_string_lossy().to_string(),
+                        version_id: None,
+                        if_match: Some(metadata.etag),
+                        if_none_match: false,
+                    })
+                    .await?;
+                if res.data.value.as_deref() != Some(conditional::PRECONDITION_FAILED) {
+                    expired += 1;
+                }
+            }
+        }
+        if expired > 0 {
+            info!(
+                "规则 {} 删除了 {} 个超过 {} 天未读取的对象",
+                rule.prefix, expired, rule.days
+            );
+        }
+    }
+    Ok(())
+}
+
+// 开启 --track-access 时定期写入访问记录，并在主节点上检查过期对象
+pub(crate) fn spawn(app: App) {
+    if !config::get().access_tracking {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+        let mut sweep_interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = flush_interval.tick() => flush(&app).await,
+                _ = sweep_interval.tick() => {
+                    let is_leader = app.raft.metrics().borrow().current_leader == Some(app.id);
+                    if standby::is_passive() || !is_leader || config::get().unread_expirations.is_empty() {
+                        continue;
+                    }
+                    flush(&app).await;
+                    if let Err(err) = sweep(&app).await {
+                        warn!("删除冷对象失败: {:#}", err);
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[derive(Deserialize)]
+pub struct AccessQuery {
+    pub prefix: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObjectAccess {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: DateTime<Utc>,
+    pub access_count: u64,
+    // 从未读取时为空
+    pub last_access: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessReport {
+    pub bucket: String,
+    pub prefix: String,
+    // 按最后读取时间（从未读取的以最后修改时间）从旧到新排列，最冷的对象在前
+    pub objects: Vec<ObjectAccess>,
+}
+
+// 列出桶内对象的读取次数和最后读取时间
+pub async fn list_access(
+    req: web::HttpRequest,
+    Query(query): Query<AccessQuery>,
+    state: State<App>,
+) -> HandlerResponse {
+    let bucket_name = req
+        .match_info()
+        .get("bucket")
+        .context("缺少桶名")?
+        .to_string();
+    if !config::get().access_tracking {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "Access tracking is disabled, start the server with --track-access",
+        ));
+    }
+    let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(&bucket_name);
+    if !bucket_dir.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    flush(&state).await;
+    let prefix = query.prefix.unwrap_or_default();
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let objects = {
+        let (bucket_name, prefix) = (bucket_name.clone(), prefix.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<ObjectAccess>> {
+            let records = load(&bucket_name);
+            let mut meta_files = vec![];
+            fs::walk_meta_files(&bucket_dir, &mut meta_files).context("遍历桶目录失败")?;
+            let mut objects = vec![];
+            for meta_file in meta_files {
+                let Some(key) = meta_file
+                    .strip_prefix(&bucket_dir)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                    .and_then(|p| p.strip_suffix(".meta"))
+                    .filter(|key| key.starts_with(&prefix))
+                else {
+                    continue;
+                };
+                let Ok(metadata) = fs::load_metadata(&meta_file) else {
+                    continue;
+                };
+                let record = records.get(key);
+                objects.push(ObjectAccess {
+                    key: key.to_string(),
+                    size: metadata.size,
+                    last_modified: metadata.time,
+                    access_count: record.map_or(0, |r| r.count),
+                    last_access: record.map(|r| r.last_access),
+                });
+            }
+            objects.sort_by_key(|o| o.last_access.unwrap_or(o.last_modified));
+            Ok(objects)
+        })
+        .await
+        .context("读取访问记录失败")??
+    };
+    Ok(HttpResponse::Ok().json(&AccessReport {
+        bucket: bucket_name,
+        prefix,
+        objects: objects.into_iter().take(limit).collect(),
+    }))
+}
@@ -0,0 +1,437 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::err::AppError;
+use crate::fs::{self, Backend, Metadata};
+use crate::HandlerResponse;
+use anyhow::Context;
+use log::warn;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+// --- 管理接口，与 /api 一样需要 SigV4 签名
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/admin/buckets/{bucket}/largest",
+        web::get().to(largest_objects),
+    )
+    .route("/admin/buckets/{bucket}/du", web::get().to(disk_usage))
+    .route("/admin/stats", web::get().to(stats));
+    crate::chunkstats::rest(cfg);
+    crate::clone::rest(cfg);
+    crate::snapshot::rest(cfg);
+    crate::diagnostics::rest(cfg);
+    crate::standby::rest(cfg);
+    crate::keys::rest(cfg);
+    crate::presign::rest(cfg);
+    crate::access::rest(cfg);
+    crate::capacity::rest(cfg);
+    crate::gc::rest(cfg);
+    crate::scrub::rest(cfg);
+    crate::startup::rest(cfg);
+    if crate::cluster::enabled() {
+        crate::cluster::rest(cfg);
+    }
+    #[cfg(feature = "profiling")]
+    if crate::config::get().debug_endpoints {
+        crate::profiling::rest(cfg);
+    }
+}
+
+// 默认返回的条目数
+const DEFAULT_TOP_N: usize = 10;
+
+#[derive(Deserialize)]
+pub struct LargestQuery {
+    pub n: Option<usize>,
+    // 排序依据：logical（默认，对象原始大小）或 physical（实际占用的磁盘空间）
+    pub by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObjectUsage {
+    pub key: String,
+    pub logical_size: u64,
+    pub physical_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrefixUsage {
+    pub prefix: String,
+    pub objects: u64,
+    pub logical_size: u64,
+    pub physical_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LargestReport {
+    pub bucket: String,
+    pub objects: Vec<ObjectUsage>,
+    pub prefixes: Vec<PrefixUsage>,
+}
+
+// 前缀的累计用量，去重分片在同一前缀内只计算一次
+#[derive(Default)]
+struct PrefixAcc {
+    objects: u64,
+    logical_size: u64,
+    physical_size: u64,
+    chunks: HashSet<String>,
+}
+
+// 分片在磁盘上的大小，分片缺失时按 0 计算
+fn chunk_size_on_disk(hash: &str) -> u64 {
+    fs::chunk_disk_usage(hash)
+}
+
+// 对象实际占用的磁盘空间：去重存储为其引用的（不重复的）分片大小之和，直通存储为原文件大小
+fn object_physical_size(object_path: &str, metadata: &Metadata) -> u64 {
+    match metadata.backend {
+        Backend::Dedup => metadata
+            .chunks
+            .iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|hash| chunk_size_on_disk(hash))
+            .sum(),
+        Backend::Passthrough => std::fs::metadata(fs::raw_path(object_path))
+            .map(|m| m.len())
+            .unwrap_or_default(),
+    }
+}
+
+impl PrefixAcc {
+    fn add(&mut self, metadata: &Metadata, physical_size: u64) {
+        self.objects += 1;
+        self.logical_size += metadata.size;
+        match metadata.backend {
+            Backend::Dedup => {
+                for hash in &metadata.chunks {
+                    if self.chunks.insert(hash.clone()) {
+                        self.physical_size += chunk_size_on_disk(hash);
+                    }
+                }
+            }
+            Backend::Passthrough => self.physical_size += physical_size,
+        }
+    }
+
+    fn into_usage(self, prefix: String) -> PrefixUsage {
+        PrefixUsage {
+            prefix,
+            objects: self.objects,
+            logical_size: self.logical_size,
+            physical_size: self.physical_size,
+        }
+    }
+}
+
+// 读取桶内全部对象的键和元数据，无法读取的元数据跳过
+fn bucket_objects(bucket_name: &str) -> Result<Vec<(String, Metadata)>, AppError> {
+    let bucket_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name);
+    if !bucket_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    let mut meta_files = Vec::new();
+    fs::walk_meta_files(&bucket_path, &mut meta_files).context("遍历桶目录失败")?;
+    let mut objects = Vec::new();
+    for path in meta_files {
+        let Some(key) = path
+            .strip_prefix(&bucket_path)
+            .ok()
+            .and_then(|p| p.to_str())
+            .and_then(|p| p.strip_suffix(".meta"))
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        match fs::load_metadata(&path) {
+            Ok(metadata) => objects.push((key, metadata)),
+            Err(err) => warn!("跳过无法读取的元数据 {:?}: {}", path, err),
+        }
+    }
+    Ok(objects)
+}
+
+// 列出桶内最大的对象和占用最多的前缀（按 '/' 划分的每一级目录）
+pub async fn largest_objects(
+    req: web::HttpRequest,
+    Query(query): Query<LargestQuery>,
+) -> HandlerResponse {
+    let bucket_name = req
+        .match_info()
+        .get("bucket")
+        .context("缺少桶名")?
+        .to_string();
+    let by_physical = match query.by.as_deref() {
+        None | Some("logical") => false,
+        Some("physical") => true,
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "`by` must be `logical` or `physical`",
+            ))
+        }
+    };
+    let n = query.n.unwrap_or(DEFAULT_TOP_N);
+
+    let mut objects = Vec::new();
+    let mut prefixes: BTreeMap<String, PrefixAcc> = BTreeMap::new();
+    for (key, metadata) in bucket_objects(&bucket_name)? {
+        let physical_size = object_physical_size(&format!("{}/{}", bucket_name, key), &metadata);
+        for (i, _) in key.match_indices('/') {
+            let acc = prefixes.entry(key[..=i].to_string()).or_default();
+            acc.add(&metadata, physical_size);
+        }
+        objects.push(ObjectUsage {
+            key,
+            logical_size: metadata.size,
+            physical_size,
+        });
+    }
+
+    let mut prefixes: Vec<PrefixUsage> = prefixes
+        .into_iter()
+        .map(|(prefix, acc)| acc.into_usage(prefix))
+        .collect();
+    if by_physical {
+        objects.sort_by_key(|o| Reverse(o.physical_size));
+        prefixes.sort_by_key(|p| Reverse(p.physical_size));
+    } else {
+        objects.sort_by_key(|o| Reverse(o.logical_size));
+        prefixes.sort_by_key(|p| Reverse(p.logical_size));
+    }
+    objects.truncate(n);
+    prefixes.truncate(n);
+    Ok(HttpResponse::Ok().json(&LargestReport {
+        bucket: bucket_name,
+        objects,
+        prefixes,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct DiskUsageQuery {
+    pub prefix: Option<String>,
+    // 向下汇总的目录层数，默认 1
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiskUsageReport {
+    pub bucket: String,
+    pub prefix: String,
+    pub depth: usize,
+    // prefix 下全部对象的用量
+    pub total: PrefixUsage,
+    // prefix 以下 depth 层以内的每个目录（含其全部子目录）的用量，按前缀排序
+    pub prefixes: Vec<PrefixUsage>,
+}
+
+// 将对象键归入 prefix 以下各级目录（最多 depth 层），返回这些目录前缀
+pub fn du_groups<'a>(key: &'a str, prefix: &str, depth: usize) -> Vec<&'a str> {
+    let Some(rest) = key.strip_prefix(prefix) else {
+        return vec![];
+    };
+    rest.match_indices('/')
+        .take(depth)
+        .map(|(i, _)| &key[..prefix.len() + i + 1])
+        .collect()
+}
+
+// 类似 du -d：汇总 prefix 以下 depth 层以内各目录的对象数、逻辑大小和实际占用
+pub async fn disk_usage(
+    req: web::HttpRequest,
+    Query(query): Query<DiskUsageQuery>,
+) -> HandlerResponse {
+    let bucket_name = req
+        .match_info()
+        .get("bucket")
+        .context("缺少桶名")?
+        .to_string();
+    let prefix = query.prefix.unwrap_or_default();
+    let depth = query.depth.unwrap_or(1);
+    let objects = {
+        let bucket_name = bucket_name.clone();
+        tokio::task::spawn_blocking(move || bucket_objects(&bucket_name))
+            .await
+            .context("统计用量失败")??
+    };
+    let mut total = PrefixAcc::default();
+    let mut prefixes: BTreeMap<String, PrefixAcc> = BTreeMap::new();
+    for (key, metadata) in objects {
+        if !key.starts_with(&prefix) {
+            continue;
+        }
+        let physical_size = object_physical_size(&format!("{}/{}", bucket_name, key), &metadata);
+        total.add(&metadata, physical_size);
+        for group in du_groups(&key, &prefix, depth) {
+            let acc = prefixes.entry(group.to_string()).or_default();
+            acc.add(&metadata, physical_size);
+        }
+    }
+    Ok(HttpResponse::Ok().json(&DiskUsageReport {
+        bucket: bucket_name,
+        total: total.into_usage(prefix.clone()),
+        prefix,
+        depth,
+        prefixes: prefixes
+            .into_iter()
+            .map(|(prefix, acc)| acc.into_usage(prefix))
+            .collect(),
+    }))
+}
+
+// 全部对象的去重统计：不重复的分片只计算一次，直通存储的文件不参与去重和压缩
+#[derive(Debug, Default)]
+pub struct DedupStats {
+    pub objects: u64,
+    // 对象原始大小之和
+    pub logical_size: u64,
+    // 去重后的大小：不重复分片的原始大小之和，旧版本写入的元数据没有分片大小时按单分片对象的大小计算
+    pub deduplicated_size: u64,
+    // 实际占用：不重复分片压缩后的大小之和
+    pub physical_size: u64,
+    pub chunks: u64,
+    // 对象引用分片的总次数
+    pub chunk_references: u64,
+    seen: HashSet<String>,
+}
+
+impl DedupStats {
+    // 累计去重存储的对象，stored_size 返回分片在磁盘上的大小
+    pub fn add_chunks(
+        &mut self,
+        size: u64,
+        chunks: &[String],
+        chunk_sizes: &[u64],
+        stored_size: impl Fn(&str) -> u64,
+    ) {
+        self.objects += 1;
+        self.logical_size += size;
+        self.chunk_references += chunks.len() as u64;
+        for (i, hash) in chunks.iter().enumerate() {
+            if !self.seen.insert(hash.clone()) {
+                continue;
+            }
+            self.chunks += 1;
+            self.physical_size += stored_size(hash);
+            self.deduplicated_size += match chunk_sizes.get(i) {
+                Some(chunk_size) if chunk_sizes.len() == chunks.len() => *chunk_size,
+                _ if chunks.len() == 1 => size,
+                _ => 0,
+            };
+        }
+    }
+
+    // 累计直通存储的对象
+    pub fn add_file(&mut self, size: u64, stored_size: u64) {
+        self.objects += 1;
+        self.logical_size += size;
+        self.deduplicated_size += size;
+        self.physical_size += stored_size;
+    }
+}
+
+// 两个大小的比值，保留两位小数，分母为 0 时为 1
+pub fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        return 1.0;
+    }
+    (numerator as f64 * 100.0 / denominator as f64).round() / 100.0
+}
+
+#[derive(Debug, Serialize)]
+pub struct BucketStats {
+    pub bucket: String,
+    pub objects: u64,
+    pub logical_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    pub objects: u64,
+    pub logical_size: u64,
+    pub deduplicated_size: u64,
+    pub physical_size: u64,
+    pub chunks: u64,
+    pub chunk_references: u64,
+    // 原始大小 / 去重后的大小
+    pub dedup_ratio: f64,
+    // 去重后的大小 / 实际占用
+    pub compression_ratio: f64,
+    // 原始大小 / 实际占用
+    pub reduction_ratio: f64,
+    pub buckets: Vec<BucketStats>,
+}
+
+fn collect_stats() -> Result<StatsReport, AppError> {
+    let buckets_dir = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
+    let mut names: Vec<String> = std::fs::read_dir(&buckets_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    let mut stats = DedupStats::default();
+    let mut buckets = Vec::new();
+    for bucket_name in names {
+        let mut bucket = BucketStats {
+            bucket: bucket_name.clone(),
+            objects: 0,
+            logical_size: 0,
+        };
+        for (key, metadata) in bucket_objects(&bucket_name)? {
+            bucket.objects += 1;
+            bucket.logical_size += metadata.size;
+            match metadata.backend {
+                Backend::Dedup => stats.add_chunks(
+                    metadata.size,
+                    &metadata.chunks,
+                    &metadata.chunk_sizes,
+                    chunk_size_on_disk,
+                ),
+                Backend::Passthrough => {
+                    let object_path = format!("{}/{}", bucket_name, key);
+                    let stored_size = object_physical_size(&object_path, &metadata);
+                    stats.add_file(metadata.size, stored_size);
+                }
+            }
+        }
+        buckets.push(bucket);
+    }
+    Ok(StatsReport {
+        objects: stats.objects,
+        logical_size: stats.logical_size,
+        deduplicated_size: stats.deduplicated_size,
+        physical_size: stats.physical_size,
+        chunks: stats.chunks,
+        chunk_references: stats.chunk_references,
+        dedup_ratio: ratio(stats.logical_size, stats.deduplicated_size),
+        compression_ratio: ratio(stats.deduplicated_size, stats.physical_size),
+        reduction_ratio: ratio(stats.logical_size, stats.physical_size),
+        buckets,
+    })
+}
+
+// 全部桶的去重和压缩效果，用于判断分片策略是否节省了空间；需要读取所有元数据，大实例上较慢
+pub async fn stats() -> HandlerResponse {
+    let report = tokio::task::spawn_blocking(collect_stats)
+        .await
+        .context("统计去重效果失败")??;
+    Ok(HttpResponse::Ok().json(&report))
+}
@@ -1,34 +1,87 @@
+use crate::access;
+use crate::archive;
+use crate::archive::{ArchiveFormat, ArchiveSource};
+use crate::checksum;
+use crate::codec;
+use crate::conditional;
+use crate::conditional::Outcome;
+use crate::config::{PluginHook, RestrictedOperation, ScanAction};
+use crate::copy;
+use crate::copy::MetadataDirective;
 use crate::err::AppError;
 use crate::err::AppError::BadRequest;
-use crate::fs::DecompressStream;
+use crate::etag;
+use crate::fs::{
+    Backend, DecompressStream, Encryption, Metadata, ResponseHeader, ScanStatus, ScanVerdict,
+    SseAlgorithm,
+};
+use crate::headers::ResponseHeadersConfiguration;
+use crate::identity::{Identity, Operation};
+use crate::lifecycle;
+use crate::lifecycle::LifecycleConfiguration;
+use crate::listing;
 use crate::model::{
-    Bucket, BucketWrapper, CompleteMultipartUploadResult, Content, HeadNotFoundResp,
-    InitiateMultipartUploadResult, ListBucketResp, ListBucketResult, Owner,
+    Bucket, BucketWrapper, CommitStagedResult, CompleteMultipartUpload,
+    CompleteMultipartUploadResult, Content, CopyObjectResult, Delete, DeleteError,
+    DeleteMarkerEntry, DeleteResult, DeletedObject, ExtractResult, InitiateMultipartUploadResult,
+    ListBucketResp, ListPartsResult, ObjectVersionEntry, Owner, Part, RenameResult, UserMetadata,
+    UserMetadataEntry,
 };
+use crate::multipart;
+use crate::multipart::CompletionError;
+use crate::plugin;
+use crate::policy;
+use crate::policy::BucketPolicy;
+use crate::pool;
 use crate::raft::app::App;
 use crate::raft::store::Request::{
-    CombineChunk, CopyFile, CreateBucket, DeleteBucket, DeleteFile, InitChunk, UploadChunk,
-    UploadFile,
+    AbortChunk, AbortStaged, CombineChunk, CommitChunkedFileV2, CommitStaged, CopyFileV2,
+    CreateBucketV2, DeleteBucket, DeleteFileV2, DeleteFilesV2, InitChunkV2, RenameObject,
+    SetBucketHeaders, SetBucketLifecycle, SetBucketPolicy, SetBucketVersioning, SetBucketWebsite,
+    SetObjectTags, StageFileV2, UploadChunkV2, UploadFileV2,
 };
-use crate::util::cry;
+use crate::raft::store::{ObjectAttrs, Request, RENAME_TARGET_EXISTS};
+use crate::range;
+use crate::range::Unsatisfiable;
+use crate::scan;
+use crate::scan::ScanInput;
+use crate::slowlog;
+use crate::spool;
+use crate::spool::{StreamedBody, UploadBody};
+use crate::sse;
+use crate::sse::{ObjectCipher, SseRequest};
+use crate::tagging;
+use crate::util;
 use crate::util::date::date_format_to_second;
-use crate::{fs, HandlerResponse};
+use crate::versioning::{VersioningConfiguration, VersioningStatus};
+use crate::website::WebsiteConfiguration;
+use crate::{config, fs, headers, versioning, website, HandlerResponse};
 use anyhow::{anyhow, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
 use futures::future::ok;
 use futures::stream::once;
 use futures::StreamExt;
-use log::info;
+use log::{info, warn};
+use ntex::http::header::{self, HeaderValue};
+use ntex::http::ConnectionType;
+use ntex::http::StatusCode;
 use ntex::util::{Bytes, BytesMut};
 use ntex::web;
 use ntex::web::types::Query;
-use ntex::web::HttpResponse;
-use quick_xml::se::to_string;
+use ntex::web::{HttpResponse, WebResponseError};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use quick_xml::se::{to_string, to_string_with_root};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs::read_dir;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::OnceCell;
 use uuid::Uuid;
-use zstd::zstd_safe::WriteBuf;
 
 pub(crate) static DATA_DIR: OnceCell<String> = OnceCell::const_new();
 pub(crate) const BASIC_PATH_SUFFIX: &str = "buckets";
@@ -43,10 +96,12 @@ pub fn rest(cfg: &mut web::ServiceConfig) {
         .route("/api/{bucket}", web::head().to(head_bucket))
         .route("/api/{bucket}", web::put().to(create_bucket))
         .route("/api/{bucket}", web::delete().to(delete_bucket))
+        .route("/api/{bucket}", web::post().to(post_bucket))
         .route("/api/{bucket}/", web::get().to(get_bucket))
         .route("/api/{bucket}/", web::head().to(head_bucket))
         .route("/api/{bucket}/", web::put().to(create_bucket))
         .route("/api/{bucket}/", web::delete().to(delete_bucket))
+        .route("/api/{bucket}/", web::post().to(post_bucket))
         .route(
             "/api/{bucket}/{object}",
             web::post().to(init_chunk_or_combine_chunk),
@@ -90,56 +145,364 @@ fn get_path_param(req: &web::HttpRequest, name: &str) -> Result<String, AppError
     Ok(param)
 }
 
-// 获取所有桶的列表
+// 客户端对真实负载签名时（非 UNSIGNED-PAYLOAD），请求头中的负载哈希
+fn expected_content_sha256(req: &web::HttpRequest) -> Option<&str> {
+    let expected = req
+        .headers()
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())?;
+    (expected.len() == 64 && expected.chars().all(|c| c.is_ascii_hexdigit())).then_some(expected)
+}
+
+// 校验请求体与 x-amz-content-sha256 是否一致，actual 为请求体的哈希（十六进制）
+fn check_payload_sha256(
+    req: &web::HttpRequest,
+    actual: impl FnOnce() -> String,
+) -> Result<(), AppError> {
+    let Some(expected) = expected_content_sha256(req) else {
+        return Ok(());
+    };
+    if !actual().eq_ignore_ascii_case(expected) {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "XAmzContentSHA256Mismatch",
+            "The provided 'x-amz-content-sha256' header does not match what was computed.",
+        ));
+    }
+    Ok(())
+}
+
+fn check_content_sha256(req: &web::HttpRequest, body: &[u8]) -> Result<(), AppError> {
+    check_payload_sha256(req, || fs::get_sha256_string(&fs::get_sha256(body)))
+}
+
+// 扩展：读取暂存上传的批次id，同一批次的对象在提交前不可见
+fn get_staging_id(req: &web::HttpRequest) -> Result<Option<String>, AppError> {
+    let Some(staging_id) = req.headers().get("x-rs3-staging-id") else {
+        return Ok(None);
+    };
+    let staging_id = staging_id.to_str().map_err(|_| BadRequest)?;
+    check_staging_id(staging_id)?;
+    Ok(Some(staging_id.to_string()))
+}
+
+// 批次id只能含字母、数字、'-' 和 '_'，用作暂存目录名
+fn check_staging_id(staging_id: &str) -> Result<(), AppError> {
+    let valid = !staging_id.is_empty()
+        && staging_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "x-rs3-staging-id may only contain letters, digits, '-' and '_'",
+        ));
+    }
+    Ok(())
+}
+
+// 响应可使用的传输压缩编码
+enum ContentEncoding {
+    Zstd,
+    Gzip,
+}
+
+// 按 Accept-Encoding 协商响应编码，zstd 优先；q=0 表示明确拒绝
+fn negotiate_encoding(req: &web::HttpRequest) -> Option<ContentEncoding> {
+    let accept = req.headers().get("Accept-Encoding")?.to_str().ok()?;
+    let accepted = |name: &str| {
+        accept.split(',').any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let coding = parts.next().unwrap_or_default();
+            let q = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            coding.eq_ignore_ascii_case(name) && q > 0.0
+        })
+    };
+    if accepted("zstd") {
+        Some(ContentEncoding::Zstd)
+    } else if accepted("gzip") {
+        Some(ContentEncoding::Gzip)
+    } else {
+        None
+    }
+}
+
+// 读取请求头的值，空值视为未设置
+fn get_header_value(req: &web::HttpRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+// 上传时可设置的对象级响应头：(请求头, 响应头)
+const OBJECT_RESPONSE_HEADERS: &[(&str, &str)] = &[
+    ("Cache-Control", "Cache-Control"),
+    ("Content-Disposition", "Content-Disposition"),
+    ("Content-Encoding", "Content-Encoding"),
+    ("Content-Language", "Content-Language"),
+    ("Expires", "Expires"),
+    ("x-rs3-content-security-policy", "Content-Security-Policy"),
+    ("x-rs3-expose-headers", "Access-Control-Expose-Headers"),
+];
+
+// 从上传请求中读取需要写入元数据的对象属性
+fn get_object_attrs(req: &web::HttpRequest) -> Result<ObjectAttrs, AppError> {
+    let mut headers: Vec<ResponseHeader> = OBJECT_RESPONSE_HEADERS
+        .iter()
+        .filter_map(|(from, to)| {
+            Some(ResponseHeader {
+                name: to.to_string(),
+                value: get_header_value(req, from)?,
+            })
+        })
+        .collect();
+    // 用户自定义元数据按原样保存，读取对象时原样返回
+    let mut user_metadata: Vec<ResponseHeader> = req
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with(USER_METADATA_PREFIX))
+        .filter_map(|(name, value)| {
+            Some(ResponseHeader {
+                name: name.as_str().to_string(),
+                value: value.to_str().ok()?.trim().to_string(),
+            })
+        })
+        .collect();
+    user_metadata.sort_by(|a, b| a.name.cmp(&b.name));
+    let size: usize = user_metadata
+        .iter()
+        .map(|h| h.name.len() - USER_METADATA_PREFIX.len() + h.value.len())
+        .sum();
+    if size > config::get().request_limits.max_metadata_size {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "MetadataTooLarge",
+            "Your metadata headers exceed the maximum allowed metadata size",
+        ));
+    }
+    headers.extend(user_metadata);
+    Ok(ObjectAttrs {
+        content_type: get_header_value(req, "Content-Type"),
+        website_redirect: get_website_redirect(req)?,
+        headers,
+        scan: None,
+        etag: None,
+        checksum_sha256: None,
+        encryption: None,
+        create_only: false,
+        tags: tagging::from_headers(req.headers()).map_err(invalid_tag)?,
+        codec: codec::from_headers(req.headers()).map_err(invalid_codec)?,
+        if_match: None,
+    })
+}
+
+fn invalid_tag(message: String) -> AppError {
+    AppError::s3(StatusCode::BAD_REQUEST, "InvalidTag", message)
+}
+
+fn invalid_codec(message: String) -> AppError {
+    AppError::s3(StatusCode::BAD_REQUEST, "InvalidArgument", message)
+}
+
+// 读取并校验 x-amz-website-redirect-location 请求头
+fn get_website_redirect(req: &web::HttpRequest) -> Result<Option<String>, AppError> {
+    let Some(value) = req.headers().get("x-amz-website-redirect-location") else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(location) if website::is_valid_redirect_location(location) => {
+            Ok(Some(location.to_string()))
+        }
+        _ => Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidRedirectLocation",
+            "The website redirect location must begin with '/', 'http://' or 'https://'",
+        )),
+    }
+}
+
+// 未携带签名访问公开桶的请求视为静态网站模式
+fn is_website_request(req: &web::HttpRequest, bucket_name: &str, object_key: &str) -> bool {
+    req.headers().get("Authorization").is_none()
+        && !req.query_string().contains("X-Amz-Credential")
+        && config::get().is_public(&format!("{}/{}", bucket_name, object_key))
+}
+
+// 用户自定义元数据请求头前缀，请求头名称已转为小写
+const USER_METADATA_PREFIX: &str = "x-amz-meta-";
+
+// 对象是否保存了指定的响应头
+fn has_object_header(object_headers: &[ResponseHeader], name: &str) -> bool {
+    object_headers
+        .iter()
+        .any(|h| h.name.eq_ignore_ascii_case(name))
+}
+
+// 上传时未指定 Content-Disposition 的对象默认作为附件下载
+fn default_content_disposition(
+    resp: &mut web::HttpResponseBuilder,
+    name: &str,
+    object_headers: &[ResponseHeader],
+) {
+    if !has_object_header(object_headers, "Content-Disposition") {
+        resp.header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", name),
+        );
+    }
+}
+
+// 附加桶的默认响应头和对象级响应头
+// 请求带 x-amz-checksum-mode: ENABLED 时返回上传时保存的校验和
+fn apply_checksum_headers(
+    resp: &mut web::HttpResponseBuilder,
+    req: &web::HttpRequest,
+    metadata: &Metadata,
+) {
+    let mode = get_header_value(req, checksum::CHECKSUM_MODE_HEADER);
+    if !checksum::is_mode_enabled(mode.as_deref()) {
+        return;
+    }
+    if let Some(sha256) = &metadata.checksum_sha256 {
+        resp.header(checksum::CHECKSUM_SHA256_HEADER, sha256)
+            .header(
+                checksum::CHECKSUM_TYPE_HEADER,
+                checksum::checksum_type(sha256),
+            );
+    }
+}
+
+fn apply_response_headers(
+    resp: &mut web::HttpResponseBuilder,
+    bucket_name: &str,
+    object_headers: &[ResponseHeader],
+) {
+    let conf = headers::load(bucket_name);
+    for (name, value) in conf.merge(object_headers) {
+        resp.header(name, value);
+    }
+}
+
+// 静态网站重定向响应
+fn website_redirect_response(code: u16, location: &str) -> HandlerResponse {
+    let status = StatusCode::from_u16(code).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+    Ok(HttpResponse::build(status)
+        .header("Location", location)
+        .finish())
+}
+
+// 暂存上传的对象
+async fn do_stage_file(
+    state: &App,
+    staging_id: String,
+    bucket_name: String,
+    object_key: String,
+    body: Vec<u8>,
+    attrs: ObjectAttrs,
+) -> HandlerResponse {
+    let object_etag = attrs.etag.clone().unwrap_or_else(|| etag::md5_hex(&body));
+    state
+        .client_write(StageFileV2 {
+            staging_id,
+            bucket_name,
+            object_key,
+            body,
+            attrs,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    Ok(etag_response(&object_etag))
+}
+
+// 获取所有桶的列表，按桶名排序
 pub async fn list_bucket() -> HandlerResponse {
     let dir_path = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
     let dir_path = dir_path.as_path();
-    if dir_path.is_dir() {
-        let mut res = Vec::new();
-        if let Ok(dir) = read_dir(dir_path) {
-            for entry in dir.flatten() {
-                if entry.file_type().unwrap().is_dir() {
-                    let metadata = entry.metadata().context("转换失败")?;
-                    let mod_time = metadata.modified().context("转换失败")?;
-                    let bucket = Bucket {
-                        name: entry.file_name().to_string_lossy().to_string(),
-                        creation_date: date_format_to_second(mod_time.into()),
-                    };
-                    res.push(bucket);
-                }
-            }
-        }
-        let mut buckets = Vec::new();
-        for bucket in res {
-            buckets.push(bucket);
-        }
-        let list_res = ListBucketResp {
-            id: "20230529".to_string(),
-            owner: Owner {
-                display_name: "minioadmin".to_string(),
-            },
-            buckets: BucketWrapper { bucket: buckets },
-        };
-        let xml = to_string(&list_res).context("序列化失败")?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
-    } else {
+    if !dir_path.is_dir() {
         std::fs::create_dir_all(dir_path).context("创建文件夹失败")?;
-        let buckets = Vec::new();
-        let list_res = ListBucketResp {
-            id: "20230529".to_string(),
-            owner: Owner {
-                display_name: "minioadmin".to_string(),
-            },
-            buckets: BucketWrapper { bucket: buckets },
-        };
-        let xml = to_string(&list_res).context("序列化失败")?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
     }
+    let mut buckets = Vec::new();
+    for entry in read_dir(dir_path).context("读取桶列表失败")?.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            buckets.push(Bucket {
+                name: entry.file_name().to_string_lossy().to_string(),
+                creation_date: fs::bucket_created(&entry.path()).context("读取桶创建时间失败")?,
+            });
+        }
+    }
+    buckets.sort_by(|a, b| a.name.cmp(&b.name));
+    let list_res = ListBucketResp {
+        owner: Owner {
+            id: "20230529".to_string(),
+            display_name: "minioadmin".to_string(),
+        },
+        buckets: BucketWrapper { bucket: buckets },
+    };
+    let xml = to_string(&list_res).context("序列化失败")?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+}
+
+// S3 的桶命名规则：3 到 63 个小写字母、数字、'.' 或 '-'，以字母或数字开头和结尾，
+// 不含 ".."，且不能是 IP 地址的形式
+pub fn is_valid_bucket_name(name: &str) -> bool {
+    let alnum = |c: u8| c.is_ascii_lowercase() || c.is_ascii_digit();
+    let bytes = name.as_bytes();
+    (3..=63).contains(&bytes.len())
+        && bytes.iter().all(|&c| alnum(c) || c == b'.' || c == b'-')
+        && alnum(bytes[0])
+        && alnum(bytes[bytes.len() - 1])
+        && !name.contains("..")
+        && name.parse::<std::net::Ipv4Addr>().is_err()
 }
 
 #[derive(Deserialize)]
 pub struct GetBucketQueryParams {
     pub prefix: Option<String>,
+    pub website: Option<String>,
+    #[serde(rename = "response-headers")]
+    pub response_headers: Option<String>,
+    // 扩展：sort=mtime 时按最后修改时间倒序分页列出
+    pub sort: Option<String>,
+    #[serde(rename = "max-keys")]
+    pub max_keys: Option<usize>,
+    #[serde(rename = "continuation-token")]
+    pub continuation_token: Option<String>,
+    #[serde(rename = "list-type")]
+    pub list_type: Option<String>,
+    pub delimiter: Option<String>,
+    pub marker: Option<String>,
+    #[serde(rename = "start-after")]
+    pub start_after: Option<String>,
+    #[serde(rename = "encoding-type")]
+    pub encoding_type: Option<String>,
+    // 扩展：archive=tar 时把 prefix 下的全部对象打包下载
+    pub archive: Option<String>,
+    pub versioning: Option<String>,
+    pub policy: Option<String>,
+    pub lifecycle: Option<String>,
+    // ListObjectVersions
+    pub versions: Option<String>,
+    #[serde(rename = "key-marker")]
+    pub key_marker: Option<String>,
+    #[serde(rename = "version-id-marker")]
+    pub version_id_marker: Option<String>,
+    // 扩展：只列出该存储类型的对象，全部对象都是 STANDARD
+    #[serde(rename = "storage-class")]
+    pub storage_class: Option<String>,
+    // 扩展：只列出带有该标签的对象，未指定 tag-value 时只要求标签键存在
+    #[serde(rename = "tag-key")]
+    pub tag_key: Option<String>,
+    #[serde(rename = "tag-value")]
+    pub tag_value: Option<String>,
+    // 扩展：metadata=true 时在列表中内联返回用户自定义元数据和标签
+    pub metadata: Option<String>,
 }
 // 获取桶的数据
 pub async fn get_bucket(
@@ -151,48 +514,963 @@ pub async fn get_bucket(
     let bucket_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
         .join(&bucket_name);
-    if let Ok(files) = std::fs::read_dir(&bucket_path) {
-        let mut contents = Vec::new();
-        for file in files.flatten() {
-            if !file.file_type().unwrap().is_dir()
-                && file.file_name().to_string_lossy().ends_with(".meta")
-            {
-                let meta_file_path = bucket_path.clone();
-                let meta_file_path = meta_file_path.join(&file.file_name());
-                let meta_file_path = meta_file_path.to_str().unwrap();
-                info!("{}", &meta_file_path);
-                let metadata = fs::load_metadata(meta_file_path)?;
-                contents.push(Content {
-                    size: metadata.size as i64,
-                    key: metadata.name,
-                    last_modified: metadata.time,
+    if !bucket_path.is_dir() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if query.website.is_some() {
+        let Some(config) = website::load_raw(&bucket_name) else {
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchWebsiteConfiguration",
+                "The specified bucket does not have a website configuration",
+            ));
+        };
+        return Ok(HttpResponse::Ok()
+            .content_type("application/xml")
+            .body(config));
+    }
+    if query.response_headers.is_some() {
+        let Some(config) = headers::load_raw(&bucket_name) else {
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchResponseHeadersConfiguration",
+                "The specified bucket does not have a response headers configuration",
+            ));
+        };
+        return Ok(HttpResponse::Ok()
+            .content_type("application/xml")
+            .body(config));
+    }
+    if query.versioning.is_some() {
+        let conf = VersioningConfiguration {
+            status: versioning::status(&bucket_name).map(|status| status.as_str().to_string()),
+        };
+        let xml = to_string(&conf).context("序列化失败")?;
+        return Ok(HttpResponse::Ok().content_type("application/xml").body(xml));
+    }
+    if query.policy.is_some() {
+        let Some(config) = policy::load_raw(&bucket_name) else {
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchBucketPolicy",
+                "The bucket policy does not exist",
+            ));
+        };
+        return Ok(HttpResponse::Ok()
+            .content_type("application/json")
+            .body(config));
+    }
+    if query.lifecycle.is_some() {
+        let Some(config) = lifecycle::load_raw(&bucket_name) else {
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchLifecycleConfiguration",
+                "The lifecycle configuration does not exist",
+            ));
+        };
+        return Ok(HttpResponse::Ok()
+            .content_type("application/xml")
+            .body(config));
+    }
+    if query.versions.is_some() {
+        let xml =
+            pool::run(move || list_object_versions(&bucket_name, bucket_path, &query)).await??;
+        return Ok(HttpResponse::Ok().content_type("application/xml").body(xml));
+    }
+    let prefix = query.prefix.as_deref().unwrap_or_default();
+    match query.archive.as_deref() {
+        None => {}
+        Some("tar") => {
+            let body = archive::tar_stream(bucket_name.clone(), bucket_path, prefix.to_string())
+                .await
+                .context("遍历桶目录失败")?;
+            return Ok(HttpResponse::Ok()
+                .content_type("application/x-tar")
+                .header(
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}.tar\"", bucket_name),
+                )
+                .streaming(body));
+        }
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "Unsupported archive format, only `tar` is supported",
+            ))
+        }
+    }
+    if accepts_html(&req) && config::get().is_public(&format!("{}/{}", bucket_name, prefix)) {
+        let prefix = prefix.to_string();
+        let html = pool::run(move || render_index(&bucket_name, &bucket_path, &prefix)).await??;
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(html));
+    }
+    match query.sort.as_deref() {
+        None => {}
+        Some("mtime") => {
+            let prefix = prefix.to_string();
+            let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+            let token = query.continuation_token.clone();
+            let inline = inline_metadata(&query);
+            let xml = pool::run(move || {
+                list_by_mtime(
+                    &bucket_name,
+                    &bucket_path,
+                    &prefix,
+                    max_keys,
+                    token.as_deref(),
+                    inline,
+                )
+            })
+            .await??;
+            return Ok(HttpResponse::Ok().content_type("application/xml").body(xml));
+        }
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "Unsupported sort order, only `mtime` is supported",
+            ))
+        }
+    }
+
+    let bucket_name = bucket_name.clone();
+    let xml = pool::run(move || list_objects(&bucket_name, bucket_path, &query)).await??;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+}
+
+// ListObjects（V1）和 ListObjectsV2：按键的字典序分页列出，delimiter 分组为 CommonPrefixes
+fn list_objects(
+    bucket_name: &str,
+    bucket_path: PathBuf,
+    query: &GetBucketQueryParams,
+) -> Result<String, AppError> {
+    let v2 = query.list_type.as_deref() == Some("2");
+    let prefix = query.prefix.as_deref().unwrap_or_default();
+    let delimiter = query.delimiter.as_deref().filter(|d| !d.is_empty());
+    let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+    let url_encoded = match query.encoding_type.as_deref() {
+        None => false,
+        Some("url") => true,
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "Invalid Encoding Method specified in Request",
+            ))
+        }
+    };
+    let token = match query.continuation_token.as_deref().filter(|_| v2) {
+        Some(token) => Some(listing::decode_token(token).ok_or_else(|| {
+            AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "The continuation token provided is incorrect",
+            )
+        })?),
+        None => None,
+    };
+    // V2 的 continuation-token 优先于 start-after
+    let after = if v2 {
+        token.as_deref().or(query.start_after.as_deref())
+    } else {
+        query.marker.as_deref()
+    };
+    if query.tag_value.is_some() && query.tag_key.is_none() {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "tag-value requires tag-key",
+        ));
+    }
+    let standard = query
+        .storage_class
+        .as_deref()
+        .is_none_or(|class| class == "STANDARD");
+    let keys = listing::BucketKeys::new(bucket_path.clone(), prefix, after);
+    let mut keys = listing::filtered(keys, |key| {
+        standard
+            && query.tag_key.as_deref().is_none_or(|tag_key| {
+                let meta_file_path = bucket_path.join(format!("{}.meta", key));
+                fs::load_metadata(&meta_file_path).is_ok_and(|metadata| {
+                    metadata.tags.iter().any(|tag| {
+                        tag.key == tag_key
+                            && query.tag_value.as_ref().is_none_or(|v| &tag.value == v)
+                    })
+                })
+            })
+    });
+    let page = listing::paginate(&mut keys, prefix, delimiter, after, max_keys);
+
+    let encode = |value: &str| -> String {
+        if url_encoded {
+            utf8_percent_encode(value, PATH_ENCODE_SET).to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    let element = |name: &str, value: &str| format!("<{0}>{1}</{0}>", name, escape(&encode(value)));
+    let mut xml = format!(
+        "<ListBucketResult><Name>{}</Name>{}<MaxKeys>{}</MaxKeys>",
+        escape(bucket_name),
+        element("Prefix", prefix),
+        max_keys,
+    );
+    if let Some(delimiter) = delimiter {
+        xml.push_str(&element("Delimiter", delimiter));
+    }
+    if url_encoded {
+        xml.push_str("<EncodingType>url</EncodingType>");
+    }
+    xml.push_str(&format!("<IsTruncated>{}</IsTruncated>", page.is_truncated));
+    if v2 {
+        xml.push_str(&format!(
+            "<KeyCount>{}</KeyCount>",
+            page.keys.len() + page.common_prefixes.len()
+        ));
+        if let Some(token) = &query.continuation_token {
+            xml.push_str(&format!(
+                "<ContinuationToken>{}</ContinuationToken>",
+                escape(token)
+            ));
+        }
+        if let Some(start_after) = &query.start_after {
+            xml.push_str(&element("StartAfter", start_after));
+        }
+        if let Some(marker) = &page.next_marker {
+            xml.push_str(&format!(
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                listing::encode_token(marker)
+            ));
+        }
+    } else {
+        xml.push_str(&element(
+            "Marker",
+            query.marker.as_deref().unwrap_or_default(),
+        ));
+        if let Some(marker) = &page.next_marker {
+            xml.push_str(&element("NextMarker", marker));
+        }
+    }
+    for key in &page.keys {
+        let meta_file_path = bucket_path.join(format!("{}.meta", key));
+        let metadata = match fs::load_metadata(&meta_file_path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!("跳过无法读取的元数据 {:?}: {}", meta_file_path, err);
+                continue;
+            }
+        };
+        let content = list_content(encode(key), &metadata, inline_metadata(query));
+        xml.push_str(&to_string_with_root("Contents", &content).context("序列化失败")?);
+    }
+    for common_prefix in &page.common_prefixes {
+        xml.push_str(&format!(
+            "<CommonPrefixes>{}</CommonPrefixes>",
+            element("Prefix", common_prefix)
+        ));
+    }
+    xml.push_str("</ListBucketResult>");
+    Ok(xml)
+}
+
+// 列表中的标签数（扩展），没有标签时不返回
+fn tag_count(metadata: &Metadata) -> Option<usize> {
+    (!metadata.tags.is_empty()).then_some(metadata.tags.len())
+}
+
+fn inline_metadata(query: &GetBucketQueryParams) -> bool {
+    query.metadata.as_deref() == Some("true")
+}
+
+// 列表中的一个对象，inline 时附带用户自定义元数据和标签
+fn list_content(key: String, metadata: &Metadata, inline: bool) -> Content {
+    let user_metadata = inline.then(|| UserMetadata {
+        entries: metadata
+            .headers
+            .iter()
+            .filter_map(|h| {
+                Some(UserMetadataEntry {
+                    key: h.name.strip_prefix(USER_METADATA_PREFIX)?.to_string(),
+                    value: h.value.clone(),
+                })
+            })
+            .collect(),
+    });
+    let tag_set = inline.then(|| tagging::tag_set(&metadata.tags));
+    Content {
+        size: metadata.size as i64,
+        key,
+        last_modified: metadata.time,
+        etag: etag::quote(&metadata.etag),
+        tag_count: tag_count(metadata),
+        user_metadata,
+        tag_set,
+    }
+}
+
+// ListObjectVersions：按键的字典序列出全部版本和删除标记，同一个键内从新到旧
+fn list_object_versions(
+    bucket_name: &str,
+    bucket_path: PathBuf,
+    query: &GetBucketQueryParams,
+) -> Result<String, AppError> {
+    let prefix = query.prefix.as_deref().unwrap_or_default();
+    let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+    let key_marker = query.key_marker.as_deref();
+    let version_id_marker = query.version_id_marker.as_deref();
+    let versions = versioning::list(bucket_name, &bucket_path, prefix)?;
+    let ids: Vec<(String, String)> = versions
+        .iter()
+        .map(|version| (version.key.clone(), version.version_id.clone()))
+        .collect();
+    let start = versioning::start_index(&ids, key_marker, version_id_marker);
+    let page = &versions[start..(start + max_keys).min(versions.len())];
+    let is_truncated = start + page.len() < versions.len();
+
+    let element = |name: &str, value: &str| format!("<{0}>{1}</{0}>", name, escape(value));
+    let mut xml = format!(
+        "<ListVersionsResult><Name>{}</Name>{}{}{}<MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+        escape(bucket_name),
+        element("Prefix", prefix),
+        element("KeyMarker", key_marker.unwrap_or_default()),
+        element("VersionIdMarker", version_id_marker.unwrap_or_default()),
+        max_keys,
+        is_truncated,
+    );
+    if let Some(last) = page.last().filter(|_| is_truncated) {
+        xml.push_str(&element("NextKeyMarker", &last.key));
+        xml.push_str(&element("NextVersionIdMarker", &last.version_id));
+    }
+    for version in page {
+        let entry = if version.metadata.delete_marker {
+            let marker = DeleteMarkerEntry {
+                key: version.key.clone(),
+                version_id: version.version_id.clone(),
+                is_latest: version.is_latest,
+                last_modified: version.metadata.time,
+            };
+            to_string_with_root("DeleteMarker", &marker)
+        } else {
+            let entry = ObjectVersionEntry {
+                key: version.key.clone(),
+                version_id: version.version_id.clone(),
+                is_latest: version.is_latest,
+                last_modified: version.metadata.time,
+                etag: etag::quote(&version.metadata.etag),
+                size: version.metadata.size as i64,
+                storage_class: "STANDARD".to_string(),
+                tag_count: tag_count(&version.metadata),
+            };
+            to_string_with_root("Version", &entry)
+        };
+        xml.push_str(&entry.context("序列化失败")?);
+    }
+    xml.push_str("</ListVersionsResult>");
+    Ok(xml)
+}
+
+#[derive(Deserialize)]
+pub struct PostBucketQuery {
+    pub commit: Option<String>,
+    pub abort: Option<String>,
+    pub rename: Option<String>,
+    pub source: Option<String>,
+    pub target: Option<String>,
+    pub extract: Option<String>,
+    pub prefix: Option<String>,
+    pub format: Option<String>,
+    pub delete: Option<String>,
+}
+
+// 批量删除对象 & 扩展：原子地发布（commit）或丢弃（abort）一个批次中暂存的全部对象
+pub async fn post_bucket(
+    req: web::HttpRequest,
+    mut body: web::types::Payload,
+    Query(query): Query<PostBucketQuery>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let bucket_name: String = get_path_param(&req, "bucket")?;
+    if query.delete.is_some() {
+        let identity = req.extensions().get::<Identity>().cloned();
+        return do_delete_objects(&state, bucket_name, identity.as_ref(), &mut body).await;
+    }
+    if query.extract.is_some() {
+        let prefix = query.prefix.unwrap_or_default();
+        return do_extract(&req, &state, bucket_name, prefix, query.format, &mut body).await;
+    }
+    if query.rename.is_some() {
+        let (Some(source), Some(target)) = (query.source, query.target) else {
+            return Err(BadRequest);
+        };
+        return do_rename(&state, bucket_name, source, target).await;
+    }
+    if let Some(staging_id) = query.commit.as_ref().or(query.abort.as_ref()) {
+        check_staging_id(staging_id)?;
+    }
+    match (query.commit, query.abort) {
+        (Some(staging_id), None) => {
+            let res = state
+                .client_write(CommitStaged {
+                    staging_id: staging_id.clone(),
+                    bucket_name: bucket_name.clone(),
+                })
+                .await
+                .map_err(|err| anyhow!(err.to_string()))?;
+            let committed: usize = match res.data.value.map(|v| v.parse()) {
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => {
+                    return Err(AppError::s3(
+                        StatusCode::NOT_FOUND,
+                        "NoSuchStagingId",
+                        "No objects are staged under the given staging id",
+                    ))
+                }
+                None => return Err(anyhow!("提交暂存对象失败").into()),
+            };
+            let res = CommitStagedResult {
+                bucket: bucket_name,
+                staging_id,
+                committed,
+            };
+            let xml = to_string(&res).context("序列化失败")?;
+            Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+        }
+        (None, Some(staging_id)) => {
+            state
+                .client_write(AbortStaged {
+                    staging_id,
+                    bucket_name,
+                })
+                .await
+                .map_err(|err| anyhow!(err.to_string()))?;
+            Ok(HttpResponse::NoContent().finish())
+        }
+        _ => Err(BadRequest),
+    }
+}
+
+// 批量删除单次最多的键数
+const MAX_DELETE_KEYS: usize = 1000;
+
+// 批量删除对象，每个键单独返回结果；不存在的键同样视为删除成功。
+// 访问密钥没有某个键的删除权限时，该键返回 AccessDenied
+async fn do_delete_objects(
+    state: &App,
+    bucket_name: String,
+    identity: Option<&Identity>,
+    body: &mut web::types::Payload,
+) -> HandlerResponse {
+    let bucket_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(&bucket_name);
+    if !bucket_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    let mut bytes = BytesMut::new();
+    while let Some(item) = body.next().await {
+        let item = item.map_err(|err| anyhow!(err.to_string()))?;
+        bytes.extend_from_slice(&item);
+    }
+    let malformed = || {
+        AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "MalformedXML",
+            "The XML you provided was not well-formed or did not validate against our published schema",
+        )
+    };
+    let xml = std::str::from_utf8(&bytes).map_err(|_| malformed())?;
+    let delete: Delete = quick_xml::de::from_str(xml).map_err(|_| malformed())?;
+    if delete.objects.is_empty() || delete.objects.len() > MAX_DELETE_KEYS {
+        return Err(malformed());
+    }
+    let mut res = DeleteResult::default();
+    let mut keys = vec![];
+    let mut version_ids = vec![];
+    for object in delete.objects {
+        let object_path = format!("{}/{}", bucket_name, object.key);
+        let invalid = if !is_valid_key(&object.key) {
+            Some(("InvalidArgument", "Invalid object key"))
+        } else if identity
+            .is_some_and(|identity| !identity.allows(Operation::Delete, Some(&object_path), false))
+        {
+            Some(("AccessDenied", "Access Denied"))
+        } else if let Some(version_id) = &object.version_id {
+            check_version_id(&bucket_name, version_id)
+                .err()
+                .map(|_| ("InvalidArgument", "Invalid version id specified"))
+        } else {
+            None
+        };
+        match invalid {
+            None => {
+                keys.push(object.key);
+                version_ids.push(object.version_id);
+            }
+            Some((code, message)) => res.errors.push(DeleteError {
+                key: object.key,
+                code: code.to_string(),
+                message: message.to_string(),
+            }),
+        }
+    }
+    let file_paths: Vec<String> = keys
+        .iter()
+        .map(|key| {
+            let meta_file = format!("{}.meta", fs::stored_key(key));
+            bucket_path.join(meta_file).to_string_lossy().to_string()
+        })
+        .collect();
+    if !file_paths.is_empty() {
+        let written = state
+            .client_write(DeleteFilesV2 {
+                file_paths: file_paths.clone(),
+                version_ids: version_ids.clone(),
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        let failed: Vec<(String, String)> = written
+            .data
+            .value
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .context("批量删除失败")?;
+        for ((key, file_path), version_id) in keys.into_iter().zip(file_paths).zip(version_ids) {
+            if failed.iter().any(|(path, _)| *path == file_path) {
+                res.errors.push(DeleteError {
+                    key,
+                    code: "InternalError".to_string(),
+                    message: "We encountered an internal error. Please try again.".to_string(),
                 });
+            } else if !delete.quiet {
+                res.deleted.push(DeletedObject { key, version_id });
             }
         }
+    }
+    let xml = to_string(&res).context("序列化失败")?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+}
+
+// 可以映射到桶目录下路径的对象键：非空，不以 '/' 开头，不含 "." 或 ".." 路径段
+pub(crate) fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.starts_with('/') && !key.split('/').any(|p| p == "." || p == "..")
+}
+
+// 请求中的对象键不能映射到桶目录下的路径时返回 400
+fn check_object_key(key: &str) -> Result<(), AppError> {
+    if is_valid_key(key) {
+        return Ok(());
+    }
+    Err(AppError::s3(
+        StatusCode::BAD_REQUEST,
+        "InvalidArgument",
+        format!("invalid object key `{}`", key),
+    ))
+}
+
+// 扩展：在桶内重命名对象；source 以 '/' 结尾时重命名整个前缀。只改写元数据，不读写分片数据；
+// 任一目标对象已存在时返回 409，不覆盖
+async fn do_rename(
+    state: &App,
+    bucket_name: String,
+    source: String,
+    target: String,
+) -> HandlerResponse {
+    let is_prefix = source.ends_with('/');
+    if !is_valid_key(&source) || !is_valid_key(&target) || is_prefix != target.ends_with('/') {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "source and target must be keys, or both prefixes ending with '/'",
+        ));
+    }
+    let res = state
+        .client_write(RenameObject {
+            bucket_name: bucket_name.clone(),
+            source: source.clone(),
+            target: target.clone(),
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    if res.data.value.as_deref() == Some(RENAME_TARGET_EXISTS) {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "KeyAlreadyExists",
+            "The target key already exists",
+        ));
+    }
+    let renamed: usize = match res.data.value.map(|v| v.parse()) {
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchKey",
+                "The specified key does not exist.",
+            ))
+        }
+        None => return Err(anyhow!("重命名失败").into()),
+    };
+    let res = RenameResult {
+        bucket: bucket_name,
+        source,
+        target,
+        renamed,
+    };
+    let xml = to_string(&res).context("序列化失败")?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+}
 
-        let result = ListBucketResult {
-            name: bucket_name,
-            prefix: query.prefix.unwrap_or_else(|| "".to_string()),
-            is_truncated: false,
-            max_keys: 100000,
-            contents,
+// 扩展：展开上传的 tar/zip 归档，每个文件保存为 prefix 下同路径的对象，Content-Type 按文件名推断。
+// 先校验全部条目的路径和桶的覆盖限制，再逐个写入；写入中途失败时已写入的对象保留
+async fn do_extract(
+    req: &web::HttpRequest,
+    state: &App,
+    bucket_name: String,
+    prefix: String,
+    format: Option<String>,
+    body: &mut web::types::Payload,
+) -> HandlerResponse {
+    let bucket_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(&bucket_name);
+    if !bucket_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    let invalid_argument =
+        |msg: String| AppError::s3(StatusCode::BAD_REQUEST, "InvalidArgument", msg);
+    let format = match format.as_deref() {
+        Some(format) => Some(format.parse::<ArchiveFormat>().map_err(invalid_argument)?),
+        None => None,
+    };
+    let source = match spool::read_body(req, body).await? {
+        UploadBody::Memory(bytes) => {
+            check_content_sha256(req, &bytes)?;
+            ArchiveSource::Memory(Arc::new(bytes))
+        }
+        UploadBody::Spooled(spooled) => {
+            check_payload_sha256(req, || spooled.sha256.clone())?;
+            ArchiveSource::Spooled(Arc::new(spooled))
+        }
+    };
+    let format = format
+        .or_else(|| ArchiveFormat::detect(source.head()))
+        .ok_or_else(|| {
+            invalid_argument("Unrecognized archive, specify format=tar or format=zip".to_string())
+        })?;
+    let entries = {
+        let source = source.clone();
+        pool::run(move || source.entries(format))
+            .await?
+            .map_err(|err| invalid_argument(format!("Malformed archive: {}", err)))?
+    };
+    let mut keyed = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let key = archive::entry_key(&prefix, &entry.path).ok_or_else(|| {
+            invalid_argument(format!(
+                "Archive entry `{}` has an invalid path",
+                entry.path
+            ))
+        })?;
+        keyed.push((key, entry));
+    }
+    let cfg = config::get();
+    if cfg.is_denied(&bucket_name, RestrictedOperation::Overwrite)
+        && keyed
+            .iter()
+            .any(|(key, _)| bucket_path.join(format!("{}.meta", key)).exists())
+    {
+        return Err(AppError::s3(
+            StatusCode::FORBIDDEN,
+            "AccessDenied",
+            format!("该桶禁止{}", RestrictedOperation::Overwrite.describe()),
+        ));
+    }
+    let mut bytes = 0;
+    for (key, entry) in &keyed {
+        let data = {
+            let source = source.clone();
+            let entry = entry.clone();
+            pool::run(move || source.read(&entry))
+                .await?
+                .map_err(|err| invalid_argument(format!("Malformed archive: {}", err)))?
         };
+        bytes += data.len() as u64;
+        let mut attrs = ObjectAttrs::default();
+        // 默认加密的桶中，展开的对象同样按 SSE-S3 加密
+        let data = match sse::for_bucket(&bucket_name) {
+            Some((encryption, cipher)) => {
+                attrs.etag = Some(etag::md5_hex(&data));
+                attrs.encryption = Some(sse::seal(encryption)?);
+                let object_path = format!("{}/{}", bucket_name, key);
+                encrypt_body(&object_path, &mut attrs, &cipher, data)?
+            }
+            None => data,
+        };
+        let mut file_path = bucket_path.join(key).to_string_lossy().to_string();
+        file_path.push_str(".meta");
+        let res = state
+            .client_write(UploadFileV2 {
+                file_path,
+                body: data,
+                attrs,
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        check_written(res.data.value)?;
+    }
+    info!(
+        "桶 {} 展开归档：{} 个对象，{} 字节",
+        bucket_name,
+        keyed.len(),
+        bytes
+    );
+    let res = ExtractResult {
+        bucket: bucket_name,
+        prefix,
+        extracted: keyed.len(),
+        bytes,
+    };
+    let xml = to_string(&res).context("序列化失败")?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+}
 
-        let xml = to_string(&result).context("序列化失败")?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
-    } else {
-        Ok(HttpResponse::NotFound().finish())
+// 分页列表单页最多返回的对象数
+const MAX_KEYS: usize = 1000;
+
+// 按最后修改时间倒序列表的分页游标：上一页最后一个对象的 "纳秒时间戳/键"
+fn encode_mtime_token(time: i64, key: &str) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}/{}", time, key))
+}
+
+fn decode_mtime_token(token: &str) -> Option<(i64, String)> {
+    let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+    let (time, key) = raw.split_once('/')?;
+    Some((time.parse().ok()?, key.to_string()))
+}
+
+// 扩展：递归列出桶内（前缀下）的全部对象，按最后修改时间从新到旧排序，时间相同按键排序
+fn list_by_mtime(
+    bucket_name: &str,
+    bucket_path: &Path,
+    prefix: &str,
+    max_keys: usize,
+    token: Option<&str>,
+    inline: bool,
+) -> Result<String, AppError> {
+    let after = match token {
+        Some(token) => Some(decode_mtime_token(token).ok_or_else(|| {
+            AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "The continuation token provided is incorrect",
+            )
+        })?),
+        None => None,
+    };
+    let mut meta_files = Vec::new();
+    fs::walk_meta_files(bucket_path, &mut meta_files).context("遍历桶目录失败")?;
+    let mut objects: Vec<(i64, String, Content)> = meta_files
+        .into_iter()
+        .filter_map(|path| {
+            let key = path.strip_prefix(bucket_path).ok()?.to_string_lossy();
+            let key = key.strip_suffix(".meta")?.to_string();
+            if !key.starts_with(prefix) {
+                return None;
+            }
+            let metadata = match fs::load_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    warn!("跳过无法读取的元数据 {:?}: {}", path, err);
+                    return None;
+                }
+            };
+            let time = metadata.time.timestamp_nanos_opt().unwrap_or_default();
+            let content = list_content(key.clone(), &metadata, inline);
+            Some((time, key, content))
+        })
+        .filter(|(time, key, _)| match &after {
+            Some((after_time, after_key)) => {
+                time < after_time || (time == after_time && key > after_key)
+            }
+            None => true,
+        })
+        .collect();
+    objects.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    let truncated = objects.len() > max_keys;
+    objects.truncate(max_keys);
+
+    let mut xml = format!(
+        "<ListBucketResult><Name>{}</Name><Prefix>{}</Prefix><MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+        escape(bucket_name),
+        escape(prefix),
+        max_keys,
+        truncated,
+    );
+    if truncated {
+        if let Some((time, key, _)) = objects.last() {
+            xml.push_str(&format!(
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                encode_mtime_token(*time, key)
+            ));
+        }
+    }
+    for (_, _, content) in &objects {
+        xml.push_str(&to_string_with_root("Contents", content).context("序列化失败")?);
     }
+    xml.push_str("</ListBucketResult>");
+    Ok(xml)
 }
 
-// 查询桶是否存在
+// 请求方是否为期望 HTML 的浏览器
+fn accepts_html(req: &web::HttpRequest) -> bool {
+    req.headers()
+        .get("Accept")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/html"))
+}
+
+// URL 路径中需要转义的字符，保留 '/' 作为层级分隔
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// 以 autoindex 风格的 HTML 页面展示公开桶中某一层前缀下的对象和子前缀
+fn render_index(bucket_name: &str, bucket_path: &Path, prefix: &str) -> Result<String, AppError> {
+    let (dir_part, name_part) = match prefix.rfind('/') {
+        Some(i) => prefix.split_at(i + 1),
+        None => ("", prefix),
+    };
+    let mut prefixes = Vec::new();
+    let mut objects = Vec::new();
+    if let Ok(entries) = read_dir(bucket_path.join(dir_part)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(name_part) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                prefixes.push(name);
+            } else if let Some(key) = name.strip_suffix(".meta") {
+                if let Ok(metadata) = fs::load_metadata(entry.path()) {
+                    objects.push((key.to_string(), metadata));
+                }
+            }
+        }
+    }
+    prefixes.sort();
+    objects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let prefix_link = |p: &str| {
+        let p: String = url::form_urlencoded::byte_serialize(p.as_bytes()).collect();
+        format!(
+            "/api/{}?prefix={}",
+            utf8_percent_encode(bucket_name, PATH_ENCODE_SET),
+            p
+        )
+    };
+    let title = escape(&format!("Index of /{}/{}", bucket_name, dir_part)).to_string();
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body><h1>{0}</h1><hr><pre>\n",
+        title
+    );
+    if !dir_part.is_empty() {
+        let parent = dir_part[..dir_part.len() - 1]
+            .rfind('/')
+            .map_or("", |i| &dir_part[..=i]);
+        html.push_str(&format!(
+            "<a href=\"{}\">../</a>\n",
+            escape(&prefix_link(parent))
+        ));
+    }
+    for name in &prefixes {
+        let href = prefix_link(&format!("{}{}/", dir_part, name));
+        html.push_str(&format!(
+            "<a href=\"{}\">{}/</a>\n",
+            escape(&href),
+            escape(name)
+        ));
+    }
+    for (name, metadata) in &objects {
+        let href = format!(
+            "/api/{}/{}",
+            utf8_percent_encode(bucket_name, PATH_ENCODE_SET),
+            utf8_percent_encode(&format!("{}{}", dir_part, name), PATH_ENCODE_SET)
+        );
+        html.push_str(&format!(
+            "<a href=\"{}\">{}</a>{:>width$} {:>20}\n",
+            escape(&href),
+            escape(name),
+            metadata.time.format("%d-%b-%Y %H:%M"),
+            metadata.size,
+            width = 60usize.saturating_sub(name.chars().count()) + 17,
+        ));
+    }
+    html.push_str("</pre><hr></body></html>\n");
+    Ok(html)
+}
+
+// 桶用量的缓存时间，期间 HeadBucket 不重新遍历桶
+const BUCKET_USAGE_TTL: Duration = Duration::from_secs(10);
+
+struct BucketUsage {
+    counted_at: Instant,
+    objects: u64,
+    bytes: u64,
+}
+
+static BUCKET_USAGE: LazyLock<Mutex<HashMap<String, BucketUsage>>> =
+    LazyLock::new(Default::default);
+
+// 桶的对象数和字节数，结果缓存 BUCKET_USAGE_TTL
+async fn bucket_usage(bucket_name: &str, bucket_path: PathBuf) -> anyhow::Result<(u64, u64)> {
+    if let Some(usage) = BUCKET_USAGE.lock().unwrap().get(bucket_name) {
+        if usage.counted_at.elapsed() < BUCKET_USAGE_TTL {
+            return Ok((usage.objects, usage.bytes));
+        }
+    }
+    let (objects, bytes) = tokio::task::spawn_blocking(move || fs::bucket_usage(&bucket_path))
+        .await?
+        .context("统计桶用量失败")?;
+    BUCKET_USAGE.lock().unwrap().insert(
+        bucket_name.to_string(),
+        BucketUsage {
+            counted_at: Instant::now(),
+            objects,
+            bytes,
+        },
+    );
+    Ok((objects, bytes))
+}
+
+// 查询桶是否存在；开启 --bucket-usage-headers 时附带对象数和用量
 pub async fn head_bucket(req: web::HttpRequest) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name);
+        .join(&bucket_name);
     if file_path.as_path().is_dir() {
-        Ok(HttpResponse::Ok().content_type("application/xml").finish())
+        let mut resp = HttpResponse::Ok();
+        if config::get().bucket_usage_headers {
+            let (count, bytes) = bucket_usage(&bucket_name, file_path).await?;
+            resp.header("x-rs3-object-count", count.to_string())
+                .header("x-rs3-bytes-used", bytes.to_string());
+        }
+        Ok(resp
+            .header("x-amz-bucket-region", "us-east-1")
+            .content_type("application/xml")
+            .finish())
     } else {
         Ok(HttpResponse::NotFound()
             .content_type("application/xml")
@@ -200,44 +1478,203 @@ pub async fn head_bucket(req: web::HttpRequest) -> HandlerResponse {
     }
 }
 
-// 创建桶
+#[derive(Deserialize)]
+pub struct BucketConfigQuery {
+    pub website: Option<String>,
+    #[serde(rename = "response-headers")]
+    pub response_headers: Option<String>,
+    pub versioning: Option<String>,
+    pub policy: Option<String>,
+    pub lifecycle: Option<String>,
+}
+
+// 创建桶 & 设置桶的静态网站配置、默认响应头配置、版本控制状态、桶策略或生命周期配置
 pub async fn create_bucket(
     req: web::HttpRequest,
+    mut body: web::types::Payload,
+    Query(query): Query<BucketConfigQuery>,
     state: web::types::State<App>,
 ) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
+    if query.website.is_some()
+        || query.response_headers.is_some()
+        || query.versioning.is_some()
+        || query.policy.is_some()
+        || query.lifecycle.is_some()
+    {
+        let mut bytes = Vec::new();
+        while let Some(item) = body.next().await {
+            let item = item.map_err(|err| anyhow!(err.to_string()))?;
+            bytes.extend_from_slice(&item);
+        }
+        let xml = String::from_utf8(bytes).map_err(|_| BadRequest)?;
+        let malformed =
+            |message: String| AppError::s3(StatusCode::BAD_REQUEST, "MalformedXML", message);
+        let request = if query.policy.is_some() {
+            BucketPolicy::parse(&xml, &bucket_name).map_err(|message| {
+                AppError::s3(StatusCode::BAD_REQUEST, "MalformedPolicy", message)
+            })?;
+            SetBucketPolicy {
+                bucket_name: bucket_name.clone(),
+                config: Some(xml),
+            }
+        } else if query.lifecycle.is_some() {
+            LifecycleConfiguration::parse(&xml).map_err(malformed)?;
+            SetBucketLifecycle {
+                bucket_name: bucket_name.clone(),
+                config: Some(xml),
+            }
+        } else if query.website.is_some() {
+            let conf: WebsiteConfiguration =
+                quick_xml::de::from_str(&xml).map_err(|err| malformed(err.to_string()))?;
+            conf.validate().map_err(malformed)?;
+            SetBucketWebsite {
+                bucket_name: bucket_name.clone(),
+                config: Some(xml),
+            }
+        } else if query.versioning.is_some() {
+            let status = versioning::parse_config(&xml).map_err(malformed)?;
+            SetBucketVersioning {
+                bucket_name: bucket_name.clone(),
+                status: status.as_str().to_string(),
+            }
+        } else {
+            quick_xml::de::from_str::<ResponseHeadersConfiguration>(&xml)
+                .map_err(|err| malformed(err.to_string()))?;
+            SetBucketHeaders {
+                bucket_name: bucket_name.clone(),
+                config: Some(xml),
+            }
+        };
+        return set_bucket_config(&state, &bucket_name, request).await;
+    }
+    if !is_valid_bucket_name(&bucket_name) {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidBucketName",
+            "The specified bucket is not valid",
+        ));
+    }
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name);
-    state
-        .raft
-        .client_write(CreateBucket {
+        .join(&bucket_name);
+    if file_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "BucketAlreadyOwnedByYou",
+            "Your previous request to create the named bucket succeeded and you already own it",
+        ));
+    }
+    let res = state
+        .client_write(CreateBucketV2 {
             bucket_name: file_path.to_string_lossy().to_string(),
+            created: Utc::now(),
         })
         .await
         .map_err(|err| anyhow!(err.to_string()))?;
-    Ok(HttpResponse::Ok().finish())
+    check_written(res.data.value)?;
+    Ok(HttpResponse::Ok()
+        .header("Location", format!("/{}", bucket_name))
+        .finish())
+}
+
+// 写入或删除桶级配置（静态网站、默认响应头、版本控制状态、桶策略、生命周期配置）
+async fn set_bucket_config(state: &App, bucket_name: &str, request: Request) -> HandlerResponse {
+    let bucket_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name);
+    if !bucket_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    // 与 S3 一致，删除配置和写入桶策略时返回 204
+    let is_delete = matches!(
+        &request,
+        SetBucketWebsite { config: None, .. }
+            | SetBucketHeaders { config: None, .. }
+            | SetBucketLifecycle { config: None, .. }
+            | SetBucketPolicy { .. }
+    );
+    state
+        .client_write(request)
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    if is_delete {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::Ok().finish())
+    }
 }
 
-// 删除桶
+// 删除桶 & 删除桶的静态网站配置、默认响应头配置、桶策略或生命周期配置
 pub async fn delete_bucket(
     req: web::HttpRequest,
+    Query(query): Query<BucketConfigQuery>,
     state: web::types::State<App>,
 ) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
+    if query.website.is_some() {
+        let request = SetBucketWebsite {
+            bucket_name: bucket_name.clone(),
+            config: None,
+        };
+        return set_bucket_config(&state, &bucket_name, request).await;
+    }
+    if query.response_headers.is_some() {
+        let request = SetBucketHeaders {
+            bucket_name: bucket_name.clone(),
+            config: None,
+        };
+        return set_bucket_config(&state, &bucket_name, request).await;
+    }
+    if query.policy.is_some() {
+        let request = SetBucketPolicy {
+            bucket_name: bucket_name.clone(),
+            config: None,
+        };
+        return set_bucket_config(&state, &bucket_name, request).await;
+    }
+    if query.lifecycle.is_some() {
+        let request = SetBucketLifecycle {
+            bucket_name: bucket_name.clone(),
+            config: None,
+        };
+        return set_bucket_config(&state, &bucket_name, request).await;
+    }
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name);
-
-    state
-        .raft
+        .join(&bucket_name);
+    if !file_path.is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    let not_empty = || {
+        AppError::s3(
+            StatusCode::CONFLICT,
+            "BucketNotEmpty",
+            "The bucket you tried to delete is not empty",
+        )
+    };
+    if fs::bucket_has_objects(&file_path) || versioning::has_versions(&bucket_name) {
+        return Err(not_empty());
+    }
+    // 检查之后写入的对象由状态机再次检查
+    let res = state
         .client_write(DeleteBucket {
             bucket_name: file_path.to_string_lossy().to_string(),
         })
         .await
         .map_err(|err| anyhow!(err.to_string()))?;
-
-    Ok(HttpResponse::Ok().finish())
+    if res.data.value.is_some() {
+        return Err(not_empty());
+    }
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[derive(Deserialize)]
@@ -255,42 +1692,20 @@ pub async fn init_chunk_or_combine_chunk(
 ) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
+    check_object_key(&object_name)?;
     if let Some(upload_id) = query.upload_id {
-        let mut bytes = BytesMut::new();
-        while let Some(item) = body.next().await {
-            let item = item.context("")?;
-            bytes.extend_from_slice(&item);
-        }
-        let body = std::str::from_utf8(bytes.as_slice()).map_err(|err| anyhow!(err))?;
-        state
-            .raft
-            .client_write(CombineChunk {
-                bucket_name: bucket_name.clone(),
-                object_key: object_name.clone(),
-                upload_id: upload_id.clone(),
-                cmu: body.to_string(),
-            })
-            .await
-            .map_err(|err| anyhow!(err.to_string()))?;
-
-        let e_tag = cry::encrypt_by_md5(&format!("{}/{}", &bucket_name, &object_name));
-        let res = CompleteMultipartUploadResult {
-            bucket_name: bucket_name.to_string(),
-            object_key: object_name.to_string(),
-            etag: e_tag,
-        };
-        let xml = to_string(&res).map_err(|err| anyhow!(err))?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+        do_complete_upload(&req, &state, &mut body, bucket_name, object_name, upload_id).await
     } else {
         let guid = Uuid::new_v4();
         let upload_id = guid.to_string();
         info!("gen upload_id: {}", &upload_id);
+        let (attrs, encryption) = multipart_attrs(&req, &bucket_name)?;
         state
-            .raft
-            .client_write(InitChunk {
+            .client_write(InitChunkV2 {
                 bucket_name: bucket_name.clone(),
                 object_key: object_name.clone(),
                 upload_id: upload_id.clone(),
+                attrs,
             })
             .await
             .map_err(|err| anyhow!(err.to_string()))?;
@@ -301,7 +1716,8 @@ pub async fn init_chunk_or_combine_chunk(
             upload_id,
         };
         let xml = to_string(&resp).map_err(|err| anyhow!(err))?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+        let resp = HttpResponse::Ok().content_type("application/xml").body(xml);
+        Ok(with_sse_headers(resp, encryption.as_ref()))
     }
 }
 
@@ -315,69 +1731,452 @@ pub async fn init_chunk_or_combine_chunk_longpath(
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
     let object_suffix: String = get_path_param(&req, "objectSuffix")?;
+    check_object_key(&format!("{}/{}", object_name, object_suffix))?;
     let object_key = PathBuf::from(&object_name)
         .join(&object_suffix)
         .to_string_lossy()
         .to_string();
     if let Some(upload_id) = query.upload_id {
         info!("uploadId: {}", upload_id);
-        let mut bytes = BytesMut::new();
-        while let Some(item) = body.next().await {
-            let item = item.context("")?;
-            bytes.extend_from_slice(&item);
-        }
-        let body = std::str::from_utf8(bytes.as_slice()).map_err(|err| anyhow!(err))?;
+        do_complete_upload(&req, &state, &mut body, bucket_name, object_key, upload_id).await
+    } else {
+        let guid = Uuid::new_v4();
+        let upload_id = guid.to_string();
+        let (attrs, encryption) = multipart_attrs(&req, &bucket_name)?;
         state
-            .raft
-            .client_write(CombineChunk {
+            .client_write(InitChunkV2 {
                 bucket_name: bucket_name.clone(),
                 object_key: object_key.clone(),
                 upload_id: upload_id.clone(),
-                cmu: body.to_string(),
+                attrs,
             })
             .await
             .map_err(|err| anyhow!(err.to_string()))?;
-        let e_tag = cry::encrypt_by_md5(&format!("{}/{}", &bucket_name, &object_key));
-        let res = CompleteMultipartUploadResult {
-            bucket_name: bucket_name.to_string(),
-            object_key: object_key.to_string(),
-            etag: e_tag,
+
+        let resp = InitiateMultipartUploadResult {
+            bucket: bucket_name,
+            object_key,
+            upload_id,
         };
-        let xml = to_string(&res).map_err(|err| anyhow!(err))?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
-    } else {
-        let guid = Uuid::new_v4();
-        let upload_id = guid.to_string();
+        let xml = to_string(&resp).map_err(|err| anyhow!(err))?;
+        let resp = HttpResponse::Ok().content_type("application/xml").body(xml);
+        Ok(with_sse_headers(resp, encryption.as_ref()))
+    }
+}
+
+// 创建分片上传时的对象属性，要求服务端加密时一并生成加密参数
+fn multipart_attrs(
+    req: &web::HttpRequest,
+    bucket_name: &str,
+) -> Result<(ObjectAttrs, Option<Encryption>), AppError> {
+    let mut attrs = get_object_attrs(req)?;
+    let encryption = sse::parse_upload_request(req.headers(), bucket_name)?
+        .map(|request| sse::new_encryption(&request));
+    attrs.encryption = encryption.clone().map(sse::seal).transpose()?;
+    Ok((attrs, encryption))
+}
+
+// 状态机保存数据失败（如 --verify-writes 读回校验失败）时返回的值，不确认写入
+pub(crate) fn check_written(value: Option<String>) -> Result<(), AppError> {
+    match value {
+        Some(_) => Err(AppError::s3(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "InternalError",
+            "We encountered an internal error. Please try again.",
+        )),
+        None => Ok(()),
+    }
+}
+
+// 请求的 versionId 查询参数
+fn requested_version(req: &web::HttpRequest) -> Option<String> {
+    url::form_urlencoded::parse(req.query_string().as_bytes())
+        .find(|(key, _)| key == "versionId")
+        .map(|(_, value)| value.to_string())
+}
+
+// 从未开启过版本控制的桶只有 null 版本
+fn check_version_id(bucket_name: &str, version_id: &str) -> Result<(), AppError> {
+    if versioning::status(bucket_name).is_none() && version_id != versioning::NULL_VERSION_ID {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "Invalid version id specified",
+        ));
+    }
+    Ok(())
+}
+
+fn no_such_version() -> AppError {
+    AppError::s3(
+        StatusCode::NOT_FOUND,
+        "NoSuchVersion",
+        "The specified version does not exist.",
+    )
+}
+
+// 开启版本控制的桶返回写入的版本号，版本号由写入对象的日志序号生成
+fn with_version_id(mut resp: HttpResponse, bucket_name: &str, log_index: u64) -> HttpResponse {
+    if versioning::status(bucket_name) == Some(VersioningStatus::Enabled) {
+        if let Ok(value) = HeaderValue::from_str(&versioning::version_id(log_index, 0)) {
+            resp.headers_mut()
+                .insert(header::HeaderName::from_static("x-amz-version-id"), value);
+        }
+    }
+    resp
+}
+
+// 上传成功，返回对象的 ETag
+fn etag_response(etag: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .header("ETag", etag::quote(etag))
+        .finish()
+}
+
+// 未读取请求体就拒绝上传时关闭连接，避免剩余的请求体被当作下一个请求
+fn reject_unread_body(req: &web::HttpRequest, err: AppError) -> HttpResponse {
+    let mut resp = err.error_response(req);
+    resp.head_mut().set_connection_type(ConnectionType::Close);
+    resp
+}
+
+// 加密对象的响应带上加密方式
+fn with_sse_headers(mut resp: HttpResponse, encryption: Option<&Encryption>) -> HttpResponse {
+    for (name, value) in encryption.map(sse::response_headers).unwrap_or_default() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            resp.headers_mut()
+                .insert(header::HeaderName::from_static(name), value);
+        }
+    }
+    resp
+}
+
+// 直通存储保存原文件，不支持服务端加密
+fn sse_not_supported() -> AppError {
+    AppError::s3(
+        StatusCode::BAD_REQUEST,
+        "InvalidRequest",
+        "Server Side Encryption is not supported for objects in passthrough storage",
+    )
+}
+
+// 加密内存中的请求体。Content-Type 和校验和按明文确定，不再由写入的密文推断
+fn encrypt_body(
+    object_path: &str,
+    attrs: &mut ObjectAttrs,
+    cipher: &ObjectCipher,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, AppError> {
+    if attrs.content_type.is_none() {
+        let file_name = Path::new(object_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        attrs.content_type = Some(util::file::detect_content_type(&file_name, &body));
+    }
+    attrs.checksum_sha256 = Some(checksum::sha256_of(&body));
+    Ok(cipher.apply(0, 0, &body).context("加密对象失败")?)
+}
+
+fn no_such_upload() -> AppError {
+    AppError::s3(
+        StatusCode::NOT_FOUND,
+        "NoSuchUpload",
+        "The specified upload does not exist. The upload ID may be invalid, or the upload may have been aborted or completed.",
+    )
+}
+
+// 上传分片
+async fn do_upload_part(
+    req: &web::HttpRequest,
+    state: &App,
+    body: &mut web::types::Payload,
+    (bucket_name, object_key): (&str, &str),
+    upload_id: String,
+    part_number: String,
+) -> HandlerResponse {
+    let part_number = multipart::parse_part_number(&part_number).ok_or_else(|| {
+        AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "Part number must be an integer between 1 and 10000, inclusive",
+        )
+    })?;
+    if !multipart::upload_exists(bucket_name, object_key, &upload_id) {
+        return Err(no_such_upload());
+    }
+    let mut bytes = Vec::new();
+    bytes.reserve_exact(8 << 20);
+    while let Some(item) = body.next().await {
+        bytes.extend_from_slice(&spool::body_item(item)?);
+    }
+    spool::check_length(spool::declared_length(req), bytes.len() as u64)?;
+    check_content_sha256(req, &bytes)?;
+    let part_etag = etag::md5_hex(&bytes);
+    let codec = codec::from_headers(req.headers()).map_err(invalid_codec)?;
+    // 加密的分片上传以分片号为段号加密每个分片，SSE-C 需要创建时的客户密钥
+    let upload_meta = fs::upload_meta_path(bucket_name, object_key, &upload_id);
+    let encryption = fs::load_metadata(&upload_meta)?.encryption;
+    let customer = sse::customer_key(req.headers())?;
+    let bytes = match sse::read_cipher(encryption.as_ref(), customer.as_ref())? {
+        Some(cipher) => cipher
+            .apply(part_number, 0, &bytes)
+            .context("加密分片失败")?,
+        None => bytes,
+    };
+    let hash = fs::sum_sha256(&bytes).await;
+    let res = state
+        .client_write(UploadChunkV2 {
+            part_number: part_number.to_string(),
+            upload_id,
+            hash,
+            body: bytes,
+            etag: Some(part_etag.clone()),
+            codec,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    check_written(res.data.value)?;
+    Ok(with_sse_headers(
+        etag_response(&part_etag),
+        encryption.as_ref(),
+    ))
+}
+
+// 完成分片上传：先校验列出的分片均已上传，再合并
+async fn do_complete_upload(
+    req: &web::HttpRequest,
+    state: &App,
+    body: &mut web::types::Payload,
+    bucket_name: String,
+    object_key: String,
+    upload_id: String,
+) -> HandlerResponse {
+    if !multipart::upload_exists(&bucket_name, &object_key, &upload_id) {
+        return Err(no_such_upload());
+    }
+    let upload_meta = fs::upload_meta_path(&bucket_name, &object_key, &upload_id);
+    let encryption = fs::load_metadata(&upload_meta)?.encryption;
+    let mut bytes = BytesMut::new();
+    while let Some(item) = body.next().await {
+        let item = item.context("")?;
+        bytes.extend_from_slice(&item);
+    }
+    let malformed = || {
+        AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "MalformedXML",
+            "The XML you provided was not well-formed or did not validate against our published schema",
+        )
+    };
+    let body = std::str::from_utf8(&bytes[..]).map_err(|_| malformed())?;
+    let mut cmu: CompleteMultipartUpload =
+        quick_xml::de::from_str(body).map_err(|_| malformed())?;
+    for part in &mut cmu.part_etags {
+        part.etag = multipart::normalize_etag(&part.etag);
+    }
+    let uploaded = multipart::uploaded_parts(&upload_id).context("读取分片记录失败")?;
+    if let Err(err) = multipart::check_completion(&cmu.part_etags, &uploaded) {
+        return Err(match err {
+            CompletionError::NoParts => malformed(),
+            CompletionError::InvalidPartOrder => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidPartOrder",
+                "The list of parts was not in ascending order. The parts list must be specified in order by part number.",
+            ),
+            CompletionError::InvalidPart(n) => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidPart",
+                format!("Part {} could not be found or its ETag does not match", n),
+            ),
+        });
+    }
+    let object_etag = multipart::completed_etag(&cmu.part_etags, &uploaded);
+    let log_index;
+    // 日志中的分片列表为分片哈希
+    for part in &mut cmu.part_etags {
+        part.etag = uploaded[&(part.part_number as u32)].hash.clone();
+    }
+    let quarantined = if scan::enabled() {
+        // 需要记录扫描结果时直接以分片写入元数据，再清理分片上传
+        let assembled = multipart::assemble(&cmu.part_etags, &uploaded);
+        let chunks = assembled.chunks;
+        let chunk_sizes = assembled.chunk_sizes;
+        let size = assembled.size;
+        let upload_meta = fs::load_metadata(&upload_meta)?;
+        let object_encryption = upload_meta.encryption.map(|mut encryption| {
+            encryption.parts = assembled.chunk_parts;
+            encryption.part_offsets = assembled.chunk_offsets;
+            encryption
+        });
+        // 扫描加密的分片上传需要解密，SSE-C 需要请求提供客户密钥
+        let customer = sse::customer_key(req.headers())?;
+        let cipher = sse::read_cipher(object_encryption.as_ref(), customer.as_ref())?;
+        let mut attrs = ObjectAttrs {
+            content_type: Some(upload_meta.file_type),
+            website_redirect: upload_meta.website_redirect,
+            headers: upload_meta.headers,
+            scan: None,
+            etag: Some(object_etag.clone()),
+            checksum_sha256: checksum::composite_sha256(&assembled.part_hashes),
+            encryption: object_encryption.map(sse::seal).transpose()?,
+            create_only: false,
+            tags: upload_meta.tags,
+            codec: None,
+            if_match: None,
+        };
+        let object_path = format!("{}/{}", bucket_name, object_key);
+        let quarantined = scan_upload(
+            &object_path,
+            &mut attrs,
+            ScanInput::Chunks(&chunks, size, cipher.as_ref()),
+        )
+        .await?;
+        let mut file_path = PathBuf::from(DATA_DIR.get().unwrap())
+            .join(BASIC_PATH_SUFFIX)
+            .join(&bucket_name)
+            .join(&object_key)
+            .to_string_lossy()
+            .to_string();
+        file_path.push_str(".meta");
+        let res = state
+            .client_write(CommitChunkedFileV2 {
+                file_path,
+                size,
+                chunks,
+                chunk_sizes,
+                attrs,
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        log_index = res.log_id.index;
         state
-            .raft
-            .client_write(InitChunk {
+            .client_write(AbortChunk {
                 bucket_name: bucket_name.clone(),
                 object_key: object_key.clone(),
-                upload_id: upload_id.clone(),
+                upload_id,
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        quarantined
+    } else {
+        // 以规范化后的分片列表写入日志
+        let cmu = to_string(&cmu).context("序列化失败")?;
+        let res = state
+            .client_write(CombineChunk {
+                bucket_name: bucket_name.clone(),
+                object_key: object_key.clone(),
+                upload_id,
+                cmu,
             })
             .await
             .map_err(|err| anyhow!(err.to_string()))?;
+        log_index = res.log_id.index;
+        None
+    };
+    if let Some(err) = quarantined {
+        return Err(err);
+    }
+    let res = CompleteMultipartUploadResult {
+        bucket_name: bucket_name.clone(),
+        object_key,
+        etag: etag::quote(&object_etag),
+    };
+    let xml = to_string(&res).map_err(|err| anyhow!(err))?;
+    let resp = HttpResponse::Ok().content_type("application/xml").body(xml);
+    let resp = with_sse_headers(resp, encryption.as_ref());
+    Ok(with_version_id(resp, &bucket_name, log_index))
+}
+
+// 放弃分片上传
+async fn do_abort_upload(
+    state: &App,
+    bucket_name: String,
+    object_key: String,
+    upload_id: String,
+) -> HandlerResponse {
+    if !multipart::upload_exists(&bucket_name, &object_key, &upload_id) {
+        return Err(no_such_upload());
+    }
+    state
+        .client_write(AbortChunk {
+            bucket_name,
+            object_key,
+            upload_id,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    Ok(HttpResponse::NoContent().finish())
+}
 
-        let resp = InitiateMultipartUploadResult {
-            bucket: bucket_name,
-            object_key,
-            upload_id,
-        };
-        let xml = to_string(&resp).map_err(|err| anyhow!(err))?;
-        Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
+#[derive(Deserialize)]
+pub struct AbortUploadQuery {
+    #[serde(rename = "uploadId")]
+    pub upload_id: Option<String>,
+    pub tagging: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ListPartsQuery {
+    #[serde(rename = "uploadId")]
+    pub upload_id: Option<String>,
+    #[serde(rename = "max-parts")]
+    pub max_parts: Option<u32>,
+    #[serde(rename = "part-number-marker")]
+    pub part_number_marker: Option<u32>,
+    pub tagging: Option<String>,
+}
+
+// 列出已上传的分片
+fn do_list_parts(
+    bucket_name: String,
+    object_key: String,
+    upload_id: String,
+    query: &ListPartsQuery,
+) -> HandlerResponse {
+    if !multipart::upload_exists(&bucket_name, &object_key, &upload_id) {
+        return Err(no_such_upload());
     }
+    let uploaded = multipart::uploaded_parts(&upload_id).context("读取分片记录失败")?;
+    let marker = query.part_number_marker.unwrap_or(0);
+    let max_parts = query
+        .max_parts
+        .unwrap_or(multipart::MAX_PARTS)
+        .min(multipart::MAX_PARTS);
+    let (page, is_truncated) = multipart::page_parts(&uploaded, marker, max_parts);
+    let res = ListPartsResult {
+        bucket: bucket_name,
+        key: object_key,
+        upload_id,
+        part_number_marker: marker,
+        next_part_number_marker: page.last().map_or(marker, |part| part.part_number),
+        max_parts,
+        is_truncated,
+        parts: page
+            .into_iter()
+            .map(|part| Part {
+                part_number: part.part_number,
+                last_modified: part.last_modified,
+                etag: etag::quote(&part.etag),
+                size: part.size,
+            })
+            .collect(),
+    };
+    let xml = to_string(&res).context("序列化失败")?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml))
 }
 
 // 查询对象信息
 pub async fn head_object(req: web::HttpRequest) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
+    check_object_key(&object_name)?;
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
+        .join(&bucket_name)
         .join(object_name);
 
-    do_head_object(file_path).await
+    do_head_object(&req, file_path, &bucket_name).await
 }
 
 #[derive(Deserialize)]
@@ -386,6 +2185,7 @@ pub struct UploadFileOrChunkQuery {
     pub upload_id: Option<String>,
     #[serde(rename = "partNumber")]
     pub part_number: Option<String>,
+    pub tagging: Option<String>,
 }
 
 // 上传文件 & 上传文件分片
@@ -397,85 +2197,499 @@ pub async fn upload_file_or_upload_chunk(
 ) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
+    check_object_key(&object_name)?;
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
         .join(&bucket_name)
         .join(&object_name);
+    if query.tagging.is_some() {
+        return do_put_object_tagging(&req, &state, &mut body, &bucket_name, &object_name).await;
+    }
     match (query.upload_id, query.part_number) {
         (Some(upload_id), Some(part_number)) => {
-            let mut bytes = Vec::new();
-            bytes.reserve_exact(8 << 20);
-            while let Some(item) = body.next().await {
-                let item = item.map_err(|err| anyhow!(err.to_string()))?;
-                bytes.extend_from_slice(&item);
-            }
-            let hash = fs::sum_sha256(&bytes).await;
-            state
-                .raft
-                .client_write(UploadChunk {
-                    part_number,
-                    upload_id,
-                    hash: hash.clone(),
-                    body: bytes.to_vec(),
-                })
-                .await
-                .map_err(|err| anyhow!(err.to_string()))?;
-            Ok(HttpResponse::Ok().header("ETag", &hash).finish())
+            do_upload_part(
+                &req,
+                &state,
+                &mut body,
+                (&bucket_name, &object_name),
+                upload_id,
+                part_number,
+            )
+            .await
         }
         _ => {
-            if let Some(copy_source) = req.headers().get("x-amz-copy-source") {
-                state
-                    .raft
-                    .client_write(CopyFile {
-                        copy_source: copy_source.to_str().unwrap().to_string(),
-                        dest_bucket: bucket_name,
-                        dest_object: object_name,
-                    })
-                    .await
-                    .map_err(|err| anyhow!(err.to_string()))?;
-                Ok(HttpResponse::Ok().finish())
+            if let Some(copy_source) = get_header_value(&req, "x-amz-copy-source") {
+                do_copy_object(&req, &state, &copy_source, bucket_name, object_name).await
             } else {
-                let mut bytes = Vec::new();
-                bytes.reserve_exact(8 << 20);
-                while let Some(item) = body.next().await {
-                    let item = item.map_err(|err| anyhow!(err.to_string()))?;
-                    bytes.extend_from_slice(&item);
-                }
-
                 let mut metainfo_file_path = file_path.clone().to_string_lossy().to_string();
                 metainfo_file_path.push_str(".meta");
-                state
-                    .raft
-                    .client_write(UploadFile {
+                let mut attrs = match get_object_attrs(&req) {
+                    Ok(attrs) => attrs,
+                    Err(err) => return Ok(reject_unread_body(&req, err)),
+                };
+                let staging_id = get_staging_id(&req)?;
+                attrs.create_only = match conditional::create_only(req.headers()) {
+                    Ok(create_only) => create_only,
+                    Err(err) => return Ok(reject_unread_body(&req, err)),
+                };
+                attrs.if_match = conditional::if_match(req.headers());
+                let if_match = attrs.if_match.as_deref();
+                if !conditional::holds(&metainfo_file_path, if_match, attrs.create_only) {
+                    return Ok(reject_unread_body(&req, conditional::precondition_failed()));
+                }
+                let sse = sse::for_upload(req.headers(), &bucket_name)?;
+                let encryption = sse.as_ref().map(|(encryption, _)| encryption);
+                let cipher = sse.as_ref().map(|(_, cipher)| cipher);
+                attrs.encryption = encryption.cloned().map(sse::seal).transpose()?;
+                // pre-put 插件需要完整的请求体
+                let object_path = format!("{}/{}", bucket_name, object_name);
+                let checked = plugin::applies(PluginHook::PrePut, &object_path);
+                // 不需要完整请求体的上传边接收边保存分片
+                let cfg = config::get();
+                if sse.is_some() && cfg.backend_for(&object_path) == Backend::Passthrough {
+                    return Err(sse_not_supported());
+                }
+                let streaming = staging_id.is_none()
+                    && !checked
+                    && cfg.scanner.is_none()
+                    && cfg.backend_for(&object_path) == Backend::Dedup;
+                let body = if streaming {
+                    match spool::stream_body(&req, &state, &mut body, cipher, attrs.codec).await? {
+                        StreamedBody::Saved(saved) => {
+                            check_payload_sha256(&req, || saved.sha256.clone())?;
+                            let object_etag = saved.md5.clone();
+                            attrs.etag = Some(object_etag.clone());
+                            attrs.checksum_sha256 = checksum::sha256_base64(&saved.sha256);
+                            let log_index =
+                                spool::commit(&state, metainfo_file_path, *saved, attrs).await?;
+                            let resp = with_sse_headers(etag_response(&object_etag), encryption);
+                            return Ok(with_version_id(resp, &bucket_name, log_index));
+                        }
+                        StreamedBody::Small(bytes) => UploadBody::Memory(bytes),
+                    }
+                } else {
+                    spool::read_body(&req, &mut body).await?
+                };
+                let bytes = match body {
+                    UploadBody::Spooled(spooled) if staging_id.is_none() && !checked => {
+                        check_payload_sha256(&req, || spooled.sha256.clone())?;
+                        attrs.etag = Some(spooled.md5.clone());
+                        attrs.checksum_sha256 = checksum::sha256_base64(&spooled.sha256);
+                        let input = ScanInput::File(spooled.path(), spooled.size);
+                        let quarantined = scan_upload(&object_path, &mut attrs, input).await?;
+                        let log_index =
+                            spool::upload(&state, metainfo_file_path, &spooled, attrs, cipher)
+                                .await?;
+                        return match quarantined {
+                            Some(err) => Err(err),
+                            None => Ok(with_version_id(
+                                with_sse_headers(etag_response(&spooled.md5), encryption),
+                                &bucket_name,
+                                log_index,
+                            )),
+                        };
+                    }
+                    // 暂存上传和需要插件校验的上传仍在内存中处理
+                    UploadBody::Spooled(spooled) => spooled.read().await?,
+                    UploadBody::Memory(bytes) => bytes,
+                };
+                check_content_sha256(&req, &bytes)?;
+                let bytes = run_pre_put_plugins(&bucket_name, &object_name, &attrs, bytes).await?;
+                let object_etag = etag::md5_hex(&bytes);
+                attrs.etag = Some(object_etag.clone());
+                let quarantined =
+                    scan_upload(&object_path, &mut attrs, ScanInput::Memory(&bytes)).await?;
+                let bytes = match cipher {
+                    Some(cipher) => encrypt_body(&object_path, &mut attrs, cipher, bytes)?,
+                    None => bytes,
+                };
+                if let Some(staging_id) = staging_id {
+                    let res =
+                        do_stage_file(&state, staging_id, bucket_name, object_name, bytes, attrs)
+                            .await?;
+                    return match quarantined {
+                        Some(err) => Err(err),
+                        None => Ok(with_sse_headers(res, encryption)),
+                    };
+                }
+
+                let res = state
+                    .client_write(UploadFileV2 {
                         file_path: metainfo_file_path,
                         body: bytes.to_vec(),
+                        attrs,
                     })
                     .await
                     .map_err(|err| anyhow!(err.to_string()))?;
-                Ok(HttpResponse::Ok().finish())
+                let log_index = res.log_id.index;
+                conditional::check_applied(res.data.value.as_deref())?;
+                check_written(res.data.value)?;
+                match quarantined {
+                    Some(err) => Err(err),
+                    None => Ok(with_version_id(
+                        with_sse_headers(etag_response(&object_etag), encryption),
+                        &bucket_name,
+                        log_index,
+                    )),
+                }
+            }
+        }
+    }
+}
+
+// 范围无法满足时返回 416，并通过 Content-Range 告知对象大小
+fn range_not_satisfiable(req: &web::HttpRequest, size: u64) -> HttpResponse {
+    let mut resp = AppError::s3(
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        "InvalidRange",
+        "The requested range is not satisfiable",
+    )
+    .error_response(req);
+    if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", size)) {
+        resp.headers_mut().insert(header::CONTENT_RANGE, value);
+    }
+    resp
+}
+
+// 整体读取对象的内容，加密的对象用 cipher 解密
+async fn read_object(
+    meta_file_path: &str,
+    metadata: &Metadata,
+    cipher: Option<&ObjectCipher>,
+) -> anyhow::Result<Vec<u8>> {
+    match metadata.backend {
+        Backend::Dedup => {
+            let chunks = metadata.chunks.clone();
+            let mut data = Vec::with_capacity(metadata.size as usize);
+            for (index, hash) in chunks.into_iter().enumerate() {
+                let chunk = pool::run(move || fs::read_chunk_decompressed(&hash))
+                    .await?
+                    .context("读取分片失败")?;
+                match cipher {
+                    Some(cipher) => data.extend_from_slice(
+                        &cipher
+                            .apply_chunk(index, data.len() as u64, &chunk)
+                            .context("解密分片失败")?,
+                    ),
+                    None => data.extend_from_slice(&chunk),
+                }
             }
+            Ok(data)
+        }
+        Backend::Passthrough => {
+            let object_path =
+                fs::object_path_from_meta(meta_file_path).context("解析对象路径失败")?;
+            tokio::fs::read(fs::raw_path(&object_path))
+                .await
+                .context("读取文件失败")
+        }
+    }
+}
+
+// 扩展：调用 pre-put 插件，插件拒绝时返回 403
+async fn run_pre_put_plugins(
+    bucket_name: &str,
+    object_key: &str,
+    attrs: &ObjectAttrs,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, AppError> {
+    if !plugin::applies(
+        PluginHook::PrePut,
+        &format!("{}/{}", bucket_name, object_key),
+    ) {
+        return Ok(body);
+    }
+    let (body, rejected) = plugin::pre_put(
+        bucket_name.to_string(),
+        object_key.to_string(),
+        attrs.content_type.clone(),
+        body,
+    )
+    .await?;
+    match rejected {
+        Some(reason) => Err(AppError::s3(StatusCode::FORBIDDEN, "AccessDenied", reason)),
+        None => Ok(body),
+    }
+}
+
+// 扩展：扫描上传内容并把结果记入 attrs。感染时按 --scan-action 直接拒绝，
+// 或返回隔离的错误，由调用方在保存对象后返回
+async fn scan_upload(
+    object_path: &str,
+    attrs: &mut ObjectAttrs,
+    input: ScanInput<'_>,
+) -> Result<Option<AppError>, AppError> {
+    let status = scan::scan(input).await.map_err(|err| {
+        warn!("扫描 {} 失败: {:#}", object_path, err);
+        AppError::s3(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "ServiceUnavailable",
+            "The content scanner is unavailable",
+        )
+    })?;
+    let Some(status) = status else {
+        return Ok(None);
+    };
+    if status.verdict != ScanVerdict::Infected {
+        attrs.scan = Some(status);
+        return Ok(None);
+    }
+    let signature = status.signature.clone().unwrap_or_default();
+    info!("扫描发现 {} 感染: {}", object_path, signature);
+    let rejected = |action: &str| {
+        AppError::s3(
+            StatusCode::FORBIDDEN,
+            "AccessDenied",
+            format!("Upload {} by the content scanner: {}", action, signature),
+        )
+    };
+    match config::get().scan_action {
+        ScanAction::Reject => Err(rejected("rejected")),
+        ScanAction::Quarantine => {
+            attrs.scan = Some(status);
+            Ok(Some(rejected("quarantined")))
+        }
+    }
+}
+
+// 条件请求不满足时的响应：未修改返回 304，前置条件不满足返回 412（HEAD 的响应没有错误信息）
+fn conditional_response(
+    req: &web::HttpRequest,
+    metadata: &Metadata,
+    head: bool,
+) -> Option<HttpResponse> {
+    match conditional::evaluate(req.headers(), &metadata.etag, metadata.time) {
+        Outcome::Proceed => None,
+        Outcome::NotModified => Some(
+            HttpResponse::NotModified()
+                .header("ETag", etag::quote(&metadata.etag))
+                .header("Last-Modified", date_format_to_second(metadata.time))
+                .finish(),
+        ),
+        Outcome::PreconditionFailed if head => {
+            Some(HttpResponse::new(StatusCode::PRECONDITION_FAILED))
+        }
+        Outcome::PreconditionFailed => Some(conditional::precondition_failed().error_response(req)),
+    }
+}
+
+// 扫描结果的响应头
+// GET 和 HEAD 共用的对象响应头
+fn apply_object_headers(
+    resp: &mut web::HttpResponseBuilder,
+    bucket_name: &str,
+    metadata: &Metadata,
+) {
+    resp.header("Content-Type", &metadata.file_type)
+        .header("Last-Modified", date_format_to_second(metadata.time))
+        .header("ETag", etag::quote(&metadata.etag))
+        .header("Accept-Ranges", "bytes");
+    if let Some(version_id) = &metadata.version_id {
+        resp.header("x-amz-version-id", version_id);
+    }
+    if let Some(location) = &metadata.website_redirect {
+        resp.header("x-amz-website-redirect-location", location);
+    }
+    if !metadata.tags.is_empty() {
+        resp.header("x-amz-tagging-count", metadata.tags.len().to_string());
+    }
+    for (name, value) in metadata.encryption.iter().flat_map(sse::response_headers) {
+        resp.header(name, value);
+    }
+    apply_response_headers(resp, bucket_name, &metadata.headers);
+    default_content_disposition(resp, &metadata.name, &metadata.headers);
+    apply_scan_headers(resp, &metadata.scan);
+}
+
+fn apply_scan_headers(resp: &mut web::HttpResponseBuilder, scan: &Option<ScanStatus>) {
+    if let Some(scan) = scan {
+        resp.header("x-rs3-scan-status", scan.verdict.as_str());
+        // 扫描失败时 signature 为错误信息，只记录在元数据中
+        if let (ScanVerdict::Infected, Some(signature)) = (scan.verdict, &scan.signature) {
+            resp.header("x-rs3-scan-signature", signature);
         }
     }
 }
 
 // 删除文件
-pub async fn delete_file(req: web::HttpRequest, state: web::types::State<App>) -> HandlerResponse {
+pub async fn delete_file(
+    req: web::HttpRequest,
+    Query(query): Query<AbortUploadQuery>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
+    check_object_key(&object_name)?;
+    if query.tagging.is_some() {
+        return do_delete_object_tagging(&req, &state, &bucket_name, &object_name).await;
+    }
+    if let Some(upload_id) = query.upload_id {
+        return do_abort_upload(&state, bucket_name, object_name, upload_id).await;
+    }
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
+        .join(&bucket_name)
         .join(object_name);
     let mut metainfo_file_path = file_path.clone().to_string_lossy().to_string();
     metainfo_file_path.push_str(".meta");
-    state
-        .raft
-        .client_write(DeleteFile {
+    do_delete_object(&req, &state, &bucket_name, metainfo_file_path).await
+}
+
+// 删除对象逻辑：指定 versionId 时永久删除该版本，开启过版本控制的桶未指定时写入删除标记
+async fn do_delete_object(
+    req: &web::HttpRequest,
+    state: &App,
+    bucket_name: &str,
+    metainfo_file_path: String,
+) -> HandlerResponse {
+    let version_id = requested_version(req);
+    // 条件删除与当前对象比较，指定 versionId 时也是如此
+    let if_none_match = conditional::create_only(req.headers())?;
+    let if_match = conditional::if_match(req.headers());
+    if !conditional::holds(&metainfo_file_path, if_match.as_deref(), if_none_match) {
+        return Err(conditional::precondition_failed());
+    }
+    let mut resp = HttpResponse::Ok();
+    if let Some(version_id) = &version_id {
+        check_version_id(bucket_name, version_id)?;
+        let deleted = versioning::resolve(&metainfo_file_path, version_id)
+            .and_then(|path| fs::load_metadata(path).ok());
+        if deleted.is_some_and(|metadata| metadata.delete_marker) {
+            resp.header("x-amz-delete-marker", "true");
+        }
+        resp.header("x-amz-version-id", version_id);
+    }
+    let res = state
+        .client_write(DeleteFileV2 {
             file_path: metainfo_file_path,
+            version_id: version_id.clone(),
+            if_match,
+            if_none_match,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    conditional::check_applied(res.data.value.as_deref())?;
+    if version_id.is_none() {
+        let marker_id = match versioning::status(bucket_name) {
+            Some(VersioningStatus::Enabled) => Some(versioning::version_id(res.log_id.index, 0)),
+            Some(VersioningStatus::Suspended) => Some(versioning::NULL_VERSION_ID.to_string()),
+            None => None,
+        };
+        if let Some(marker_id) = marker_id {
+            resp.header("x-amz-delete-marker", "true")
+                .header("x-amz-version-id", marker_id);
+        }
+    }
+    Ok(resp.finish())
+}
+
+// 对象标签接口操作的元数据文件，指定 versionId 时为该版本
+fn tagging_target(
+    req: &web::HttpRequest,
+    bucket_name: &str,
+    object_key: &str,
+) -> Result<(String, Metadata), AppError> {
+    let mut metainfo_file_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name)
+        .join(object_key)
+        .to_string_lossy()
+        .to_string();
+    metainfo_file_path.push_str(".meta");
+    if let Some(version_id) = requested_version(req) {
+        check_version_id(bucket_name, &version_id)?;
+        let resolved =
+            versioning::resolve(&metainfo_file_path, &version_id).ok_or_else(no_such_version)?;
+        metainfo_file_path = resolved.to_string_lossy().to_string();
+    }
+    let no_such_key = || {
+        AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchKey",
+            "The specified key does not exist.",
+        )
+    };
+    if std::fs::metadata(&metainfo_file_path).is_err() {
+        return Err(no_such_key());
+    }
+    let metadata = fs::load_metadata(&metainfo_file_path)?;
+    if metadata.delete_marker {
+        return Err(no_such_key());
+    }
+    Ok((metainfo_file_path, metadata))
+}
+
+fn with_tagging_version(mut resp: web::HttpResponseBuilder, metadata: &Metadata) -> HttpResponse {
+    if let Some(version_id) = &metadata.version_id {
+        resp.header("x-amz-version-id", version_id);
+    }
+    resp.finish()
+}
+
+// PutObjectTagging：替换对象的全部标签
+async fn do_put_object_tagging(
+    req: &web::HttpRequest,
+    state: &App,
+    body: &mut web::types::Payload,
+    bucket_name: &str,
+    object_key: &str,
+) -> HandlerResponse {
+    let mut bytes = BytesMut::new();
+    while let Some(item) = body.next().await {
+        let item = item.map_err(|err| anyhow!(err.to_string()))?;
+        bytes.extend_from_slice(&item);
+    }
+    let malformed = || {
+        AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "MalformedXML",
+            "The XML you provided was not well-formed or did not validate against our published schema",
+        )
+    };
+    let xml = std::str::from_utf8(&bytes).map_err(|_| malformed())?;
+    let tags = tagging::parse_xml(xml).map_err(|err| match err {
+        Some(message) => invalid_tag(message),
+        None => malformed(),
+    })?;
+    let (file_path, metadata) = tagging_target(req, bucket_name, object_key)?;
+    state
+        .client_write(SetObjectTags { file_path, tags })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    Ok(with_tagging_version(HttpResponse::Ok(), &metadata))
+}
+
+// GetObjectTagging
+fn do_get_object_tagging(
+    req: &web::HttpRequest,
+    bucket_name: &str,
+    object_key: &str,
+) -> HandlerResponse {
+    let (_, metadata) = tagging_target(req, bucket_name, object_key)?;
+    let xml = tagging::to_xml(&metadata.tags).context("序列化失败")?;
+    let mut resp = HttpResponse::Ok();
+    if let Some(version_id) = &metadata.version_id {
+        resp.header("x-amz-version-id", version_id);
+    }
+    Ok(resp.content_type("application/xml").body(xml))
+}
+
+// DeleteObjectTagging：删除对象的全部标签
+async fn do_delete_object_tagging(
+    req: &web::HttpRequest,
+    state: &App,
+    bucket_name: &str,
+    object_key: &str,
+) -> HandlerResponse {
+    let (file_path, metadata) = tagging_target(req, bucket_name, object_key)?;
+    state
+        .client_write(SetObjectTags {
+            file_path,
+            tags: vec![],
         })
         .await
         .map_err(|err| anyhow!(err.to_string()))?;
-    Ok(HttpResponse::Ok().finish())
+    Ok(with_tagging_version(HttpResponse::NoContent(), &metadata))
 }
 
 // 长路径获取对象信息
@@ -483,42 +2697,267 @@ pub async fn head_object_longpath(req: web::HttpRequest) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
     let object_suffix: String = get_path_param(&req, "objectSuffix")?;
+    check_object_key(&format!("{}/{}", object_name, object_suffix))?;
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
+        .join(&bucket_name)
         .join(object_name)
         .join(object_suffix);
-    do_head_object(file_path).await
+    do_head_object(&req, file_path, &bucket_name).await
 }
 
-// 获取对象信息逻辑
-async fn do_head_object(file_path: PathBuf) -> HandlerResponse {
+// 获取对象信息逻辑：返回与 GET 相同的响应头（含 Content-Length 和用户元数据），不读取对象内容
+async fn do_head_object(
+    req: &web::HttpRequest,
+    file_path: PathBuf,
+    bucket_name: &str,
+) -> HandlerResponse {
     let mut metainfo_file_path = file_path.clone().to_string_lossy().to_string();
     metainfo_file_path.push_str(".meta");
-    info!("{}", metainfo_file_path);
-    if std::fs::metadata(&metainfo_file_path).is_err() {
-        let resp = HeadNotFoundResp {
-            no_exist: "1".to_string(),
+    // HEAD 的响应没有响应体，错误只能通过状态码表示
+    if let Some(version_id) = requested_version(req) {
+        let resolved = check_version_id(bucket_name, &version_id)
+            .ok()
+            .and_then(|_| versioning::resolve(&metainfo_file_path, &version_id));
+        let Some(resolved) = resolved else {
+            return Ok(web::HttpResponse::NotFound().finish());
         };
-        let xml = to_string(&resp).context("序列化失败")?;
-        return Ok(web::HttpResponse::NotFound()
-            .content_type("application/xml")
-            .body(xml));
+        metainfo_file_path = resolved.to_string_lossy().to_string();
+    }
+    if std::fs::metadata(&metainfo_file_path).is_err() {
+        return Ok(web::HttpResponse::NotFound().finish());
     }
     let metainfo = fs::load_metadata(&metainfo_file_path)?;
+    slowlog::note_backend(metainfo.backend);
+    if metainfo.delete_marker {
+        return Ok(web::HttpResponse::MethodNotAllowed()
+            .header("x-amz-delete-marker", "true")
+            .header(
+                "x-amz-version-id",
+                metainfo
+                    .version_id
+                    .as_deref()
+                    .unwrap_or(versioning::NULL_VERSION_ID),
+            )
+            .finish());
+    }
 
+    // 加密的对象与 GET 一样需要正确的 SSE-C 密钥
+    let cipher = sse::customer_key(req.headers())
+        .and_then(|customer| sse::read_cipher(metainfo.encryption.as_ref(), customer.as_ref()));
+    if let Err(err) = cipher {
+        return Ok(web::HttpResponse::new(err.status_code()));
+    }
+    if let Some(resp) = conditional_response(req, &metainfo, true) {
+        return Ok(resp);
+    }
+    // 已隔离的对象返回 403，仍带上扫描结果
+    let mut resp = if metainfo.is_quarantined() {
+        web::HttpResponse::Forbidden()
+    } else {
+        web::HttpResponse::Ok()
+    };
+    apply_object_headers(&mut resp, bucket_name, &metainfo);
+    let range_header = req
+        .headers()
+        .get("Range")
+        .and_then(|value| value.to_str().ok());
+    let length = match range_header.map(|header| range::parse(header, metainfo.size)) {
+        Some(Err(Unsatisfiable)) => return Ok(range_not_satisfiable(req, metainfo.size)),
+        Some(Ok(Some(r))) => {
+            resp.status(StatusCode::PARTIAL_CONTENT)
+                .header("Content-Range", r.content_range(metainfo.size));
+            r.length()
+        }
+        _ => {
+            apply_checksum_headers(&mut resp, req, &metainfo);
+            metainfo.size
+        }
+    };
     let body = once(ok::<_, web::Error>(Bytes::new()));
-    let last_modified = date_format_to_second(metainfo.time);
-    Ok(web::HttpResponse::Ok()
-        .content_type(metainfo.file_type)
-        .header(
-            "Content-Disposition",
-            format!("attachment; filename=\"{}\"", metainfo.name),
+    Ok(resp.content_length(length).no_chunking().streaming(body))
+}
+
+// 拷贝对象：只复制元数据，新对象引用源对象的分片
+async fn do_copy_object(
+    req: &web::HttpRequest,
+    state: &App,
+    copy_source: &str,
+    bucket_name: String,
+    object_key: String,
+) -> HandlerResponse {
+    let Some(source) = copy::parse_source(copy_source) else {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "Copy Source must mention the source bucket and key: sourcebucket/sourcekey",
+        ));
+    };
+    let directive = match get_header_value(req, "x-amz-metadata-directive") {
+        Some(value) => value
+            .parse::<MetadataDirective>()
+            .map_err(|err| AppError::s3(StatusCode::BAD_REQUEST, "InvalidArgument", err))?,
+        None => MetadataDirective::Copy,
+    };
+    let same_object = source.stored_path() == format!("{}/{}", bucket_name, object_key);
+    let src_metadata_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(format!("{}.meta", source.stored_path()));
+    if !src_metadata_path.is_file() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchKey",
+            "The specified key does not exist.",
+        ));
+    }
+    let src_metadata = fs::load_metadata(&src_metadata_path)?;
+    if src_metadata.is_quarantined() {
+        return Err(AppError::s3(
+            StatusCode::FORBIDDEN,
+            "AccessDenied",
+            "The object is quarantined by the content scanner",
+        ));
+    }
+    // SSE-C 加密的源对象需要 x-amz-copy-source-server-side-encryption-customer-* 提供密钥
+    let src_key = sse::copy_source_key(req.headers())?;
+    let src_cipher = sse::read_cipher(src_metadata.encryption.as_ref(), src_key.as_ref())?;
+    let dest_sse = sse::parse_upload_request(req.headers(), &bucket_name)?;
+    // 加密方式不变时新对象引用源对象的分片，否则读出明文按新的加密方式重新写入
+    let shared = match (&src_metadata.encryption, &dest_sse) {
+        (None, None) => true,
+        (Some(src), None) | (Some(src), Some(SseRequest::S3)) => src.algorithm == SseAlgorithm::S3,
+        (Some(_), Some(SseRequest::Customer(dest))) => {
+            src_key.as_ref().is_some_and(|src| src.key == dest.key)
+        }
+        (None, Some(_)) => false,
+    };
+    if same_object && directive == MetadataDirective::Copy && shared {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "This copy request is illegal because it is trying to copy an object to itself without changing the object's metadata, storage class, website redirect location or encryption attributes.",
+        ));
+    }
+    if !shared {
+        return do_reencrypt_copy(
+            req,
+            state,
+            (&src_metadata_path.to_string_lossy(), &src_metadata),
+            (src_cipher, dest_sse),
+            directive,
+            (bucket_name, object_key),
         )
-        .header("Last-Modified", last_modified)
-        .content_length(metainfo.size as u64)
-        .no_chunking()
-        .streaming(body))
+        .await;
+    }
+    let tags = tagging::for_copy(req.headers(), &src_metadata.tags).map_err(invalid_tag)?;
+    // 只替换标签时沿用源对象的其他属性
+    let attrs = match directive {
+        MetadataDirective::Copy if tags == src_metadata.tags => None,
+        MetadataDirective::Copy => Some(ObjectAttrs {
+            content_type: Some(src_metadata.file_type.clone()),
+            website_redirect: src_metadata.website_redirect.clone(),
+            headers: src_metadata.headers.clone(),
+            tags,
+            ..Default::default()
+        }),
+        MetadataDirective::Replace => Some(ObjectAttrs {
+            tags,
+            ..get_object_attrs(req)?
+        }),
+    };
+    let res = state
+        .client_write(CopyFileV2 {
+            copy_source: copy_source.to_string(),
+            dest_bucket: bucket_name.clone(),
+            dest_object: object_key,
+            attrs,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let log_index = res.log_id.index;
+    let last_modified = res
+        .data
+        .value
+        .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+        .context("拷贝对象失败")?;
+    // 拷贝的对象内容不变，沿用源对象的 ETag
+    let res = CopyObjectResult {
+        last_modified: last_modified.with_timezone(&Utc),
+        etag: etag::quote(&src_metadata.etag),
+    };
+    let xml = to_string(&res).context("序列化失败")?;
+    let resp = HttpResponse::Ok().content_type("application/xml").body(xml);
+    let resp = with_sse_headers(resp, src_metadata.encryption.as_ref());
+    Ok(with_version_id(resp, &bucket_name, log_index))
+}
+
+// 改变加密方式的拷贝：读出源对象的明文，按目标的加密方式作为新对象写入
+async fn do_reencrypt_copy(
+    req: &web::HttpRequest,
+    state: &App,
+    (src_metadata_path, src_metadata): (&str, &Metadata),
+    (src_cipher, dest_sse): (Option<ObjectCipher>, Option<SseRequest>),
+    directive: MetadataDirective,
+    (bucket_name, object_key): (String, String),
+) -> HandlerResponse {
+    let object_path = format!("{}/{}", bucket_name, object_key);
+    if dest_sse.is_some() && config::get().backend_for(&object_path) == Backend::Passthrough {
+        return Err(sse_not_supported());
+    }
+    let mut attrs = match directive {
+        MetadataDirective::Copy => ObjectAttrs {
+            content_type: Some(src_metadata.file_type.clone()),
+            website_redirect: src_metadata.website_redirect.clone(),
+            headers: src_metadata.headers.clone(),
+            ..Default::default()
+        },
+        MetadataDirective::Replace => get_object_attrs(req)?,
+    };
+    attrs.tags = tagging::for_copy(req.headers(), &src_metadata.tags).map_err(invalid_tag)?;
+    attrs.scan = src_metadata.scan.clone();
+    attrs.etag = Some(src_metadata.etag.clone());
+    let body = read_object(src_metadata_path, src_metadata, src_cipher.as_ref()).await?;
+    let encryption = dest_sse.as_ref().map(sse::new_encryption);
+    let body = match &encryption {
+        Some(encryption) => {
+            let key = match &dest_sse {
+                Some(SseRequest::Customer(customer)) => customer.key.clone(),
+                _ => encryption.key.clone(),
+            };
+            let cipher = ObjectCipher::new(encryption, key);
+            attrs.encryption = Some(sse::seal(encryption.clone())?);
+            encrypt_body(&object_path, &mut attrs, &cipher, body)?
+        }
+        None => {
+            attrs.checksum_sha256 = Some(checksum::sha256_of(&body));
+            body
+        }
+    };
+    let mut file_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(&object_path)
+        .to_string_lossy()
+        .to_string();
+    file_path.push_str(".meta");
+    let res = state
+        .client_write(UploadFileV2 {
+            file_path: file_path.clone(),
+            body,
+            attrs,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let log_index = res.log_id.index;
+    check_written(res.data.value)?;
+    let metadata = fs::load_metadata(&file_path)?;
+    let res = CopyObjectResult {
+        last_modified: metadata.time,
+        etag: etag::quote(&src_metadata.etag),
+    };
+    let xml = to_string(&res).context("序列化失败")?;
+    let resp = HttpResponse::Ok().content_type("application/xml").body(xml);
+    let resp = with_sse_headers(resp, encryption.as_ref());
+    Ok(with_version_id(resp, &bucket_name, log_index))
 }
 
 // 长路径上传文件 & 上传文件分片
@@ -531,6 +2970,7 @@ pub async fn upload_file_or_upload_chunk_longpath(
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
     let object_suffix: String = get_path_param(&req, "objectSuffix")?;
+    check_object_key(&format!("{}/{}", object_name, object_suffix))?;
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
         .join(&bucket_name)
@@ -540,58 +2980,135 @@ pub async fn upload_file_or_upload_chunk_longpath(
         .join(&object_suffix)
         .to_string_lossy()
         .to_string();
+    if query.tagging.is_some() {
+        return do_put_object_tagging(&req, &state, &mut body, &bucket_name, &object_key).await;
+    }
     match (query.upload_id, query.part_number) {
         (Some(upload_id), Some(part_number)) => {
-            info!("long path");
-            let mut bytes = Vec::new();
-            bytes.reserve_exact(8 << 20);
-            while let Some(item) = body.next().await {
-                let item = item.map_err(|err| anyhow!(err.to_string()))?;
-                bytes.extend_from_slice(&item);
-            }
-            let hash = fs::sum_sha256(&bytes).await;
-            state
-                .raft
-                .client_write(UploadChunk {
-                    part_number,
-                    upload_id,
-                    hash: hash.clone(),
-                    body: bytes.to_vec(),
-                })
-                .await
-                .map_err(|err| anyhow!(err.to_string()))?;
-            Ok(HttpResponse::Ok().header("ETag", &hash).finish())
+            do_upload_part(
+                &req,
+                &state,
+                &mut body,
+                (&bucket_name, &object_key),
+                upload_id,
+                part_number,
+            )
+            .await
         }
         _ => {
-            if let Some(copy_source) = req.headers().get("x-amz-copy-source") {
-                state
-                    .raft
-                    .client_write(CopyFile {
-                        copy_source: copy_source.to_str().unwrap().to_string(),
-                        dest_bucket: bucket_name,
-                        dest_object: object_key,
-                    })
-                    .await
-                    .map_err(|err| anyhow!(err.to_string()))?;
-                Ok(HttpResponse::Ok().finish())
+            if let Some(copy_source) = get_header_value(&req, "x-amz-copy-source") {
+                do_copy_object(&req, &state, &copy_source, bucket_name, object_key).await
             } else {
-                let mut bytes = Vec::new();
-                bytes.reserve_exact(8 << 20);
-                while let Some(item) = body.next().await {
-                    let item = item.map_err(|err| anyhow!(err.to_string()))?;
-                    bytes.extend_from_slice(&item);
-                }
                 let mut metainfo_file_path = file_path.clone().to_string_lossy().to_string();
                 metainfo_file_path.push_str(".meta");
-                state
-                    .raft
-                    .client_write(UploadFile {
+                let mut attrs = match get_object_attrs(&req) {
+                    Ok(attrs) => attrs,
+                    Err(err) => return Ok(reject_unread_body(&req, err)),
+                };
+                let staging_id = get_staging_id(&req)?;
+                attrs.create_only = match conditional::create_only(req.headers()) {
+                    Ok(create_only) => create_only,
+                    Err(err) => return Ok(reject_unread_body(&req, err)),
+                };
+                attrs.if_match = conditional::if_match(req.headers());
+                let if_match = attrs.if_match.as_deref();
+                if !conditional::holds(&metainfo_file_path, if_match, attrs.create_only) {
+                    return Ok(reject_unread_body(&req, conditional::precondition_failed()));
+                }
+                let sse = sse::for_upload(req.headers(), &bucket_name)?;
+                let encryption = sse.as_ref().map(|(encryption, _)| encryption);
+                let cipher = sse.as_ref().map(|(_, cipher)| cipher);
+                attrs.encryption = encryption.cloned().map(sse::seal).transpose()?;
+                // pre-put 插件需要完整的请求体
+                let object_path = format!("{}/{}", bucket_name, object_key);
+                let checked = plugin::applies(PluginHook::PrePut, &object_path);
+                // 不需要完整请求体的上传边接收边保存分片
+                let cfg = config::get();
+                if sse.is_some() && cfg.backend_for(&object_path) == Backend::Passthrough {
+                    return Err(sse_not_supported());
+                }
+                let streaming = staging_id.is_none()
+                    && !checked
+                    && cfg.scanner.is_none()
+                    && cfg.backend_for(&object_path) == Backend::Dedup;
+                let body = if streaming {
+                    match spool::stream_body(&req, &state, &mut body, cipher, attrs.codec).await? {
+                        StreamedBody::Saved(saved) => {
+                            check_payload_sha256(&req, || saved.sha256.clone())?;
+                            let object_etag = saved.md5.clone();
+                            attrs.etag = Some(object_etag.clone());
+                            attrs.checksum_sha256 = checksum::sha256_base64(&saved.sha256);
+                            let log_index =
+                                spool::commit(&state, metainfo_file_path, *saved, attrs).await?;
+                            let resp = with_sse_headers(etag_response(&object_etag), encryption);
+                            return Ok(with_version_id(resp, &bucket_name, log_index));
+                        }
+                        StreamedBody::Small(bytes) => UploadBody::Memory(bytes),
+                    }
+                } else {
+                    spool::read_body(&req, &mut body).await?
+                };
+                let bytes = match body {
+                    UploadBody::Spooled(spooled) if staging_id.is_none() && !checked => {
+                        check_payload_sha256(&req, || spooled.sha256.clone())?;
+                        attrs.etag = Some(spooled.md5.clone());
+                        attrs.checksum_sha256 = checksum::sha256_base64(&spooled.sha256);
+                        let input = ScanInput::File(spooled.path(), spooled.size);
+                        let quarantined = scan_upload(&object_path, &mut attrs, input).await?;
+                        let log_index =
+                            spool::upload(&state, metainfo_file_path, &spooled, attrs, cipher)
+                                .await?;
+                        return match quarantined {
+                            Some(err) => Err(err),
+                            None => Ok(with_version_id(
+                                with_sse_headers(etag_response(&spooled.md5), encryption),
+                                &bucket_name,
+                                log_index,
+                            )),
+                        };
+                    }
+                    // 暂存上传和需要插件校验的上传仍在内存中处理
+                    UploadBody::Spooled(spooled) => spooled.read().await?,
+                    UploadBody::Memory(bytes) => bytes,
+                };
+                check_content_sha256(&req, &bytes)?;
+                let bytes = run_pre_put_plugins(&bucket_name, &object_key, &attrs, bytes).await?;
+                let object_etag = etag::md5_hex(&bytes);
+                attrs.etag = Some(object_etag.clone());
+                let quarantined =
+                    scan_upload(&object_path, &mut attrs, ScanInput::Memory(&bytes)).await?;
+                let bytes = match cipher {
+                    Some(cipher) => encrypt_body(&object_path, &mut attrs, cipher, bytes)?,
+                    None => bytes,
+                };
+                if let Some(staging_id) = staging_id {
+                    let res =
+                        do_stage_file(&state, staging_id, bucket_name, object_key, bytes, attrs)
+                            .await?;
+                    return match quarantined {
+                        Some(err) => Err(err),
+                        None => Ok(with_sse_headers(res, encryption)),
+                    };
+                }
+                let res = state
+                    .client_write(UploadFileV2 {
                         file_path: metainfo_file_path,
                         body: bytes.to_vec(),
+                        attrs,
                     })
                     .await
                     .map_err(|err| anyhow!(err.to_string()))?;
-                Ok(HttpResponse::Ok().finish())
+                let log_index = res.log_id.index;
+                conditional::check_applied(res.data.value.as_deref())?;
+                check_written(res.data.value)?;
+                match quarantined {
+                    Some(err) => Err(err),
+                    None => Ok(with_version_id(
+                        with_sse_headers(etag_response(&object_etag), encryption),
+                        &bucket_name,
+                        log_index,
+                    )),
+                }
             }
         }
     }
@@ -600,61 +3117,275 @@ pub async fn upload_file_or_upload_chunk_longpath(
 // 长路径删除文件
 pub async fn delete_file_longpath(
     req: web::HttpRequest,
+    Query(query): Query<AbortUploadQuery>,
     state: web::types::State<App>,
 ) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
     let object_suffix: String = get_path_param(&req, "objectSuffix")?;
+    check_object_key(&format!("{}/{}", object_name, object_suffix))?;
+    let object_key = PathBuf::from(&object_name)
+        .join(&object_suffix)
+        .to_string_lossy()
+        .to_string();
+    if query.tagging.is_some() {
+        return do_delete_object_tagging(&req, &state, &bucket_name, &object_key).await;
+    }
+    if let Some(upload_id) = query.upload_id {
+        return do_abort_upload(&state, bucket_name, object_key, upload_id).await;
+    }
     let file_path = PathBuf::from(DATA_DIR.get().unwrap())
         .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
+        .join(&bucket_name)
         .join(object_name)
         .join(object_suffix);
-    state
-        .raft
-        .client_write(DeleteFile {
-            file_path: file_path.to_string_lossy().to_string(),
-        })
-        .await
-        .map_err(|err| anyhow!(err.to_string()))?;
-    Ok(HttpResponse::Ok().finish())
+    let mut metainfo_file_path = file_path.to_string_lossy().to_string();
+    metainfo_file_path.push_str(".meta");
+    do_delete_object(&req, &state, &bucket_name, metainfo_file_path).await
 }
 
 // 产路径删除文件
-pub async fn download_file_longpath(req: web::HttpRequest) -> HandlerResponse {
+pub async fn download_file_longpath(
+    req: web::HttpRequest,
+    Query(query): Query<ListPartsQuery>,
+) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
     let object_suffix: String = get_path_param(&req, "objectSuffix")?;
-    let file_path = PathBuf::from(DATA_DIR.get().unwrap())
-        .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
-        .join(object_name)
-        .join(object_suffix);
-    do_download_file(file_path).await
+    check_object_key(&format!("{}/{}", object_name, object_suffix))?;
+    let object_key = PathBuf::from(&object_name)
+        .join(&object_suffix)
+        .to_string_lossy()
+        .to_string();
+    if query.tagging.is_some() {
+        return do_get_object_tagging(&req, &bucket_name, &object_key);
+    }
+    if let Some(upload_id) = query.upload_id.clone() {
+        return do_list_parts(bucket_name, object_key, upload_id, &query);
+    }
+    do_download_file(&req, &bucket_name, &object_key).await
 }
 
-// 下载文件
-pub async fn download_file(req: web::HttpRequest) -> HandlerResponse {
+// 下载文件 & 列出已上传的分片
+pub async fn download_file(
+    req: web::HttpRequest,
+    Query(query): Query<ListPartsQuery>,
+) -> HandlerResponse {
     let bucket_name: String = get_path_param(&req, "bucket")?;
     let object_name: String = get_path_param(&req, "object")?;
-    let file_path = PathBuf::from(DATA_DIR.get().unwrap())
-        .join(BASIC_PATH_SUFFIX)
-        .join(bucket_name)
-        .join(object_name);
-    do_download_file(file_path).await
+    check_object_key(&object_name)?;
+    if query.tagging.is_some() {
+        return do_get_object_tagging(&req, &bucket_name, &object_name);
+    }
+    if let Some(upload_id) = query.upload_id.clone() {
+        return do_list_parts(bucket_name, object_name, upload_id, &query);
+    }
+    do_download_file(&req, &bucket_name, &object_name).await
 }
 
-// 下载文件逻辑
-async fn do_download_file(file_path: PathBuf) -> HandlerResponse {
-    let mut metainfo_file_path = file_path.clone().to_string_lossy().to_string();
+// 下载文件逻辑，静态网站模式下先按桶的重定向规则和对象的重定向地址处理
+async fn do_download_file(
+    req: &web::HttpRequest,
+    bucket_name: &str,
+    object_key: &str,
+) -> HandlerResponse {
+    check_object_key(object_key)?;
+    let mut metainfo_file_path = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name)
+        .join(object_key)
+        .to_string_lossy()
+        .to_string();
     metainfo_file_path.push_str(".meta");
+    if let Some(version_id) = requested_version(req) {
+        check_version_id(bucket_name, &version_id)?;
+        let resolved =
+            versioning::resolve(&metainfo_file_path, &version_id).ok_or_else(no_such_version)?;
+        metainfo_file_path = resolved.to_string_lossy().to_string();
+    }
+    let website_mode = is_website_request(req, bucket_name, object_key);
+    if website_mode {
+        let conf = website::load(bucket_name).unwrap_or_default();
+        if let Some((code, location)) = conf.redirect_for(bucket_name, object_key, None) {
+            return website_redirect_response(code, &location);
+        }
+        if std::fs::metadata(&metainfo_file_path).is_err() {
+            if let Some((code, location)) = conf.redirect_for(bucket_name, object_key, Some(404)) {
+                return website_redirect_response(code, &location);
+            }
+            return Err(AppError::s3(
+                StatusCode::NOT_FOUND,
+                "NoSuchKey",
+                "The specified key does not exist.",
+            ));
+        }
+    }
+    // 删除后（包括写入删除标记后）不存在的对象
+    if std::fs::metadata(&metainfo_file_path).is_err() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchKey",
+            "The specified key does not exist.",
+        ));
+    }
     let meta_info = fs::load_metadata(&metainfo_file_path)?;
-    let body = DecompressStream::new(meta_info.chunks);
-    let content_disposition = format!("attachment; filename=\"{}\"", meta_info.name);
-    Ok(web::HttpResponse::Ok()
-        .header("Content-Type", "application/octet-stream")
-        .header("Content-Length", meta_info.size)
-        .header("Last-Modified", date_format_to_second(meta_info.time))
-        .header("Content-Disposition", content_disposition)
-        .streaming(body))
+    slowlog::note_backend(meta_info.backend);
+    // 只有按 versionId 读取时才会读到删除标记
+    if meta_info.delete_marker {
+        let mut resp = AppError::s3(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "MethodNotAllowed",
+            "The specified method is not allowed against this resource.",
+        )
+        .error_response(req);
+        resp.headers_mut().insert(
+            header::HeaderName::from_static("x-amz-delete-marker"),
+            HeaderValue::from_static("true"),
+        );
+        return Ok(resp);
+    }
+    if meta_info.is_quarantined() {
+        return Err(AppError::s3(
+            StatusCode::FORBIDDEN,
+            "AccessDenied",
+            "The object is quarantined by the content scanner",
+        ));
+    }
+    if let Some(location) = &meta_info.website_redirect {
+        if website_mode {
+            let location = website::resolve_redirect_location(bucket_name, location);
+            return website_redirect_response(301, &location);
+        }
+    }
+    let customer = sse::customer_key(req.headers())?;
+    let cipher = sse::read_cipher(meta_info.encryption.as_ref(), customer.as_ref())?;
+    if let Some(resp) = conditional_response(req, &meta_info, false) {
+        return Ok(resp);
+    }
+    access::record(bucket_name, object_key);
+    let mut resp = web::HttpResponse::Ok();
+    apply_object_headers(&mut resp, bucket_name, &meta_info);
+    // If-Range 不匹配时忽略 Range，返回完整内容
+    let range_header = req
+        .headers()
+        .get("Range")
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| conditional::if_range_matches(req.headers(), &meta_info.etag, meta_info.time));
+    // post-get 插件改写的内容整体读入内存后返回，不压缩，范围按改写后的内容计算
+    if plugin::applies(
+        PluginHook::PostGet,
+        &format!("{}/{}", bucket_name, object_key),
+    ) {
+        let body = read_object(&metainfo_file_path, &meta_info, cipher.as_ref()).await?;
+        let body = plugin::post_get(
+            bucket_name.to_string(),
+            object_key.to_string(),
+            meta_info.file_type.clone(),
+            body,
+        )
+        .await?;
+        let size = body.len() as u64;
+        return match range_header.map(|header| range::parse(header, size)) {
+            Some(Err(Unsatisfiable)) => Ok(range_not_satisfiable(req, size)),
+            Some(Ok(Some(r))) => Ok(resp
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header("Content-Range", r.content_range(size))
+                .body(body[r.start as usize..=r.end as usize].to_vec())),
+            _ => Ok(resp.body(body)),
+        };
+    }
+    // 校验和对应完整的对象内容，范围读取时不返回
+    if range_header.is_none() {
+        apply_checksum_headers(&mut resp, req, &meta_info);
+    }
+    // 范围读取返回原始内容，不协商压缩
+    match range_header.map(|header| range::parse(header, meta_info.size)) {
+        Some(Err(Unsatisfiable)) => return Ok(range_not_satisfiable(req, meta_info.size)),
+        Some(Ok(Some(r))) => {
+            resp.status(StatusCode::PARTIAL_CONTENT)
+                .header("Content-Range", r.content_range(meta_info.size))
+                .header("Content-Length", r.length());
+            return match meta_info.backend {
+                Backend::Dedup => {
+                    // 旧版本写入的元数据没有分片大小，只能从第一个分片开始解压
+                    let chunk_sizes = if meta_info.chunk_sizes.len() == meta_info.chunks.len() {
+                        &meta_info.chunk_sizes[..]
+                    } else {
+                        &[]
+                    };
+                    let (first, skip) = range::locate(chunk_sizes, r.start);
+                    let chunks = meta_info.chunks[first.min(meta_info.chunks.len())..].to_vec();
+                    let chunks = DecompressStream::new(chunks);
+                    // 加密的对象从第一个分片的起始偏移开始解密
+                    if let Some(cipher) = cipher {
+                        let chunks = sse::decrypt_chunks(chunks, cipher, first, r.start - skip);
+                        let body = range::slice_stream(chunks, skip, r.length());
+                        return Ok(resp.streaming(Box::pin(body)));
+                    }
+                    let body = range::slice_stream(chunks, skip, r.length());
+                    Ok(resp.streaming(Box::pin(body)))
+                }
+                Backend::Passthrough => {
+                    let object_path = fs::object_path_from_meta(&metainfo_file_path)
+                        .context("解析对象路径失败")?;
+                    let body =
+                        fs::raw_file_range_stream(fs::raw_path(&object_path), r.start, r.length())
+                            .await
+                            .context("读取文件失败")?;
+                    Ok(resp.streaming(Box::pin(body)))
+                }
+            };
+        }
+        _ => {}
+    }
+    // 上传时已指定 Content-Encoding 的对象原样返回，不再协商压缩；加密的对象分片中是密文，也不压缩
+    let compressible = meta_info.backend == Backend::Dedup
+        && meta_info.encryption.is_none()
+        && util::file::is_compressible(&meta_info.file_type)
+        && !has_object_header(&meta_info.headers, "Content-Encoding");
+    if compressible {
+        resp.header("Vary", "Accept-Encoding");
+        match negotiate_encoding(req) {
+            Some(ContentEncoding::Zstd) => {
+                // 分片本身就是 zstd 帧，直接拼接返回，无需解压再压缩；有其他编码的分片时不压缩
+                let chunks = meta_info.chunks.clone();
+                let size = tokio::task::spawn_blocking(move || fs::zstd_frames_size(&chunks))
+                    .await
+                    .context("读取分片失败")?
+                    .context("读取分片失败")?;
+                if let Some(size) = size {
+                    return Ok(resp
+                        .header("Content-Encoding", "zstd")
+                        .content_length(size)
+                        .no_chunking()
+                        .streaming(Box::pin(fs::zstd_frame_stream(meta_info.chunks))));
+                }
+            }
+            Some(ContentEncoding::Gzip) => {
+                let body = fs::gzip_stream(DecompressStream::new(meta_info.chunks));
+                return Ok(resp
+                    .header("Content-Encoding", "gzip")
+                    .streaming(Box::pin(body)));
+            }
+            None => {}
+        }
+    }
+    resp.header("Content-Length", meta_info.size);
+    match meta_info.backend {
+        Backend::Dedup => {
+            let chunks = DecompressStream::new(meta_info.chunks);
+            match cipher {
+                Some(cipher) => Ok(resp.streaming(sse::decrypt_chunks(chunks, cipher, 0, 0))),
+                None => Ok(resp.streaming(chunks)),
+            }
+        }
+        Backend::Passthrough => {
+            let object_path =
+                fs::object_path_from_meta(&metainfo_file_path).context("解析对象路径失败")?;
+            let body = fs::raw_file_stream(fs::raw_path(&object_path))
+                .await
+                .context("读取文件失败")?;
+            Ok(resp.streaming(Box::pin(body)))
+        }
+    }
 }
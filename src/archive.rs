@@ -0,0 +1,566 @@
+use crate::fs;
+use crate::fs::{Backend, DecompressStream};
+use crate::pool;
+use crate::spool::SpoolFile;
+use crate::sse;
+use flate2::read::DeflateDecoder;
+use flate2::Crc;
+use futures::future::{ok, ready};
+use futures::stream::{once, LocalBoxStream};
+use futures::{StreamExt, TryStreamExt};
+use ntex::util::Bytes;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// --- 扩展：按前缀打包下载（tar）。归档随读取各对象的分片流逐个生成，不在内存或磁盘中缓存
+
+const BLOCK_SIZE: usize = 512;
+// ustar 头中 name 和 prefix 字段的长度
+const NAME_LEN: usize = 100;
+const PREFIX_LEN: usize = 155;
+
+// 写入定长的八进制数字字段（末尾为 NUL）
+fn write_octal(field: &mut [u8], value: u64) {
+    let len = field.len() - 1;
+    let digits = format!("{:0width$o}", value, width = len);
+    field[..len].copy_from_slice(digits.as_bytes());
+    field[len] = 0;
+}
+
+// 写入大小字段：放不下八进制时使用 GNU 的 base-256 编码
+fn write_size(field: &mut [u8; 12], size: u64) {
+    if size < 1 << 33 {
+        write_octal(field, size);
+    } else {
+        field.fill(0);
+        field[0] = 0x80;
+        field[4..].copy_from_slice(&size.to_be_bytes());
+    }
+}
+
+fn header_block(name: &[u8], prefix: &[u8], size: u64, mtime: u64, typeflag: u8) -> [u8; 512] {
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..name.len()].copy_from_slice(name);
+    write_octal(&mut block[100..108], 0o644);
+    write_octal(&mut block[108..116], 0);
+    write_octal(&mut block[116..124], 0);
+    let mut size_field = [0u8; 12];
+    write_size(&mut size_field, size);
+    block[124..136].copy_from_slice(&size_field);
+    write_octal(&mut block[136..148], mtime);
+    block[156] = typeflag;
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    block[345..345 + prefix.len()].copy_from_slice(prefix);
+    // 校验和按校验和字段全为空格计算
+    block[148..156].fill(b' ');
+    let checksum: u32 = block.iter().map(|b| *b as u32).sum();
+    block[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+    block
+}
+
+// 普通文件的 tar 头：路径放不进 ustar 的 name/prefix 字段时，先写一个 GNU 长文件名条目
+pub fn tar_header(path: &str, size: u64, mtime: u64) -> Vec<u8> {
+    let bytes = path.as_bytes();
+    if bytes.len() <= NAME_LEN {
+        return header_block(bytes, b"", size, mtime, b'0').to_vec();
+    }
+    let split = path
+        .match_indices('/')
+        .map(|(i, _)| i)
+        .find(|&i| i <= PREFIX_LEN && bytes.len() - i - 1 <= NAME_LEN && i + 1 < bytes.len());
+    if let Some(i) = split {
+        return header_block(&bytes[i + 1..], &bytes[..i], size, mtime, b'0').to_vec();
+    }
+    let mut header = header_block(b"././@LongLink", b"", bytes.len() as u64 + 1, 0, b'L').to_vec();
+    header.extend_from_slice(bytes);
+    header.resize(header.len() + padding(bytes.len() as u64 + 1) + 1, 0);
+    header.extend_from_slice(&header_block(&bytes[..NAME_LEN], b"", size, mtime, b'0'));
+    header
+}
+
+// 数据补齐到整块需要的字节数
+pub fn padding(size: u64) -> usize {
+    (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE
+}
+
+// 单个对象的 tar 条目：头、数据和补齐；读出的数据比元数据记录的少时中止归档，
+// 否则后续条目会错位
+async fn tar_entry(
+    bucket_name: String,
+    bucket_path: PathBuf,
+    key: String,
+) -> io::Result<LocalBoxStream<'static, io::Result<Bytes>>> {
+    let meta_file_path = bucket_path.join(format!("{}.meta", key));
+    // 打包期间被删除的对象跳过；读取和解密元数据在后台线程池中进行
+    let metadata = pool::run(move || {
+        meta_file_path
+            .exists()
+            .then(|| fs::load_metadata(&meta_file_path))
+            .transpose()
+    })
+    .await
+    .map_err(io::Error::other)?
+    .map_err(io::Error::other)?;
+    let Some(metadata) = metadata else {
+        return Ok(futures::stream::empty().boxed_local());
+    };
+    // 已隔离的对象不打包
+    if metadata.is_quarantined() {
+        return Ok(futures::stream::empty().boxed_local());
+    }
+    // SSE-C 加密的对象没有客户密钥，不打包
+    let Ok(cipher) = sse::read_cipher(metadata.encryption.as_ref(), None) else {
+        return Ok(futures::stream::empty().boxed_local());
+    };
+    let size = metadata.size;
+    let header = tar_header(&key, size, metadata.time.timestamp().max(0) as u64);
+    let body = match metadata.backend {
+        Backend::Dedup => {
+            let chunks = DecompressStream::new(metadata.chunks);
+            match cipher {
+                Some(cipher) => sse::decrypt_chunks(chunks, cipher, 0, 0).boxed_local(),
+                None => chunks.boxed_local(),
+            }
+        }
+        Backend::Passthrough => {
+            let object_path = format!("{}/{}", bucket_name, key);
+            fs::raw_file_stream(fs::raw_path(&object_path))
+                .await?
+                .boxed_local()
+        }
+    };
+    let written = Arc::new(AtomicU64::new(0));
+    let counter = written.clone();
+    let body = body.inspect_ok(move |bytes| {
+        counter.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+    });
+    let tail = once(async move {
+        let written = written.load(Ordering::Relaxed);
+        if written != size {
+            return Err(io::Error::other(format!(
+                "对象 {} 只读取到 {}/{} 字节",
+                key, written, size
+            )));
+        }
+        Ok(Bytes::from(vec![0u8; padding(size)]))
+    });
+    Ok(once(ok(Bytes::from(header)))
+        .chain(body)
+        .chain(tail)
+        .boxed_local())
+}
+
+// 前缀下全部对象（按键排序）组成的 tar 流
+pub(crate) async fn tar_stream(
+    bucket_name: String,
+    bucket_path: PathBuf,
+    prefix: String,
+) -> io::Result<LocalBoxStream<'static, io::Result<Bytes>>> {
+    let walk_path = bucket_path.clone();
+    let keys = pool::run(move || -> io::Result<Vec<String>> {
+        let mut meta_files = Vec::new();
+        fs::walk_meta_files(&walk_path, &mut meta_files)?;
+        let mut keys: Vec<String> = meta_files
+            .iter()
+            .filter_map(|path| {
+                let key = path.strip_prefix(&walk_path).ok()?.to_str()?;
+                key.strip_suffix(".meta").map(str::to_string)
+            })
+            .filter(|key| key.starts_with(&prefix))
+            .collect();
+        keys.sort_unstable();
+        Ok(keys)
+    })
+    .await
+    .map_err(io::Error::other)??;
+    let entries = futures::stream::iter(keys)
+        .then(move |key| tar_entry(bucket_name.clone(), bucket_path.clone(), key))
+        .try_flatten();
+    // 归档以两个全零块结束
+    Ok(entries
+        .chain(once(ready(Ok(Bytes::from(vec![0u8; BLOCK_SIZE * 2])))))
+        .boxed_local())
+}
+
+// --- 扩展：上传 tar/zip 归档并在服务端展开为对象。先读出全部条目的位置，再逐个解压上传，
+// 内存中最多只有一个条目的数据
+
+// 单个条目的大小上限：条目整体读入内存后上传
+const MAX_ENTRY_SIZE: u64 = 1 << 30;
+// GNU 长文件名和 pax 扩展头的大小上限
+const MAX_EXTENDED_HEADER: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar" => Ok(ArchiveFormat::Tar),
+            "zip" => Ok(ArchiveFormat::Zip),
+            _ => Err(format!(
+                "unknown archive format `{}`, expected tar or zip",
+                s
+            )),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    // 按文件开头识别归档格式，不带 ustar 标记的旧式 tar 需显式指定
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if head.get(257..262) == Some(&b"ustar"[..]) {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+// 归档中的一个普通文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub path: String,
+    // 数据在归档中的偏移和存储的长度
+    pub offset: u64,
+    pub stored_size: u64,
+    // 解压后的长度
+    pub size: u64,
+    pub deflated: bool,
+    pub crc32: Option<u32>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// 读取 tar 头中的数字字段，兼容 GNU 的 base-256 编码
+fn parse_number(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        let value = field[1..]
+            .iter()
+            .fold(u64::from(field[0] & 0x7f), |acc, b| {
+                (acc << 8) | u64::from(*b)
+            });
+        return Ok(value);
+    }
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| invalid("tar 头中的数字字段无效"))
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+fn read_extended<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+    if size > MAX_EXTENDED_HEADER {
+        return Err(invalid("tar 扩展头过大"));
+    }
+    let mut data = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+// pax 扩展头中的记录："长度 键=值\n"
+fn parse_pax(data: &[u8]) -> HashMap<String, String> {
+    let mut records = HashMap::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|b| *b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .filter(|len| *len > space && *len <= rest.len())
+        else {
+            break;
+        };
+        let record = String::from_utf8_lossy(&rest[space + 1..len]).to_string();
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.insert(key.to_string(), value.to_string());
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+// tar 归档中的全部普通文件，支持 ustar、GNU 长文件名和 pax 扩展头；目录、链接等其他条目跳过
+pub fn tar_entries<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut long_name: Option<String> = None;
+    let mut pax = HashMap::new();
+    let mut offset = 0u64;
+    let mut block = [0u8; BLOCK_SIZE];
+    loop {
+        reader.seek(SeekFrom::Start(offset))?;
+        match reader.read_exact(&mut block) {
+            Ok(()) => {}
+            // 缺少结尾的全零块也视为结束
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && offset > 0 => break,
+            Err(err) => return Err(err),
+        }
+        if block.iter().all(|b| *b == 0) {
+            break;
+        }
+        let checksum: u64 = block
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    u64::from(*b)
+                }
+            })
+            .sum();
+        if parse_number(&block[148..156])? != checksum {
+            return Err(invalid(format!("偏移 {} 处的 tar 头校验和错误", offset)));
+        }
+        let data_offset = offset + BLOCK_SIZE as u64;
+        let typeflag = block[156];
+        let mut size = parse_number(&block[124..136])?;
+        if matches!(typeflag, b'0' | b'\0' | b'7') {
+            if let Some(pax_size) = pax.get("size").and_then(|s: &String| s.parse().ok()) {
+                size = pax_size;
+            }
+        }
+        match typeflag {
+            b'L' => {
+                let data = read_extended(reader, data_offset, size)?;
+                long_name = Some(field_str(&data));
+            }
+            b'x' => pax = parse_pax(&read_extended(reader, data_offset, size)?),
+            b'0' | b'\0' | b'7' => {
+                let path = match (long_name.take(), pax.remove("path")) {
+                    (_, Some(path)) => path,
+                    (Some(path), None) => path,
+                    (None, None) => {
+                        let name = field_str(&block[..NAME_LEN]);
+                        let prefix = field_str(&block[345..345 + PREFIX_LEN]);
+                        if prefix.is_empty() {
+                            name
+                        } else {
+                            format!("{}/{}", prefix, name)
+                        }
+                    }
+                };
+                entries.push(ArchiveEntry {
+                    path,
+                    offset: data_offset,
+                    stored_size: size,
+                    size,
+                    deflated: false,
+                    crc32: None,
+                });
+                pax.clear();
+            }
+            _ => {
+                long_name = None;
+                pax.clear();
+            }
+        }
+        offset = data_offset + size + padding(size) as u64;
+    }
+    Ok(entries)
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// zip 归档中的全部文件，按中央目录读取；只支持不加密的 stored 和 deflate 条目，不支持 zip64
+pub fn zip_entries<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<ArchiveEntry>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    // 目录结尾记录 22 字节，其后最多 65535 字节的注释
+    let tail_len = len.min(22 + 65535);
+    let mut tail = vec![0u8; tail_len as usize];
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    reader.read_exact(&mut tail)?;
+    let eocd = tail
+        .windows(4)
+        .rposition(|w| w == b"PK\x05\x06")
+        .filter(|i| i + 22 <= tail.len())
+        .ok_or_else(|| invalid("找不到 zip 中央目录"))?;
+    let eocd = &tail[eocd..];
+    let count = u16_at(eocd, 10);
+    let directory_size = u32_at(eocd, 12);
+    let directory_offset = u32_at(eocd, 16);
+    if count == u16::MAX || directory_offset == u32::MAX {
+        return Err(invalid("不支持 zip64 归档"));
+    }
+    let mut directory = vec![0u8; directory_size as usize];
+    reader.seek(SeekFrom::Start(u64::from(directory_offset)))?;
+    reader.read_exact(&mut directory)?;
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    for _ in 0..count {
+        let record = directory
+            .get(pos..pos + 46)
+            .filter(|record| record.starts_with(b"PK\x01\x02"))
+            .ok_or_else(|| invalid("zip 中央目录损坏"))?;
+        let flags = u16_at(record, 8);
+        let method = u16_at(record, 10);
+        let crc32 = u32_at(record, 16);
+        let stored_size = u32_at(record, 20);
+        let size = u32_at(record, 24);
+        let name_len = u16_at(record, 28) as usize;
+        let extra_len = u16_at(record, 30) as usize;
+        let comment_len = u16_at(record, 32) as usize;
+        let local_offset = u32_at(record, 42);
+        let name = directory
+            .get(pos + 46..pos + 46 + name_len)
+            .ok_or_else(|| invalid("zip 中央目录损坏"))?;
+        let path = String::from_utf8_lossy(name).to_string();
+        pos += 46 + name_len + extra_len + comment_len;
+        if path.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(invalid(format!("不支持加密的 zip 条目 {}", path)));
+        }
+        if stored_size == u32::MAX || size == u32::MAX || local_offset == u32::MAX {
+            return Err(invalid("不支持 zip64 归档"));
+        }
+        let deflated = match method {
+            0 => false,
+            8 => true,
+            _ => {
+                return Err(invalid(format!(
+                    "zip 条目 {} 使用了不支持的压缩方式 {}",
+                    path, method
+                )))
+            }
+        };
+        // 数据位于本地文件头之后，本地头的扩展字段长度可能与中央目录不同
+        let mut local = [0u8; 30];
+        reader.seek(SeekFrom::Start(u64::from(local_offset)))?;
+        reader.read_exact(&mut local)?;
+        if !local.starts_with(b"PK\x03\x04") {
+            return Err(invalid(format!("zip 条目 {} 的本地文件头损坏", path)));
+        }
+        let offset = u64::from(local_offset)
+            + 30
+            + u64::from(u16_at(&local, 26))
+            + u64::from(u16_at(&local, 28));
+        entries.push(ArchiveEntry {
+            path,
+            offset,
+            stored_size: u64::from(stored_size),
+            size: u64::from(size),
+            deflated,
+            crc32: Some(crc32),
+        });
+    }
+    Ok(entries)
+}
+
+pub fn entries<R: Read + Seek>(
+    reader: &mut R,
+    format: ArchiveFormat,
+) -> io::Result<Vec<ArchiveEntry>> {
+    match format {
+        ArchiveFormat::Tar => tar_entries(reader),
+        ArchiveFormat::Zip => zip_entries(reader),
+    }
+}
+
+// 读出并解压单个条目的数据，长度或 CRC 与记录不符时报错
+pub fn read_entry<R: Read + Seek>(reader: &mut R, entry: &ArchiveEntry) -> io::Result<Vec<u8>> {
+    if entry.size > MAX_ENTRY_SIZE {
+        return Err(invalid(format!(
+            "条目 {} 超过 {} 字节",
+            entry.path, MAX_ENTRY_SIZE
+        )));
+    }
+    reader.seek(SeekFrom::Start(entry.offset))?;
+    let stored = (&mut *reader).take(entry.stored_size);
+    let mut data = Vec::with_capacity(entry.size as usize);
+    if entry.deflated {
+        DeflateDecoder::new(stored)
+            .take(entry.size + 1)
+            .read_to_end(&mut data)?;
+    } else {
+        stored.take(entry.size + 1).read_to_end(&mut data)?;
+    }
+    if data.len() as u64 != entry.size {
+        return Err(invalid(format!("条目 {} 的数据不完整", entry.path)));
+    }
+    if let Some(crc32) = entry.crc32 {
+        let mut crc = Crc::new();
+        crc.update(&data);
+        if crc.sum() != crc32 {
+            return Err(invalid(format!("条目 {} 的 CRC 校验失败", entry.path)));
+        }
+    }
+    Ok(data)
+}
+
+// 条目路径对应的对象键：去掉开头的 "./" 和 '/'，含 ".." 的路径无效
+pub fn entry_key(prefix: &str, path: &str) -> Option<String> {
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    if path.is_empty() || path.split('/').any(|part| part == "..") {
+        return None;
+    }
+    let path: Vec<&str> = path
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
+    Some(format!("{}{}", prefix, path.join("/")))
+}
+
+// 待展开的归档：内存中的请求体或落盘的临时文件
+#[derive(Clone)]
+pub(crate) enum ArchiveSource {
+    Memory(Arc<Vec<u8>>),
+    Spooled(Arc<SpoolFile>),
+}
+
+impl ArchiveSource {
+    pub(crate) fn head(&self) -> &[u8] {
+        match self {
+            ArchiveSource::Memory(bytes) => bytes,
+            ArchiveSource::Spooled(spooled) => &spooled.head,
+        }
+    }
+
+    pub(crate) fn entries(&self, format: ArchiveFormat) -> io::Result<Vec<ArchiveEntry>> {
+        match self {
+            ArchiveSource::Memory(bytes) => entries(&mut Cursor::new(bytes.as_slice()), format),
+            ArchiveSource::Spooled(spooled) => {
+                entries(&mut BufReader::new(File::open(spooled.path())?), format)
+            }
+        }
+    }
+
+    pub(crate) fn read(&self, entry: &ArchiveEntry) -> io::Result<Vec<u8>> {
+        match self {
+            ArchiveSource::Memory(bytes) => read_entry(&mut Cursor::new(bytes.as_slice()), entry),
+            ArchiveSource::Spooled(spooled) => {
+                read_entry(&mut BufReader::new(File::open(spooled.path())?), entry)
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::client::S3Client;
+use anyhow::{bail, Context};
+use futures::future::join_all;
+use rand::Rng;
+use reqwest::Method;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// --- 基准测试子命令：按给定的对象大小、并发数和读写比例压测 S3 服务
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchOpt {
+    /// S3 endpoint including the path prefix
+    #[clap(long, default_value_t = String::from("http://127.0.0.1:9000/api"))]
+    pub endpoint: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub access_key: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub secret_key: String,
+
+    /// Bucket holding the benchmark objects, created if missing
+    #[clap(long, default_value_t = String::from("bench"))]
+    pub bucket: String,
+
+    /// Object size such as `4KiB` or `1MiB`; repeat to mix sizes
+    #[clap(long = "size", value_parser = parse_size, default_value = "64KiB")]
+    pub sizes: Vec<u64>,
+
+    /// Number of requests in flight
+    #[clap(long, default_value_t = 16)]
+    pub concurrency: usize,
+
+    /// Run time in seconds, not counting the initial upload of the key set
+    #[clap(long, default_value_t = 10)]
+    pub duration: u64,
+
+    /// Fraction of operations that are GETs, the rest are PUTs
+    #[clap(long, default_value_t = 0.5)]
+    pub read_ratio: f64,
+
+    /// Number of distinct keys, uploaded once before the timed run
+    #[clap(long, default_value_t = 100)]
+    pub objects: usize,
+
+    /// Seed for object sizes, payloads and the operation mix
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Delete the benchmark objects afterwards
+    #[clap(long)]
+    pub cleanup: bool,
+
+    /// Print the report as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+// 解析带单位的大小，支持 B/KB/KiB/MB/MiB/GB/GiB，不带单位时按字节计算
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size `{}`", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1000,
+        "kib" => 1 << 10,
+        "mb" | "m" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "gb" | "g" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        _ => return Err(format!("invalid size unit in `{}`", s)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+// 已排序延迟的百分位数（最近秩法），p 取值 0-100
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Default)]
+struct OpSamples {
+    latencies: Vec<Duration>,
+    bytes: u64,
+    errors: u64,
+}
+
+impl OpSamples {
+    fn merge(&mut self, other: OpSamples) {
+        self.latencies.extend(other.latencies);
+        self.bytes += other.bytes;
+        self.errors += other.errors;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpReport {
+    pub op: String,
+    pub count: usize,
+    pub errors: u64,
+    pub ops_per_sec: f64,
+    pub mib_per_sec: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub endpoint: String,
+    pub concurrency: usize,
+    pub sizes: Vec<u64>,
+    pub read_ratio: f64,
+    pub elapsed_secs: f64,
+    pub ops: Vec<OpReport>,
+}
+
+fn op_report(op: &str, mut samples: OpSamples, elapsed: Duration) -> OpReport {
+    samples.latencies.sort();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    OpReport {
+        op: op.to_string(),
+        count: samples.latencies.len(),
+        errors: samples.errors,
+        ops_per_sec: samples.latencies.len() as f64 / secs,
+        mib_per_sec: samples.bytes as f64 / secs / (1 << 20) as f64,
+        p50_ms: ms(percentile(&samples.latencies, 50.0)),
+        p90_ms: ms(percentile(&samples.latencies, 90.0)),
+        p99_ms: ms(percentile(&samples.latencies, 99.0)),
+        max_ms: ms(samples.latencies.last().copied().unwrap_or_default()),
+    }
+}
+
+// 简单的 xorshift 随机数，保证同一个 seed 下每个并发任务的操作序列可复现
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+fn object_key(i: usize) -> String {
+    format!("bench-{:06}", i)
+}
+
+// 每次写入都生成新的随机内容，避免被去重存储直接命中
+fn payload(rng: &mut XorShift, sizes: &[u64]) -> Vec<u8> {
+    let mut body = vec![0u8; sizes[rng.below(sizes.len())] as usize];
+    rng.fill(&mut body);
+    body
+}
+
+async fn put_object(client: &S3Client, bucket: &str, key: &str, body: Vec<u8>) -> bool {
+    match client
+        .send(Method::PUT, &format!("{}/{}", bucket, key), &[], &[], body)
+        .await
+    {
+        Ok(resp) => resp.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+async fn get_object(client: &S3Client, bucket: &str, key: &str) -> Option<u64> {
+    let resp = client
+        .send(
+            Method::GET,
+            &format!("{}/{}", bucket, key),
+            &[],
+            &[],
+            Vec::new(),
+        )
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    resp.bytes().await.ok().map(|b| b.len() as u64)
+}
+
+// 单个并发任务：在截止时间前按读写比例循环发送请求
+async fn worker(
+    client: &S3Client,
+    opt: &BenchOpt,
+    mut rng: XorShift,
+    deadline: Instant,
+) -> (OpSamples, OpSamples) {
+    let mut reads = OpSamples::default();
+    let mut writes = OpSamples::default();
+    while Instant::now() < deadline {
+        let key = object_key(rng.below(opt.objects));
+        if rng.next_f64() < opt.read_ratio {
+            let start = Instant::now();
+            match get_object(client, &opt.bucket, &key).await {
+                Some(n) => {
+                    reads.latencies.push(start.elapsed());
+                    reads.bytes += n;
+                }
+                None => reads.errors += 1,
+            }
+        } else {
+            let body = payload(&mut rng, &opt.sizes);
+            let n = body.len() as u64;
+            let start = Instant::now();
+            if put_object(client, &opt.bucket, &key, body).await {
+                writes.latencies.push(start.elapsed());
+                writes.bytes += n;
+            } else {
+                writes.errors += 1;
+            }
+        }
+    }
+    (reads, writes)
+}
+
+pub async fn run(opt: BenchOpt) -> anyhow::Result<()> {
+    if opt.concurrency == 0 || opt.objects == 0 {
+        bail!("--concurrency 和 --objects 必须大于 0");
+    }
+    if !(0.0..=1.0).contains(&opt.read_ratio) {
+        bail!("--read-ratio 必须在 0 到 1 之间");
+    }
+    let client = S3Client::new(&opt.endpoint, &opt.access_key, &opt.secret_key);
+    // 桶已存在时服务端会返回错误，这里忽略
+    let _ = client
+        .send(Method::PUT, &opt.bucket, &[], &[], Vec::new())
+        .await
+        .context("无法连接服务")?;
+
+    let seed = opt.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    eprintln!(
+        "uploading {} objects to {}/{} (seed {})",
+        opt.objects, opt.endpoint, opt.bucket, seed
+    );
+    let mut rng = XorShift(seed | 1);
+    for i in 0..opt.objects {
+        let body = payload(&mut rng, &opt.sizes);
+        if !put_object(&client, &opt.bucket, &object_key(i), body).await {
+            bail!("上传 {} 失败", object_key(i));
+        }
+    }
+
+    eprintln!(
+        "running for {}s with concurrency {}",
+        opt.duration, opt.concurrency
+    );
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(opt.duration);
+    let results = join_all((0..opt.concurrency).map(|i| {
+        let rng = XorShift((seed ^ (i as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1);
+        worker(&client, &opt, rng, deadline)
+    }))
+    .await;
+    let elapsed = start.elapsed();
+    let mut reads = OpSamples::default();
+    let mut writes = OpSamples::default();
+    for (r, w) in results {
+        reads.merge(r);
+        writes.merge(w);
+    }
+
+    if opt.cleanup {
+        for i in 0..opt.objects {
+            let _ = client
+                .send(
+                    Method::DELETE,
+                    &format!("{}/{}", opt.bucket, object_key(i)),
+                    &[],
+                    &[],
+                    Vec::new(),
+                )
+                .await;
+        }
+    }
+
+    let report = BenchReport {
+        endpoint: opt.endpoint.clone(),
+        concurrency: opt.concurrency,
+        sizes: opt.sizes.clone(),
+        read_ratio: opt.read_ratio,
+        elapsed_secs: elapsed.as_secs_f64(),
+        ops: vec![
+            op_report("GET", reads, elapsed),
+            op_report("PUT", writes, elapsed),
+        ],
+    };
+    if opt.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "{:<4} {:>8} {:>7} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "op", "count", "errors", "ops/s", "MiB/s", "p50(ms)", "p90(ms)", "p99(ms)", "max(ms)"
+        );
+        for op in &report.ops {
+            println!(
+                "{:<4} {:>8} {:>7} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+                op.op,
+                op.count,
+                op.errors,
+                op.ops_per_sec,
+                op.mib_per_sec,
+                op.p50_ms,
+                op.p90_ms,
+                op.p99_ms,
+                op.max_ms
+            );
+        }
+    }
+    Ok(())
+}
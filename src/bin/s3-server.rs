@@ -1,57 +1,713 @@
 #![feature(fn_traits, unboxed_closures)]
+#![recursion_limit = "256"]
+#[cfg(not(feature = "profiling"))]
 #[global_allocator]
 static ALLOC: MiMalloc = MiMalloc;
+#[cfg(feature = "profiling")]
+#[global_allocator]
+static ALLOC: rs_s3_local::profiling::CountingAlloc<MiMalloc> =
+    rs_s3_local::profiling::CountingAlloc(MiMalloc);
 
-use clap::Parser;
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
 use mimalloc::MiMalloc;
+use rs_s3_local::bench::BenchOpt;
+use rs_s3_local::codec::Codec;
+use rs_s3_local::compat::CompatOpt;
+use rs_s3_local::config;
+use rs_s3_local::config::{
+    ApiGroup, BucketRestriction, CertIdentity, Chunking, CompressionConfig, DedupVerify,
+    DiskWatermarks, JwtConfig, MasterKeySource, PluginConfig, RequestLimits, ScanAction, Scanner,
+    ScriptConfig, ServerConfig, StatsdConfig, StorageRoute, TlsConfig, UnreadExpiration,
+};
+use rs_s3_local::fs::Backend;
+use rs_s3_local::gc::GcOpt;
+use rs_s3_local::logging::{LogConfig, LogFormat, LogRotation};
+use rs_s3_local::scrub::ScrubOpt;
 use rs_s3_local::start_example_raft_node;
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Clone, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Opt {
+    /// TOML file with default values for these options, keyed by option name (e.g.
+    /// `http-addr = "0.0.0.0:9000"`); command-line options and environment variables take
+    /// precedence
+    #[clap(long, env = "S3_CONFIG")]
+    pub config: Option<PathBuf>,
+
     #[clap(long, default_value_t = 1)]
     pub id: u64,
 
-    #[clap(long, default_value_t = String::from("127.0.0.1:9000"))]
+    #[clap(long, env = "S3_HTTP_ADDR", default_value_t = String::from("127.0.0.1:9000"))]
     pub http_addr: String,
 
-    #[clap(long, default_value_t = String::from("127.0.0.1:32001"))]
+    #[clap(long, env = "S3_RPC_ADDR", default_value_t = String::from("127.0.0.1:32001"))]
     pub rpc_addr: String,
 
-    #[clap(long, default_value_t = String::from("."))]
+    /// Override the port of `--http-addr`; `0` picks a free port
+    #[clap(long, env = "S3_PORT")]
+    pub port: Option<u16>,
+
+    /// Write the bound HTTP port to this file once the node is ready
+    #[clap(long)]
+    pub port_file: Option<PathBuf>,
+
+    /// HTTPS listen address, served alongside `--http-addr`; needs `--tls-cert` and `--tls-key`
+    #[clap(long, env = "S3_TLS_ADDR", requires_all = ["tls_cert", "tls_key"])]
+    pub tls_addr: Option<String>,
+
+    /// PEM certificate chain for `--tls-addr`
+    #[clap(long)]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for `--tls-addr`
+    #[clap(long)]
+    pub tls_key: Option<PathBuf>,
+
+    /// PEM CA certificates verifying client certificates; clients without one are still accepted
+    /// unless `--tls-require-client-cert` is set
+    #[clap(long, requires = "tls_addr")]
+    pub tls_client_ca: Option<PathBuf>,
+
+    /// Reject TLS connections without a valid client certificate
+    #[clap(long, requires = "tls_client_ca")]
+    pub tls_require_client_cert: bool,
+
+    /// Map a client certificate subject to an access key, e.g. `CN=billing,O=Acme=billing-key`;
+    /// unsigned requests with a matching certificate act as that key. Repeatable, first match wins
+    #[clap(long = "tls-cert-identity", requires = "tls_client_ca")]
+    pub tls_cert_identities: Vec<CertIdentity>,
+
+    /// Experimental HTTP/3 (QUIC) listen address (UDP) using the `--tls-addr` certificate; needs
+    /// the `http3` feature
+    #[clap(long, env = "S3_HTTP3_ADDR", requires = "tls_addr")]
+    pub http3_addr: Option<String>,
+
+    /// Data directory: metadata, raft logs and (without `--chunk-root`) chunks are kept under it
+    #[clap(long, alias = "data-dir", env = "S3_FS_ROOT", default_value_t = String::from("."))]
     pub fs_root: String,
 
     #[clap(long)]
     pub leader_http_addr: Option<String>,
 
-    #[clap(long, default_value_t = String::from("minioadmin"))]
+    #[clap(long, env = "S3_ACCESS_KEY", default_value_t = String::from("minioadmin"))]
     pub access_key: String,
 
-    #[clap(long, default_value_t = String::from("minioadmin"))]
+    #[clap(
+        long,
+        env = "S3_SECRET_KEY",
+        hide_env_values = true,
+        default_value_t = String::from("minioadmin")
+    )]
     pub secret_key: String,
+
+    /// Master keys encrypting metadata, as `ID:HEX[,ID:HEX...]` with 64 hex digits each; the last
+    /// one encrypts new data, the others only decrypt. Add a key at the end to rotate
+    #[clap(
+        long,
+        env = "S3_MASTER_KEY",
+        hide_env_values = true,
+        conflicts_with_all = ["master_key_file", "master_key_command"]
+    )]
+    pub master_key: Option<String>,
+
+    /// File with the master keys, one `ID:HEX` per line
+    #[clap(long, conflicts_with = "master_key_command")]
+    pub master_key_file: Option<PathBuf>,
+
+    /// Shell command printing the master keys in the `--master-key-file` format, e.g. to fetch
+    /// them from a KMS
+    #[clap(long)]
+    pub master_key_command: Option<String>,
+
+    /// Refuse to start without a master key instead of using the built-in one
+    #[clap(long)]
+    pub secure: bool,
+
+    /// Store object metadata unencrypted so it can be inspected on disk; metadata written
+    /// before stays readable either way
+    #[clap(long, conflicts_with = "secure")]
+    pub plain_metadata: bool,
+
+    /// Route a bucket or key prefix to a storage backend, e.g. `logs/=passthrough`
+    #[clap(long = "storage-route")]
+    pub storage_routes: Vec<StorageRoute>,
+
+    /// Allow anonymous GET/HEAD on a bucket or `bucket/prefix`
+    #[clap(long = "public-prefix")]
+    pub public_prefixes: Vec<String>,
+
+    /// Encrypt uploads to this bucket with SSE-S3 unless the request asks otherwise, so deleting
+    /// the bucket crypto-shreds its contents. Repeatable
+    #[clap(long = "encrypted-bucket")]
+    pub encrypted_buckets: Vec<String>,
+
+    /// Expose debug endpoints such as `/admin/debug/pprof/*` (needs the `profiling` feature)
+    #[clap(long)]
+    pub debug_endpoints: bool,
+
+    /// Add `Server-Timing`, `x-amz-storage-backend` and dedup hit/miss headers to responses
+    #[clap(long)]
+    pub debug_headers: bool,
+
+    /// Wait for written data and metadata to be fsynced before answering writes
+    #[clap(long)]
+    pub fsync: bool,
+
+    /// Group-commit window for `--fsync`: writes arriving within it share one sync
+    #[clap(long, default_value_t = 2)]
+    pub fsync_window_ms: u64,
+
+    /// Chunks to read ahead while streaming a multi-chunk object; 0 disables
+    #[clap(long, default_value_t = 2)]
+    pub read_ahead: usize,
+
+    /// Directory for chunk data; repeat to spread chunks over several disks
+    #[clap(long = "chunk-root")]
+    pub chunk_roots: Vec<PathBuf>,
+
+    /// Number of chunk roots each chunk is written to (2 keeps a mirror)
+    #[clap(long, default_value_t = 1)]
+    pub chunk_replicas: usize,
+
+    /// Erasure-coding data shards per chunk, used with `--erasure-parity`
+    #[clap(long, default_value_t = 4)]
+    pub erasure_data: usize,
+
+    /// Erasure-coding parity shards per chunk; 0 disables erasure coding
+    #[clap(long, default_value_t = 0)]
+    pub erasure_parity: usize,
+
+    /// Experimental: place chunks on cluster members by consistent hashing
+    #[clap(long)]
+    pub cluster_ring: bool,
+
+    /// Members holding each chunk in `--cluster-ring` mode
+    #[clap(long, default_value_t = 2)]
+    pub ring_replicas: usize,
+
+    /// Run as a hot standby of this primary (e.g. `http://10.0.0.1:9000`); needs the same `--fs-root`
+    #[clap(long)]
+    pub standby_of: Option<String>,
+
+    /// Promote the standby after the primary is unreachable this many seconds; 0 means manual only
+    #[clap(long, default_value_t = 0)]
+    pub promote_after: u64,
+
+    /// Listen address for the change-data-capture stream (newline-delimited JSON over TCP)
+    #[clap(long)]
+    pub cdc_addr: Option<String>,
+
+    /// Domain for virtual-hosted-style requests: `<bucket>.<domain>` is served as that bucket,
+    /// e.g. `s3.localhost`
+    #[clap(long)]
+    pub vhost_domain: Option<String>,
+
+    /// UDP address of a DNS responder resolving `--vhost-domain` and its subdomains to this server,
+    /// e.g. `127.0.0.1:5353`
+    #[clap(long, requires = "vhost_domain")]
+    pub dns_addr: Option<String>,
+
+    /// Address returned by the DNS responder; defaults to the `--http-addr` address
+    #[clap(long, requires = "dns_addr")]
+    pub dns_answer: Option<std::net::IpAddr>,
+
+    /// OIDC issuer whose JWKS validates Bearer tokens on the admin API
+    #[clap(long)]
+    pub oidc_issuer: Option<String>,
+
+    /// Shared secret for HS256/384/512 admin tokens
+    #[clap(long, env = "S3_ADMIN_JWT_SECRET", hide_env_values = true)]
+    pub jwt_secret: Option<String>,
+
+    /// PEM public key (RSA, EC or Ed25519) for admin tokens; repeatable
+    #[clap(long = "jwt-public-key")]
+    pub jwt_public_keys: Vec<PathBuf>,
+
+    /// Required `aud` of admin tokens; unchecked when empty
+    #[clap(long)]
+    pub jwt_audience: Option<String>,
+
+    /// Claim holding the roles, `.` separates nested fields (e.g. `realm_access.roles`)
+    #[clap(long, default_value = "roles")]
+    pub jwt_role_claim: String,
+
+    /// Role granting full admin access
+    #[clap(long, default_value = "admin")]
+    pub jwt_admin_role: String,
+
+    /// Role granting GET/HEAD-only admin access
+    #[clap(long, default_value = "read-only")]
+    pub jwt_readonly_role: String,
+
+    /// Resolve unknown access keys by POSTing `{"access_key": ...}` to this URL
+    #[clap(long)]
+    pub auth_webhook: Option<String>,
+
+    /// Seconds to cache access keys resolved by `--auth-webhook`
+    #[clap(long, default_value_t = 300)]
+    pub auth_cache_secs: u64,
+
+    /// Deny operations on a bucket regardless of credentials, e.g. `golden=delete,overwrite,list`
+    #[clap(long = "bucket-deny")]
+    pub bucket_restrictions: Vec<BucketRestriction>,
+
+    /// Turn off an API group for the whole instance: delete, create-bucket, delete-bucket,
+    /// bucket-config, multipart, copy or admin. Repeatable; requests get 405 MethodNotAllowed
+    #[clap(long = "disable-api")]
+    pub disabled_apis: Vec<ApiGroup>,
+
+    /// statsd UDP address to push request counters and timings to, e.g. `127.0.0.1:8125`
+    #[clap(long)]
+    pub statsd_addr: Option<String>,
+
+    /// Prefix of statsd metric names
+    #[clap(long, default_value = "s3")]
+    pub statsd_prefix: String,
+
+    /// Send dogstatsd tags instead of appending tag values to metric names
+    #[clap(long)]
+    pub statsd_tags: bool,
+
+    /// Tag request metrics with the S3 API name, e.g. `GetObject` or `PutObject`
+    #[clap(long)]
+    pub statsd_api_labels: bool,
+
+    /// Tag request metrics with the bucket name for up to this many buckets, later buckets are
+    /// reported as `_other`; 0 disables the bucket tag
+    #[clap(long, default_value = "0")]
+    pub statsd_max_buckets: usize,
+
+    /// Log line format: plain, logfmt or json
+    #[clap(long, default_value = "plain")]
+    pub log_format: LogFormat,
+
+    /// Write access.log, audit.log, slow.log and server.log to this directory instead of stderr
+    #[clap(long)]
+    pub log_dir: Option<PathBuf>,
+
+    /// Rotate a log file once it exceeds this many MiB (0 disables size-based rotation)
+    #[clap(long, default_value_t = 100)]
+    pub log_max_mb: u64,
+
+    /// Time-based log rotation: never, hourly or daily
+    #[clap(long, default_value = "never")]
+    pub log_rotate: LogRotation,
+
+    /// Rotated files kept per log (`access.log.1` is the newest)
+    #[clap(long, default_value_t = 5)]
+    pub log_keep: usize,
+
+    /// Number of HTTP worker threads (0 = one per CPU)
+    #[clap(long, default_value_t = 0)]
+    pub workers: usize,
+
+    /// Threads in the compression/decompression and chunk-read pool (0 = one per CPU)
+    #[clap(long, default_value_t = 0)]
+    pub blocking_threads: usize,
+
+    /// Pin HTTP workers and pool threads to CPU cores, round-robin
+    #[clap(long)]
+    pub pin_cpus: bool,
+
+    /// Spool upload bodies larger than this many MiB to a temp file (0 keeps them in memory)
+    #[clap(long, default_value_t = 256)]
+    pub spool_threshold_mb: u64,
+
+    /// Directory for spooled upload bodies (default: `<data dir>/tmp/spool`)
+    #[clap(long)]
+    pub spool_dir: Option<PathBuf>,
+
+    /// Add `x-rs3-object-count` and `x-rs3-bytes-used` to HeadBucket responses
+    #[clap(long)]
+    pub bucket_usage_headers: bool,
+
+    /// Load a WASM plugin on hooks (pre-put, post-get, event), optionally limited to a
+    /// bucket or prefix, e.g. `post-get=/plugins/redact.wasm@reports/`; repeatable
+    #[clap(long = "plugin")]
+    pub plugins: Vec<PluginConfig>,
+
+    /// Run a rhai script's `on_event(event)` on object events (put, delete), optionally
+    /// limited to a bucket or prefix, e.g. `put=/scripts/manifest.rhai@media/uploads/`;
+    /// repeatable
+    #[clap(long = "script")]
+    pub scripts: Vec<ScriptConfig>,
+
+    /// Scan uploads with a shell command that reads the content on stdin and exits 0 for
+    /// clean or 1 for infected, e.g. `clamdscan --no-summary -`
+    #[clap(long, conflicts_with = "scan_icap")]
+    pub scan_command: Option<String>,
+
+    /// Scan uploads with an ICAP service, e.g. `icap://127.0.0.1:1344/avscan`
+    #[clap(long)]
+    pub scan_icap: Option<String>,
+
+    /// What to do with infected uploads: reject, or quarantine (store but deny downloads)
+    #[clap(long, default_value = "reject")]
+    pub scan_action: ScanAction,
+
+    /// Accept uploads when the scanner fails instead of rejecting them
+    #[clap(long)]
+    pub scan_fail_open: bool,
+
+    /// Record per-object read counts and last-read times
+    #[clap(long)]
+    pub track_access: bool,
+
+    /// Delete objects in a bucket or prefix not read for this many days, e.g. `logs/=30`;
+    /// repeatable, requires `--track-access`
+    #[clap(long = "expire-unread", requires = "track_access")]
+    pub unread_expirations: Vec<UnreadExpiration>,
+
+    /// Log requests taking longer than this many milliseconds, with hash, compression, disk and
+    /// raft timings
+    #[clap(long)]
+    pub slow_request_ms: Option<u64>,
+
+    /// Disk usage percentages reported as warning and critical by `/admin/capacity`
+    #[clap(long, default_value = "85,95")]
+    pub disk_watermarks: DiskWatermarks,
+
+    /// Codec for stored chunks: zstd (at `--zstd-level`), zstd:LEVEL, lz4 or none; uploads can
+    /// pick another one with the `x-rs3-compression` header
+    #[clap(long, default_value = "zstd")]
+    pub compression_codec: Codec,
+
+    /// zstd level for stored chunks (0 uses the zstd default, negative levels are faster)
+    #[clap(
+        long,
+        env = "S3_ZSTD_LEVEL",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub zstd_level: i32,
+
+    /// Lower the zstd level while CPU or the compression queue is saturated, restoring it
+    /// when idle
+    #[clap(long)]
+    pub adaptive_compression: bool,
+
+    /// Lowest zstd level used by `--adaptive-compression`
+    #[clap(long, default_value_t = 1, allow_negative_numbers = true)]
+    pub zstd_min_level: i32,
+
+    /// Re-compress chunks of objects not read for this many days at `--recompress-level`
+    #[clap(long)]
+    pub recompress_cold_after: Option<u64>,
+
+    /// zstd level used when re-compressing cold chunks
+    #[clap(long, default_value_t = 19, value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub recompress_level: i32,
+
+    /// Remove chunks no longer referenced by any object every this many hours
+    #[clap(long)]
+    pub gc_interval_hours: Option<u64>,
+
+    /// Re-hash every stored chunk, quarantining and repairing corrupted ones, every this many
+    /// hours
+    #[clap(long)]
+    pub scrub_interval_hours: Option<u64>,
+
+    /// Apply bucket lifecycle rules (expiration, aborting stale multipart uploads) every this
+    /// many seconds; 0 disables
+    #[clap(long, default_value_t = 3600)]
+    pub lifecycle_interval_secs: u64,
+
+    /// Length in seconds of a "day" in lifecycle rules, e.g. 60 to try out retention rules
+    /// locally
+    #[clap(long, default_value_t = 86400, value_parser = clap::value_parser!(u64).range(1..))]
+    pub lifecycle_day_secs: u64,
+
+    /// Verify an already stored chunk before reusing it for a new object: never, always, or
+    /// sampled[:N] (every Nth reuse, default 100)
+    #[clap(long, default_value = "never")]
+    pub dedup_verify: DedupVerify,
+
+    /// Read every chunk back from disk and verify its hash right after writing it, before
+    /// acknowledging the upload
+    #[clap(long)]
+    pub verify_writes: bool,
+
+    /// Size in MiB objects are split into for deduplication; changing it stops new uploads
+    /// from deduplicating against objects stored before
+    #[clap(long, env = "S3_CHUNK_SIZE_MB", default_value_t = 8, value_parser = clap::value_parser!(u64).range(1..=1024))]
+    pub chunk_size_mb: u64,
+
+    /// How chunk boundaries are chosen: fixed (every --chunk-size-mb) or cdc (content-defined,
+    /// FastCDC, averaging --chunk-size-mb, from a quarter to four times that size)
+    #[clap(long, default_value = "fixed")]
+    pub chunking: Chunking,
+
+    /// Seconds during which a retried PUT, POST or DELETE with the same `Idempotency-Key`
+    /// header gets the first response instead of running again; 0 disables
+    #[clap(long, default_value_t = 600)]
+    pub idempotency_window_secs: u64,
+
+    /// Reject requests with more headers than this with `RequestHeaderSectionTooLarge` (the
+    /// HTTP/1 parser itself rejects more than 96)
+    #[clap(long, default_value_t = RequestLimits::default().max_header_count)]
+    pub max_header_count: usize,
+
+    /// Reject requests whose headers exceed this many bytes with `RequestHeaderSectionTooLarge`
+    #[clap(long, default_value_t = RequestLimits::default().max_header_size)]
+    pub max_header_size: usize,
+
+    /// Reject requests whose path and query exceed this many bytes with `InvalidURI`
+    #[clap(long, default_value_t = RequestLimits::default().max_url_length)]
+    pub max_url_length: usize,
+
+    /// Reject object keys longer than this many bytes with `KeyTooLongError`
+    #[clap(long, default_value_t = RequestLimits::default().max_key_length)]
+    pub max_key_length: usize,
+
+    /// Reject uploads whose `x-amz-meta-*` headers exceed this many bytes with `MetadataTooLarge`
+    #[clap(long, default_value_t = RequestLimits::default().max_metadata_size)]
+    pub max_metadata_size: usize,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Benchmark an S3 endpoint with a configurable read/write mix
+    Bench(BenchOpt),
+    /// Run S3 protocol checks against an endpoint and print a compatibility matrix
+    VerifyCompat(CompatOpt),
+    /// Ask a running server to remove chunks no longer referenced by any object
+    Gc(GcOpt),
+    /// Ask a running server to verify every stored chunk and quarantine corrupted ones
+    Scrub(ScrubOpt),
+}
+
+// 解析命令行参数；指定了 --config 时，文件中的值作为命令行和环境变量都没有设置的参数的值
+fn parse_options() -> anyhow::Result<Opt> {
+    let matches = Opt::command().get_matches();
+    let Some(path) = matches.get_one::<PathBuf>("config") else {
+        return Ok(Opt::from_arg_matches(&matches)?);
+    };
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("读取配置文件 {:?} 失败", path))?;
+    let settings = config::parse_config_file(&text)
+        .with_context(|| format!("解析配置文件 {:?} 失败", path))?;
+    let command = Opt::command();
+    let mut args: Vec<OsString> = std::env::args_os().take(1).collect();
+    for (key, values) in settings {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key.as_str()) && key != "config")
+            .with_context(|| format!("配置文件中的 {} 不是可用的参数", key))?;
+        let source = matches.value_source(arg.get_id().as_str());
+        if matches!(
+            source,
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            match values.as_slice() {
+                [value] if value == "true" => args.push(format!("--{}", key).into()),
+                [value] if value == "false" => {}
+                _ => anyhow::bail!("配置文件中的 {} 必须是布尔值", key),
+            }
+            continue;
+        }
+        args.extend(
+            values
+                .iter()
+                .map(|value| format!("--{}={}", key, value).into()),
+        );
+    }
+    args.extend(std::env::args_os().skip(1));
+    Ok(Opt::from_arg_matches(
+        &Opt::command().get_matches_from(args),
+    )?)
 }
 
 #[ntex::main]
 async fn main() -> anyhow::Result<()> {
-    // 初始化环境日志记录器
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
     // 创建一个新的 HTTP 服务器实例。
     // Parse the parameters passed by arguments.
-    let options = Opt::parse();
+    let options = parse_options()?;
+    // 初始化日志记录器
+    rs_s3_local::logging::init(LogConfig {
+        format: options.log_format,
+        dir: options.log_dir.clone(),
+        max_bytes: options.log_max_mb << 20,
+        rotation: options.log_rotate,
+        keep: options.log_keep,
+    })?;
+    // 启动 tokio-console 采集端（默认监听 127.0.0.1:6669）
+    #[cfg(feature = "console")]
+    console_subscriber::init();
+    if options.chunk_replicas == 0 || options.chunk_replicas > options.chunk_roots.len().max(1) {
+        anyhow::bail!("--chunk-replicas 必须在 1 到 --chunk-root 的个数之间");
+    }
+    if options.erasure_parity > 0 {
+        if options.chunk_replicas > 1 {
+            anyhow::bail!("纠删码和 --chunk-replicas 不能同时使用");
+        }
+        if options.erasure_data == 0
+            || options.erasure_data + options.erasure_parity > options.chunk_roots.len()
+        {
+            anyhow::bail!(
+                "--erasure-data 必须大于 0，且与 --erasure-parity 之和不能超过 --chunk-root 的个数"
+            );
+        }
+    }
+    // 直通存储不支持加密
+    for bucket in &options.encrypted_buckets {
+        let bucket_prefix = format!("{}/", bucket);
+        let passthrough = options.storage_routes.iter().any(|route| {
+            route.backend == Backend::Passthrough
+                && (route.prefix.starts_with(&bucket_prefix)
+                    || bucket_prefix.starts_with(&route.prefix))
+        });
+        if passthrough {
+            anyhow::bail!("--encrypted-bucket {} 的对象不能路由到直通存储", bucket);
+        }
+    }
+    match options.command {
+        Some(Command::Bench(opt)) => return rs_s3_local::bench::run(opt).await,
+        Some(Command::VerifyCompat(opt)) => return rs_s3_local::compat::run(opt).await,
+        Some(Command::Gc(opt)) => return rs_s3_local::gc::run(opt).await,
+        Some(Command::Scrub(opt)) => return rs_s3_local::scrub::run(opt).await,
+        None => {}
+    }
 
+    let http_addr = match options.port {
+        Some(port) => {
+            let host = options
+                .http_addr
+                .rsplit_once(':')
+                .map_or(options.http_addr.as_str(), |(host, _)| host);
+            format!("{}:{}", host, port)
+        }
+        None => options.http_addr,
+    };
     start_example_raft_node(
         options.id,
         PathBuf::from(options.fs_root.clone())
             .join(format!("{}-db", options.id))
             .to_string_lossy()
             .to_string(),
-        options.http_addr,
+        http_addr,
         options.rpc_addr,
         options.fs_root,
         options.access_key,
         options.secret_key,
         options.leader_http_addr,
+        options.port_file,
+        ServerConfig {
+            storage_routes: options.storage_routes,
+            public_prefixes: options.public_prefixes,
+            encrypted_buckets: options.encrypted_buckets,
+            debug_endpoints: options.debug_endpoints,
+            debug_headers: options.debug_headers,
+            fsync: options.fsync,
+            fsync_window_ms: options.fsync_window_ms,
+            read_ahead_chunks: options.read_ahead,
+            chunk_roots: options.chunk_roots,
+            chunk_replicas: options.chunk_replicas,
+            erasure_data: options.erasure_data,
+            erasure_parity: options.erasure_parity,
+            cluster_ring: options.cluster_ring,
+            ring_replicas: options.ring_replicas,
+            standby_of: options.standby_of,
+            standby_promote_after_secs: options.promote_after,
+            cdc_addr: options.cdc_addr,
+            jwt: JwtConfig {
+                oidc_issuer: options.oidc_issuer,
+                secret: options.jwt_secret,
+                public_keys: options.jwt_public_keys,
+                audience: options.jwt_audience,
+                role_claim: options.jwt_role_claim,
+                admin_role: options.jwt_admin_role,
+                readonly_role: options.jwt_readonly_role,
+            },
+            auth_webhook: options.auth_webhook,
+            auth_cache_secs: options.auth_cache_secs,
+            bucket_restrictions: options.bucket_restrictions,
+            disabled_apis: options.disabled_apis,
+            statsd: StatsdConfig {
+                addr: options.statsd_addr,
+                prefix: options.statsd_prefix,
+                tags: options.statsd_tags,
+                api_labels: options.statsd_api_labels,
+                max_buckets: options.statsd_max_buckets,
+            },
+            http_workers: options.workers,
+            blocking_threads: options.blocking_threads,
+            pin_cpus: options.pin_cpus,
+            spool_threshold: options.spool_threshold_mb << 20,
+            spool_dir: options.spool_dir,
+            bucket_usage_headers: options.bucket_usage_headers,
+            plugins: options.plugins,
+            scripts: options.scripts,
+            scanner: match (options.scan_command, options.scan_icap) {
+                (Some(command), _) => Some(Scanner::Command(command)),
+                (None, Some(url)) => Some(Scanner::Icap(url)),
+                (None, None) => None,
+            },
+            scan_action: options.scan_action,
+            scan_fail_open: options.scan_fail_open,
+            access_tracking: options.track_access,
+            unread_expirations: options.unread_expirations,
+            slow_request_threshold: options.slow_request_ms.map(Duration::from_millis),
+            disk_watermarks: options.disk_watermarks,
+            compression: CompressionConfig {
+                codec: options.compression_codec,
+                level: options.zstd_level,
+                adaptive: options.adaptive_compression,
+                min_level: options.zstd_min_level,
+                recompress_after_days: options.recompress_cold_after,
+                recompress_level: options.recompress_level,
+            },
+            gc_interval_hours: options.gc_interval_hours,
+            scrub_interval_hours: options.scrub_interval_hours,
+            lifecycle_interval_secs: options.lifecycle_interval_secs,
+            lifecycle_day_secs: options.lifecycle_day_secs,
+            dedup_verify: options.dedup_verify,
+            verify_writes: options.verify_writes,
+            chunk_size: (options.chunk_size_mb << 20) as usize,
+            chunking: options.chunking,
+            idempotency_window_secs: options.idempotency_window_secs,
+            request_limits: RequestLimits {
+                max_header_count: options.max_header_count,
+                max_header_size: options.max_header_size,
+                max_url_length: options.max_url_length,
+                max_key_length: options.max_key_length,
+                max_metadata_size: options.max_metadata_size,
+            },
+            master_key: match (
+                options.master_key,
+                options.master_key_file,
+                options.master_key_command,
+            ) {
+                (Some(keys), _, _) => MasterKeySource::Inline(keys),
+                (None, Some(path), _) => MasterKeySource::File(path),
+                (None, None, Some(command)) => MasterKeySource::Command(command),
+                (None, None, None) => MasterKeySource::Builtin,
+            },
+            secure: options.secure,
+            plain_metadata: options.plain_metadata,
+            tls: options.tls_addr.map(|addr| TlsConfig {
+                addr,
+                cert: options.tls_cert.unwrap_or_default(),
+                key: options.tls_key.unwrap_or_default(),
+                client_ca: options.tls_client_ca,
+                require_client_cert: options.tls_require_client_cert,
+                cert_identities: options.tls_cert_identities,
+                http3_addr: options.http3_addr,
+            }),
+            vhost_domain: options.vhost_domain,
+            dns_addr: options.dns_addr,
+            dns_answer: options.dns_answer,
+        },
     )
     .await?;
     Ok(())
@@ -0,0 +1,313 @@
+use crate::api::DATA_DIR;
+use crate::config;
+use crate::config::DiskWatermarks;
+use crate::fs;
+use crate::spool;
+use crate::HandlerResponse;
+use anyhow::Context;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+// --- 容量报告：GET /admin/capacity 返回数据目录所在文件系统的空间和 inode 用量、
+// 暂存批次/分片上传/落盘请求体的用量，以及按 --disk-watermarks 判断的告警状态。
+// 带 min_free_bytes 时任一文件系统的可用空间不足即返回 507，供测试前的预检使用
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/capacity", web::get().to(capacity));
+}
+
+// 使用率相对告警线的状态，按严重程度排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkState {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl WatermarkState {
+    pub fn of(watermarks: &DiskWatermarks, used_percent: f64) -> WatermarkState {
+        if used_percent >= watermarks.critical as f64 {
+            WatermarkState::Critical
+        } else if used_percent >= watermarks.warning as f64 {
+            WatermarkState::Warning
+        } else {
+            WatermarkState::Ok
+        }
+    }
+}
+
+// statvfs 的结果，块数以 block_size 为单位
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStats {
+    pub block_size: u64,
+    pub blocks: u64,
+    pub blocks_free: u64,
+    // 非特权用户可用的块数
+    pub blocks_avail: u64,
+    pub files: u64,
+    pub files_free: u64,
+    pub files_avail: u64,
+}
+
+// 一个文件系统的用量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolumeUsage {
+    // 位于该文件系统上的数据目录
+    pub paths: Vec<String>,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
+    pub total_inodes: u64,
+    pub used_inodes: u64,
+    pub free_inodes: u64,
+    // 与 df 一致，保留给 root 的空间不计入
+    pub used_percent: f64,
+    pub inode_used_percent: f64,
+    // 空间和 inode 中较严重的状态
+    pub state: WatermarkState,
+}
+
+impl VolumeUsage {
+    pub fn new(paths: Vec<String>, stats: &FsStats, watermarks: &DiskWatermarks) -> Self {
+        let used_blocks = stats.blocks.saturating_sub(stats.blocks_free);
+        let used_inodes = stats.files.saturating_sub(stats.files_free);
+        let used_percent = percent(used_blocks, used_blocks + stats.blocks_avail);
+        let inode_used_percent = percent(used_inodes, used_inodes + stats.files_avail);
+        VolumeUsage {
+            paths,
+            total_bytes: stats.blocks * stats.block_size,
+            used_bytes: used_blocks * stats.block_size,
+            free_bytes: stats.blocks_avail * stats.block_size,
+            total_inodes: stats.files,
+            used_inodes,
+            free_inodes: stats.files_avail,
+            used_percent,
+            inode_used_percent,
+            state: WatermarkState::of(watermarks, used_percent)
+                .max(WatermarkState::of(watermarks, inode_used_percent)),
+        }
+    }
+}
+
+// 保留两位小数的百分比，总量为 0（如不限 inode 的文件系统）时为 0
+fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (used as f64 * 10000.0 / total as f64).round() / 100.0
+}
+
+// 暂存批次的用量，bytes 为暂存对象的大小之和
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StagedUsage {
+    pub batches: u64,
+    pub objects: u64,
+    pub bytes: u64,
+}
+
+// 进行中的分片上传的用量，bytes 为已上传分片的大小之和
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MultipartUsage {
+    pub uploads: u64,
+    pub parts: u64,
+    pub bytes: u64,
+}
+
+// 目录下文件的实际大小
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DirUsage {
+    pub path: String,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StagingUsage {
+    pub staged: StagedUsage,
+    pub multipart: MultipartUsage,
+    pub spool: DirUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatermarksReport {
+    pub warning: u8,
+    pub critical: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapacityReport {
+    pub volumes: Vec<VolumeUsage>,
+    pub staging: StagingUsage,
+    pub watermarks: WatermarksReport,
+    // 所有文件系统中最严重的状态
+    pub state: WatermarkState,
+}
+
+#[derive(Deserialize)]
+pub struct CapacityQuery {
+    // 要求每个文件系统至少有这么多可用字节，否则返回 507
+    pub min_free_bytes: Option<u64>,
+}
+
+pub async fn capacity(Query(query): Query<CapacityQuery>) -> HandlerResponse {
+    let report = tokio::task::spawn_blocking(collect)
+        .await
+        .context("统计容量失败")??;
+    let insufficient = query
+        .min_free_bytes
+        .is_some_and(|min| report.volumes.iter().any(|v| v.free_bytes < min));
+    let status = if insufficient {
+        StatusCode::INSUFFICIENT_STORAGE
+    } else {
+        StatusCode::OK
+    };
+    Ok(HttpResponse::build(status).json(&report))
+}
+
+fn collect() -> anyhow::Result<CapacityReport> {
+    let cfg = config::get();
+    let watermarks = cfg.disk_watermarks;
+    let mut dirs = vec![PathBuf::from(DATA_DIR.get().unwrap())];
+    dirs.extend(cfg.chunk_roots.iter().cloned());
+    dirs.extend(cfg.spool_dir.iter().cloned());
+    // 同一文件系统上的目录合并为一项
+    let mut volumes: Vec<(u64, Vec<String>, FsStats)> = vec![];
+    for dir in dirs {
+        // 目录还未创建时统计其所在的上级目录
+        let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(&dir);
+        let (fsid, stats) =
+            statvfs(existing).with_context(|| format!("读取文件系统信息失败 {:?}", dir))?;
+        let path = dir.to_string_lossy().to_string();
+        match volumes.iter_mut().find(|(id, _, _)| *id == fsid) {
+            Some((_, paths, _)) => {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+            None => volumes.push((fsid, vec![path], stats)),
+        }
+    }
+    let volumes: Vec<VolumeUsage> = volumes
+        .into_iter()
+        .map(|(_, paths, stats)| VolumeUsage::new(paths, &stats, &watermarks))
+        .collect();
+    let spool_dir = spool::spool_dir();
+    let staging = StagingUsage {
+        staged: staged_usage(&fs::staging_root()),
+        multipart: multipart_usage(&fs::tmp_root()),
+        spool: DirUsage {
+            path: spool_dir.to_string_lossy().to_string(),
+            ..dir_usage(&spool_dir)
+        },
+    };
+    Ok(CapacityReport {
+        state: volumes
+            .iter()
+            .map(|v| v.state)
+            .max()
+            .unwrap_or(WatermarkState::Ok),
+        volumes,
+        staging,
+        watermarks: WatermarksReport {
+            warning: watermarks.warning,
+            critical: watermarks.critical,
+        },
+    })
+}
+
+// 返回文件系统标识和用量；各字段的类型因平台而异，统一转为 u64
+#[allow(clippy::unnecessary_cast)]
+fn statvfs(path: &Path) -> io::Result<(u64, FsStats)> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    let stats = FsStats {
+        block_size: stat.f_frsize as u64,
+        blocks: stat.f_blocks as u64,
+        blocks_free: stat.f_bfree as u64,
+        blocks_avail: stat.f_bavail as u64,
+        files: stat.f_files as u64,
+        files_free: stat.f_ffree as u64,
+        files_avail: stat.f_favail as u64,
+    };
+    Ok((stat.f_fsid as u64, stats))
+}
+
+// 暂存批次的对象数和对象大小之和
+fn staged_usage(staging_root: &Path) -> StagedUsage {
+    let mut usage = StagedUsage::default();
+    let Ok(batches) = std::fs::read_dir(staging_root) else {
+        return usage;
+    };
+    for batch in batches.flatten() {
+        let mut meta_files = vec![];
+        if fs::walk_meta_files(&batch.path(), &mut meta_files).is_err() {
+            continue;
+        }
+        usage.batches += 1;
+        usage.objects += meta_files.len() as u64;
+        usage.bytes += meta_files
+            .iter()
+            .filter_map(|path| fs::load_metadata(path).ok())
+            .map(|metadata| metadata.size)
+            .sum::<u64>();
+    }
+    usage
+}
+
+// 进行中的分片上传数、已上传的分片数和分片大小之和（分片记录为 "大小\n哈希"）
+fn multipart_usage(tmp_root: &Path) -> MultipartUsage {
+    let mut usage = MultipartUsage::default();
+    let Ok(uploads) = std::fs::read_dir(tmp_root) else {
+        return usage;
+    };
+    for upload in uploads.flatten() {
+        if upload.file_name() == spool::SPOOL_DIR_NAME || !upload.path().is_dir() {
+            continue;
+        }
+        usage.uploads += 1;
+        for part in std::fs::read_dir(upload.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let Ok(record) = std::fs::read_to_string(part.path()) else {
+                continue;
+            };
+            usage.parts += 1;
+            usage.bytes += record
+                .lines()
+                .next()
+                .and_then(|size| size.parse::<u64>().ok())
+                .unwrap_or_default();
+        }
+    }
+    usage
+}
+
+// 目录下（不含子目录）的文件数和大小
+fn dir_usage(dir: &Path) -> DirUsage {
+    let mut usage = DirUsage::default();
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            usage.files += 1;
+            usage.bytes += metadata.len();
+        }
+    }
+    usage
+}
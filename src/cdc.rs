@@ -0,0 +1,341 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::config;
+use crate::fs;
+use crate::plugin;
+use crate::raft::store::Request;
+use crate::script;
+use crate::versioning::NULL_VERSION_ID;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+
+// --- 变更数据捕获（CDC）：状态机每应用一条会改动对象的日志，就比较改动前后的对象状态，
+// 以换行分隔的 JSON 推送给通过 TCP 连接的订阅者。各节点独立推送，seq 为日志序号，在各节点一致
+
+// 推送队列长度，订阅者落后超过该数量的事件时断开连接
+const CHANNEL_CAPACITY: usize = 4096;
+
+// 对象在变更前或变更后的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectState {
+    pub etag: String,
+    pub version_id: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Put,
+    Delete,
+    CreateBucket,
+    DeleteBucket,
+}
+
+// 推送给订阅者的一条变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CdcEvent {
+    pub seq: u64,
+    pub time: DateTime<Utc>,
+    pub op: Op,
+    pub bucket: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub before: Option<ObjectState>,
+    pub after: Option<ObjectState>,
+}
+
+// 比较同一批对象（"桶/键" -> 状态）变更前后的状态，生成对象的新增、覆盖和删除事件
+pub fn object_events(
+    seq: u64,
+    time: DateTime<Utc>,
+    before: &BTreeMap<String, ObjectState>,
+    after: &BTreeMap<String, ObjectState>,
+) -> Vec<CdcEvent> {
+    let mut paths: Vec<&String> = before.keys().chain(after.keys()).collect();
+    paths.sort_unstable();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter(|path| before.get(*path) != after.get(*path))
+        .filter_map(|path| {
+            let (bucket, key) = path.split_once('/')?;
+            let after = after.get(path).cloned();
+            Some(CdcEvent {
+                seq,
+                time,
+                op: if after.is_some() { Op::Put } else { Op::Delete },
+                bucket: bucket.to_string(),
+                key: Some(key.to_string()),
+                before: before.get(path).cloned(),
+                after,
+            })
+        })
+        .collect()
+}
+
+fn object_state(meta_file_path: &Path) -> Option<ObjectState> {
+    let metadata = fs::load_metadata(meta_file_path).ok()?;
+    Some(ObjectState {
+        etag: metadata.etag,
+        version_id: metadata
+            .version_id
+            .unwrap_or_else(|| NULL_VERSION_ID.to_string()),
+        size: metadata.size,
+    })
+}
+
+fn buckets_dir() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX)
+}
+
+// 一条日志会改动的对象：明确的元数据文件，以及需要整体扫描的目录（前缀重命名、删除桶、克隆桶）
+#[derive(Default)]
+struct Scope {
+    meta_files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+
+impl Scope {
+    fn snapshot(&self) -> BTreeMap<PathBuf, ObjectState> {
+        let mut meta_files = self.meta_files.clone();
+        for dir in &self.dirs {
+            if dir.is_dir() {
+                let _ = fs::walk_meta_files(dir, &mut meta_files);
+            }
+        }
+        meta_files
+            .into_iter()
+            .filter_map(|path| Some((path.clone(), object_state(&path)?)))
+            .collect()
+    }
+}
+
+fn meta_file(path: &str) -> PathBuf {
+    if path.ends_with(".meta") {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(format!("{}.meta", path))
+    }
+}
+
+fn scope_of(req: &Request) -> Option<Scope> {
+    let buckets_dir = buckets_dir();
+    let mut scope = Scope::default();
+    match req {
+        Request::UploadFileV2 { file_path, .. }
+        | Request::CommitChunkedFileV2 { file_path, .. }
+        | Request::DeleteFileV2 { file_path, .. } => {
+            scope.meta_files.push(meta_file(file_path));
+        }
+        Request::CombineChunk {
+            bucket_name,
+            object_key,
+            ..
+        } => scope.meta_files.push(meta_file(
+            &buckets_dir
+                .join(bucket_name)
+                .join(object_key)
+                .to_string_lossy(),
+        )),
+        Request::CopyFileV2 {
+            dest_bucket,
+            dest_object,
+            ..
+        } => scope.meta_files.push(meta_file(
+            &buckets_dir
+                .join(dest_bucket)
+                .join(dest_object)
+                .to_string_lossy(),
+        )),
+        Request::CommitStaged {
+            staging_id,
+            bucket_name,
+        } => {
+            let staging_dir = fs::staging_dir(staging_id, bucket_name);
+            let mut staged = Vec::new();
+            if staging_dir.is_dir() {
+                let _ = fs::walk_meta_files(&staging_dir, &mut staged);
+            }
+            let bucket_dir = buckets_dir.join(bucket_name);
+            scope.meta_files = staged
+                .iter()
+                .filter_map(|path| Some(bucket_dir.join(path.strip_prefix(&staging_dir).ok()?)))
+                .collect();
+        }
+        Request::RenameObject {
+            bucket_name,
+            source,
+            target,
+        } => {
+            let bucket_dir = buckets_dir.join(bucket_name);
+            if source.ends_with('/') {
+                scope.dirs = vec![bucket_dir.join(source), bucket_dir.join(target)];
+            } else {
+                scope.meta_files = vec![
+                    bucket_dir.join(format!("{}.meta", source)),
+                    bucket_dir.join(format!("{}.meta", target)),
+                ];
+            }
+        }
+        Request::DeleteFilesV2 { file_paths, .. } => {
+            scope.meta_files = file_paths.iter().map(|path| meta_file(path)).collect();
+        }
+        Request::DeleteBucket { bucket_name } => scope.dirs.push(PathBuf::from(bucket_name)),
+        Request::RestoreSnapshot { bucket_name, .. } => {
+            scope.dirs.push(buckets_dir.join(bucket_name))
+        }
+        Request::CloneBucket { target, .. } => scope.dirs.push(buckets_dir.join(target)),
+        Request::CreateBucketV2 { .. } => {}
+        _ => return None,
+    }
+    Some(scope)
+}
+
+// 应用日志前记录的对象状态，应用后调用 publish 推送变更
+pub(crate) struct Pending {
+    seq: u64,
+    // 桶的创建或删除事件，以及桶目录；应用后目录确实创建或删除了才推送
+    bucket_op: Option<(Op, String, PathBuf)>,
+    scope: Scope,
+    before: BTreeMap<PathBuf, ObjectState>,
+}
+
+static SENDER: OnceLock<broadcast::Sender<String>> = OnceLock::new();
+
+// 有订阅者、event 插件或事件脚本时记录日志改动前的对象状态，不改动对象的日志返回 None
+pub(crate) fn capture(seq: u64, req: &Request) -> Option<Pending> {
+    let subscribed = SENDER
+        .get()
+        .is_some_and(|sender| sender.receiver_count() > 0);
+    if !subscribed && !plugin::has_event_hooks() && !script::has_scripts() {
+        return None;
+    }
+    let scope = scope_of(req)?;
+    let bucket_name = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    };
+    let bucket_op = match req {
+        Request::CreateBucketV2 {
+            bucket_name: path, ..
+        } if !Path::new(path).is_dir() => {
+            Some((Op::CreateBucket, bucket_name(path)?, PathBuf::from(path)))
+        }
+        Request::DeleteBucket { bucket_name: path } if Path::new(path).is_dir() => {
+            Some((Op::DeleteBucket, bucket_name(path)?, PathBuf::from(path)))
+        }
+        // 克隆创建目标桶，先推送创建事件，再推送复制过去的对象
+        Request::CloneBucket { target, .. } => {
+            let target_dir = buckets_dir().join(target);
+            (!target_dir.exists()).then(|| (Op::CreateBucket, target.clone(), target_dir))
+        }
+        _ => None,
+    };
+    Some(Pending {
+        seq,
+        bucket_op,
+        before: scope.snapshot(),
+        scope,
+    })
+}
+
+impl Pending {
+    pub(crate) fn publish(self) {
+        let by_object_path = |states: BTreeMap<PathBuf, ObjectState>| {
+            states
+                .into_iter()
+                .filter_map(|(path, state)| Some((fs::object_path_from_meta(path)?, state)))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let after = self.scope.snapshot();
+        let time = Utc::now();
+        let mut events = object_events(
+            self.seq,
+            time,
+            &by_object_path(self.before),
+            &by_object_path(after),
+        );
+        let bucket_op = self
+            .bucket_op
+            .filter(|(op, _, dir)| dir.is_dir() == (*op == Op::CreateBucket));
+        if let Some((op, bucket, _)) = bucket_op {
+            let event = CdcEvent {
+                seq: self.seq,
+                time,
+                op,
+                bucket,
+                key: None,
+                before: None,
+                after: None,
+            };
+            match op {
+                Op::CreateBucket => events.insert(0, event),
+                _ => events.push(event),
+            }
+        }
+        for event in events {
+            plugin::emit(&event);
+            script::emit(&event);
+            if let (Some(sender), Ok(line)) = (SENDER.get(), serde_json::to_string(&event)) {
+                let _ = sender.send(line);
+            }
+        }
+    }
+}
+
+// 配置了 --cdc-addr 时监听订阅连接，每个连接从建立时起收到全部变更
+pub(crate) async fn spawn() -> std::io::Result<()> {
+    let Some(addr) = config::get().cdc_addr.clone() else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind(&addr).await?;
+    let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let _ = SENDER.set(sender);
+    info!("CDC 订阅地址 {}", addr);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let rx = SENDER.get().unwrap().subscribe();
+                    tokio::spawn(serve(stream, peer.to_string(), rx));
+                }
+                Err(err) => warn!("接受 CDC 连接失败: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn serve(
+    mut stream: tokio::net::TcpStream,
+    peer: String,
+    mut rx: broadcast::Receiver<String>,
+) {
+    info!("CDC 订阅者 {} 已连接", peer);
+    loop {
+        let line = match rx.recv().await {
+            Ok(line) => line,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("CDC 订阅者 {} 落后 {} 条变更，断开连接", peer, n);
+                return;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let res = async {
+            stream.write_all(line.as_bytes()).await?;
+            stream.write_all(b"\n").await
+        };
+        if res.await.is_err() {
+            info!("CDC 订阅者 {} 已断开", peer);
+            return;
+        }
+    }
+}
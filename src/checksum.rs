@@ -0,0 +1,47 @@
+use crate::fs;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+// --- 对象校验和：上传时保存整个对象的 SHA-256，分片上传的对象为各分片 SHA-256 拼接后的
+// SHA-256 加 "-分片数"（分片哈希即分片内容的 SHA-256，可由元数据中的分片列表计算）。
+// 读取请求带 x-amz-checksum-mode: ENABLED 时以 x-amz-checksum-sha256 返回，客户端可据此
+// 校验收到的内容
+
+pub const CHECKSUM_MODE_HEADER: &str = "x-amz-checksum-mode";
+pub const CHECKSUM_SHA256_HEADER: &str = "x-amz-checksum-sha256";
+pub const CHECKSUM_TYPE_HEADER: &str = "x-amz-checksum-type";
+
+// 对象内容的校验和
+pub fn sha256_of(data: &[u8]) -> String {
+    STANDARD.encode(fs::get_sha256(data))
+}
+
+// 十六进制的 SHA-256 转为响应头中的 base64 形式
+pub fn sha256_base64(hex_digest: &str) -> Option<String> {
+    let digest = hex::decode(hex_digest).ok().filter(|d| d.len() == 32)?;
+    Some(STANDARD.encode(digest))
+}
+
+// 分片上传对象的组合校验和，分片哈希为十六进制的 SHA-256
+pub fn composite_sha256<S: AsRef<str>>(part_hashes: &[S]) -> Option<String> {
+    let mut digests = Vec::with_capacity(part_hashes.len() * 32);
+    for hash in part_hashes {
+        digests.extend_from_slice(&hex::decode(hash.as_ref()).ok().filter(|d| d.len() == 32)?);
+    }
+    let digest = fs::get_sha256(&digests);
+    Some(format!("{}-{}", STANDARD.encode(digest), part_hashes.len()))
+}
+
+// 组合校验和带 "-分片数" 后缀，不是整个对象内容的校验和
+pub fn checksum_type(checksum: &str) -> &'static str {
+    if checksum.contains('-') {
+        "COMPOSITE"
+    } else {
+        "FULL_OBJECT"
+    }
+}
+
+// x-amz-checksum-mode 只接受 ENABLED
+pub fn is_mode_enabled(mode: Option<&str>) -> bool {
+    mode.is_some_and(|mode| mode.eq_ignore_ascii_case("ENABLED"))
+}
@@ -0,0 +1,212 @@
+use crate::admin::ratio;
+use crate::config;
+use crate::fs;
+use crate::gc;
+use crate::HandlerResponse;
+use anyhow::Context;
+use log::warn;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+// --- 分片级统计：分片数、分片大小分布、引用次数分布和被共享最多的分片，用于调整分片大小和
+// 切分方式时观察对实际数据的效果。统计所有对象（含历史版本、快照）、暂存批次和进行中的
+// 分片上传引用的分片，不读取分片文件；GET /admin/chunks/stats?top=N
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/chunks/stats", web::get().to(chunk_stats));
+}
+
+// 默认返回的共享分片数
+const DEFAULT_TOP_N: usize = 10;
+
+#[derive(Deserialize)]
+pub struct ChunkStatsQuery {
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct ChunkEntry {
+    // 分片原始大小，旧版本写入的元数据没有分片大小时为 0
+    size: u64,
+    references: u64,
+}
+
+// 按哈希累计的分片引用
+#[derive(Debug, Default)]
+pub struct ChunkCensus {
+    chunks: HashMap<String, ChunkEntry>,
+}
+
+impl ChunkCensus {
+    // 累计一个对象的分片，分片大小的取法与 admin::DedupStats 相同
+    pub fn add_object(&mut self, size: u64, chunks: &[String], chunk_sizes: &[u64]) {
+        for (i, hash) in chunks.iter().enumerate() {
+            let chunk_size = match chunk_sizes.get(i) {
+                Some(chunk_size) if chunk_sizes.len() == chunks.len() => Some(*chunk_size),
+                _ if chunks.len() == 1 => Some(size),
+                _ => None,
+            };
+            self.add(hash, chunk_size);
+        }
+    }
+
+    // 累计一次分片引用，size 未知时为空
+    pub fn add(&mut self, hash: &str, size: Option<u64>) {
+        let entry = self.chunks.entry(hash.to_string()).or_default();
+        entry.references += 1;
+        if let Some(size) = size {
+            entry.size = entry.size.max(size);
+        }
+    }
+
+    // 汇总为报告，stored_size 返回分片在磁盘上的大小，只对共享最多的 top 个分片调用
+    pub fn report(&self, top: usize, stored_size: impl Fn(&str) -> u64) -> ChunkStatsReport {
+        let mut sizes: BTreeMap<u64, HistogramBucket> = BTreeMap::new();
+        let mut references: BTreeMap<u64, HistogramBucket> = BTreeMap::new();
+        let mut report = ChunkStatsReport {
+            chunk_size: 0,
+            chunks: self.chunks.len() as u64,
+            ..Default::default()
+        };
+        for entry in self.chunks.values() {
+            report.references += entry.references;
+            report.unique_size += entry.size;
+            report.referenced_size += entry.size * entry.references;
+            report.max_chunk_size = report.max_chunk_size.max(entry.size);
+            if entry.references > 1 {
+                report.shared_chunks += 1;
+            }
+            sizes
+                .entry(histogram_bound(entry.size))
+                .or_insert_with_key(|le| HistogramBucket::new(*le))
+                .add(entry.size);
+            references
+                .entry(histogram_bound(entry.references))
+                .or_insert_with_key(|le| HistogramBucket::new(*le))
+                .add(entry.size);
+        }
+        report.average_chunk_size = report
+            .unique_size
+            .checked_div(report.chunks)
+            .unwrap_or_default();
+        report.dedup_ratio = ratio(report.referenced_size, report.unique_size);
+        report.size_histogram = sizes.into_values().collect();
+        report.reference_histogram = references.into_values().collect();
+
+        let mut shared: Vec<(&String, &ChunkEntry)> = self
+            .chunks
+            .iter()
+            .filter(|(_, entry)| entry.references > 1)
+            .collect();
+        shared.sort_by_key(|(hash, entry)| (Reverse(entry.references), Reverse(entry.size), *hash));
+        report.top_shared = shared
+            .into_iter()
+            .take(top)
+            .map(|(hash, entry)| SharedChunk {
+                hash: hash.clone(),
+                size: entry.size,
+                stored_size: stored_size(hash),
+                references: entry.references,
+            })
+            .collect();
+        report
+    }
+}
+
+// 直方图区间的上界（含）：不小于 value 的最小的 2 的幂
+pub fn histogram_bound(value: u64) -> u64 {
+    value.checked_next_power_of_two().unwrap_or(u64::MAX)
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HistogramBucket {
+    // 区间上界（含），下界为上一个区间的上界
+    pub le: u64,
+    pub chunks: u64,
+    // 这些分片的原始大小之和
+    pub bytes: u64,
+}
+
+impl HistogramBucket {
+    fn new(le: u64) -> Self {
+        HistogramBucket {
+            le,
+            chunks: 0,
+            bytes: 0,
+        }
+    }
+
+    fn add(&mut self, size: u64) {
+        self.chunks += 1;
+        self.bytes += size;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedChunk {
+    pub hash: String,
+    pub size: u64,
+    // 压缩后在磁盘上的大小
+    pub stored_size: u64,
+    pub references: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChunkStatsReport {
+    // 当前配置的分片大小，已有分片可能是用其他大小写入的
+    pub chunk_size: u64,
+    // 当前配置的切分方式
+    pub chunking: String,
+    // 不重复的分片数
+    pub chunks: u64,
+    // 引用总次数
+    pub references: u64,
+    // 被引用不止一次的分片数
+    pub shared_chunks: u64,
+    // 不重复分片的原始大小之和
+    pub unique_size: u64,
+    // 按引用次数计算的原始大小之和
+    pub referenced_size: u64,
+    pub average_chunk_size: u64,
+    pub max_chunk_size: u64,
+    // 按引用计算的大小 / 不重复分片的大小
+    pub dedup_ratio: f64,
+    // 按分片原始大小划分
+    pub size_histogram: Vec<HistogramBucket>,
+    // 按引用次数划分，le 为引用次数
+    pub reference_histogram: Vec<HistogramBucket>,
+    // 引用次数最多的分片，只含被共享的分片
+    pub top_shared: Vec<SharedChunk>,
+}
+
+fn collect(top: usize) -> anyhow::Result<ChunkStatsReport> {
+    let mut census = ChunkCensus::default();
+    for meta_file in gc::metadata_files()? {
+        match fs::load_metadata(&meta_file) {
+            Ok(metadata) => {
+                census.add_object(metadata.size, &metadata.chunks, &metadata.chunk_sizes)
+            }
+            Err(err) => warn!("跳过无法读取的元数据 {:?}: {}", meta_file, err),
+        }
+    }
+    for (hash, size) in gc::upload_part_chunks()? {
+        census.add(&hash, Some(size));
+    }
+    let mut report = census.report(top, fs::chunk_disk_usage);
+    report.chunk_size = config::get().chunk_bytes() as u64;
+    report.chunking = format!("{:?}", config::get().chunking);
+    Ok(report)
+}
+
+// 需要读取所有元数据，大实例上较慢
+pub async fn chunk_stats(Query(query): Query<ChunkStatsQuery>) -> HandlerResponse {
+    let top = query.top.unwrap_or(DEFAULT_TOP_N);
+    let report = tokio::task::spawn_blocking(move || collect(top))
+        .await
+        .context("统计分片失败")??;
+    Ok(HttpResponse::Ok().json(&report))
+}
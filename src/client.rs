@@ -0,0 +1,150 @@
+use crate::fs;
+use crate::util::cry::{do_bytes_to_hex, do_hex, do_hmac_sha256};
+use anyhow::Context;
+use chrono::Utc;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Method;
+
+// --- 内置子命令（bench 等）使用的最小 S3 客户端，请求头方式的 SigV4 签名
+
+// SigV4 规范 URI 编码：除字母数字和 `-_.~` 外全部编码
+const URI_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// 按段编码对象路径，保留 `/`
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|s| utf8_percent_encode(s, URI_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub struct S3Client {
+    // 服务地址，包含路径前缀，如 http://127.0.0.1:9000/api
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    region: String,
+    http: reqwest::Client,
+}
+
+impl S3Client {
+    pub fn new(endpoint: &str, access_key: &str, secret_key: &str) -> Self {
+        Self::with_http(endpoint, access_key, secret_key, reqwest::Client::new())
+    }
+
+    // 使用已有的 HTTP 客户端（共享连接池和超时设置）
+    pub(crate) fn with_http(
+        endpoint: &str,
+        access_key: &str,
+        secret_key: &str,
+        http: reqwest::Client,
+    ) -> Self {
+        S3Client {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            region: "us-east-1".to_string(),
+            http,
+        }
+    }
+
+    // 未签名的请求地址，用于匿名访问
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.endpoint, encode_path(path))
+    }
+
+    // 发送签名请求，path 为相对 endpoint 的未编码路径（如 `bucket/key`）
+    pub async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let base = url::Url::parse(&self.endpoint).context("服务地址无效")?;
+        let host = match base.port() {
+            Some(port) => format!("{}:{}", base.host_str().context("服务地址缺少主机")?, port),
+            None => base.host_str().context("服务地址缺少主机")?.to_string(),
+        };
+        let uri = format!(
+            "{}/{}",
+            base.path().trim_end_matches('/'),
+            encode_path(path)
+        );
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| {
+                (
+                    utf8_percent_encode(k, URI_ENCODE_SET).to_string(),
+                    utf8_percent_encode(v, URI_ENCODE_SET).to_string(),
+                )
+            })
+            .collect();
+        query.sort();
+        let query_string = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let content_hash = hex::encode(fs::get_sha256(&body));
+        let mut signed: Vec<(String, String)> = headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+            .collect();
+        signed.push(("host".to_string(), host.clone()));
+        signed.push(("x-amz-content-sha256".to_string(), content_hash.clone()));
+        signed.push(("x-amz-date".to_string(), amz_date.clone()));
+        signed.sort();
+        let signed_headers = signed
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let mut canonical_request = format!("{}\n{}\n{}\n", method, uri, query_string);
+        for (name, value) in &signed {
+            canonical_request.push_str(&format!("{}:{}\n", name, value));
+        }
+        canonical_request.push_str(&format!("\n{}\n{}", signed_headers, content_hash));
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            do_hex(&canonical_request)
+        );
+        let k_date = do_hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), &date)?;
+        let k_region = do_hmac_sha256(&k_date, &self.region)?;
+        let k_service = do_hmac_sha256(&k_region, "s3")?;
+        let signing_key = do_hmac_sha256(&k_service, "aws4_request")?;
+        let signature = do_bytes_to_hex(&do_hmac_sha256(&signing_key, &string_to_sign)?);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+
+        let mut url = format!("{}://{}{}", base.scheme(), host, uri);
+        if !query_string.is_empty() {
+            url.push('?');
+            url.push_str(&query_string);
+        }
+        let mut request = self
+            .http
+            .request(method, url)
+            .header("Authorization", authorization)
+            .body(body);
+        for (name, value) in signed.iter().filter(|(k, _)| k != "host") {
+            request = request.header(name, value);
+        }
+        request.send().await.context("发送请求失败")
+    }
+}
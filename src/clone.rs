@@ -0,0 +1,161 @@
+use crate::api::{is_valid_bucket_name, BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::durability;
+use crate::err::AppError;
+use crate::fs::{self, Backend};
+use crate::headers;
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::website;
+use crate::HandlerResponse;
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use log::info;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// --- 桶克隆（写时复制）：POST /admin/buckets/{bucket}/clone?target=新桶 创建一个包含源桶全部当前
+// 对象的新桶。只复制对象元数据（按新桶的数据密钥重新加密），分片通过去重与源桶共享，之后两个桶
+// 的修改互不影响；直通存储的对象需要复制原文件。静态网站和默认响应头配置一起复制；桶策略
+// （资源中含桶名）、版本控制状态和历史版本不复制，克隆出的对象都是 null 版本。
+// 克隆的桶与普通桶一样删除，分片在不再被引用后由垃圾回收清理
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/admin/buckets/{bucket}/clone",
+        web::post().to(clone_bucket),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct CloneQuery {
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CloneSummary {
+    pub source: String,
+    pub target: String,
+    pub objects: u64,
+    // 对象原始大小之和，不占用额外的分片空间
+    pub bytes: u64,
+}
+
+fn buckets_dir() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX)
+}
+
+pub async fn clone_bucket(
+    req: web::HttpRequest,
+    Query(query): Query<CloneQuery>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let source = req
+        .match_info()
+        .get("bucket")
+        .context("缺少桶名")?
+        .to_string();
+    let Some(target) = query.target.filter(|target| !target.is_empty()) else {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "Missing target bucket",
+        ));
+    };
+    if !buckets_dir().join(&source).is_dir() {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            "The specified bucket does not exist",
+        ));
+    }
+    if !is_valid_bucket_name(&target) {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidBucketName",
+            "The specified bucket is not valid",
+        ));
+    }
+    if buckets_dir().join(&target).exists() {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "BucketAlreadyExists",
+            "The requested bucket name is not available",
+        ));
+    }
+    let res = state
+        .client_write(Request::CloneBucket {
+            source,
+            target: target.clone(),
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let summary: CloneSummary = res
+        .data
+        .value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .with_context(|| format!("克隆桶 {} 失败", target))?;
+    Ok(HttpResponse::Ok().json(&summary))
+}
+
+// 状态机中执行克隆，失败时删除已创建的目标桶
+pub(crate) fn apply(source: &str, target: &str) -> anyhow::Result<CloneSummary> {
+    let target_dir = buckets_dir().join(target);
+    if target_dir.exists() {
+        return Err(anyhow!("目标桶 {} 已存在", target));
+    }
+    let res = copy_bucket(source, target, &target_dir);
+    if res.is_err() {
+        let _ = std::fs::remove_dir_all(&target_dir);
+        let _ = std::fs::remove_dir_all(fs::raw_path(target));
+    }
+    res
+}
+
+fn copy_bucket(source: &str, target: &str, target_dir: &Path) -> anyhow::Result<CloneSummary> {
+    let source_dir = buckets_dir().join(source);
+    fs::create_bucket_key(target_dir).context("创建桶失败")?;
+    fs::save_bucket_created(target_dir, Utc::now())?;
+    let configs = [
+        (website::config_path(source), website::config_path(target)),
+        (headers::config_path(source), headers::config_path(target)),
+    ];
+    for (from, to) in configs {
+        if from.is_file() {
+            std::fs::copy(&from, &to).context("复制桶配置失败")?;
+            durability::enqueue(to);
+        }
+    }
+    let mut meta_files = Vec::new();
+    fs::walk_meta_files(&source_dir, &mut meta_files).context("遍历桶目录失败")?;
+    let mut summary = CloneSummary {
+        source: source.to_string(),
+        target: target.to_string(),
+        ..Default::default()
+    };
+    for path in meta_files {
+        let relative = path.strip_prefix(&source_dir)?;
+        let mut metadata = fs::load_metadata(&path)?;
+        metadata.version_id = None;
+        if metadata.backend == Backend::Passthrough {
+            let key = relative.with_extension("");
+            let key = key.to_string_lossy();
+            let dest_raw = fs::raw_path(&format!("{}/{}", target, key));
+            std::fs::create_dir_all(dest_raw.parent().unwrap())?;
+            std::fs::copy(fs::raw_path(&format!("{}/{}", source, key)), &dest_raw)
+                .context("复制文件失败")?;
+            durability::enqueue(dest_raw);
+        }
+        fs::save_metadata(target_dir.join(relative), &metadata)?;
+        summary.objects += 1;
+        summary.bytes += metadata.size;
+    }
+    info!(
+        "克隆桶 {} -> {}：{} 个对象",
+        source, target, summary.objects
+    );
+    Ok(summary)
+}
@@ -0,0 +1,245 @@
+use crate::client::S3Client;
+use crate::config;
+use crate::fs;
+use crate::identity::Identity;
+use crate::keys;
+use crate::raft::{Node, NodeId};
+use crate::HandlerResponse;
+use ntex::web;
+use ntex::web::HttpResponse;
+use reqwest::Method;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+// --- 实验性的集群分片模式：raft 成员组成一致性哈希环，每个分片只保存在环上顺时针的
+// ring_replicas 个节点中，本地没有的分片通过其他节点的 /admin 接口读取，请求用根密钥签名
+
+// 每个节点在环上的虚拟节点数
+const VIRTUAL_NODES: usize = 64;
+// 读取远端分片的超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/cluster/chunks/{hash}", web::get().to(get_chunk))
+        .route(
+            "/admin/cluster/chunks/{hash}/len",
+            web::get().to(get_chunk_len),
+        );
+}
+
+// 一致性哈希环
+#[derive(Debug, Default)]
+pub struct Ring {
+    // （环上位置，节点），按位置排序
+    points: Vec<(u64, NodeId)>,
+    // 节点的 HTTP 地址
+    addrs: BTreeMap<NodeId, String>,
+}
+
+// 取 sha256 十六进制字符串的前 16 位作为环上的位置
+fn ring_position(hex: &str) -> u64 {
+    hex.get(..16)
+        .and_then(|h| u64::from_str_radix(h, 16).ok())
+        .unwrap_or_default()
+}
+
+impl Ring {
+    pub fn new(addrs: BTreeMap<NodeId, String>) -> Self {
+        let mut points: Vec<(u64, NodeId)> = addrs
+            .keys()
+            .flat_map(|&id| {
+                (0..VIRTUAL_NODES).map(move |i| {
+                    let hash =
+                        fs::get_sha256_string(&fs::get_sha256(format!("{}-{}", id, i).as_bytes()));
+                    (ring_position(&hash), id)
+                })
+            })
+            .collect();
+        points.sort_unstable();
+        Ring { points, addrs }
+    }
+
+    // 分片的归属节点：从分片哈希在环上的位置顺时针取 replicas 个不同节点
+    pub fn owners(&self, hash: &str, replicas: usize) -> Vec<NodeId> {
+        let replicas = replicas.clamp(1, self.addrs.len().max(1));
+        let start = self
+            .points
+            .partition_point(|&(pos, _)| pos < ring_position(hash));
+        let mut owners = Vec::with_capacity(replicas);
+        for i in 0..self.points.len() {
+            let (_, id) = self.points[(start + i) % self.points.len()];
+            if !owners.contains(&id) {
+                owners.push(id);
+                if owners.len() == replicas {
+                    break;
+                }
+            }
+        }
+        owners
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+}
+
+static RING: RwLock<Option<Ring>> = RwLock::new(None);
+static LOCAL_NODE: OnceLock<NodeId> = OnceLock::new();
+// 读取远端分片的 HTTP 客户端，以及在后台线程中执行请求使用的运行时
+static CLIENT: OnceLock<(reqwest::Client, Handle)> = OnceLock::new();
+
+pub(crate) fn enabled() -> bool {
+    config::get().cluster_ring
+}
+
+// 需在 tokio 运行时中调用
+pub(crate) fn set_local_node(id: NodeId) {
+    let _ = LOCAL_NODE.set(id);
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .expect("创建 HTTP 客户端失败");
+    let _ = CLIENT.set((client, Handle::current()));
+}
+
+// 应用新的 raft 成员配置（含 learner）后重建哈希环
+pub(crate) fn update_members<'a>(nodes: impl Iterator<Item = (&'a NodeId, &'a Node)>) {
+    if !enabled() {
+        return;
+    }
+    let addrs = nodes
+        .map(|(id, node)| (*id, node.api_addr.clone()))
+        .collect();
+    *RING.write().unwrap() = Some(Ring::new(addrs));
+}
+
+// 本节点是否负责保存该分片；未启用集群模式或尚无成员时总是保存
+pub(crate) fn is_local(hash: &str) -> bool {
+    if !enabled() {
+        return true;
+    }
+    let ring = RING.read().unwrap();
+    match (ring.as_ref(), LOCAL_NODE.get()) {
+        (Some(ring), Some(local)) if !ring.is_empty() => ring
+            .owners(hash, config::get().ring_replicas)
+            .contains(local),
+        _ => true,
+    }
+}
+
+// 读取远端分片时依次尝试的节点地址：先是归属节点，再是其余节点（成员变化前写入的分片）
+fn remote_addrs(hash: &str) -> Vec<String> {
+    let ring = RING.read().unwrap();
+    let Some(ring) = ring.as_ref() else {
+        return vec![];
+    };
+    let local = LOCAL_NODE.get();
+    let mut ids = ring.owners(hash, config::get().ring_replicas);
+    for id in ring.addrs.keys() {
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+    ids.into_iter()
+        .filter(|id| Some(id) != local)
+        .filter_map(|id| ring.addrs.get(&id).cloned())
+        .collect()
+}
+
+async fn http_get(http: &reqwest::Client, addr: &str, path: &str) -> io::Result<Vec<u8>> {
+    let (access_key, secret_key) =
+        keys::root_credentials().ok_or_else(|| io::Error::other("根密钥未初始化"))?;
+    let endpoint = format!("http://{}/admin", addr);
+    let client = S3Client::with_http(&endpoint, &access_key, &secret_key, http.clone());
+    let resp = client
+        .send(Method::GET, path, &[], &[], vec![])
+        .await
+        .map_err(io::Error::other)?;
+    if !resp.status().is_success() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("节点 {} 返回 {}", addr, resp.status()),
+        ));
+    }
+    let body = resp.bytes().await.map_err(io::Error::other)?;
+    Ok(body.to_vec())
+}
+
+// 阻塞调用，需在后台线程（pool::spawn 或 spawn_blocking）中使用，不能在异步任务中调用
+fn fetch_remote<T>(hash: &str, path: &str, parse: impl Fn(Vec<u8>) -> Option<T>) -> io::Result<T> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, format!("分片 {} 不存在", hash));
+    let Some((client, handle)) = CLIENT.get() else {
+        return Err(last_err);
+    };
+    for addr in remote_addrs(hash) {
+        match handle.block_on(http_get(client, &addr, path)) {
+            Ok(body) => match parse(body) {
+                Some(res) => return Ok(res),
+                None => last_err = io::Error::other(format!("节点 {} 返回的分片无效", addr)),
+            },
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+// 从其他节点读取分片的原始内容（zstd 帧）
+pub(crate) fn fetch_chunk(hash: &str) -> io::Result<Vec<u8>> {
+    fetch_remote(hash, &format!("cluster/chunks/{}", hash), Some)
+}
+
+// 从其他节点读取分片压缩后的大小
+pub(crate) fn fetch_chunk_len(hash: &str) -> io::Result<u64> {
+    fetch_remote(hash, &format!("cluster/chunks/{}/len", hash), |body| {
+        String::from_utf8(body).ok()?.trim().parse().ok()
+    })
+}
+
+fn is_chunk_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// 只接受其他节点用根密钥签名的请求，其他访问密钥和 JWT 即使可以调用管理接口也不能读取分片
+fn is_peer(req: &web::HttpRequest) -> bool {
+    let root = keys::root().map(|(access_key, _)| access_key);
+    req.extensions()
+        .get::<Identity>()
+        .is_some_and(|identity| Some(&identity.access_key) == root.as_ref())
+}
+
+// 返回本节点保存的分片，不会再转发到其他节点
+pub async fn get_chunk(req: web::HttpRequest, hash: web::types::Path<String>) -> HandlerResponse {
+    if !is_peer(&req) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let hash = hash.into_inner();
+    if !is_chunk_hash(&hash) {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    match tokio::task::spawn_blocking(move || fs::read_local_chunk(&hash)).await {
+        Ok(Ok(data)) => Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .body(data)),
+        _ => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+pub async fn get_chunk_len(
+    req: web::HttpRequest,
+    hash: web::types::Path<String>,
+) -> HandlerResponse {
+    if !is_peer(&req) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let hash = hash.into_inner();
+    if !is_chunk_hash(&hash) {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    match tokio::task::spawn_blocking(move || fs::local_chunk_len(&hash)).await {
+        Ok(Ok(len)) => Ok(HttpResponse::Ok().body(len.to_string())),
+        _ => Ok(HttpResponse::NotFound().finish()),
+    }
+}
@@ -0,0 +1,140 @@
+use crate::compression;
+use crate::config;
+use crate::lz4;
+use crate::slowlog::{self, Phase};
+use ntex::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::str::FromStr;
+use zstd::stream::read::Decoder;
+
+// --- 分片的压缩算法：zstd（默认）、lz4 或不压缩。默认算法由 --compression-codec 配置，上传请求
+// 可以用 x-rs3-compression 请求头按对象指定（zstd[:级别]、lz4 或 none）。zstd 分片与旧版本一样
+// 直接保存 zstd 帧；其他算法的分片带头部记录算法，读取时按头部解码，不同算法的分片可以共存。
+// 去重命中已有的分片时保留其原有的编码
+
+pub(crate) const REQUEST_HEADER: &str = "x-rs3-compression";
+
+// 非 zstd 分片的头部：魔数和算法（1 字节），lz4 之后为 8 字节的原始大小。zstd 帧以
+// 28 B5 2F FD 开头，不会与之混淆
+const HEADER_MAGIC: &[u8] = b"\0RSC";
+const CODEC_NONE: u8 = 0;
+const CODEC_LZ4: u8 = 4;
+
+// 分片的编码方式，命令行和请求头格式为 zstd[:级别]、lz4 或 none
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Codec {
+    // 级别为 0 时使用当前的压缩级别（--zstd-level，开启 --adaptive-compression 时随负载调整）
+    Zstd(i32),
+    Lz4,
+    Uncompressed,
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::Zstd(0)
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "zstd" => Ok(Codec::Zstd(0)),
+            None if s == "lz4" => Ok(Codec::Lz4),
+            None if s == "none" => Ok(Codec::Uncompressed),
+            Some(("zstd", level)) => level
+                .parse::<i32>()
+                .ok()
+                .filter(|level| zstd::compression_level_range().contains(level))
+                .map(Codec::Zstd)
+                .ok_or_else(|| format!("invalid zstd level `{}`", level)),
+            _ => Err(format!(
+                "unknown compression codec `{}`, expected zstd[:LEVEL], lz4 or none",
+                s
+            )),
+        }
+    }
+}
+
+// 请求指定的编码，未指定时为服务端配置的默认编码
+pub(crate) fn resolve(codec: Option<Codec>) -> Codec {
+    codec.unwrap_or(config::get().compression.codec)
+}
+
+// 读取 x-rs3-compression 请求头
+pub(crate) fn from_headers(headers: &HeaderMap) -> Result<Option<Codec>, String> {
+    match headers.get(REQUEST_HEADER) {
+        Some(value) => value
+            .to_str()
+            .map_err(|err| err.to_string())?
+            .trim()
+            .parse()
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+fn with_header(codec: u8, size: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_MAGIC.len() + 9 + size);
+    out.extend_from_slice(HEADER_MAGIC);
+    out.push(codec);
+    out
+}
+
+// 编码分片内容
+pub fn encode(data: &[u8], codec: Codec) -> io::Result<Vec<u8>> {
+    slowlog::time(Phase::Compress, || match codec {
+        Codec::Zstd(0) => zstd::stream::encode_all(data, compression::level()),
+        Codec::Zstd(level) => zstd::stream::encode_all(data, level),
+        Codec::Lz4 => {
+            let compressed = lz4::compress(data);
+            let mut out = with_header(CODEC_LZ4, compressed.len());
+            out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            out.extend_from_slice(&compressed);
+            Ok(out)
+        }
+        Codec::Uncompressed => {
+            let mut out = with_header(CODEC_NONE, data.len());
+            out.extend_from_slice(data);
+            Ok(out)
+        }
+    })
+}
+
+// 解码分片文件的内容，没有头部时按 zstd 帧解压
+pub fn decode(chunk: &[u8]) -> io::Result<Vec<u8>> {
+    slowlog::time(Phase::Decompress, || {
+        let Some(rest) = chunk.strip_prefix(HEADER_MAGIC) else {
+            let mut decoder = Decoder::new(chunk)?;
+            let mut result = Vec::new();
+            decoder.read_to_end(&mut result)?;
+            return Ok(result);
+        };
+        match rest.split_first() {
+            Some((&CODEC_NONE, data)) => Ok(data.to_vec()),
+            Some((&CODEC_LZ4, rest)) if rest.len() >= 8 => {
+                // 头部记录的原始大小不可信，超过分片大小上限时不按其分配内存
+                let size = u64::from_le_bytes(rest[..8].try_into().unwrap());
+                let max_size = config::get().chunker().max_chunk_size();
+                match usize::try_from(size) {
+                    Ok(size) if size <= max_size => lz4::decompress(&rest[8..], size),
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("lz4 分片的原始大小 {} 超过分片大小上限 {}", size, max_size),
+                    )),
+                }
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "无法识别的分片编码",
+            )),
+        }
+    })
+}
+
+// 分片文件是否为 zstd 帧，可以直接作为 Content-Encoding: zstd 的响应返回
+pub fn is_zstd(chunk: &[u8]) -> bool {
+    !chunk.starts_with(HEADER_MAGIC)
+}
@@ -0,0 +1,609 @@
+use crate::client::S3Client;
+use crypto_hash::{hex_digest, Algorithm};
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use serde::Serialize;
+
+// --- 协议兼容性自检子命令：对运行中的服务执行一组 S3 协议检查并输出兼容性矩阵
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct CompatOpt {
+    /// S3 endpoint including the path prefix
+    #[clap(long, default_value_t = String::from("http://127.0.0.1:9000/api"))]
+    pub endpoint: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub access_key: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub secret_key: String,
+
+    /// Print the matrix as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub category: &'static str,
+    pub check: &'static str,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompatReport {
+    pub passed: usize,
+    pub total: usize,
+    pub checks: Vec<CheckResult>,
+}
+
+impl CompatReport {
+    fn record(&mut self, category: &'static str, check: &'static str, res: Result<(), String>) {
+        self.total += 1;
+        let (outcome, detail) = match res {
+            Ok(()) => {
+                self.passed += 1;
+                (Outcome::Pass, String::new())
+            }
+            Err(detail) => (Outcome::Fail, detail),
+        };
+        self.checks.push(CheckResult {
+            category,
+            check,
+            outcome,
+            detail,
+        });
+    }
+}
+
+// 取出 XML 中所有 <tag>…</tag> 的文本，不处理嵌套的同名元素
+pub fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        values.push(rest[..end].to_string());
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+struct Resp {
+    status: u16,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Resp {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    }
+
+    fn expect_status(&self, status: u16) -> Result<(), String> {
+        if self.status == status {
+            Ok(())
+        } else {
+            Err(format!("expected HTTP {}, got {}", status, self.status))
+        }
+    }
+
+    // 校验错误响应的状态码和 <Code>
+    fn expect_error(&self, status: u16, code: &str) -> Result<(), String> {
+        let actual = xml_values(&self.text(), "Code").into_iter().next();
+        if self.status == status && actual.as_deref() == Some(code) {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} {}, got {} {}",
+                status,
+                code,
+                self.status,
+                actual.unwrap_or_else(|| "(no Code)".to_string())
+            ))
+        }
+    }
+}
+
+struct Ctx {
+    client: S3Client,
+    opt: CompatOpt,
+    bucket: String,
+}
+
+impl Ctx {
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<Resp, String> {
+        let resp = self
+            .client
+            .send(method, path, query, headers, body)
+            .await
+            .map_err(|err| format!("{:#}", err))?;
+        let status = resp.status().as_u16();
+        let headers = resp.headers().clone();
+        let body = resp.bytes().await.map_err(|err| err.to_string())?.to_vec();
+        Ok(Resp {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    async fn get(&self, path: &str, headers: &[(&str, &str)]) -> Result<Resp, String> {
+        self.call(Method::GET, path, &[], headers, Vec::new()).await
+    }
+
+    async fn put(&self, key: &str, body: &[u8]) -> Result<Resp, String> {
+        self.call(
+            Method::PUT,
+            &format!("{}/{}", self.bucket, key),
+            &[],
+            &[],
+            body.to_vec(),
+        )
+        .await
+    }
+
+    async fn list(&self, query: &[(&str, &str)]) -> Result<Resp, String> {
+        self.call(Method::GET, &self.bucket, query, &[], Vec::new())
+            .await
+    }
+}
+
+fn quoted_md5(body: &[u8]) -> String {
+    format!("\"{}\"", hex_digest(Algorithm::MD5, body))
+}
+
+fn expect_eq<T: PartialEq + std::fmt::Debug>(
+    what: &str,
+    actual: T,
+    expected: T,
+) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: expected {:?}, got {:?}",
+            what, expected, actual
+        ))
+    }
+}
+
+const ETAG_KEY: &str = "etag.txt";
+const ETAG_BODY: &[u8] = b"hello verify-compat";
+const LIST_KEYS: [&str; 4] = [
+    "list/a.txt",
+    "list/b.txt",
+    "list/sub/c.txt",
+    "list/sub/d.txt",
+];
+const MULTIPART_KEY: &str = "multipart.bin";
+const PART_SIZE: usize = 5 << 20;
+
+async fn check_errors(ctx: &Ctx, report: &mut CompatReport) {
+    let res = async {
+        ctx.get(&format!("{}-missing/key", ctx.bucket), &[])
+            .await?
+            .expect_error(404, "NoSuchBucket")
+    };
+    report.record("errors", "NoSuchBucket for a missing bucket", res.await);
+
+    let res = async {
+        ctx.get(&format!("{}/missing-key", ctx.bucket), &[])
+            .await?
+            .expect_error(404, "NoSuchKey")
+    };
+    report.record("errors", "NoSuchKey for a missing key", res.await);
+
+    let res = async {
+        let resp = reqwest::get(ctx.client.url(&ctx.bucket))
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = resp.status().as_u16();
+        let body = resp.bytes().await.map_err(|err| err.to_string())?.to_vec();
+        Resp {
+            status,
+            headers: HeaderMap::new(),
+            body,
+        }
+        .expect_error(403, "AccessDenied")
+    };
+    report.record("errors", "AccessDenied for anonymous requests", res.await);
+
+    let res = async {
+        let wrong = S3Client::new(&ctx.opt.endpoint, &ctx.opt.access_key, "not-the-secret");
+        let resp = wrong
+            .send(Method::GET, &ctx.bucket, &[], &[], Vec::new())
+            .await
+            .map_err(|err| format!("{:#}", err))?;
+        let status = resp.status().as_u16();
+        let body = resp.bytes().await.map_err(|err| err.to_string())?.to_vec();
+        Resp {
+            status,
+            headers: HeaderMap::new(),
+            body,
+        }
+        .expect_error(403, "SignatureDoesNotMatch")
+    };
+    report.record(
+        "errors",
+        "SignatureDoesNotMatch for a bad signature",
+        res.await,
+    );
+
+    let res = async {
+        ctx.call(Method::DELETE, &ctx.bucket, &[], &[], Vec::new())
+            .await?
+            .expect_error(409, "BucketNotEmpty")
+    };
+    report.record(
+        "errors",
+        "BucketNotEmpty when deleting a non-empty bucket",
+        res.await,
+    );
+}
+
+async fn check_etags(ctx: &Ctx, report: &mut CompatReport) -> Option<String> {
+    let put_etag = match ctx.put(ETAG_KEY, ETAG_BODY).await {
+        Ok(resp) => resp.header("etag"),
+        Err(_) => None,
+    };
+    report.record(
+        "etags",
+        "PutObject returns an ETag",
+        put_etag
+            .as_ref()
+            .map(|_| ())
+            .ok_or_else(|| "no ETag header".to_string()),
+    );
+    report.record(
+        "etags",
+        "single-part ETag is the quoted MD5",
+        expect_eq("ETag", put_etag.clone(), Some(quoted_md5(ETAG_BODY))),
+    );
+    let res = async {
+        let etag = put_etag.clone().ok_or("PutObject returned no ETag")?;
+        let resp = ctx
+            .get(&format!("{}/{}", ctx.bucket, ETAG_KEY), &[])
+            .await?;
+        expect_eq("ETag", resp.header("etag"), Some(etag))
+    };
+    report.record("etags", "GetObject ETag matches PutObject", res.await);
+    let res = async {
+        let etag = put_etag.clone().ok_or("PutObject returned no ETag")?;
+        let resp = ctx
+            .call(
+                Method::HEAD,
+                &format!("{}/{}", ctx.bucket, ETAG_KEY),
+                &[],
+                &[],
+                Vec::new(),
+            )
+            .await?;
+        expect_eq("ETag", resp.header("etag"), Some(etag))
+    };
+    report.record("etags", "HeadObject ETag matches PutObject", res.await);
+    put_etag
+}
+
+async fn check_listings(ctx: &Ctx, report: &mut CompatReport) {
+    for key in LIST_KEYS {
+        let _ = ctx.put(key, key.as_bytes()).await;
+    }
+    let all: Vec<String> = LIST_KEYS.iter().map(|k| k.to_string()).collect();
+
+    let res = async {
+        let resp = ctx.list(&[("prefix", "list/")]).await?;
+        resp.expect_status(200)?;
+        expect_eq("keys", xml_values(&resp.text(), "Key"), all.clone())
+    };
+    report.record("listings", "ListObjects (v1) with prefix", res.await);
+
+    let res = async {
+        let resp = ctx.list(&[("list-type", "2"), ("prefix", "list/")]).await?;
+        resp.expect_status(200)?;
+        let body = resp.text();
+        expect_eq("keys", xml_values(&body, "Key"), all.clone())?;
+        expect_eq(
+            "KeyCount",
+            xml_values(&body, "KeyCount"),
+            vec!["4".to_string()],
+        )
+    };
+    report.record("listings", "ListObjectsV2 with prefix", res.await);
+
+    let res = async {
+        let resp = ctx
+            .list(&[("list-type", "2"), ("prefix", "list/"), ("delimiter", "/")])
+            .await?;
+        resp.expect_status(200)?;
+        let body = resp.text();
+        expect_eq("keys", xml_values(&body, "Key"), all[..2].to_vec())?;
+        let prefixes: Vec<String> = xml_values(&body, "CommonPrefixes")
+            .iter()
+            .flat_map(|p| xml_values(p, "Prefix"))
+            .collect();
+        expect_eq("CommonPrefixes", prefixes, vec!["list/sub/".to_string()])
+    };
+    report.record("listings", "delimiter groups CommonPrefixes", res.await);
+
+    let res = async {
+        let resp = ctx
+            .list(&[("list-type", "2"), ("prefix", "list/"), ("max-keys", "2")])
+            .await?;
+        resp.expect_status(200)?;
+        let body = resp.text();
+        expect_eq(
+            "IsTruncated",
+            xml_values(&body, "IsTruncated"),
+            vec!["true".to_string()],
+        )?;
+        let mut keys = xml_values(&body, "Key");
+        let token = xml_values(&body, "NextContinuationToken")
+            .into_iter()
+            .next()
+            .ok_or_else(|| "no NextContinuationToken".to_string())?;
+        let resp = ctx
+            .list(&[
+                ("list-type", "2"),
+                ("prefix", "list/"),
+                ("max-keys", "2"),
+                ("continuation-token", &token),
+            ])
+            .await?;
+        keys.extend(xml_values(&resp.text(), "Key"));
+        expect_eq("keys across pages", keys, all.clone())
+    };
+    report.record(
+        "listings",
+        "max-keys pagination with continuation token",
+        res.await,
+    );
+}
+
+async fn check_conditional(ctx: &Ctx, report: &mut CompatReport, etag: Option<String>) {
+    let path = format!("{}/{}", ctx.bucket, ETAG_KEY);
+    let res = async {
+        let etag = etag
+            .clone()
+            .ok_or_else(|| "object has no ETag".to_string())?;
+        ctx.get(&path, &[("If-None-Match", &etag)])
+            .await?
+            .expect_status(304)
+    };
+    report.record(
+        "conditional",
+        "If-None-Match with current ETag -> 304",
+        res.await,
+    );
+
+    let res = async {
+        ctx.get(
+            &path,
+            &[("If-Match", "\"00000000000000000000000000000000\"")],
+        )
+        .await?
+        .expect_status(412)
+    };
+    report.record(
+        "conditional",
+        "If-Match with another ETag -> 412",
+        res.await,
+    );
+
+    let res = async {
+        let last_modified = ctx
+            .get(&path, &[])
+            .await?
+            .header("last-modified")
+            .ok_or_else(|| "no Last-Modified header".to_string())?;
+        ctx.get(&path, &[("If-Modified-Since", &last_modified)])
+            .await?
+            .expect_status(304)
+    };
+    report.record(
+        "conditional",
+        "If-Modified-Since Last-Modified -> 304",
+        res.await,
+    );
+
+    let res = async {
+        ctx.get(
+            &path,
+            &[("If-Unmodified-Since", "Sat, 01 Jan 2000 00:00:00 GMT")],
+        )
+        .await?
+        .expect_status(412)
+    };
+    report.record(
+        "conditional",
+        "If-Unmodified-Since in the past -> 412",
+        res.await,
+    );
+}
+
+async fn check_multipart(ctx: &Ctx, report: &mut CompatReport) {
+    let path = format!("{}/{}", ctx.bucket, MULTIPART_KEY);
+    let upload_id = async {
+        let resp = ctx
+            .call(Method::POST, &path, &[("uploads", "")], &[], Vec::new())
+            .await?;
+        resp.expect_status(200)?;
+        xml_values(&resp.text(), "UploadId")
+            .into_iter()
+            .next()
+            .ok_or_else(|| "no UploadId".to_string())
+    }
+    .await;
+    report.record(
+        "multipart",
+        "CreateMultipartUpload returns an UploadId",
+        upload_id.as_ref().map(|_| ()).map_err(String::clone),
+    );
+    let Ok(upload_id) = upload_id else {
+        return;
+    };
+
+    let parts = [vec![b'a'; PART_SIZE], b"tail".to_vec()];
+    let completed = async {
+        let mut complete = String::from("<CompleteMultipartUpload>");
+        for (i, part) in parts.iter().enumerate() {
+            let number = (i + 1).to_string();
+            let resp = ctx
+                .call(
+                    Method::PUT,
+                    &path,
+                    &[("partNumber", &number), ("uploadId", &upload_id)],
+                    &[],
+                    part.clone(),
+                )
+                .await?;
+            resp.expect_status(200)?;
+            complete.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                number,
+                resp.header("etag").unwrap_or_default()
+            ));
+        }
+        complete.push_str("</CompleteMultipartUpload>");
+        let resp = ctx
+            .call(
+                Method::POST,
+                &path,
+                &[("uploadId", &upload_id)],
+                &[],
+                complete.into_bytes(),
+            )
+            .await?;
+        resp.expect_status(200)?;
+        Ok(resp.text())
+    }
+    .await;
+    report.record(
+        "multipart",
+        "UploadPart and CompleteMultipartUpload",
+        completed.as_ref().map(|_| ()).map_err(String::clone),
+    );
+
+    let res = async {
+        let resp = ctx.get(&path, &[]).await?;
+        resp.expect_status(200)?;
+        if resp.body == parts.concat() {
+            Ok(())
+        } else {
+            Err(format!("content differs ({} bytes)", resp.body.len()))
+        }
+    };
+    report.record(
+        "multipart",
+        "completed object has the parts' content",
+        res.await,
+    );
+
+    let etag = completed
+        .ok()
+        .and_then(|body| xml_values(&body, "ETag").into_iter().next());
+    report.record(
+        "multipart",
+        "multipart ETag has a -<parts> suffix",
+        match etag {
+            Some(etag) if etag.trim_matches('"').ends_with("-2") => Ok(()),
+            other => Err(format!("got {:?}", other)),
+        },
+    );
+}
+
+// 清理自检创建的对象和桶
+async fn cleanup(ctx: &Ctx) {
+    let mut keys = vec![ETAG_KEY, MULTIPART_KEY];
+    keys.extend(LIST_KEYS);
+    for key in keys {
+        let _ = ctx
+            .call(
+                Method::DELETE,
+                &format!("{}/{}", ctx.bucket, key),
+                &[],
+                &[],
+                Vec::new(),
+            )
+            .await;
+    }
+    let _ = ctx
+        .call(Method::DELETE, &ctx.bucket, &[], &[], Vec::new())
+        .await;
+}
+
+pub async fn run(opt: CompatOpt) -> anyhow::Result<()> {
+    let client = S3Client::new(&opt.endpoint, &opt.access_key, &opt.secret_key);
+    let bucket = format!("compat-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let ctx = Ctx {
+        client,
+        opt: opt.clone(),
+        bucket,
+    };
+    let resp = ctx
+        .call(Method::PUT, &ctx.bucket, &[], &[], Vec::new())
+        .await
+        .map_err(anyhow::Error::msg)?;
+    if resp.status != 200 {
+        anyhow::bail!("创建测试桶 {} 失败: HTTP {}", ctx.bucket, resp.status);
+    }
+
+    let mut report = CompatReport::default();
+    let etag = check_etags(&ctx, &mut report).await;
+    check_listings(&ctx, &mut report).await;
+    check_conditional(&ctx, &mut report, etag).await;
+    check_multipart(&ctx, &mut report).await;
+    // 包含删除非空桶的检查，放在最后
+    check_errors(&ctx, &mut report).await;
+    cleanup(&ctx).await;
+
+    if opt.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let mut category = "";
+        for c in &report.checks {
+            if c.category != category {
+                category = c.category;
+                println!("{}", category);
+            }
+            let mark = match c.outcome {
+                Outcome::Pass => "PASS",
+                Outcome::Fail => "FAIL",
+            };
+            if c.detail.is_empty() {
+                println!("  {}  {}", mark, c.check);
+            } else {
+                println!("  {}  {} ({})", mark, c.check, c.detail);
+            }
+        }
+        println!("{}/{} checks passed", report.passed, report.total);
+    }
+    Ok(())
+}
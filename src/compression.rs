@@ -0,0 +1,173 @@
+use crate::config;
+use crate::pool;
+use log::info;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+// --- 自适应压缩级别：开启 --adaptive-compression 后每秒采样 CPU 使用率和后台线程池中
+// 排队的任务数，繁忙时降低 zstd 级别（负数级别以压缩率换取接近 lz4 的速度），
+// 空闲后逐级恢复到 --zstd-level。解压不受级别影响
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+// CPU 使用率达到该值，或排队的任务数不少于线程数时视为繁忙
+const BUSY_CPU: f64 = 0.85;
+// CPU 使用率低于该值且没有排队的任务时视为空闲
+const IDLE_CPU: f64 = 0.5;
+// 繁忙时每次降低的级别数，空闲时每次只恢复一级
+const DROP_STEP: i32 = 2;
+
+static LEVEL: AtomicI32 = AtomicI32::new(zstd::DEFAULT_COMPRESSION_LEVEL);
+
+// 当前使用的压缩级别
+pub(crate) fn level() -> i32 {
+    LEVEL.load(Ordering::Relaxed)
+}
+
+// 配置的级别为 0 时使用 zstd 的默认级别
+pub fn effective_level(level: i32) -> i32 {
+    if level == 0 {
+        zstd::DEFAULT_COMPRESSION_LEVEL
+    } else {
+        level
+    }
+}
+
+// 一次采样的负载
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Load {
+    // 整机 CPU 使用率，0 到 1
+    pub cpu: f64,
+    pub queued: usize,
+    pub threads: usize,
+}
+
+impl Load {
+    pub fn is_busy(&self) -> bool {
+        self.cpu >= BUSY_CPU || (self.threads > 0 && self.queued >= self.threads)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.cpu < IDLE_CPU && self.queued == 0
+    }
+}
+
+// 在 [min, max] 之间按负载调整压缩级别
+#[derive(Debug, Clone)]
+pub struct Controller {
+    max: i32,
+    min: i32,
+    current: i32,
+}
+
+impl Controller {
+    pub fn new(max: i32, min: i32) -> Self {
+        let (max, min) = (effective_level(max), effective_level(min));
+        Controller {
+            max,
+            min: min.min(max),
+            current: max,
+        }
+    }
+
+    pub fn current(&self) -> i32 {
+        self.current
+    }
+
+    // 按本次采样调整级别，返回调整后的级别；级别 0 在 zstd 中等同默认级别，跳过
+    pub fn update(&mut self, load: &Load) -> i32 {
+        if load.is_busy() {
+            let level = self.current - DROP_STEP;
+            let level = if level == 0 { -1 } else { level };
+            self.current = level.max(self.min);
+        } else if load.is_idle() {
+            let level = self.current + 1;
+            let level = if level == 0 { 1 } else { level };
+            self.current = level.min(self.max);
+        }
+        self.current
+    }
+}
+
+// /proc/stat 中累计的 CPU 时间
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    // 解析 /proc/stat 的第一行（"cpu user nice system idle iowait irq softirq steal ..."），
+    // idle 和 iowait 计为空闲
+    pub fn parse(line: &str) -> Option<CpuTimes> {
+        let mut fields = line.split_whitespace();
+        if fields.next()? != "cpu" {
+            return None;
+        }
+        let values: Vec<u64> = fields.map(|v| v.parse().ok()).collect::<Option<_>>()?;
+        if values.len() < 4 {
+            return None;
+        }
+        // guest 时间已计入 user，不重复累加
+        let total: u64 = values.iter().take(8).sum();
+        let idle = values[3] + values.get(4).copied().unwrap_or_default();
+        Some(CpuTimes {
+            busy: total - idle,
+            total,
+        })
+    }
+
+    // 两次采样之间的 CPU 使用率
+    pub fn usage_since(&self, earlier: &CpuTimes) -> f64 {
+        let total = self.total.saturating_sub(earlier.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.busy.saturating_sub(earlier.busy) as f64 / total as f64
+    }
+}
+
+// 读取不到 /proc/stat（非 Linux）时只按排队的任务数判断
+fn read_cpu_times() -> Option<CpuTimes> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    CpuTimes::parse(stat.lines().next()?)
+}
+
+pub(crate) fn spawn() {
+    let cfg = &config::get().compression;
+    LEVEL.store(effective_level(cfg.level), Ordering::Relaxed);
+    if !cfg.adaptive {
+        return;
+    }
+    let mut controller = Controller::new(cfg.level, cfg.min_level);
+    info!("自适应压缩级别：{} 到 {}", controller.min, controller.max);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut last = read_cpu_times();
+        loop {
+            interval.tick().await;
+            let now = read_cpu_times();
+            let cpu = match (&now, &last) {
+                (Some(now), Some(last)) => now.usage_since(last),
+                _ => 0.0,
+            };
+            last = now;
+            let load = Load {
+                cpu,
+                queued: pool::queued(),
+                threads: pool::threads(),
+            };
+            let previous = controller.current();
+            let level = controller.update(&load);
+            if level != previous {
+                info!(
+                    "压缩级别 {} -> {}（CPU {:.0}%，排队任务 {}）",
+                    previous,
+                    level,
+                    cpu * 100.0,
+                    load.queued
+                );
+                LEVEL.store(level, Ordering::Relaxed);
+            }
+        }
+    });
+}
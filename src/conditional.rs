@@ -0,0 +1,139 @@
+use crate::err::AppError;
+use crate::fs;
+use chrono::{DateTime, Utc};
+use ntex::http::{HeaderMap, StatusCode};
+
+// --- 条件请求：GET 和 HEAD 把 If-Match、If-Unmodified-Since、If-None-Match、If-Modified-Since
+// 与对象的 ETag 和 Last-Modified（精确到秒）比较，前置条件不满足时返回 412，未修改时返回 304。
+// 与 S3 一致，If-Match 满足时忽略 If-Unmodified-Since，带 If-None-Match 时忽略 If-Modified-Since，
+// 无法解析的日期忽略。PUT 和 DELETE 带 If-Match 时只在当前对象的 ETag 匹配时执行，带
+// If-None-Match: * 时只在对象不存在时执行；接收请求时先检查一次，应用日志时再检查，
+// 并发的条件写入只有一个成功。带 Range 的 GET 在 If-Range 与对象的 ETag（强比较）或
+// Last-Modified 一致时返回 206，不一致时忽略 Range 返回完整内容
+
+// 应用日志时对象已存在，返回给请求方的值
+pub(crate) const PRECONDITION_FAILED: &str = "PreconditionFailed";
+
+// 读取请求的条件判断结果
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Outcome {
+    Proceed,
+    NotModified,
+    PreconditionFailed,
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+// ETag 列表（逗号分隔，带引号，可为弱 ETag）是否包含 etag，* 匹配任意对象
+pub fn etag_matches(list: &str, etag: &str) -> bool {
+    list.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == etag)
+}
+
+// HTTP 日期，如 `Sat, 1 Jan 2000 00:00:00 GMT`
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+// 按条件头判断读取请求，etag 不含引号
+pub fn evaluate(headers: &HeaderMap, etag: &str, last_modified: DateTime<Utc>) -> Outcome {
+    let last_modified = last_modified.timestamp();
+    let since = |name: &str| header(headers, name).and_then(parse_http_date);
+    match header(headers, "If-Match") {
+        Some(list) if !etag_matches(list, etag) => return Outcome::PreconditionFailed,
+        Some(_) => {}
+        None => {
+            if since("If-Unmodified-Since").is_some_and(|since| last_modified > since.timestamp()) {
+                return Outcome::PreconditionFailed;
+            }
+        }
+    }
+    match header(headers, "If-None-Match") {
+        Some(list) if etag_matches(list, etag) => return Outcome::NotModified,
+        Some(_) => {}
+        None => {
+            if since("If-Modified-Since").is_some_and(|since| last_modified <= since.timestamp()) {
+                return Outcome::NotModified;
+            }
+        }
+    }
+    Outcome::Proceed
+}
+
+// If-Range 是否允许按 Range 返回部分内容：未携带时允许；ETag 按强比较（弱 ETag 不匹配），
+// 日期需与 Last-Modified（精确到秒）相同，无法解析时不匹配
+pub fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: DateTime<Utc>) -> bool {
+    match header(headers, "If-Range") {
+        None => true,
+        Some(value) if value.starts_with('"') => value.trim_matches('"') == etag,
+        Some(value) if value.starts_with("W/") => false,
+        Some(value) => {
+            parse_http_date(value).is_some_and(|date| date.timestamp() == last_modified.timestamp())
+        }
+    }
+}
+
+// PUT 和 DELETE 的 If-None-Match，只支持 *（对象不存在时才执行）
+pub fn create_only(headers: &HeaderMap) -> Result<bool, AppError> {
+    match header(headers, "If-None-Match") {
+        None => Ok(false),
+        Some("*") => Ok(true),
+        Some(_) => Err(AppError::s3(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "A header you provided implies functionality that is not implemented",
+        )),
+    }
+}
+
+// PUT 和 DELETE 的 If-Match（ETag 列表）
+pub fn if_match(headers: &HeaderMap) -> Option<String> {
+    header(headers, "If-Match").map(str::to_string)
+}
+
+// 写入或删除的前置条件是否满足：If-Match 要求对象存在且 ETag 匹配，If-None-Match: * 要求
+// 对象不存在；current 为当前对象的 ETag，对象不存在时为 None
+pub fn write_allowed(if_match: Option<&str>, if_none_match: bool, current: Option<&str>) -> bool {
+    if if_none_match && current.is_some() {
+        return false;
+    }
+    if_match.is_none_or(|list| current.is_some_and(|etag| etag_matches(list, etag)))
+}
+
+// 按元数据文件当前的对象判断写入或删除的前置条件，删除标记只在历史版本中，不算当前对象
+pub(crate) fn holds(meta_file_path: &str, if_match: Option<&str>, if_none_match: bool) -> bool {
+    if if_match.is_none() && !if_none_match {
+        return true;
+    }
+    let current = std::path::Path::new(meta_file_path).exists().then(|| {
+        // 无法读取的元数据只匹配 *
+        fs::load_metadata(meta_file_path)
+            .map(|metadata| metadata.etag)
+            .unwrap_or_default()
+    });
+    write_allowed(if_match, if_none_match, current.as_deref())
+}
+
+pub(crate) fn precondition_failed() -> AppError {
+    AppError::s3(
+        StatusCode::PRECONDITION_FAILED,
+        "PreconditionFailed",
+        "At least one of the pre-conditions you specified did not hold",
+    )
+}
+
+// 写入或删除请求的日志应用结果，前置条件不满足而未执行时返回 412
+pub(crate) fn check_applied(value: Option<&str>) -> Result<(), AppError> {
+    match value {
+        Some(PRECONDITION_FAILED) => Err(precondition_failed()),
+        _ => Ok(()),
+    }
+}
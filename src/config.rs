@@ -0,0 +1,902 @@
+use crate::codec::Codec;
+use crate::fs::{Backend, Chunker};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+pub(crate) static SERVER_CONFIG: OnceCell<ServerConfig> = OnceCell::const_new();
+static DEFAULT_CONFIG: LazyLock<ServerConfig> = LazyLock::new(ServerConfig::default);
+
+// 默认的分片大小
+pub const DEFAULT_CHUNK_SIZE: usize = 8 << 20;
+
+// 服务运行配置
+#[derive(Debug, Clone, Default)]
+pub struct ServerConfig {
+    // 按 "桶/键前缀" 选择存储后端，未命中时使用去重存储
+    pub storage_routes: Vec<StorageRoute>,
+    // 允许匿名读取（GET/HEAD）的 "桶/键前缀"，不含 '/' 时表示整个桶
+    pub public_prefixes: Vec<String>,
+    // 未要求加密的上传默认使用 SSE-S3 的桶，删除桶即销毁对象内容的数据密钥
+    pub encrypted_buckets: Vec<String>,
+    // 是否开放调试接口（如 /admin/debug/pprof/*，需编译对应特性）
+    pub debug_endpoints: bool,
+    // 在响应头中返回存储后端、分片去重命中数和各阶段耗时
+    pub debug_headers: bool,
+    // 写请求返回前是否等待数据和元数据 fsync 落盘
+    pub fsync: bool,
+    // fsync 组提交的等待窗口（毫秒），窗口内到达的写请求合并为一次同步
+    pub fsync_window_ms: u64,
+    // 顺序读取大对象时预读的分片数，为 0 时不预读
+    pub read_ahead_chunks: usize,
+    // 分片数据目录，为空时使用数据目录下的 file
+    pub chunk_roots: Vec<PathBuf>,
+    // 每个分片写入的数据目录数，大于 1 时读取失败会回退到镜像
+    pub chunk_replicas: usize,
+    // 纠删码的数据块数和校验块数，校验块数为 0 时不启用；启用后每个分片写入 k+m 个数据目录
+    pub erasure_data: usize,
+    pub erasure_parity: usize,
+    // 实验性的集群分片模式：分片按一致性哈希只保存在 ring_replicas 个节点上
+    pub cluster_ring: bool,
+    pub ring_replicas: usize,
+    // 作为备用节点时主节点的地址（如 http://10.0.0.1:9000），为空时不拉取日志
+    pub standby_of: Option<String>,
+    // 主节点持续无法访问超过该秒数时自动提升，为 0 时只能手动提升
+    pub standby_promote_after_secs: u64,
+    // CDC 订阅监听地址（如 127.0.0.1:7070），为空时不推送变更
+    pub cdc_addr: Option<String>,
+    // 管理接口的 JWT 认证
+    pub jwt: JwtConfig,
+    // 按访问密钥查询密钥和权限的认证回调地址，为空时只接受根密钥
+    pub auth_webhook: Option<String>,
+    // 认证回调结果的缓存秒数
+    pub auth_cache_secs: u64,
+    // 按桶禁止的操作，与访问密钥的权限无关
+    pub bucket_restrictions: Vec<BucketRestriction>,
+    // 整个实例关闭的接口组，请求返回 405 MethodNotAllowed
+    pub disabled_apis: Vec<ApiGroup>,
+    // statsd 指标推送
+    pub statsd: StatsdConfig,
+    // HTTP worker 数，0 表示与 CPU 核心数相同
+    pub http_workers: usize,
+    // 压缩、解压和分片读取的后台线程数，0 表示与 CPU 核心数相同
+    pub blocking_threads: usize,
+    // 把 HTTP worker 和后台线程绑定到 CPU 核心
+    pub pin_cpus: bool,
+    // 上传的请求体超过该字节数后写入临时文件，0 表示总是在内存中处理
+    pub spool_threshold: u64,
+    // 落盘请求体的临时目录，为空时使用数据目录下的 tmp/spool
+    pub spool_dir: Option<PathBuf>,
+    // HeadBucket 返回 x-rs3-object-count 和 x-rs3-bytes-used
+    pub bucket_usage_headers: bool,
+    // 按顺序调用的 WASM 插件
+    pub plugins: Vec<PluginConfig>,
+    // 对象事件脚本
+    pub scripts: Vec<ScriptConfig>,
+    // 上传内容扫描器，为空时不扫描
+    pub scanner: Option<Scanner>,
+    pub scan_action: ScanAction,
+    // 扫描器出错时仍接受上传
+    pub scan_fail_open: bool,
+    // 记录对象的读取次数和最后读取时间
+    pub access_tracking: bool,
+    // 超过指定天数未读取的对象自动删除
+    pub unread_expirations: Vec<UnreadExpiration>,
+    // 总耗时超过该值的请求记录慢请求日志，为空时不记录
+    pub slow_request_threshold: Option<Duration>,
+    // 数据目录空间或 inode 使用率的告警线，由容量接口报告
+    pub disk_watermarks: DiskWatermarks,
+    pub compression: CompressionConfig,
+    // 定期回收未被引用的分片的间隔小时数，为空时只能通过管理接口执行
+    pub gc_interval_hours: Option<u64>,
+    // 定期巡检分片完整性的间隔小时数，为空时只能通过管理接口执行
+    pub scrub_interval_hours: Option<u64>,
+    // 执行桶生命周期规则的间隔秒数，0 表示不执行
+    pub lifecycle_interval_secs: u64,
+    // 生命周期规则中一天的秒数，0 表示 86400；调小后可以在本地快速验证过期规则
+    pub lifecycle_day_secs: u64,
+    // 写入时分片已存在（去重命中）是否先校验已有的分片
+    pub dedup_verify: DedupVerify,
+    // 写入分片后立即从磁盘读回并校验哈希，校验通过后才确认写入
+    pub verify_writes: bool,
+    // 对象切分的分片字节数，0 表示 DEFAULT_CHUNK_SIZE；修改后相同内容得到不同的分片，
+    // 与修改前保存的对象不能去重
+    pub chunk_size: usize,
+    // 确定分片边界的方式，修改后同样不能与修改前保存的对象去重
+    pub chunking: Chunking,
+    // 带 Idempotency-Key 的写请求在该秒数内重试时返回第一次的响应，0 表示不处理
+    pub idempotency_window_secs: u64,
+    pub request_limits: RequestLimits,
+    // 主密钥的来源
+    pub master_key: MasterKeySource,
+    // 未配置主密钥时拒绝启动，而不是使用程序内置的密钥
+    pub secure: bool,
+    // 对象元数据不加密保存；读取时按头部区分明文和密文，切换前后写入的元数据都能读取
+    pub plain_metadata: bool,
+    // HTTPS 监听，为空时只提供 HTTP
+    pub tls: Option<TlsConfig>,
+    // 虚拟主机风格寻址的域名（如 s3.localhost），为空时只支持路径风格
+    pub vhost_domain: Option<String>,
+    // 应答 vhost_domain 及其子域名查询的 DNS 监听地址（UDP），为空时不启动
+    pub dns_addr: Option<String>,
+    // DNS 应答的地址，为空时使用 HTTP 监听的地址
+    pub dns_answer: Option<IpAddr>,
+}
+
+// 分片的压缩算法和 zstd 压缩级别
+#[derive(Debug, Clone, Default)]
+pub struct CompressionConfig {
+    // 默认的分片编码，上传请求可以另行指定
+    pub codec: Codec,
+    // 0 表示 zstd 的默认级别，负数级别更快但压缩率更低
+    pub level: i32,
+    // 按 CPU 使用率和排队的任务数在 min_level 和 level 之间调整
+    pub adaptive: bool,
+    pub min_level: i32,
+    // 超过该天数未读取的对象的分片在后台以 recompress_level 重新压缩，为空时不处理
+    pub recompress_after_days: Option<u64>,
+    pub recompress_level: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StatsdConfig {
+    // statsd 的 UDP 地址（如 127.0.0.1:8125），为空时不推送
+    pub addr: Option<String>,
+    // 指标名前缀
+    pub prefix: String,
+    // 使用 dogstatsd 标签（|#k:v），否则把标签值拼进指标名
+    pub tags: bool,
+    // 按 S3 接口（GetObject、PutObject 等）细分请求指标
+    pub api_labels: bool,
+    // 按桶细分请求指标时最多区分的桶数，之后出现的桶归入 _other；0 表示不按桶细分
+    pub max_buckets: usize,
+}
+
+// 管理接口的 JWT 认证：配置 OIDC 签发方、共享密钥或公钥文件中的任意一项即启用，
+// 令牌通过 Authorization: Bearer 携带，SigV4 签名仍然有效
+#[derive(Debug, Clone, Default)]
+pub struct JwtConfig {
+    // OIDC 签发方地址，从其 .well-known/openid-configuration 获取 JWKS，并校验 iss
+    pub oidc_issuer: Option<String>,
+    // HS256/384/512 共享密钥
+    pub secret: Option<String>,
+    // RSA 或 EC 公钥（PEM）文件
+    pub public_keys: Vec<PathBuf>,
+    // 令牌的 aud 需包含该值，为空时不校验
+    pub audience: Option<String>,
+    // 角色所在的声明，可用 '.' 表示嵌套（如 realm_access.roles）
+    pub role_claim: String,
+    // 拥有全部管理权限的角色
+    pub admin_role: String,
+    // 只能调用 GET/HEAD 管理接口的角色
+    pub readonly_role: String,
+}
+
+impl JwtConfig {
+    pub fn enabled(&self) -> bool {
+        self.oidc_issuer.is_some() || self.secret.is_some() || !self.public_keys.is_empty()
+    }
+}
+
+impl ServerConfig {
+    // 按最长前缀匹配对象路径（"桶/键"）对应的存储后端
+    pub fn backend_for(&self, object_path: &str) -> Backend {
+        self.storage_routes
+            .iter()
+            .filter(|r| object_path.starts_with(&r.prefix))
+            .max_by_key(|r| r.prefix.len())
+            .map(|r| r.backend)
+            .unwrap_or(Backend::Dedup)
+    }
+
+    // 生命周期规则中的一天
+    pub fn lifecycle_day(&self) -> chrono::Duration {
+        match self.lifecycle_day_secs {
+            0 => chrono::Duration::days(1),
+            secs => chrono::Duration::seconds(secs as i64),
+        }
+    }
+
+    // 对象切分的分片字节数
+    pub fn chunk_bytes(&self) -> usize {
+        match self.chunk_size {
+            0 => DEFAULT_CHUNK_SIZE,
+            size => size,
+        }
+    }
+
+    // 按配置切分对象的分片器
+    pub fn chunker(&self) -> Chunker {
+        Chunker::new(self.chunk_bytes(), self.chunking)
+    }
+
+    // 启用纠删码时返回（数据块数，校验块数）
+    pub fn erasure(&self) -> Option<(usize, usize)> {
+        (self.erasure_parity > 0).then_some((self.erasure_data, self.erasure_parity))
+    }
+
+    // 分片的候选数据目录，按优先级排列：首个为主副本，其后为镜像（纠删码时依次存放各块）；
+    // 未配置数据目录时为空
+    pub fn chunk_roots_for(&self, hash: &str) -> Vec<&Path> {
+        let n = self.chunk_roots.len();
+        if n == 0 {
+            return vec![];
+        }
+        let primary = hash
+            .get(..8)
+            .and_then(|h| u64::from_str_radix(h, 16).ok())
+            .unwrap_or_default() as usize
+            % n;
+        let count = match self.erasure() {
+            Some((k, m)) => k + m,
+            None => self.chunk_replicas,
+        };
+        (0..count.clamp(1, n))
+            .map(|i| self.chunk_roots[(primary + i) % n].as_path())
+            .collect()
+    }
+
+    // 桶是否禁止某类操作
+    pub fn is_denied(&self, bucket: &str, operation: RestrictedOperation) -> bool {
+        self.bucket_restrictions
+            .iter()
+            .any(|r| r.bucket == bucket && r.denied.contains(&operation))
+    }
+
+    // 请求所属的接口组中第一个被关闭的组
+    pub fn disabled_api(&self, groups: &[ApiGroup]) -> Option<ApiGroup> {
+        groups
+            .iter()
+            .find(|group| self.disabled_apis.contains(group))
+            .copied()
+    }
+
+    // 对象路径（"桶/键"）是否允许匿名读取
+    pub fn is_public(&self, object_path: &str) -> bool {
+        self.public_prefixes
+            .iter()
+            .any(|prefix| prefix_matches(prefix, object_path))
+    }
+
+    // 桶的上传是否默认加密
+    pub fn is_encrypted_bucket(&self, bucket: &str) -> bool {
+        self.encrypted_buckets.iter().any(|b| b == bucket)
+    }
+}
+
+// 对象路径（"桶/键"）是否匹配 "桶/键前缀"，前缀不含 '/' 时匹配整个桶
+pub fn prefix_matches(prefix: &str, object_path: &str) -> bool {
+    if prefix.contains('/') {
+        object_path.starts_with(prefix)
+    } else {
+        object_path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+// 解析 TOML 配置文件，返回（命令行参数名，参数值）列表。键为不带 `--` 的参数名，
+// '_' 与 '-' 等价；可重复的参数用数组，布尔值为 "true" 或 "false"
+pub fn parse_config_file(text: &str) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+    let table: toml::Table = text.parse()?;
+    let scalar = |key: &str, value: &toml::Value| match value {
+        toml::Value::String(value) => Ok(value.clone()),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        _ => Err(anyhow::anyhow!(
+            "config key `{}` must be a string, number, boolean or array of them",
+            key
+        )),
+    };
+    table
+        .iter()
+        .map(|(key, value)| {
+            let values = match value {
+                toml::Value::Array(values) => values
+                    .iter()
+                    .map(|value| scalar(key, value))
+                    .collect::<anyhow::Result<_>>()?,
+                value => vec![scalar(key, value)?],
+            };
+            Ok((key.replace('_', "-"), values))
+        })
+        .collect()
+}
+
+// 获取当前配置，未初始化时返回默认配置
+pub(crate) fn get() -> &'static ServerConfig {
+    SERVER_CONFIG.get().unwrap_or(&DEFAULT_CONFIG)
+}
+
+// 存储路由规则，命令行格式为 `<桶/键前缀>=<dedup|passthrough>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageRoute {
+    pub prefix: String,
+    pub backend: Backend,
+}
+
+impl FromStr for StorageRoute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, backend) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("invalid storage route `{}`, expected PREFIX=BACKEND", s))?;
+        Ok(StorageRoute {
+            prefix: prefix.trim_start_matches('/').to_string(),
+            backend: backend.parse()?,
+        })
+    }
+}
+
+// 可以按桶禁止的操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestrictedOperation {
+    // 删除对象（含重命名的源对象）或删除桶
+    Delete,
+    // 覆盖已存在的对象
+    Overwrite,
+    // 列出桶中的对象
+    List,
+}
+
+impl RestrictedOperation {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            RestrictedOperation::Delete => "删除",
+            RestrictedOperation::Overwrite => "覆盖已存在的对象",
+            RestrictedOperation::List => "列出对象",
+        }
+    }
+}
+
+impl FromStr for RestrictedOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delete" => Ok(RestrictedOperation::Delete),
+            "overwrite" => Ok(RestrictedOperation::Overwrite),
+            "list" => Ok(RestrictedOperation::List),
+            _ => Err(format!(
+                "unknown operation `{}`, expected delete, overwrite or list",
+                s
+            )),
+        }
+    }
+}
+
+// 桶的操作限制，命令行格式为 `<桶>=<操作>[,<操作>...]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketRestriction {
+    pub bucket: String,
+    pub denied: Vec<RestrictedOperation>,
+}
+
+impl FromStr for BucketRestriction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bucket, operations) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid bucket restriction `{}`, expected BUCKET=OPS", s))?;
+        Ok(BucketRestriction {
+            bucket: bucket.to_string(),
+            denied: operations
+                .split(',')
+                .map(|op| op.trim().parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+// 可以整体关闭的接口组
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiGroup {
+    // 删除对象、批量删除和重命名（删除源对象）
+    Delete,
+    CreateBucket,
+    DeleteBucket,
+    // 桶的 website、response-headers、versioning 配置和桶策略的读写
+    BucketConfig,
+    // 分片上传的全部接口
+    Multipart,
+    // 拷贝对象和拷贝分片
+    Copy,
+    // /admin 下的管理接口
+    Admin,
+}
+
+impl ApiGroup {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiGroup::Delete => "delete",
+            ApiGroup::CreateBucket => "create-bucket",
+            ApiGroup::DeleteBucket => "delete-bucket",
+            ApiGroup::BucketConfig => "bucket-config",
+            ApiGroup::Multipart => "multipart",
+            ApiGroup::Copy => "copy",
+            ApiGroup::Admin => "admin",
+        }
+    }
+
+    // 请求所属的接口组，copy 表示带 x-amz-copy-source；列出、读取和普通上传不属于任何组
+    pub fn of(method: &str, path: &str, query: &str, copy: bool) -> Vec<ApiGroup> {
+        if path == "/admin" || path.starts_with("/admin/") {
+            return vec![ApiGroup::Admin];
+        }
+        let Some(rest) = path.strip_prefix("/api") else {
+            return vec![];
+        };
+        let rest = rest.trim_start_matches('/');
+        if rest.is_empty() {
+            return vec![];
+        }
+        let has = |name: &str| {
+            query
+                .split('&')
+                .any(|param| param.split('=').next() == Some(name))
+        };
+        let mut groups = vec![];
+        if !rest.trim_end_matches('/').contains('/') {
+            if has("website")
+                || has("response-headers")
+                || has("versioning")
+                || has("policy")
+                || has("lifecycle")
+            {
+                groups.push(ApiGroup::BucketConfig);
+            } else {
+                match method {
+                    "PUT" => groups.push(ApiGroup::CreateBucket),
+                    "DELETE" => groups.push(ApiGroup::DeleteBucket),
+                    "POST" if has("delete") || has("rename") => groups.push(ApiGroup::Delete),
+                    _ => {}
+                }
+            }
+            return groups;
+        }
+        if has("uploads") || has("uploadId") {
+            groups.push(ApiGroup::Multipart);
+        } else if method == "DELETE" {
+            groups.push(ApiGroup::Delete);
+        }
+        if copy && method == "PUT" {
+            groups.push(ApiGroup::Copy);
+        }
+        groups
+    }
+}
+
+impl FromStr for ApiGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delete" => Ok(ApiGroup::Delete),
+            "create-bucket" => Ok(ApiGroup::CreateBucket),
+            "delete-bucket" => Ok(ApiGroup::DeleteBucket),
+            "bucket-config" => Ok(ApiGroup::BucketConfig),
+            "multipart" => Ok(ApiGroup::Multipart),
+            "copy" => Ok(ApiGroup::Copy),
+            "admin" => Ok(ApiGroup::Admin),
+            _ => Err(format!(
+                "unknown API group `{}`, expected delete, create-bucket, delete-bucket, \
+                 bucket-config, multipart, copy or admin",
+                s
+            )),
+        }
+    }
+}
+
+// HTTPS 监听和客户端证书认证
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    pub addr: String,
+    // 服务端证书链和私钥（PEM）
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    // 校验客户端证书的 CA 证书（PEM），为空时不请求客户端证书
+    pub client_ca: Option<PathBuf>,
+    // 拒绝没有客户端证书的连接
+    pub require_client_cert: bool,
+    // 客户端证书主题对应的访问密钥
+    pub cert_identities: Vec<CertIdentity>,
+    // 实验性的 HTTP/3（QUIC）监听地址（UDP），使用同一套证书，需编译 http3 特性
+    pub http3_addr: Option<String>,
+}
+
+// 客户端证书主题对应的访问密钥，命令行格式为 `<属性>=<值>[,<属性>=<值>...]=<访问密钥>`
+// （如 `CN=billing,O=Acme=billing-key`），证书主题包含全部属性时匹配
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertIdentity {
+    pub subject: Vec<(String, String)>,
+    pub access_key: String,
+}
+
+impl FromStr for CertIdentity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid certificate identity `{}`, expected ATTR=VALUE[,ATTR=VALUE...]=ACCESS_KEY",
+                s
+            )
+        };
+        let (subject, access_key) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let subject = subject
+            .split(',')
+            .map(|attr| {
+                let (name, value) = attr.split_once('=')?;
+                let name = name.trim();
+                (!name.is_empty()).then(|| (name.to_ascii_uppercase(), value.trim().to_string()))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let access_key = access_key.trim();
+        if access_key.is_empty() {
+            return Err(invalid());
+        }
+        Ok(CertIdentity {
+            subject,
+            access_key: access_key.to_string(),
+        })
+    }
+}
+
+// WASM 插件可以挂载的调用点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginHook {
+    // 上传对象前校验，可拒绝上传
+    PrePut,
+    // 下载对象时改写返回的内容
+    PostGet,
+    // 对象或桶变更后收到 CDC 格式的事件
+    Event,
+}
+
+impl PluginHook {
+    // 插件需要导出的函数名
+    pub fn export_name(&self) -> &'static str {
+        match self {
+            PluginHook::PrePut => "pre_put",
+            PluginHook::PostGet => "post_get",
+            PluginHook::Event => "on_event",
+        }
+    }
+}
+
+impl FromStr for PluginHook {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pre-put" => Ok(PluginHook::PrePut),
+            "post-get" => Ok(PluginHook::PostGet),
+            "event" => Ok(PluginHook::Event),
+            _ => Err(format!(
+                "unknown plugin hook `{}`, expected pre-put, post-get or event",
+                s
+            )),
+        }
+    }
+}
+
+// WASM 插件，命令行格式为 `<调用点>[,<调用点>...]=<模块路径>[@<桶/键前缀>]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginConfig {
+    pub hooks: Vec<PluginHook>,
+    // .wasm 或 .wat 模块
+    pub path: PathBuf,
+    // 只对匹配的 "桶/键前缀" 调用，为空时对全部对象调用
+    pub scope: Option<String>,
+}
+
+impl FromStr for PluginConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hooks, rest) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid plugin `{}`, expected HOOKS=PATH[@SCOPE]", s))?;
+        let (path, scope) = match rest.rsplit_once('@') {
+            Some((path, scope)) => (path, Some(scope.trim_start_matches('/').to_string())),
+            None => (rest, None),
+        };
+        Ok(PluginConfig {
+            hooks: hooks
+                .split(',')
+                .map(|hook| hook.trim().parse())
+                .collect::<Result<_, _>>()?,
+            path: PathBuf::from(path),
+            scope,
+        })
+    }
+}
+
+impl PluginConfig {
+    // 插件是否处理该对象路径（"桶/键"）
+    pub fn applies_to(&self, object_path: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_none_or(|scope| prefix_matches(scope, object_path))
+    }
+}
+
+// 事件脚本响应的对象事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptEvent {
+    // 对象新增或覆盖
+    Put,
+    Delete,
+}
+
+impl FromStr for ScriptEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "put" => Ok(ScriptEvent::Put),
+            "delete" => Ok(ScriptEvent::Delete),
+            _ => Err(format!(
+                "unknown script event `{}`, expected put or delete",
+                s
+            )),
+        }
+    }
+}
+
+// rhai 事件脚本，命令行格式为 `<事件>[,<事件>...]=<脚本路径>[@<桶/键前缀>]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptConfig {
+    pub events: Vec<ScriptEvent>,
+    // 定义了 on_event(event) 的 .rhai 脚本
+    pub path: PathBuf,
+    // 只对匹配的 "桶/键前缀" 执行，为空时对全部对象执行
+    pub scope: Option<String>,
+}
+
+impl FromStr for ScriptConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (events, rest) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid script `{}`, expected EVENTS=PATH[@SCOPE]", s))?;
+        let (path, scope) = match rest.rsplit_once('@') {
+            Some((path, scope)) => (path, Some(scope.trim_start_matches('/').to_string())),
+            None => (rest, None),
+        };
+        Ok(ScriptConfig {
+            events: events
+                .split(',')
+                .map(|event| event.trim().parse())
+                .collect::<Result<_, _>>()?,
+            path: PathBuf::from(path),
+            scope,
+        })
+    }
+}
+
+impl ScriptConfig {
+    // 脚本是否响应该对象路径（"桶/键"）上的事件
+    pub fn applies_to(&self, event: ScriptEvent, object_path: &str) -> bool {
+        self.events.contains(&event)
+            && self
+                .scope
+                .as_deref()
+                .is_none_or(|scope| prefix_matches(scope, object_path))
+    }
+}
+
+// 上传内容扫描器
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scanner {
+    // 由 sh -c 执行的命令，内容从标准输入传入；退出码 0 为正常，1 为感染
+    Command(String),
+    // ICAP 服务地址，如 icap://127.0.0.1:1344/avscan
+    Icap(String),
+}
+
+// 扫描出感染时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScanAction {
+    // 拒绝上传，不保存对象
+    #[default]
+    Reject,
+    // 保存对象并标记为已隔离，对象不可下载
+    Quarantine,
+}
+
+impl FromStr for ScanAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(ScanAction::Reject),
+            "quarantine" => Ok(ScanAction::Quarantine),
+            _ => Err(format!(
+                "unknown scan action `{}`, expected reject or quarantine",
+                s
+            )),
+        }
+    }
+}
+
+// 确定分片边界的方式，命令行格式为 fixed 或 cdc
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Chunking {
+    // 按固定的分片大小切分
+    #[default]
+    Fixed,
+    // 按内容确定边界（FastCDC），平均为分片大小；内容插入或删除后，其余位置的分片不变
+    ContentDefined,
+}
+
+impl FromStr for Chunking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Chunking::Fixed),
+            "cdc" => Ok(Chunking::ContentDefined),
+            _ => Err(format!("unknown chunking `{}`, expected fixed or cdc", s)),
+        }
+    }
+}
+
+// 去重命中时校验已有分片的方式，命令行格式为 never、always 或 sampled[:N]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DedupVerify {
+    // 直接引用已有的分片
+    #[default]
+    Never,
+    // 每次都读取、解压并校验哈希
+    Always,
+    // 每 N 次命中校验一次
+    Sampled(u32),
+}
+
+// sampled 未指定 N 时的抽样间隔
+const DEFAULT_DEDUP_SAMPLE: u32 = 100;
+
+impl FromStr for DedupVerify {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "never" => Ok(DedupVerify::Never),
+            None if s == "always" => Ok(DedupVerify::Always),
+            None if s == "sampled" => Ok(DedupVerify::Sampled(DEFAULT_DEDUP_SAMPLE)),
+            Some(("sampled", n)) => n
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(DedupVerify::Sampled)
+                .ok_or_else(|| format!("invalid sample interval `{}`", n)),
+            _ => Err(format!(
+                "unknown dedup verification `{}`, expected never, always or sampled[:N]",
+                s
+            )),
+        }
+    }
+}
+
+// 按最后读取时间过期的规则，命令行格式为 `<桶/键前缀>=<天数>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreadExpiration {
+    pub prefix: String,
+    pub days: u64,
+}
+
+impl FromStr for UnreadExpiration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, days) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("invalid expiration `{}`, expected PREFIX=DAYS", s))?;
+        let prefix = prefix.trim_start_matches('/');
+        if prefix.is_empty() {
+            return Err(format!(
+                "invalid expiration `{}`, the bucket is required",
+                s
+            ));
+        }
+        let days = days
+            .parse()
+            .ok()
+            .filter(|days| *days > 0)
+            .ok_or_else(|| format!("invalid number of days `{}`", days))?;
+        Ok(UnreadExpiration {
+            prefix: prefix.to_string(),
+            days,
+        })
+    }
+}
+
+// 主密钥的来源，内容均为 `编号:十六进制` 列表（见 kms）
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum MasterKeySource {
+    // 程序内置的密钥
+    #[default]
+    Builtin,
+    Inline(String),
+    File(PathBuf),
+    // 执行 shell 命令，标准输出为密钥列表
+    Command(String),
+}
+
+// 请求大小的上限，超过时返回与 S3 相同的错误，默认值与 S3 一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestLimits {
+    // 请求头的个数和总大小（名称、值和分隔符）；HTTP/1 解析器本身最多接受 96 个请求头，
+    // 超过时直接返回没有 S3 错误码的 400
+    pub max_header_count: usize,
+    pub max_header_size: usize,
+    // 请求 URI（路径和查询参数）的长度
+    pub max_url_length: usize,
+    // 对象键 UTF-8 编码后的字节数
+    pub max_key_length: usize,
+    // 用户自定义元数据（不含 x-amz-meta- 前缀）的总大小
+    pub max_metadata_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_header_count: 90,
+            max_header_size: 8 << 10,
+            max_url_length: 8 << 10,
+            max_key_length: 1024,
+            max_metadata_size: 2 << 10,
+        }
+    }
+}
+
+// 磁盘使用率的告警线（百分比），命令行格式为 `<warning>,<critical>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskWatermarks {
+    pub warning: u8,
+    pub critical: u8,
+}
+
+impl Default for DiskWatermarks {
+    fn default() -> Self {
+        DiskWatermarks {
+            warning: 85,
+            critical: 95,
+        }
+    }
+}
+
+impl FromStr for DiskWatermarks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (warning, critical) = s
+            .split_once(',')
+            .ok_or_else(|| format!("invalid watermarks `{}`, expected WARNING,CRITICAL", s))?;
+        let percent = |value: &str| {
+            value
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .ok_or_else(|| format!("invalid percentage `{}`", value))
+        };
+        let (warning, critical) = (percent(warning)?, percent(critical)?);
+        if warning > critical {
+            return Err(format!(
+                "invalid watermarks `{}`, the warning level exceeds the critical level",
+                s
+            ));
+        }
+        Ok(DiskWatermarks { warning, critical })
+    }
+}
@@ -0,0 +1,63 @@
+use crate::api;
+use crate::fs;
+use percent_encoding::percent_decode_str;
+use std::str::FromStr;
+
+// --- 服务端拷贝：PUT 请求带 x-amz-copy-source 时复制源对象的元数据，新对象引用相同的分片，
+// 不需要重新上传数据
+
+// 拷贝的源对象，键已解码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopySource {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl CopySource {
+    // "桶/键"
+    pub fn object_path(&self) -> String {
+        format!("{}/{}", self.bucket, self.key)
+    }
+
+    // 数据目录下的 "桶/键"
+    pub fn stored_path(&self) -> String {
+        format!("{}/{}", self.bucket, fs::stored_key(&self.key))
+    }
+}
+
+// 解析 x-amz-copy-source 请求头（URL 编码的 "[/]桶/键[?versionId=...]"），
+// 不支持多版本，忽略 versionId；桶或键为空、含 "." 或 ".." 时无效
+pub fn parse_source(header: &str) -> Option<CopySource> {
+    let path = header.split('?').next().unwrap_or_default();
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    let (bucket, key) = path.trim_start_matches('/').split_once('/')?;
+    let invalid = |part: &str| part.is_empty() || part == "." || part == "..";
+    // 键与请求路径中的键规则相同，另外不能有空路径段
+    if invalid(bucket) || !api::is_valid_key(key) || key.split('/').any(str::is_empty) {
+        return None;
+    }
+    Some(CopySource {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+    })
+}
+
+// x-amz-metadata-directive：COPY 沿用源对象的 Content-Type 和响应头，REPLACE 使用请求中的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataDirective {
+    #[default]
+    Copy,
+    Replace,
+}
+
+impl FromStr for MetadataDirective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "COPY" => Ok(MetadataDirective::Copy),
+            "REPLACE" => Ok(MetadataDirective::Replace),
+            _ => Err(format!("unknown metadata directive `{}`", s)),
+        }
+    }
+}
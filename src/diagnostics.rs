@@ -0,0 +1,180 @@
+use crate::compression;
+use crate::HandlerResponse;
+use log::warn;
+use ntex::web;
+use ntex::web::HttpResponse;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// --- 异步运行时诊断：每个 HTTP worker 一个延迟探针，检测阻塞事件循环的同步操作
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/runtime", web::get().to(runtime_stats));
+}
+
+// 探针唤醒间隔
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+// 唤醒延迟超过该值视为一次卡顿，并记录告警日志
+const STALL_THRESHOLD: Duration = Duration::from_millis(200);
+
+static PROBES: Mutex<Vec<Arc<WorkerProbe>>> = Mutex::new(Vec::new());
+
+// 单个 worker 的探针数据，时间均为微秒
+struct WorkerProbe {
+    name: String,
+    last_tick_us: AtomicU64,
+    last_lag_us: AtomicU64,
+    max_lag_us: AtomicU64,
+    total_lag_us: AtomicU64,
+    ticks: AtomicU64,
+    stalls: AtomicU64,
+}
+
+fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+// 在当前 worker 的事件循环上启动延迟探针，需在 worker 线程内调用
+pub fn spawn_lag_probe() {
+    let probe = {
+        let mut probes = PROBES.lock().unwrap();
+        let name = std::thread::current()
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("worker-{}", probes.len()));
+        let probe = Arc::new(WorkerProbe {
+            name,
+            last_tick_us: AtomicU64::new(unix_micros()),
+            last_lag_us: AtomicU64::new(0),
+            max_lag_us: AtomicU64::new(0),
+            total_lag_us: AtomicU64::new(0),
+            ticks: AtomicU64::new(0),
+            stalls: AtomicU64::new(0),
+        });
+        probes.push(probe.clone());
+        probe
+    };
+    ntex::rt::spawn(async move {
+        loop {
+            let start = Instant::now();
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            let lag = start.elapsed().saturating_sub(PROBE_INTERVAL);
+            let lag_us = lag.as_micros() as u64;
+            probe.last_tick_us.store(unix_micros(), Ordering::Relaxed);
+            probe.last_lag_us.store(lag_us, Ordering::Relaxed);
+            probe.max_lag_us.fetch_max(lag_us, Ordering::Relaxed);
+            probe.total_lag_us.fetch_add(lag_us, Ordering::Relaxed);
+            probe.ticks.fetch_add(1, Ordering::Relaxed);
+            if lag >= STALL_THRESHOLD {
+                probe.stalls.fetch_add(1, Ordering::Relaxed);
+                warn!("{} 事件循环被阻塞了 {:?}", probe.name, lag);
+            }
+        }
+    });
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerStats {
+    pub name: String,
+    // 探针上次被唤醒距今的时间，持续增长说明该 worker 此刻正被阻塞
+    pub since_last_tick_ms: u64,
+    pub blocked: bool,
+    pub last_lag_ms: f64,
+    pub max_lag_ms: f64,
+    pub mean_lag_ms: f64,
+    pub stalls: u64,
+}
+
+// 处理本次请求的 worker 所在 tokio 运行时的指标，需以 --cfg tokio_unstable 编译
+#[derive(Debug, Serialize)]
+pub struct TokioStats {
+    pub workers: usize,
+    pub active_tasks: usize,
+    pub blocking_threads: usize,
+    pub idle_blocking_threads: usize,
+    pub blocking_queue_depth: usize,
+    pub injection_queue_depth: usize,
+    pub polls: u64,
+    pub mean_poll_us: f64,
+    pub busy_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuntimeStats {
+    pub workers: Vec<WorkerStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokio: Option<TokioStats>,
+    // 当前的分片压缩级别，开启 --adaptive-compression 时随负载变化
+    pub compression_level: i32,
+}
+
+fn worker_stats(probe: &WorkerProbe, now_us: u64) -> WorkerStats {
+    let since_last_tick =
+        Duration::from_micros(now_us.saturating_sub(probe.last_tick_us.load(Ordering::Relaxed)));
+    let ticks = probe.ticks.load(Ordering::Relaxed);
+    let total_lag_us = probe.total_lag_us.load(Ordering::Relaxed);
+    WorkerStats {
+        name: probe.name.clone(),
+        since_last_tick_ms: since_last_tick.as_millis() as u64,
+        blocked: since_last_tick >= PROBE_INTERVAL + STALL_THRESHOLD,
+        last_lag_ms: probe.last_lag_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        max_lag_ms: probe.max_lag_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        mean_lag_ms: if ticks == 0 {
+            0.0
+        } else {
+            total_lag_us as f64 / ticks as f64 / 1000.0
+        },
+        stalls: probe.stalls.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(tokio_unstable)]
+fn tokio_stats() -> Option<TokioStats> {
+    let metrics = tokio::runtime::Handle::try_current().ok()?.metrics();
+    let workers = metrics.num_workers();
+    let polls = (0..workers).map(|w| metrics.worker_poll_count(w)).sum();
+    let busy: Duration = (0..workers)
+        .map(|w| metrics.worker_total_busy_duration(w))
+        .sum();
+    let mean_poll: Duration = (0..workers)
+        .map(|w| metrics.worker_mean_poll_time(w))
+        .sum::<Duration>()
+        / workers.max(1) as u32;
+    Some(TokioStats {
+        workers,
+        active_tasks: metrics.active_tasks_count(),
+        blocking_threads: metrics.num_blocking_threads(),
+        idle_blocking_threads: metrics.num_idle_blocking_threads(),
+        blocking_queue_depth: metrics.blocking_queue_depth(),
+        injection_queue_depth: metrics.injection_queue_depth(),
+        polls,
+        mean_poll_us: mean_poll.as_secs_f64() * 1e6,
+        busy_ms: busy.as_secs_f64() * 1e3,
+    })
+}
+
+#[cfg(not(tokio_unstable))]
+fn tokio_stats() -> Option<TokioStats> {
+    None
+}
+
+// 各 worker 的事件循环延迟，以及（tokio_unstable 下）当前运行时的任务和轮询指标
+pub async fn runtime_stats() -> HandlerResponse {
+    let now_us = unix_micros();
+    let workers = PROBES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|probe| worker_stats(probe, now_us))
+        .collect();
+    Ok(HttpResponse::Ok().json(&RuntimeStats {
+        workers,
+        tokio: tokio_stats(),
+        compression_level: compression::level(),
+    }))
+}
@@ -0,0 +1,143 @@
+use crate::config;
+use log::{info, warn};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::UdpSocket;
+
+// --- 本地测试用的 DNS 应答：配置 --dns-addr 后在该 UDP 地址上应答 --vhost-domain 及其子域名的
+// A/AAAA 查询，地址为 --dns-answer（默认为 --http-addr 的地址），其他名称返回 NXDOMAIN，不做递归。
+// 把系统或程序的 DNS 指向该地址即可使用虚拟主机风格寻址，如 `dig @127.0.0.1 -p 5353 photos.s3.localhost`
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const TTL_SECS: u32 = 60;
+
+const RCODE_FORMERR: u16 = 1;
+const RCODE_NXDOMAIN: u16 = 3;
+const RCODE_NOTIMP: u16 = 4;
+
+// 响应、权威应答和期望递归的标志位
+const FLAG_QR: u16 = 0x8000;
+const FLAG_AA: u16 = 0x0400;
+const FLAG_RD: u16 = 0x0100;
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]))
+}
+
+// 只含一个问题的查询，返回小写的名称、类型、类别和问题的结束位置；不支持名称压缩
+fn parse_question(query: &[u8]) -> Option<(String, u16, u16, usize)> {
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *query.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        if len > 63 {
+            return None;
+        }
+        let label = query.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+    let qtype = read_u16(query, pos)?;
+    let qclass = read_u16(query, pos + 2)?;
+    Some((labels.join("."), qtype, qclass, pos + 4))
+}
+
+fn header(id: &[u8], flags: u16, qdcount: u16, ancount: u16) -> Vec<u8> {
+    let mut resp = id.to_vec();
+    for value in [flags, qdcount, ancount, 0, 0] {
+        resp.extend_from_slice(&value.to_be_bytes());
+    }
+    resp
+}
+
+// 名称是否为 domain 或其子域名
+pub fn in_domain(name: &str, domain: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    name == domain || name.ends_with(&format!(".{}", domain))
+}
+
+// 生成查询的响应，收到的是响应或不足一个报文头时不回复
+pub fn respond(query: &[u8], domain: &str, answer: IpAddr) -> Option<Vec<u8>> {
+    let id = query.get(..2)?;
+    let flags = read_u16(query, 2)?;
+    let qdcount = read_u16(query, 4)?;
+    if flags & FLAG_QR != 0 {
+        return None;
+    }
+    // 保留操作码和期望递归标志
+    let flags = FLAG_QR | FLAG_AA | (flags & (0x7800 | FLAG_RD));
+    if flags & 0x7800 != 0 {
+        return Some(header(id, flags | RCODE_NOTIMP, 0, 0));
+    }
+    let question = match parse_question(query) {
+        Some(question) if qdcount == 1 => question,
+        _ => return Some(header(id, flags | RCODE_FORMERR, 0, 0)),
+    };
+    let (name, qtype, qclass, end) = question;
+    if !in_domain(&name, domain) {
+        let mut resp = header(id, flags | RCODE_NXDOMAIN, 1, 0);
+        resp.extend_from_slice(&query[12..end]);
+        return Some(resp);
+    }
+    let (rtype, rdata) = match answer {
+        IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+        IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+    };
+    let answered = qclass == CLASS_IN && (qtype == rtype || qtype == TYPE_ANY);
+    let mut resp = header(id, flags, 1, answered as u16);
+    resp.extend_from_slice(&query[12..end]);
+    if answered {
+        // 名称指向问题中的名称（偏移 12）
+        resp.extend_from_slice(&0xc00cu16.to_be_bytes());
+        resp.extend_from_slice(&rtype.to_be_bytes());
+        resp.extend_from_slice(&CLASS_IN.to_be_bytes());
+        resp.extend_from_slice(&TTL_SECS.to_be_bytes());
+        resp.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        resp.extend_from_slice(&rdata);
+    }
+    Some(resp)
+}
+
+// 未配置 --dns-answer 时应答的地址：HTTP 监听的地址，监听所有地址或无法解析时为回环地址
+pub fn default_answer(http_addr: &str) -> IpAddr {
+    match http_addr.parse::<SocketAddr>() {
+        Ok(addr) if !addr.ip().is_unspecified() => addr.ip(),
+        Ok(SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    }
+}
+
+// 配置了 --dns-addr 时启动 DNS 应答
+pub(crate) async fn spawn(http_addr: &str) -> std::io::Result<()> {
+    let cfg = config::get();
+    let (Some(addr), Some(domain)) = (cfg.dns_addr.clone(), cfg.vhost_domain.clone()) else {
+        return Ok(());
+    };
+    let answer = cfg.dns_answer.unwrap_or_else(|| default_answer(http_addr));
+    let socket = UdpSocket::bind(&addr).await?;
+    info!("DNS 应答地址 {}，*.{} -> {}", addr, domain, answer);
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        loop {
+            match socket.recv_from(&mut buf).await {
+                Ok((len, peer)) => {
+                    let Some(resp) = respond(&buf[..len], &domain, answer) else {
+                        continue;
+                    };
+                    if let Err(err) = socket.send_to(&resp, peer).await {
+                        warn!("发送 DNS 响应失败: {}", err);
+                    }
+                }
+                Err(err) => warn!("接收 DNS 查询失败: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use crate::config;
+use anyhow::anyhow;
+use log::warn;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+// --- fsync 组提交：状态机写入的文件先登记，由后台线程在一个短窗口内合并同步，
+// 写请求在返回客户端前等待自己登记的文件落盘
+
+// 没有等待者时，后台线程也按该间隔同步已登记的文件（如从节点的写入）
+const IDLE_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Pending {
+    paths: Vec<PathBuf>,
+    // 后台线程是否正在同步已取走的文件
+    syncing: bool,
+}
+
+static PENDING: Mutex<Pending> = Mutex::new(Pending {
+    paths: Vec::new(),
+    syncing: false,
+});
+static SYNCER: OnceLock<mpsc::Sender<oneshot::Sender<Result<(), String>>>> = OnceLock::new();
+
+// 登记需要落盘的文件，未开启 fsync 时忽略
+pub(crate) fn enqueue(path: impl Into<PathBuf>) {
+    if config::get().fsync {
+        PENDING.lock().unwrap().paths.push(path.into());
+    }
+}
+
+// 等待此前登记的文件全部落盘，并发调用会合并为一次同步
+pub(crate) async fn flush() -> anyhow::Result<()> {
+    if !config::get().fsync {
+        return Ok(());
+    }
+    {
+        let pending = PENDING.lock().unwrap();
+        if pending.paths.is_empty() && !pending.syncing {
+            return Ok(());
+        }
+    }
+    let (tx, rx) = oneshot::channel();
+    syncer().send(tx).map_err(|_| anyhow!("同步线程已退出"))?;
+    rx.await
+        .map_err(|_| anyhow!("同步线程已退出"))?
+        .map_err(|err| anyhow!("同步文件失败: {}", err))
+}
+
+fn syncer() -> &'static mpsc::Sender<oneshot::Sender<Result<(), String>>> {
+    SYNCER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        let window = Duration::from_millis(config::get().fsync_window_ms);
+        std::thread::Builder::new()
+            .name("fsync".to_string())
+            .spawn(move || sync_loop(rx, window))
+            .expect("启动同步线程失败");
+        tx
+    })
+}
+
+fn sync_loop(rx: mpsc::Receiver<oneshot::Sender<Result<(), String>>>, window: Duration) {
+    loop {
+        let mut waiters = Vec::new();
+        match rx.recv_timeout(IDLE_SYNC_INTERVAL) {
+            Ok(waiter) => waiters.push(waiter),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+        // 第一个等待者到达后再等一个窗口，收集同一批的其他请求
+        if !waiters.is_empty() {
+            let deadline = Instant::now() + window;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                match rx.recv_timeout(left) {
+                    Ok(waiter) => waiters.push(waiter),
+                    Err(_) => break,
+                }
+            }
+        }
+        let paths = {
+            let mut pending = PENDING.lock().unwrap();
+            pending.syncing = true;
+            std::mem::take(&mut pending.paths)
+        };
+        let res = sync_paths(paths).map_err(|err| err.to_string());
+        PENDING.lock().unwrap().syncing = false;
+        if let Err(err) = &res {
+            warn!("同步文件失败: {}", err);
+        }
+        for waiter in waiters {
+            let _ = waiter.send(res.clone());
+        }
+    }
+}
+
+// 同步文件内容以及所在目录（新建或重命名的目录项）；已被删除的文件跳过
+fn sync_paths(paths: Vec<PathBuf>) -> std::io::Result<()> {
+    let files: BTreeSet<PathBuf> = paths.into_iter().collect();
+    let mut dirs = BTreeSet::new();
+    for path in &files {
+        match File::open(path) {
+            Ok(file) => file.sync_all()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+        if let Some(parent) = path.parent() {
+            dirs.insert(parent.to_path_buf());
+        }
+    }
+    for dir in dirs {
+        match File::open(&dir) {
+            Ok(dir) => dir.sync_all()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
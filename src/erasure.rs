@@ -0,0 +1,272 @@
+use crate::config;
+use crate::durability;
+use crate::fs::{get_sha256, hash_relative_path};
+use log::{info, warn};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// --- 纠删码存储：压缩后的分片切成 k 个数据块并计算 m 个校验块，分别写入不同的数据目录，
+// 任意 m 个目录丢失时仍可还原。块按序号依次放在分片的候选目录中，数据目录的顺序不能改变
+
+// 块文件头：块序号(1) + 分片原始长度(8) + 前两者与块内容的 sha256(32)
+const HEADER_LEN: usize = 41;
+// 块文件的扩展名，与副本存储的分片文件区分
+const SHARD_EXTENSION: &str = "ec";
+// 后台重建扫描的间隔
+const REBUILD_INTERVAL: Duration = Duration::from_secs(3600);
+
+// 将数据切分为 data_shards 个等长的数据块（末尾补零），并追加 parity_shards 个校验块
+pub fn encode_shards(
+    data: &[u8],
+    data_shards: usize,
+    parity_shards: usize,
+) -> io::Result<Vec<Vec<u8>>> {
+    let rs = ReedSolomon::new(data_shards, parity_shards).map_err(io::Error::other)?;
+    let shard_len = data.len().div_ceil(data_shards).max(1);
+    let mut shards: Vec<Vec<u8>> = data
+        .chunks(shard_len)
+        .map(|chunk| {
+            let mut shard = chunk.to_vec();
+            shard.resize(shard_len, 0);
+            shard
+        })
+        .collect();
+    shards.resize(data_shards + parity_shards, vec![0; shard_len]);
+    rs.encode(&mut shards).map_err(io::Error::other)?;
+    Ok(shards)
+}
+
+// 由至少 data_shards 个完好的块还原原始数据，缺失的块为 None
+pub fn decode_shards(
+    mut shards: Vec<Option<Vec<u8>>>,
+    data_shards: usize,
+    parity_shards: usize,
+    len: usize,
+) -> io::Result<Vec<u8>> {
+    let rs = ReedSolomon::new(data_shards, parity_shards).map_err(io::Error::other)?;
+    rs.reconstruct_data(&mut shards).map_err(io::Error::other)?;
+    Ok(join_shards(&shards, data_shards, len))
+}
+
+fn join_shards(shards: &[Option<Vec<u8>>], data_shards: usize, len: usize) -> Vec<u8> {
+    let mut data: Vec<u8> = shards[..data_shards]
+        .iter()
+        .flat_map(|shard| shard.as_deref().unwrap_or_default())
+        .copied()
+        .collect();
+    data.truncate(len);
+    data
+}
+
+fn shard_checksum(head: &[u8], shard: &[u8]) -> Vec<u8> {
+    get_sha256(&[head, shard].concat())
+}
+
+fn shard_file(index: usize, len: u64, shard: &[u8]) -> Vec<u8> {
+    let mut head = vec![index as u8];
+    head.extend_from_slice(&len.to_le_bytes());
+    let checksum = shard_checksum(&head, shard);
+    [head.as_slice(), &checksum, shard].concat()
+}
+
+// 解析块文件，返回（序号，分片原始长度，块内容），校验失败时返回 None
+fn parse_shard_file(mut bytes: Vec<u8>) -> Option<(usize, u64, Vec<u8>)> {
+    if bytes.len() < HEADER_LEN {
+        return None;
+    }
+    let shard = bytes.split_off(HEADER_LEN);
+    if shard_checksum(&bytes[..9], &shard) != bytes[9..] {
+        return None;
+    }
+    let len = u64::from_le_bytes(bytes[1..9].try_into().ok()?);
+    Some((bytes[0] as usize, len, shard))
+}
+
+// 分片各块的保存路径，第 i 个块位于第 i 个候选目录
+pub(crate) fn shard_paths(hash: &str) -> Vec<PathBuf> {
+    let relative = hash_relative_path(hash).with_extension(SHARD_EXTENSION);
+    config::get()
+        .chunk_roots_for(hash)
+        .iter()
+        .map(|root| root.join(&relative))
+        .collect()
+}
+
+// 块文件的头部和校验和是否完整
+pub(crate) fn is_shard_file_intact(bytes: Vec<u8>) -> bool {
+    parse_shard_file(bytes).is_some()
+}
+
+fn write_shard(path: &Path, content: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, content)?;
+    durability::enqueue(path);
+    Ok(())
+}
+
+// 编码并写入分片所有缺失的块
+pub(crate) fn save(hash: &str, data: &[u8]) -> io::Result<()> {
+    let Some((k, m)) = config::get().erasure() else {
+        return Err(io::Error::other("未启用纠删码"));
+    };
+    let paths = shard_paths(hash);
+    if paths.iter().all(|p| p.exists()) {
+        return Ok(());
+    }
+    let shards = encode_shards(data, k, m)?;
+    for (i, (path, shard)) in paths.iter().zip(&shards).enumerate() {
+        if !path.exists() {
+            write_shard(path, &shard_file(i, data.len() as u64, shard))?;
+        }
+    }
+    Ok(())
+}
+
+// 读取分片（压缩后的内容）；有块缺失或损坏时用其余的块还原，并重写这些块
+pub(crate) fn read(hash: &str) -> io::Result<Vec<u8>> {
+    let Some((k, m)) = config::get().erasure() else {
+        return Err(io::Error::other("未启用纠删码"));
+    };
+    let paths = shard_paths(hash);
+    let mut shards: Vec<Option<Vec<u8>>> = vec![None; k + m];
+    let mut len = 0;
+    for path in &paths {
+        match fs::read(path).map(parse_shard_file) {
+            Ok(Some((i, l, shard))) if i < k + m => {
+                shards[i] = Some(shard);
+                len = l as usize;
+            }
+            Ok(_) => warn!("纠删码块 {:?} 校验失败", path),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => warn!("读取纠删码块 {:?} 失败: {}", path, err),
+        }
+    }
+    let missing: Vec<usize> = (0..k + m).filter(|&i| shards[i].is_none()).collect();
+    if missing.is_empty() {
+        return Ok(join_shards(&shards, k, len));
+    }
+    if missing.len() > m {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "分片 {} 只剩 {} 个可用的纠删码块",
+                hash,
+                k + m - missing.len()
+            ),
+        ));
+    }
+    let rs = ReedSolomon::new(k, m).map_err(io::Error::other)?;
+    rs.reconstruct(&mut shards).map_err(io::Error::other)?;
+    warn!("分片 {} 缺少 {} 个纠删码块，已重建", hash, missing.len());
+    for i in missing {
+        let content = shard_file(i, len as u64, shards[i].as_deref().unwrap());
+        if let Err(err) = write_shard(&paths[i], &content) {
+            warn!("重写纠删码块 {:?} 失败: {}", paths[i], err);
+        }
+    }
+    Ok(join_shards(&shards, k, len))
+}
+
+// 分片的块是否都已写入
+pub(crate) fn is_stored(hash: &str) -> bool {
+    shard_paths(hash).iter().all(|p| p.exists())
+}
+
+// 存在的块是否足以还原分片
+pub(crate) fn is_readable(hash: &str) -> bool {
+    let k = config::get().erasure().map_or(1, |(k, _)| k);
+    shard_paths(hash).iter().filter(|p| p.exists()).count() >= k
+}
+
+// 从任一块的文件头读取分片压缩后的长度
+pub(crate) fn stored_len(hash: &str) -> io::Result<u64> {
+    let mut last_err = io::Error::from(io::ErrorKind::NotFound);
+    for path in shard_paths(hash) {
+        let mut head = [0u8; 9];
+        match fs::File::open(&path).and_then(|mut f| f.read_exact(&mut head)) {
+            Ok(()) => return Ok(u64::from_le_bytes(head[1..].try_into().unwrap())),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+// 分片所有块占用的磁盘空间
+pub(crate) fn disk_usage(hash: &str) -> u64 {
+    shard_paths(hash)
+        .iter()
+        .filter_map(|p| fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+// 启动后台重建线程：定期扫描各数据目录，为缺块的分片补齐纠删码块（如更换硬盘后）
+pub(crate) fn spawn_rebuild() {
+    if config::get().erasure().is_none() {
+        return;
+    }
+    std::thread::Builder::new()
+        .name("ec-rebuild".to_string())
+        .spawn(|| loop {
+            rebuild_all();
+            std::thread::sleep(REBUILD_INTERVAL);
+        })
+        .expect("启动纠删码重建线程失败");
+}
+
+fn rebuild_all() {
+    let mut hashes = BTreeSet::new();
+    for root in &config::get().chunk_roots {
+        collect_hashes(root, &mut hashes);
+    }
+    let (mut rebuilt, mut lost) = (0, 0);
+    for hash in hashes {
+        if is_stored(&hash) {
+            continue;
+        }
+        match read(&hash) {
+            Ok(_) => rebuilt += 1,
+            Err(err) => {
+                lost += 1;
+                warn!("重建分片 {} 失败: {}", hash, err);
+            }
+        }
+    }
+    if rebuilt + lost > 0 {
+        info!(
+            "纠删码重建完成：重建 {} 个分片，{} 个分片无法恢复",
+            rebuilt, lost
+        );
+    }
+}
+
+fn dir_entries(dir: &Path) -> impl Iterator<Item = fs::DirEntry> {
+    fs::read_dir(dir).into_iter().flatten().flatten()
+}
+
+// 按 <h0>/<h1h2>/<rest>.ec 的目录结构收集数据目录下的分片哈希
+fn collect_hashes(root: &Path, out: &mut BTreeSet<String>) {
+    for a in dir_entries(root) {
+        for b in dir_entries(&a.path()) {
+            for c in dir_entries(&b.path()) {
+                let path = c.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(SHARD_EXTENSION) {
+                    continue;
+                }
+                let Some(rest) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                out.insert(format!(
+                    "{}{}{}",
+                    a.file_name().to_string_lossy(),
+                    b.file_name().to_string_lossy(),
+                    rest
+                ));
+            }
+        }
+    }
+}
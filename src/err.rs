@@ -1,4 +1,7 @@
+use crate::model::ErrorResp;
+use ntex::http::StatusCode;
 use ntex::web;
+use ntex::web::HttpResponse;
 use thiserror::Error;
 
 // 自定义错误类型
@@ -11,6 +14,56 @@ pub enum AppError {
     NotFound,
     #[error("bad request")]
     BadRequest,
+    // S3 协议错误，以标准的 <Error> XML 返回
+    #[error("{code}: {message}")]
+    S3Error {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+    },
 }
 
-impl web::error::WebResponseError for AppError {}
+impl AppError {
+    pub fn s3(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        AppError::S3Error {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    // 经由 anyhow 传递的 S3 错误（如读取请求体时的 IncompleteBody）按原错误返回
+    fn unwrap_s3(&self) -> &AppError {
+        match self {
+            AppError::Anyhow(err) => err.downcast_ref::<AppError>().unwrap_or(self),
+            _ => self,
+        }
+    }
+}
+
+impl web::error::WebResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self.unwrap_s3() {
+            AppError::S3Error { status, .. } => *status,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self, _: &web::HttpRequest) -> HttpResponse {
+        match self.unwrap_s3() {
+            AppError::S3Error { code, message, .. } => {
+                let resp = ErrorResp {
+                    code: code.to_string(),
+                    message: message.clone(),
+                };
+                let xml = quick_xml::se::to_string(&resp).unwrap_or_default();
+                HttpResponse::build(self.status_code())
+                    .content_type("application/xml")
+                    .body(xml)
+            }
+            _ => HttpResponse::build(self.status_code())
+                .content_type("text/plain; charset=utf-8")
+                .body(self.to_string()),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crypto_hash::{hex_digest, Algorithm, Hasher};
+use std::io::Write;
+
+// --- ETag：与 S3 一致，普通上传为请求体的 MD5，分片上传为各分片 MD5（二进制）拼接后的
+// MD5 加 "-分片数"。保存在元数据中（不含引号），PUT、GET、HEAD 和列表中原样返回，
+// 同步工具据此判断对象是否需要重新上传
+
+// 请求体的 MD5（小写十六进制）
+pub fn md5_hex(data: &[u8]) -> String {
+    hex_digest(Algorithm::MD5, data)
+}
+
+// 分片上传对象的 ETag；分片 ETag 可带引号，不是十六进制时按原文参与计算
+pub fn multipart_etag<S: AsRef<str>>(part_etags: &[S]) -> String {
+    let mut digests = Vec::with_capacity(part_etags.len() * 16);
+    for etag in part_etags {
+        let etag = etag.as_ref().trim_matches('"');
+        match hex::decode(etag) {
+            Ok(digest) => digests.extend_from_slice(&digest),
+            Err(_) => digests.extend_from_slice(etag.as_bytes()),
+        }
+    }
+    format!("{}-{}", md5_hex(&digests), part_etags.len())
+}
+
+// 响应头和 XML 中的 ETag 带双引号
+pub fn quote(etag: &str) -> String {
+    format!("\"{}\"", etag)
+}
+
+// 边接收边计算请求体的 MD5
+pub(crate) struct Md5(Hasher);
+
+impl Md5 {
+    pub(crate) fn new() -> Self {
+        Md5(Hasher::new(Algorithm::MD5))
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.write_all(data).expect("计算 MD5 失败");
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        hex::encode(self.0.finish())
+    }
+}
@@ -1,18 +1,65 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::cluster;
+use crate::codec::{self, Codec};
+use crate::config;
+use crate::config::{Chunking, DedupVerify};
+use crate::durability;
+use crate::erasure;
+use crate::gc;
+use crate::kms;
+use crate::pool;
+use crate::rollback::{self, FailPoint, WrittenFiles};
+use crate::slowlog;
+use crate::slowlog::Phase;
 use crate::util::cry;
 use anyhow::Context;
 use chrono::{DateTime, Utc};
-use futures::Stream;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{Future, Stream, StreamExt};
 use hex::ToHex;
+use log::warn;
 use memmap2::{Mmap, MmapOptions};
-use ntex::util::{Bytes, BytesMut};
+use ntex::util::Bytes;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use rkyv::{Archive, Deserialize, Infallible, Serialize};
 use sha2::{Digest, Sha256};
-use std::fs::{self, File};
-use std::io::{self, Cursor, Read};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use tokio::fs::OpenOptions;
-use zstd::stream::read::Decoder;
-use zstd::zstd_safe::WriteBuf;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::oneshot;
+
+// 对象数据的存储后端
+#[derive(Archive, Deserialize, Serialize, Debug, PartialEq, Clone, Copy, Default)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub enum Backend {
+    // 分片、压缩并按sha256去重保存
+    #[default]
+    Dedup,
+    // 按原始内容直接保存为文件
+    Passthrough,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dedup" => Ok(Backend::Dedup),
+            "passthrough" => Ok(Backend::Passthrough),
+            _ => Err(format!("unknown storage backend `{}`", s)),
+        }
+    }
+}
 
 // 定义元数据结构
 #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
@@ -24,23 +71,455 @@ pub struct Metadata {
     pub file_type: String,
     pub time: DateTime<Utc>,
     pub chunks: Vec<String>,
+    // 每个分片解压后的大小，与 chunks 一一对应，范围读取据此直接定位到分片
+    pub chunk_sizes: Vec<u64>,
+    pub backend: Backend,
+    // x-amz-website-redirect-location，静态网站模式下访问该对象时重定向
+    pub website_redirect: Option<String>,
+    // 对象级响应头（如 Cache-Control），覆盖桶的默认响应头
+    pub headers: Vec<ResponseHeader>,
+    // 上传时的内容扫描结果，未配置扫描器时为空
+    pub scan: Option<ScanStatus>,
+    // ETag（不含引号），进行中的分片上传的元数据为空
+    pub etag: String,
+    // 对象内容的 SHA-256（base64），分片上传的对象为组合校验和
+    pub checksum_sha256: Option<String>,
+    // 开启版本控制后写入的版本号，未开启或暂停时为空（null 版本）
+    pub version_id: Option<String>,
+    // 删除标记，只出现在历史版本中
+    pub delete_marker: bool,
+    // 服务端加密，为空时分片保存的是明文
+    pub encryption: Option<Encryption>,
+    // 对象标签
+    pub tags: Vec<Tag>,
+}
+
+// 加入格式版本头部之前（最初版本）的元数据，只用于读取旧数据
+#[derive(Archive, Deserialize)]
+#[archive(check_bytes)]
+struct LegacyMetadata {
+    name: String,
+    size: u64,
+    file_type: String,
+    time: DateTime<Utc>,
+    chunks: Vec<String>,
+}
+
+impl From<LegacyMetadata> for Metadata {
+    fn from(legacy: LegacyMetadata) -> Self {
+        // 旧数据没有保存 ETag，以分片列表的 MD5 代替，内容不变时保持不变
+        let etag = cry::encrypt_by_md5(&legacy.chunks.concat());
+        Metadata {
+            name: legacy.name,
+            size: legacy.size,
+            file_type: legacy.file_type,
+            time: legacy.time,
+            chunks: legacy.chunks,
+            chunk_sizes: vec![],
+            backend: Backend::Dedup,
+            website_redirect: None,
+            headers: vec![],
+            scan: None,
+            etag,
+            checksum_sha256: None,
+            version_id: None,
+            delete_marker: false,
+            encryption: None,
+            tags: vec![],
+        }
+    }
+}
+
+// 对象级响应头
+#[derive(
+    Archive, Deserialize, Serialize, serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub struct ResponseHeader {
+    pub name: String,
+    pub value: String,
+}
+
+// 对象标签
+#[derive(
+    Archive, Deserialize, Serialize, serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+}
+
+impl Metadata {
+    // 扫描发现感染而隔离的对象不可下载
+    pub fn is_quarantined(&self) -> bool {
+        self.scan
+            .as_ref()
+            .is_some_and(|scan| scan.verdict == ScanVerdict::Infected)
+    }
+}
+
+// 上传内容的扫描结论
+#[derive(
+    Archive,
+    Deserialize,
+    Serialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    PartialEq,
+    Clone,
+    Copy,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub enum ScanVerdict {
+    Clean,
+    // 已隔离，不可下载
+    Infected,
+    // 扫描器出错，按 --scan-fail-open 放行
+    Failed,
+}
+
+impl ScanVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanVerdict::Clean => "clean",
+            ScanVerdict::Infected => "infected",
+            ScanVerdict::Failed => "error",
+        }
+    }
+}
+
+#[derive(
+    Archive, Deserialize, Serialize, serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub struct ScanStatus {
+    pub verdict: ScanVerdict,
+    // 病毒名或扫描器的错误信息
+    pub signature: Option<String>,
+    pub time: DateTime<Utc>,
+}
+
+// 服务端加密的方式
+#[derive(
+    Archive,
+    Deserialize,
+    Serialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    PartialEq,
+    Clone,
+    Copy,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub enum SseAlgorithm {
+    // SSE-S3，服务端生成的数据密钥
+    S3,
+    // SSE-C，客户提供的密钥
+    Customer,
 }
 
-// 定义元数据存储路径前缀
+// 对象的服务端加密参数，见 sse 模块
+#[derive(
+    Archive, Deserialize, Serialize, serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone,
+)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub struct Encryption {
+    pub algorithm: SseAlgorithm,
+    // SSE-S3 的数据密钥，SSE-C 时为空；raft 日志中为用主密钥加密后的密钥
+    pub key: Vec<u8>,
+    // 每个对象随机生成的计数器前缀
+    pub nonce: Vec<u8>,
+    // SSE-C 客户密钥以 nonce 为密钥的 HMAC-SHA256，用于校验读取时提供的密钥
+    pub key_hmac: Vec<u8>,
+    // SSE-C 客户密钥的 MD5（base64），在响应头中返回
+    pub customer_key_md5: Option<String>,
+    // 分片上传的对象每个分片的分片号；为空时所有分片依次组成一段连续的密文
+    pub parts: Vec<u32>,
+    // 与 parts 一一对应，分片在所属上传分片中的偏移；为空时均为 0（每个上传分片只有一个分片）
+    #[serde(default)]
+    pub part_offsets: Vec<u64>,
+}
+
+// 旧版本（相对工作目录）的分片目录，数据目录未初始化时同样使用
 const PATH_PREFIX: &str = "data/file";
+// 直通存储的文件目录
+const RAW_PATH_SUFFIX: &str = "raw";
+// 暂存上传的元数据目录
+const STAGING_PATH_SUFFIX: &str = "staging";
+// 桶数据密钥文件名
+const BUCKET_KEY_FILE: &str = ".bucket.key";
+// 桶创建时间文件名
+const BUCKET_CREATED_FILE: &str = ".bucket.created";
+// 明文元数据的头部，之后为 rkyv 序列化的内容；密文的 IV 是可打印字符，主密钥密文的头部为
+// \0RSK，都不会与之混淆
+const PLAIN_METADATA_MAGIC: &[u8] = b"\0RSP";
 
-// 将sh256值解析为hash路径
-pub(crate) fn path_from_hash(hash: &str) -> PathBuf {
+// 序列化的元数据（加密前）的头部：魔数和 1 字节的格式版本，之后为 rkyv 序列化的内容。
+// 没有头部的是最初版本的格式
+const METADATA_MAGIC: &[u8] = b"\0RSM";
+// 当前的元数据格式版本，Metadata 增删字段时递增，并保留旧版本的解码
+const METADATA_VERSION: u8 = 1;
+
+// 去重命中的次数，--dedup-verify sampled 按此抽样
+static DEDUP_HITS: AtomicU64 = AtomicU64::new(0);
+// 垃圾回收删除分片文件与写入时的去重判断互斥
+pub(crate) static CHUNK_GC_LOCK: Mutex<()> = Mutex::new(());
+
+// 请求路径中对象键的 URL 编码方式，与 S3 客户端一致
+const KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// 对象按请求路径中 URL 编码后的键保存，请求体或请求头中给出的键需要同样编码后才能找到对象
+pub fn stored_key(key: &str) -> String {
+    utf8_percent_encode(key, KEY_ENCODE_SET).to_string()
+}
+
+// 由元数据路径解析出对象路径（"桶/键"）
+pub(crate) fn object_path_from_meta(meta_file_path: impl AsRef<Path>) -> Option<String> {
+    let buckets_dir = PathBuf::from(DATA_DIR.get()?).join(BASIC_PATH_SUFFIX);
+    let object_path = meta_file_path.as_ref().strip_prefix(buckets_dir).ok()?;
+    let object_path = object_path.to_string_lossy();
+    Some(object_path.strip_suffix(".meta")?.to_string())
+}
+
+// 暂存批次的根目录，每个批次一个子目录
+pub(crate) fn staging_root() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(STAGING_PATH_SUFFIX)
+}
+
+// 暂存批次中某个桶的元数据目录
+pub(crate) fn staging_dir(staging_id: &str, bucket: &str) -> PathBuf {
+    staging_root().join(staging_id).join(bucket)
+}
+
+// 暂存批次提交清单的目录，批次id不含 '.'，不会与批次目录重名
+pub(crate) const STAGING_COMMIT_SUFFIX: &str = ".commit";
+
+// 暂存批次中某个桶的提交清单
+pub(crate) fn staging_commit_manifest(staging_id: &str, bucket: &str) -> PathBuf {
+    staging_root()
+        .join(format!("{}{}", staging_id, STAGING_COMMIT_SUFFIX))
+        .join(bucket)
+}
+
+// 临时目录，存放进行中的分片上传，默认也存放落盘的请求体
+pub(crate) fn tmp_root() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join("tmp")
+}
+
+// 分片上传的临时目录，每个已上传的分片对应一个以分片号命名的记录文件
+pub(crate) fn upload_parts_dir(upload_id: &str) -> PathBuf {
+    tmp_root().join(upload_id)
+}
+
+// 分片上传的临时元数据，完成上传时写入对象的元数据
+pub(crate) fn upload_meta_path(bucket: &str, object_key: &str, upload_id: &str) -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket)
+        .join(format!("{}.meta.{}", object_key, upload_id))
+}
+
+// 递归收集目录下的全部元数据文件
+pub(crate) fn walk_meta_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            walk_meta_files(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "meta") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+// 桶内的对象数和对象大小之和，无法读取的元数据按 0 字节计算
+pub(crate) fn bucket_usage(bucket_path: &Path) -> io::Result<(u64, u64)> {
+    let mut meta_files = Vec::new();
+    walk_meta_files(bucket_path, &mut meta_files)?;
+    let bytes = meta_files
+        .iter()
+        .filter_map(|path| load_metadata(path).ok())
+        .map(|metadata| metadata.size)
+        .sum();
+    Ok((meta_files.len() as u64, bytes))
+}
+
+// 自底向上删除空目录
+pub(crate) fn remove_empty_dirs(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_empty_dirs(&entry.path())?;
+        }
+    }
+    if fs::read_dir(dir)?.next().is_none() {
+        fs::remove_dir(dir)?;
+    }
+    Ok(())
+}
+
+// 直通存储的对象文件路径
+pub(crate) fn raw_path(object_path: &str) -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap())
+        .join(RAW_PATH_SUFFIX)
+        .join(object_path)
+}
+
+// 分片在数据目录下的相对路径
+pub(crate) fn hash_relative_path(hash: &str) -> PathBuf {
     let hash_prefix = &hash[0..1];
     let hash_subprefix = &hash[1..3];
     let hash_suffix = &hash[3..];
 
-    PathBuf::from(PATH_PREFIX)
-        .join(hash_prefix)
+    PathBuf::from(hash_prefix)
         .join(hash_subprefix)
         .join(hash_suffix)
 }
 
+// 未配置 --chunk-root 时的分片目录：数据目录（<fs-root>/data）下的 file
+fn default_chunk_root() -> PathBuf {
+    DATA_DIR.get().map_or_else(
+        || PathBuf::from(PATH_PREFIX),
+        |dir| Path::new(dir).join("file"),
+    )
+}
+
+// 旧版本未配置 --chunk-root 时分片保存在工作目录下的 data/file，--fs-root 不是工作目录时
+// 启动时移到数据目录下的 file，返回移动的分片数。目标已有同名分片时保留旧文件不动
+pub(crate) fn migrate_legacy_chunks() -> anyhow::Result<usize> {
+    let legacy = PathBuf::from(PATH_PREFIX);
+    let target = default_chunk_root();
+    if !config::get().chunk_roots.is_empty() || !legacy.is_dir() {
+        return Ok(0);
+    }
+    if target.exists() && fs::canonicalize(&legacy)? == fs::canonicalize(&target)? {
+        return Ok(0);
+    }
+    let mut moved = 0;
+    move_chunk_tree(&legacy, &target, &mut moved)?;
+    let _ = remove_empty_dirs(&legacy);
+    Ok(moved)
+}
+
+fn move_chunk_tree(from: &Path, to: &Path, moved: &mut usize) -> anyhow::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (src, dest) = (entry.path(), to.join(entry.file_name()));
+        if entry.file_type()?.is_dir() {
+            move_chunk_tree(&src, &dest, moved)?;
+            continue;
+        }
+        if dest.exists() {
+            continue;
+        }
+        // 跨文件系统时先复制到临时文件再发布
+        if fs::rename(&src, &dest).is_err() {
+            let tmp = tmp_sibling(&dest);
+            fs::copy(&src, &tmp).with_context(|| format!("复制分片 {:?} 失败", src))?;
+            publish_file(&tmp, &dest)?;
+            fs::remove_file(&src)?;
+        }
+        *moved += 1;
+    }
+    Ok(())
+}
+
+// 分片在各数据目录中的保存路径，首个为主副本，其后为镜像
+pub(crate) fn chunk_paths(hash: &str) -> Vec<PathBuf> {
+    let relative = hash_relative_path(hash);
+    let roots = config::get().chunk_roots_for(hash);
+    if roots.is_empty() {
+        return vec![default_chunk_root().join(relative)];
+    }
+    roots.iter().map(|root| root.join(&relative)).collect()
+}
+
+// 将sh256值解析为hash路径：首个存在的副本，都不存在时为主副本
+pub(crate) fn path_from_hash(hash: &str) -> PathBuf {
+    let mut paths = chunk_paths(hash);
+    if paths.len() > 1 {
+        if let Some(i) = paths.iter().position(|p| p.exists()) {
+            return paths.swap_remove(i);
+        }
+    }
+    paths.swap_remove(0)
+}
+
+// 读取并解码分片，集群模式下本地没有时从其他节点读取
+fn read_chunk_with<T>(hash: &str, decode: impl Fn(Vec<u8>) -> io::Result<T>) -> io::Result<T> {
+    match read_local_chunk_with(hash, &decode) {
+        Err(_) if cluster::enabled() => cluster::fetch_chunk(hash).and_then(decode),
+        res => res,
+    }
+}
+
+// 依次尝试各副本读取并解码分片，主副本失败时回退到镜像，并用成功的副本修复之前失败的副本
+fn read_local_chunk_with<T>(
+    hash: &str,
+    decode: impl Fn(Vec<u8>) -> io::Result<T>,
+) -> io::Result<T> {
+    if config::get().erasure().is_some() {
+        return slowlog::time(Phase::Disk, || erasure::read(hash)).and_then(decode);
+    }
+    let paths = chunk_paths(hash);
+    let mut last_err = None;
+    for (i, path) in paths.iter().enumerate() {
+        match slowlog::time(Phase::Disk, || fs::read(path)).and_then(&decode) {
+            Ok(res) => {
+                if i > 0 {
+                    warn!("分片 {} 的主副本不可用，已从镜像 {:?} 读取", hash, path);
+                    repair_chunk(path, &paths[..i]);
+                }
+                return Ok(res);
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap())
+}
+
+// 用完好的副本覆盖损坏或缺失的副本，失败时只记录日志
+pub(crate) fn repair_chunk(good: &Path, broken: &[PathBuf]) {
+    for path in broken {
+        let res = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::copy(good, path));
+        match res {
+            Ok(_) => durability::enqueue(path),
+            Err(err) => warn!("修复分片副本 {:?} 失败: {}", path, err),
+        }
+    }
+}
+
+// 读取分片文件的原始内容（编码后的分片，见 codec）
+pub(crate) fn read_chunk(hash: &str) -> io::Result<Vec<u8>> {
+    read_chunk_with(hash, Ok)
+}
+
+// 只读取本节点保存的分片（编码后的分片）
+pub(crate) fn read_local_chunk(hash: &str) -> io::Result<Vec<u8>> {
+    read_local_chunk_with(hash, Ok)
+}
+
+// 读取并解压分片，解压失败同样视为该副本损坏
+pub(crate) fn read_chunk_decompressed(hash: &str) -> io::Result<Vec<u8>> {
+    read_chunk_with(hash, |data| decompress_bytes(&data))
+}
+
 #[allow(dead_code)]
 async fn mmap_read_file(p: impl AsRef<Path>) -> io::Result<Vec<u8>> {
     let file = tokio::fs::File::open(p).await?;
@@ -63,24 +542,128 @@ async fn mmap_write_file(p: impl AsRef<Path>, content: &[u8]) -> io::Result<()>
     Ok(())
 }
 
-// 保存文件
-pub(crate) async fn save_file(hash_code: &str, data: &[u8]) -> anyhow::Result<()> {
-    let file_path = path_from_hash(hash_code);
-    tokio::fs::create_dir_all(file_path.parent().unwrap()).await?;
-    mmap_write_file(file_path, data).await?;
+// 保存文件，写入所有缺失的副本；开启 --verify-writes 时读回校验，失败的文件重写一次。
+// 新建的文件登记到 written，由调用方在写入失败时回滚
+pub(crate) async fn save_file(
+    hash_code: &str,
+    data: &[u8],
+    written: &mut WrittenFiles,
+) -> anyhow::Result<()> {
+    if !cluster::is_local(hash_code) {
+        return Ok(());
+    }
+    let start = Instant::now();
+    write_chunk_files(hash_code, data, written).await?;
+    if config::get().verify_writes {
+        rollback::check(FailPoint::ChunkVerify)?;
+        let broken = read_back_broken_files(hash_code).await?;
+        if !broken.is_empty() {
+            warn!(
+                "分片 {} 读回校验失败，重新写入 {} 个文件",
+                hash_code,
+                broken.len()
+            );
+            for path in &broken {
+                match tokio::fs::remove_file(path).await {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+            }
+            write_chunk_files(hash_code, data, written).await?;
+            if !read_back_broken_files(hash_code).await?.is_empty() {
+                anyhow::bail!("分片 {} 重新写入后读回校验仍然失败", hash_code);
+            }
+        }
+    }
+    slowlog::record(Phase::Disk, start.elapsed());
     Ok(())
 }
 
+async fn write_chunk_files(
+    hash_code: &str,
+    data: &[u8],
+    written: &mut WrittenFiles,
+) -> anyhow::Result<()> {
+    if config::get().erasure().is_some() {
+        // 纠删码只写入缺失的块，先登记再写入
+        for path in erasure::shard_paths(hash_code) {
+            written.track(&path);
+        }
+        let hash_code = hash_code.to_string();
+        let data = data.to_vec();
+        tokio::task::spawn_blocking(move || erasure::save(&hash_code, &data)).await??;
+    } else {
+        for file_path in chunk_paths(hash_code) {
+            if !written.track(&file_path) {
+                continue;
+            }
+            tokio::fs::create_dir_all(file_path.parent().unwrap()).await?;
+            mmap_write_file(&file_path, data).await?;
+            durability::enqueue(file_path);
+        }
+    }
+    rollback::check(FailPoint::ChunkWrite)
+}
+
+// 读回分片的所有副本（纠删码时为所有块），返回内容与哈希不一致的文件
+async fn read_back_broken_files(hash_code: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let hash_code = hash_code.to_string();
+    let broken = tokio::task::spawn_blocking(move || {
+        if config::get().erasure().is_none() {
+            return chunk_paths(&hash_code)
+                .into_iter()
+                .filter(|path| !is_intact_chunk(&hash_code, read_uncached(path)))
+                .collect();
+        }
+        let paths = erasure::shard_paths(&hash_code);
+        let broken: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| !read_uncached(path).is_ok_and(erasure::is_shard_file_intact))
+            .cloned()
+            .collect();
+        // 每个块都完整时还原整个分片校验，编码出错时重写所有块
+        if broken.is_empty() && !is_intact_chunk(&hash_code, erasure::read(&hash_code)) {
+            return paths;
+        }
+        broken
+    })
+    .await?;
+    Ok(broken)
+}
+
+// 先落盘再丢弃页缓存后读取文件，读到的是磁盘上的内容而不是刚写入的缓存
+pub(crate) fn read_uncached(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.sync_all()?;
+    #[cfg(target_os = "linux")]
+    unsafe {
+        use std::os::fd::AsRawFd;
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+    let mut content = vec![];
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+// 分片文件能解压且内容与哈希一致
+pub(crate) fn is_intact_chunk(hash: &str, chunk: io::Result<Vec<u8>>) -> bool {
+    chunk
+        .and_then(|chunk| decompress_bytes(&chunk))
+        .is_ok_and(|data| get_sha256_string(&get_sha256(&data)) == hash)
+}
+
 // 获取sha256值
-fn get_sha256(data: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    let result = hasher.finalize();
-    result.to_vec()
+pub(crate) fn get_sha256(data: &[u8]) -> Vec<u8> {
+    slowlog::time(Phase::Hash, || {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        let result = hasher.finalize();
+        result.to_vec()
+    })
 }
 
 // 获取sha256字符串
-fn get_sha256_string(hash: &[u8]) -> String {
+pub(crate) fn get_sha256_string(hash: &[u8]) -> String {
     let hash_string: String = hash.encode_hex();
     hash_string.to_uppercase()
 }
@@ -92,50 +675,311 @@ pub(crate) async fn sum_sha256(data: &[u8]) -> String {
 }
 
 // 压缩分片
-pub(crate) fn compress_chunk(mut reader: impl std::io::Read) -> anyhow::Result<Vec<u8>> {
-    let mut res = Vec::new();
-    zstd::stream::copy_encode(&mut reader, &mut res, 0)?;
-    Ok(res)
+pub(crate) fn compress_chunk(data: &[u8], codec: Codec) -> anyhow::Result<Vec<u8>> {
+    Ok(codec::encode(data, codec)?)
 }
 
-// 解压分片
-fn decompress_chunk(chunk_path: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
-    let file = File::open(chunk_path)?;
-    let chunk_file = unsafe { Mmap::map(&file)? };
-    let mut decoder = Decoder::new(&chunk_file[..])?;
-    let mut result = Vec::new();
-    decoder.read_to_end(&mut result)?;
-    Ok(result)
+// 直通存储保存文件：先写入临时文件并登记，元数据保存后由调用方重命名为正式文件，
+// 失败时原文件不受影响
+pub(crate) async fn save_raw_file(
+    path: impl AsRef<Path>,
+    data: &[u8],
+    written: &mut WrittenFiles,
+) -> anyhow::Result<PathBuf> {
+    let start = Instant::now();
+    let tmp = tmp_sibling(path.as_ref());
+    tokio::fs::create_dir_all(path.as_ref().parent().unwrap()).await?;
+    written.track(&tmp);
+    tokio::fs::write(&tmp, data).await?;
+    rollback::check(FailPoint::ChunkWrite)?;
+    slowlog::record(Phase::Disk, start.elapsed());
+    Ok(tmp)
+}
+
+// 同目录下的临时文件，重命名后替换 path
+pub(crate) fn tmp_sibling(path: &Path) -> PathBuf {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+    PathBuf::from(format!(
+        "{}.tmp-{}-{}",
+        path.to_string_lossy(),
+        std::process::id(),
+        seq
+    ))
+}
+
+// 将临时文件重命名为正式文件
+pub(crate) fn publish_file(tmp: &Path, path: &Path) -> anyhow::Result<()> {
+    fs::rename(tmp, path).context("发布写入的文件失败")?;
+    durability::enqueue(path);
+    Ok(())
+}
+
+// 读取直通存储的文件流
+pub(crate) async fn raw_file_stream(
+    path: impl AsRef<Path>,
+) -> io::Result<impl Stream<Item = io::Result<Bytes>>> {
+    let file = tokio::fs::File::open(path).await?;
+    Ok(reader_stream(file))
+}
+
+// 从 start 开始读取原样保存的文件中的 len 个字节
+pub(crate) async fn raw_file_range_stream(
+    path: impl AsRef<Path>,
+    start: u64,
+    len: u64,
+) -> io::Result<impl Stream<Item = io::Result<Bytes>>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(io::SeekFrom::Start(start)).await?;
+    Ok(reader_stream(file.take(len)))
+}
+
+fn reader_stream(
+    reader: impl tokio::io::AsyncRead + Unpin,
+) -> impl Stream<Item = io::Result<Bytes>> {
+    futures::stream::try_unfold(reader, |mut reader| async move {
+        let mut buf = vec![0; 64 << 10];
+        let start = Instant::now();
+        let read = reader.read(&mut buf).await?;
+        slowlog::record(Phase::Disk, start.elapsed());
+        if read == 0 {
+            Ok(None)
+        } else {
+            buf.truncate(read);
+            Ok(Some((Bytes::from(buf), reader)))
+        }
+    })
+}
+
+// 解压内存中的分片数据
+fn decompress_bytes(data: &[u8]) -> io::Result<Vec<u8>> {
+    codec::decode(data)
+}
+
+// 桶级配置文件路径，与对象元数据一起保存在桶目录下
+pub(crate) fn bucket_config_path(bucket: &str, file_name: &str) -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket)
+        .join(file_name)
+}
+
+// 由元数据路径解析出所属桶的目录
+fn bucket_dir_from_meta(meta_file_path: &Path) -> Option<PathBuf> {
+    let buckets_dir = PathBuf::from(DATA_DIR.get()?).join(BASIC_PATH_SUFFIX);
+    let bucket = meta_file_path
+        .strip_prefix(&buckets_dir)
+        .ok()?
+        .components()
+        .next()?;
+    Some(buckets_dir.join(bucket))
+}
+
+// 桶的数据密钥文件
+pub(crate) fn bucket_key_path(bucket_dir: impl AsRef<Path>) -> PathBuf {
+    bucket_dir.as_ref().join(BUCKET_KEY_FILE)
+}
+
+// 为桶生成数据密钥，以主密钥加密后保存在桶目录中；删除桶即销毁密钥。
+// 只用于加密桶内的元数据，分片在桶之间去重，不用桶的密钥加密；--encrypted-bucket 的桶按 SSE-S3
+// 加密分片，数据密钥只保存在元数据中，删除桶同样销毁对象内容
+pub(crate) fn create_bucket_key(bucket_dir: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
+    let key = cry::gen_data_key();
+    let wrapped = kms::encrypt(&key)?;
+    fs::create_dir_all(&bucket_dir)?;
+    fs::write(bucket_key_path(&bucket_dir), wrapped)?;
+    durability::enqueue(bucket_key_path(&bucket_dir));
+    Ok(key)
+}
+
+// 记录桶的创建时间
+pub(crate) fn save_bucket_created(
+    bucket_dir: impl AsRef<Path>,
+    time: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let path = bucket_dir.as_ref().join(BUCKET_CREATED_FILE);
+    fs::write(&path, time.to_rfc3339())?;
+    durability::enqueue(path);
+    Ok(())
+}
+
+// 桶的创建时间，没有记录（旧版本创建的桶）时为目录的修改时间
+pub(crate) fn bucket_created(bucket_dir: &Path) -> io::Result<DateTime<Utc>> {
+    let recorded = fs::read_to_string(bucket_dir.join(BUCKET_CREATED_FILE))
+        .ok()
+        .and_then(|time| DateTime::parse_from_rfc3339(time.trim()).ok());
+    match recorded {
+        Some(time) => Ok(time.with_timezone(&Utc)),
+        None => Ok(fs::metadata(bucket_dir)?.modified()?.into()),
+    }
+}
+
+// 桶内是否还有对象，找到第一个元数据文件即返回
+pub(crate) fn bucket_has_objects(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                bucket_has_objects(&path)
+            } else {
+                path.extension().is_some_and(|ext| ext == "meta")
+            }
+        })
+}
+
+// 读取桶的数据密钥
+fn load_bucket_key(bucket_dir: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    match fs::read(bucket_key_path(bucket_dir)) {
+        Ok(wrapped) => Ok(Some(kms::decrypt(&wrapped)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
 }
 
 // 保存元数据
 pub(crate) fn save_metadata(meta_file_path: impl AsRef<Path>, metadata: &Metadata) -> anyhow::Result<()> {
-    let meta_data = rkyv::to_bytes::<_, 256>(metadata)?;
+    slowlog::time(Phase::Metadata, || {
+        write_metadata(meta_file_path.as_ref(), metadata)
+    })
+}
+
+fn write_metadata(meta_file_path: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+    let meta_data = serialize_metadata(metadata)?;
     let meta_data = meta_data.as_slice();
-    fs::create_dir_all(meta_file_path.as_ref().parent().unwrap())?;
-    let meta_bytes = cry::aes_256_cbc_encrypt(meta_data)?;
-    fs::write(meta_file_path, &meta_bytes)?;
-    Ok(())
+    fs::create_dir_all(meta_file_path.parent().unwrap())?;
+    let meta_bytes = match bucket_dir_from_meta(meta_file_path) {
+        _ if config::get().plain_metadata => [PLAIN_METADATA_MAGIC, meta_data].concat(),
+        // 数据密钥只在创建桶时生成；没有数据密钥的旧桶继续用主密钥加密，与读取时一致
+        Some(bucket_dir) => match load_bucket_key(&bucket_dir)? {
+            Some(key) => cry::aes_256_cbc_encrypt_with_key(&key, meta_data)?,
+            None => kms::encrypt(meta_data)?,
+        },
+        None => kms::encrypt(meta_data)?,
+    };
+    // 先写临时文件再重命名，写入失败时原有元数据保持不变
+    let tmp = tmp_sibling(meta_file_path);
+    let res = fs::write(&tmp, &meta_bytes)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| rollback::check(FailPoint::Metadata))
+        .and_then(|_| publish_file(&tmp, meta_file_path));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res
 }
 
 // 加载元数据
 pub(crate) fn load_metadata(meta_file_path: impl AsRef<Path>) -> anyhow::Result<Metadata> {
+    slowlog::time(Phase::Metadata, || read_metadata(meta_file_path.as_ref()))
+}
+
+fn read_metadata(meta_file_path: &Path) -> anyhow::Result<Metadata> {
     let metadata_bytes = fs::read(meta_file_path).context("元数据地址不存在")?;
-    let metadata_bytes = cry::aes_256_cbc_decrypt(&metadata_bytes)?;
-    let archived = rkyv::check_archived_root::<Metadata>(&metadata_bytes[..]).unwrap();
-    let res: Metadata = archived.deserialize(&mut Infallible)?;
-    Ok(res)
+    if let Some(plain) = metadata_bytes.strip_prefix(PLAIN_METADATA_MAGIC) {
+        return deserialize_metadata(plain);
+    }
+    // 没有桶密钥的旧数据仍使用主密钥解密
+    let bucket_key = match bucket_dir_from_meta(meta_file_path) {
+        Some(bucket_dir) => load_bucket_key(&bucket_dir)?,
+        None => None,
+    };
+    let Some(key) = bucket_key else {
+        return deserialize_metadata(&kms::decrypt(&metadata_bytes)?);
+    };
+    // 旧版本在已有对象的桶中补建过数据密钥，补建之前写入的元数据仍是主密钥加密的
+    cry::aes_256_cbc_decrypt_with_key(&key, &metadata_bytes)
+        .and_then(|bytes| deserialize_metadata(&bytes))
+        .or_else(|err| {
+            kms::decrypt(&metadata_bytes)
+                .and_then(|bytes| deserialize_metadata(&bytes))
+                .map_err(|_| err)
+        })
+}
+
+// 带格式版本头部序列化元数据
+pub fn serialize_metadata(metadata: &Metadata) -> anyhow::Result<Vec<u8>> {
+    let archived = rkyv::to_bytes::<_, 256>(metadata)?;
+    Ok([METADATA_MAGIC, &[METADATA_VERSION], archived.as_slice()].concat())
+}
+
+// 按头部中的格式版本反序列化元数据，没有头部时按最初版本的格式解码；内容损坏时返回错误
+pub fn deserialize_metadata(bytes: &[u8]) -> anyhow::Result<Metadata> {
+    let Some(rest) = bytes.strip_prefix(METADATA_MAGIC) else {
+        let legacy: LegacyMetadata = decode_archived(bytes).context("元数据格式无效")?;
+        return Ok(legacy.into());
+    };
+    match rest.split_first() {
+        Some((&METADATA_VERSION, archived)) => decode_archived(archived).context("元数据格式无效"),
+        Some((version, _)) => anyhow::bail!("不支持的元数据格式版本 {}", version),
+        None => anyhow::bail!("元数据头部不完整"),
+    }
 }
 
-// 定义解压流
+fn decode_archived<T>(bytes: &[u8]) -> anyhow::Result<T>
+where
+    T: Archive,
+    T::Archived: for<'a> rkyv::CheckBytes<rkyv::validation::validators::DefaultValidator<'a>>
+        + Deserialize<T, Infallible>,
+{
+    // rkyv 要求按对齐的地址读取，去掉头部后的切片不一定对齐
+    let mut aligned = rkyv::AlignedVec::with_capacity(bytes.len());
+    aligned.extend_from_slice(bytes);
+    let archived =
+        rkyv::check_archived_root::<T>(&aligned[..]).map_err(|err| anyhow::anyhow!("{}", err))?;
+    Ok(archived.deserialize(&mut Infallible)?)
+}
+
+// 是否为明文保存的元数据（--plain-metadata）
+pub(crate) fn is_plain_metadata(data: &[u8]) -> bool {
+    data.starts_with(PLAIN_METADATA_MAGIC)
+}
+
+// 定义解压流：分片的读取和解压都在后台线程池中执行，并行预读之后的若干个分片；
+// 读取或解压失败时返回错误并结束，不会把截断的内容当作完整对象返回
 pub(crate) struct DecompressStream {
     hashes: Vec<String>,
     idx: usize,
+    // 预读窗口上限（分片数），为 0 时只读取当前分片
+    read_ahead: usize,
+    // 当前预读窗口，随顺序读取逐步扩大到上限
+    window: usize,
+    // 从 idx 开始、已提交后台读取并解压的分片
+    prefetched: VecDeque<oneshot::Receiver<io::Result<Vec<u8>>>>,
+    // 已返回错误，之后的分片不再读取
+    failed: bool,
 }
 
 impl DecompressStream {
     pub(crate) fn new(hashes: Vec<String>) -> Self {
-        DecompressStream { hashes, idx: 0 }
+        // 只有多个分片的大对象才预读
+        let read_ahead = if hashes.len() > 1 {
+            config::get().read_ahead_chunks
+        } else {
+            0
+        };
+        DecompressStream {
+            hashes,
+            idx: 0,
+            read_ahead,
+            window: 0,
+            prefetched: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    // 保证当前分片及其后 window 个分片都已提交读取
+    fn fill_prefetch(&mut self) {
+        while self.prefetched.len() <= self.window
+            && self.idx + self.prefetched.len() < self.hashes.len()
+        {
+            let hash = self.hashes[self.idx + self.prefetched.len()].clone();
+            // 某个副本无法解压时按副本顺序读取下一个
+            self.prefetched
+                .push_back(pool::spawn(move || read_chunk_decompressed(&hash)));
+        }
     }
 }
 
@@ -145,68 +989,425 @@ impl Stream for DecompressStream {
 
     fn poll_next(
         mut self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
+        cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        if self.idx >= self.hashes.len() {
-            std::task::Poll::Ready(None)
-        } else {
-            let x = &self.hashes[self.idx];
-            let path = path_from_hash(x);
-            if let Ok(res) = decompress_chunk(&path) {
-                self.idx += 1;
-                std::task::Poll::Ready(Some(Ok(Bytes::from(res))))
-            } else {
-                std::task::Poll::Ready(None)
+        if self.failed || self.idx >= self.hashes.len() {
+            return std::task::Poll::Ready(None);
+        }
+        self.fill_prefetch();
+        let front = self.prefetched.front_mut().unwrap();
+        let res = match std::pin::Pin::new(front).poll(cx) {
+            std::task::Poll::Pending => return std::task::Poll::Pending,
+            std::task::Poll::Ready(res) => res,
+        };
+        self.prefetched.pop_front();
+        let hash = self.hashes[self.idx].clone();
+        self.idx += 1;
+        match res.map_err(io::Error::other).and_then(|res| res) {
+            Ok(data) => {
+                // 顺序读取时窗口按 1、2、4… 扩大，补齐后续分片的读取
+                self.window = (self.window * 2).max(1).min(self.read_ahead);
+                self.fill_prefetch();
+                std::task::Poll::Ready(Some(Ok(Bytes::from(data))))
+            }
+            Err(err) => {
+                warn!("读取分片 {} 失败: {}", hash, err);
+                self.failed = true;
+                self.prefetched.clear();
+                std::task::Poll::Ready(Some(Err(io::Error::new(
+                    err.kind(),
+                    format!("读取分片 {} 失败: {}", hash, err),
+                ))))
             }
         }
     }
 }
 
+// 直接读取分片文件中保存的 zstd 帧，多个帧首尾相接仍是合法的 zstd 流
+pub(crate) fn zstd_frame_stream(hashes: Vec<String>) -> impl Stream<Item = io::Result<Bytes>> {
+    let read_ahead = config::get().read_ahead_chunks;
+    futures::stream::iter(hashes)
+        .map(|hash| async move {
+            pool::spawn(move || read_chunk(&hash))
+                .await
+                .map_err(io::Error::other)?
+                .map(Bytes::from)
+        })
+        .buffered(read_ahead + 1)
+}
+
+// 全部分片都是 zstd 帧时返回压缩后的总大小，有其他编码的分片时为空
+pub(crate) fn zstd_frames_size(hashes: &[String]) -> io::Result<Option<u64>> {
+    for hash in hashes {
+        if !is_zstd_chunk(hash)? {
+            return Ok(None);
+        }
+    }
+    hashes
+        .iter()
+        .map(|hash| chunk_len(hash))
+        .sum::<io::Result<u64>>()
+        .map(Some)
+}
+
+// 按分片文件开头判断编码，纠删码或分片不在本节点时读取整个分片
+fn is_zstd_chunk(hash: &str) -> io::Result<bool> {
+    if config::get().erasure().is_none() {
+        for path in chunk_paths(hash) {
+            if let Ok(file) = fs::File::open(&path) {
+                let mut head = vec![];
+                file.take(8).read_to_end(&mut head)?;
+                return Ok(codec::is_zstd(&head));
+            }
+        }
+    }
+    read_chunk(hash).map(|chunk| codec::is_zstd(&chunk))
+}
+
+// 分片压缩后的大小，集群模式下本地没有时向其他节点查询
+pub(crate) fn chunk_len(hash: &str) -> io::Result<u64> {
+    match local_chunk_len(hash) {
+        Err(_) if cluster::enabled() => cluster::fetch_chunk_len(hash),
+        res => res,
+    }
+}
+
+// 本节点保存的分片压缩后的大小
+pub(crate) fn local_chunk_len(hash: &str) -> io::Result<u64> {
+    if config::get().erasure().is_some() {
+        return erasure::stored_len(hash);
+    }
+    fs::metadata(path_from_hash(hash)).map(|m| m.len())
+}
+
+// 分片在本节点实际占用的磁盘空间，纠删码时为所有块之和，缺失时按 0 计算
+pub(crate) fn chunk_disk_usage(hash: &str) -> u64 {
+    if config::get().erasure().is_some() {
+        return erasure::disk_usage(hash);
+    }
+    fs::metadata(path_from_hash(hash))
+        .map(|m| m.len())
+        .unwrap_or_default()
+}
+
+// 将解压后的分片流重新编码为 gzip 流
+pub(crate) fn gzip_stream(chunks: DecompressStream) -> impl Stream<Item = io::Result<Bytes>> {
+    let encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    futures::stream::try_unfold(Some((chunks, encoder)), |state| async move {
+        let Some((mut chunks, mut encoder)) = state else {
+            return Ok(None);
+        };
+        match chunks.next().await {
+            Some(chunk) => {
+                encoder.write_all(&chunk?)?;
+                let out = std::mem::take(encoder.get_mut());
+                Ok(Some((Bytes::from(out), Some((chunks, encoder)))))
+            }
+            None => Ok(Some((Bytes::from(encoder.finish()?), None))),
+        }
+    })
+}
+
 // 判断路径是否存在
 #[inline]
 pub(crate) fn is_path_exist(hash: &str) -> bool {
+    // 集群模式下不归本节点保存的分片由归属节点负责，这里视为存在，保证各节点的判断一致
+    if !cluster::is_local(hash) {
+        return true;
+    }
+    if config::get().erasure().is_some() {
+        return erasure::is_readable(hash);
+    }
     let path = path_from_hash(hash);
     path.exists()
 }
 
+// 分片在本节点的所有文件（副本或纠删码块）
+pub(crate) fn chunk_files(hash: &str) -> Vec<PathBuf> {
+    if config::get().erasure().is_some() {
+        return erasure::shard_paths(hash);
+    }
+    chunk_paths(hash)
+}
+
+// 分片数据目录，未配置时为默认的 data/file
+pub(crate) fn chunk_root_dirs() -> Vec<PathBuf> {
+    let roots = &config::get().chunk_roots;
+    if roots.is_empty() {
+        return vec![default_chunk_root()];
+    }
+    roots.clone()
+}
+
+// 分片是否已完整写入（所有副本或所有纠删码块）。去重命中的分片会被新对象引用，修改时间
+// 超过半个宽限期时在垃圾回收锁内刷新，保证引用它的元数据保存前垃圾回收不会删除它们；
+// 反复命中的分片只需读取修改时间，不争用垃圾回收锁
+fn is_chunk_stored(hash: &str) -> bool {
+    let files = chunk_files(hash);
+    let now = SystemTime::now();
+    let refresh_after = gc::GRACE_PERIOD / 2;
+    let is_recent = |path: &PathBuf| {
+        fs::metadata(path)
+            .is_ok_and(|metadata| !gc::is_past_grace(metadata.modified().ok(), now, refresh_after))
+    };
+    if files.iter().all(is_recent) {
+        return true;
+    }
+    let _guard = CHUNK_GC_LOCK.lock().unwrap();
+    let mut stored = true;
+    for path in files {
+        let touched = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        stored &= touched.is_ok();
+    }
+    stored
+}
+
+// 分片是否可以直接引用：已完整写入，且按 --dedup-verify 校验通过
+async fn is_chunk_reusable(hash: &str) -> anyhow::Result<bool> {
+    let reusable = check_chunk_reusable(hash).await?;
+    slowlog::count_dedup(reusable);
+    Ok(reusable)
+}
+
+async fn check_chunk_reusable(hash: &str) -> anyhow::Result<bool> {
+    if !is_chunk_stored(hash) {
+        return Ok(false);
+    }
+    let verify = match config::get().dedup_verify {
+        DedupVerify::Never => false,
+        DedupVerify::Always => true,
+        DedupVerify::Sampled(n) => DEDUP_HITS
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(n as u64),
+    };
+    if !verify {
+        return Ok(true);
+    }
+    let hash = hash.to_string();
+    pool::run(move || verify_stored_chunk(&hash)).await
+}
+
+// 校验本节点保存的分片，删除解压失败或内容与哈希不一致的副本（纠删码时删除全部块），
+// 由调用方用新上传的数据重新写入
+fn verify_stored_chunk(hash: &str) -> bool {
+    let is_intact = |chunk| is_intact_chunk(hash, chunk);
+    let broken: Vec<PathBuf> = if config::get().erasure().is_some() {
+        if is_intact(erasure::read(hash)) {
+            return true;
+        }
+        erasure::shard_paths(hash)
+    } else {
+        chunk_paths(hash)
+            .into_iter()
+            .filter(|path| !is_intact(fs::read(path)))
+            .collect()
+    };
+    if broken.is_empty() {
+        return true;
+    }
+    warn!(
+        "去重命中的分片 {} 已损坏，重新写入 {} 个文件",
+        hash,
+        broken.len()
+    );
+    for path in broken {
+        if let Err(err) = fs::remove_file(&path) {
+            warn!("删除损坏的分片文件 {:?} 失败: {}", path, err);
+        }
+    }
+    false
+}
+
+// 按内容切分时分片大小的下限为平均大小的 1/4，上限为 4 倍
+const CDC_MIN_DIVISOR: usize = 4;
+const CDC_MAX_MULTIPLIER: usize = 4;
+
+// gear 哈希使用的随机表，由固定种子生成（splitmix64），保证各节点、各版本的切分结果一致
+static GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6a09_e667_f3bc_c908;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+// 高 n 位为 1 的掩码；gear 哈希的高位受最近 64 个字节影响，低位只受最近几个字节影响
+fn top_bits(n: u32) -> u64 {
+    match n {
+        0 => 0,
+        n => !0u64 << (64 - n.min(64)),
+    }
+}
+
+// 确定分片边界：固定大小，或按内容（FastCDC）。按内容切分时从下限开始计算 gear 哈希，
+// 到平均大小前用更难命中的掩码、之后用更容易命中的掩码（归一化切分），使分片大小集中在
+// 平均大小附近，到上限时强制切分
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    chunking: Chunking,
+    avg: usize,
+    min: usize,
+    max: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Chunker {
+    pub fn new(chunk_size: usize, chunking: Chunking) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let bits = chunk_size.ilog2();
+        Chunker {
+            chunking,
+            avg: chunk_size,
+            min: chunk_size / CDC_MIN_DIVISOR,
+            max: chunk_size.saturating_mul(CDC_MAX_MULTIPLIER),
+            mask_small: top_bits(bits + 2),
+            mask_large: top_bits(bits.saturating_sub(2)),
+        }
+    }
+
+    // 单个分片的最大字节数
+    pub fn max_chunk_size(&self) -> usize {
+        match self.chunking {
+            Chunking::Fixed => self.avg,
+            Chunking::ContentDefined => self.max,
+        }
+    }
+
+    // data 开头的下一个分片的长度。eof 表示 data 之后没有更多内容；否则 data 不足以确定边界时
+    // 返回空，data 不短于 max_chunk_size 时总能确定
+    pub fn next_len(&self, data: &[u8], eof: bool) -> Option<usize> {
+        if data.is_empty() {
+            return None;
+        }
+        let limit = self.max_chunk_size();
+        let boundary = match self.chunking {
+            Chunking::Fixed => None,
+            Chunking::ContentDefined => self.find_boundary(&data[..data.len().min(limit)]),
+        };
+        match boundary {
+            Some(len) => Some(len),
+            None if data.len() >= limit => Some(limit),
+            None => eof.then_some(data.len()),
+        }
+    }
+
+    fn find_boundary(&self, data: &[u8]) -> Option<usize> {
+        let mut hash = 0u64;
+        for (i, byte) in data.iter().enumerate().skip(self.min) {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = if i < self.avg {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if hash & mask == 0 {
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+
+    // 切分完整的内容，返回各分片的范围
+    pub fn split(&self, data: &[u8]) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut start = 0;
+        while let Some(len) = self.next_len(&data[start..], true) {
+            ranges.push(start..start + len);
+            start += len;
+        }
+        ranges
+    }
+}
+
+// 边接收边切分的缓冲区
+pub struct ChunkBuffer {
+    chunker: Chunker,
+    buf: Vec<u8>,
+}
+
+impl ChunkBuffer {
+    pub fn new(chunker: Chunker) -> Self {
+        ChunkBuffer {
+            chunker,
+            buf: vec![],
+        }
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    // 取出下一个分片，eof 表示不会再有更多内容
+    pub fn next_chunk(&mut self, eof: bool) -> Option<Vec<u8>> {
+        let len = self.chunker.next_len(&self.buf, eof)?;
+        let rest = self.buf.split_off(len);
+        Some(std::mem::replace(&mut self.buf, rest))
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
 // 数据分片并保存
 pub(crate) async fn split_file_and_save(
     data: Vec<u8>,
-    chunk_size: usize,
-) -> anyhow::Result<(usize, Vec<String>)> {
+    chunker: Chunker,
+    codec: Codec,
+    written: &mut WrittenFiles,
+) -> anyhow::Result<(usize, Vec<String>, Vec<u64>)> {
+    let data = Arc::new(data);
     let mut chunks = Vec::new();
-    let mut size = 0;
-    let mut buffer = BytesMut::new();
-    let mut reader = Cursor::new(data);
-    loop {
-        let read = reader.read(&mut buffer)?;
-        if read > 0 {
-            size += read;
-            if buffer.len() >= chunk_size {
-                let chunk = buffer.split_to(chunk_size);
-                let hash_code = sum_sha256(chunk.as_slice()).await;
-                chunks.push(hash_code.clone());
-
-                if !is_path_exist(&hash_code) {
-                    let compressed_chunk = compress_chunk(std::io::Cursor::new(chunk))?;
-                    save_file(&hash_code, &compressed_chunk).await?;
-                }
-            }
+    let mut chunk_sizes = Vec::new();
+    for Range { start, end } in chunker.split(&data) {
+        let hash_code = sum_sha256(&data[start..end]).await;
+        chunks.push(hash_code.clone());
+        chunk_sizes.push((end - start) as u64);
+
+        if cluster::is_local(&hash_code) && !is_chunk_reusable(&hash_code).await? {
+            let data = data.clone();
+            let compressed_chunk =
+                pool::run(move || compress_chunk(&data[start..end], codec)).await??;
+            save_file(&hash_code, &compressed_chunk, written).await?;
         }
+    }
+    Ok((data.len(), chunks, chunk_sizes))
+}
 
-        if read == 0 {
-            if !buffer.is_empty() {
-                let chunk = buffer.as_slice();
-                let hash_code = sum_sha256(chunk).await;
-                chunks.push(hash_code.clone());
-
-                if !is_path_exist(&hash_code) {
-                    let compressed_chunk = compress_chunk(std::io::Cursor::new(chunk))?;
-                    save_file(&hash_code, &compressed_chunk).await?;
-                }
-            }
-            break;
+// 保存单个分片，已保存或不归本节点保存时跳过；写入失败时删除本次新建的文件
+pub(crate) async fn save_chunk(
+    hash_code: String,
+    data: Vec<u8>,
+    codec: Codec,
+) -> anyhow::Result<()> {
+    if cluster::is_local(&hash_code) && !is_chunk_reusable(&hash_code).await? {
+        let compressed_chunk = pool::run(move || compress_chunk(&data, codec)).await??;
+        let mut written = WrittenFiles::new();
+        if let Err(err) = save_file(&hash_code, &compressed_chunk, &mut written).await {
+            written.rollback();
+            return Err(err);
         }
     }
-    Ok((size, chunks))
+    Ok(())
 }
@@ -0,0 +1,260 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::client::S3Client;
+use crate::config;
+use crate::err::AppError;
+use crate::fs;
+use crate::multipart;
+use crate::snapshot;
+use crate::spool;
+use crate::versioning;
+use crate::HandlerResponse;
+use anyhow::Context;
+use log::{info, warn};
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// --- 分片垃圾回收（标记-清除）：收集所有对象（含历史版本）、暂存批次和进行中的分片上传引用的
+// 分片，删除本节点数据目录中不再被引用的分片文件。修改时间在宽限期内的文件不删除，覆盖正在
+// 写入、元数据还未保存的分片；去重命中修改时间超过半个宽限期的分片时会刷新其修改时间（见
+// fs::is_chunk_stored）。通过 POST /admin/gc 或 gc 子命令手动执行，或用 --gc-interval-hours
+// 定期执行
+
+// 宽限期，修改时间在这之内的分片文件不删除
+pub(crate) const GRACE_PERIOD: Duration = Duration::from_secs(3600);
+
+// 同一时间只执行一次垃圾回收
+static RUNNING: Mutex<()> = Mutex::new(());
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct GcOpt {
+    /// Server address, without the `/api` prefix
+    #[clap(long, default_value_t = String::from("http://127.0.0.1:9000"))]
+    pub endpoint: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub access_key: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub secret_key: String,
+
+    /// Only report the chunks that would be removed
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/gc", web::post().to(collect_garbage));
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    // 被引用的不同分片数
+    pub referenced_chunks: u64,
+    // 扫描到的分片文件数（每个副本或纠删码块各算一个）
+    pub scanned_files: u64,
+    // 未被引用的分片文件数和大小，不含宽限期内的文件
+    pub orphaned_files: u64,
+    pub reclaimed_bytes: u64,
+    // 未被引用但仍在宽限期内的文件
+    pub recent_files: u64,
+    pub duration_ms: u64,
+}
+
+// 由数据目录下 <h0>/<h1h2>/<rest>[.ec] 的相对路径还原分片哈希，不符合该结构时为空
+pub fn chunk_hash_from_path(relative: &Path) -> Option<String> {
+    let parts: Vec<&str> = relative.iter().map(|p| p.to_str()).collect::<Option<_>>()?;
+    let [a, b, c] = parts[..] else {
+        return None;
+    };
+    let c = c.strip_suffix(".ec").unwrap_or(c);
+    let hash = format!("{}{}{}", a, b, c);
+    let valid = a.len() == 1
+        && b.len() == 2
+        && hash.len() == 64
+        && hash.bytes().all(|b| b.is_ascii_hexdigit());
+    valid.then_some(hash)
+}
+
+// 修改时间是否已超过宽限期，读取不到修改时间时视为未超过
+pub fn is_past_grace(modified: Option<SystemTime>, now: SystemTime, grace: Duration) -> bool {
+    modified
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age > grace)
+}
+
+#[derive(Deserialize)]
+pub struct GcQuery {
+    // 只统计可回收的分片，不删除
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// 执行一次垃圾回收，只处理本节点的分片文件
+pub async fn collect_garbage(Query(query): Query<GcQuery>) -> HandlerResponse {
+    let report = tokio::task::spawn_blocking(move || collect(query.dry_run))
+        .await
+        .context("垃圾回收失败")??;
+    match report {
+        Some(report) => Ok(HttpResponse::Ok().json(&report)),
+        None => Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "OperationAborted",
+            "A garbage collection is already running",
+        )),
+    }
+}
+
+// 开启 --gc-interval-hours 时定期执行垃圾回收
+pub(crate) fn spawn() {
+    let Some(hours) = config::get().gc_interval_hours else {
+        return;
+    };
+    std::thread::Builder::new()
+        .name("chunk-gc".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(hours * 3600));
+            if let Err(err) = collect(false) {
+                warn!("分片垃圾回收失败: {:#}", err);
+            }
+        })
+        .expect("启动垃圾回收线程失败");
+}
+
+// 已有垃圾回收在执行时返回空
+fn collect(dry_run: bool) -> anyhow::Result<Option<GcReport>> {
+    let Ok(_running) = RUNNING.try_lock() else {
+        return Ok(None);
+    };
+    let start = Instant::now();
+    let referenced = referenced_chunks()?;
+    let mut report = GcReport {
+        dry_run,
+        referenced_chunks: referenced.len() as u64,
+        ..Default::default()
+    };
+    for root in fs::chunk_root_dirs() {
+        sweep(&root, &root, &referenced, &mut report);
+    }
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    if report.orphaned_files > 0 {
+        info!(
+            "分片垃圾回收{}：{} 个未引用的分片文件，共 {} 字节",
+            if dry_run { "（试运行）" } else { "" },
+            report.orphaned_files,
+            report.reclaimed_bytes
+        );
+    }
+    Ok(Some(report))
+}
+
+// 所有对象（含历史版本）和暂存批次的元数据文件
+pub(crate) fn metadata_files() -> anyhow::Result<Vec<PathBuf>> {
+    let mut meta_files = vec![];
+    let dirs = [
+        PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX),
+        fs::staging_root(),
+        versioning::versions_root(),
+    ];
+    for dir in dirs.into_iter().chain(snapshot::metadata_dirs()) {
+        if dir.is_dir() {
+            fs::walk_meta_files(&dir, &mut meta_files)
+                .with_context(|| format!("遍历元数据目录失败 {:?}", dir))?;
+        }
+    }
+    Ok(meta_files)
+}
+
+// 所有元数据引用的分片；任一元数据无法读取时中止，避免误删它引用的分片
+fn referenced_chunks() -> anyhow::Result<HashSet<String>> {
+    let mut referenced = HashSet::new();
+    for meta_file in metadata_files()? {
+        let metadata = fs::load_metadata(&meta_file)
+            .with_context(|| format!("读取元数据失败 {:?}，中止垃圾回收", meta_file))?;
+        referenced.extend(metadata.chunks);
+    }
+    referenced.extend(upload_part_chunks()?.into_iter().map(|(hash, _)| hash));
+    Ok(referenced)
+}
+
+// 进行中的分片上传的记录中的分片及其大小，见 multipart
+pub(crate) fn upload_part_chunks() -> anyhow::Result<Vec<(String, u64)>> {
+    let mut chunks = vec![];
+    for upload in std::fs::read_dir(fs::tmp_root()).into_iter().flatten() {
+        let upload = upload?;
+        if upload.file_name() == spool::SPOOL_DIR_NAME || !upload.path().is_dir() {
+            continue;
+        }
+        for part in std::fs::read_dir(upload.path())? {
+            let record = std::fs::read_to_string(part?.path()).context("读取分片上传记录失败")?;
+            chunks.extend(multipart::parse_part_chunks(&record).unwrap_or_default());
+        }
+    }
+    Ok(chunks)
+}
+
+// 删除目录下未被引用且已超过宽限期的分片文件
+fn sweep(root: &Path, dir: &Path, referenced: &HashSet<String>, report: &mut GcReport) {
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            sweep(root, &path, referenced, report);
+            continue;
+        }
+        let Some(hash) = path.strip_prefix(root).ok().and_then(chunk_hash_from_path) else {
+            continue;
+        };
+        report.scanned_files += 1;
+        if referenced.contains(&hash) {
+            continue;
+        }
+        // 与写入时的去重判断互斥，判断修改时间后到删除前分片不会被重新引用
+        let _guard = fs::CHUNK_GC_LOCK.lock().unwrap();
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if !is_past_grace(metadata.modified().ok(), SystemTime::now(), GRACE_PERIOD) {
+            report.recent_files += 1;
+            continue;
+        }
+        if !report.dry_run {
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!("删除分片文件 {:?} 失败: {}", path, err);
+                continue;
+            }
+        }
+        report.orphaned_files += 1;
+        report.reclaimed_bytes += metadata.len();
+    }
+}
+
+// gc 子命令：请求运行中的服务执行垃圾回收并输出结果
+pub async fn run(opt: GcOpt) -> anyhow::Result<()> {
+    let client = S3Client::new(&opt.endpoint, &opt.access_key, &opt.secret_key);
+    let dry_run = opt.dry_run.to_string();
+    let resp = client
+        .send(
+            Method::POST,
+            "admin/gc",
+            &[("dry_run", &dry_run)],
+            &[],
+            Vec::new(),
+        )
+        .await?;
+    let status = resp.status();
+    let body = resp.text().await.context("读取响应失败")?;
+    if !status.is_success() {
+        anyhow::bail!("垃圾回收失败: HTTP {} {}", status.as_u16(), body);
+    }
+    let report: serde_json::Value = serde_json::from_str(&body).context("解析响应失败")?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::fs;
+use crate::fs::ResponseHeader;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// 桶默认响应头配置文件名
+const HEADERS_CONFIG_FILE: &str = ".headers.xml";
+
+// 扩展：桶的默认响应头配置（PUT /api/{bucket}?response-headers 请求体），
+// 读取对象时附加到响应中，对象上传时设置的同名响应头优先
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename = "ResponseHeadersConfiguration")]
+pub struct ResponseHeadersConfiguration {
+    #[serde(
+        rename = "CacheControl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cache_control: Option<String>,
+    #[serde(
+        rename = "ContentSecurityPolicy",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub content_security_policy: Option<String>,
+    #[serde(
+        rename = "ExposeHeaders",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expose_headers: Option<String>,
+}
+
+impl ResponseHeadersConfiguration {
+    // 按响应头名称列出已配置的默认值
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("Cache-Control", &self.cache_control),
+            ("Content-Security-Policy", &self.content_security_policy),
+            ("Access-Control-Expose-Headers", &self.expose_headers),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .collect()
+    }
+
+    // 合并桶默认响应头和对象级响应头，同名时以对象级为准
+    pub fn merge<'a>(&'a self, object_headers: &'a [ResponseHeader]) -> Vec<(&'a str, &'a str)> {
+        let mut headers: Vec<(&str, &str)> = self
+            .headers()
+            .into_iter()
+            .filter(|(name, _)| {
+                !object_headers
+                    .iter()
+                    .any(|h| h.name.eq_ignore_ascii_case(name))
+            })
+            .collect();
+        headers.extend(
+            object_headers
+                .iter()
+                .map(|h| (h.name.as_str(), h.value.as_str())),
+        );
+        headers
+    }
+}
+
+// 桶默认响应头配置文件路径
+pub(crate) fn config_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, HEADERS_CONFIG_FILE)
+}
+
+// 读取桶的默认响应头配置原文
+pub(crate) fn load_raw(bucket: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(bucket)).ok()
+}
+
+// 读取并解析桶的默认响应头配置，不存在或无法解析时使用空配置
+pub(crate) fn load(bucket: &str) -> ResponseHeadersConfiguration {
+    load_raw(bucket)
+        .and_then(|raw| quick_xml::de::from_str(&raw).ok())
+        .unwrap_or_default()
+}
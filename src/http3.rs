@@ -0,0 +1,179 @@
+use crate::config::TlsConfig;
+use anyhow::Context;
+use bytes::{Buf, Bytes};
+use futures::channel::mpsc;
+use futures::SinkExt;
+use h3::server::{RequestResolver, RequestStream};
+use log::{info, warn};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+// --- 实验性的 HTTP/3 监听：编译 http3 特性并以 --http3-addr 启动时，在该 UDP 地址上用 --tls-cert、
+// --tls-key 接受 QUIC 连接（TLS 1.3，ALPN 为 h3）。ntex 不支持 HTTP/3，每个请求原样转发给本节点的
+// HTTP 监听，认证、路由和 --tls-addr 上的请求完全相同，请求体和响应体都按流转发。
+// QUIC 握手不请求客户端证书，证书对应的访问密钥在这里不生效，--tls-require-client-cert 时拒绝启动
+
+// HTTP/3 中不允许出现的逐跳头，转发时不复制
+const HOP_BY_HOP: [&str; 6] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "te",
+];
+
+// 请求体转发通道的容量（数据帧数）
+const BODY_CHANNEL_FRAMES: usize = 8;
+
+type H3Resolver = RequestResolver<h3_quinn::Connection, Bytes>;
+
+// 绑定 HTTP/3 监听并在后台接受连接，请求转发到 http_addr，返回实际监听的地址
+pub(crate) fn spawn(tls: &TlsConfig, addr: &str, http_addr: &str) -> anyhow::Result<SocketAddr> {
+    if tls.require_client_cert {
+        anyhow::bail!("HTTP/3 监听不校验客户端证书，不能与 --tls-require-client-cert 同时使用");
+    }
+    let certs = CertificateDer::pem_file_iter(&tls.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("读取证书 {:?} 失败", tls.cert))?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key)
+        .with_context(|| format!("读取私钥 {:?} 失败", tls.key))?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut crypto = rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("私钥与证书不匹配")?;
+    crypto.alpn_protocols = vec![b"h3".to_vec()];
+    let crypto = quinn::crypto::rustls::QuicServerConfig::try_from(crypto)?;
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let addr = addr
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .with_context(|| format!("无效的 HTTP/3 监听地址 {}", addr))?;
+    let endpoint = quinn::Endpoint::server(server_config, addr).context("绑定 HTTP/3 监听失败")?;
+    let local_addr = endpoint.local_addr()?;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let upstream = format!("http://{}", http_addr);
+    tokio::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            let client = client.clone();
+            let upstream = upstream.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_connection(incoming, client, upstream).await {
+                    info!("HTTP/3 连接结束: {:#}", err);
+                }
+            });
+        }
+    });
+    Ok(local_addr)
+}
+
+async fn serve_connection(
+    incoming: quinn::Incoming,
+    client: reqwest::Client,
+    upstream: String,
+) -> anyhow::Result<()> {
+    let conn = incoming.await.context("QUIC 握手失败")?;
+    let mut conn = h3::server::Connection::new(h3_quinn::Connection::new(conn)).await?;
+    loop {
+        let resolver = match conn.accept().await {
+            Ok(Some(resolver)) => resolver,
+            Ok(None) => break,
+            // 客户端正常关闭连接
+            Err(err) if err.is_h3_no_error() => break,
+            Err(err) => return Err(err.into()),
+        };
+        let client = client.clone();
+        let upstream = upstream.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_request(resolver, &client, &upstream).await {
+                warn!("转发 HTTP/3 请求失败: {:#}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+// 把一个 HTTP/3 请求转发给 HTTP 监听，再把响应写回
+async fn serve_request(
+    resolver: H3Resolver,
+    client: &reqwest::Client,
+    upstream: &str,
+) -> anyhow::Result<()> {
+    let (request, stream) = resolver.resolve_request().await?;
+    let (mut send, recv) = stream.split();
+    let path = request.uri().path_and_query().map_or("/", |p| p.as_str());
+    let method = reqwest::Method::from_bytes(request.method().as_str().as_bytes())?;
+    let mut forwarded = client.request(method.clone(), format!("{}{}", upstream, path));
+    for (name, value) in request.headers() {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            forwarded = forwarded.header(name.as_str(), value.as_bytes());
+        }
+    }
+    // HTTP/3 用 :authority 代替 Host，签名中的 host 就是它
+    if !request.headers().contains_key("host") {
+        if let Some(authority) = request.uri().authority() {
+            forwarded = forwarded.header("host", authority.as_str());
+        }
+    }
+    let content_length = request
+        .headers()
+        .get("content-length")
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    let bodiless = match content_length {
+        Some(len) => len == 0,
+        None => method == reqwest::Method::GET || method == reqwest::Method::HEAD,
+    };
+    if !bodiless {
+        let (tx, rx) = mpsc::channel(BODY_CHANNEL_FRAMES);
+        tokio::spawn(forward_body(recv, tx));
+        forwarded = forwarded.body(reqwest::Body::wrap_stream(rx));
+    }
+    let mut response = match forwarded.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            let head = http::Response::builder()
+                .status(http::StatusCode::BAD_GATEWAY)
+                .body(())?;
+            send.send_response(head).await?;
+            send.finish().await?;
+            return Err(err).context("请求 HTTP 监听失败");
+        }
+    };
+    let mut head = http::Response::builder().status(response.status().as_u16());
+    for (name, value) in response.headers() {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            head = head.header(name.as_str(), value.as_bytes());
+        }
+    }
+    send.send_response(head.body(())?).await?;
+    while let Some(chunk) = response.chunk().await? {
+        send.send_data(chunk).await?;
+    }
+    send.finish().await?;
+    Ok(())
+}
+
+// 把 HTTP/3 请求体的数据帧写入通道，读取出错时把错误传给转发的请求
+async fn forward_body(
+    mut recv: RequestStream<h3_quinn::RecvStream, Bytes>,
+    mut tx: mpsc::Sender<Result<Bytes, std::io::Error>>,
+) {
+    loop {
+        let frame = match recv.recv_data().await {
+            Ok(Some(mut data)) => Ok(data.copy_to_bytes(data.remaining())),
+            Ok(None) => break,
+            Err(err) => Err(std::io::Error::other(err.to_string())),
+        };
+        let failed = frame.is_err();
+        if tx.send(frame).await.is_err() || failed {
+            break;
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::config;
+use crate::err::AppError;
+use crate::middleware;
+use ntex::http::body::{Body, ResponseBody};
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::http::{HeaderMap, Method, StatusCode};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+// --- 幂等重试：写请求（PUT、POST、DELETE）带 Idempotency-Key 头时，--idempotency-window-secs
+// 内以相同的键重试（同一访问密钥）直接返回第一次的状态码、响应头和响应体，不再执行，
+// 避免客户端重试产生重复的对象版本或重复删除。键用于不同的请求（方法、路径、查询参数或
+// 请求体哈希不同）时返回 400，第一次请求还未完成时返回 409。5xx 响应不记录，重试时重新执行；
+// 流式响应体不记录。记录只保存在本节点内存中
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+// 重放的响应带该头
+pub const REPLAYED_HEADER: &str = "x-rs3-idempotent-replayed";
+
+// 最多保存的记录数，超过后（清理过期记录后）新请求不再记录
+const MAX_ENTRIES: usize = 10000;
+// 键的最大长度
+const MAX_KEY_LEN: usize = 255;
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| Mutex::new(Cache::default()));
+
+// 记录的响应
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: CachedBody,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachedBody {
+    None,
+    Empty,
+    Bytes(Bytes),
+}
+
+impl CachedBody {
+    // 一次性的响应体，流式响应体为空
+    fn of(body: &ResponseBody<Body>) -> Option<Self> {
+        let (ResponseBody::Body(body) | ResponseBody::Other(body)) = body;
+        match body {
+            Body::None => Some(CachedBody::None),
+            Body::Empty => Some(CachedBody::Empty),
+            Body::Bytes(bytes) => Some(CachedBody::Bytes(bytes.clone())),
+            Body::Message(_) => None,
+        }
+    }
+
+    fn into_body(self) -> Body {
+        match self {
+            CachedBody::None => Body::None,
+            CachedBody::Empty => Body::Empty,
+            CachedBody::Bytes(bytes) => Body::Bytes(bytes),
+        }
+    }
+}
+
+// 收到带键的请求时的处理方式
+#[derive(Debug)]
+pub enum Begin {
+    // 第一次收到，执行请求并在完成后记录
+    Execute,
+    // 第一次的响应
+    Replay(CachedResponse),
+    // 第一次请求还未完成
+    InFlight,
+    // 键已用于其他请求
+    Mismatch,
+    // 记录已满，执行请求但不记录
+    Untracked,
+}
+
+struct Entry {
+    fingerprint: String,
+    created: Instant,
+    // 为空时第一次请求还未完成
+    response: Option<CachedResponse>,
+}
+
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<String, Entry>,
+}
+
+impl Cache {
+    pub fn begin(&mut self, key: &str, fingerprint: &str, window: Duration, now: Instant) -> Begin {
+        if let Some(entry) = self.entries.get(key) {
+            if now.duration_since(entry.created) < window {
+                if entry.fingerprint != fingerprint {
+                    return Begin::Mismatch;
+                }
+                return match &entry.response {
+                    Some(response) => Begin::Replay(response.clone()),
+                    None => Begin::InFlight,
+                };
+            }
+        }
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries
+                .retain(|_, entry| now.duration_since(entry.created) < window);
+            if self.entries.len() >= MAX_ENTRIES {
+                return Begin::Untracked;
+            }
+        }
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                fingerprint: fingerprint.to_string(),
+                created: now,
+                response: None,
+            },
+        );
+        Begin::Execute
+    }
+
+    // 记录第一次请求的响应，5xx 或没有响应（请求被取消）时删除记录，允许重试重新执行
+    pub fn finish(&mut self, key: &str, response: Option<CachedResponse>) {
+        match response.filter(|response| !response.status.is_server_error()) {
+            Some(response) => {
+                if let Some(entry) = self.entries.get_mut(key) {
+                    entry.response = Some(response);
+                }
+            }
+            None => {
+                self.entries.remove(key);
+            }
+        }
+    }
+}
+
+// 同一个键的请求是否相同：方法、路径、查询参数和签名中的请求体哈希
+pub fn fingerprint(method: &str, path: &str, query: &str, content_sha256: Option<&str>) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        method,
+        path,
+        query,
+        content_sha256.unwrap_or_default()
+    )
+}
+
+// 请求未完成（被取消或出错）时删除未完成的记录
+struct Pending<'a> {
+    key: &'a str,
+    done: bool,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            CACHE.lock().unwrap().finish(self.key, None);
+        }
+    }
+}
+
+pub struct Idempotency;
+
+impl<S> Middleware<S> for Idempotency {
+    type Service = IdempotencyMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        IdempotencyMiddleware { service }
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: S,
+}
+
+impl<S, Err> Service<web::WebRequest<Err>> for IdempotencyMiddleware<S>
+where
+    S: Service<web::WebRequest<Err>, Response = web::WebResponse, Error = web::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = web::WebResponse;
+    type Error = web::Error;
+
+    ntex::forward_poll_ready!(service);
+
+    async fn call(
+        &self,
+        req: web::WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let window = Duration::from_secs(config::get().idempotency_window_secs);
+        let is_write = matches!(*req.method(), Method::PUT | Method::POST | Method::DELETE);
+        let idempotency_key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let Some(idempotency_key) = idempotency_key.filter(|_| is_write && !window.is_zero())
+        else {
+            return ctx.call(&self.service, req).await;
+        };
+        if idempotency_key.is_empty() || idempotency_key.len() > MAX_KEY_LEN {
+            let err = AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "Idempotency-Key must be 1 to 255 characters",
+            );
+            return Ok(middleware::error_response(req, err));
+        }
+        // 按访问密钥区分，不同用户使用相同的键互不影响
+        let key = format!(
+            "{}\n{}",
+            middleware::access_key_of(&req).unwrap_or_default(),
+            idempotency_key
+        );
+        let fingerprint = fingerprint(
+            req.method().as_str(),
+            req.path(),
+            req.query_string(),
+            req.headers()
+                .get("x-amz-content-sha256")
+                .and_then(|v| v.to_str().ok()),
+        );
+        let begin = CACHE
+            .lock()
+            .unwrap()
+            .begin(&key, &fingerprint, window, Instant::now());
+        match begin {
+            Begin::Execute => {}
+            Begin::Untracked => return ctx.call(&self.service, req).await,
+            Begin::Replay(cached) => {
+                let mut resp = web::HttpResponse::new(cached.status);
+                *resp.headers_mut() = cached.headers;
+                resp.headers_mut().insert(
+                    HeaderName::from_static(REPLAYED_HEADER),
+                    HeaderValue::from_static("true"),
+                );
+                let (request, _) = req.into_parts();
+                return Ok(web::WebResponse::new(
+                    resp.set_body(cached.body.into_body()),
+                    request,
+                ));
+            }
+            Begin::InFlight => {
+                let err = AppError::s3(
+                    StatusCode::CONFLICT,
+                    "OperationAborted",
+                    "A request with this Idempotency-Key is still in progress",
+                );
+                return Ok(middleware::error_response(req, err));
+            }
+            Begin::Mismatch => {
+                let err = AppError::s3(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidArgument",
+                    "Idempotency-Key was already used for a different request",
+                );
+                return Ok(middleware::error_response(req, err));
+            }
+        }
+        let mut pending = Pending {
+            key: &key,
+            done: false,
+        };
+        let res = ctx.call(&self.service, req).await?;
+        let cached = CachedBody::of(res.response().body()).map(|body| CachedResponse {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body,
+        });
+        CACHE.lock().unwrap().finish(&key, cached);
+        pending.done = true;
+        Ok(res)
+    }
+}
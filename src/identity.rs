@@ -0,0 +1,220 @@
+use crate::config;
+use crate::keys;
+use crate::middleware;
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+// --- 访问密钥：除启动参数中的根密钥外，可通过外部的认证回调（webhook）按访问密钥查询
+// 密钥及权限，结果在本地缓存
+
+// 认证回调的超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+// 回调确认密钥不存在时的缓存时间
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+// S3 请求的操作类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    // 读取对象或桶配置（GET/HEAD 对象、HEAD 桶）
+    Read,
+    // 列出桶或对象
+    List,
+    // 上传、拷贝对象，创建桶，写入桶配置
+    Write,
+    // 删除对象或桶
+    Delete,
+}
+
+impl Operation {
+    // 由请求方法和路径（/api/...）判断操作类别
+    pub fn of(method: &str, path: &str) -> Operation {
+        let rest = path.trim_start_matches("/api").trim_start_matches('/');
+        let is_object = rest.trim_end_matches('/').contains('/');
+        match (method, rest.is_empty(), is_object) {
+            ("DELETE", _, _) => Operation::Delete,
+            ("GET", true, _) | ("GET", false, false) => Operation::List,
+            ("GET" | "HEAD", _, _) => Operation::Read,
+            _ => Operation::Write,
+        }
+    }
+}
+
+// 访问密钥的权限
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Permissions {
+    // 只允许读取和列出
+    #[serde(default)]
+    pub read_only: bool,
+    // 允许的操作类别，为空时不限制
+    #[serde(default)]
+    pub operations: Vec<Operation>,
+    // 可访问的 "桶/键前缀"，不含 '/' 时表示整个桶；为空时不限制
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    // 是否可以调用 /admin 管理接口
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl Permissions {
+    // 根密钥的权限
+    pub fn full() -> Self {
+        Permissions {
+            admin: true,
+            ..Default::default()
+        }
+    }
+
+    // 是否允许以 operation 访问 object_path（"桶/键"，列出所有桶时为 None）；管理接口只看 admin。
+    // 有前缀范围时，含 "." 或 ".." 路径段的路径（如列出时的 prefix 参数）不匹配任何前缀
+    pub fn allows(&self, operation: Operation, object_path: Option<&str>, admin_api: bool) -> bool {
+        if admin_api {
+            return self.admin;
+        }
+        if self.read_only && !matches!(operation, Operation::Read | Operation::List) {
+            return false;
+        }
+        if !self.operations.is_empty() && !self.operations.contains(&operation) {
+            return false;
+        }
+        match object_path {
+            Some(path) if !self.prefixes.is_empty() => {
+                middleware::is_valid_api_path(path)
+                    && self
+                        .prefixes
+                        .iter()
+                        .any(|prefix| config::prefix_matches(prefix, path))
+            }
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub access_key: String,
+    pub secret_key: String,
+    // 轮换后仍在宽限期内的旧密钥
+    pub previous_secret_key: Option<String>,
+    pub permissions: Permissions,
+    // 服务账号所属父密钥的权限，请求需同时满足两者
+    pub inherited: Option<Permissions>,
+}
+
+impl Identity {
+    pub fn allows(&self, operation: Operation, object_path: Option<&str>, admin_api: bool) -> bool {
+        self.permissions.allows(operation, object_path, admin_api)
+            && self
+                .inherited
+                .as_ref()
+                .is_none_or(|p| p.allows(operation, object_path, admin_api))
+    }
+}
+
+// 认证回调的响应体
+#[derive(Debug, Deserialize)]
+struct WebhookResponse {
+    secret_key: String,
+    #[serde(flatten)]
+    permissions: Permissions,
+}
+
+// 认证回调的查询结果，identity 为 None 表示密钥不存在
+struct CacheEntry {
+    cached_at: Instant,
+    identity: Option<Identity>,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<String, CacheEntry>>> = LazyLock::new(Default::default);
+
+// 查询访问密钥：依次匹配本地密钥库、根密钥、缓存和认证回调；密钥不存在时返回 None。
+// 服务账号同时带上父密钥的权限，父密钥不存在时服务账号失效
+pub(crate) async fn resolve(
+    access_key: &str,
+    root: (&str, &str),
+) -> anyhow::Result<Option<Identity>> {
+    match keys::lookup(access_key) {
+        Some((mut identity, Some(parent))) => {
+            let Some(parent) = resolve_key(&parent, root).await? else {
+                return Ok(None);
+            };
+            identity.inherited = Some(parent.permissions);
+            Ok(Some(identity))
+        }
+        Some((identity, None)) => Ok(Some(identity)),
+        None => resolve_key(access_key, root).await,
+    }
+}
+
+// 查询不是服务账号的访问密钥
+async fn resolve_key(access_key: &str, root: (&str, &str)) -> anyhow::Result<Option<Identity>> {
+    if let Some((identity, _)) = keys::lookup(access_key) {
+        return Ok(Some(identity));
+    }
+    if access_key == root.0 {
+        return Ok(Some(Identity {
+            access_key: root.0.to_string(),
+            secret_key: root.1.to_string(),
+            previous_secret_key: None,
+            permissions: Permissions::full(),
+            inherited: None,
+        }));
+    }
+    let Some(url) = config::get().auth_webhook.as_deref() else {
+        return Ok(None);
+    };
+    if let Some(entry) = CACHE.lock().unwrap().get(access_key) {
+        let ttl = match entry.identity {
+            Some(_) => Duration::from_secs(config::get().auth_cache_secs),
+            None => NEGATIVE_CACHE_TTL,
+        };
+        if entry.cached_at.elapsed() < ttl {
+            return Ok(entry.identity.clone());
+        }
+    }
+    let identity = call_webhook(url, access_key).await?;
+    CACHE.lock().unwrap().insert(
+        access_key.to_string(),
+        CacheEntry {
+            cached_at: Instant::now(),
+            identity: identity.clone(),
+        },
+    );
+    Ok(identity)
+}
+
+// 向认证回调 POST {"access_key": ...}：200 返回密钥和权限，404/403 表示密钥不存在，
+// 其他状态视为回调故障（不缓存）
+async fn call_webhook(url: &str, access_key: &str) -> anyhow::Result<Option<Identity>> {
+    let client = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()?;
+    let resp = client
+        .post(url)
+        .json(&serde_json::json!({ "access_key": access_key }))
+        .send()
+        .await
+        .context("调用认证回调失败")?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::FORBIDDEN {
+        info!("认证回调未找到访问密钥 {}", access_key);
+        return Ok(None);
+    }
+    if !status.is_success() {
+        warn!("认证回调返回 {}", status);
+        anyhow::bail!("认证回调返回 {}", status);
+    }
+    let body: WebhookResponse = resp.json().await.context("解析认证回调响应失败")?;
+    Ok(Some(Identity {
+        access_key: access_key.to_string(),
+        secret_key: body.secret_key,
+        previous_secret_key: None,
+        permissions: body.permissions,
+        inherited: None,
+    }))
+}
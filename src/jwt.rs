@@ -0,0 +1,214 @@
+use crate::config;
+use crate::config::JwtConfig;
+use anyhow::{anyhow, bail, Context};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+// --- 管理接口的 JWT 认证：校验 Bearer 令牌的签名、有效期（以及配置的 iss/aud），
+// 再按角色声明映射为管理员或只读权限
+
+// 遇到未知 kid 时重新拉取 JWKS 的最小间隔，避免伪造的令牌频繁触发请求
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+// 拉取 OIDC 配置与 JWKS 的超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdminRole {
+    Admin,
+    ReadOnly,
+}
+
+impl AdminRole {
+    // 只读角色只能调用 GET/HEAD 管理接口
+    pub fn allows(&self, method: &ntex::http::Method) -> bool {
+        match self {
+            AdminRole::Admin => true,
+            AdminRole::ReadOnly => {
+                matches!(*method, ntex::http::Method::GET | ntex::http::Method::HEAD)
+            }
+        }
+    }
+}
+
+// 按 '.' 分隔的路径取出角色声明，支持字符串数组或以空格分隔的字符串（如 scope）
+fn claim_values(claims: &Value, path: &str) -> Vec<String> {
+    let value = path
+        .split('.')
+        .try_fold(claims, |value, name| value.get(name));
+    match value {
+        Some(Value::String(s)) => s.split_whitespace().map(str::to_string).collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// 由令牌声明得到管理权限，同时拥有两种角色时取管理员；都没有时返回 None
+pub fn role_from_claims(claims: &Value, cfg: &JwtConfig) -> Option<AdminRole> {
+    let roles = claim_values(claims, &cfg.role_claim);
+    if roles.contains(&cfg.admin_role) {
+        Some(AdminRole::Admin)
+    } else if roles.contains(&cfg.readonly_role) {
+        Some(AdminRole::ReadOnly)
+    } else {
+        None
+    }
+}
+
+// 取出 Authorization: Bearer 中的令牌
+pub(crate) fn bearer_token(headers: &ntex::http::HeaderMap) -> Option<String> {
+    let value = headers.get("Authorization")?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+}
+
+static PUBLIC_KEYS: OnceLock<Vec<DecodingKey>> = OnceLock::new();
+
+struct Jwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+static JWKS: RwLock<Option<Jwks>> = RwLock::new(None);
+
+// 读取配置的 PEM 公钥，依次按 RSA、EC、Ed25519 解析；无法解析的文件忽略
+fn public_keys() -> &'static [DecodingKey] {
+    PUBLIC_KEYS.get_or_init(|| {
+        config::get()
+            .jwt
+            .public_keys
+            .iter()
+            .filter_map(|path| {
+                let pem = match std::fs::read(path) {
+                    Ok(pem) => pem,
+                    Err(err) => {
+                        warn!("读取 JWT 公钥 {:?} 失败: {}", path, err);
+                        return None;
+                    }
+                };
+                let key = DecodingKey::from_rsa_pem(&pem)
+                    .or_else(|_| DecodingKey::from_ec_pem(&pem))
+                    .or_else(|_| DecodingKey::from_ed_pem(&pem));
+                if key.is_err() {
+                    warn!("JWT 公钥 {:?} 不是 RSA/EC/Ed25519 PEM", path);
+                }
+                key.ok()
+            })
+            .collect()
+    })
+}
+
+#[derive(Deserialize)]
+struct OidcConfiguration {
+    jwks_uri: String,
+}
+
+async fn fetch_jwks(issuer: &str) -> anyhow::Result<JwkSet> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    let discovery = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let oidc: OidcConfiguration = client
+        .get(&discovery)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("解析 OIDC 配置失败")?;
+    let keys: JwkSet = client
+        .get(&oidc.jwks_uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("解析 JWKS 失败")?;
+    info!(
+        "已从 {} 获取 {} 个 JWT 验证密钥",
+        oidc.jwks_uri,
+        keys.keys.len()
+    );
+    Ok(keys)
+}
+
+// 在 JWKS 中按 kid 查找密钥；找不到且距上次拉取超过间隔时重新拉取（签发方轮换密钥）
+async fn jwks_key(issuer: &str, kid: Option<&str>) -> anyhow::Result<DecodingKey> {
+    let find = |jwks: &Jwks| {
+        let jwk = match kid {
+            Some(kid) => jwks.keys.find(kid),
+            None => jwks.keys.keys.first(),
+        };
+        jwk.map(DecodingKey::from_jwk)
+    };
+    let stale = {
+        let jwks = JWKS.read().unwrap();
+        match jwks.as_ref() {
+            Some(jwks) => match find(jwks) {
+                Some(key) => return Ok(key?),
+                None => jwks.fetched_at.elapsed() >= JWKS_REFRESH_INTERVAL,
+            },
+            None => true,
+        }
+    };
+    if !stale {
+        bail!("未知的 JWT 密钥 {:?}", kid);
+    }
+    let jwks = Jwks {
+        keys: fetch_jwks(issuer).await?,
+        fetched_at: Instant::now(),
+    };
+    let key = find(&jwks);
+    *JWKS.write().unwrap() = Some(jwks);
+    Ok(key.ok_or_else(|| anyhow!("未知的 JWT 密钥 {:?}", kid))??)
+}
+
+fn validation(alg: Algorithm, cfg: &JwtConfig) -> Validation {
+    let mut validation = Validation::new(alg);
+    if let Some(issuer) = &cfg.oidc_issuer {
+        validation.set_issuer(&[issuer]);
+    }
+    match &cfg.audience {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+    validation
+}
+
+// 校验令牌并返回其管理权限；令牌有效但没有对应角色时返回 None
+pub(crate) async fn authorize(token: &str) -> anyhow::Result<Option<AdminRole>> {
+    let cfg = &config::get().jwt;
+    let header = decode_header(token).context("无效的 JWT")?;
+    let validation = validation(header.alg, cfg);
+    let claims = match header.alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            let secret = cfg.secret.as_ref().context("未配置 JWT 共享密钥")?;
+            let key = DecodingKey::from_secret(secret.as_bytes());
+            decode::<Value>(token, &key, &validation)?.claims
+        }
+        _ => {
+            let local = public_keys()
+                .iter()
+                .find_map(|key| decode::<Value>(token, key, &validation).ok());
+            match (local, &cfg.oidc_issuer) {
+                (Some(data), _) => data.claims,
+                (None, Some(issuer)) => {
+                    let key = jwks_key(issuer, header.kid.as_deref()).await?;
+                    decode::<Value>(token, &key, &validation)?.claims
+                }
+                (None, None) => bail!("JWT 签名校验失败"),
+            }
+        }
+    };
+    Ok(role_from_claims(&claims, cfg))
+}
@@ -0,0 +1,404 @@
+use crate::api::DATA_DIR;
+use crate::durability;
+use crate::err::AppError;
+use crate::identity::{self, Identity, Permissions};
+use crate::kms;
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::util::cry;
+use crate::HandlerResponse;
+use anyhow::{anyhow, Context};
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+// --- 本地密钥库：由管理接口维护的访问密钥（轮换后的根密钥等），通过 raft 同步到各节点，
+// 以主密钥加密保存在 DATA_DIR/access-keys
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/keys", web::get().to(list_keys))
+        .route(
+            "/admin/keys/{access_key}/rotate",
+            web::post().to(rotate_key),
+        )
+        .route(
+            "/admin/service-accounts",
+            web::get().to(list_service_accounts),
+        )
+        .route(
+            "/admin/service-accounts",
+            web::post().to(create_service_account),
+        )
+        .route(
+            "/admin/service-accounts/{access_key}",
+            web::delete().to(delete_service_account),
+        );
+}
+
+// 密钥库文件名
+const KEYS_FILE: &str = "access-keys";
+// 轮换后旧密钥默认继续有效的秒数
+const DEFAULT_GRACE_SECS: u64 = 24 * 3600;
+
+// 轮换前的密钥，过期前仍可签名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousSecret {
+    pub secret_key: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredKey {
+    pub secret_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PreviousSecret>,
+    #[serde(default)]
+    pub permissions: Permissions,
+    // 服务账号所属的父密钥，权限不会超出父密钥
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub type KeyStore = BTreeMap<String, StoredKey>;
+
+static ROOT: OnceLock<(String, String)> = OnceLock::new();
+static STORE: RwLock<Option<KeyStore>> = RwLock::new(None);
+
+// 记录启动参数中的根密钥，轮换根密钥时作为旧密钥
+pub(crate) fn set_root(access_key: String, secret_key: String) {
+    let _ = ROOT.set((access_key, secret_key));
+}
+
+// 启动参数中的根密钥
+pub(crate) fn root() -> Option<(String, String)> {
+    ROOT.get().cloned()
+}
+
+// 当前的根密钥，轮换后使用密钥库中的新密钥
+pub(crate) fn root_credentials() -> Option<(String, String)> {
+    let (access_key, secret_key) = root()?;
+    match lookup(&access_key) {
+        Some((identity, _)) => Some((access_key, identity.secret_key)),
+        None => Some((access_key, secret_key)),
+    }
+}
+
+pub(crate) fn keys_path() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(KEYS_FILE)
+}
+
+fn decode_store(bytes: &[u8]) -> anyhow::Result<KeyStore> {
+    let plain = kms::decrypt(bytes)?;
+    serde_json::from_slice(&plain).context("解析密钥库失败")
+}
+
+fn load_store() -> KeyStore {
+    match std::fs::read(keys_path()) {
+        Ok(bytes) => decode_store(&bytes).unwrap_or_else(|err| {
+            warn!("读取密钥库失败: {}", err);
+            KeyStore::new()
+        }),
+        Err(_) => KeyStore::new(),
+    }
+}
+
+// 当前密钥库的副本
+pub(crate) fn snapshot() -> KeyStore {
+    if let Some(store) = STORE.read().unwrap().as_ref() {
+        return store.clone();
+    }
+    let store = load_store();
+    *STORE.write().unwrap() = Some(store.clone());
+    store
+}
+
+// 应用 raft 日志中的密钥库（已加密），写入文件并更新内存中的副本
+pub(crate) fn apply(bytes: &[u8]) -> anyhow::Result<()> {
+    let store = decode_store(bytes)?;
+    let path = keys_path();
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    // 日志中的密钥库可能是用旧的主密钥加密的
+    std::fs::write(&path, kms::reseal(bytes)?).context("写入密钥库失败")?;
+    durability::enqueue(path);
+    *STORE.write().unwrap() = Some(store);
+    Ok(())
+}
+
+// 通过 raft 写入新的密钥库
+pub(crate) async fn save(app: &App, store: &KeyStore) -> anyhow::Result<()> {
+    let plain = serde_json::to_vec(store)?;
+    let keys = kms::encrypt(&plain)?;
+    app.client_write(Request::SetAccessKeys { keys }).await?;
+    Ok(())
+}
+
+// 密钥库中的访问密钥及其父密钥，旧密钥已过期时忽略
+pub(crate) fn lookup(access_key: &str) -> Option<(Identity, Option<String>)> {
+    let store = snapshot();
+    let key = store.get(access_key)?;
+    let identity = Identity {
+        access_key: access_key.to_string(),
+        secret_key: key.secret_key.clone(),
+        previous_secret_key: key
+            .previous
+            .as_ref()
+            .filter(|p| p.expires_at > Utc::now())
+            .map(|p| p.secret_key.clone()),
+        permissions: key.permissions.clone(),
+        inherited: None,
+    };
+    Some((identity, key.parent.clone()))
+}
+
+// 随机生成与 AWS 格式相同的 20 位访问密钥
+fn gen_access_key() -> String {
+    hex::encode_upper(&cry::gen_data_key()[..10])
+}
+
+// 随机生成与 AWS 格式相同的 40 位密钥
+pub(crate) fn gen_secret_key() -> String {
+    STANDARD_NO_PAD.encode(&cry::gen_data_key()[..30])
+}
+
+// 以 target=audit 记录一条审计事件（JSON）
+pub(crate) fn audit(event: &str, access_key: &str, extra: serde_json::Value) {
+    let mut record = serde_json::json!({
+        "time": Utc::now(),
+        "event": event,
+        "access_key": access_key,
+    });
+    if let (Some(record), serde_json::Value::Object(extra)) = (record.as_object_mut(), extra) {
+        record.extend(extra);
+    }
+    info!(target: "audit", "{}", record);
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeySummary {
+    pub access_key: String,
+    pub created_at: DateTime<Utc>,
+    pub previous_expires_at: Option<DateTime<Utc>>,
+    pub permissions: Permissions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+fn summaries(filter: impl Fn(&StoredKey) -> bool) -> Vec<KeySummary> {
+    snapshot()
+        .into_iter()
+        .filter(|(_, key)| filter(key))
+        .map(|(access_key, key)| KeySummary {
+            access_key,
+            created_at: key.created_at,
+            previous_expires_at: key.previous.map(|p| p.expires_at),
+            permissions: key.permissions,
+            parent: key.parent,
+        })
+        .collect()
+}
+
+// 列出密钥库中的访问密钥（不含密钥）
+pub async fn list_keys() -> HandlerResponse {
+    Ok(HttpResponse::Ok().json(&summaries(|_| true)))
+}
+
+#[derive(Deserialize)]
+pub struct RotateQuery {
+    // 旧密钥继续有效的秒数，为 0 时立即失效
+    pub grace: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotateResult {
+    pub access_key: String,
+    pub secret_key: String,
+    pub previous_expires_at: DateTime<Utc>,
+}
+
+// 为访问密钥生成新密钥，旧密钥在宽限期内仍然有效；根密钥首次轮换时写入密钥库
+pub async fn rotate_key(
+    req: web::HttpRequest,
+    Query(query): Query<RotateQuery>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let access_key = req
+        .match_info()
+        .get("access_key")
+        .context("缺少访问密钥")?
+        .to_string();
+    let mut store = snapshot();
+    let current = match store.get(&access_key) {
+        Some(key) => key.clone(),
+        None => match ROOT.get() {
+            Some((root, secret)) if *root == access_key => StoredKey {
+                secret_key: secret.clone(),
+                previous: None,
+                permissions: Permissions::full(),
+                parent: None,
+                created_at: Utc::now(),
+            },
+            _ => {
+                return Err(AppError::s3(
+                    StatusCode::NOT_FOUND,
+                    "NoSuchKey",
+                    "访问密钥不在本地密钥库中（外部认证的密钥需在外部轮换）",
+                ))
+            }
+        },
+    };
+    let grace = query.grace.unwrap_or(DEFAULT_GRACE_SECS);
+    let expires_at = Utc::now() + chrono::Duration::seconds(grace as i64);
+    let secret_key = gen_secret_key();
+    store.insert(
+        access_key.clone(),
+        StoredKey {
+            secret_key: secret_key.clone(),
+            previous: Some(PreviousSecret {
+                secret_key: current.secret_key,
+                expires_at,
+            }),
+            ..current
+        },
+    );
+    save(&state, &store)
+        .await
+        .map_err(|err| anyhow!("保存密钥库失败: {}", err))?;
+    audit(
+        "access_key_rotated",
+        &access_key,
+        serde_json::json!({ "previous_expires_at": expires_at }),
+    );
+    Ok(HttpResponse::Ok().json(&RotateResult {
+        access_key,
+        secret_key,
+        previous_expires_at: expires_at,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ServiceAccountQuery {
+    pub parent: Option<String>,
+}
+
+// 列出服务账号，可按父密钥过滤
+pub async fn list_service_accounts(Query(query): Query<ServiceAccountQuery>) -> HandlerResponse {
+    let accounts = summaries(|key| match (&key.parent, &query.parent) {
+        (Some(parent), Some(wanted)) => parent == wanted,
+        (parent, None) => parent.is_some(),
+        _ => false,
+    });
+    Ok(HttpResponse::Ok().json(&accounts))
+}
+
+#[derive(Deserialize)]
+pub struct CreateServiceAccount {
+    pub parent: String,
+    // 服务账号的权限，实际权限为其与父密钥权限的交集；服务账号不能调用管理接口
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountCredentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub parent: String,
+}
+
+fn invalid_request(message: &str) -> AppError {
+    AppError::s3(
+        StatusCode::BAD_REQUEST,
+        "InvalidRequest",
+        message.to_string(),
+    )
+}
+
+// 为已有的访问密钥（本地密钥库、根密钥或认证回调中的密钥）创建服务账号
+pub async fn create_service_account(
+    body: web::types::Json<CreateServiceAccount>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let CreateServiceAccount {
+        parent,
+        mut permissions,
+    } = body.into_inner();
+    let mut store = snapshot();
+    if store.get(&parent).is_some_and(|key| key.parent.is_some()) {
+        return Err(invalid_request("服务账号不能再创建服务账号"));
+    }
+    let (root, root_secret) = ROOT.get().context("根密钥未初始化")?;
+    if identity::resolve(&parent, (root, root_secret))
+        .await?
+        .is_none()
+    {
+        return Err(invalid_request("父密钥不存在"));
+    }
+    permissions.admin = false;
+    let access_key = gen_access_key();
+    let secret_key = gen_secret_key();
+    store.insert(
+        access_key.clone(),
+        StoredKey {
+            secret_key: secret_key.clone(),
+            previous: None,
+            permissions: permissions.clone(),
+            parent: Some(parent.clone()),
+            created_at: Utc::now(),
+        },
+    );
+    save(&state, &store)
+        .await
+        .map_err(|err| anyhow!("保存密钥库失败: {}", err))?;
+    audit(
+        "service_account_created",
+        &access_key,
+        serde_json::json!({ "parent": parent, "permissions": permissions }),
+    );
+    Ok(HttpResponse::Ok().json(&ServiceAccountCredentials {
+        access_key,
+        secret_key,
+        parent,
+    }))
+}
+
+pub async fn delete_service_account(
+    req: web::HttpRequest,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let access_key = req
+        .match_info()
+        .get("access_key")
+        .context("缺少访问密钥")?
+        .to_string();
+    let mut store = snapshot();
+    if !store
+        .get(&access_key)
+        .is_some_and(|key| key.parent.is_some())
+    {
+        return Err(AppError::s3(
+            StatusCode::NOT_FOUND,
+            "NoSuchKey",
+            "服务账号不存在",
+        ));
+    }
+    store.remove(&access_key);
+    save(&state, &store)
+        .await
+        .map_err(|err| anyhow!("保存密钥库失败: {}", err))?;
+    audit(
+        "service_account_deleted",
+        &access_key,
+        serde_json::json!({}),
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
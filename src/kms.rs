@@ -0,0 +1,316 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::config::{self, MasterKeySource};
+use crate::fs;
+use crate::keys;
+use crate::snapshot;
+use crate::util::cry;
+use crate::versioning;
+use anyhow::{anyhow, bail, Context};
+use log::{info, warn};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// --- 主密钥管理：主密钥加密桶的数据密钥、桶目录之外的元数据（历史版本、暂存批次、快照）和访问
+// 密钥库。主密钥以 `编号:64 位十六进制` 的列表给出，来自 --master-key（S3_MASTER_KEY）、
+// --master-key-file 或 --master-key-command 输出，最后一个用于加密，其余只用于解密。密文
+// 头部记录加密所用的密钥编号；启动时把用其他密钥加密的文件改用当前密钥重新加密。轮换时在
+// 列表末尾追加新密钥并重启，旧密钥在所有节点都已重启、raft 日志中用旧密钥加密的条目已压缩
+// 后才能删除。集群各节点必须使用相同的密钥列表。未配置主密钥时使用程序内置的密钥并按旧格式
+// 写入（不带头部），--secure 时拒绝启动
+
+// 内置密钥的编号；不带头部的旧数据也用内置密钥解密
+pub const BUILTIN_KEY_ID: &str = "builtin";
+
+// 旧版本内置在程序中的密钥，只用于未配置主密钥时和解密旧数据
+const BUILTIN_KEY: &[u8; 32] = b"000102030405060708090A0B0C0D0E0F";
+
+// 密文头部：魔数、密钥编号长度（1 字节）和密钥编号，之后为 IV 和密文。旧格式的 IV 是可打印
+// 字符，不会以 0 开头
+const HEADER_MAGIC: &[u8] = b"\0RSK";
+
+static KEYRING: OnceLock<Keyring> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct MasterKey {
+    pub id: String,
+    key: Vec<u8>,
+}
+
+// 主密钥列表，最后一个用于加密
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Vec<MasterKey>,
+}
+
+impl Keyring {
+    // 只有内置密钥
+    pub fn builtin() -> Self {
+        Keyring {
+            keys: vec![MasterKey {
+                id: BUILTIN_KEY_ID.to_string(),
+                key: BUILTIN_KEY.to_vec(),
+            }],
+        }
+    }
+
+    // 解析 `编号:十六进制` 列表，以换行或逗号分隔，忽略空行和 # 开头的注释
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keys: Vec<MasterKey> = vec![];
+        for entry in text.split(['\n', ',']).map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (id, hex_key) = entry
+                .split_once(':')
+                .ok_or_else(|| "invalid master key, expected ID:HEX".to_string())?;
+            let valid_id = !id.is_empty()
+                && id.len() <= u8::MAX as usize
+                && id
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b));
+            if !valid_id || id == BUILTIN_KEY_ID {
+                return Err(format!("invalid master key id `{}`", id));
+            }
+            let key = hex::decode(hex_key.trim())
+                .ok()
+                .filter(|key| key.len() == 32)
+                .ok_or_else(|| format!("master key `{}` must be 64 hex digits", id))?;
+            if keys.iter().any(|k| k.id == id) {
+                return Err(format!("duplicate master key id `{}`", id));
+            }
+            keys.push(MasterKey {
+                id: id.to_string(),
+                key,
+            });
+        }
+        if keys.is_empty() {
+            return Err("no master key given".to_string());
+        }
+        Ok(Keyring { keys })
+    }
+
+    // 用于加密的密钥编号
+    pub fn active_id(&self) -> &str {
+        &self.keys.last().unwrap().id
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.active_id() == BUILTIN_KEY_ID
+    }
+
+    fn key(&self, id: &str) -> Option<&[u8]> {
+        match self.keys.iter().find(|k| k.id == id) {
+            Some(key) => Some(&key.key),
+            None if id == BUILTIN_KEY_ID => Some(BUILTIN_KEY),
+            None => None,
+        }
+    }
+
+    // 用当前密钥加密，内置密钥按旧格式写入
+    pub fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let active = self.keys.last().unwrap();
+        let sealed = cry::aes_256_cbc_encrypt_with_key(&active.key, data)?;
+        if self.is_builtin() {
+            return Ok(sealed);
+        }
+        let mut out = Vec::with_capacity(HEADER_MAGIC.len() + 1 + active.id.len() + sealed.len());
+        out.extend_from_slice(HEADER_MAGIC);
+        out.push(active.id.len() as u8);
+        out.extend_from_slice(active.id.as_bytes());
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    // 按头部中的密钥编号解密，没有头部时用内置密钥
+    pub fn open(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (id, sealed) = split_header(data)?;
+        let id = id.unwrap_or(BUILTIN_KEY_ID);
+        let key = self
+            .key(id)
+            .ok_or_else(|| anyhow!("主密钥 {} 不在密钥列表中", id))?;
+        cry::aes_256_cbc_decrypt_with_key(key, sealed)
+    }
+}
+
+// 密文头部中的密钥编号，旧格式为空
+pub fn key_id(data: &[u8]) -> Option<&str> {
+    split_header(data).ok()?.0
+}
+
+fn split_header(data: &[u8]) -> anyhow::Result<(Option<&str>, &[u8])> {
+    let Some(rest) = data.strip_prefix(HEADER_MAGIC) else {
+        return Ok((None, data));
+    };
+    let Some((&len, rest)) = rest.split_first() else {
+        bail!("密文头部不完整");
+    };
+    if rest.len() < len as usize {
+        bail!("密文头部不完整");
+    }
+    let (id, sealed) = rest.split_at(len as usize);
+    let id = std::str::from_utf8(id).context("密文头部中的密钥编号无效")?;
+    Ok((Some(id), sealed))
+}
+
+fn keyring() -> &'static Keyring {
+    KEYRING.get_or_init(Keyring::builtin)
+}
+
+// 当前主密钥的编号
+pub(crate) fn active_key_id() -> String {
+    keyring().active_id().to_string()
+}
+
+// 用当前主密钥加密
+pub(crate) fn encrypt(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    keyring().seal(data)
+}
+
+// 用加密时的主密钥解密
+pub(crate) fn decrypt(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    keyring().open(data)
+}
+
+// 不是用当前主密钥加密时重新加密，否则原样返回
+pub(crate) fn reseal(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let keyring = keyring();
+    if keyring.is_builtin() || key_id(data) == Some(keyring.active_id()) {
+        return Ok(data.to_vec());
+    }
+    keyring.seal(&keyring.open(data)?)
+}
+
+// 按配置读取主密钥，需在状态机回放日志前调用
+pub(crate) fn load() -> anyhow::Result<()> {
+    let cfg = config::get();
+    let text = match &cfg.master_key {
+        MasterKeySource::Builtin if cfg.secure => {
+            bail!("--secure 模式下必须通过 --master-key、--master-key-file 或 --master-key-command 配置主密钥")
+        }
+        MasterKeySource::Builtin if cfg.plain_metadata => {
+            info!("对象元数据以明文保存");
+            return Ok(());
+        }
+        MasterKeySource::Builtin => {
+            warn!("未配置主密钥，使用程序内置的密钥加密元数据");
+            return Ok(());
+        }
+        MasterKeySource::Inline(text) => text.clone(),
+        MasterKeySource::File(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("读取主密钥文件 {:?} 失败", path))?,
+        MasterKeySource::Command(command) => run_key_command(command)?,
+    };
+    let keyring = Keyring::parse(&text).map_err(|err| anyhow!("主密钥无效: {}", err))?;
+    info!(
+        "已加载 {} 个主密钥，当前密钥 {}",
+        keyring.keys.len(),
+        keyring.active_id()
+    );
+    let _ = KEYRING.set(keyring);
+    Ok(())
+}
+
+// 执行密钥命令，标准输出为密钥列表
+fn run_key_command(command: &str) -> anyhow::Result<String> {
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("执行主密钥命令失败")?;
+    if !output.status.success() {
+        bail!(
+            "主密钥命令失败（{}）: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("主密钥命令的输出不是 UTF-8")
+}
+
+// 用主密钥加密的文件：桶的数据密钥（没有数据密钥的旧桶为其中的元数据）、历史版本、暂存批次
+// 和快照的元数据、访问密钥库
+fn master_key_files() -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let buckets_dir = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
+    for bucket in std::fs::read_dir(&buckets_dir).into_iter().flatten() {
+        let bucket_dir = bucket?.path();
+        if !bucket_dir.is_dir() {
+            continue;
+        }
+        let key_file = fs::bucket_key_path(&bucket_dir);
+        if key_file.exists() {
+            files.push(key_file);
+            // 旧版本补建数据密钥之前写入的元数据仍由主密钥加密，带密文头部
+            let mut meta_files = vec![];
+            fs::walk_meta_files(&bucket_dir, &mut meta_files)?;
+            files.extend(meta_files.into_iter().filter(|path| has_header(path)));
+        } else {
+            fs::walk_meta_files(&bucket_dir, &mut files)?;
+        }
+    }
+    let dirs = [fs::staging_root(), versioning::versions_root()];
+    for dir in dirs.into_iter().chain(snapshot::metadata_dirs()) {
+        if dir.is_dir() {
+            fs::walk_meta_files(&dir, &mut files)?;
+        }
+    }
+    let keys_file = keys::keys_path();
+    if keys_file.exists() {
+        files.push(keys_file);
+    }
+    Ok(files)
+}
+
+// 文件是否以密文头部开头，桶密钥加密的元数据以可打印的 IV 开头
+fn has_header(path: &Path) -> bool {
+    let mut magic = [0u8; HEADER_MAGIC.len()];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|_| magic == HEADER_MAGIC)
+}
+
+// 启动时把用其他主密钥加密的文件改用当前密钥重新加密，需在状态机回放日志前调用。未配置
+// 主密钥时不处理
+pub(crate) fn rewrap() -> anyhow::Result<()> {
+    let keyring = keyring();
+    if keyring.is_builtin() {
+        return Ok(());
+    }
+    let mut rewrapped = 0;
+    for path in master_key_files()? {
+        match rewrap_file(&path) {
+            Ok(true) => rewrapped += 1,
+            Ok(false) => {}
+            Err(err) => warn!("重新加密 {:?} 失败: {:#}", path, err),
+        }
+    }
+    if rewrapped > 0 {
+        info!(
+            "已用主密钥 {} 重新加密 {} 个文件",
+            keyring.active_id(),
+            rewrapped
+        );
+    }
+    Ok(())
+}
+
+// 文件不是用当前密钥加密时重新加密，返回是否重写
+fn rewrap_file(path: &Path) -> anyhow::Result<bool> {
+    let sealed = std::fs::read(path)?;
+    if fs::is_plain_metadata(&sealed) {
+        return Ok(false);
+    }
+    let resealed = reseal(&sealed)?;
+    if resealed == sealed {
+        return Ok(false);
+    }
+    let tmp = fs::tmp_sibling(path);
+    std::fs::write(&tmp, resealed)?;
+    if let Err(err) = fs::publish_file(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(err);
+    }
+    Ok(true)
+}
@@ -1,3 +1,4 @@
+use crate::config::ServerConfig;
 use crate::err::AppError;
 use crate::middleware::CredentialsV4;
 use crate::raft::app::App;
@@ -11,20 +12,80 @@ use ntex::web::HttpResponse;
 use ntex_cors::Cors;
 use openraft::Config;
 use std::collections::BTreeSet;
-use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+pub mod access;
+pub mod admin;
 pub mod api;
+pub mod archive;
+pub mod bench;
+pub mod capacity;
+pub mod cdc;
+pub mod checksum;
+pub mod chunkstats;
+pub mod client;
+pub mod clone;
+pub mod cluster;
+pub mod codec;
+pub mod compat;
+pub mod compression;
+pub mod conditional;
+pub mod config;
+pub mod copy;
+pub mod diagnostics;
+pub mod dns;
+mod durability;
+pub mod erasure;
 mod err;
+pub mod etag;
 pub mod fs;
+pub mod gc;
+pub mod headers;
+#[cfg(feature = "http3")]
+mod http3;
+pub mod idempotency;
+pub mod identity;
+pub mod jwt;
+mod keys;
+pub mod kms;
+pub mod lifecycle;
+pub mod limits;
+pub mod listing;
+pub mod logging;
+pub mod lz4;
 pub mod management;
 pub mod middleware;
 pub mod model;
+pub mod multipart;
+mod plugin;
+pub mod policy;
+mod pool;
+pub mod presign;
+#[cfg(feature = "profiling")]
+pub mod profiling;
 mod raft;
+pub mod range;
+pub mod recompress;
+pub mod rollback;
+pub mod scan;
+mod script;
+pub mod scrub;
+pub mod slowlog;
+pub mod snapshot;
+mod spool;
+pub mod sse;
+mod standby;
+pub mod startup;
+pub mod statsd;
 mod stream;
+pub mod tagging;
+pub mod tls;
 pub mod util;
+pub mod versioning;
+pub mod vhost;
+pub mod website;
 pub type HandlerResponse = Result<HttpResponse, AppError>;
 
 pub async fn start_example_raft_node<P>(
@@ -36,10 +97,38 @@ pub async fn start_example_raft_node<P>(
     access_key: String,
     secret_key: String,
     leader_http_addr: Option<String>,
+    port_file: Option<PathBuf>,
+    server_config: ServerConfig,
 ) -> std::io::Result<()>
 where
     P: AsRef<Path>,
 {
+    // 状态机应用日志时就会用到服务配置，需在创建 raft 实例前设置
+    let _ = config::SERVER_CONFIG.set(server_config);
+    // 分片和元数据的路径都在数据目录下，状态机回放日志前设置
+    api::DATA_DIR
+        .get_or_init(|| async {
+            PathBuf::from(fs_root.clone())
+                .join("data")
+                .to_string_lossy()
+                .to_string()
+        })
+        .await;
+    let moved = fs::migrate_legacy_chunks()
+        .map_err(|err| std::io::Error::other(format!("迁移旧分片目录失败: {:#}", err)))?;
+    if moved > 0 {
+        info!("已把工作目录下 data/file 中的 {} 个分片移到数据目录", moved);
+    }
+    cluster::set_local_node(node_id);
+    plugin::load().map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+    script::load().map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+    // 状态机回放日志时就会解密访问密钥库
+    kms::load().map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+    kms::rewrap().map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+    // 完成上次运行中途中断的暂存批次提交
+    raft::store::recover_staged_commits()
+        .map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+
     // Create a configuration for the raft instance.
     let config = Config {
         heartbeat_interval: 250,
@@ -62,12 +151,31 @@ where
         node_id,
         config.clone(),
         network,
-        log_store,
+        log_store.clone(),
         state_machine_store,
     )
     .await
     .unwrap();
 
+    // 先绑定监听地址，端口为 0 时由系统分配，之后以实际地址注册节点
+    let http_listener = std::net::TcpListener::bind(&http_addr)?;
+    let http_addr = http_listener.local_addr()?.to_string();
+    let tls_listener = match &config::get().tls {
+        Some(tls) => {
+            let acceptor =
+                tls::acceptor(tls).map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+            Some((std::net::TcpListener::bind(&tls.addr)?, acceptor))
+        }
+        None => None,
+    };
+    let tls_addr = match &tls_listener {
+        Some((listener, _)) => Some(listener.local_addr()?.to_string()),
+        None => None,
+    };
+    let http3_addr = spawn_http3(&http_addr)?;
+    let rpc_listener = tokio::net::TcpListener::bind(&rpc_addr).await?;
+    let rpc_addr = rpc_listener.local_addr()?.to_string();
+
     let mut set = BTreeSet::new();
     set.insert(node_id);
     let app = App {
@@ -75,51 +183,81 @@ where
         api_addr: http_addr.clone(),
         rpc_addr: rpc_addr.clone(),
         raft,
+        log_store,
         key_values: kvs,
         config,
         nodes: Arc::new(Mutex::new(set)),
     };
 
-    let addr: SocketAddr = rpc_addr.parse().unwrap();
     let raft_node = Raft::new(Arc::new(app.clone()));
     tokio::spawn(async move {
-        let addr = volo::net::Address::from(addr);
-
         info!("websocket server");
         volo_gen::rpc::raft::RaftServiceServer::new(raft_node)
-            .run(addr)
+            .make_codec(raft::network::make_codec())
+            .run(volo::net::incoming::DefaultIncoming::from(rpc_listener))
             .await
             .unwrap();
     });
 
-    // Create an application that will store all the instances created above, this will
-    // be later used on the actix-web services.
-    api::DATA_DIR
-        .get_or_init(|| async {
-            PathBuf::from(fs_root.clone())
-                .join("data")
-                .to_string_lossy()
-                .to_string()
-        })
-        .await;
-    let server_start = web::HttpServer::new(move || {
+    erasure::spawn_rebuild();
+    keys::set_root(access_key.clone(), secret_key.clone());
+    cdc::spawn().await?;
+    dns::spawn(&http_addr).await?;
+    script::set_app(app.clone());
+    access::spawn(app.clone());
+    lifecycle::spawn(app.clone());
+    statsd::spawn()?;
+    compression::spawn();
+    recompress::spawn();
+    gc::spawn();
+    scrub::spawn();
+    let standby_app = app.clone();
+    let standby_keys = (access_key.clone(), secret_key.clone());
+    let node_info = startup::NodeInfo {
+        node_id,
+        http_addr: http_addr.clone(),
+        tls_addr,
+        http3_addr,
+        rpc_addr: rpc_addr.clone(),
+        fs_root: fs_root.clone(),
+        data_dir: api::DATA_DIR.get().cloned().unwrap_or_default(),
+        access_key: access_key.clone(),
+        leader_http_addr: leader_http_addr.clone(),
+        master_key_id: kms::active_key_id(),
+    };
+    let mut server = web::HttpServer::new(move || {
         info!("web server");
+        pool::pin_current_thread();
+        diagnostics::spawn_lag_probe();
         let app = app.clone();
         web::App::new()
             .state(app)
+            // 最内层，记录的是处理函数的原始响应
+            .wrap(idempotency::Idempotency)
             .wrap(ntex::web::middleware::Logger::default())
+            .wrap(statsd::Metrics)
+            .wrap(slowlog::SlowLog)
             .wrap(Cors::default())
             // 应用 AWS 签名版本 4 的认证中间件。
             .wrap(CredentialsV4::new(access_key.clone(), secret_key.clone()))
+            // 最外层，认证和路由看到的都是改写后的路径风格请求
+            .wrap(vhost::VirtualHost)
             .configure(management::rest)
+            .configure(admin::rest)
             .configure(api::rest)
     })
-    .bind(&http_addr)
-    .unwrap()
-    .run();
+    .workers(match config::get().http_workers {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    })
+    .listen(http_listener)?;
+    if let Some((listener, acceptor)) = tls_listener {
+        server = server.listen_openssl(listener, acceptor)?;
+    }
+    let server_start = server.run();
 
     let client = reqwest::Client::new();
-    if let Some(addr) = leader_http_addr {
+    if let Some(addr) = &leader_http_addr {
         let response = client
             .post(format!("http://{}/cluster/add-learner", addr))
             .body(format!(
@@ -148,6 +286,54 @@ where
             .unwrap();
         info!("cluster init resp status {}", response.status());
     }
+    report_addrs(&http_addr, &rpc_addr, port_file.as_deref())?;
+    startup::report(node_info);
+    standby::spawn(standby_app, standby_keys.0, standby_keys.1);
     server_start.await?;
     Ok(())
 }
+
+// 配置了 --http3-addr 时启动 HTTP/3 监听，请求转发到 HTTP 监听，返回实际监听的地址
+#[cfg(feature = "http3")]
+fn spawn_http3(http_addr: &str) -> std::io::Result<Option<String>> {
+    let Some(tls) = &config::get().tls else {
+        return Ok(None);
+    };
+    let Some(addr) = &tls.http3_addr else {
+        return Ok(None);
+    };
+    let addr = http3::spawn(tls, addr, http_addr)
+        .map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+    info!("HTTP/3 监听 {}", addr);
+    Ok(Some(addr.to_string()))
+}
+
+#[cfg(not(feature = "http3"))]
+fn spawn_http3(_http_addr: &str) -> std::io::Result<Option<String>> {
+    match config::get()
+        .tls
+        .as_ref()
+        .and_then(|tls| tls.http3_addr.as_ref())
+    {
+        Some(_) => Err(std::io::Error::other(
+            "--http3-addr 需要编译 http3 特性（cargo build --features http3）",
+        )),
+        None => Ok(None),
+    }
+}
+
+// 节点就绪后在标准输出打印实际监听的地址，并把 HTTP 端口写入端口文件（先写临时文件再
+// 重命名，读取方不会读到不完整的内容）
+fn report_addrs(http_addr: &str, rpc_addr: &str, port_file: Option<&Path>) -> std::io::Result<()> {
+    println!("http-addr {}", http_addr);
+    println!("rpc-addr {}", rpc_addr);
+    if let Some(path) = port_file {
+        let port = http_addr
+            .rsplit_once(':')
+            .map_or(http_addr, |(_, port)| port);
+        let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+        std::fs::write(&tmp, format!("{}\n", port))?;
+        std::fs::rename(&tmp, path)?;
+    }
+    Ok(())
+}
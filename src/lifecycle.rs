@@ -0,0 +1,509 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::conditional;
+use crate::config;
+use crate::config::RestrictedOperation;
+use crate::fs::{self, Tag};
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::standby;
+use crate::tagging::TagEntry;
+use crate::versioning;
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// --- 桶生命周期：PUT/GET/DELETE /api/{bucket}?lifecycle 读写 S3 生命周期配置的一个子集，每条规则
+// 按 Filter（Prefix、Tag 或 And）或旧格式的 Prefix 选择对象，支持 Expiration（Days 或 Date）删除
+// 当前对象（开启版本控制时写入删除标记）、Expiration 的 ExpiredObjectDeleteMarker 删除已没有历史版本的
+// 删除标记，以及 AbortIncompleteMultipartUpload 中止过期的分片上传；
+// 不支持 Transition、NoncurrentVersionExpiration 等其他动作，出现时拒绝整个配置。
+// 规则由主节点每隔 --lifecycle-interval-secs 秒执行一次，删除和中止都通过 raft 写入；
+// Days 从对象最后修改（分片上传创建）起按 --lifecycle-day-secs 计算，到期后的下一轮执行时处理
+
+// 桶生命周期配置文件名
+const LIFECYCLE_CONFIG_FILE: &str = ".lifecycle.xml";
+
+// 与 S3 一致的限制
+const MAX_RULES: usize = 1000;
+const MAX_ID_LEN: usize = 255;
+
+// PutBucketLifecycleConfiguration 请求体，GET 时原样返回
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename = "LifecycleConfiguration")]
+pub struct LifecycleConfiguration {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(rename = "ID", default)]
+    pub id: Option<String>,
+    // Enabled 或 Disabled
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Filter", default)]
+    pub filter: Option<Filter>,
+    // 旧格式，与 Filter 不能同时出现
+    #[serde(rename = "Prefix", default)]
+    pub prefix: Option<String>,
+    #[serde(rename = "Expiration", default)]
+    pub expiration: Option<Expiration>,
+    #[serde(rename = "AbortIncompleteMultipartUpload", default)]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+// 规则选择的对象，全部为空时匹配整个桶
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Filter {
+    #[serde(rename = "Prefix", default)]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tag: Option<TagEntry>,
+    #[serde(rename = "And", default)]
+    pub and: Option<And>,
+}
+
+// 同时满足前缀和全部标签
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct And {
+    #[serde(rename = "Prefix", default)]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<TagEntry>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expiration {
+    #[serde(rename = "Days", default)]
+    pub days: Option<u64>,
+    // ISO 8601 时间，到达后删除全部匹配的对象
+    #[serde(rename = "Date", default)]
+    pub date: Option<String>,
+    // 为 true 时删除已没有任何历史版本的删除标记，不能与 Days、Date 或标签同时出现
+    #[serde(rename = "ExpiredObjectDeleteMarker", default)]
+    pub expired_object_delete_marker: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbortIncompleteMultipartUpload {
+    #[serde(rename = "DaysAfterInitiation")]
+    pub days_after_initiation: u64,
+}
+
+impl LifecycleConfiguration {
+    // 解析并校验生命周期配置
+    pub fn parse(xml: &str) -> Result<LifecycleConfiguration, String> {
+        let conf: LifecycleConfiguration =
+            quick_xml::de::from_str(xml).map_err(|err| err.to_string())?;
+        conf.validate()?;
+        Ok(conf)
+    }
+
+    // 校验规则：至少一条、ID 不重复，每条规则至少有一个动作且天数为正
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rules.is_empty() || self.rules.len() > MAX_RULES {
+            return Err(format!(
+                "Lifecycle configuration must have 1 to {} rules",
+                MAX_RULES
+            ));
+        }
+        for (i, rule) in self.rules.iter().enumerate() {
+            if let Some(id) = &rule.id {
+                if id.len() > MAX_ID_LEN {
+                    return Err("ID length should not exceed allowed limit of 255".to_string());
+                }
+                if self.rules[..i]
+                    .iter()
+                    .any(|other| other.id.as_ref() == Some(id))
+                {
+                    return Err("Rule ID must be unique".to_string());
+                }
+            }
+            if rule.status != "Enabled" && rule.status != "Disabled" {
+                return Err(format!("Unknown rule status `{}`", rule.status));
+            }
+            if rule.filter.is_some() && rule.prefix.is_some() {
+                return Err("Rule cannot have both Filter and Prefix".to_string());
+            }
+            if let Some(filter) = &rule.filter {
+                let set = [
+                    filter.prefix.is_some(),
+                    filter.tag.is_some(),
+                    filter.and.is_some(),
+                ];
+                if set.iter().filter(|&&set| set).count() > 1 {
+                    return Err("Filter must have only one of Prefix, Tag or And".to_string());
+                }
+            }
+            if rule.expiration.is_none() && rule.abort_incomplete_multipart_upload.is_none() {
+                return Err("At least one action needs to be specified in a rule".to_string());
+            }
+            if let Some(expiration) = &rule.expiration {
+                let marker = expiration.expired_object_delete_marker;
+                match (expiration.days, &expiration.date, marker) {
+                    (Some(0), None, None) => {
+                        return Err("Days must be a positive integer".to_string());
+                    }
+                    (Some(_), None, None) => {}
+                    (None, Some(date), None) => {
+                        DateTime::parse_from_rfc3339(date)
+                            .map_err(|_| format!("Invalid expiration date `{}`", date))?;
+                    }
+                    (None, None, Some(_)) => {
+                        if !rule.tags().is_empty() {
+                            return Err("ExpiredObjectDeleteMarker cannot be specified with Tags"
+                                .to_string());
+                        }
+                    }
+                    (_, _, Some(_)) => {
+                        return Err(
+                            "ExpiredObjectDeleteMarker cannot be specified with Days or Date"
+                                .to_string(),
+                        );
+                    }
+                    _ => {
+                        return Err("Expiration must have exactly one of Days or Date".to_string());
+                    }
+                }
+            }
+            if let Some(abort) = &rule.abort_incomplete_multipart_upload {
+                if abort.days_after_initiation == 0 {
+                    return Err("DaysAfterInitiation must be a positive integer".to_string());
+                }
+                if !rule.tags().is_empty() {
+                    return Err(
+                        "AbortIncompleteMultipartUpload cannot be specified with Tags".to_string(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Rule {
+    pub fn enabled(&self) -> bool {
+        self.status == "Enabled"
+    }
+
+    // 规则的键前缀，未设置时为空
+    pub fn prefix(&self) -> &str {
+        let filter = self.filter.as_ref();
+        filter
+            .and_then(|f| f.prefix.as_deref())
+            .or_else(|| filter.and_then(|f| f.and.as_ref()?.prefix.as_deref()))
+            .or(self.prefix.as_deref())
+            .unwrap_or_default()
+    }
+
+    // 对象需要带有的全部标签
+    pub fn tags(&self) -> Vec<&TagEntry> {
+        let Some(filter) = &self.filter else {
+            return vec![];
+        };
+        let and_tags = filter.and.iter().flat_map(|and| &and.tags);
+        filter.tag.iter().chain(and_tags).collect()
+    }
+
+    // 规则是否选中该对象
+    pub fn matches(&self, key: &str, tags: &[Tag]) -> bool {
+        key.starts_with(self.prefix())
+            && self.tags().iter().all(|wanted| {
+                tags.iter()
+                    .any(|tag| tag.key == wanted.key && tag.value == wanted.value)
+            })
+    }
+
+    // 最后修改时间为 modified 的对象是否已到期
+    pub fn expires(
+        &self,
+        key: &str,
+        tags: &[Tag],
+        modified: DateTime<Utc>,
+        now: DateTime<Utc>,
+        day: Duration,
+    ) -> bool {
+        let Some(expiration) = self.expiration.as_ref().filter(|_| self.enabled()) else {
+            return false;
+        };
+        if !self.matches(key, tags) {
+            return false;
+        }
+        match (expiration.days, &expiration.date) {
+            (Some(days), _) => age_reached(now - modified, days, day),
+            (None, Some(date)) => DateTime::parse_from_rfc3339(date).is_ok_and(|date| now >= date),
+            (None, None) => false,
+        }
+    }
+
+    // 是否删除该键上已没有历史版本的删除标记
+    pub fn removes_expired_marker(&self, key: &str) -> bool {
+        self.enabled()
+            && key.starts_with(self.prefix())
+            && self
+                .expiration
+                .as_ref()
+                .and_then(|expiration| expiration.expired_object_delete_marker)
+                .unwrap_or(false)
+    }
+
+    // 创建时间为 initiated 的分片上传是否需要中止
+    pub fn aborts(
+        &self,
+        key: &str,
+        initiated: DateTime<Utc>,
+        now: DateTime<Utc>,
+        day: Duration,
+    ) -> bool {
+        let Some(abort) = self
+            .abort_incomplete_multipart_upload
+            .as_ref()
+            .filter(|_| self.enabled())
+        else {
+            return false;
+        };
+        key.starts_with(self.prefix())
+            && age_reached(now - initiated, abort.days_after_initiation, day)
+    }
+}
+
+// 是否已满 days 天，天数过大溢出时视为永不到期
+fn age_reached(age: Duration, days: u64, day: Duration) -> bool {
+    i64::try_from(days)
+        .ok()
+        .and_then(|days| day.num_seconds().checked_mul(days))
+        .is_some_and(|limit| age.num_seconds() >= limit)
+}
+
+// 桶生命周期配置文件路径
+pub(crate) fn config_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, LIFECYCLE_CONFIG_FILE)
+}
+
+// 读取桶生命周期配置原文
+pub(crate) fn load_raw(bucket: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(bucket)).ok()
+}
+
+// 读取并解析桶生命周期配置，不存在或无法解析时返回 None
+pub(crate) fn load(bucket: &str) -> Option<LifecycleConfiguration> {
+    LifecycleConfiguration::parse(&load_raw(bucket)?).ok()
+}
+
+// 分片上传临时元数据的文件名为 "对象名.meta.上传ID"，解析出对象名和上传 ID
+pub fn split_upload_meta_name(name: &str) -> Option<(&str, &str)> {
+    let (object_name, upload_id) = name.rsplit_once(".meta.")?;
+    uuid::Uuid::parse_str(upload_id).ok()?;
+    Some((object_name, upload_id))
+}
+
+// 递归收集目录下进行中的分片上传：（对象键，上传 ID，临时元数据文件路径）
+fn walk_uploads(
+    bucket_dir: &Path,
+    dir: &Path,
+    out: &mut Vec<(String, String, PathBuf)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            walk_uploads(bucket_dir, &path, out)?;
+            continue;
+        }
+        let Some(relative) = path
+            .strip_prefix(bucket_dir)
+            .ok()
+            .and_then(|relative| relative.to_str())
+        else {
+            continue;
+        };
+        if let Some((object_key, upload_id)) = split_upload_meta_name(relative) {
+            out.push((object_key.to_string(), upload_id.to_string(), path.clone()));
+        }
+    }
+    Ok(())
+}
+
+// 删除规则选中的、已没有当前版本和其他历史版本的删除标记，返回删除的数量
+async fn remove_expired_markers(
+    app: &App,
+    bucket: &str,
+    bucket_dir: &Path,
+    conf: &LifecycleConfiguration,
+) -> anyhow::Result<u64> {
+    let versions = versioning::list(bucket, bucket_dir, "").context("列出对象版本失败")?;
+    let mut removed = 0;
+    for (i, version) in versions.iter().enumerate() {
+        // 同一个键的版本相邻，从新到旧排列，唯一的版本为删除标记时才删除
+        let alone = version.is_latest
+            && versions
+                .get(i + 1)
+                .is_none_or(|next| next.key != version.key);
+        if !alone || !version.metadata.delete_marker {
+            continue;
+        }
+        if !conf
+            .rules
+            .iter()
+            .any(|rule| rule.removes_expired_marker(&version.key))
+        {
+            continue;
+        }
+        let meta_file = bucket_dir.join(format!("{}.meta", version.key));
+        app.client_write(Request::DeleteFileV2 {
+            file_path: meta_file.to_string_lossy().to_string(),
+            version_id: Some(version.version_id.clone()),
+            if_match: None,
+            if_none_match: false,
+        })
+        .await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+// 对一个桶执行生命周期规则，返回（删除的对象数，中止的分片上传数）
+async fn apply_rules(
+    app: &App,
+    bucket: &str,
+    bucket_dir: &Path,
+    conf: &LifecycleConfiguration,
+) -> anyhow::Result<(u64, u64)> {
+    let day = config::get().lifecycle_day();
+    let now = Utc::now();
+    let mut expired = 0;
+    if conf.rules.iter().any(|rule| rule.expiration.is_some()) {
+        let mut meta_files = vec![];
+        fs::walk_meta_files(bucket_dir, &mut meta_files).context("遍历桶目录失败")?;
+        for meta_file in meta_files {
+            let Some(object_path) = fs::object_path_from_meta(&meta_file) else {
+                continue;
+            };
+            let Ok(metadata) = fs::load_metadata(&meta_file) else {
+                continue;
+            };
+            if metadata.delete_marker {
+                continue;
+            }
+            let key = &object_path[bucket.len() + 1..];
+            let due = conf
+                .rules
+                .iter()
+                .any(|rule| rule.expires(key, &metadata.tags, metadata.time, now, day));
+            if due {
+                // 仅删除扫描时的版本，期间被覆盖的对象留给下一轮判断
+                let res = app
+                    .client_write(Request::DeleteFileV2 {
+                        file_path: meta_file.to_string_lossy().to_string(),
+                        version_id: None,
+                        if_match: Some(metadata.etag),
+                        if_none_match: false,
+                    })
+                    .await?;
+                if res.data.value.as_deref() != Some(conditional::PRECONDITION_FAILED) {
+                    expired += 1;
+                }
+            }
+        }
+    }
+    if conf.rules.iter().any(|rule| {
+        rule.expiration
+            .as_ref()
+            .is_some_and(|e| e.expired_object_delete_marker == Some(true))
+    }) {
+        expired += remove_expired_markers(app, bucket, bucket_dir, conf).await?;
+    }
+    let mut aborted = 0;
+    if conf
+        .rules
+        .iter()
+        .any(|rule| rule.abort_incomplete_multipart_upload.is_some())
+    {
+        let mut uploads = vec![];
+        walk_uploads(bucket_dir, bucket_dir, &mut uploads).context("遍历桶目录失败")?;
+        for (object_key, upload_id, meta_path) in uploads {
+            // 临时元数据创建后不再修改，修改时间即上传的创建时间
+            let Ok(initiated) = std::fs::metadata(&meta_path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            let initiated = DateTime::<Utc>::from(initiated);
+            if conf
+                .rules
+                .iter()
+                .any(|rule| rule.aborts(&object_key, initiated, now, day))
+            {
+                app.client_write(Request::AbortChunk {
+                    bucket_name: bucket.to_string(),
+                    object_key,
+                    upload_id,
+                })
+                .await?;
+                aborted += 1;
+            }
+        }
+    }
+    Ok((expired, aborted))
+}
+
+// 对全部配置了生命周期规则的桶执行一轮，只在主节点上执行
+async fn sweep(app: &App) -> anyhow::Result<()> {
+    let cfg = config::get();
+    let buckets_dir = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
+    if !buckets_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(&buckets_dir).context("读取桶目录失败")? {
+        let bucket_dir = entry?.path();
+        let Some(bucket) = bucket_dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(conf) = load(bucket) else {
+            continue;
+        };
+        if !conf.rules.iter().any(Rule::enabled) {
+            continue;
+        }
+        if cfg.is_denied(bucket, RestrictedOperation::Delete) {
+            warn!("桶 {} 禁止删除，跳过生命周期规则", bucket);
+            continue;
+        }
+        match apply_rules(app, bucket, &bucket_dir, &conf).await {
+            Ok((0, 0)) => {}
+            Ok((expired, aborted)) => info!(
+                "桶 {} 的生命周期规则删除了 {} 个对象，中止了 {} 个分片上传",
+                bucket, expired, aborted
+            ),
+            Err(err) => warn!("执行桶 {} 的生命周期规则失败: {:#}", bucket, err),
+        }
+    }
+    Ok(())
+}
+
+// 定期执行桶的生命周期规则
+pub(crate) fn spawn(app: App) {
+    let secs = config::get().lifecycle_interval_secs;
+    if secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(secs));
+        loop {
+            interval.tick().await;
+            let is_leader = app.raft.metrics().borrow().current_leader == Some(app.id);
+            if standby::is_passive() || !is_leader {
+                continue;
+            }
+            if let Err(err) = sweep(&app).await {
+                warn!("执行生命周期规则失败: {:#}", err);
+            }
+        }
+    });
+}
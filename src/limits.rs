@@ -0,0 +1,71 @@
+use crate::config::RequestLimits;
+use crate::err::AppError;
+use ntex::http::{HeaderMap, StatusCode};
+use percent_encoding::percent_decode_str;
+
+// --- 请求大小限制：请求头的个数和总大小、URI 长度和对象键长度超过 --max-header-count、
+// --max-header-size、--max-url-length、--max-key-length 时返回与 S3 相同的错误，在认证中间件
+// 中先于签名校验检查。用户自定义元数据的大小（--max-metadata-size）在读取上传请求的对象属性时检查
+
+// 请求超过的限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    HeaderCount,
+    HeaderSize,
+    UrlLength,
+    KeyLength,
+}
+
+impl Violation {
+    pub fn error(self) -> AppError {
+        match self {
+            Violation::HeaderCount | Violation::HeaderSize => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "RequestHeaderSectionTooLarge",
+                "Your request header section exceeds the maximum allowed size",
+            ),
+            Violation::UrlLength => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidURI",
+                "The request URI exceeds the maximum allowed length",
+            ),
+            Violation::KeyLength => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "KeyTooLongError",
+                "Your key is too long",
+            ),
+        }
+    }
+}
+
+// 检查请求是否超过限制；path 为未解码的请求路径，对象键按 /api/<桶>/<键> 取出并解码
+pub fn check(
+    limits: &RequestLimits,
+    headers: &HeaderMap,
+    path: &str,
+    query: &str,
+) -> Option<Violation> {
+    if headers.iter().count() > limits.max_header_count {
+        return Some(Violation::HeaderCount);
+    }
+    // 每个请求头另有 ": " 和换行共 4 个字节
+    let header_size: usize = headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum();
+    if header_size > limits.max_header_size {
+        return Some(Violation::HeaderSize);
+    }
+    let url_length = path.len() + if query.is_empty() { 0 } else { query.len() + 1 };
+    if url_length > limits.max_url_length {
+        return Some(Violation::UrlLength);
+    }
+    let key = path
+        .strip_prefix("/api/")
+        .and_then(|path| path.split_once('/'))
+        .map(|(_, key)| percent_decode_str(key).count());
+    if key.is_some_and(|len| len > limits.max_key_length) {
+        return Some(Violation::KeyLength);
+    }
+    None
+}
@@ -0,0 +1,225 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use log::warn;
+use std::path::PathBuf;
+
+// --- 对象列表：按键的字典序遍历桶目录，支持 prefix、delimiter 以及 V1 的 marker、
+// V2 的 continuation-token/start-after 分页。对象键中的 '/' 对应目录层级，
+// 遍历时跳过与前缀无关或在分页起点之前的目录。扩展参数按存储类型或标签过滤时，
+// 不满足条件的键在分页之前就被跳过，公共前缀也只由满足条件的键产生
+
+// 可以按字典序逐个取出键，并整体跳过某个前缀下剩余键的来源
+pub trait KeySource: Iterator<Item = String> {
+    // 跳过接下来以 prefix 开头的全部键
+    fn skip_prefix(&mut self, prefix: &str);
+}
+
+impl<I: Iterator<Item = String>> KeySource for std::iter::Peekable<I> {
+    fn skip_prefix(&mut self, prefix: &str) {
+        while self.next_if(|key| key.starts_with(prefix)).is_some() {}
+    }
+}
+
+// 只取出满足条件的键；跳过前缀直接交给内部的来源
+pub struct Filtered<S, F> {
+    source: S,
+    keep: F,
+}
+
+pub fn filtered<S: KeySource, F: FnMut(&str) -> bool>(source: S, keep: F) -> Filtered<S, F> {
+    Filtered { source, keep }
+}
+
+impl<S: KeySource, F: FnMut(&str) -> bool> Iterator for Filtered<S, F> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let key = self.source.next()?;
+            if (self.keep)(&key) {
+                return Some(key);
+            }
+        }
+    }
+}
+
+impl<S: KeySource, F: FnMut(&str) -> bool> KeySource for Filtered<S, F> {
+    fn skip_prefix(&mut self, prefix: &str) {
+        self.source.skip_prefix(prefix);
+    }
+}
+
+// 一页列表结果
+#[derive(Debug, Default, PartialEq)]
+pub struct Page {
+    pub keys: Vec<String>,
+    pub common_prefixes: Vec<String>,
+    pub is_truncated: bool,
+    // 本页最后一个键或公共前缀，作为下一页的起点
+    pub next_marker: Option<String>,
+}
+
+// 列出 prefix 下排在 after 之后的至多 max_keys 个键和公共前缀，键和公共前缀合计计数
+pub fn paginate(
+    source: &mut impl KeySource,
+    prefix: &str,
+    delimiter: Option<&str>,
+    after: Option<&str>,
+    max_keys: usize,
+) -> Page {
+    let mut page = Page::default();
+    if max_keys == 0 {
+        return page;
+    }
+    let after = after.unwrap_or_default();
+    let delimiter = delimiter.filter(|d| !d.is_empty());
+    let mut count = 0;
+    while let Some(key) = source.next() {
+        if !key.starts_with(prefix) || key.as_str() <= after {
+            continue;
+        }
+        let common_prefix = delimiter.and_then(|delimiter| {
+            let rest = &key[prefix.len()..];
+            rest.find(delimiter)
+                .map(|idx| key[..prefix.len() + idx + delimiter.len()].to_string())
+        });
+        if let Some(common_prefix) = &common_prefix {
+            source.skip_prefix(common_prefix);
+            if common_prefix.as_str() <= after {
+                continue;
+            }
+        }
+        if count == max_keys {
+            page.is_truncated = true;
+            break;
+        }
+        count += 1;
+        match common_prefix {
+            Some(common_prefix) => {
+                page.next_marker = Some(common_prefix.clone());
+                page.common_prefixes.push(common_prefix);
+            }
+            None => {
+                page.next_marker = Some(key.clone());
+                page.keys.push(key);
+            }
+        }
+    }
+    if !page.is_truncated {
+        page.next_marker = None;
+    }
+    page
+}
+
+// V2 的 continuation-token 为上一页最后一个键或公共前缀
+pub fn encode_token(marker: &str) -> String {
+    URL_SAFE_NO_PAD.encode(marker)
+}
+
+pub fn decode_token(token: &str) -> Option<String> {
+    String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()
+}
+
+enum Entry {
+    Key(String),
+    // 目录对应的键前缀，以 '/' 结尾
+    Dir(String),
+}
+
+impl Entry {
+    fn name(&self) -> &str {
+        match self {
+            Entry::Key(key) | Entry::Dir(key) => key,
+        }
+    }
+}
+
+// 按字典序遍历桶目录下的对象键。目录 "a" 下的键都以 "a/" 开头，
+// 同一目录中的条目按 "目录名/" 和对象键排序后深度优先遍历，得到的就是全局有序的键
+pub(crate) struct BucketKeys {
+    bucket_path: PathBuf,
+    prefix: String,
+    after: String,
+    // 待遍历的条目，栈顶为最小的条目
+    stack: Vec<Entry>,
+}
+
+impl BucketKeys {
+    pub(crate) fn new(bucket_path: PathBuf, prefix: &str, after: Option<&str>) -> Self {
+        BucketKeys {
+            bucket_path,
+            prefix: prefix.to_string(),
+            after: after.unwrap_or_default().to_string(),
+            stack: vec![Entry::Dir(String::new())],
+        }
+    }
+
+    // 目录下是否可能有需要列出的键
+    fn wanted(&self, dir: &str) -> bool {
+        (dir.starts_with(&self.prefix) || self.prefix.starts_with(dir))
+            && (dir >= self.after.as_str() || self.after.starts_with(dir))
+    }
+
+    fn expand(&mut self, dir: &str) {
+        let path = self.bucket_path.join(dir);
+        let entries = match std::fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("跳过无法读取的目录 {:?}: {}", path, err);
+                return;
+            }
+        };
+        let mut children: Vec<Entry> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                if entry.file_type().ok()?.is_dir() {
+                    Some(Entry::Dir(format!("{}{}/", dir, name)))
+                } else {
+                    let key = name.strip_suffix(".meta")?;
+                    Some(Entry::Key(format!("{}{}", dir, key)))
+                }
+            })
+            .collect();
+        children.sort_by(|a, b| b.name().cmp(a.name()));
+        self.stack.extend(children);
+    }
+}
+
+impl Iterator for BucketKeys {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while let Some(entry) = self.stack.pop() {
+            match entry {
+                Entry::Key(key) => {
+                    if key.starts_with(&self.prefix) && key > self.after {
+                        return Some(key);
+                    }
+                }
+                Entry::Dir(dir) => {
+                    if self.wanted(&dir) {
+                        self.expand(&dir);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl KeySource for BucketKeys {
+    fn skip_prefix(&mut self, prefix: &str) {
+        while let Some(entry) = self.stack.last() {
+            let name = entry.name().to_string();
+            if name.starts_with(prefix) {
+                self.stack.pop();
+            } else if matches!(entry, Entry::Dir(_)) && prefix.starts_with(&name) {
+                self.stack.pop();
+                self.expand(&name);
+            } else {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::Context;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use log::{Level, Log, Metadata, Record};
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// --- 日志输出：支持 plain、logfmt、JSON 三种格式；配置 --log-dir 时按目标分别写入
+// access.log（访问日志）、audit.log（审计日志）、slow.log（慢请求日志）和 server.log，并按大小或时间轮转
+
+// 访问日志所在的日志目标（ntex 的 Logger 中间件）
+const ACCESS_TARGET: &str = "ntex::web::middleware::logger";
+// 审计日志的日志目标
+const AUDIT_TARGET: &str = "audit";
+// 慢请求日志的日志目标
+const SLOW_TARGET: &str = "slow";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat {
+    #[default]
+    Plain,
+    Logfmt,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(LogFormat::Plain),
+            "logfmt" => Ok(LogFormat::Logfmt),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "unknown log format `{}`, expected plain, logfmt or json",
+                s
+            )),
+        }
+    }
+}
+
+// 按时间轮转的周期
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(LogRotation::Never),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            _ => Err(format!(
+                "unknown rotation `{}`, expected never, hourly or daily",
+                s
+            )),
+        }
+    }
+}
+
+impl LogRotation {
+    // 时间所在的轮转周期，周期变化时轮转
+    pub fn period(&self, time: DateTime<Utc>) -> Option<(i32, u32, u32)> {
+        match self {
+            LogRotation::Never => None,
+            LogRotation::Hourly => Some((time.year(), time.ordinal(), time.hour())),
+            LogRotation::Daily => Some((time.year(), time.ordinal(), 0)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    // 日志文件目录，为空时输出到标准错误
+    pub dir: Option<PathBuf>,
+    // 单个日志文件的大小上限，0 表示不按大小轮转
+    pub max_bytes: u64,
+    pub rotation: LogRotation,
+    // 每种日志保留的已轮转文件数
+    pub keep: usize,
+}
+
+// logfmt 的值：含空格、引号、'=' 或为空时加引号并转义
+fn logfmt_value(value: &str) -> String {
+    if !value.is_empty() && !value.contains([' ', '"', '=', '\n', '\t']) {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+// 按格式生成一行日志（不含换行）
+pub fn format_line(
+    format: LogFormat,
+    time: DateTime<Utc>,
+    level: Level,
+    target: &str,
+    message: &str,
+) -> String {
+    let time = time.format("%Y-%m-%dT%H:%M:%S%.3fZ");
+    match format {
+        LogFormat::Plain => format!("[{} {:<5} {}] {}", time, level, target, message),
+        LogFormat::Logfmt => format!(
+            "time={} level={} target={} msg={}",
+            time,
+            level.as_str().to_lowercase(),
+            logfmt_value(target),
+            logfmt_value(message)
+        ),
+        LogFormat::Json => json!({
+            "time": time.to_string(),
+            "level": level.as_str().to_lowercase(),
+            "target": target,
+            "msg": message,
+        })
+        .to_string(),
+    }
+}
+
+// 轮转后的文件名：access.log.1 最新，序号越大越旧
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), n))
+}
+
+// 超过大小上限或进入新的轮转周期时轮转的日志文件
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    period: Option<(i32, u32, u32)>,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, rotation: LogRotation) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // 沿用已有文件时，以其修改时间所在的周期为当前周期
+        let modified = metadata.modified().map(DateTime::<Utc>::from);
+        Ok(RotatingFile {
+            period: rotation.period(modified.unwrap_or_else(|_| Utc::now())),
+            size: metadata.len(),
+            file,
+            path,
+        })
+    }
+
+    fn rotate(&mut self, keep: usize) -> std::io::Result<()> {
+        if keep == 0 {
+            self.file.set_len(0)?;
+        } else {
+            let _ = std::fs::remove_file(rotated_path(&self.path, keep));
+            for n in (1..keep).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str, now: DateTime<Utc>, cfg: &LogConfig) {
+        let period = cfg.rotation.period(now);
+        let len = line.len() as u64 + 1;
+        let full = cfg.max_bytes > 0 && self.size > 0 && self.size + len > cfg.max_bytes;
+        if full || period != self.period {
+            if let Err(err) = self.rotate(cfg.keep) {
+                eprintln!("轮转日志 {:?} 失败: {}", self.path, err);
+            }
+            self.period = period;
+        }
+        if writeln!(self.file, "{}", line).is_ok() {
+            self.size += len;
+        }
+    }
+}
+
+struct Files {
+    access: RotatingFile,
+    audit: RotatingFile,
+    slow: RotatingFile,
+    server: RotatingFile,
+}
+
+struct Logger {
+    filter: env_logger::Logger,
+    cfg: LogConfig,
+    files: Option<Mutex<Files>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+        let now = Utc::now();
+        let line = format_line(
+            self.cfg.format,
+            now,
+            record.level(),
+            record.target(),
+            &record.args().to_string(),
+        );
+        let Some(files) = &self.files else {
+            eprintln!("{}", line);
+            return;
+        };
+        let mut files = files.lock().unwrap();
+        let file = match record.target() {
+            ACCESS_TARGET => &mut files.access,
+            AUDIT_TARGET => &mut files.audit,
+            SLOW_TARGET => &mut files.slow,
+            _ => &mut files.server,
+        };
+        file.write_line(&line, now, &self.cfg);
+    }
+
+    // 每行日志直接写入，无需刷新
+    fn flush(&self) {}
+}
+
+// 初始化全局日志，级别过滤沿用 RUST_LOG（默认 info）
+pub fn init(cfg: LogConfig) -> anyhow::Result<()> {
+    let filter =
+        env_logger::Builder::from_env(env_logger::Env::new().default_filter_or("info")).build();
+    let files = match &cfg.dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).context("创建日志目录失败")?;
+            let open = |name: &str| {
+                RotatingFile::open(dir.join(name), cfg.rotation)
+                    .with_context(|| format!("打开日志文件 {} 失败", name))
+            };
+            Some(Mutex::new(Files {
+                access: open("access.log")?,
+                audit: open("audit.log")?,
+                slow: open("slow.log")?,
+                server: open("server.log")?,
+            }))
+        }
+        None => None,
+    };
+    log::set_max_level(filter.filter());
+    log::set_boxed_logger(Box::new(Logger { filter, cfg, files })).context("初始化日志失败")
+}
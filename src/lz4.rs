@@ -0,0 +1,150 @@
+use std::io;
+
+// --- LZ4 块格式的压缩和解压，供 --compression-codec lz4 使用。只实现块格式（不含帧头和校验），
+// 与 lz4 库的 LZ4_compress_default / LZ4_decompress_safe 互通；压缩使用单一哈希表的贪心匹配，
+// 速度优先，不追求 lz4hc 的压缩率
+
+const MIN_MATCH: usize = 4;
+// 块末尾至少 5 个字节为字面量，最后一个匹配至少在块末尾 12 个字节之前开始
+const LAST_LITERALS: usize = 5;
+const MF_LIMIT: usize = 12;
+const MAX_DISTANCE: usize = 65535;
+const HASH_LOG: u32 = 16;
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+}
+
+// 长度超过 15 的部分以 255 为单位追加
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_code = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_code.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_code >= 15 {
+            write_length(out, match_code - 15);
+        }
+    }
+}
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut anchor = 0;
+    if input.len() > MF_LIMIT {
+        let mut table = vec![0usize; 1 << HASH_LOG];
+        let match_limit = input.len() - MF_LIMIT;
+        let match_end = input.len() - LAST_LITERALS;
+        let mut pos = 0;
+        while pos <= match_limit {
+            let sequence = read_u32(input, pos);
+            let slot = hash(sequence);
+            let candidate = table[slot];
+            table[slot] = pos;
+            if candidate >= pos
+                || pos - candidate > MAX_DISTANCE
+                || read_u32(input, candidate) != sequence
+            {
+                pos += 1;
+                continue;
+            }
+            let mut len = MIN_MATCH;
+            while pos + len < match_end && input[candidate + len] == input[pos + len] {
+                len += 1;
+            }
+            // 向前扩展匹配，吸收已作为字面量跳过的相同内容
+            let (mut start, mut from) = (pos, candidate);
+            while start > anchor && from > 0 && input[start - 1] == input[from - 1] {
+                start -= 1;
+                from -= 1;
+                len += 1;
+            }
+            write_sequence(&mut out, &input[anchor..start], Some((start - from, len)));
+            pos = start + len;
+            anchor = pos;
+        }
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_length(input: &[u8], pos: &mut usize) -> io::Result<usize> {
+    let mut len = 0usize;
+    loop {
+        let byte = *input.get(*pos).ok_or_else(|| invalid("lz4 长度不完整"))?;
+        *pos += 1;
+        len = len
+            .checked_add(byte as usize)
+            .ok_or_else(|| invalid("lz4 长度溢出"))?;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+// 解压一个块，size 为原始大小，解压结果与之不符时返回错误
+pub fn decompress(input: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 0;
+    loop {
+        let token = *input.get(pos).ok_or_else(|| invalid("lz4 数据不完整"))?;
+        pos += 1;
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 {
+            literal_len += read_length(input, &mut pos)?;
+        }
+        let literals = pos
+            .checked_add(literal_len)
+            .and_then(|end| input.get(pos..end))
+            .ok_or_else(|| invalid("lz4 字面量不完整"))?;
+        out.extend_from_slice(literals);
+        pos += literal_len;
+        // 最后一个序列只有字面量
+        if pos == input.len() {
+            break;
+        }
+        let offset = input
+            .get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| invalid("lz4 偏移不完整"))?;
+        pos += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(invalid("lz4 偏移无效"));
+        }
+        let mut match_len = (token & 15) as usize + MIN_MATCH;
+        if token & 15 == 15 {
+            match_len += read_length(input, &mut pos)?;
+        }
+        if out.len() + match_len > size {
+            return Err(invalid("lz4 解压后的大小不符"));
+        }
+        // 匹配可以与正在写入的内容重叠，逐字节复制
+        let start = out.len() - offset;
+        for i in 0..match_len {
+            out.push(out[start + i]);
+        }
+    }
+    if out.len() != size {
+        return Err(invalid("lz4 解压后的大小不符"));
+    }
+    Ok(out)
+}
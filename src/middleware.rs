@@ -1,11 +1,27 @@
+use crate::api::{self, BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::config;
+use crate::config::{ApiGroup, RestrictedOperation};
+use crate::copy;
+use crate::err::AppError;
+use crate::fs;
+use crate::identity::{self, Identity, Operation};
+use crate::limits;
+use crate::policy::{self, Decision};
+use crate::slowlog::AuthElapsed;
+use crate::tls;
 use crate::util::cry::{do_bytes_to_hex, do_hex, do_hmac_sha256};
+use crate::vhost;
 use anyhow::Context;
-use chrono::{NaiveDateTime, Utc};
-use log::info;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+use ntex::http::{ConnectionType, HeaderMap, Method, StatusCode};
 use ntex::service::{Middleware, Service, ServiceCtx};
 use ntex::web;
 use ntex::web::HttpResponse;
+use percent_encoding::percent_decode_str;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
 
 pub struct CredentialsV4 {
     access_key: String,
@@ -54,45 +70,571 @@ where
         req: web::WebRequest<Err>,
         ctx: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
-        if !req.uri().to_string().starts_with("/api") {
+        let path = req.uri().path();
+        if !path.starts_with("/api") && !path.starts_with("/admin") {
             let res = ctx.call(&self.service, req).await?;
             return Ok(res);
         }
+        let start = Instant::now();
+        // 路径段含 "." 或 ".." 的请求在认证和任何权限判断之前拒绝
+        let api_path = decode_api_path(path);
+        if api_path.as_deref().is_some_and(|p| !is_valid_api_path(p)) {
+            let err = AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidURI",
+                "请求路径不能含 \".\"、\"..\" 或空路径段",
+            );
+            return Ok(error_response(req, err));
+        }
+        // 超过大小限制的请求不做认证
+        let limits = config::get().request_limits;
+        if let Some(violation) = limits::check(&limits, req.headers(), path, req.query_string()) {
+            return Ok(error_response(req, violation.error()));
+        }
+        // 关闭的接口不做认证，对所有请求方一致地返回 405
+        let copy = req.headers().contains_key("x-amz-copy-source");
+        let groups = ApiGroup::of(req.method().as_str(), path, req.query_string(), copy);
+        if let Some(group) = config::get().disabled_api(&groups) {
+            let err = AppError::s3(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "MethodNotAllowed",
+                format!("接口组 {} 已关闭", group.name()),
+            );
+            return Ok(error_response(req, err));
+        }
+        // 管理接口可使用 JWT 代替签名
+        if path.starts_with("/admin") && config::get().jwt.enabled() {
+            if let Some(token) = crate::jwt::bearer_token(req.headers()) {
+                let status = match crate::jwt::authorize(&token).await {
+                    Ok(Some(role)) if role.allows(req.method()) => {
+                        req.extensions_mut().insert(AuthElapsed(start.elapsed()));
+                        return ctx.call(&self.service, req).await;
+                    }
+                    Ok(_) => StatusCode::FORBIDDEN,
+                    Err(err) => {
+                        info!("JWT 认证失败: {}", err);
+                        StatusCode::UNAUTHORIZED
+                    }
+                };
+                return Ok(req.into_response(HttpResponse::new(status)));
+            }
+        }
         // do filter here
         let authorization = req.headers().get("Authorization");
+        let root = (self.access_key.as_str(), self.secret_key.as_str());
+        let presigned = authorization.is_none()
+            && url::form_urlencoded::parse(req.query_string().as_bytes())
+                .any(|(key, _)| key == "X-Amz-Credential");
+        let mut identity = None;
         let mut flag = false;
-        if authorization.is_some() {
-            match valid_authorization_header(&req, &self.access_key, &self.secret_key) {
-                Ok(b) => flag = b,
+        if authorization.is_none() && is_public_read(&req, api_path.as_deref()) {
+            flag = true;
+        } else if authorization.is_some() || presigned {
+            match authenticate(&req, root, presigned).await {
+                Ok(res) => identity = Some(res),
                 Err(err) => {
-                    info!("middleware error: {}", err);
-                    flag = false
+                    info!("签名校验失败 {} {}: {:?}", req.method(), path, err);
+                    return Ok(error_response(req, err.into_app_error(presigned)));
                 }
             }
-        } else {
-            let qs = req.query_string();
-            let credential = url::form_urlencoded::parse(qs.as_bytes())
-                .find(|(key, _)| key == "X-Amz-Credential");
-            if credential.is_some() {
-                match valid_authorization_url(&req, &self.access_key, &self.secret_key) {
-                    Ok(b) => flag = b,
-                    Err(err) => {
-                        info!("middleware error: {}", err);
-                        flag = false
-                    }
+        } else if let Some(access_key) = tls::peer_access_key(&req) {
+            // 没有签名时按客户端证书对应的访问密钥认证
+            match identity::resolve(&access_key, root).await {
+                Ok(Some(res)) => identity = Some(res),
+                Ok(None) => {
+                    info!("客户端证书对应的访问密钥 {} 不存在", access_key);
+                    let err = SignatureError::InvalidAccessKeyId.into_app_error(false);
+                    return Ok(error_response(req, err));
                 }
+                Err(err) => {
+                    warn!("查询访问密钥 {} 失败: {:#}", access_key, err);
+                    let err = SignatureError::Unavailable.into_app_error(false);
+                    return Ok(error_response(req, err));
+                }
+            }
+        }
+        // 桶策略在认证之后判断，Allow 可补充访问密钥的权限
+        let checked = policy::check(
+            &req,
+            api_path.as_deref(),
+            identity.as_ref().map(|i| i.access_key.as_str()),
+            root.0,
+        );
+        if let Some(identity) = &identity {
+            flag = true;
+            let admin_api = path.starts_with("/admin");
+            let required = if admin_api {
+                vec![(Operation::of(req.method().as_str(), path), None)]
+            } else {
+                required_permissions(req.method(), path, api_path.as_deref(), req.query_string())
+            };
+            // 拷贝对象还需要有读取源对象的权限
+            let copy_source = req
+                .headers()
+                .get("x-amz-copy-source")
+                .and_then(|v| v.to_str().ok())
+                .and_then(copy::parse_source);
+            let source_denied = copy_source.is_some_and(|source| {
+                checked.source != Decision::Allow
+                    && !identity.allows(Operation::Read, Some(&source.object_path()), false)
+            });
+            let target_denied = checked.target != Decision::Allow
+                && !required.iter().all(|(operation, object_path)| {
+                    identity.allows(*operation, object_path.as_deref(), admin_api)
+                });
+            if source_denied || target_denied {
+                let err = AppError::s3(StatusCode::FORBIDDEN, "AccessDenied", "访问密钥没有该权限");
+                return Ok(error_response(req, err));
             }
         }
+        if checked.target == Decision::Deny || checked.source == Decision::Deny {
+            let err = AppError::s3(StatusCode::FORBIDDEN, "AccessDenied", "桶策略拒绝访问");
+            return Ok(error_response(req, err));
+        }
+        // 桶策略允许匿名访问，拷贝时源对象也需要被允许
+        let anonymous_copy = req.headers().contains_key("x-amz-copy-source");
+        if !flag
+            && identity.is_none()
+            && checked.target == Decision::Allow
+            && (!anonymous_copy || checked.source == Decision::Allow)
+        {
+            flag = true;
+        }
         if !flag {
             return Ok(req.into_response(HttpResponse::Unauthorized().finish()));
         }
+        if let Some(operation) = restricted_operation(&req, api_path.as_deref()) {
+            let err = AppError::s3(
+                StatusCode::FORBIDDEN,
+                "AccessDenied",
+                format!("该桶禁止{}", operation.describe()),
+            );
+            return Ok(error_response(req, err));
+        }
+        if crate::standby::is_passive()
+            && path.starts_with("/api")
+            && !matches!(*req.method(), Method::GET | Method::HEAD)
+        {
+            let err = AppError::s3(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "ServiceUnavailable",
+                "备用节点只读，提升为主节点后才能写入",
+            );
+            return Ok(error_response(req, err));
+        }
 
         // end do
+        // 批量删除的键在请求体中，由处理函数按访问密钥的权限逐个校验
+        if let Some(identity) = identity {
+            req.extensions_mut().insert(identity);
+        }
+        req.extensions_mut().insert(AuthElapsed(start.elapsed()));
         let res = ctx.call(&self.service, req).await?;
         Ok(res)
     }
 }
 
+pub(crate) fn error_response<Err>(req: web::WebRequest<Err>, err: AppError) -> web::WebResponse {
+    // 请求体未读取时关闭连接，否则剩余的请求体会被当成下一个请求
+    let has_body = req.headers().contains_key("Transfer-Encoding")
+        || req
+            .headers()
+            .get("Content-Length")
+            .is_some_and(|v| v.as_bytes() != b"0");
+    let (request, _) = req.into_parts();
+    let mut resp = web::WebResponseError::error_response(&err, &request);
+    if has_body {
+        resp.head_mut().set_connection_type(ConnectionType::Close);
+    }
+    web::WebResponse::new(resp, request)
+}
+
+// 请求触发的、被桶的操作限制禁止的操作（与凭证无关，匿名读取同样受限），api_path 为校验过
+// 路径段的 "桶/键"
+fn restricted_operation(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    api_path: Option<&str>,
+) -> Option<RestrictedOperation> {
+    let cfg = config::get();
+    if cfg.bucket_restrictions.is_empty() {
+        return None;
+    }
+    let path = api_path?;
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    if bucket.is_empty() {
+        return None;
+    }
+    let query: HashMap<String, String> =
+        url::form_urlencoded::parse(request.query_string().as_bytes())
+            .into_owned()
+            .collect();
+    let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket);
+    let exists =
+        |key: &str| api::is_valid_key(key) && bucket_dir.join(format!("{}.meta", key)).exists();
+    let mut operations = vec![];
+    match *request.method() {
+        // 中止分片上传不删除对象
+        Method::DELETE if !query.contains_key("uploadId") => {
+            operations.push(RestrictedOperation::Delete)
+        }
+        Method::GET
+            if key.is_empty() && !BUCKET_CONFIG_QUERIES.iter().any(|q| query.contains_key(*q)) =>
+        {
+            operations.push(RestrictedOperation::List)
+        }
+        // 上传分片不会覆盖对象，完成分片上传时才会
+        Method::PUT if !query.contains_key("partNumber") && exists(key) => {
+            operations.push(RestrictedOperation::Overwrite)
+        }
+        Method::POST if query.contains_key("uploadId") && exists(key) => {
+            operations.push(RestrictedOperation::Overwrite)
+        }
+        Method::POST if key.is_empty() && query.contains_key("delete") => {
+            operations.push(RestrictedOperation::Delete)
+        }
+        // 重命名删除源对象；目标已存在时整个重命名被拒绝，不会覆盖
+        Method::POST if key.is_empty() && query.contains_key("rename") => {
+            operations.push(RestrictedOperation::Delete)
+        }
+        // 提交暂存批次时，任一暂存对象已存在即为覆盖
+        Method::POST if key.is_empty() => {
+            let staged = query.get("commit").map(|id| fs::staging_dir(id, bucket));
+            let mut meta_files = vec![];
+            if let Some(dir) = staged.as_ref().filter(|dir| dir.is_dir()) {
+                let _ = fs::walk_meta_files(dir, &mut meta_files);
+            }
+            let overwrites = meta_files.iter().any(|meta| {
+                meta.strip_prefix(staged.as_ref().unwrap())
+                    .is_ok_and(|relative| bucket_dir.join(relative).exists())
+            });
+            if overwrites {
+                operations.push(RestrictedOperation::Overwrite);
+            }
+        }
+        _ => {}
+    }
+    operations
+        .into_iter()
+        .find(|operation| cfg.is_denied(bucket, *operation))
+}
+
+// 解码后的 /api 请求路径（"桶/键"，不含 "/api/"），不是 /api/ 下的请求时返回 None
+pub fn decode_api_path(path: &str) -> Option<String> {
+    let path = path.strip_prefix("/api/")?;
+    Some(percent_decode_str(path).decode_utf8_lossy().to_string())
+}
+
+// 解码后的 "桶/键" 不能含 "." 或 ".." 路径段和空路径段（末尾的 '/' 除外）：公开前缀、访问密钥
+// 的前缀范围、桶策略和桶的操作限制都按这个路径判断，必须与处理函数实际访问的桶目录一致
+pub fn is_valid_api_path(path: &str) -> bool {
+    let path = path.strip_suffix('/').unwrap_or(path);
+    path.is_empty()
+        || path
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+// 桶级 GET 请求读取的桶配置子资源，其余的桶级 GET 请求都是列出对象
+const BUCKET_CONFIG_QUERIES: [&str; 5] = [
+    "website",
+    "response-headers",
+    "versioning",
+    "policy",
+    "lifecycle",
+];
+
+// 请求需要访问密钥具备的（操作，对象路径）权限，对象路径为 "桶/键"，列出所有桶时为 None。
+// 桶级请求只有列出对象和展开归档以 prefix 参数作为键前缀，其余桶级请求（桶配置、删除桶、
+// 提交暂存批次等）需要整个桶的权限；重命名分别校验源和目标，批量删除的键在请求体中，
+// 由处理函数逐个校验
+pub fn required_permissions(
+    method: &Method,
+    path: &str,
+    api_path: Option<&str>,
+    query: &str,
+) -> Vec<(Operation, Option<String>)> {
+    let operation = Operation::of(method.as_str(), path);
+    let Some(api_path) = api_path.filter(|p| !p.is_empty()) else {
+        return vec![(operation, None)];
+    };
+    if api_path.trim_end_matches('/').contains('/') {
+        return vec![(operation, Some(api_path.to_string()))];
+    }
+    let bucket = api_path.trim_end_matches('/');
+    let query: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let key_path = |name: &str| {
+        let key = query.get(name).map(String::as_str).unwrap_or_default();
+        Some(format!("{}/{}", bucket, key))
+    };
+    match *method {
+        Method::GET if !BUCKET_CONFIG_QUERIES.iter().any(|q| query.contains_key(*q)) => {
+            vec![(operation, key_path("prefix"))]
+        }
+        Method::POST if query.contains_key("delete") => vec![(Operation::Delete, None)],
+        Method::POST if query.contains_key("rename") => vec![
+            (Operation::Read, key_path("source")),
+            (Operation::Delete, key_path("source")),
+            (Operation::Write, key_path("target")),
+        ],
+        Method::POST if query.contains_key("extract") => vec![(operation, key_path("prefix"))],
+        _ => vec![(operation, Some(format!("{}/", bucket)))],
+    }
+}
+
+// 未携带签名的 GET/HEAD 请求是否访问公开的桶或前缀
+fn is_public_read(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    api_path: Option<&str>,
+) -> bool {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return false;
+    }
+    let qs = request.query_string();
+    if url::form_urlencoded::parse(qs.as_bytes()).any(|(key, _)| key == "X-Amz-Credential") {
+        return false;
+    }
+    let required = required_permissions(request.method(), request.path(), api_path, qs);
+    required.iter().all(|(_, object_path)| {
+        object_path
+            .as_deref()
+            .is_some_and(|path| config::get().is_public(path))
+    })
+}
+
+// 请求签名或客户端证书对应的访问密钥，匿名请求为空
+pub(crate) fn access_key_of(request: &web::WebRequest<impl web::ErrorRenderer>) -> Option<String> {
+    let presigned = !request.headers().contains_key("Authorization");
+    request_access_key(request, presigned).or_else(|| tls::peer_access_key(request))
+}
+
+// 签名中的访问密钥，presigned 时从 URL 参数读取
+fn request_access_key(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    presigned: bool,
+) -> Option<String> {
+    let credential = if presigned {
+        url::form_urlencoded::parse(request.query_string().as_bytes())
+            .find(|(key, _)| key == "X-Amz-Credential")
+            .map(|(_, value)| value.into_owned())?
+    } else {
+        let authorization = request.headers().get("Authorization")?.to_str().ok()?;
+        authorization
+            .split(',')
+            .next()?
+            .split('=')
+            .nth(1)?
+            .to_string()
+    };
+    credential.split('/').next().map(str::to_string)
+}
+
+// 允许的客户端与服务端时钟偏差（秒），与 S3 一致
+const MAX_CLOCK_SKEW: i64 = 15 * 60;
+// 预签名 URL 的最长有效期（秒）
+const MAX_PRESIGNED_EXPIRES: i64 = 7 * 24 * 3600;
+
+// 签名校验失败的原因
+#[derive(Debug, PartialEq)]
+pub enum SignatureError {
+    // Authorization 头或预签名参数缺失、格式错误
+    Malformed(String),
+    // 访问密钥不存在
+    InvalidAccessKeyId,
+    SignatureDoesNotMatch,
+    // 请求时间与服务端时间相差超过 MAX_CLOCK_SKEW
+    RequestTimeTooSkewed,
+    // 预签名 URL 已过期
+    Expired,
+    // 预签名 URL 的签名时间在未来
+    NotYetValid,
+    // 查询认证回调失败
+    Unavailable,
+}
+
+impl SignatureError {
+    fn into_app_error(self, presigned: bool) -> AppError {
+        let forbidden = |code, message: &str| AppError::s3(StatusCode::FORBIDDEN, code, message);
+        match self {
+            SignatureError::Malformed(message) if presigned => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationQueryParametersError",
+                message,
+            ),
+            SignatureError::Malformed(message) => AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationHeaderMalformed",
+                message,
+            ),
+            SignatureError::InvalidAccessKeyId => forbidden(
+                "InvalidAccessKeyId",
+                "The AWS Access Key Id you provided does not exist in our records.",
+            ),
+            SignatureError::SignatureDoesNotMatch => forbidden(
+                "SignatureDoesNotMatch",
+                "The request signature we calculated does not match the signature you provided. Check your key and signing method.",
+            ),
+            SignatureError::RequestTimeTooSkewed => forbidden(
+                "RequestTimeTooSkewed",
+                "The difference between the request time and the current time is too large.",
+            ),
+            SignatureError::Expired => forbidden("AccessDenied", "Request has expired"),
+            SignatureError::NotYetValid => forbidden("AccessDenied", "Request is not valid yet"),
+            SignatureError::Unavailable => AppError::s3(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "ServiceUnavailable",
+                "Unable to look up the access key, please retry",
+            ),
+        }
+    }
+}
+
+// 校验签名时间 request_date（X-Amz-Date）：日期须与凭证范围中的日期一致；请求头签名须在服务端
+// 时间前后 MAX_CLOCK_SKEW 内，预签名 URL（expires 为 X-Amz-Expires）须已生效且未过期
+pub fn check_signing_time(
+    request_date: &str,
+    scope_date: &str,
+    expires: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<(), SignatureError> {
+    let malformed = |message: &str| SignatureError::Malformed(message.to_string());
+    let time = NaiveDateTime::parse_from_str(request_date, "%Y%m%dT%H%M%SZ")
+        .map_err(|_| {
+            malformed("X-Amz-Date must be in the ISO8601 Long Format \"yyyyMMdd'T'HHmmss'Z'\"")
+        })?
+        .and_utc();
+    if time.format("%Y%m%d").to_string() != scope_date {
+        return Err(malformed(
+            "The credential date does not match the date in X-Amz-Date",
+        ));
+    }
+    let skew = chrono::Duration::seconds(MAX_CLOCK_SKEW);
+    match expires {
+        None if time - now > skew || now - time > skew => Err(SignatureError::RequestTimeTooSkewed),
+        None => Ok(()),
+        Some(expires) if !(1..=MAX_PRESIGNED_EXPIRES).contains(&expires) => Err(malformed(
+            "X-Amz-Expires must be between 1 and 604800 seconds",
+        )),
+        Some(_) if time - now > skew => Err(SignatureError::NotYetValid),
+        Some(expires) if now > time + chrono::Duration::seconds(expires) => {
+            Err(SignatureError::Expired)
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+// 凭证格式为 访问密钥/日期/区域/服务/aws4_request
+fn split_credential(credential: &str) -> anyhow::Result<[&str; 5]> {
+    let parts: Vec<&str> = credential.split('/').collect();
+    match parts[..] {
+        [access_key, date, region, service, terminator] if terminator == "aws4_request" => {
+            Ok([access_key, date, region, service, terminator])
+        }
+        _ => anyhow::bail!("凭证格式错误: {}", credential),
+    }
+}
+
+// 按位比较，耗时与签名在第几位不同无关
+fn signature_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+// 签名时间和凭证范围中的日期，预签名时另有有效期
+fn signing_time(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    presigned: bool,
+) -> anyhow::Result<(String, String, Option<i64>)> {
+    let query = |name: &str| {
+        url::form_urlencoded::parse(request.query_string().as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let (request_date, credential, expires) = if presigned {
+        let expires = query("X-Amz-Expires")
+            .context("X-Amz-Expires不存在")?
+            .parse()
+            .context("X-Amz-Expires不是整数")?;
+        (
+            query("X-Amz-Date").context("X-Amz-Date不存在")?,
+            query("X-Amz-Credential").context("X-Amz-Credential不存在")?,
+            Some(expires),
+        )
+    } else {
+        let authorization = header("Authorization").context("Authorization不存在")?;
+        let credential = authorization
+            .split(',')
+            .next()
+            .and_then(|part| part.split('=').nth(1))
+            .context("Authorization缺少Credential")?
+            .to_string();
+        (
+            header("x-amz-date").context("x-amz-date不存在")?,
+            credential,
+            None,
+        )
+    };
+    let scope_date = split_credential(&credential)?[1].to_string();
+    Ok((request_date, scope_date, expires))
+}
+
+// 按签名中的访问密钥查询身份并校验签名时间和签名
+async fn authenticate(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    root: (&str, &str),
+    presigned: bool,
+) -> Result<Identity, SignatureError> {
+    let malformed = |err: anyhow::Error| SignatureError::Malformed(format!("{:#}", err));
+    let (request_date, scope_date, expires) =
+        signing_time(request, presigned).map_err(malformed)?;
+    check_signing_time(&request_date, &scope_date, expires, Utc::now())?;
+    let access_key = request_access_key(request, presigned)
+        .ok_or_else(|| SignatureError::Malformed("缺少访问密钥".to_string()))?;
+    let identity = match identity::resolve(&access_key, root).await {
+        Ok(Some(identity)) => identity,
+        Ok(None) => return Err(SignatureError::InvalidAccessKeyId),
+        Err(err) => {
+            warn!("查询访问密钥 {} 失败: {:#}", access_key, err);
+            return Err(SignatureError::Unavailable);
+        }
+    };
+    let valid = |secret_key: &str| {
+        if presigned {
+            valid_authorization_url(request, &identity.access_key, secret_key)
+        } else {
+            valid_authorization_header(request, &identity.access_key, secret_key)
+        }
+        .map_err(malformed)
+    };
+    if valid(&identity.secret_key)? {
+        return Ok(identity);
+    }
+    match &identity.previous_secret_key {
+        Some(previous) if valid(previous)? => {
+            crate::keys::audit(
+                "access_key_previous_secret_used",
+                &identity.access_key,
+                serde_json::json!({ "path": request.path() }),
+            );
+            Ok(identity)
+        }
+        _ => Err(SignatureError::SignatureDoesNotMatch),
+    }
+}
+
 // 如果验证信息在请求头中
 fn valid_authorization_header(
     request: &web::WebRequest<impl web::ErrorRenderer>,
@@ -115,9 +657,7 @@ fn valid_authorization_header(
         .context("x-amz-content-sha256不存在")?
         .to_str()?;
     let http_method = request.method().to_string();
-    let uri = request
-        .uri()
-        .path()
+    let uri = vhost::signed_path(request)
         .split('?')
         .next()
         .context("不存在")?
@@ -126,20 +666,24 @@ fn valid_authorization_header(
 
     // Splitting authorization into parts
     let parts: Vec<&str> = authorization.trim().split(',').collect();
-    let credential = parts[0].split('=').nth(1).context("不存在")?;
-    let credentials: Vec<&str> = credential.split('/').collect();
-    let access_key = credentials[0];
+    let part = |i: usize| {
+        parts
+            .get(i)
+            .and_then(|part| part.split('=').nth(1))
+            .context("Authorization格式错误")
+    };
+    let credential = part(0)?;
+    let [access_key, date, region, service, aws4_request] = split_credential(credential)?;
     if access_key_id != access_key {
         return Ok(false);
     }
-    let date = credentials[1];
-    let region = credentials[2];
-    let service = credentials[3];
-    let aws4_request = credentials[4];
 
-    let signed_header = parts[1].split('=').nth(1).context("不存在")?;
+    let signed_header = part(1)?;
     let signed_headers: Vec<&str> = signed_header.split(';').collect();
-    let signature = parts[2].split('=').nth(1).context("不存在")?;
+    if !signed_headers.contains(&"host") {
+        anyhow::bail!("SignedHeaders必须包含host");
+    }
+    let signature = part(2)?;
 
     let string_to_sign = {
         let mut string_to_sign = String::new();
@@ -196,7 +740,7 @@ fn valid_authorization_header(
     let signature_key = do_hmac_sha256(&k_service, aws4_request)?;
     let auth_signature = do_hmac_sha256(&signature_key, &string_to_sign)?;
     let str_hex_signature = do_bytes_to_hex(&auth_signature);
-    Ok(signature == str_hex_signature)
+    Ok(signature_eq(signature, &str_hex_signature))
 }
 
 // 如果验证信息在请求参数中
@@ -205,35 +749,43 @@ fn valid_authorization_url(
     access_key_id: &str,
     secret_access_key: &str,
 ) -> anyhow::Result<bool> {
-    let qs = request.query_string();
+    let uri = vhost::signed_path(request);
+    verify_presigned_url(
+        request.method().as_str(),
+        &uri,
+        request.query_string(),
+        request.headers(),
+        access_key_id,
+        secret_access_key,
+    )
+}
+
+// 校验预签名 URL（签名在查询参数中）的签名，不校验有效期。uri 为签名时的请求路径，
+// headers 中需要有 X-Amz-SignedHeaders 列出的请求头
+pub fn verify_presigned_url(
+    http_method: &str,
+    uri: &str,
+    qs: &str,
+    headers: &HeaderMap,
+    access_key_id: &str,
+    secret_access_key: &str,
+) -> anyhow::Result<bool> {
     let request_date = url::form_urlencoded::parse(qs.as_bytes())
         .find(|(key, _)| key == "X-Amz-Date")
         .map(|(_, value)| value.into_owned())
         .context("X-Amz-Date不存在")?;
     let content_hash = "UNSIGNED-PAYLOAD";
-    let http_method = request.method().to_string();
-    let uri = request
-        .uri()
-        .path()
-        .split('?')
-        .next()
-        .context("不存在")?
-        .to_owned();
-    let query_string = request.query_string().to_owned();
+    let uri = uri.split('?').next().context("不存在")?;
+    let query_string = qs;
 
     let credential = url::form_urlencoded::parse(qs.as_bytes())
         .find(|(key, _)| key == "X-Amz-Credential")
         .map(|(_, value)| value.into_owned())
         .context("X-Amz-Credential不存在")?;
-    let credentials: Vec<&str> = credential.split('/').collect();
-    let access_key = credentials[0];
+    let [access_key, date, region, service, aws4_request] = split_credential(&credential)?;
     if access_key_id != access_key {
         return Ok(false);
     }
-    let date = credentials[1];
-    let region = credentials[2];
-    let service = credentials[3];
-    let aws4_request = credentials[4];
 
     // 第二部分-签名头中包含哪些字段
     let signed_header = url::form_urlencoded::parse(qs.as_bytes())
@@ -241,6 +793,9 @@ fn valid_authorization_url(
         .map(|(_, value)| value.into_owned())
         .context("X-Amz-SignedHeaders不存在")?;
     let signed_headers: Vec<&str> = signed_header.split(';').collect();
+    if !signed_headers.contains(&"host") {
+        anyhow::bail!("X-Amz-SignedHeaders必须包含host");
+    }
 
     // 第三部分-生成的签名
     let signature = url::form_urlencoded::parse(qs.as_bytes())
@@ -248,19 +803,7 @@ fn valid_authorization_url(
         .map(|(_, value)| value.into_owned())
         .context("X-Amz-Signature不存在")?;
 
-    // 验证过期
-    let expires = url::form_urlencoded::parse(qs.as_bytes())
-        .find(|(key, _)| key == "X-Amz-Expires")
-        .and_then(|(_, value)| value.parse::<i64>().ok())
-        .context("X-Amz-Expires不存在")?;
-    let fmt = "%Y%m%dT%H%M%SZ";
-    let request_date_time = NaiveDateTime::parse_from_str(&request_date, fmt)
-        .context("解析日期错误")?
-        .and_utc();
-    let end_date = request_date_time + chrono::Duration::seconds(expires);
-    if end_date < Utc::now() {
-        return Ok(false);
-    }
+    // 有效期已在 check_signing_time 中校验
     let string_to_sign = {
         let mut string_to_sign = String::new();
         string_to_sign.push_str("AWS4-HMAC-SHA256\n");
@@ -276,7 +819,9 @@ fn valid_authorization_url(
         hashed_canonical_request.push_str(&format!("{}\n", uri));
 
         if !query_string.is_empty() {
-            let query_params = parse_query_params(&query_string);
+            // 签名本身不参与签名
+            let mut query_params = parse_query_params(query_string);
+            query_params.remove("X-Amz-Signature");
             let mut query_string_builder = String::new();
             let mut query_params_vec: Vec<_> = query_params.iter().collect();
             query_params_vec.sort_by(|a, b| a.0.cmp(b.0));
@@ -289,7 +834,6 @@ fn valid_authorization_url(
             hashed_canonical_request.push('\n');
         }
 
-        let headers = request.headers();
         for name in signed_headers {
             if let Some(header_value) = headers.get(name) {
                 hashed_canonical_request.push_str(&format!(
@@ -315,19 +859,16 @@ fn valid_authorization_url(
     let signature_key = do_hmac_sha256(&k_service, aws4_request)?;
     let auth_signature = do_hmac_sha256(&signature_key, &string_to_sign)?;
     let str_hex_signature = do_bytes_to_hex(&auth_signature);
-    Ok(signature == str_hex_signature)
+    Ok(signature_eq(&signature, &str_hex_signature))
 }
 
 // 解析请求参数中的参数
 fn parse_query_params(query_string: &str) -> HashMap<String, String> {
     let mut query_params = HashMap::new();
-    for param in query_string.split('&') {
-        let parts: Vec<&str> = param.splitn(2, '=').collect();
-        if parts.len() == 2 {
-            let key = parts[0].to_owned();
-            let value = parts[1].to_owned();
-            query_params.insert(key, value);
-        }
+    for param in query_string.split('&').filter(|p| !p.is_empty()) {
+        // 无值的参数（如 `?uploads`）在规范请求中记为 `key=`
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        query_params.insert(key.to_owned(), value.to_owned());
     }
     query_params
 }
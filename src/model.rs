@@ -1,3 +1,4 @@
+use crate::tagging::TagSet;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -9,7 +10,7 @@ pub struct Bucket {
     #[serde(rename = "Name")]
     pub name: String,
     #[serde(rename = "CreationDate")]
-    pub creation_date: String,
+    pub creation_date: DateTime<Utc>,
 }
 
 // 完成上传请求体
@@ -30,6 +31,97 @@ pub struct CompleteMultipartUploadResult {
     pub etag: String,
 }
 
+// 提交暂存对象返回结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitStagedResult {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "StagingId")]
+    pub staging_id: String,
+    #[serde(rename = "Committed")]
+    pub committed: usize,
+}
+
+// 展开归档返回结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtractResult {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "Prefix")]
+    pub prefix: String,
+    #[serde(rename = "Extracted")]
+    pub extracted: usize,
+    #[serde(rename = "Bytes")]
+    pub bytes: u64,
+}
+
+// 重命名对象返回结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameResult {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "Source")]
+    pub source: String,
+    #[serde(rename = "Target")]
+    pub target: String,
+    #[serde(rename = "Renamed")]
+    pub renamed: usize,
+}
+
+// 拷贝对象返回结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CopyObjectResult {
+    #[serde(rename = "LastModified")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+// 批量删除请求体
+#[derive(Debug, Clone, Deserialize)]
+pub struct Delete {
+    #[serde(rename = "Object", default)]
+    pub objects: Vec<ObjectIdentifier>,
+    // 为 true 时只返回删除失败的对象
+    #[serde(rename = "Quiet", default)]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObjectIdentifier {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId", default)]
+    pub version_id: Option<String>,
+}
+
+// 批量删除返回结果
+#[derive(Debug, Default, Serialize)]
+pub struct DeleteResult {
+    #[serde(rename = "Deleted")]
+    pub deleted: Vec<DeletedObject>,
+    #[serde(rename = "Error")]
+    pub errors: Vec<DeleteError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedObject {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId", skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteError {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Code")]
+    pub code: String,
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
 // 初始化分片上传请求结果
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InitiateMultipartUploadResult {
@@ -64,15 +156,112 @@ pub struct Content {
     pub key: String,
     #[serde(rename = "LastModified")]
     pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    #[serde(rename = "Size")]
+    pub size: i64,
+    // 扩展：对象的标签数
+    #[serde(rename = "TagCount", skip_serializing_if = "Option::is_none")]
+    pub tag_count: Option<usize>,
+    // 扩展：metadata=true 时内联返回用户自定义元数据和标签
+    #[serde(rename = "UserMetadata", skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<UserMetadata>,
+    #[serde(rename = "TagSet", skip_serializing_if = "Option::is_none")]
+    pub tag_set: Option<TagSet>,
+}
+
+// 用户自定义元数据，键不含 x-amz-meta- 前缀
+#[derive(Debug, Serialize)]
+pub struct UserMetadata {
+    #[serde(rename = "Entry")]
+    pub entries: Vec<UserMetadataEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserMetadataEntry {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+// 对象版本列表中的一个版本
+#[derive(Debug, Serialize)]
+pub struct ObjectVersionEntry {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId")]
+    pub version_id: String,
+    #[serde(rename = "IsLatest")]
+    pub is_latest: bool,
+    #[serde(rename = "LastModified")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
     #[serde(rename = "Size")]
     pub size: i64,
+    #[serde(rename = "StorageClass")]
+    pub storage_class: String,
+    #[serde(rename = "TagCount", skip_serializing_if = "Option::is_none")]
+    pub tag_count: Option<usize>,
+}
+
+// 对象版本列表中的一个删除标记
+#[derive(Debug, Serialize)]
+pub struct DeleteMarkerEntry {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId")]
+    pub version_id: String,
+    #[serde(rename = "IsLatest")]
+    pub is_latest: bool,
+    #[serde(rename = "LastModified")]
+    pub last_modified: DateTime<Utc>,
 }
 
-// 判断是否存在请求结果
+// 已上传分片列表
+#[derive(Debug, Serialize)]
+#[serde(rename = "ListPartsResult")]
+pub struct ListPartsResult {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "UploadId")]
+    pub upload_id: String,
+    #[serde(rename = "PartNumberMarker")]
+    pub part_number_marker: u32,
+    #[serde(rename = "NextPartNumberMarker")]
+    pub next_part_number_marker: u32,
+    #[serde(rename = "MaxParts")]
+    pub max_parts: u32,
+    #[serde(rename = "IsTruncated")]
+    pub is_truncated: bool,
+    #[serde(rename = "Part")]
+    pub parts: Vec<Part>,
+}
+
+// 已上传分片
+#[derive(Debug, Serialize)]
+pub struct Part {
+    #[serde(rename = "PartNumber")]
+    pub part_number: u32,
+    #[serde(rename = "LastModified")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    #[serde(rename = "Size")]
+    pub size: u64,
+}
+
+// 错误返回结果
 #[derive(Debug, Serialize, Deserialize)]
-pub struct HeadNotFoundResp {
-    #[serde(rename = "NoExist")]
-    pub no_exist: String,
+#[serde(rename = "Error")]
+pub struct ErrorResp {
+    #[serde(rename = "Code")]
+    pub code: String,
+    #[serde(rename = "Message")]
+    pub message: String,
 }
 
 // 元数据
@@ -123,16 +312,16 @@ pub struct BucketWrapper {
 // 桶拥有者实体
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Owner {
+    #[serde(rename = "ID")]
+    pub id: String,
     #[serde(rename = "DisplayName")]
     pub display_name: String,
 }
 
 // 桶列表请求结果
-
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename = "ListAllMyBucketsResult")]
 pub struct ListBucketResp {
-    #[serde(rename = "Id")]
-    pub id: String,
     #[serde(rename = "Owner")]
     pub owner: Owner,
     #[serde(rename = "Buckets")]
@@ -0,0 +1,213 @@
+use crate::etag;
+use crate::fs;
+use crate::model::PartETag;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::io;
+
+// --- 分片上传的分片记录：每个已上传的分片在临时目录中有一个以分片号命名的记录文件，
+// 内容为 "大小\n哈希\nMD5"，分片的 ETag 为分片的 MD5，旧版本写入的记录没有 MD5，ETag 为分片哈希。
+// 超过分片大小的上传分片按分片大小切分保存，记录第四行为切分后的 "哈希:大小" 列表（空格分隔）；
+// 没有第四行时整个上传分片就是一个分片。完成上传时按顺序拼接各上传分片的分片列表写入元数据，
+// 不读取分片内容
+
+// 分片号的上限，与 S3 一致
+pub const MAX_PART_NUMBER: u32 = 10000;
+// ListParts 默认和最多返回的分片数
+pub const MAX_PARTS: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadedPart {
+    pub part_number: u32,
+    // 分片内容的哈希，即保存的分片
+    pub hash: String,
+    pub etag: String,
+    pub size: u64,
+    pub last_modified: DateTime<Utc>,
+    // 保存的分片及其大小
+    pub chunks: Vec<(String, u64)>,
+}
+
+// 完成上传后对象的分片列表
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssembledParts {
+    pub size: u64,
+    pub chunks: Vec<String>,
+    pub chunk_sizes: Vec<u64>,
+    // 与 chunks 一一对应，分片所属的分片号和在该上传分片中的偏移，用于解密
+    pub chunk_parts: Vec<u32>,
+    pub chunk_offsets: Vec<u64>,
+    // 各上传分片内容的哈希，用于计算组合校验和
+    pub part_hashes: Vec<String>,
+}
+
+pub fn parse_part_number(s: &str) -> Option<u32> {
+    s.parse().ok().filter(|n| (1..=MAX_PART_NUMBER).contains(n))
+}
+
+// 解析分片记录，返回 (大小, 哈希, ETag)
+pub fn parse_part_record(content: &str) -> Option<(u64, String, String)> {
+    let mut lines = content.lines().map(str::trim);
+    let size = lines.next()?.parse().ok()?;
+    let hash = lines.next().filter(|hash| !hash.is_empty())?;
+    let etag = lines.next().filter(|etag| !etag.is_empty()).unwrap_or(hash);
+    Some((size, hash.to_string(), etag.to_string()))
+}
+
+// 生成分片记录，只有一个分片时省略分片列表
+pub fn format_part_record(size: u64, hash: &str, etag: &str, chunks: &[(String, u64)]) -> String {
+    let mut record = format!("{}\n{}\n{}", size, hash, etag);
+    if chunks.len() > 1 {
+        let chunks: Vec<String> = chunks
+            .iter()
+            .map(|(hash, size)| format!("{}:{}", hash, size))
+            .collect();
+        record.push('\n');
+        record.push_str(&chunks.join(" "));
+    }
+    record
+}
+
+// 解析分片记录中保存的分片及其大小，没有分片列表时为整个上传分片
+pub fn parse_part_chunks(content: &str) -> Option<Vec<(String, u64)>> {
+    let (size, hash, _) = parse_part_record(content)?;
+    let Some(list) = content
+        .lines()
+        .nth(3)
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+    else {
+        return Some(vec![(hash, size)]);
+    };
+    list.split(' ')
+        .map(|item| {
+            let (hash, size) = item.split_once(':')?;
+            Some((hash.to_string(), size.parse().ok()?))
+        })
+        .collect()
+}
+
+// 客户端回传的 ETag 可能带引号，去掉引号并转为大写后与分片 ETag 比较
+pub fn normalize_etag(etag: &str) -> String {
+    etag.trim().trim_matches('"').to_uppercase()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CompletionError {
+    // 没有列出任何分片
+    NoParts,
+    // 分片号未按升序排列
+    InvalidPartOrder,
+    // 分片未上传或 ETag 不一致
+    InvalidPart(i32),
+}
+
+// 校验完成上传请求中的分片列表（ETag 已规范化）
+pub fn check_completion(
+    requested: &[PartETag],
+    uploaded: &BTreeMap<u32, UploadedPart>,
+) -> Result<(), CompletionError> {
+    if requested.is_empty() {
+        return Err(CompletionError::NoParts);
+    }
+    if requested
+        .windows(2)
+        .any(|pair| pair[0].part_number >= pair[1].part_number)
+    {
+        return Err(CompletionError::InvalidPartOrder);
+    }
+    for part in requested {
+        let matched = u32::try_from(part.part_number)
+            .ok()
+            .and_then(|n| uploaded.get(&n))
+            .is_some_and(|uploaded| normalize_etag(&uploaded.etag) == part.etag);
+        if !matched {
+            return Err(CompletionError::InvalidPart(part.part_number));
+        }
+    }
+    Ok(())
+}
+
+// 完成上传后对象的 ETag，按请求中的分片顺序计算（分片已校验存在）
+pub fn completed_etag(requested: &[PartETag], uploaded: &BTreeMap<u32, UploadedPart>) -> String {
+    let part_etags: Vec<&str> = requested
+        .iter()
+        .filter_map(|part| uploaded.get(&u32::try_from(part.part_number).ok()?))
+        .map(|part| part.etag.as_str())
+        .collect();
+    etag::multipart_etag(&part_etags)
+}
+
+// 按请求中的分片顺序拼接各上传分片的分片列表（分片已校验存在）
+pub fn assemble(requested: &[PartETag], uploaded: &BTreeMap<u32, UploadedPart>) -> AssembledParts {
+    let mut assembled = AssembledParts::default();
+    let parts = requested
+        .iter()
+        .filter_map(|part| uploaded.get(&u32::try_from(part.part_number).ok()?));
+    for part in parts {
+        let mut offset = 0;
+        for (hash, size) in &part.chunks {
+            assembled.chunks.push(hash.clone());
+            assembled.chunk_sizes.push(*size);
+            assembled.chunk_parts.push(part.part_number);
+            assembled.chunk_offsets.push(offset);
+            offset += size;
+        }
+        assembled.size += part.size;
+        assembled.part_hashes.push(part.hash.clone());
+    }
+    assembled
+}
+
+// 从 marker 之后取最多 max_parts 个分片，返回分片和是否还有更多分片
+pub fn page_parts(
+    uploaded: &BTreeMap<u32, UploadedPart>,
+    marker: u32,
+    max_parts: u32,
+) -> (Vec<UploadedPart>, bool) {
+    let mut rest = uploaded.range(marker.saturating_add(1)..).map(|(_, p)| p);
+    let page: Vec<UploadedPart> = rest.by_ref().take(max_parts as usize).cloned().collect();
+    (page, rest.next().is_some())
+}
+
+// 上传 ID 由服务端生成为 UUID，其他值一律视为不存在，避免拼接出任意路径
+pub(crate) fn upload_exists(bucket: &str, object_key: &str, upload_id: &str) -> bool {
+    uuid::Uuid::parse_str(upload_id).is_ok()
+        && fs::upload_meta_path(bucket, object_key, upload_id).exists()
+}
+
+// 读取已上传的分片，无法解析的记录跳过
+pub(crate) fn uploaded_parts(upload_id: &str) -> io::Result<BTreeMap<u32, UploadedPart>> {
+    let mut parts = BTreeMap::new();
+    for entry in std::fs::read_dir(fs::upload_parts_dir(upload_id))? {
+        let entry = entry?;
+        let Some(part_number) = entry.file_name().to_str().and_then(parse_part_number) else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let (Some((size, hash, etag)), Some(chunks)) =
+            (parse_part_record(&content), parse_part_chunks(&content))
+        else {
+            continue;
+        };
+        let last_modified = entry
+            .metadata()?
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        parts.insert(
+            part_number,
+            UploadedPart {
+                part_number,
+                hash,
+                etag,
+                size,
+                last_modified,
+                chunks,
+            },
+        );
+    }
+    Ok(parts)
+}
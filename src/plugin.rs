@@ -0,0 +1,297 @@
+use crate::cdc::CdcEvent;
+use crate::config;
+use crate::config::{PluginConfig, PluginHook};
+use crate::pool;
+use anyhow::{anyhow, bail, Context};
+use log::{info, warn};
+use serde_json::json;
+use std::sync::OnceLock;
+use wasmi::{
+    Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+// --- 扩展：WASM 插件。模块导出 memory、alloc(len) -> ptr 和所挂载调用点的函数：
+//   pre_put(ctx_ptr, ctx_len, body_ptr, body_len) -> i64   返回非 0 时拒绝上传，结果为拒绝原因
+//   post_get(ctx_ptr, ctx_len, body_ptr, body_len) -> i64  返回非 0 时以结果替换返回的内容
+//   on_event(ctx_ptr, ctx_len)                              ctx 为 CDC 格式的事件
+// ctx 为 JSON，i64 结果为 (ptr << 32) | len。每次调用使用新的实例，插件之间、调用之间不共享状态；
+// 可导入 rs3.log(ptr, len) 输出日志
+
+// 单次调用的燃料（约等于执行的指令数）上限，避免插件死循环
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+// 单个实例的内存上限
+const MAX_MEMORY: usize = 256 << 20;
+
+struct Plugin {
+    name: String,
+    config: PluginConfig,
+    module: Module,
+}
+
+struct HostState {
+    plugin: String,
+    limits: StoreLimits,
+}
+
+static PLUGINS: OnceLock<Vec<Plugin>> = OnceLock::new();
+
+fn plugins() -> &'static [Plugin] {
+    PLUGINS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+// 启动时编译全部插件，并检查挂载的调用点均已导出
+pub(crate) fn load() -> anyhow::Result<()> {
+    let configs = &config::get().plugins;
+    if configs.is_empty() {
+        return Ok(());
+    }
+    let mut engine_config = Config::default();
+    engine_config.consume_fuel(true);
+    let engine = Engine::new(&engine_config);
+    let mut loaded = Vec::with_capacity(configs.len());
+    for plugin in configs {
+        let wasm = std::fs::read(&plugin.path)
+            .with_context(|| format!("读取插件 {:?} 失败", plugin.path))?;
+        let module = Module::new(&engine, wasm)
+            .map_err(|err| anyhow!("编译插件 {:?} 失败: {}", plugin.path, err))?;
+        let exports: Vec<&str> = module.exports().map(|export| export.name()).collect();
+        for name in ["memory", "alloc"]
+            .into_iter()
+            .chain(plugin.hooks.iter().map(PluginHook::export_name))
+        {
+            if !exports.contains(&name) {
+                bail!("插件 {:?} 没有导出 {}", plugin.path, name);
+            }
+        }
+        let name = plugin
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        info!("加载插件 {}（{:?}）", name, plugin.hooks);
+        loaded.push(Plugin {
+            name,
+            config: plugin.clone(),
+            module,
+        });
+    }
+    let _ = PLUGINS.set(loaded);
+    Ok(())
+}
+
+// 是否有插件处理该调用点和对象路径（"桶/键"）
+pub(crate) fn applies(hook: PluginHook, object_path: &str) -> bool {
+    plugins()
+        .iter()
+        .any(|p| p.config.hooks.contains(&hook) && p.config.applies_to(object_path))
+}
+
+pub(crate) fn has_event_hooks() -> bool {
+    plugins()
+        .iter()
+        .any(|p| p.config.hooks.contains(&PluginHook::Event))
+}
+
+fn read_memory(
+    memory: &Memory,
+    store: &Store<HostState>,
+    ptr: u32,
+    len: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len as usize];
+    memory
+        .read(store, ptr as usize, &mut buf)
+        .map_err(|err| anyhow!("读取插件内存失败: {}", err))?;
+    Ok(buf)
+}
+
+// 一次调用的实例
+struct Call {
+    store: Store<HostState>,
+    instance: Instance,
+    memory: Memory,
+}
+
+impl Call {
+    fn new(plugin: &Plugin) -> anyhow::Result<Self> {
+        let engine = plugin.module.engine();
+        let mut store = Store::new(
+            engine,
+            HostState {
+                plugin: plugin.name.clone(),
+                limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let mut linker = Linker::new(engine);
+        linker.func_wrap(
+            "rs3",
+            "log",
+            |caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+                let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                    return;
+                };
+                let mut buf = vec![0u8; len as usize];
+                if memory.read(&caller, ptr as usize, &mut buf).is_ok() {
+                    info!(
+                        "插件 {}: {}",
+                        caller.data().plugin,
+                        String::from_utf8_lossy(&buf)
+                    );
+                }
+            },
+        )?;
+        let instance = linker.instantiate_and_start(&mut store, &plugin.module)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .context("插件没有导出 memory")?;
+        Ok(Call {
+            store,
+            instance,
+            memory,
+        })
+    }
+
+    // 把数据写入插件分配的内存，返回 (ptr, len)
+    fn write(&mut self, data: &[u8]) -> anyhow::Result<(u32, u32)> {
+        let alloc = self
+            .instance
+            .get_typed_func::<u32, u32>(&self.store, "alloc")?;
+        let len = u32::try_from(data.len()).context("数据超过 4GiB")?;
+        let ptr = alloc.call(&mut self.store, len)?;
+        self.memory
+            .write(&mut self.store, ptr as usize, data)
+            .map_err(|err| anyhow!("写入插件内存失败: {}", err))?;
+        Ok((ptr, len))
+    }
+
+    // 调用带 ctx 和 body 的调用点，返回结果数据，结果为 0 时返回 None
+    fn call(
+        &mut self,
+        hook: PluginHook,
+        ctx: &[u8],
+        body: &[u8],
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let (ctx_ptr, ctx_len) = self.write(ctx)?;
+        let (body_ptr, body_len) = self.write(body)?;
+        let func = self
+            .instance
+            .get_typed_func::<(u32, u32, u32, u32), u64>(&self.store, hook.export_name())?;
+        let packed = func.call(&mut self.store, (ctx_ptr, ctx_len, body_ptr, body_len))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        read_memory(
+            &self.memory,
+            &self.store,
+            (packed >> 32) as u32,
+            packed as u32,
+        )
+        .map(Some)
+    }
+
+    fn event(&mut self, ctx: &[u8]) -> anyhow::Result<()> {
+        let (ctx_ptr, ctx_len) = self.write(ctx)?;
+        let func = self
+            .instance
+            .get_typed_func::<(u32, u32), ()>(&self.store, PluginHook::Event.export_name())?;
+        func.call(&mut self.store, (ctx_ptr, ctx_len))?;
+        Ok(())
+    }
+}
+
+fn object_ctx(
+    hook: &str,
+    bucket: &str,
+    key: &str,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Vec<u8> {
+    json!({
+        "hook": hook,
+        "bucket": bucket,
+        "key": key,
+        "size": body.len(),
+        "content_type": content_type,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+fn matching(hook: PluginHook, object_path: &str) -> impl Iterator<Item = &'static Plugin> + '_ {
+    plugins()
+        .iter()
+        .filter(move |p| p.config.hooks.contains(&hook) && p.config.applies_to(object_path))
+}
+
+// 依次调用 pre-put 插件，任一插件拒绝时返回拒绝原因；请求体原样交还
+pub(crate) async fn pre_put(
+    bucket: String,
+    key: String,
+    content_type: Option<String>,
+    body: Vec<u8>,
+) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+    pool::run(move || {
+        let ctx = object_ctx("pre-put", &bucket, &key, content_type.as_deref(), &body);
+        let object_path = format!("{}/{}", bucket, key);
+        for plugin in matching(PluginHook::PrePut, &object_path) {
+            let rejected = Call::new(plugin)
+                .and_then(|mut call| call.call(PluginHook::PrePut, &ctx, &body))
+                .with_context(|| format!("插件 {} 执行失败", plugin.name))?;
+            if let Some(reason) = rejected {
+                let reason = String::from_utf8_lossy(&reason).to_string();
+                info!("插件 {} 拒绝上传 {}: {}", plugin.name, object_path, reason);
+                return Ok((body, Some(reason)));
+            }
+        }
+        Ok((body, None))
+    })
+    .await?
+}
+
+// 依次调用 post-get 插件，每个插件处理上一个插件的输出
+pub(crate) async fn post_get(
+    bucket: String,
+    key: String,
+    content_type: String,
+    mut body: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    pool::run(move || {
+        let object_path = format!("{}/{}", bucket, key);
+        for plugin in matching(PluginHook::PostGet, &object_path) {
+            let ctx = object_ctx("post-get", &bucket, &key, Some(&content_type), &body);
+            let replaced = Call::new(plugin)
+                .and_then(|mut call| call.call(PluginHook::PostGet, &ctx, &body))
+                .with_context(|| format!("插件 {} 执行失败", plugin.name))?;
+            if let Some(replaced) = replaced {
+                body = replaced;
+            }
+        }
+        Ok(body)
+    })
+    .await?
+}
+
+// 在后台把变更事件发送给 event 插件，失败只记录日志
+pub(crate) fn emit(event: &CdcEvent) {
+    let object_path = match &event.key {
+        Some(key) => format!("{}/{}", event.bucket, key),
+        None => event.bucket.clone(),
+    };
+    let targets: Vec<&'static Plugin> = matching(PluginHook::Event, &object_path).collect();
+    if targets.is_empty() {
+        return;
+    }
+    let Ok(ctx) = serde_json::to_vec(event) else {
+        return;
+    };
+    drop(pool::spawn(move || {
+        for plugin in targets {
+            if let Err(err) = Call::new(plugin).and_then(|mut call| call.event(&ctx)) {
+                warn!("插件 {} 处理事件失败: {:#}", plugin.name, err);
+            }
+        }
+    }));
+}
@@ -0,0 +1,342 @@
+use crate::copy;
+use crate::fs;
+use crate::identity::Operation;
+use crate::statsd;
+use ntex::web;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+// --- 桶策略：PUT /api/{bucket}?policy 保存 IAM 策略 JSON 的一个子集，每条语句包含 Effect
+// （Allow/Deny）、Principal（"*" 或 {"AWS": 访问密钥或列表}）、Action（如 s3:GetObject，支持 *）
+// 和 Resource（本桶的 arn:aws:s3:::桶 或 arn:aws:s3:::桶/键前缀*），不支持 Condition 等其他字段。
+// 签名认证之后生效：匹配的 Deny 总是拒绝；匹配的 Allow 可以补充访问密钥本身没有的权限，
+// Principal 为 "*" 时也允许匿名请求；没有匹配的语句时按访问密钥的权限处理。
+// 根密钥读写和删除桶策略时不受策略限制，避免策略把自己锁在外面
+
+// 桶策略文件名
+const POLICY_CONFIG_FILE: &str = ".policy.json";
+
+const ARN_PREFIX: &str = "arn:aws:s3:::";
+
+// 单个值或列表
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn values(&self) -> &[String] {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Principal {
+    // 只能为 "*"
+    Any(String),
+    Aws {
+        #[serde(rename = "AWS")]
+        aws: OneOrMany,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Statement {
+    #[serde(rename = "Sid", default)]
+    pub sid: Option<String>,
+    #[serde(rename = "Effect")]
+    pub effect: Effect,
+    #[serde(rename = "Principal")]
+    pub principal: Principal,
+    #[serde(rename = "Action")]
+    pub action: OneOrMany,
+    #[serde(rename = "Resource")]
+    pub resource: OneOrMany,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Statements {
+    One(Box<Statement>),
+    Many(Vec<Statement>),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyDocument {
+    #[serde(rename = "Version", default)]
+    _version: Option<String>,
+    #[serde(rename = "Id", default)]
+    _id: Option<String>,
+    #[serde(rename = "Statement")]
+    statement: Statements,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketPolicy {
+    pub statements: Vec<Statement>,
+}
+
+// 策略对请求的判断结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    // 没有匹配的语句
+    NoMatch,
+}
+
+// 策略作用的资源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resource<'a> {
+    Bucket(&'a str),
+    Object(&'a str, &'a str),
+    // 桶内事先不知道的一批对象（批量删除）：任一对象级的 Deny 都拒绝，Allow 需覆盖整个桶
+    AnyObject(&'a str),
+}
+
+// 只支持 * 和 ? 的通配符匹配
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let (p, v) = (pattern.as_bytes(), value.as_bytes());
+    let (mut pi, mut vi) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while vi < v.len() {
+        if pi < p.len() && (p[pi] == b'?' || p[pi] == v[vi]) {
+            pi += 1;
+            vi += 1;
+        } else if pi < p.len() && p[pi] == b'*' {
+            star = Some((pi, vi));
+            pi += 1;
+        } else if let Some((star_pi, star_vi)) = star {
+            pi = star_pi + 1;
+            vi = star_vi + 1;
+            star = Some((star_pi, star_vi + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == b'*')
+}
+
+impl Statement {
+    fn matches_principal(&self, principal: Option<&str>) -> bool {
+        let values = match &self.principal {
+            Principal::Any(_) => return true,
+            Principal::Aws { aws } => aws.values(),
+        };
+        values
+            .iter()
+            .any(|value| value == "*" || Some(value.as_str()) == principal)
+    }
+
+    fn matches_action(&self, action: &str) -> bool {
+        self.action.values().iter().any(|pattern| {
+            wildcard_match(&pattern.to_ascii_lowercase(), &action.to_ascii_lowercase())
+        })
+    }
+
+    fn matches_resource(&self, resource: Resource) -> bool {
+        self.resource.values().iter().any(|pattern| {
+            let pattern = pattern.strip_prefix(ARN_PREFIX).unwrap_or(pattern);
+            match resource {
+                Resource::Bucket(bucket) => wildcard_match(pattern, bucket),
+                Resource::Object(bucket, key) => {
+                    wildcard_match(pattern, &format!("{}/{}", bucket, key))
+                }
+                Resource::AnyObject(bucket) => match self.effect {
+                    Effect::Allow => wildcard_match(pattern, &format!("{}/*", bucket)),
+                    Effect::Deny => pattern.contains('/') || pattern.contains('*'),
+                },
+            }
+        })
+    }
+}
+
+impl BucketPolicy {
+    // 解析并校验桶策略，资源只能属于该桶
+    pub fn parse(json: &str, bucket: &str) -> Result<BucketPolicy, String> {
+        let document: PolicyDocument =
+            serde_json::from_str(json).map_err(|err| format!("invalid policy: {}", err))?;
+        let statements = match document.statement {
+            Statements::One(statement) => vec![*statement],
+            Statements::Many(statements) => statements,
+        };
+        if statements.is_empty() {
+            return Err("policy has no statements".to_string());
+        }
+        for statement in &statements {
+            if let Principal::Any(value) = &statement.principal {
+                if value != "*" {
+                    return Err(format!("unsupported principal `{}`", value));
+                }
+            }
+            for action in statement.action.values() {
+                if !action.starts_with("s3:") && action != "*" {
+                    return Err(format!("unsupported action `{}`", action));
+                }
+            }
+            for resource in statement.resource.values() {
+                let path = resource
+                    .strip_prefix(ARN_PREFIX)
+                    .ok_or_else(|| format!("resource `{}` is not an S3 ARN", resource))?;
+                let in_bucket = path == bucket
+                    || path
+                        .strip_prefix(bucket)
+                        .is_some_and(|rest| rest.starts_with('/'));
+                if !in_bucket {
+                    return Err(format!(
+                        "resource `{}` is outside bucket `{}`",
+                        resource, bucket
+                    ));
+                }
+            }
+        }
+        Ok(BucketPolicy { statements })
+    }
+
+    // 显式拒绝优先于允许
+    pub fn evaluate(&self, principal: Option<&str>, action: &str, resource: Resource) -> Decision {
+        let mut decision = Decision::NoMatch;
+        for statement in &self.statements {
+            if !statement.matches_principal(principal)
+                || !statement.matches_action(action)
+                || !statement.matches_resource(resource)
+            {
+                continue;
+            }
+            match statement.effect {
+                Effect::Deny => return Decision::Deny,
+                Effect::Allow => decision = Decision::Allow,
+            }
+        }
+        decision
+    }
+}
+
+// 请求对应的 S3 操作名（策略中的 Action）
+pub fn action_of(method: &str, path: &str, query: &str, copy: bool) -> &'static str {
+    match statsd::api_of(method, path, query, copy).unwrap_or("Other") {
+        "GetObject" | "HeadObject" => "s3:GetObject",
+        "ListObjects" | "ListObjectsV2" | "HeadBucket" => "s3:ListBucket",
+        "ListObjectVersions" => "s3:ListBucketVersions",
+        "ListBuckets" => "s3:ListAllMyBuckets",
+        "PutObject"
+        | "CopyObject"
+        | "UploadPart"
+        | "UploadPartCopy"
+        | "CreateMultipartUpload"
+        | "CompleteMultipartUpload" => "s3:PutObject",
+        "DeleteObject" | "DeleteObjects" => "s3:DeleteObject",
+        "GetObjectTagging" => "s3:GetObjectTagging",
+        "PutObjectTagging" => "s3:PutObjectTagging",
+        "DeleteObjectTagging" => "s3:DeleteObjectTagging",
+        "AbortMultipartUpload" => "s3:AbortMultipartUpload",
+        "ListParts" => "s3:ListMultipartUploadParts",
+        "CreateBucket" => "s3:CreateBucket",
+        "DeleteBucket" => "s3:DeleteBucket",
+        "GetBucketWebsite" => "s3:GetBucketWebsite",
+        "PutBucketWebsite" => "s3:PutBucketWebsite",
+        "DeleteBucketWebsite" => "s3:DeleteBucketWebsite",
+        "GetBucketVersioning" => "s3:GetBucketVersioning",
+        "PutBucketVersioning" => "s3:PutBucketVersioning",
+        "GetBucketPolicy" => "s3:GetBucketPolicy",
+        "PutBucketPolicy" => "s3:PutBucketPolicy",
+        "DeleteBucketPolicy" => "s3:DeleteBucketPolicy",
+        // S3 中删除生命周期配置也使用 PutLifecycleConfiguration 权限
+        "GetBucketLifecycle" => "s3:GetLifecycleConfiguration",
+        "PutBucketLifecycle" | "DeleteBucketLifecycle" => "s3:PutLifecycleConfiguration",
+        // 扩展接口按操作类别对应
+        _ => match Operation::of(method, path) {
+            Operation::Read => "s3:GetObject",
+            Operation::List => "s3:ListBucket",
+            Operation::Write => "s3:PutObject",
+            Operation::Delete => "s3:DeleteObject",
+        },
+    }
+}
+
+// 桶策略文件路径
+pub(crate) fn config_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, POLICY_CONFIG_FILE)
+}
+
+// 读取桶策略原文
+pub(crate) fn load_raw(bucket: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(bucket)).ok()
+}
+
+// 读取并解析桶策略，不存在或无法解析时返回 None
+pub(crate) fn load(bucket: &str) -> Option<BucketPolicy> {
+    BucketPolicy::parse(&load_raw(bucket)?, bucket).ok()
+}
+
+// 请求的目标对象和拷贝源对象的判断结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checked {
+    pub target: Decision,
+    pub source: Decision,
+}
+
+// 按目标桶（和拷贝源桶）的策略判断请求，principal 为访问密钥，匿名请求为 None；
+// api_path 为中间件校验过路径段的 "桶/键"，不是 /api/ 下的请求时为 None
+pub(crate) fn check(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+    api_path: Option<&str>,
+    principal: Option<&str>,
+    root_access_key: &str,
+) -> Checked {
+    let mut checked = Checked {
+        target: Decision::NoMatch,
+        source: Decision::NoMatch,
+    };
+    let Some(path) = api_path else {
+        return checked;
+    };
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    if bucket.is_empty() {
+        return checked;
+    }
+    let copy_source = request
+        .headers()
+        .get("x-amz-copy-source")
+        .and_then(|v| v.to_str().ok())
+        .and_then(copy::parse_source);
+    let action = action_of(
+        request.method().as_str(),
+        request.uri().path(),
+        request.query_string(),
+        copy_source.is_some(),
+    );
+    let exempt = principal == Some(root_access_key) && action.ends_with("BucketPolicy");
+    if let Some(policy) = load(bucket).filter(|_| !exempt) {
+        let resource = match (key, action) {
+            (_, "s3:DeleteObject") if key.is_empty() => Resource::AnyObject(bucket),
+            ("", _) => Resource::Bucket(bucket),
+            _ => Resource::Object(bucket, key),
+        };
+        checked.target = policy.evaluate(principal, action, resource);
+    }
+    if let Some(source) = copy_source {
+        let source_path = source.object_path();
+        if let Some((source_bucket, source_key)) = source_path.split_once('/') {
+            if let Some(policy) = load(source_bucket) {
+                let resource = Resource::Object(source_bucket, source_key);
+                checked.source = policy.evaluate(principal, "s3:GetObject", resource);
+            }
+        }
+    }
+    checked
+}
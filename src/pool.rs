@@ -0,0 +1,81 @@
+use crate::config;
+use crate::slowlog;
+use anyhow::Context;
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tokio::sync::oneshot;
+
+// --- 压缩、解压和分片读取使用的后台线程池。线程数由 --blocking-threads 配置，
+// 开启 --pin-cpus 时 HTTP worker 与后台线程依次绑定到各个 CPU 核心
+
+static POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+// 已绑定核心的线程数，用于轮流分配核心
+static PINNED: AtomicUsize = AtomicUsize::new(0);
+// 已提交但还未开始执行的任务数
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+
+// 开启 --pin-cpus 时把当前线程绑定到下一个核心
+pub(crate) fn pin_current_thread() {
+    if !config::get().pin_cpus {
+        return;
+    }
+    let Some(cores) = core_affinity::get_core_ids().filter(|cores| !cores.is_empty()) else {
+        warn!("无法获取 CPU 核心列表，不绑定核心");
+        return;
+    };
+    let core = cores[PINNED.fetch_add(1, Ordering::Relaxed) % cores.len()];
+    if !core_affinity::set_for_current(core) {
+        warn!("线程绑定到核心 {} 失败", core.id);
+    }
+}
+
+fn pool() -> &'static rayon::ThreadPool {
+    POOL.get_or_init(|| {
+        let threads = config::get().blocking_threads;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("s3-blocking-{}", i))
+            .start_handler(|_| pin_current_thread())
+            // 任务 panic 时丢弃其结果，等待方收到错误
+            .panic_handler(|_| error!("后台任务 panic"))
+            .build()
+            .expect("创建后台线程池失败");
+        info!("后台线程池 {} 个线程", pool.current_num_threads());
+        pool
+    })
+}
+
+// 排队等待执行的任务数
+pub(crate) fn queued() -> usize {
+    QUEUED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn threads() -> usize {
+    pool().current_num_threads()
+}
+
+// 在后台线程池中执行，返回的接收端在任务完成后得到结果；任务 panic 时接收端返回错误
+pub(crate) fn spawn<T, F>(f: F) -> oneshot::Receiver<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let timings = slowlog::current();
+    QUEUED.fetch_add(1, Ordering::Relaxed);
+    pool().spawn(move || {
+        QUEUED.fetch_sub(1, Ordering::Relaxed);
+        let _ = tx.send(slowlog::with(timings, f));
+    });
+    rx
+}
+
+// 在后台线程池中执行并等待结果
+pub(crate) async fn run<T, F>(f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    spawn(f).await.context("后台任务失败")
+}
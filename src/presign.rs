@@ -0,0 +1,140 @@
+use crate::err::AppError;
+use crate::identity;
+use crate::keys;
+use crate::util::cry::{do_bytes_to_hex, do_hex, do_hmac_sha256};
+use crate::HandlerResponse;
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Json;
+use ntex::web::HttpResponse;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+// --- 扩展：用服务端自己的根密钥生成预签名 URL（SigV4 查询参数形式），
+// 供不使用 AWS SDK 的脚本和前端获取临时的下载和上传链接
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/presign", web::post().to(presign));
+}
+
+// 预签名 URL 的默认和最长有效期（秒），与 S3 一致
+const DEFAULT_EXPIRES: u64 = 3600;
+const MAX_EXPIRES: u64 = 7 * 24 * 3600;
+const REGION: &str = "us-east-1";
+
+// SigV4 的 URI 编码：只保留非保留字符
+const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+// 路径中另外保留 '/'
+const PATH_ENCODE_SET: &AsciiSet = &QUERY_ENCODE_SET.remove(b'/');
+
+pub struct PresignParams<'a> {
+    pub method: &'a str,
+    pub host: &'a str,
+    // 未编码的请求路径，如 /api/bucket/key
+    pub path: &'a str,
+    pub access_key: &'a str,
+    pub secret_key: &'a str,
+    pub region: &'a str,
+    pub expires: u64,
+    pub time: DateTime<Utc>,
+}
+
+// 生成预签名请求的路径和查询参数（不含协议和主机），只签名 host 头，请求体不签名
+pub fn presign_path(params: &PresignParams) -> anyhow::Result<String> {
+    let date = params.time.format("%Y%m%d").to_string();
+    let request_date = params.time.format("%Y%m%dT%H%M%SZ").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, params.region);
+    let credential = format!("{}/{}", params.access_key, scope);
+    // 参数已按名称排序
+    let query = [
+        ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential", credential),
+        ("X-Amz-Date", request_date.clone()),
+        ("X-Amz-Expires", params.expires.to_string()),
+        ("X-Amz-SignedHeaders", "host".to_string()),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{}={}", key, utf8_percent_encode(value, QUERY_ENCODE_SET)))
+    .collect::<Vec<_>>()
+    .join("&");
+    let path = utf8_percent_encode(params.path, PATH_ENCODE_SET).to_string();
+    let canonical_request = format!(
+        "{}\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+        params.method, path, query, params.host
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        request_date,
+        scope,
+        do_hex(&canonical_request)
+    );
+    let k_secret = format!("AWS4{}", params.secret_key);
+    let k_date = do_hmac_sha256(k_secret.as_bytes(), &date)?;
+    let k_region = do_hmac_sha256(&k_date, params.region)?;
+    let k_service = do_hmac_sha256(&k_region, "s3")?;
+    let signature_key = do_hmac_sha256(&k_service, "aws4_request")?;
+    let signature = do_bytes_to_hex(&do_hmac_sha256(&signature_key, &string_to_sign)?);
+    Ok(format!("{}?{}&X-Amz-Signature={}", path, query, signature))
+}
+
+#[derive(Deserialize)]
+pub struct PresignRequest {
+    // GET（默认）或 PUT
+    pub method: Option<String>,
+    pub bucket: String,
+    pub key: String,
+    // 有效期（秒），默认 DEFAULT_EXPIRES
+    pub expires: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PresignResponse {
+    pub method: String,
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 生成预签名 URL；主机和协议取自本次请求，使链接与调用方访问服务端的地址一致
+pub async fn presign(req: web::HttpRequest, Json(body): Json<PresignRequest>) -> HandlerResponse {
+    let invalid = |msg: &str| AppError::s3(StatusCode::BAD_REQUEST, "InvalidArgument", msg);
+    let method = body.method.as_deref().unwrap_or("GET").to_uppercase();
+    if method != "GET" && method != "PUT" {
+        return Err(invalid("method must be GET or PUT"));
+    }
+    if body.bucket.is_empty() || body.bucket.contains('/') || body.key.is_empty() {
+        return Err(invalid("bucket and key are required"));
+    }
+    let expires = body.expires.unwrap_or(DEFAULT_EXPIRES);
+    if expires == 0 || expires > MAX_EXPIRES {
+        return Err(invalid("expires must be between 1 and 604800 seconds"));
+    }
+    // 根密钥轮换后使用当前的密钥
+    let (root, root_secret) = keys::root().context("根密钥未初始化")?;
+    let identity = identity::resolve(&root, (&root, &root_secret))
+        .await?
+        .context("根密钥不存在")?;
+    let info = req.connection_info();
+    let time = Utc::now();
+    let path = presign_path(&PresignParams {
+        method: &method,
+        host: info.host(),
+        path: &format!("/api/{}/{}", body.bucket, body.key),
+        access_key: &identity.access_key,
+        secret_key: &identity.secret_key,
+        region: REGION,
+        expires,
+        time,
+    })?;
+    let res = PresignResponse {
+        url: format!("{}://{}{}", info.scheme(), info.host(), path),
+        method,
+        expires_at: time + Duration::seconds(expires as i64),
+    };
+    Ok(HttpResponse::Ok().json(&res))
+}
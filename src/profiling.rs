@@ -0,0 +1,169 @@
+use crate::err::AppError;
+use crate::HandlerResponse;
+use anyhow::{anyhow, Context};
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use pprof::protos::Message;
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+// --- 运行时性能分析接口，需编译 profiling 特性并以 --debug-endpoints 启动
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/debug/pprof/profile", web::get().to(cpu_profile))
+        .route("/admin/debug/pprof/heap", web::get().to(heap_stats));
+}
+
+// 同一时间只允许一个 CPU 采样
+static PROFILING: AtomicBool = AtomicBool::new(false);
+// 单次采样最长时间（秒）
+const MAX_PROFILE_SECONDS: u64 = 300;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static FREED_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// 统计分配次数和字节数的全局分配器包装
+pub struct CountingAlloc<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.0.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let allocated = ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+    let live = allocated.saturating_sub(FREED_BYTES.load(Ordering::Relaxed));
+    PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    FREED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+#[derive(Deserialize)]
+pub struct ProfileQuery {
+    // 采样时长（秒），默认 10
+    pub seconds: Option<u64>,
+    // 采样频率（Hz），默认 99
+    pub frequency: Option<i32>,
+    // 输出格式：pprof（默认，可用 `go tool pprof` 打开）或 flamegraph（SVG）
+    pub format: Option<String>,
+}
+
+// 采样 CPU 调用栈，采样期间请求会一直挂起
+pub async fn cpu_profile(Query(query): Query<ProfileQuery>) -> HandlerResponse {
+    let flamegraph = match query.format.as_deref() {
+        None | Some("pprof") => false,
+        Some("flamegraph") => true,
+        Some(_) => {
+            return Err(AppError::s3(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "`format` must be `pprof` or `flamegraph`",
+            ))
+        }
+    };
+    if PROFILING.swap(true, Ordering::AcqRel) {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "ProfileInProgress",
+            "Another CPU profile is being collected",
+        ));
+    }
+    let res = collect_profile(
+        query.seconds.unwrap_or(10).clamp(1, MAX_PROFILE_SECONDS),
+        query.frequency.unwrap_or(99),
+        flamegraph,
+    )
+    .await;
+    PROFILING.store(false, Ordering::Release);
+    let body = res?;
+    let content_type = if flamegraph {
+        "image/svg+xml"
+    } else {
+        "application/octet-stream"
+    };
+    Ok(HttpResponse::Ok().content_type(content_type).body(body))
+}
+
+async fn collect_profile(
+    seconds: u64,
+    frequency: i32,
+    flamegraph: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(frequency)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .context("启动采样失败")?;
+    tokio::time::sleep(Duration::from_secs(seconds)).await;
+    let report = guard.report().build().context("生成采样报告失败")?;
+    let mut body = Vec::new();
+    if flamegraph {
+        report.flamegraph(&mut body).context("生成火焰图失败")?;
+    } else {
+        let profile = report.pprof().context("生成 pprof 数据失败")?;
+        profile.encode(&mut body).map_err(|err| anyhow!(err))?;
+    }
+    Ok(body)
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeapStats {
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub allocated_bytes: u64,
+    pub freed_bytes: u64,
+    pub live_bytes: u64,
+    pub peak_live_bytes: u64,
+}
+
+// 分配统计快照，需在二进制中使用 CountingAlloc 作为全局分配器
+pub async fn heap_stats() -> HandlerResponse {
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let freed_bytes = FREED_BYTES.load(Ordering::Relaxed);
+    Ok(HttpResponse::Ok().json(&HeapStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes,
+        freed_bytes,
+        live_bytes: allocated_bytes.saturating_sub(freed_bytes),
+        peak_live_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed),
+    }))
+}
@@ -1,11 +1,19 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
+use std::time::Instant;
 
+use openraft::raft::ClientWriteResponse;
 use openraft::Config;
 use tokio::sync::{Mutex, RwLock};
 
+use crate::durability;
+use crate::raft::store::{LogStore, Request};
 use crate::raft::ExampleRaft;
 use crate::raft::NodeId;
+use crate::raft::TypeConfig;
+use crate::slowlog;
+use crate::slowlog::Phase;
+use crate::standby;
 
 // Representation of an application state. This struct can be shared around to share
 // instances of raft, store and more.
@@ -15,7 +23,32 @@ pub struct App {
     pub api_addr: String,
     pub rpc_addr: String,
     pub raft: ExampleRaft,
+    // 与 raft 共用的日志存储，供备用节点拉取已提交的条目
+    pub log_store: LogStore,
     pub key_values: Arc<RwLock<BTreeMap<String, String>>>,
     pub config: Arc<Config>,
     pub nodes: Arc<Mutex<BTreeSet<NodeId>>>,
 }
+
+impl App {
+    // 提交写请求；开启 fsync 时等待状态机写入的文件落盘后再返回
+    pub async fn client_write(
+        &self,
+        request: Request,
+    ) -> anyhow::Result<ClientWriteResponse<TypeConfig>> {
+        if standby::is_passive() {
+            anyhow::bail!("备用节点只读");
+        }
+        let start = Instant::now();
+        let res = self
+            .raft
+            .client_write(request)
+            .await
+            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+        durability::flush().await?;
+        // raft 耗时包含应用日志，应用阶段中的各项耗时另外计入
+        slowlog::record(Phase::Raft, start.elapsed());
+        slowlog::merge_applied(res.log_id.index);
+        Ok(res)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod raft;
 mod raft_network_impl;
 
+pub(crate) use raft_network_impl::make_codec;
 pub use raft_network_impl::Network;
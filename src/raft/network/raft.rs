@@ -19,13 +19,12 @@ impl volo_gen::rpc::raft::RaftService for Raft {
         &self,
         req: volo_gen::rpc::raft::RaftRequest,
     ) -> Result<volo_gen::rpc::raft::RaftReply, volo_thrift::ServerError> {
-        let data = req.data.as_bytes();
+        let data = &req.data;
         let vote = postcard::from_bytes(data).unwrap();
         let resp = self.app.raft.vote(vote).await.unwrap();
         let result = postcard::to_stdvec(&resp).unwrap();
-        let result = String::from_utf8(result).unwrap();
         Ok(volo_gen::rpc::raft::RaftReply {
-            data: result.parse().unwrap(),
+            data: result.into(),
             error: Default::default(),
         })
     }
@@ -34,13 +33,12 @@ impl volo_gen::rpc::raft::RaftService for Raft {
         req: volo_gen::rpc::raft::RaftRequest,
     ) -> Result<volo_gen::rpc::raft::RaftReply, volo_thrift::ServerError> {
         debug!("handle append");
-        let data = req.data.as_bytes();
+        let data = &req.data;
         let req = postcard::from_bytes(data).unwrap();
         let resp = self.app.raft.append_entries(req).await.unwrap();
         let result = postcard::to_stdvec(&resp).unwrap();
-        let result = String::from_utf8(result).unwrap();
         Ok(volo_gen::rpc::raft::RaftReply {
-            data: result.parse().unwrap(),
+            data: result.into(),
             error: Default::default(),
         })
     }
@@ -48,13 +46,12 @@ impl volo_gen::rpc::raft::RaftService for Raft {
         &self,
         req: volo_gen::rpc::raft::RaftRequest,
     ) -> Result<volo_gen::rpc::raft::RaftReply, volo_thrift::ServerError> {
-        let data = req.data.as_bytes();
+        let data = &req.data;
         let req = postcard::from_bytes(data).unwrap();
         let resp = self.app.raft.install_snapshot(req).await.unwrap();
         let result = postcard::to_stdvec(&resp).unwrap();
-        let result = String::from_utf8(result).unwrap();
         Ok(volo_gen::rpc::raft::RaftReply {
-            data: result.parse().unwrap(),
+            data: result.into(),
             error: Default::default(),
         })
     }
@@ -19,8 +19,20 @@ use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
 use serde::de::DeserializeOwned;
 use volo_gen::rpc::raft::RaftRequest;
+use volo_thrift::codec::default::framed::MakeFramedCodec;
+use volo_thrift::codec::default::thrift::MakeThriftCodec;
+use volo_thrift::codec::default::ttheader::MakeTTHeaderCodec;
+use volo_thrift::codec::DefaultMakeCodec;
 use volo_thrift::ClientError;
 
+// 日志条目带有对象内容，放宽 thrift 默认 16MB 的帧大小限制，客户端和服务端需一致
+pub(crate) fn make_codec() -> DefaultMakeCodec<MakeTTHeaderCodec<MakeFramedCodec<MakeThriftCodec>>>
+{
+    DefaultMakeCodec::new(MakeTTHeaderCodec::new(
+        MakeFramedCodec::new(MakeThriftCodec::default()).with_max_frame_size(i32::MAX),
+    ))
+}
+
 pub struct Network {}
 
 // NOTE: This could be implemented also on `Arc<ExampleNetwork>`, but since it's empty, implemented
@@ -33,6 +45,7 @@ impl RaftNetworkFactory<TypeConfig> for Network {
 
         let client = volo_gen::rpc::raft::RaftServiceClientBuilder::new("raft-service")
             .address(addr)
+            .make_codec(make_codec())
             .build();
 
         NetworkConnection { client, target }
@@ -92,15 +105,12 @@ impl RaftNetwork<TypeConfig> for NetworkConnection {
         debug!("got connection");
 
         let req = postcard::to_stdvec(&req).unwrap();
-        let req = String::from_utf8(req).unwrap();
         let x = c
-            .append(RaftRequest {
-                data: req.parse().unwrap(),
-            })
+            .append(RaftRequest { data: req.into() })
             .await
             .map_err(|e| to_error(e, self.target))?;
 
-        let resp = postcard::from_bytes(x.data.as_bytes()).unwrap();
+        let resp = postcard::from_bytes(&x.data).unwrap();
         Ok(resp)
     }
 
@@ -114,16 +124,13 @@ impl RaftNetwork<TypeConfig> for NetworkConnection {
     > {
         debug!("install_snapshot");
         let req = postcard::to_stdvec(&req).unwrap();
-        let req = String::from_utf8(req).unwrap();
         let x = self
             .c()
             .await?
-            .snapshot(RaftRequest {
-                data: req.parse().unwrap(),
-            })
+            .snapshot(RaftRequest { data: req.into() })
             .await
             .map_err(|e| to_error(e, self.target))?;
-        let resp = postcard::from_bytes(x.data.as_bytes()).unwrap();
+        let resp = postcard::from_bytes(&x.data).unwrap();
         Ok(resp)
     }
 
@@ -134,16 +141,13 @@ impl RaftNetwork<TypeConfig> for NetworkConnection {
     ) -> Result<VoteResponse<NodeId>, RPCError<NodeId, Node, RaftError<NodeId>>> {
         debug!("vote");
         let req = postcard::to_stdvec(&req).unwrap();
-        let req = String::from_utf8(req).unwrap();
         let x = self
             .c()
             .await?
-            .vote(RaftRequest {
-                data: req.parse().unwrap(),
-            })
+            .vote(RaftRequest { data: req.into() })
             .await
             .map_err(|e| to_error(e, self.target))?;
-        let resp = postcard::from_bytes(x.data.as_bytes()).unwrap();
+        let resp = postcard::from_bytes(&x.data).unwrap();
         Ok(resp)
     }
 }
@@ -6,16 +6,42 @@ use std::ops::RangeBounds;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::access;
+use crate::access::AccessRecord;
 use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::cdc;
+use crate::checksum;
+use crate::clone;
+use crate::cluster;
+use crate::codec::{self, Codec};
+use crate::conditional;
+use crate::config;
+use crate::copy;
+use crate::durability;
+use crate::etag;
 use crate::fs;
-use crate::fs::{save_metadata, split_file_and_save, Metadata};
+use crate::fs::{
+    save_metadata, split_file_and_save, Backend, Encryption, Metadata, ResponseHeader, ScanStatus,
+    Tag,
+};
+use crate::headers;
+use crate::keys;
+use crate::lifecycle;
 use crate::model::CompleteMultipartUpload;
+use crate::multipart;
+use crate::policy;
+use crate::rollback::WrittenFiles;
+use crate::slowlog;
+use crate::snapshot;
+use crate::sse;
+use crate::util;
+use crate::versioning;
+use crate::website;
 use byteorder::BigEndian;
 use byteorder::ReadBytesExt;
 use byteorder::WriteBytesExt;
-use chrono::Utc;
-use log::{debug, info};
-use mime_guess::MimeGuess;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
 use openraft::storage::LogFlushed;
 use openraft::storage::LogState;
 use openraft::storage::RaftLogStorage;
@@ -44,7 +70,6 @@ use crate::raft::Node;
 use crate::raft::NodeId;
 use crate::raft::SnapshotData;
 use crate::raft::TypeConfig;
-use rayon::prelude::*;
 use sled::Db;
 
 /**
@@ -53,6 +78,8 @@ use sled::Db;
  * The `AddNode` will append a new node to the current existing shared list of nodes.
  * You will want to add any request that can write data in all nodes here.
  */
+// 日志条目用 postcard 编码，按字段顺序读取，serde(default) 不起作用：已有变体不能再增加字段，
+// 需要新字段时在末尾增加新变体。旧变体保留用于回放旧版本写入的日志，应用前由 upgrade 转换
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Request {
     CreateBucket {
@@ -90,6 +117,327 @@ pub enum Request {
         dest_bucket: String,
         dest_object: String,
     },
+    StageFile {
+        staging_id: String,
+        bucket_name: String,
+        object_key: String,
+        body: Vec<u8>,
+        attrs: ObjectAttrsV1,
+    },
+    CommitStaged {
+        staging_id: String,
+        bucket_name: String,
+    },
+    AbortStaged {
+        staging_id: String,
+        bucket_name: String,
+    },
+    RenameObject {
+        bucket_name: String,
+        source: String,
+        target: String,
+    },
+    SetBucketWebsite {
+        bucket_name: String,
+        config: Option<String>,
+    },
+    SetBucketHeaders {
+        bucket_name: String,
+        config: Option<String>,
+    },
+    // 整个本地密钥库（已加密）
+    SetAccessKeys {
+        keys: Vec<u8>,
+    },
+    // 落盘上传的一个分片，全部保存后由 CommitChunkedFile 写入元数据
+    SaveChunk {
+        hash: String,
+        body: Vec<u8>,
+    },
+    CommitChunkedFile {
+        file_path: String,
+        size: u64,
+        chunks: Vec<String>,
+        chunk_sizes: Vec<u64>,
+        attrs: ObjectAttrsV1,
+    },
+    // 放弃分片上传，删除临时元数据和已上传分片的记录
+    AbortChunk {
+        bucket_name: String,
+        object_key: String,
+        upload_id: String,
+    },
+    // 一批对象读取记录，合并进桶的访问记录
+    RecordAccess {
+        bucket_name: String,
+        updates: Vec<(String, AccessRecord)>,
+    },
+    // 批量删除对象，file_paths 为元数据文件路径
+    DeleteFiles {
+        file_paths: Vec<String>,
+    },
+    // 桶的版本控制状态（Enabled 或 Suspended）
+    SetBucketVersioning {
+        bucket_name: String,
+        status: String,
+    },
+    // 桶策略（JSON），为空时删除
+    SetBucketPolicy {
+        bucket_name: String,
+        config: Option<String>,
+    },
+    // 替换对象（或对象的某个版本）的标签，file_path 为元数据文件路径
+    SetObjectTags {
+        file_path: String,
+        tags: Vec<Tag>,
+    },
+    // 克隆桶：复制源桶全部当前对象的元数据，分片共享
+    CloneBucket {
+        source: String,
+        target: String,
+    },
+    // 桶生命周期配置（XML），为空时删除
+    SetBucketLifecycle {
+        bucket_name: String,
+        config: Option<String>,
+    },
+    // 记录桶内全部当前对象的元数据，作为只读快照
+    CreateSnapshot {
+        bucket_name: String,
+        name: String,
+        created: DateTime<Utc>,
+    },
+    // 把桶恢复到快照时的对象集合
+    RestoreSnapshot {
+        bucket_name: String,
+        name: String,
+    },
+    DeleteSnapshot {
+        bucket_name: String,
+        name: String,
+    },
+    // etag 为接收请求时计算的分片 MD5，codec 为请求指定的分片编码，为空时使用默认编码
+    UploadChunkV2 {
+        part_number: String,
+        upload_id: String,
+        hash: String,
+        body: Vec<u8>,
+        etag: Option<String>,
+        codec: Option<Codec>,
+    },
+    SaveChunkV2 {
+        hash: String,
+        body: Vec<u8>,
+        codec: Option<Codec>,
+    },
+    StageFileV2 {
+        staging_id: String,
+        bucket_name: String,
+        object_key: String,
+        body: Vec<u8>,
+        attrs: ObjectAttrs,
+    },
+    // 开启过版本控制的桶中 version_id 为要永久删除的版本，为空时写入删除标记；
+    // if_match 和 if_none_match 为 If-Match 和 If-None-Match: *，与当前对象比较
+    DeleteFileV2 {
+        file_path: String,
+        version_id: Option<String>,
+        if_match: Option<String>,
+        if_none_match: bool,
+    },
+    CommitChunkedFileV2 {
+        file_path: String,
+        size: u64,
+        chunks: Vec<String>,
+        chunk_sizes: Vec<u64>,
+        attrs: ObjectAttrs,
+    },
+    // version_ids 与 file_paths 一一对应
+    DeleteFilesV2 {
+        file_paths: Vec<String>,
+        version_ids: Vec<Option<String>>,
+    },
+    UploadFileV2 {
+        file_path: String,
+        body: Vec<u8>,
+        attrs: ObjectAttrs,
+    },
+    // created 由接收请求的节点确定，保证各节点记录的创建时间一致
+    CreateBucketV2 {
+        bucket_name: String,
+        created: DateTime<Utc>,
+    },
+    // attrs 为创建分片上传时指定的对象属性，完成上传时写入对象的元数据
+    InitChunkV2 {
+        bucket_name: String,
+        object_key: String,
+        upload_id: String,
+        attrs: ObjectAttrs,
+    },
+    // attrs 为 x-amz-metadata-directive 为 REPLACE 时使用的元数据
+    CopyFileV2 {
+        copy_source: String,
+        dest_bucket: String,
+        dest_object: String,
+        attrs: Option<ObjectAttrs>,
+    },
+}
+
+impl Request {
+    // 把旧版本日志中的请求转换为对应的新变体，缺少的字段取默认值
+    fn upgrade(self) -> Request {
+        match self {
+            Request::UploadChunk {
+                part_number,
+                upload_id,
+                hash,
+                body,
+            } => Request::UploadChunkV2 {
+                part_number,
+                upload_id,
+                hash,
+                body,
+                etag: None,
+                codec: None,
+            },
+            Request::SaveChunk { hash, body } => Request::SaveChunkV2 {
+                hash,
+                body,
+                codec: None,
+            },
+            Request::StageFile {
+                staging_id,
+                bucket_name,
+                object_key,
+                body,
+                attrs,
+            } => Request::StageFileV2 {
+                staging_id,
+                bucket_name,
+                object_key,
+                body,
+                attrs: attrs.into(),
+            },
+            Request::DeleteFile { file_path } => Request::DeleteFileV2 {
+                file_path,
+                version_id: None,
+                if_match: None,
+                if_none_match: false,
+            },
+            Request::CommitChunkedFile {
+                file_path,
+                size,
+                chunks,
+                chunk_sizes,
+                attrs,
+            } => Request::CommitChunkedFileV2 {
+                file_path,
+                size,
+                chunks,
+                chunk_sizes,
+                attrs: attrs.into(),
+            },
+            Request::DeleteFiles { file_paths } => Request::DeleteFilesV2 {
+                version_ids: vec![None; file_paths.len()],
+                file_paths,
+            },
+            Request::UploadFile { file_path, body } => Request::UploadFileV2 {
+                file_path,
+                body,
+                attrs: ObjectAttrs::default(),
+            },
+            // 旧版本的日志没有记录创建时间，按应用时间记录
+            Request::CreateBucket { bucket_name } => Request::CreateBucketV2 {
+                bucket_name,
+                created: Utc::now(),
+            },
+            Request::InitChunk {
+                bucket_name,
+                object_key,
+                upload_id,
+            } => Request::InitChunkV2 {
+                bucket_name,
+                object_key,
+                upload_id,
+                attrs: ObjectAttrs::default(),
+            },
+            Request::CopyFile {
+                copy_source,
+                dest_bucket,
+                dest_object,
+            } => Request::CopyFileV2 {
+                copy_source,
+                dest_bucket,
+                dest_object,
+                attrs: None,
+            },
+            req => req,
+        }
+    }
+}
+
+// 随上传请求一起写入元数据的对象属性
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ObjectAttrs {
+    // 请求指定的 Content-Type，为空时由服务端推断
+    pub content_type: Option<String>,
+    pub website_redirect: Option<String>,
+    // 读取对象时附加的响应头，覆盖桶的默认响应头
+    pub headers: Vec<ResponseHeader>,
+    // 上传时的内容扫描结果
+    pub scan: Option<ScanStatus>,
+    // 接收请求体时计算的 ETag，为空时由写入的数据计算
+    #[serde(default)]
+    pub etag: Option<String>,
+    // 接收请求体时计算的校验和（base64），为空时由写入的数据计算
+    #[serde(default)]
+    pub checksum_sha256: Option<String>,
+    // 服务端加密参数，数据密钥用主密钥加密
+    #[serde(default)]
+    pub encryption: Option<Encryption>,
+    // If-None-Match: *，对象已存在时不写入；暂存上传只在接收请求时检查
+    #[serde(default)]
+    pub create_only: bool,
+    // 对象标签
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    // 请求指定的分片编码，为空时使用默认编码
+    #[serde(default)]
+    pub codec: Option<Codec>,
+    // If-Match，当前对象的 ETag 不匹配时不写入；暂存上传只在接收请求时检查
+    #[serde(default)]
+    pub if_match: Option<String>,
+}
+
+// 增加 codec 和 if_match 前的对象属性，用于解码旧版本日志中的 StageFile、CommitChunkedFile
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectAttrsV1 {
+    pub content_type: Option<String>,
+    pub website_redirect: Option<String>,
+    pub headers: Vec<ResponseHeader>,
+    pub scan: Option<ScanStatus>,
+    pub etag: Option<String>,
+    pub checksum_sha256: Option<String>,
+    pub encryption: Option<Encryption>,
+    pub create_only: bool,
+    pub tags: Vec<Tag>,
+}
+
+impl From<ObjectAttrsV1> for ObjectAttrs {
+    fn from(attrs: ObjectAttrsV1) -> Self {
+        ObjectAttrs {
+            content_type: attrs.content_type,
+            website_redirect: attrs.website_redirect,
+            headers: attrs.headers,
+            scan: attrs.scan,
+            etag: attrs.etag,
+            checksum_sha256: attrs.checksum_sha256,
+            encryption: attrs.encryption,
+            create_only: attrs.create_only,
+            tags: attrs.tags,
+            ..Default::default()
+        }
+    }
 }
 
 /**
@@ -201,6 +549,7 @@ impl StateMachineStore {
 
         self.data.last_applied_log_id = snapshot.meta.last_log_id;
         self.data.last_membership = snapshot.meta.last_membership.clone();
+        cluster::update_members(self.data.last_membership.nodes());
         let mut x = self.data.kvs.write().await;
         *x = kvs;
 
@@ -270,21 +619,57 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
         for ent in entries {
             self.data.last_applied_log_id = Some(ent.log_id);
 
-            let resp_value = None;
-
-            match ent.payload {
+            let mut resp_value = None;
+            let payload = match ent.payload {
+                EntryPayload::Normal(req) => EntryPayload::Normal(req.upgrade()),
+                payload => payload,
+            };
+            let change = match &payload {
+                EntryPayload::Normal(req) => cdc::capture(ent.log_id.index, req),
+                _ => None,
+            };
+
+            match payload {
                 EntryPayload::Blank => {}
                 EntryPayload::Normal(req) => match req {
-                    Request::CreateBucket { bucket_name } => {
-                        std::fs::create_dir_all(bucket_name)
-                            .context("创建桶失败")
-                            .unwrap();
+                    Request::CreateBucketV2 {
+                        bucket_name,
+                        created,
+                    } => {
+                        // 桶已存在时不能重新生成数据密钥，否则已有对象的元数据无法解密
+                        if !Path::new(&bucket_name).is_dir() {
+                            let res = std::fs::create_dir_all(&bucket_name)
+                                .context("创建桶失败")
+                                .and_then(|_| fs::create_bucket_key(&bucket_name));
+                            match res {
+                                Ok(_) => {
+                                    let _ = fs::save_bucket_created(&bucket_name, created);
+                                }
+                                // 没有数据密钥的桶按旧桶用主密钥加密元数据，删除目录以便重试
+                                Err(err) => {
+                                    let _ = std::fs::remove_dir_all(&bucket_name);
+                                    resp_value = Some(write_failed(err));
+                                }
+                            }
+                        }
                     }
                     Request::DeleteBucket { bucket_name } => {
-                        if std::fs::metadata(&bucket_name).is_ok() {
+                        let bucket = Path::new(&bucket_name)
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        if fs::bucket_has_objects(Path::new(&bucket_name))
+                            || versioning::has_versions(&bucket)
+                        {
+                            resp_value = Some("BucketNotEmpty".to_string());
+                        } else if std::fs::metadata(&bucket_name).is_ok() {
                             std::fs::remove_dir_all(&bucket_name)
                                 .context("删除桶失败")
                                 .unwrap();
+                            let _ =
+                                std::fs::remove_dir_all(versioning::bucket_versions_dir(&bucket));
+                            let _ =
+                                std::fs::remove_dir_all(snapshot::bucket_snapshots_dir(&bucket));
                         }
                     }
                     // Request::Set { key, value } => {
@@ -293,23 +678,50 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
                     //     let mut st = self.data.kvs.write().await;
                     //     st.insert(key, value);
                     // }
-                    Request::InitChunk {
+                    Request::InitChunkV2 {
                         bucket_name,
                         object_key,
                         upload_id,
+                        attrs,
                     } => {
-                        let _ = init_chunk(bucket_name, object_key, upload_id).await;
+                        let _ = init_chunk(bucket_name, object_key, upload_id, attrs).await;
                     }
-                    Request::UploadChunk {
+                    Request::UploadChunkV2 {
                         part_number,
                         upload_id,
                         hash,
                         body,
+                        etag,
+                        codec,
                     } => {
-                        let _ = upload_chunk(&part_number, &upload_id, &hash, body).await;
+                        let upload = upload_chunk(
+                            &part_number,
+                            &upload_id,
+                            (&hash, etag.as_deref()),
+                            body,
+                            codec::resolve(codec),
+                        );
+                        if let Err(err) = slowlog::applying(ent.log_id.index, upload).await {
+                            resp_value = Some(write_failed(err));
+                        }
                     }
-                    Request::UploadFile { file_path, body } => {
-                        let _ = upload_file(file_path, body).await;
+                    Request::UploadFileV2 {
+                        file_path,
+                        body,
+                        attrs,
+                    } => {
+                        if !conditional::holds(
+                            &file_path,
+                            attrs.if_match.as_deref(),
+                            attrs.create_only,
+                        ) {
+                            resp_value = Some(conditional::PRECONDITION_FAILED.to_string());
+                        } else {
+                            let upload = upload_file(file_path, body, attrs, ent.log_id.index);
+                            if let Err(err) = slowlog::applying(ent.log_id.index, upload).await {
+                                resp_value = Some(write_failed(err));
+                            }
+                        }
                     }
                     Request::CombineChunk {
                         bucket_name,
@@ -318,23 +730,258 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
                         cmu,
                     } => {
                         let cmu: CompleteMultipartUpload = quick_xml::de::from_str(&cmu).unwrap();
-                        let _ = combine_chunk(&bucket_name, &object_key, &upload_id, cmu).await;
+                        let _ = combine_chunk(
+                            (&bucket_name, &object_key, &upload_id),
+                            cmu,
+                            ent.log_id.index,
+                        )
+                        .await;
                     }
-                    Request::DeleteFile { file_path } => {
-                        let _ = do_delete_file(file_path).await;
+                    Request::DeleteFileV2 {
+                        file_path,
+                        version_id,
+                        if_match,
+                        if_none_match,
+                    } => {
+                        if !conditional::holds(&file_path, if_match.as_deref(), if_none_match) {
+                            resp_value = Some(conditional::PRECONDITION_FAILED.to_string());
+                        } else {
+                            let marker_id = versioning::version_id(ent.log_id.index, 0);
+                            let _ =
+                                delete_object(file_path, version_id.as_deref(), marker_id).await;
+                        }
                     }
-                    Request::CopyFile {
+                    Request::CopyFileV2 {
                         copy_source,
                         dest_bucket,
                         dest_object,
+                        attrs,
+                    } => {
+                        let dest = (dest_bucket.as_str(), dest_object.as_str());
+                        let copy = copy_object(&copy_source, dest, attrs, ent.log_id.index);
+                        match slowlog::applying(ent.log_id.index, copy).await {
+                            Ok(time) => resp_value = Some(time.to_rfc3339()),
+                            Err(err) => info!("拷贝对象失败: {}", err),
+                        }
+                    }
+                    Request::StageFileV2 {
+                        staging_id,
+                        bucket_name,
+                        object_key,
+                        body,
+                        attrs,
+                    } => {
+                        let stage = stage_file(&staging_id, &bucket_name, &object_key, body, attrs);
+                        let _ = slowlog::applying(ent.log_id.index, stage).await;
+                    }
+                    Request::CommitStaged {
+                        staging_id,
+                        bucket_name,
+                    } => match commit_staged(&staging_id, &bucket_name) {
+                        Ok(committed) => resp_value = Some(committed.to_string()),
+                        Err(err) => info!("提交暂存对象失败: {}", err),
+                    },
+                    Request::AbortStaged {
+                        staging_id,
+                        bucket_name,
+                    } => {
+                        let _ = abort_staged(&staging_id, &bucket_name);
+                    }
+                    Request::RenameObject {
+                        bucket_name,
+                        source,
+                        target,
+                    } => match rename_objects(&bucket_name, &source, &target) {
+                        Ok(Some(renamed)) => resp_value = Some(renamed.to_string()),
+                        Ok(None) => resp_value = Some(RENAME_TARGET_EXISTS.to_string()),
+                        Err(err) => info!("重命名失败: {}", err),
+                    },
+                    Request::SetBucketWebsite {
+                        bucket_name,
+                        config,
+                    } => {
+                        let path = website::config_path(&bucket_name);
+                        let res = match config {
+                            Some(config) => std::fs::write(&path, config),
+                            None => std::fs::remove_file(&path),
+                        };
+                        match res {
+                            Ok(()) => durability::enqueue(path),
+                            Err(err) => info!("更新静态网站配置失败: {}", err),
+                        }
+                    }
+                    Request::SetBucketHeaders {
+                        bucket_name,
+                        config,
+                    } => {
+                        let path = headers::config_path(&bucket_name);
+                        let res = match config {
+                            Some(config) => std::fs::write(&path, config),
+                            None => std::fs::remove_file(&path),
+                        };
+                        match res {
+                            Ok(()) => durability::enqueue(path),
+                            Err(err) => info!("更新默认响应头配置失败: {}", err),
+                        }
+                    }
+                    Request::SetAccessKeys { keys: store } => {
+                        if let Err(err) = keys::apply(&store) {
+                            info!("更新密钥库失败: {}", err);
+                        }
+                    }
+                    Request::SaveChunkV2 { hash, body, codec } => {
+                        let save = fs::save_chunk(hash, body, codec::resolve(codec));
+                        if let Err(err) = slowlog::applying(ent.log_id.index, save).await {
+                            resp_value = Some(write_failed(err));
+                        }
+                    }
+                    Request::CommitChunkedFileV2 {
+                        file_path,
+                        size,
+                        chunks,
+                        chunk_sizes,
+                        attrs,
+                    } => {
+                        if !conditional::holds(
+                            &file_path,
+                            attrs.if_match.as_deref(),
+                            attrs.create_only,
+                        ) {
+                            resp_value = Some(conditional::PRECONDITION_FAILED.to_string());
+                        } else {
+                            let _ = commit_chunked_file(
+                                file_path,
+                                size,
+                                (chunks, chunk_sizes),
+                                attrs,
+                                ent.log_id.index,
+                            );
+                        }
+                    }
+                    Request::AbortChunk {
+                        bucket_name,
+                        object_key,
+                        upload_id,
+                    } => {
+                        if let Err(err) = abort_chunk(&bucket_name, &object_key, &upload_id) {
+                            info!("放弃分片上传失败: {}", err);
+                        }
+                    }
+                    Request::RecordAccess {
+                        bucket_name,
+                        updates,
+                    } => {
+                        if let Err(err) = access::apply(&bucket_name, updates) {
+                            info!("更新访问记录失败: {}", err);
+                        }
+                    }
+                    Request::DeleteFilesV2 {
+                        file_paths,
+                        version_ids,
+                    } => {
+                        // 返回删除失败的元数据文件及原因
+                        let mut failed = vec![];
+                        for (i, file_path) in file_paths.into_iter().enumerate() {
+                            let version_id = version_ids.get(i).cloned().flatten();
+                            let marker_id = versioning::version_id(ent.log_id.index, i);
+                            let delete =
+                                delete_object(file_path.clone(), version_id.as_deref(), marker_id);
+                            if let Err(err) = delete.await {
+                                info!("删除文件失败 {}: {}", file_path, err);
+                                failed.push((file_path, err.to_string()));
+                            }
+                        }
+                        resp_value = serde_json::to_string(&failed).ok();
+                    }
+                    Request::SetBucketVersioning {
+                        bucket_name,
+                        status,
+                    } => {
+                        let path = versioning::config_path(&bucket_name);
+                        match std::fs::write(&path, status) {
+                            Ok(()) => durability::enqueue(path),
+                            Err(err) => info!("更新版本控制状态失败: {}", err),
+                        }
+                    }
+                    Request::SetBucketPolicy {
+                        bucket_name,
+                        config,
+                    } => {
+                        let path = policy::config_path(&bucket_name);
+                        let res = match config {
+                            Some(config) => std::fs::write(&path, config),
+                            None => std::fs::remove_file(&path),
+                        };
+                        match res {
+                            Ok(()) => durability::enqueue(path),
+                            Err(err) => info!("更新桶策略失败: {}", err),
+                        }
+                    }
+                    Request::SetObjectTags { file_path, tags } => {
+                        if let Err(err) = set_object_tags(&file_path, tags) {
+                            info!("更新对象标签失败: {}", err);
+                        }
+                    }
+                    Request::CloneBucket { source, target } => {
+                        match clone::apply(&source, &target) {
+                            Ok(summary) => resp_value = serde_json::to_string(&summary).ok(),
+                            Err(err) => info!("克隆桶失败: {:#}", err),
+                        }
+                    }
+                    Request::SetBucketLifecycle {
+                        bucket_name,
+                        config,
                     } => {
-                        let _ = copy_object(&copy_source, &dest_bucket, &dest_object).await;
+                        let path = lifecycle::config_path(&bucket_name);
+                        let res = match config {
+                            Some(config) => std::fs::write(&path, config),
+                            None => std::fs::remove_file(&path),
+                        };
+                        match res {
+                            Ok(()) => durability::enqueue(path),
+                            Err(err) => info!("更新生命周期配置失败: {}", err),
+                        }
+                    }
+                    Request::CreateSnapshot {
+                        bucket_name,
+                        name,
+                        created,
+                    } => match snapshot::create(&bucket_name, &name, created) {
+                        Ok(info) => resp_value = serde_json::to_string(&info).ok(),
+                        Err(err) => info!("创建快照失败: {:#}", err),
+                    },
+                    Request::RestoreSnapshot { bucket_name, name } => {
+                        match snapshot::restore(&bucket_name, &name) {
+                            Ok(summary) => resp_value = serde_json::to_string(&summary).ok(),
+                            Err(err) => info!("恢复快照失败: {:#}", err),
+                        }
+                    }
+                    Request::DeleteSnapshot { bucket_name, name } => {
+                        if let Err(err) = snapshot::delete(&bucket_name, &name) {
+                            info!("删除快照失败: {:#}", err);
+                        }
+                    }
+                    req @ (Request::UploadChunk { .. }
+                    | Request::SaveChunk { .. }
+                    | Request::StageFile { .. }
+                    | Request::DeleteFile { .. }
+                    | Request::CommitChunkedFile { .. }
+                    | Request::DeleteFiles { .. }
+                    | Request::UploadFile { .. }
+                    | Request::CreateBucket { .. }
+                    | Request::InitChunk { .. }
+                    | Request::CopyFile { .. }) => {
+                        unreachable!("旧版本的请求应已转换: {:?}", req)
                     }
                 },
                 EntryPayload::Membership(mem) => {
+                    cluster::update_members(mem.nodes());
                     self.data.last_membership = StoredMembership::new(Some(ent.log_id), mem);
                 }
             }
+            if let Some(change) = change {
+                change.publish();
+            }
 
             replies.push(Response { value: resp_value });
         }
@@ -381,97 +1028,497 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
 }
 
 // 上传文件
-async fn upload_file(metainfo_file_path: String, body: Vec<u8>) -> anyhow::Result<()> {
+// 写入失败时返回给请求方的错误说明，请求方据此返回 500 而不是确认写入
+fn write_failed(err: anyhow::Error) -> String {
+    info!("写入对象数据失败: {:#}", err);
+    format!("{:#}", err)
+}
+
+pub(crate) async fn upload_file(
+    metainfo_file_path: String,
+    body: Vec<u8>,
+    attrs: ObjectAttrs,
+    log_index: u64,
+) -> anyhow::Result<()> {
+    let mut written = WrittenFiles::new();
+    let res = save_uploaded_file(metainfo_file_path, body, attrs, log_index, &mut written).await;
+    if res.is_err() {
+        written.rollback();
+    }
+    res
+}
+
+async fn save_uploaded_file(
+    metainfo_file_path: String,
+    body: Vec<u8>,
+    attrs: ObjectAttrs,
+    log_index: u64,
+    written: &mut WrittenFiles,
+) -> anyhow::Result<()> {
     let file_name = PathBuf::from(&metainfo_file_path)
         .file_name()
         .context("解析文件名失败")?
         .to_string_lossy()
         .to_string();
-    let tmp_filename = file_name.clone();
-    let file_type = MimeGuess::from_path(Path::new(&tmp_filename))
-        .first_or_text_plain()
+    let file_name = file_name
+        .strip_suffix(".meta")
+        .unwrap_or(&file_name)
         .to_string();
-
-    let (file_size, hashcodes) = split_file_and_save(body, 8 << 20).await?;
-    let metainfo = Metadata {
+    let file_type = attrs
+        .content_type
+        .unwrap_or_else(|| util::file::detect_content_type(&file_name, &body));
+    let etag = attrs.etag.unwrap_or_else(|| etag::md5_hex(&body));
+    let checksum_sha256 = attrs
+        .checksum_sha256
+        .unwrap_or_else(|| checksum::sha256_of(&body));
+
+    let object_path = fs::object_path_from_meta(&metainfo_file_path).context("解析对象路径失败")?;
+    let backend = config::get().backend_for(&object_path);
+    let (file_size, hashcodes, chunk_sizes, raw_tmp) = match backend {
+        Backend::Dedup => {
+            let (size, chunks, chunk_sizes) = split_file_and_save(
+                body,
+                config::get().chunker(),
+                codec::resolve(attrs.codec),
+                written,
+            )
+            .await?;
+            (size, chunks, chunk_sizes, None)
+        }
+        Backend::Passthrough => {
+            let tmp = fs::save_raw_file(fs::raw_path(&object_path), &body, written).await?;
+            (body.len(), vec![], vec![], Some(tmp))
+        }
+    };
+    let mut metainfo = Metadata {
         name: file_name,
         size: file_size as u64,
         file_type: file_type.to_string(),
         time: Utc::now(),
         chunks: hashcodes,
+        chunk_sizes,
+        backend,
+        website_redirect: attrs.website_redirect,
+        headers: attrs.headers,
+        scan: attrs.scan,
+        etag,
+        checksum_sha256: Some(checksum_sha256),
+        version_id: None,
+        delete_marker: false,
+        encryption: sse::unseal(attrs.encryption)?,
+        tags: attrs.tags,
     };
-    fs::save_metadata(&metainfo_file_path, &metainfo)?;
+    versioning::save_current(&metainfo_file_path, &mut metainfo, log_index)?;
+    if let Some(tmp) = raw_tmp {
+        fs::publish_file(&tmp, &fs::raw_path(&object_path))?;
+    }
     Ok(())
 }
 
-// 桶间拷贝对象数据
-async fn copy_object(
-    copy_source: &str,
-    dest_bucket: &str,
-    dest_object: &str,
+// 写入落盘上传的对象元数据，分片已由 SaveChunk 保存
+fn commit_chunked_file(
+    metainfo_file_path: String,
+    size: u64,
+    (chunks, chunk_sizes): (Vec<String>, Vec<u64>),
+    attrs: ObjectAttrs,
+    log_index: u64,
 ) -> anyhow::Result<()> {
-    let mut copy_source = copy_source.to_string();
-    if copy_source.contains('?') {
-        copy_source = copy_source.split('?').next().unwrap().to_string();
+    let file_name = PathBuf::from(&metainfo_file_path)
+        .file_name()
+        .context("解析文件名失败")?
+        .to_string_lossy()
+        .to_string();
+    let mut metainfo = Metadata {
+        name: file_name
+            .strip_suffix(".meta")
+            .unwrap_or(&file_name)
+            .to_string(),
+        size,
+        file_type: attrs.content_type.unwrap_or_default(),
+        time: Utc::now(),
+        chunks,
+        chunk_sizes,
+        backend: Backend::Dedup,
+        website_redirect: attrs.website_redirect,
+        headers: attrs.headers,
+        scan: attrs.scan,
+        etag: attrs.etag.unwrap_or_default(),
+        checksum_sha256: attrs.checksum_sha256,
+        version_id: None,
+        delete_marker: false,
+        encryption: sse::unseal(attrs.encryption)?,
+        tags: attrs.tags,
+    };
+    versioning::save_current(&metainfo_file_path, &mut metainfo, log_index)?;
+    Ok(())
+}
+
+// 暂存上传的对象，元数据写入暂存目录，提交前对读取和列表不可见
+async fn stage_file(
+    staging_id: &str,
+    bucket_name: &str,
+    object_key: &str,
+    body: Vec<u8>,
+    attrs: ObjectAttrs,
+) -> anyhow::Result<()> {
+    let mut written = WrittenFiles::new();
+    let res = save_staged_file(
+        staging_id,
+        bucket_name,
+        object_key,
+        body,
+        attrs,
+        &mut written,
+    )
+    .await;
+    if res.is_err() {
+        written.rollback();
     }
+    res
+}
 
-    let copy_list: Vec<&str> = copy_source.split('/').collect();
-    let copy_list = &copy_list[..copy_list.len() - 1];
+async fn save_staged_file(
+    staging_id: &str,
+    bucket_name: &str,
+    object_key: &str,
+    body: Vec<u8>,
+    attrs: ObjectAttrs,
+    written: &mut WrittenFiles,
+) -> anyhow::Result<()> {
+    let file_name = Path::new(object_key)
+        .file_name()
+        .context("解析文件名失败")?
+        .to_string_lossy()
+        .to_string();
+    let file_type = attrs
+        .content_type
+        .unwrap_or_else(|| util::file::detect_content_type(&file_name, &body));
+    let etag = attrs.etag.unwrap_or_else(|| etag::md5_hex(&body));
+    let checksum_sha256 = attrs
+        .checksum_sha256
+        .unwrap_or_else(|| checksum::sha256_of(&body));
+    // 暂存对象总是写入去重存储，避免提交前覆盖直通存储中的原文件
+    let (file_size, hashcodes, chunk_sizes) = split_file_and_save(
+        body,
+        config::get().chunker(),
+        codec::resolve(attrs.codec),
+        written,
+    )
+    .await?;
+    let metainfo = Metadata {
+        name: file_name,
+        size: file_size as u64,
+        file_type,
+        time: Utc::now(),
+        chunks: hashcodes,
+        chunk_sizes,
+        backend: Backend::Dedup,
+        website_redirect: attrs.website_redirect,
+        headers: attrs.headers,
+        scan: attrs.scan,
+        etag,
+        checksum_sha256: Some(checksum_sha256),
+        version_id: None,
+        delete_marker: false,
+        encryption: sse::unseal(attrs.encryption)?,
+        tags: attrs.tags,
+    };
+    let mut meta_file_path = fs::staging_dir(staging_id, bucket_name)
+        .join(object_key)
+        .to_string_lossy()
+        .to_string();
+    meta_file_path.push_str(".meta");
+    save_metadata(meta_file_path, &metainfo)?;
+    Ok(())
+}
 
-    let mut src_bucket_name = String::new();
-    for &it in copy_list {
-        if !it.is_empty() {
-            src_bucket_name = it.to_string();
-            break;
+// 提交暂存批次：先将全部元数据写成临时文件，全部成功后写入提交清单，再逐个重命名发布。
+// 重命名中途中断时清单仍在，重启时按清单完成剩余的重命名；写入清单之前中断则不发布任何对象
+fn commit_staged(staging_id: &str, bucket_name: &str) -> anyhow::Result<usize> {
+    // 上次提交中断后还未恢复（如重命名失败），先完成上次的提交
+    let manifest = fs::staging_commit_manifest(staging_id, bucket_name);
+    if manifest.is_file() {
+        return finish_staged_commit(staging_id, bucket_name);
+    }
+    let staging_dir = fs::staging_dir(staging_id, bucket_name);
+    if !staging_dir.is_dir() {
+        return Ok(0);
+    }
+    let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name);
+    let mut staged = Vec::new();
+    fs::walk_meta_files(&staging_dir, &mut staged)?;
+
+    let mut prepared = Vec::with_capacity(staged.len());
+    let res: anyhow::Result<()> = staged.iter().try_for_each(|meta_file_path| {
+        let dest = bucket_dir.join(meta_file_path.strip_prefix(&staging_dir)?);
+        let tmp = PathBuf::from(format!("{}.commit-{}", dest.to_string_lossy(), staging_id));
+        let metadata = fs::load_metadata(meta_file_path)?;
+        save_metadata(&tmp, &metadata)?;
+        prepared.push((tmp, dest));
+        Ok(())
+    });
+    let res = res.and_then(|_| write_commit_manifest(&manifest, &prepared));
+    if let Err(err) = res {
+        for (tmp, _) in &prepared {
+            let _ = std::fs::remove_file(tmp);
         }
+        return Err(err);
     }
+    finish_staged_commit(staging_id, bucket_name)
+}
 
-    let mut res = String::new();
-    for i in copy_list.iter().skip(1) {
-        res.push_str(i);
-        res.push('/');
+// 写入提交清单：临时文件和目标文件的路径。临时文件和清单先落盘，清单以重命名原子地出现
+fn write_commit_manifest(manifest: &Path, prepared: &[(PathBuf, PathBuf)]) -> anyhow::Result<()> {
+    std::fs::create_dir_all(manifest.parent().unwrap())?;
+    let sync = config::get().fsync;
+    if sync {
+        for (tmp, _) in prepared {
+            std::fs::File::open(tmp)?.sync_all()?;
+        }
     }
-    let src_object = &res;
-    let src_metadata_path = PathBuf::from(DATA_DIR.get().unwrap())
-        .join(BASIC_PATH_SUFFIX)
-        .join(src_bucket_name)
-        .join(src_object);
-    let dest_metadata_path = PathBuf::from(DATA_DIR.get().unwrap())
-        .join(BASIC_PATH_SUFFIX)
-        .join(dest_bucket)
-        .join(dest_object);
-    std::fs::copy(src_metadata_path, dest_metadata_path).map_err(|err| anyhow!(err))?;
+    // 桶名不以 '.' 开头，写入中的清单不会与其他桶的清单重名
+    let name = manifest.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = manifest.with_file_name(format!(".{}.tmp", name));
+    let mut file = std::fs::File::create(&tmp)?;
+    serde_json::to_writer(&mut file, prepared)?;
+    if sync {
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, manifest).context("写入提交清单失败")?;
+    Ok(())
+}
 
+// 按提交清单发布暂存对象，已重命名过的跳过；完成后删除清单和暂存目录，返回清单中的对象数
+fn finish_staged_commit(staging_id: &str, bucket_name: &str) -> anyhow::Result<usize> {
+    let manifest = fs::staging_commit_manifest(staging_id, bucket_name);
+    let file = std::fs::File::open(&manifest).context("读取提交清单失败")?;
+    let prepared: Vec<(PathBuf, PathBuf)> =
+        serde_json::from_reader(std::io::BufReader::new(file)).context("解析提交清单失败")?;
+    for (tmp, dest) in &prepared {
+        if tmp.exists() {
+            std::fs::rename(tmp, dest).context("发布暂存对象失败")?;
+        }
+        durability::enqueue(dest);
+    }
+    std::fs::remove_file(&manifest).context("删除提交清单失败")?;
+    if let Some(dir) = manifest.parent() {
+        // 同一批次其他桶的清单还在时目录非空，忽略错误
+        let _ = std::fs::remove_dir(dir);
+    }
+    abort_staged(staging_id, bucket_name)?;
+    Ok(prepared.len())
+}
+
+// 启动时、回放日志之前调用：完成提交中途中断的暂存批次，返回完成的桶数
+pub(crate) fn recover_staged_commits() -> anyhow::Result<usize> {
+    let root = fs::staging_root();
+    if !root.is_dir() {
+        return Ok(0);
+    }
+    let mut recovered = 0;
+    for entry in std::fs::read_dir(&root)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let Some(staging_id) = name.strip_suffix(fs::STAGING_COMMIT_SUFFIX) else {
+            continue;
+        };
+        if !path.is_dir() {
+            continue;
+        }
+        for manifest in std::fs::read_dir(&path)? {
+            let manifest = manifest?.path();
+            let bucket_name = manifest.file_name().unwrap_or_default().to_string_lossy();
+            // 写入一半的清单对应的提交还没有发布任何对象，临时文件在下次提交时重写
+            if bucket_name.starts_with('.') {
+                let _ = std::fs::remove_file(&manifest);
+                continue;
+            }
+            let committed = finish_staged_commit(staging_id, &bucket_name)?;
+            warn!(
+                "完成中断的暂存批次提交 {}/{}：{} 个对象",
+                staging_id, bucket_name, committed
+            );
+            recovered += 1;
+        }
+        let _ = std::fs::remove_dir(&path);
+    }
+    Ok(recovered)
+}
+
+// 丢弃暂存批次
+fn abort_staged(staging_id: &str, bucket_name: &str) -> anyhow::Result<()> {
+    let staging_dir = fs::staging_dir(staging_id, bucket_name);
+    if staging_dir.is_dir() {
+        std::fs::remove_dir_all(&staging_dir).context("删除暂存目录失败")?;
+    }
+    if let Some(batch_dir) = staging_dir.parent() {
+        // 批次中其他桶仍有暂存对象时目录非空，忽略错误
+        let _ = std::fs::remove_dir(batch_dir);
+    }
     Ok(())
 }
 
+// 重命名的目标对象已存在时的响应值
+pub(crate) const RENAME_TARGET_EXISTS: &str = "TargetExists";
+
+// 重命名对象或前缀：移动元数据文件（直通存储同时移动原文件），分片数据保持不变。
+// 任一目标对象已存在时不移动任何对象，返回 None：覆盖会绕过版本控制和桶的覆盖限制
+fn rename_objects(bucket_name: &str, source: &str, target: &str) -> anyhow::Result<Option<usize>> {
+    let bucket_dir = PathBuf::from(DATA_DIR.get().unwrap())
+        .join(BASIC_PATH_SUFFIX)
+        .join(bucket_name);
+    let mut moves = Vec::new();
+    if source.ends_with('/') {
+        let source_dir = bucket_dir.join(source);
+        let target_dir = bucket_dir.join(target);
+        let mut meta_files = Vec::new();
+        if source_dir.is_dir() {
+            fs::walk_meta_files(&source_dir, &mut meta_files)?;
+        }
+        for meta_file_path in meta_files {
+            let dest = target_dir.join(meta_file_path.strip_prefix(&source_dir)?);
+            moves.push((meta_file_path, dest));
+        }
+    } else {
+        let meta_file_path = bucket_dir.join(format!("{}.meta", source));
+        if meta_file_path.is_file() {
+            moves.push((meta_file_path, bucket_dir.join(format!("{}.meta", target))));
+        }
+    }
+    if moves.iter().any(|(_, dest)| dest.exists()) {
+        return Ok(None);
+    }
+
+    for (src, dest) in &moves {
+        let mut metadata = fs::load_metadata(src)?;
+        if metadata.backend == Backend::Passthrough {
+            let src_object = fs::object_path_from_meta(src).context("解析对象路径失败")?;
+            let dest_object = fs::object_path_from_meta(dest).context("解析对象路径失败")?;
+            let dest_raw = fs::raw_path(&dest_object);
+            std::fs::create_dir_all(dest_raw.parent().unwrap())?;
+            std::fs::rename(fs::raw_path(&src_object), &dest_raw).context("移动文件失败")?;
+            durability::enqueue(dest_raw);
+        }
+        std::fs::create_dir_all(dest.parent().unwrap())?;
+        let file_name = dest.file_stem().context("解析文件名失败")?;
+        let file_name = file_name.to_string_lossy().to_string();
+        if metadata.name != file_name {
+            metadata.name = file_name;
+            save_metadata(dest, &metadata)?;
+            std::fs::remove_file(src).context("删除元数据失败")?;
+        } else {
+            std::fs::rename(src, dest).context("移动元数据失败")?;
+            durability::enqueue(dest);
+        }
+    }
+    if source.ends_with('/') && bucket_dir.join(source).is_dir() {
+        fs::remove_empty_dirs(&bucket_dir.join(source))?;
+    }
+    Ok(Some(moves.len()))
+}
+
+// 拷贝对象：复制源对象的元数据，新对象引用相同的分片；直接保存的对象需要复制文件。
+// 元数据按目标桶的密钥重新加密保存
+async fn copy_object(
+    copy_source: &str,
+    (dest_bucket, dest_object): (&str, &str),
+    attrs: Option<ObjectAttrs>,
+    log_index: u64,
+) -> anyhow::Result<DateTime<Utc>> {
+    let source = copy::parse_source(copy_source).context("解析拷贝源失败")?;
+    let buckets_dir = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
+    let src_metadata_path = buckets_dir.join(format!("{}.meta", source.stored_path()));
+    let dest_object_path = format!("{}/{}", dest_bucket, dest_object);
+    let dest_metadata_path = buckets_dir.join(format!("{}.meta", dest_object_path));
+    let mut metadata = fs::load_metadata(&src_metadata_path)?;
+    if metadata.backend == Backend::Passthrough && src_metadata_path != dest_metadata_path {
+        let dest_raw = fs::raw_path(&dest_object_path);
+        std::fs::create_dir_all(dest_raw.parent().unwrap())?;
+        std::fs::copy(fs::raw_path(&source.stored_path()), &dest_raw).context("复制文件失败")?;
+        durability::enqueue(dest_raw);
+    }
+    let file_name = dest_metadata_path.file_stem().context("解析文件名失败")?;
+    metadata.name = file_name.to_string_lossy().to_string();
+    metadata.time = Utc::now();
+    if let Some(attrs) = attrs {
+        if let Some(content_type) = attrs.content_type {
+            metadata.file_type = content_type;
+        }
+        metadata.website_redirect = attrs.website_redirect;
+        metadata.headers = attrs.headers;
+        metadata.tags = attrs.tags;
+    }
+    let dest_metadata_path = dest_metadata_path.to_string_lossy();
+    versioning::save_current(&dest_metadata_path, &mut metadata, log_index)?;
+    Ok(metadata.time)
+}
+
+// 替换对象的标签，不改变最后修改时间
+fn set_object_tags(metainfo_file_path: &str, tags: Vec<Tag>) -> anyhow::Result<()> {
+    let mut metadata = fs::load_metadata(metainfo_file_path)?;
+    metadata.tags = tags;
+    save_metadata(metainfo_file_path, &metadata)
+}
+
 // 上传分片
 pub(crate) async fn upload_chunk(
     part_number: &str,
     upload_id: &str,
-    hash: &str,
+    (hash, etag): (&str, Option<&str>),
     body: Vec<u8>,
+    codec: Codec,
 ) -> anyhow::Result<()> {
-    let path = fs::path_from_hash(&hash);
-    if fs::is_path_exist(&path.to_string_lossy().to_string()) {
-        return Ok(());
-    }
-    let hash_clone = hash;
-    let len = body.len();
-    let part_path = PathBuf::from(DATA_DIR.get().unwrap())
-        .join("tmp")
-        .join(upload_id)
-        .join(part_number);
-    tokio::fs::write(part_path, format!("{}", len))
+    // 分片记录为 "大小\n哈希\nMD5"，内容已存在的分片同样需要记录
+    let part_path = fs::upload_parts_dir(upload_id).join(part_number);
+    let etag = etag
+        .map(str::to_string)
+        .unwrap_or_else(|| etag::md5_hex(&body));
+    let size = body.len() as u64;
+    // 只有一个分片的上传分片整体保存
+    let chunker = config::get().chunker();
+    if chunker
+        .next_len(&body, true)
+        .is_none_or(|len| len == body.len())
+    {
+        tokio::fs::write(
+            &part_path,
+            multipart::format_part_record(size, hash, &etag, &[]),
+        )
         .await
         .map_err(|err| anyhow!(err.to_string()))?;
-    let body = fs::compress_chunk(std::io::Cursor::new(&body))?;
-    fs::save_file(&hash_clone, &body).await?;
+        durability::enqueue(part_path);
+        return fs::save_chunk(hash.to_string(), body, codec).await;
+    }
+    // 较大的上传分片切分保存，记录中附带分片列表
+    let mut written = WrittenFiles::new();
+    let (_, chunks, chunk_sizes) =
+        match split_file_and_save(body, chunker, codec, &mut written).await {
+            Ok(res) => res,
+            Err(err) => {
+                written.rollback();
+                return Err(err);
+            }
+        };
+    let chunks: Vec<(String, u64)> = chunks.into_iter().zip(chunk_sizes).collect();
+    tokio::fs::write(
+        &part_path,
+        multipart::format_part_record(size, hash, &etag, &chunks),
+    )
+    .await
+    .map_err(|err| anyhow!(err.to_string()))?;
+    durability::enqueue(part_path);
     Ok(())
 }
 
 // 初始化分片上传
-async fn init_chunk(bucket: String, object_key: String, upload_id: String) -> anyhow::Result<()> {
+async fn init_chunk(
+    bucket: String,
+    object_key: String,
+    upload_id: String,
+    attrs: ObjectAttrs,
+) -> anyhow::Result<()> {
     let file_size_dir = PathBuf::from(DATA_DIR.get().unwrap())
         .join("tmp")
         .join(&upload_id);
@@ -489,15 +1536,26 @@ async fn init_chunk(bucket: String, object_key: String, upload_id: String) -> an
         .context("解析文件名失败")?
         .to_string_lossy()
         .to_string();
-    let file_type = MimeGuess::from_path(Path::new(&file_name))
-        .first_or_text_plain()
-        .to_string();
+    let file_type = attrs
+        .content_type
+        .unwrap_or_else(|| util::file::detect_content_type(&file_name, &[]));
     let meta_info = Metadata {
         name: file_name,
         size: 0,
         file_type,
         time: Default::default(),
         chunks: vec![],
+        chunk_sizes: vec![],
+        backend: Backend::Dedup,
+        website_redirect: attrs.website_redirect,
+        headers: attrs.headers,
+        scan: None,
+        etag: String::new(),
+        checksum_sha256: None,
+        version_id: None,
+        delete_marker: false,
+        encryption: sse::unseal(attrs.encryption)?,
+        tags: attrs.tags,
     };
     save_metadata(&tmp_dir, &meta_info)?;
     Ok(())
@@ -505,17 +1563,13 @@ async fn init_chunk(bucket: String, object_key: String, upload_id: String) -> an
 
 // 完成分片上传
 async fn combine_chunk(
-    bucket_name: &str,
-    object_key: &str,
-    upload_id: &str,
+    (bucket_name, object_key, upload_id): (&str, &str, &str),
     cmu: CompleteMultipartUpload,
+    log_index: u64,
 ) -> anyhow::Result<()> {
     info!("合并分片，uploadId: {}", upload_id);
     let mut part_etags = cmu.part_etags;
 
-    let mut check = true;
-    let mut total_len: u64 = 0;
-
     let extension = &format!(".meta.{}", &upload_id);
     let mut tmp_metadata_dir = PathBuf::from(DATA_DIR.get().unwrap())
         .join(crate::api::BASIC_PATH_SUFFIX)
@@ -530,33 +1584,36 @@ async fn combine_chunk(
         return Err(anyhow!("未初始化".to_string()));
     }
 
-    for part_etag in &part_etags {
-        if !fs::is_path_exist(&part_etag.etag) {
-            check = false;
-            break;
-        }
-        let len_path = PathBuf::from(DATA_DIR.get().unwrap())
-            .join("tmp")
-            .join(upload_id)
-            .join(&format!("{}", part_etag.part_number));
-        let len: u64 = std::fs::read_to_string(len_path)
-            .context("读取长度文件失败")?
-            .parse()
-            .context("解析长度文件失败")?;
-        total_len += len;
-    }
-
+    // 日志中每个分片的 ETag 为分片内容的哈希，需与分片记录一致且保存的分片均存在
+    let uploaded = multipart::uploaded_parts(upload_id).context("读取分片记录失败")?;
+    let check = part_etags.iter().all(|part_etag| {
+        u32::try_from(part_etag.part_number)
+            .ok()
+            .and_then(|n| uploaded.get(&n))
+            .is_some_and(|part| {
+                part.hash == part_etag.etag
+                    && part.chunks.iter().all(|(hash, _)| fs::is_path_exist(hash))
+            })
+    });
     if !check {
         info!("分片不完整");
         return Err(anyhow!("分片不完整".to_string()));
     }
     part_etags.sort_by_key(|p| p.part_number);
-    let chunks: Vec<String> = part_etags.par_iter().map(|p| p.etag.clone()).collect();
+    // 拼接各分片的分片列表，不读取分片内容
+    let assembled = multipart::assemble(&part_etags, &uploaded);
     let mut metadata = fs::load_metadata(tmp_metadata_dir.to_string_lossy().as_ref())?;
     info!("读取临时元数据成功");
-    metadata.size = total_len;
-    metadata.chunks = chunks;
+    metadata.size = assembled.size;
+    metadata.checksum_sha256 = checksum::composite_sha256(&assembled.part_hashes);
+    metadata.chunks = assembled.chunks;
+    metadata.chunk_sizes = assembled.chunk_sizes;
     metadata.time = Utc::now();
+    if let Some(encryption) = &mut metadata.encryption {
+        encryption.parts = assembled.chunk_parts;
+        encryption.part_offsets = assembled.chunk_offsets;
+    }
+    metadata.etag = multipart::completed_etag(&part_etags, &uploaded);
 
     let mut metadata_dir = PathBuf::from(DATA_DIR.get().unwrap())
         .join(crate::api::BASIC_PATH_SUFFIX)
@@ -565,7 +1622,7 @@ async fn combine_chunk(
         .to_string_lossy()
         .to_string();
     metadata_dir.push_str(".meta");
-    save_metadata(&metadata_dir, &metadata)?;
+    versioning::save_current(&metadata_dir, &mut metadata, log_index)?;
     info!("保存新元数据成功");
     std::fs::remove_file(tmp_metadata_dir).context("删除临时元数据失败")?;
     std::fs::remove_dir_all(
@@ -577,10 +1634,44 @@ async fn combine_chunk(
     Ok(())
 }
 
+// 放弃分片上传；已保存的分片内容与其他对象共用，留给垃圾回收处理
+fn abort_chunk(bucket_name: &str, object_key: &str, upload_id: &str) -> anyhow::Result<()> {
+    let meta_path = fs::upload_meta_path(bucket_name, object_key, upload_id);
+    if meta_path.exists() {
+        std::fs::remove_file(&meta_path).context("删除临时元数据失败")?;
+    }
+    let parts_dir = fs::upload_parts_dir(upload_id);
+    if parts_dir.exists() {
+        std::fs::remove_dir_all(&parts_dir).context("删除临时文件夹失败")?;
+    }
+    Ok(())
+}
+
+// 删除对象，开启过版本控制的桶按版本删除
+async fn delete_object(
+    metainfo_file_path: String,
+    version_id: Option<&str>,
+    marker_id: String,
+) -> anyhow::Result<()> {
+    if versioning::delete(&metainfo_file_path, version_id, marker_id)? {
+        return Ok(());
+    }
+    do_delete_file(metainfo_file_path).await
+}
+
 // 删除文件逻辑
 async fn do_delete_file(metainfo_file_path: String) -> anyhow::Result<()> {
     if std::fs::metadata(&metainfo_file_path).is_ok() {
+        let metadata = fs::load_metadata(&metainfo_file_path)?;
         std::fs::remove_file(&metainfo_file_path).context("删除文件失败")?;
+        if let Err(err) = access::forget(&metainfo_file_path) {
+            info!("删除访问记录失败: {}", err);
+        }
+        if metadata.backend == Backend::Passthrough {
+            if let Some(object_path) = fs::object_path_from_meta(&metainfo_file_path) {
+                let _ = std::fs::remove_file(fs::raw_path(&object_path));
+            }
+        }
     }
     Ok(())
 }
@@ -603,7 +1694,45 @@ fn bin_to_id(buf: &[u8]) -> u64 {
     (&buf[0..8]).read_u64::<BigEndian>().unwrap()
 }
 
+// （日志序号，业务请求），非业务条目的请求为 None
+pub(crate) type LogRequests = Vec<(u64, Option<Request>)>;
+
 impl LogStore {
+    // 读取 [from, to] 内的日志条目及其中的业务请求，最多 max_entries 条，累计超过 max_bytes 后停止；
+    // from 处的日志已被清理时返回 None
+    pub(crate) fn read_entries(
+        &self,
+        from: u64,
+        to: u64,
+        max_entries: u64,
+        max_bytes: usize,
+    ) -> anyhow::Result<Option<LogRequests>> {
+        let mut entries = Vec::new();
+        let mut bytes = 0;
+        let (start, end) = (id_to_bin(from), id_to_bin(to));
+        for res in self
+            .logs()
+            .range::<&[u8], _>(start.as_slice()..=end.as_slice())
+        {
+            let (id, val) = res.context("读取日志失败")?;
+            let index = bin_to_id(&id);
+            if entries.is_empty() && index != from {
+                return Ok(None);
+            }
+            if entries.len() as u64 >= max_entries || bytes >= max_bytes {
+                break;
+            }
+            bytes += val.len();
+            let entry: Entry<TypeConfig> = postcard::from_bytes(&val).context("解析日志失败")?;
+            let request = match entry.payload {
+                EntryPayload::Normal(request) => Some(request),
+                _ => None,
+            };
+            entries.push((index, request));
+        }
+        Ok(Some(entries))
+    }
+
     fn store(&self) -> sled::Tree {
         self.db.open_tree("store").expect("logs open failed")
     }
@@ -734,7 +1863,7 @@ impl RaftLogStorage<TypeConfig> for LogStore {
             .and_then(|res| {
                 let (_, ent) = res;
                 Some(
-                    serde_json::from_slice::<Entry<TypeConfig>>(&ent)
+                    postcard::from_bytes::<Entry<TypeConfig>>(&ent)
                         .ok()
                         .unwrap()
                         .log_id,
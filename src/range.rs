@@ -0,0 +1,121 @@
+use futures::{Stream, TryStreamExt};
+use ntex::util::Bytes;
+use std::io;
+
+// --- 范围读取：解析 Range 请求头，按元数据中记录的分片大小直接定位到范围起点所在的分片，
+// 只解压需要的分片
+
+// 请求的字节范围，end 包含在内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    // Content-Range 响应头
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, size)
+    }
+}
+
+// 范围起点超出对象大小，应返回 416
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unsatisfiable;
+
+// 解析 Range 请求头；不支持的写法（如多个范围）或语法错误时返回 None，按完整对象返回
+pub fn parse(header: &str, size: u64) -> Result<Option<ByteRange>, Unsatisfiable> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // bytes=-N：最后 N 个字节
+        let Ok(suffix) = last.parse::<u64>() else {
+            return Ok(None);
+        };
+        if suffix == 0 || size == 0 {
+            return Err(Unsatisfiable);
+        }
+        return Ok(Some(ByteRange {
+            start: size.saturating_sub(suffix),
+            end: size - 1,
+        }));
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return Ok(None);
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return Ok(None),
+        }
+    };
+    if start >= size {
+        return Err(Unsatisfiable);
+    }
+    Ok(Some(ByteRange {
+        start,
+        end: end.min(size - 1),
+    }))
+}
+
+// 找到 offset 所在的分片，返回分片序号和需要在该分片内跳过的字节数；
+// 没有分片大小（旧版本写入的元数据）时只能从第一个分片开始跳过
+pub fn locate(chunk_sizes: &[u64], offset: u64) -> (usize, u64) {
+    let mut skip = offset;
+    for (idx, size) in chunk_sizes.iter().enumerate() {
+        if skip < *size {
+            return (idx, skip);
+        }
+        skip -= size;
+    }
+    if chunk_sizes.is_empty() {
+        (0, offset)
+    } else {
+        (chunk_sizes.len(), skip)
+    }
+}
+
+// 跳过数据流开头的 skip 个字节，只取之后的 len 个字节，取够后不再读取后续分片
+pub(crate) fn slice_stream(
+    stream: impl Stream<Item = io::Result<Bytes>> + Unpin,
+    skip: u64,
+    len: u64,
+) -> impl Stream<Item = io::Result<Bytes>> {
+    futures::stream::try_unfold(
+        (stream, skip, len),
+        |(mut stream, mut skip, mut remaining)| async move {
+            while remaining > 0 {
+                let Some(bytes) = stream.try_next().await? else {
+                    break;
+                };
+                let dropped = skip.min(bytes.len() as u64);
+                skip -= dropped;
+                let bytes = bytes.slice(dropped as usize..);
+                if bytes.is_empty() {
+                    continue;
+                }
+                let taken = remaining.min(bytes.len() as u64);
+                remaining -= taken;
+                return Ok(Some((
+                    bytes.slice(..taken as usize),
+                    (stream, skip, remaining),
+                )));
+            }
+            Ok(None)
+        },
+    )
+}
@@ -0,0 +1,192 @@
+use crate::access;
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::cluster;
+use crate::codec;
+use crate::config;
+use crate::durability;
+use crate::fs;
+use crate::pool;
+use chrono::Utc;
+use log::{info, warn};
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// --- 冷分片重新压缩：开启 --recompress-cold-after 后定期找出超过指定天数未读取（未开启
+// --track-access 时按最后修改时间）的对象，把只被这些对象引用的分片以 --recompress-level
+// 重新压缩，回收以低级别快速写入的数据占用的空间。分片按未压缩的内容计算哈希，重新压缩
+// 不影响去重和读取；处理过的分片末尾追加一个记录级别的 zstd 可跳过帧，之后不再重复处理
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+// 后台线程池有排队的任务时每个分片之后让出的时间
+const BACKOFF: Duration = Duration::from_millis(100);
+// zstd 可跳过帧的魔数范围为 0x184D2A50 到 0x184D2A5F，解码时忽略帧内容
+const SKIPPABLE_MAGIC: u32 = 0x184D2A5E;
+const MARKER_TAG: &[u8; 4] = b"RCMP";
+// 魔数、帧长度、标记和级别各 4 字节
+pub const MARKER_LEN: usize = 16;
+
+// 记录重新压缩级别的可跳过帧
+pub fn marker(level: i32) -> [u8; MARKER_LEN] {
+    let mut frame = [0u8; MARKER_LEN];
+    frame[0..4].copy_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+    frame[4..8].copy_from_slice(&8u32.to_le_bytes());
+    frame[8..12].copy_from_slice(MARKER_TAG);
+    frame[12..16].copy_from_slice(&level.to_le_bytes());
+    frame
+}
+
+// 分片文件末尾记录的重新压缩级别，没有处理过时为空
+pub fn marked_level(chunk: &[u8]) -> Option<i32> {
+    let frame = chunk.get(chunk.len().checked_sub(MARKER_LEN)?..)?;
+    if frame[0..12] != marker(0)[0..12] {
+        return None;
+    }
+    Some(i32::from_le_bytes(frame[12..16].try_into().unwrap()))
+}
+
+// 以 level 重新压缩解压后的分片并追加标记；结果不比原来小时保留原有的压缩数据，
+// 同样追加标记避免反复尝试（如已压缩过的图片）。lz4 或不压缩的分片总是改为 zstd 帧，
+// 标记只能追加在 zstd 帧之后。返回新的分片文件内容
+pub fn recompress(compressed: &[u8], data: &[u8], level: i32) -> io::Result<Vec<u8>> {
+    let mut out = zstd::stream::encode_all(data, level)?;
+    if codec::is_zstd(compressed) && out.len() >= compressed.len() {
+        out = compressed.to_vec();
+    }
+    out.extend_from_slice(&marker(level));
+    Ok(out)
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    chunks: u64,
+    saved: u64,
+    failed: u64,
+}
+
+pub(crate) fn spawn() {
+    let cfg = &config::get().compression;
+    let Some(days) = cfg.recompress_after_days else {
+        return;
+    };
+    if config::get().erasure().is_some() {
+        warn!("纠删码模式下不支持重新压缩分片，忽略 --recompress-cold-after");
+        return;
+    }
+    let level = cfg.recompress_level;
+    info!(
+        "超过 {} 天未读取的对象的分片将以级别 {} 重新压缩",
+        days, level
+    );
+    std::thread::Builder::new()
+        .name("recompress".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(SWEEP_INTERVAL);
+            match cold_chunks(days) {
+                Ok(hashes) => run(hashes, level),
+                Err(err) => warn!("查找冷分片失败: {}", err),
+            }
+        })
+        .expect("启动重新压缩线程失败");
+}
+
+// 只被冷对象引用的分片；同时被其他对象引用的分片视为热数据
+fn cold_chunks(days: u64) -> io::Result<BTreeSet<String>> {
+    let buckets_dir = PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX);
+    let now = Utc::now();
+    let (mut cold, mut hot) = (BTreeSet::new(), BTreeSet::new());
+    for bucket in std::fs::read_dir(&buckets_dir)?.flatten() {
+        if !bucket.path().is_dir() {
+            continue;
+        }
+        let bucket_name = bucket.file_name().to_string_lossy().to_string();
+        let records = access::load(&bucket_name);
+        let mut meta_files = vec![];
+        fs::walk_meta_files(&bucket.path(), &mut meta_files)?;
+        for meta_file in meta_files {
+            let Ok(metadata) = fs::load_metadata(&meta_file) else {
+                continue;
+            };
+            let key = meta_file
+                .strip_prefix(bucket.path())
+                .ok()
+                .and_then(|p| p.to_str())
+                .and_then(|p| p.strip_suffix(".meta"))
+                .unwrap_or_default();
+            let last_access = records.get(key).map(|r| r.last_access);
+            if access::is_unread_for(last_access, metadata.time, days, now) {
+                cold.extend(metadata.chunks);
+            } else {
+                hot.extend(metadata.chunks);
+            }
+        }
+    }
+    Ok(cold
+        .into_iter()
+        .filter(|hash| !hot.contains(hash) && cluster::is_local(hash))
+        .collect())
+}
+
+fn run(hashes: BTreeSet<String>, level: i32) {
+    let mut summary = Summary::default();
+    for hash in hashes {
+        match recompress_chunk(&hash, level) {
+            Ok(saved) if saved > 0 => {
+                summary.chunks += 1;
+                summary.saved += saved;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                summary.failed += 1;
+                warn!("重新压缩分片 {} 失败: {}", hash, err);
+            }
+        }
+        if pool::queued() > 0 {
+            std::thread::sleep(BACKOFF);
+        }
+    }
+    if summary.chunks + summary.failed > 0 {
+        info!(
+            "重新压缩了 {} 个冷分片，节省 {} 字节，{} 个分片失败",
+            summary.chunks, summary.saved, summary.failed
+        );
+    }
+}
+
+// 重新压缩分片的所有副本，返回节省的字节数；已以不低于 level 的级别处理过时跳过
+fn recompress_chunk(hash: &str, level: i32) -> io::Result<u64> {
+    let compressed = fs::read_local_chunk(hash)?;
+    if marked_level(&compressed).is_some_and(|marked| marked >= level) {
+        return Ok(0);
+    }
+    let data = codec::decode(&compressed)?;
+    // 分片内容与哈希不一致时保持原样，交给后续的校验处理
+    if fs::get_sha256_string(&fs::get_sha256(&data)) != hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "分片内容与哈希不一致",
+        ));
+    }
+    let out = recompress(&compressed, &data, level)?;
+    for path in fs::chunk_paths(hash) {
+        // 写入期间被删除的副本不再重建
+        if path.exists() {
+            replace_file(&path, &out)?;
+        }
+    }
+    Ok((compressed.len() as u64).saturating_sub(out.len() as u64))
+}
+
+// 先写入同目录下的临时文件再改名，读取方只会看到完整的旧文件或新文件；
+// 改名前总是同步临时文件，避免宕机后已落盘的分片被未写完的文件替换
+fn replace_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("recompress.tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    durability::enqueue(path);
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::fs;
+#[cfg(any(test, feature = "failpoints"))]
+use anyhow::anyhow;
+use log::warn;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// --- 写入失败回滚：PUT 写分片、读回校验或保存元数据的任何一步失败时，删除本次请求新建的
+// 分片文件（写入前不存在的副本或纠删码块）。状态机按顺序应用写请求，这些文件只可能被本次
+// 请求引用；写入前已存在的文件（去重命中或补写缺失副本时的其余副本）保持不变。元数据先写
+// 临时文件再重命名，失败时不会留下指向缺失分片的元数据，也不会破坏原有对象。
+// 落盘上传分多条请求写入分片，之前写入的分片可能已被其他对象去重引用，只回滚失败的分片，
+// 其余留给垃圾回收处理
+
+// 写入过程中可注入失败的步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailPoint {
+    ChunkWrite,
+    ChunkVerify,
+    Metadata,
+}
+
+// 注入的失败只影响当前线程，并行运行的测试互不干扰；只在测试或开启 failpoints 特性时编译
+#[cfg(any(test, feature = "failpoints"))]
+thread_local! {
+    static FAIL_POINT: std::cell::Cell<Option<FailPoint>> = const { std::cell::Cell::new(None) };
+}
+
+// 让当前线程之后的写入在指定步骤失败，为空时取消
+#[cfg(any(test, feature = "failpoints"))]
+pub fn inject(point: Option<FailPoint>) {
+    FAIL_POINT.with(|fail_point| fail_point.set(point));
+}
+
+// 写入流程在每一步完成后调用，该步骤被注入失败时返回错误
+#[cfg(any(test, feature = "failpoints"))]
+pub fn check(point: FailPoint) -> anyhow::Result<()> {
+    if FAIL_POINT.with(|fail_point| fail_point.get()) == Some(point) {
+        return Err(anyhow!("注入的写入失败: {:?}", point));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(test, feature = "failpoints")))]
+#[inline]
+pub fn check(_point: FailPoint) -> anyhow::Result<()> {
+    Ok(())
+}
+
+// 测试用：按状态机应用上传请求的流程保存对象（写分片、写元数据，失败时回滚）。
+// 分片和元数据写入 data_dir 下，同一进程只能使用第一次传入的数据目录
+#[cfg(any(test, feature = "failpoints"))]
+pub async fn upload(data_dir: &Path, object_path: &str, body: Vec<u8>) -> anyhow::Result<()> {
+    let data_dir = crate::api::DATA_DIR
+        .get_or_init(|| async { data_dir.to_string_lossy().to_string() })
+        .await;
+    let meta_file_path = Path::new(data_dir)
+        .join(crate::api::BASIC_PATH_SUFFIX)
+        .join(format!("{}.meta", object_path));
+    crate::raft::store::upload_file(
+        meta_file_path.to_string_lossy().to_string(),
+        body,
+        Default::default(),
+        0,
+    )
+    .await
+}
+
+// 本次请求新建的文件
+#[derive(Debug, Default)]
+pub struct WrittenFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl WrittenFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 登记即将写入的文件，返回文件是否需要写入；已存在的文件不登记，回滚时不删除
+    pub fn track(&mut self, path: &Path) -> bool {
+        if path.exists() {
+            return false;
+        }
+        self.paths.push(path.to_path_buf());
+        true
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    // 删除登记的文件（包括只写入了一部分的文件），返回删除的文件数。在垃圾回收锁内删除，
+    // 与去重判断互斥
+    pub fn rollback(self) -> usize {
+        let _guard = fs::CHUNK_GC_LOCK.lock().unwrap();
+        let mut removed = 0;
+        for path in &self.paths {
+            match std::fs::remove_file(path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => warn!("回滚删除文件 {:?} 失败: {}", path, err),
+            }
+        }
+        if removed > 0 {
+            warn!("写入失败，已删除本次写入的 {} 个文件", removed);
+        }
+        removed
+    }
+}
@@ -0,0 +1,301 @@
+use crate::config;
+use crate::config::Scanner;
+use crate::fs;
+use crate::fs::{ScanStatus, ScanVerdict};
+use crate::pool;
+use crate::sse::ObjectCipher;
+use anyhow::{anyhow, bail, Context};
+use chrono::Utc;
+use log::warn;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// --- 扩展：上传内容扫描。配置 --scan-command 或 --scan-icap 后，对象在写入前先交给扫描器，
+// 结果记录在元数据中；感染的对象按 --scan-action 拒绝上传或隔离（保存但不可下载）
+
+// 单次扫描的超时时间
+const SCAN_TIMEOUT: Duration = Duration::from_secs(300);
+// 传给扫描器的单个数据块大小
+const BLOCK_SIZE: usize = 1 << 20;
+const ICAP_DEFAULT_PORT: u16 = 1344;
+// ICAP 响应头的长度上限
+const MAX_ICAP_HEAD: usize = 64 << 10;
+
+// 待扫描的内容及其大小
+pub(crate) enum ScanInput<'a> {
+    Memory(&'a [u8]),
+    // 落盘的请求体
+    File(&'a Path, u64),
+    // 已保存的分片（分片上传），加密的分片上传带解密参数
+    Chunks(&'a [String], u64, Option<&'a ObjectCipher>),
+}
+
+impl ScanInput<'_> {
+    fn size(&self) -> u64 {
+        match self {
+            ScanInput::Memory(data) => data.len() as u64,
+            ScanInput::File(_, size) | ScanInput::Chunks(_, size, _) => *size,
+        }
+    }
+
+    // 依次把内容写入 writer，frame 为 true 时按 HTTP chunked 编码分块
+    async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        frame: bool,
+    ) -> anyhow::Result<()> {
+        let mut write_block = async |block: &[u8]| -> std::io::Result<()> {
+            if block.is_empty() {
+                return Ok(());
+            }
+            if frame {
+                writer
+                    .write_all(format!("{:x}\r\n", block.len()).as_bytes())
+                    .await?;
+            }
+            writer.write_all(block).await?;
+            if frame {
+                writer.write_all(b"\r\n").await?;
+            }
+            Ok(())
+        };
+        match self {
+            ScanInput::Memory(data) => {
+                for block in data.chunks(BLOCK_SIZE) {
+                    write_block(block).await?;
+                }
+            }
+            ScanInput::File(path, _) => {
+                let mut file = tokio::fs::File::open(path)
+                    .await
+                    .context("打开临时文件失败")?;
+                let mut block = vec![0u8; BLOCK_SIZE];
+                loop {
+                    let n = file.read(&mut block).await.context("读取临时文件失败")?;
+                    if n == 0 {
+                        break;
+                    }
+                    write_block(&block[..n]).await?;
+                }
+            }
+            ScanInput::Chunks(chunks, _, cipher) => {
+                for (index, hash) in chunks.iter().enumerate() {
+                    let hash = hash.clone();
+                    let mut chunk = pool::run(move || fs::read_chunk_decompressed(&hash))
+                        .await?
+                        .context("读取分片失败")?;
+                    if let Some(cipher) = cipher {
+                        chunk = cipher.apply_chunk(index, 0, &chunk)?;
+                    }
+                    write_block(&chunk).await?;
+                }
+            }
+        }
+        if frame {
+            writer.write_all(b"0\r\n\r\n").await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+pub(crate) fn enabled() -> bool {
+    config::get().scanner.is_some()
+}
+
+// 扫描内容，未配置扫描器时返回 None；扫描器出错时返回错误，配置了 --scan-fail-open 时记为 Failed 放行
+pub(crate) async fn scan(input: ScanInput<'_>) -> anyhow::Result<Option<ScanStatus>> {
+    let cfg = config::get();
+    let Some(scanner) = &cfg.scanner else {
+        return Ok(None);
+    };
+    let res = tokio::time::timeout(SCAN_TIMEOUT, async {
+        match scanner {
+            Scanner::Command(command) => scan_command(command, &input).await,
+            Scanner::Icap(url) => scan_icap(url, &input).await,
+        }
+    })
+    .await
+    .unwrap_or_else(|_| Err(anyhow!("扫描超时")));
+    let (verdict, signature) = match res {
+        Ok(res) => res,
+        Err(err) if cfg.scan_fail_open => {
+            warn!("扫描失败，按配置放行: {:#}", err);
+            (ScanVerdict::Failed, Some(format!("{:#}", err)))
+        }
+        Err(err) => return Err(err),
+    };
+    Ok(Some(ScanStatus {
+        verdict,
+        signature,
+        time: Utc::now(),
+    }))
+}
+
+// 从扫描命令的输出中找出病毒名，如 clamscan 的 "stdin: Eicar-Test-Signature FOUND"
+pub fn command_signature(stdout: &str) -> Option<String> {
+    let lines = || {
+        stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+    };
+    if let Some(found) = lines().find_map(|line| line.strip_suffix(" FOUND")) {
+        let name = found.rsplit_once(": ").map_or(found, |(_, name)| name);
+        return Some(name.to_string());
+    }
+    lines().next().map(str::to_string)
+}
+
+async fn scan_command(
+    command: &str,
+    input: &ScanInput<'_>,
+) -> anyhow::Result<(ScanVerdict, Option<String>)> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("启动扫描命令失败")?;
+    let mut stdin = child.stdin.take().context("打开扫描命令的标准输入失败")?;
+    let feed = async move {
+        // 扫描器可能不读完内容就退出，写入失败时以退出码为准
+        let res = input.write_to(&mut stdin, false).await;
+        drop(stdin);
+        res
+    };
+    let (fed, output) = tokio::join!(feed, child.wait_with_output());
+    let output = output.context("等待扫描命令失败")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match output.status.code() {
+        Some(0) => Ok((ScanVerdict::Clean, None)),
+        Some(1) => Ok((ScanVerdict::Infected, command_signature(&stdout))),
+        code => {
+            if let Err(err) = fed {
+                warn!("写入扫描命令失败: {:#}", err);
+            }
+            bail!(
+                "扫描命令退出码 {:?}: {}",
+                code,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+    }
+}
+
+// 解析 icap://host[:port]/service
+pub fn parse_icap_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("icap://")?;
+    let (authority, service) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, ICAP_DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port, service.to_string()))
+}
+
+// RESPMOD 请求头，内容作为 HTTP 响应体封装，随后按 chunked 编码发送
+pub fn icap_request_head(host: &str, port: u16, service: &str, size: u64) -> String {
+    let http_head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+        size
+    );
+    format!(
+        "RESPMOD icap://{host}:{port}{service} ICAP/1.0\r\nHost: {host}:{port}\r\nAllow: 204\r\nConnection: close\r\nEncapsulated: res-hdr=0, res-body={}\r\n\r\n{}",
+        http_head.len(),
+        http_head
+    )
+}
+
+// 解析 ICAP 响应：204 为正常；200 时按感染相关的响应头或封装的 HTTP 状态判断
+pub fn parse_icap_response(response: &str) -> anyhow::Result<(ScanVerdict, Option<String>)> {
+    let (icap_head, rest) = response.split_once("\r\n\r\n").unwrap_or((response, ""));
+    let mut lines = icap_head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .context("无效的 ICAP 响应")?;
+    match status {
+        "204" => return Ok((ScanVerdict::Clean, None)),
+        "200" => {}
+        _ => bail!(
+            "ICAP 服务返回 {}",
+            icap_head.lines().next().unwrap_or_default()
+        ),
+    }
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            // 如 "Type=0; Resolution=2; Threat=Eicar-Test-Signature;"
+            "x-infection-found" => {
+                let threat = value
+                    .split(';')
+                    .find_map(|part| part.trim().strip_prefix("Threat="))
+                    .unwrap_or(value);
+                return Ok((ScanVerdict::Infected, Some(threat.to_string())));
+            }
+            "x-virus-id" | "x-violations-found" => {
+                return Ok((ScanVerdict::Infected, Some(value.to_string())));
+            }
+            _ => {}
+        }
+    }
+    // 没有感染相关的响应头时，封装的 HTTP 响应不是 2xx 视为拦截
+    let blocked = rest
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .is_some_and(|code| !code.starts_with('2'));
+    if blocked {
+        return Ok((ScanVerdict::Infected, None));
+    }
+    Ok((ScanVerdict::Clean, None))
+}
+
+async fn scan_icap(
+    url: &str,
+    input: &ScanInput<'_>,
+) -> anyhow::Result<(ScanVerdict, Option<String>)> {
+    let (host, port, service) =
+        parse_icap_url(url).with_context(|| format!("无效的 ICAP 地址 {}", url))?;
+    let mut stream = TcpStream::connect((host.as_str(), port))
+        .await
+        .with_context(|| format!("连接 ICAP 服务 {}:{} 失败", host, port))?;
+    stream
+        .write_all(icap_request_head(&host, port, &service, input.size()).as_bytes())
+        .await?;
+    input.write_to(&mut stream, true).await?;
+    // 读到 ICAP 响应头和封装的 HTTP 响应头（若有）即可，不需要响应体
+    let mut response = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.context("读取 ICAP 响应失败")?;
+        response.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&response);
+        let heads = text.matches("\r\n\r\n").count();
+        let wants_http_head = text.contains("res-hdr") && !text.starts_with("ICAP/1.0 204");
+        if n == 0
+            || heads >= 2
+            || (heads == 1 && !wants_http_head)
+            || response.len() > MAX_ICAP_HEAD
+        {
+            break;
+        }
+    }
+    parse_icap_response(&String::from_utf8_lossy(&response))
+}
@@ -0,0 +1,399 @@
+use crate::api::{BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::cdc::{CdcEvent, Op};
+use crate::config;
+use crate::config::{RestrictedOperation, ScriptConfig, ScriptEvent};
+use crate::fs;
+use crate::fs::Backend;
+use crate::pool;
+use crate::raft::app::App;
+use crate::raft::store::{ObjectAttrs, Request};
+use crate::sse;
+use crate::standby;
+use anyhow::{anyhow, Context};
+use log::{info, warn};
+use rhai::{Array, Blob, Dynamic, Engine, EvalAltResult, Scope, AST};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::runtime::Handle;
+
+// --- 扩展：rhai 事件脚本。脚本定义 on_event(event)，对象新增、覆盖或删除后在主节点上执行，
+// event 为 CDC 格式的事件。脚本可调用的存储接口：
+//   get_object(bucket, key)          对象内容（UTF-8 时为字符串，否则为 blob），不存在时为 ()
+//   object_exists(bucket, key)
+//   list_objects(bucket, prefix)     键的数组，最多 MAX_LIST_KEYS 个
+//   put_object(bucket, key, content[, content_type])
+//   delete_object(bucket, key)
+//   log(message)
+// 写入和删除在脚本返回后依次通过 raft 提交，脚本执行失败时全部丢弃；
+// 脚本写入的对象产生的事件不再触发脚本，避免循环
+
+// 单次执行的操作数上限，避免脚本死循环
+const MAX_OPERATIONS: u64 = 10_000_000;
+// 字符串和 blob 的长度上限
+const MAX_STRING_SIZE: usize = 16 << 20;
+const MAX_ARRAY_SIZE: usize = 100_000;
+const MAX_MAP_SIZE: usize = 10_000;
+const MAX_CALL_LEVELS: usize = 64;
+// 单次执行最多写入或删除的对象数
+const MAX_ACTIONS: usize = 100;
+const MAX_LIST_KEYS: usize = 1000;
+
+struct Script {
+    name: String,
+    config: ScriptConfig,
+    ast: AST,
+}
+
+// 脚本执行后提交的改动
+enum Action {
+    Put {
+        meta_file_path: String,
+        body: Vec<u8>,
+        content_type: Option<String>,
+    },
+    Delete {
+        meta_file_path: String,
+    },
+}
+
+static SCRIPTS: OnceLock<Vec<Script>> = OnceLock::new();
+static RUNTIME: OnceLock<(App, Handle)> = OnceLock::new();
+// 正在提交的脚本改动的对象路径，其事件不触发脚本
+static WRITING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn scripts() -> &'static [Script] {
+    SCRIPTS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_map_size(MAX_MAP_SIZE)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .disable_symbol("eval");
+    engine
+}
+
+// 启动时编译全部脚本，并检查定义了 on_event
+pub(crate) fn load() -> anyhow::Result<()> {
+    let configs = &config::get().scripts;
+    if configs.is_empty() {
+        return Ok(());
+    }
+    let engine = new_engine();
+    let mut loaded = Vec::with_capacity(configs.len());
+    for script in configs {
+        let source = std::fs::read_to_string(&script.path)
+            .with_context(|| format!("读取脚本 {:?} 失败", script.path))?;
+        let ast = engine
+            .compile(source)
+            .map_err(|err| anyhow!("编译脚本 {:?} 失败: {}", script.path, err))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "on_event" && f.params.len() == 1)
+        {
+            anyhow::bail!("脚本 {:?} 没有定义 on_event(event)", script.path);
+        }
+        let name = script
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        info!("加载脚本 {}（{:?}）", name, script.events);
+        loaded.push(Script {
+            name,
+            config: script.clone(),
+            ast,
+        });
+    }
+    let _ = SCRIPTS.set(loaded);
+    Ok(())
+}
+
+// 记录提交改动使用的 raft 实例；需在 tokio 运行时中调用
+pub(crate) fn set_app(app: App) {
+    let _ = RUNTIME.set((app, Handle::current()));
+}
+
+pub(crate) fn has_scripts() -> bool {
+    !scripts().is_empty()
+}
+
+fn buckets_dir() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX)
+}
+
+// 校验脚本传入的桶和键，返回对象的元数据路径
+fn meta_path(bucket: &str, key: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+    let invalid = bucket.is_empty()
+        || bucket.contains('/')
+        || bucket == ".."
+        || key.is_empty()
+        || key.starts_with('/')
+        || key.split('/').any(|part| part.is_empty() || part == "..");
+    if invalid {
+        return Err(format!("无效的对象 {}/{}", bucket, key).into());
+    }
+    let bucket_dir = buckets_dir().join(bucket);
+    if !bucket_dir.is_dir() {
+        return Err(format!("桶 {} 不存在", bucket).into());
+    }
+    Ok(bucket_dir.join(format!("{}.meta", key)))
+}
+
+fn read_object(meta_file_path: &Path) -> anyhow::Result<Vec<u8>> {
+    let metadata = fs::load_metadata(meta_file_path)?;
+    if metadata.is_quarantined() {
+        anyhow::bail!("对象已隔离");
+    }
+    let cipher = sse::read_cipher(metadata.encryption.as_ref(), None)
+        .map_err(|_| anyhow!("对象使用客户提供的密钥加密"))?;
+    match metadata.backend {
+        Backend::Dedup => {
+            let mut data = Vec::with_capacity(metadata.size as usize);
+            for (index, hash) in metadata.chunks.iter().enumerate() {
+                let chunk = fs::read_chunk_decompressed(hash).context("读取分片失败")?;
+                match &cipher {
+                    Some(cipher) => data.extend_from_slice(&cipher.apply_chunk(
+                        index,
+                        data.len() as u64,
+                        &chunk,
+                    )?),
+                    None => data.extend_from_slice(&chunk),
+                }
+            }
+            Ok(data)
+        }
+        Backend::Passthrough => {
+            let object_path =
+                fs::object_path_from_meta(meta_file_path).context("解析对象路径失败")?;
+            std::fs::read(fs::raw_path(&object_path)).context("读取文件失败")
+        }
+    }
+}
+
+fn list_keys(bucket: &str, prefix: &str) -> Result<Array, Box<EvalAltResult>> {
+    if bucket.is_empty() || bucket.contains('/') || bucket == ".." {
+        return Err(format!("无效的桶 {}", bucket).into());
+    }
+    let bucket_dir = buckets_dir().join(bucket);
+    let mut meta_files = Vec::new();
+    fs::walk_meta_files(&bucket_dir, &mut meta_files)
+        .map_err(|err| format!("遍历桶 {} 失败: {}", bucket, err))?;
+    let mut keys: Vec<String> = meta_files
+        .iter()
+        .filter_map(|path| {
+            let key = path.strip_prefix(&bucket_dir).ok()?.to_string_lossy();
+            Some(key.strip_suffix(".meta")?.to_string())
+        })
+        .filter(|key| key.starts_with(prefix))
+        .collect();
+    keys.sort_unstable();
+    keys.truncate(MAX_LIST_KEYS);
+    Ok(keys.into_iter().map(Dynamic::from).collect())
+}
+
+fn object_content(content: Dynamic) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    if content.is_string() {
+        return Ok(content.into_string()?.into_bytes());
+    }
+    if content.is_blob() {
+        return Ok(content.cast::<Blob>());
+    }
+    Err(format!("对象内容须为字符串或 blob，而不是 {}", content.type_name()).into())
+}
+
+// 注册存储接口，写入和删除记录到 actions
+fn register_api(engine: &mut Engine, name: &str, actions: &Arc<Mutex<Vec<Action>>>) {
+    let script = name.to_string();
+    engine.on_print(move |msg| info!("脚本 {}: {}", script, msg));
+    let script = name.to_string();
+    engine.register_fn("log", move |msg: &str| info!("脚本 {}: {}", script, msg));
+    engine.register_fn(
+        "get_object",
+        |bucket: &str, key: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = meta_path(bucket, key)?;
+            if !path.exists() {
+                return Ok(Dynamic::UNIT);
+            }
+            let data = read_object(&path)
+                .map_err(|err| format!("读取对象 {}/{} 失败: {:#}", bucket, key, err))?;
+            if data.len() > MAX_STRING_SIZE {
+                return Err(format!("对象 {}/{} 过大", bucket, key).into());
+            }
+            Ok(match String::from_utf8(data) {
+                Ok(text) => text.into(),
+                Err(err) => Dynamic::from_blob(err.into_bytes()),
+            })
+        },
+    );
+    engine.register_fn(
+        "object_exists",
+        |bucket: &str, key: &str| -> Result<bool, Box<EvalAltResult>> {
+            Ok(meta_path(bucket, key)?.exists())
+        },
+    );
+    engine.register_fn("list_objects", list_keys);
+
+    let push = {
+        let actions = actions.clone();
+        move |action: Action| -> Result<(), Box<EvalAltResult>> {
+            let mut actions = actions.lock().unwrap();
+            if actions.len() >= MAX_ACTIONS {
+                return Err(format!("单次执行最多改动 {} 个对象", MAX_ACTIONS).into());
+            }
+            actions.push(action);
+            Ok(())
+        }
+    };
+    let put = {
+        let push = push.clone();
+        move |bucket: &str, key: &str, content: Dynamic, content_type: Option<String>| {
+            let path = meta_path(bucket, key)?;
+            if path.exists() && config::get().is_denied(bucket, RestrictedOperation::Overwrite) {
+                return Err(format!(
+                    "桶 {} 禁止{}",
+                    bucket,
+                    RestrictedOperation::Overwrite.describe()
+                )
+                .into());
+            }
+            push(Action::Put {
+                meta_file_path: path.to_string_lossy().to_string(),
+                body: object_content(content)?,
+                content_type,
+            })
+        }
+    };
+    {
+        let put = put.clone();
+        engine.register_fn(
+            "put_object",
+            move |bucket: &str, key: &str, content: Dynamic| put(bucket, key, content, None),
+        );
+    }
+    engine.register_fn(
+        "put_object",
+        move |bucket: &str, key: &str, content: Dynamic, content_type: &str| {
+            put(bucket, key, content, Some(content_type.to_string()))
+        },
+    );
+    engine.register_fn(
+        "delete_object",
+        move |bucket: &str, key: &str| -> Result<(), Box<EvalAltResult>> {
+            let path = meta_path(bucket, key)?;
+            if config::get().is_denied(bucket, RestrictedOperation::Delete) {
+                return Err(format!(
+                    "桶 {} 禁止{}",
+                    bucket,
+                    RestrictedOperation::Delete.describe()
+                )
+                .into());
+            }
+            push(Action::Delete {
+                meta_file_path: path.to_string_lossy().to_string(),
+            })
+        },
+    );
+}
+
+// 执行一个脚本，返回其记录的改动
+fn run(script: &Script, event: &CdcEvent) -> anyhow::Result<Vec<Action>> {
+    let actions = Arc::new(Mutex::new(Vec::new()));
+    let mut engine = new_engine();
+    register_api(&mut engine, &script.name, &actions);
+    let event = rhai::serde::to_dynamic(event).map_err(|err| anyhow!("{}", err))?;
+    // 返回值不使用
+    let _ = engine
+        .call_fn::<Dynamic>(&mut Scope::new(), &script.ast, "on_event", (event,))
+        .map_err(|err| anyhow!("{}", err))?;
+    drop(engine);
+    let actions = std::mem::take(&mut *actions.lock().unwrap());
+    Ok(actions)
+}
+
+async fn apply(app: &App, action: Action) -> anyhow::Result<()> {
+    let (meta_file_path, request) = match action {
+        Action::Put {
+            meta_file_path,
+            body,
+            content_type,
+        } => (
+            meta_file_path.clone(),
+            Request::UploadFileV2 {
+                file_path: meta_file_path,
+                body,
+                attrs: ObjectAttrs {
+                    content_type,
+                    ..Default::default()
+                },
+            },
+        ),
+        Action::Delete { meta_file_path } => (
+            meta_file_path.clone(),
+            Request::DeleteFileV2 {
+                file_path: meta_file_path,
+                version_id: None,
+                if_match: None,
+                if_none_match: false,
+            },
+        ),
+    };
+    let object_path = fs::object_path_from_meta(&meta_file_path).context("解析对象路径失败")?;
+    WRITING.lock().unwrap().insert(object_path.clone());
+    let res = app.client_write(request).await;
+    WRITING.lock().unwrap().remove(&object_path);
+    res.map(|_| ())
+}
+
+// 在主节点上为对象事件执行匹配的脚本，失败只记录日志
+pub(crate) fn emit(event: &CdcEvent) {
+    let script_event = match event.op {
+        Op::Put => ScriptEvent::Put,
+        Op::Delete => ScriptEvent::Delete,
+        Op::CreateBucket | Op::DeleteBucket => return,
+    };
+    let Some(key) = &event.key else {
+        return;
+    };
+    let object_path = format!("{}/{}", event.bucket, key);
+    let targets: Vec<&'static Script> = scripts()
+        .iter()
+        .filter(|s| s.config.applies_to(script_event, &object_path))
+        .collect();
+    if targets.is_empty() || WRITING.lock().unwrap().contains(&object_path) {
+        return;
+    }
+    let Some((app, handle)) = RUNTIME.get() else {
+        return;
+    };
+    // 各节点都会应用日志，只在主节点上执行一次
+    if standby::is_passive() || app.raft.metrics().borrow().current_leader != Some(app.id) {
+        return;
+    }
+    let event = event.clone();
+    handle.spawn(async move {
+        for script in targets {
+            let res = {
+                let event = event.clone();
+                pool::run(move || run(script, &event)).await
+            };
+            let actions = match res.and_then(|res| res) {
+                Ok(actions) => actions,
+                Err(err) => {
+                    warn!("脚本 {} 处理 {} 失败: {:#}", script.name, object_path, err);
+                    continue;
+                }
+            };
+            for action in actions {
+                if let Err(err) = apply(app, action).await {
+                    warn!("脚本 {} 提交改动失败: {:#}", script.name, err);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,313 @@
+use crate::api::DATA_DIR;
+use crate::client::S3Client;
+use crate::config;
+use crate::erasure;
+use crate::err::AppError;
+use crate::fs;
+use crate::gc;
+use crate::HandlerResponse;
+use anyhow::Context;
+use log::warn;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::Query;
+use ntex::web::HttpResponse;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// --- 分片巡检：重新读取本节点数据目录中的每个分片文件，副本解压后与路径中的 SHA-256 比较，
+// 纠删码块校验头部和校验和。损坏的文件移到数据目录下的 quarantine 目录，有完好的副本时用它
+// 覆盖，纠删码块用其余的块重建。无法修复的分片和引用它们的对象列在结果中，之后读取这些对象
+// 返回错误而不是损坏的数据；重新上传相同的内容会重新写入分片。修改时间在宽限期内的文件跳过，
+// 覆盖正在写入的分片。通过 POST /admin/scrub 或 scrub 子命令手动执行，或用
+// --scrub-interval-hours 定期执行
+
+// 宽限期，修改时间在这之内的分片文件不校验
+const GRACE_PERIOD: Duration = Duration::from_secs(600);
+
+// 隔离目录，位于分片目录之外，垃圾回收不会处理其中的文件
+const QUARANTINE_DIR: &str = "quarantine";
+
+// 同一时间只执行一次巡检
+static RUNNING: Mutex<()> = Mutex::new(());
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ScrubOpt {
+    /// Server address, without the `/api` prefix
+    #[clap(long, default_value_t = String::from("http://127.0.0.1:9000"))]
+    pub endpoint: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub access_key: String,
+
+    #[clap(long, default_value_t = String::from("minioadmin"))]
+    pub secret_key: String,
+
+    /// Only report corrupted chunks, without quarantining or repairing them
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/scrub", web::post().to(scrub_chunks));
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ScrubReport {
+    pub dry_run: bool,
+    // 校验的分片文件数和大小（每个副本或纠删码块各算一个）
+    pub scanned_files: u64,
+    pub scanned_bytes: u64,
+    // 仍在宽限期内、跳过的文件
+    pub recent_files: u64,
+    // 损坏或读取失败的文件，及其中已移入隔离目录、已修复的文件
+    pub corrupted_files: u64,
+    pub quarantined_files: u64,
+    pub repaired_files: u64,
+    // 无法修复的分片，以及引用它们的对象（"桶/键"，历史版本和暂存批次为元数据的相对路径）
+    pub lost_chunks: Vec<String>,
+    pub affected_objects: Vec<String>,
+    pub duration_ms: u64,
+}
+
+// 分片文件是否完好：纠删码块校验头部和校验和，副本解压后与哈希比较
+pub fn is_intact_file(hash: &str, is_shard: bool, content: Vec<u8>) -> bool {
+    if is_shard {
+        erasure::is_shard_file_intact(content)
+    } else {
+        fs::is_intact_chunk(hash, Ok(content))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ScrubQuery {
+    // 只统计损坏的分片，不隔离也不修复
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// 执行一次巡检，只处理本节点的分片文件
+pub async fn scrub_chunks(Query(query): Query<ScrubQuery>) -> HandlerResponse {
+    let report = tokio::task::spawn_blocking(move || scrub(query.dry_run))
+        .await
+        .context("分片巡检失败")??;
+    match report {
+        Some(report) => Ok(HttpResponse::Ok().json(&report)),
+        None => Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "OperationAborted",
+            "A scrub is already running",
+        )),
+    }
+}
+
+// 开启 --scrub-interval-hours 时定期执行巡检
+pub(crate) fn spawn() {
+    let Some(hours) = config::get().scrub_interval_hours else {
+        return;
+    };
+    std::thread::Builder::new()
+        .name("chunk-scrub".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(hours * 3600));
+            if let Err(err) = scrub(false) {
+                warn!("分片巡检失败: {:#}", err);
+            }
+        })
+        .expect("启动分片巡检线程失败");
+}
+
+// 已有巡检在执行时返回空
+fn scrub(dry_run: bool) -> anyhow::Result<Option<ScrubReport>> {
+    let Ok(_running) = RUNNING.try_lock() else {
+        return Ok(None);
+    };
+    let start = Instant::now();
+    let mut report = ScrubReport {
+        dry_run,
+        ..Default::default()
+    };
+    let quarantine_root = PathBuf::from(DATA_DIR.get().unwrap()).join(QUARANTINE_DIR);
+    let mut lost = BTreeSet::new();
+    // 各数据目录中相对路径相同，隔离时按数据目录的序号区分
+    for (i, root) in fs::chunk_root_dirs().iter().enumerate() {
+        let quarantine = quarantine_root.join(i.to_string());
+        check_dir(root, root, &quarantine, &mut report, &mut lost);
+    }
+    if !lost.is_empty() {
+        report.affected_objects = affected_objects(&lost)?;
+    }
+    report.lost_chunks = lost.into_iter().collect();
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    if report.corrupted_files > 0 {
+        warn!(
+            "分片巡检{}：{} 个损坏的分片文件，已修复 {} 个，{} 个分片无法修复",
+            if dry_run { "（试运行）" } else { "" },
+            report.corrupted_files,
+            report.repaired_files,
+            report.lost_chunks.len()
+        );
+    }
+    Ok(Some(report))
+}
+
+// 校验目录下的分片文件，隔离并修复损坏的文件
+fn check_dir(
+    root: &Path,
+    dir: &Path,
+    quarantine: &Path,
+    report: &mut ScrubReport,
+    lost: &mut BTreeSet<String>,
+) {
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            check_dir(root, &path, quarantine, report, lost);
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let Some(hash) = gc::chunk_hash_from_path(relative) else {
+            continue;
+        };
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if !gc::is_past_grace(metadata.modified().ok(), SystemTime::now(), GRACE_PERIOD) {
+            report.recent_files += 1;
+            continue;
+        }
+        report.scanned_files += 1;
+        report.scanned_bytes += metadata.len();
+        let is_shard = path.extension().is_some_and(|ext| ext == "ec");
+        let intact = match fs::read_uncached(&path) {
+            Ok(content) => is_intact_file(&hash, is_shard, content),
+            // 校验期间被垃圾回收删除
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                warn!("读取分片文件 {:?} 失败: {}", path, err);
+                false
+            }
+        };
+        if intact {
+            continue;
+        }
+        report.corrupted_files += 1;
+        warn!("分片文件 {:?} 已损坏", path);
+        let good = intact_replica(&hash, is_shard, &path);
+        if report.dry_run {
+            if good.is_none() && !(is_shard && can_rebuild(&hash, &path)) {
+                lost.insert(hash);
+            }
+            continue;
+        }
+        // 与写入时的去重判断互斥，隔离到修复完成前分片不会被新对象引用
+        let _guard = fs::CHUNK_GC_LOCK.lock().unwrap();
+        if let Err(err) = move_file(&path, &quarantine.join(relative)) {
+            warn!("隔离分片文件 {:?} 失败: {}", path, err);
+            continue;
+        }
+        report.quarantined_files += 1;
+        let repaired = match good {
+            Some(good) => {
+                fs::repair_chunk(&good, std::slice::from_ref(&path));
+                path.exists()
+            }
+            // 读取时会用其余的块重建缺失的块
+            None if is_shard => erasure::read(&hash).is_ok(),
+            None => false,
+        };
+        if repaired {
+            report.repaired_files += 1;
+        } else {
+            lost.insert(hash);
+        }
+    }
+}
+
+// 分片在本节点的其他完好副本，纠删码块没有副本
+fn intact_replica(hash: &str, is_shard: bool, broken: &Path) -> Option<PathBuf> {
+    if is_shard {
+        return None;
+    }
+    fs::chunk_paths(hash).into_iter().find(|path| {
+        path != broken && fs::read_uncached(path).is_ok_and(|c| is_intact_file(hash, false, c))
+    })
+}
+
+// 除损坏的块外，完好的纠删码块是否足够重建分片
+fn can_rebuild(hash: &str, broken: &Path) -> bool {
+    let Some((k, _)) = config::get().erasure() else {
+        return false;
+    };
+    let intact = erasure::shard_paths(hash)
+        .iter()
+        .filter(|path| *path != broken)
+        .filter(|path| std::fs::read(path).is_ok_and(erasure::is_shard_file_intact))
+        .count();
+    intact >= k
+}
+
+// 移动文件，不在同一文件系统时复制后删除
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to.parent().unwrap())?;
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+// 引用了无法修复的分片的对象
+fn affected_objects(lost: &BTreeSet<String>) -> anyhow::Result<Vec<String>> {
+    let data_dir = PathBuf::from(DATA_DIR.get().unwrap());
+    let mut objects = vec![];
+    for meta_file in gc::metadata_files()? {
+        let metadata = match fs::load_metadata(&meta_file) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!("读取元数据 {:?} 失败: {:#}", meta_file, err);
+                continue;
+            }
+        };
+        if !metadata.chunks.iter().any(|chunk| lost.contains(chunk)) {
+            continue;
+        }
+        let object = fs::object_path_from_meta(&meta_file).unwrap_or_else(|| {
+            let relative = meta_file.strip_prefix(&data_dir).unwrap_or(&meta_file);
+            relative.to_string_lossy().into_owned()
+        });
+        objects.push(object);
+    }
+    objects.sort();
+    Ok(objects)
+}
+
+// scrub 子命令：请求运行中的服务执行巡检并输出结果
+pub async fn run(opt: ScrubOpt) -> anyhow::Result<()> {
+    let client = S3Client::new(&opt.endpoint, &opt.access_key, &opt.secret_key);
+    let dry_run = opt.dry_run.to_string();
+    let resp = client
+        .send(
+            Method::POST,
+            "admin/scrub",
+            &[("dry_run", &dry_run)],
+            &[],
+            Vec::new(),
+        )
+        .await?;
+    let status = resp.status();
+    let body = resp.text().await.context("读取响应失败")?;
+    if !status.is_success() {
+        anyhow::bail!("分片巡检失败: HTTP {} {}", status.as_u16(), body);
+    }
+    let report: serde_json::Value = serde_json::from_str(&body).context("解析响应失败")?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
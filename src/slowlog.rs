@@ -0,0 +1,402 @@
+use crate::config;
+use crate::fs::Backend;
+use crate::statsd;
+use log::warn;
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web;
+use percent_encoding::percent_decode_str;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+// --- 慢请求日志：配置 --slow-request-ms 后统计每个请求在计算 sha256、压缩、解压、读写磁盘和
+// 等待 raft 写入上的耗时，总耗时（含响应体的发送）超过阈值的请求以 target=slow 记录一行详情，
+// 配置 --log-dir 时写入 slow.log。后台线程池中的任务沿用提交任务的请求的统计；
+// 写入在 raft 应用日志时完成，按日志序号暂存应用阶段的耗时，由发起写入的请求取回。
+// 配置 --debug-headers 时同样统计，并在响应头中返回：Server-Timing 为发送响应头之前各阶段的耗时
+// （GET 的响应体在之后读取和解压，不包含在内），x-amz-storage-backend 为对象的存储后端，
+// x-rs3-dedup-hits / x-rs3-dedup-misses 为写入时已存在（直接引用）和新写入的分片数
+
+// 暂存的应用阶段耗时条数上限，其他节点发起的写入不会被取回
+const MAX_APPLIED: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Hash,
+    Compress,
+    Decompress,
+    Disk,
+    Raft,
+    // 签名认证，在统计开始之前完成，由认证中间件记录在请求扩展中
+    Auth,
+    Metadata,
+}
+
+impl Phase {
+    const ALL: [Phase; 7] = [
+        Phase::Hash,
+        Phase::Compress,
+        Phase::Decompress,
+        Phase::Disk,
+        Phase::Raft,
+        Phase::Auth,
+        Phase::Metadata,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Hash => "hash",
+            Phase::Compress => "compress",
+            Phase::Decompress => "decompress",
+            Phase::Disk => "disk",
+            Phase::Raft => "raft",
+            Phase::Auth => "auth",
+            Phase::Metadata => "metadata",
+        }
+    }
+}
+
+// 各阶段累计的耗时，以及调试响应头需要的分片去重命中数和对象的存储后端
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    phases: [Duration; 7],
+    pub dedup_hits: u64,
+    pub dedup_misses: u64,
+    pub backend: Option<Backend>,
+}
+
+impl Timings {
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        self.phases[phase as usize] += elapsed;
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.phases[phase as usize]
+    }
+
+    pub fn merge(&mut self, other: &Timings) {
+        for phase in Phase::ALL {
+            self.add(phase, other.get(phase));
+        }
+        self.dedup_hits += other.dedup_hits;
+        self.dedup_misses += other.dedup_misses;
+        self.backend = self.backend.or(other.backend);
+    }
+}
+
+// 认证耗时，由认证中间件放入请求扩展
+#[derive(Debug, Clone, Copy)]
+pub struct AuthElapsed(pub Duration);
+
+// Server-Timing 响应头，耗时单位为毫秒，total 含认证
+pub fn server_timing(timings: &Timings, total: Duration) -> String {
+    let dur = |d: Duration| format!("{:.3}", d.as_secs_f64() * 1000.0);
+    let mut items: Vec<String> = Phase::ALL
+        .iter()
+        .map(|phase| format!("{};dur={}", phase.name(), dur(timings.get(*phase))))
+        .collect();
+    items.push(format!("total;dur={}", dur(total)));
+    items.join(", ")
+}
+
+// 慢请求日志的一条记录
+#[derive(Debug)]
+pub struct SlowRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub api: Option<&'a str>,
+    pub status: u16,
+    pub duration: Duration,
+    // 请求头中的 Content-Length，没有时为空
+    pub request_bytes: Option<u64>,
+    pub response_bytes: u64,
+    pub timings: &'a Timings,
+}
+
+// 慢请求日志的内容，各项为 key=value
+pub fn format_entry(req: &SlowRequest) -> String {
+    let mut line = format!("method={} path={}", req.method, req.path);
+    if let Some(api) = req.api {
+        line.push_str(&format!(" api={}", api));
+    }
+    line.push_str(&format!(
+        " status={} duration_ms={}",
+        req.status,
+        req.duration.as_millis()
+    ));
+    if let Some(bytes) = req.request_bytes {
+        line.push_str(&format!(" request_bytes={}", bytes));
+    }
+    line.push_str(&format!(" response_bytes={}", req.response_bytes));
+    for phase in Phase::ALL {
+        line.push_str(&format!(
+            " {}_ms={}",
+            phase.name(),
+            req.timings.get(phase).as_millis()
+        ));
+    }
+    line
+}
+
+type Handle = Arc<Mutex<Timings>>;
+
+tokio::task_local! {
+    // 当前请求的耗时统计
+    static CURRENT: Handle;
+}
+
+// 应用日志时统计的耗时：日志序号 -> 耗时
+static APPLIED: Mutex<BTreeMap<u64, Timings>> = Mutex::new(BTreeMap::new());
+
+// 是否统计请求耗时
+fn enabled() -> bool {
+    let cfg = config::get();
+    cfg.slow_request_threshold.is_some() || cfg.debug_headers
+}
+
+// 当前请求的耗时统计，没有在统计时为空
+pub(crate) fn current() -> Option<Handle> {
+    CURRENT.try_with(|handle| handle.clone()).ok()
+}
+
+// 在给定的统计下执行，用于后台线程池中的任务
+pub(crate) fn with<T>(handle: Option<Handle>, f: impl FnOnce() -> T) -> T {
+    match handle {
+        Some(handle) => CURRENT.sync_scope(handle, f),
+        None => f(),
+    }
+}
+
+// 把一段耗时计入当前请求
+pub(crate) fn record(phase: Phase, elapsed: Duration) {
+    let _ = CURRENT.try_with(|handle| handle.lock().unwrap().add(phase, elapsed));
+}
+
+// 执行并把耗时计入当前请求
+pub(crate) fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if CURRENT.try_with(|_| ()).is_err() {
+        return f();
+    }
+    let start = Instant::now();
+    let res = f();
+    record(phase, start.elapsed());
+    res
+}
+
+// 记录一次写入分片时的去重结果
+pub(crate) fn count_dedup(hit: bool) {
+    let _ = CURRENT.try_with(|handle| {
+        let mut timings = handle.lock().unwrap();
+        if hit {
+            timings.dedup_hits += 1;
+        } else {
+            timings.dedup_misses += 1;
+        }
+    });
+}
+
+// 记录当前请求访问的对象的存储后端
+pub(crate) fn note_backend(backend: Backend) {
+    let _ = CURRENT.try_with(|handle| handle.lock().unwrap().backend = Some(backend));
+}
+
+// 应用序号为 index 的日志，统计其耗时供发起写入的请求取回
+pub(crate) async fn applying<F: Future>(index: u64, fut: F) -> F::Output {
+    if !enabled() {
+        return fut.await;
+    }
+    let handle = Handle::default();
+    let res = CURRENT.scope(handle.clone(), fut).await;
+    let timings = handle.lock().unwrap().clone();
+    let mut applied = APPLIED.lock().unwrap();
+    applied.insert(index, timings);
+    while applied.len() > MAX_APPLIED {
+        applied.pop_first();
+    }
+    res
+}
+
+// 把序号为 index 的日志的应用耗时计入当前请求
+pub(crate) fn merge_applied(index: u64) {
+    let Some(timings) = APPLIED.lock().unwrap().remove(&index) else {
+        return;
+    };
+    let _ = CURRENT.try_with(|handle| handle.lock().unwrap().merge(&timings));
+}
+
+// 统计请求耗时，响应体发送完成后判断是否记录慢请求日志
+pub struct SlowLog;
+
+impl<S> Middleware<S> for SlowLog {
+    type Service = SlowLogMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        SlowLogMiddleware { service }
+    }
+}
+
+pub struct SlowLogMiddleware<S> {
+    service: S,
+}
+
+impl<S, Err> Service<web::WebRequest<Err>> for SlowLogMiddleware<S>
+where
+    S: Service<web::WebRequest<Err>, Response = web::WebResponse, Error = web::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = web::WebResponse;
+    type Error = web::Error;
+
+    ntex::forward_poll_ready!(service);
+
+    async fn call(
+        &self,
+        req: web::WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !enabled() {
+            return ctx.call(&self.service, req).await;
+        }
+        let cfg = config::get();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let api = statsd::api_of(
+            &method,
+            &path,
+            req.query_string(),
+            req.headers().contains_key("x-amz-copy-source"),
+        );
+        let request_bytes = req
+            .headers()
+            .get("Content-Length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let start = Instant::now();
+        let handle = Handle::default();
+        if let Some(auth) = req.extensions().get::<AuthElapsed>() {
+            handle.lock().unwrap().add(Phase::Auth, auth.0);
+        }
+        let mut res = CURRENT
+            .scope(handle.clone(), ctx.call(&self.service, req))
+            .await?;
+        if cfg.debug_headers {
+            let timings = handle.lock().unwrap();
+            let total = start.elapsed() + timings.get(Phase::Auth);
+            add_debug_headers(&mut res, &timings, &path, total);
+        }
+        let Some(threshold) = cfg.slow_request_threshold else {
+            return Ok(res);
+        };
+        let status = res.status().as_u16();
+        Ok(res.map_body(move |_, body| {
+            ResponseBody::Other(Body::from_message(TimedBody {
+                body,
+                handle,
+                start,
+                threshold,
+                method,
+                path,
+                api,
+                status,
+                request_bytes,
+                response_bytes: 0,
+            }))
+        }))
+    }
+}
+
+// 对象请求的存储后端：读取对象时为元数据中记录的后端，否则为对象路径按 --storage-route 选择的后端
+fn request_backend(timings: &Timings, path: &str) -> Option<Backend> {
+    if timings.backend.is_some() {
+        return timings.backend;
+    }
+    let object_path = path.strip_prefix("/api/")?;
+    let object_path = percent_decode_str(object_path).decode_utf8_lossy();
+    let (_, key) = object_path.split_once('/')?;
+    if key.is_empty() {
+        return None;
+    }
+    Some(config::get().backend_for(&object_path))
+}
+
+fn add_debug_headers(res: &mut web::WebResponse, timings: &Timings, path: &str, total: Duration) {
+    let mut headers = vec![("server-timing", server_timing(timings, total))];
+    if let Some(backend) = request_backend(timings, path) {
+        let backend = match backend {
+            Backend::Dedup => "dedup",
+            Backend::Passthrough => "passthrough",
+        };
+        headers.push(("x-amz-storage-backend", backend.to_string()));
+    }
+    if timings.dedup_hits + timings.dedup_misses > 0 {
+        headers.push(("x-rs3-dedup-hits", timings.dedup_hits.to_string()));
+        headers.push(("x-rs3-dedup-misses", timings.dedup_misses.to_string()));
+    }
+    for (name, value) in headers {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            res.headers_mut()
+                .insert(HeaderName::from_static(name), value);
+        }
+    }
+}
+
+// 在请求的统计下发送响应体，发送完成（或连接断开）时记录慢请求
+struct TimedBody {
+    body: ResponseBody<Body>,
+    handle: Handle,
+    start: Instant,
+    threshold: Duration,
+    method: String,
+    path: String,
+    api: Option<&'static str>,
+    status: u16,
+    request_bytes: Option<u64>,
+    response_bytes: u64,
+}
+
+impl Drop for TimedBody {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        if duration < self.threshold {
+            return;
+        }
+        let timings = self.handle.lock().unwrap().clone();
+        warn!(
+            target: "slow",
+            "{}",
+            format_entry(&SlowRequest {
+                method: &self.method,
+                path: &self.path,
+                api: self.api,
+                status: self.status,
+                duration,
+                request_bytes: self.request_bytes,
+                response_bytes: self.response_bytes,
+                timings: &timings,
+            })
+        );
+    }
+}
+
+impl MessageBody for TimedBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn std::error::Error>>>> {
+        let body = &mut self.body;
+        let res = CURRENT.sync_scope(self.handle.clone(), || body.poll_next_chunk(cx));
+        if let Poll::Ready(Some(Ok(chunk))) = &res {
+            self.response_bytes += chunk.len() as u64;
+        }
+        res
+    }
+}
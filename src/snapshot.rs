@@ -0,0 +1,402 @@
+use crate::access;
+use crate::api::{is_valid_bucket_name, BASIC_PATH_SUFFIX, DATA_DIR};
+use crate::durability;
+use crate::err::AppError;
+use crate::fs::{self, Backend};
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::versioning;
+use crate::HandlerResponse;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use log::info;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::types::{Query, State};
+use ntex::web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// --- 桶快照：POST /admin/buckets/{bucket}/snapshots?name=快照名 记录桶内全部当前对象的元数据
+// （对象清单），分片通过去重共享，不复制内容；直通存储的对象复制原文件。快照只读，不能通过 S3
+// 接口访问。POST .../snapshots/{name}/restore 把桶恢复到快照时的对象集合：快照之后新增的对象被删除，
+// 修改或删除的对象恢复为快照中的元数据，适合在测试之间快速重置数据。
+// 快照保存在数据目录的 snapshots/桶/快照名 下，元数据按主密钥加密（与历史版本相同）；只记录对象，
+// 不含桶配置；开启过版本控制的桶不支持快照。删除桶时一并删除它的快照，分片由垃圾回收清理
+
+// 快照信息文件，最后写入，存在时快照才完整
+const INFO_FILE: &str = ".snapshot.json";
+// 快照中的元数据目录
+const OBJECTS_DIR: &str = "objects";
+// 快照中直通存储对象的原文件目录
+const RAW_DIR: &str = "raw";
+// 快照名的最大长度
+const MAX_NAME_LEN: usize = 64;
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/admin/buckets/{bucket}/snapshots",
+        web::post().to(create_snapshot),
+    )
+    .route(
+        "/admin/buckets/{bucket}/snapshots",
+        web::get().to(list_snapshots),
+    )
+    .route(
+        "/admin/buckets/{bucket}/snapshots/{name}",
+        web::delete().to(delete_snapshot),
+    )
+    .route(
+        "/admin/buckets/{bucket}/snapshots/{name}/restore",
+        web::post().to(restore_snapshot),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotQuery {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub created: DateTime<Utc>,
+    pub objects: u64,
+    // 对象原始大小之和，不占用额外的分片空间
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub bucket: String,
+    pub snapshot: String,
+    // 从快照写回的对象数
+    pub restored: u64,
+    // 快照之后新增、恢复时删除的对象数
+    pub removed: u64,
+}
+
+// 快照名只能由字母、数字、'.'、'_' 和 '-' 组成，不能以 '.' 开头
+pub fn is_valid_snapshot_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn buckets_dir() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(BASIC_PATH_SUFFIX)
+}
+
+// 全部快照的根目录
+pub(crate) fn snapshots_root() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join("snapshots")
+}
+
+// 桶的快照目录
+pub(crate) fn bucket_snapshots_dir(bucket: &str) -> PathBuf {
+    snapshots_root().join(bucket)
+}
+
+fn snapshot_dir(bucket: &str, name: &str) -> PathBuf {
+    bucket_snapshots_dir(bucket).join(name)
+}
+
+// 全部快照的元数据目录，垃圾回收时保留其中引用的分片
+pub(crate) fn metadata_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    for bucket in std::fs::read_dir(snapshots_root())
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        for snapshot in std::fs::read_dir(bucket.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let objects = snapshot.path().join(OBJECTS_DIR);
+            if objects.is_dir() {
+                dirs.push(objects);
+            }
+        }
+    }
+    dirs
+}
+
+// 读取快照信息，快照不存在或不完整时返回 None
+pub(crate) fn load_info(bucket: &str, name: &str) -> Option<SnapshotInfo> {
+    let bytes = std::fs::read(snapshot_dir(bucket, name).join(INFO_FILE)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+// 桶的全部快照，按创建时间排序
+pub(crate) fn list(bucket: &str) -> Vec<SnapshotInfo> {
+    let mut snapshots: Vec<SnapshotInfo> = std::fs::read_dir(bucket_snapshots_dir(bucket))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| load_info(bucket, entry.file_name().to_str()?))
+        .collect();
+    snapshots.sort_by(|a, b| (a.created, &a.name).cmp(&(b.created, &b.name)));
+    snapshots
+}
+
+fn path_param(req: &web::HttpRequest, name: &str) -> Result<String, AppError> {
+    Ok(req
+        .match_info()
+        .get(name)
+        .with_context(|| format!("缺少参数 {}", name))?
+        .to_string())
+}
+
+// 桶名参数，不是合法桶名（如 ".."）时视为不存在，避免拼接出快照目录之外的路径
+fn bucket_param(req: &web::HttpRequest) -> Result<String, AppError> {
+    let bucket = path_param(req, "bucket")?;
+    if !is_valid_bucket_name(&bucket) {
+        return Err(no_such_bucket());
+    }
+    Ok(bucket)
+}
+
+fn no_such_bucket() -> AppError {
+    AppError::s3(
+        StatusCode::NOT_FOUND,
+        "NoSuchBucket",
+        "The specified bucket does not exist",
+    )
+}
+
+fn no_such_snapshot() -> AppError {
+    AppError::s3(
+        StatusCode::NOT_FOUND,
+        "NoSuchSnapshot",
+        "The specified snapshot does not exist",
+    )
+}
+
+// 检查桶存在且未开启过版本控制
+fn check_bucket(bucket: &str) -> Result<(), AppError> {
+    if !buckets_dir().join(bucket).is_dir() {
+        return Err(no_such_bucket());
+    }
+    if versioning::status(bucket).is_some() {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "InvalidBucketState",
+            "Snapshots are not supported for buckets with versioning",
+        ));
+    }
+    Ok(())
+}
+
+pub async fn create_snapshot(
+    req: web::HttpRequest,
+    Query(query): Query<SnapshotQuery>,
+    state: State<App>,
+) -> HandlerResponse {
+    let bucket = bucket_param(&req)?;
+    let Some(name) = query.name.filter(|name| is_valid_snapshot_name(name)) else {
+        return Err(AppError::s3(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "Missing or invalid snapshot name",
+        ));
+    };
+    check_bucket(&bucket)?;
+    if snapshot_dir(&bucket, &name).exists() {
+        return Err(AppError::s3(
+            StatusCode::CONFLICT,
+            "SnapshotAlreadyExists",
+            "A snapshot with this name already exists",
+        ));
+    }
+    let res = state
+        .client_write(Request::CreateSnapshot {
+            bucket_name: bucket.clone(),
+            name: name.clone(),
+            created: Utc::now(),
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let info: SnapshotInfo = res
+        .data
+        .value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .with_context(|| format!("创建桶 {} 的快照 {} 失败", bucket, name))?;
+    Ok(HttpResponse::Ok().json(&info))
+}
+
+pub async fn list_snapshots(req: web::HttpRequest) -> HandlerResponse {
+    let bucket = bucket_param(&req)?;
+    if !buckets_dir().join(&bucket).is_dir() {
+        return Err(no_such_bucket());
+    }
+    Ok(HttpResponse::Ok().json(&list(&bucket)))
+}
+
+pub async fn delete_snapshot(req: web::HttpRequest, state: State<App>) -> HandlerResponse {
+    let bucket = bucket_param(&req)?;
+    let name = path_param(&req, "name")?;
+    if !is_valid_snapshot_name(&name) || !snapshot_dir(&bucket, &name).exists() {
+        return Err(no_such_snapshot());
+    }
+    state
+        .client_write(Request::DeleteSnapshot {
+            bucket_name: bucket,
+            name,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn restore_snapshot(req: web::HttpRequest, state: State<App>) -> HandlerResponse {
+    let bucket = bucket_param(&req)?;
+    let name = path_param(&req, "name")?;
+    check_bucket(&bucket)?;
+    if !is_valid_snapshot_name(&name) || load_info(&bucket, &name).is_none() {
+        return Err(no_such_snapshot());
+    }
+    let res = state
+        .client_write(Request::RestoreSnapshot {
+            bucket_name: bucket.clone(),
+            name: name.clone(),
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let summary: RestoreSummary = res
+        .data
+        .value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .with_context(|| format!("恢复桶 {} 到快照 {} 失败", bucket, name))?;
+    Ok(HttpResponse::Ok().json(&summary))
+}
+
+// 对象键对应的直通存储原文件
+fn raw_object_path(bucket: &str, relative_meta: &Path) -> PathBuf {
+    let key = relative_meta.with_extension("");
+    fs::raw_path(&format!("{}/{}", bucket, key.to_string_lossy()))
+}
+
+// 状态机中创建快照，失败时删除不完整的快照
+pub(crate) fn create(
+    bucket: &str,
+    name: &str,
+    created: DateTime<Utc>,
+) -> anyhow::Result<SnapshotInfo> {
+    let dir = snapshot_dir(bucket, name);
+    if dir.exists() {
+        return Err(anyhow!("快照 {} 已存在", name));
+    }
+    let res = capture(bucket, name, created, &dir);
+    if res.is_err() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    res
+}
+
+fn capture(
+    bucket: &str,
+    name: &str,
+    created: DateTime<Utc>,
+    dir: &Path,
+) -> anyhow::Result<SnapshotInfo> {
+    let bucket_dir = buckets_dir().join(bucket);
+    let mut meta_files = Vec::new();
+    fs::walk_meta_files(&bucket_dir, &mut meta_files).context("遍历桶目录失败")?;
+    let mut info = SnapshotInfo {
+        name: name.to_string(),
+        created,
+        ..Default::default()
+    };
+    for path in meta_files {
+        let relative = path.strip_prefix(&bucket_dir)?;
+        let metadata = fs::load_metadata(&path)?;
+        if metadata.delete_marker {
+            continue;
+        }
+        if metadata.backend == Backend::Passthrough {
+            let dest = dir.join(RAW_DIR).join(relative.with_extension(""));
+            std::fs::create_dir_all(dest.parent().unwrap())?;
+            std::fs::copy(raw_object_path(bucket, relative), &dest).context("复制文件失败")?;
+            durability::enqueue(dest);
+        }
+        fs::save_metadata(dir.join(OBJECTS_DIR).join(relative), &metadata)?;
+        info.objects += 1;
+        info.bytes += metadata.size;
+    }
+    std::fs::create_dir_all(dir)?;
+    let info_path = dir.join(INFO_FILE);
+    std::fs::write(&info_path, serde_json::to_vec(&info)?)?;
+    durability::enqueue(info_path);
+    info!("创建桶 {} 的快照 {}：{} 个对象", bucket, name, info.objects);
+    Ok(info)
+}
+
+// 状态机中把桶恢复到快照：先删除快照中没有的对象，再写回快照中的全部对象
+pub(crate) fn restore(bucket: &str, name: &str) -> anyhow::Result<RestoreSummary> {
+    if load_info(bucket, name).is_none() {
+        return Err(anyhow!("快照 {} 不存在", name));
+    }
+    let bucket_dir = buckets_dir().join(bucket);
+    let dir = snapshot_dir(bucket, name);
+    let objects_dir = dir.join(OBJECTS_DIR);
+    let mut snapshot_files = Vec::new();
+    if objects_dir.is_dir() {
+        fs::walk_meta_files(&objects_dir, &mut snapshot_files).context("遍历快照目录失败")?;
+    }
+    let mut relatives = HashSet::new();
+    for path in &snapshot_files {
+        relatives.insert(path.strip_prefix(&objects_dir)?.to_path_buf());
+    }
+    let mut summary = RestoreSummary {
+        bucket: bucket.to_string(),
+        snapshot: name.to_string(),
+        ..Default::default()
+    };
+    let mut current = Vec::new();
+    fs::walk_meta_files(&bucket_dir, &mut current).context("遍历桶目录失败")?;
+    for path in current {
+        let relative = path.strip_prefix(&bucket_dir)?;
+        if relatives.contains(relative) {
+            continue;
+        }
+        let metadata = fs::load_metadata(&path)?;
+        std::fs::remove_file(&path).context("删除文件失败")?;
+        if let Err(err) = access::forget(&path.to_string_lossy()) {
+            info!("删除访问记录失败: {}", err);
+        }
+        if metadata.backend == Backend::Passthrough {
+            let _ = std::fs::remove_file(raw_object_path(bucket, relative));
+        }
+        summary.removed += 1;
+    }
+    for path in snapshot_files {
+        let relative = path.strip_prefix(&objects_dir)?;
+        let metadata = fs::load_metadata(&path)?;
+        if metadata.backend == Backend::Passthrough {
+            let dest = raw_object_path(bucket, relative);
+            std::fs::create_dir_all(dest.parent().unwrap())?;
+            std::fs::copy(dir.join(RAW_DIR).join(relative.with_extension("")), &dest)
+                .context("复制文件失败")?;
+            durability::enqueue(dest);
+        }
+        fs::save_metadata(bucket_dir.join(relative), &metadata)?;
+        summary.restored += 1;
+    }
+    info!(
+        "桶 {} 恢复到快照 {}：写回 {} 个对象，删除 {} 个对象",
+        bucket, name, summary.restored, summary.removed
+    );
+    Ok(summary)
+}
+
+// 状态机中删除快照
+pub(crate) fn delete(bucket: &str, name: &str) -> anyhow::Result<()> {
+    std::fs::remove_dir_all(snapshot_dir(bucket, name)).context("删除快照失败")?;
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use crate::codec::Codec;
+use crate::conditional;
+use crate::config;
+use crate::err::AppError;
+use crate::etag;
+use crate::fs;
+use crate::fs::Backend;
+use crate::raft::app::App;
+use crate::raft::store::{ObjectAttrs, Request};
+use crate::sse::ObjectCipher;
+use crate::util;
+use anyhow::{anyhow, Context};
+use futures::StreamExt;
+use hex::ToHex;
+use log::{info, warn};
+use ntex::http::error::PayloadError;
+use ntex::http::StatusCode;
+use ntex::util::Bytes;
+use ntex::web;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// --- 大请求体落盘：上传的请求体超过 --spool-threshold-mb 后，其余部分写入临时文件，
+// 再按分片逐个通过 raft 保存，最后写入元数据，内存中最多只有一个分片。
+// 不需要完整请求体的普通上传不落盘，边接收边按分片保存（见 stream_body）
+
+// 默认的落盘目录在临时目录下的名称
+pub(crate) const SPOOL_DIR_NAME: &str = "spool";
+
+// 落盘的请求体，释放时删除临时文件
+pub(crate) struct SpoolFile {
+    path: PathBuf,
+    pub size: u64,
+    // 请求体的 SHA-256（大写十六进制）
+    pub sha256: String,
+    // 请求体的 MD5，即对象的 ETag
+    pub md5: String,
+    // 请求体开头的内容，用于推断 Content-Type
+    pub head: Vec<u8>,
+}
+
+impl SpoolFile {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 读回完整的请求体，用于只能在内存中处理的上传（如暂存上传）
+    pub(crate) async fn read(&self) -> anyhow::Result<Vec<u8>> {
+        tokio::fs::read(&self.path)
+            .await
+            .context("读取临时文件失败")
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                warn!("删除临时文件 {:?} 失败: {}", self.path, err);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) enum UploadBody {
+    Memory(Vec<u8>),
+    Spooled(SpoolFile),
+}
+
+// 边接收边保存的请求体
+pub(crate) enum StreamedBody {
+    // 不超过一个最大分片的请求体，仍按普通上传处理
+    Small(Vec<u8>),
+    Saved(Box<SavedChunks>),
+}
+
+// 已通过 raft 保存的分片，写入元数据后对象才可见
+pub(crate) struct SavedChunks {
+    pub size: u64,
+    // 请求体的 SHA-256（大写十六进制）
+    pub sha256: String,
+    // 请求体的 MD5，即对象的 ETag
+    pub md5: String,
+    // 请求体开头的内容，用于推断 Content-Type
+    pub head: Vec<u8>,
+    chunks: Vec<String>,
+    chunk_sizes: Vec<u64>,
+    // 服务端加密的对象保存密文
+    cipher: Option<ObjectCipher>,
+    // 请求指定的分片编码
+    codec: Option<Codec>,
+}
+
+impl SavedChunks {
+    fn new(cipher: Option<&ObjectCipher>, codec: Option<Codec>) -> Self {
+        SavedChunks {
+            size: 0,
+            sha256: String::new(),
+            md5: String::new(),
+            head: vec![],
+            chunks: vec![],
+            chunk_sizes: vec![],
+            cipher: cipher.cloned(),
+            codec,
+        }
+    }
+
+    async fn push(&mut self, state: &App, chunk: Vec<u8>) -> anyhow::Result<()> {
+        if self.head.is_empty() {
+            self.head = chunk.clone();
+        }
+        let chunk = match &self.cipher {
+            Some(cipher) => cipher.apply(0, self.size, &chunk)?,
+            None => chunk,
+        };
+        self.size += chunk.len() as u64;
+        self.chunk_sizes.push(chunk.len() as u64);
+        let hash = fs::sum_sha256(&chunk).await;
+        let res = state
+            .client_write(Request::SaveChunkV2 {
+                hash: hash.clone(),
+                body: chunk,
+                codec: self.codec,
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        if let Some(err) = res.data.value {
+            anyhow::bail!("保存分片失败: {}", err);
+        }
+        self.chunks.push(hash);
+        Ok(())
+    }
+}
+
+// 落盘请求体的临时目录
+pub(crate) fn spool_dir() -> PathBuf {
+    config::get()
+        .spool_dir
+        .clone()
+        .unwrap_or_else(|| fs::tmp_root().join(SPOOL_DIR_NAME))
+}
+
+// 请求头中的 Content-Length
+pub(crate) fn declared_length(req: &web::HttpRequest) -> Option<u64> {
+    req.headers()
+        .get("Content-Length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+// 请求体没有按 Content-Length 接收完整（如客户端中途断开），不保存对象
+pub(crate) fn incomplete_body() -> anyhow::Error {
+    AppError::s3(
+        StatusCode::BAD_REQUEST,
+        "IncompleteBody",
+        "You did not provide the number of bytes specified by the Content-Length HTTP header",
+    )
+    .into()
+}
+
+// 接收到的长度与 Content-Length 不一致时返回 IncompleteBody
+pub(crate) fn check_length(declared: Option<u64>, received: u64) -> anyhow::Result<()> {
+    match declared {
+        Some(declared) if declared != received => {
+            warn!(
+                "请求体不完整：Content-Length 为 {}，实际接收 {} 字节",
+                declared, received
+            );
+            Err(incomplete_body())
+        }
+        _ => Ok(()),
+    }
+}
+
+// 读取请求体的下一段，连接中断导致的错误视为请求体不完整
+pub(crate) fn body_item(item: Result<Bytes, PayloadError>) -> anyhow::Result<Bytes> {
+    item.map_err(|err| match err {
+        PayloadError::Incomplete(_) | PayloadError::Io(_) => incomplete_body(),
+        err => anyhow!(err.to_string()),
+    })
+}
+
+// 读取上传的请求体，超过阈值后转为写入临时文件
+pub(crate) async fn read_body(
+    req: &web::HttpRequest,
+    body: &mut web::types::Payload,
+) -> anyhow::Result<UploadBody> {
+    let threshold = config::get().spool_threshold;
+    let mut bytes = Vec::new();
+    bytes.reserve_exact(8 << 20);
+    while let Some(item) = body.next().await {
+        let item = body_item(item)?;
+        bytes.extend_from_slice(&item);
+        if threshold > 0 && bytes.len() as u64 > threshold {
+            let spooled = spool(bytes, body).await?;
+            check_length(declared_length(req), spooled.size)?;
+            return Ok(UploadBody::Spooled(spooled));
+        }
+    }
+    check_length(declared_length(req), bytes.len() as u64)?;
+    Ok(UploadBody::Memory(bytes))
+}
+
+// 读取上传的请求体，超过一个最大分片后每切分出一个分片就通过 raft 保存，内存中最多只有一个最大分片。
+// 请求体不完整时已保存的分片没有元数据引用，由垃圾回收清理（分片可能被其他对象共用，不直接删除）。
+// 给出 cipher 时保存加密后的分片，Small 仍为明文
+pub(crate) async fn stream_body(
+    req: &web::HttpRequest,
+    state: &App,
+    body: &mut web::types::Payload,
+    cipher: Option<&ObjectCipher>,
+    codec: Option<Codec>,
+) -> anyhow::Result<StreamedBody> {
+    let mut hasher = Sha256::new();
+    let mut md5 = etag::Md5::new();
+    let mut saved: Option<SavedChunks> = None;
+    // 切分方式与普通上传一致，保证相同内容得到相同的分片；缓冲的内容超过最大分片后才切分，
+    // 此时总能确定边界
+    let chunker = config::get().chunker();
+    let mut buf = fs::ChunkBuffer::new(chunker);
+    while let Some(item) = body.next().await {
+        buf.extend(&body_item(item)?);
+        while buf.len() > chunker.max_chunk_size() {
+            let Some(chunk) = buf.next_chunk(false) else {
+                break;
+            };
+            hasher.update(&chunk);
+            md5.update(&chunk);
+            let saved = saved.get_or_insert_with(|| SavedChunks::new(cipher, codec));
+            saved.push(state, chunk).await?;
+        }
+    }
+    let received = saved.as_ref().map_or(0, |saved| saved.size) + buf.len() as u64;
+    check_length(declared_length(req), received)?;
+    let Some(mut saved) = saved else {
+        return Ok(StreamedBody::Small(buf.into_inner()));
+    };
+    while let Some(chunk) = buf.next_chunk(true) {
+        hasher.update(&chunk);
+        md5.update(&chunk);
+        saved.push(state, chunk).await?;
+    }
+    saved.sha256 = hasher.finalize().encode_hex_upper();
+    saved.md5 = md5.finish();
+    info!(
+        "边接收边保存请求体：{} 字节，{} 个分片",
+        saved.size,
+        saved.chunks.len()
+    );
+    Ok(StreamedBody::Saved(Box::new(saved)))
+}
+
+async fn spool(received: Vec<u8>, body: &mut web::types::Payload) -> anyhow::Result<SpoolFile> {
+    let dir = spool_dir();
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .context("创建临时目录失败")?;
+    let mut spooled = SpoolFile {
+        path: dir.join(uuid::Uuid::new_v4().to_string()),
+        size: received.len() as u64,
+        sha256: String::new(),
+        md5: String::new(),
+        head: received[..received.len().min(config::get().chunk_bytes())].to_vec(),
+    };
+    info!(
+        "请求体超过 {} 字节，写入 {:?}",
+        received.len(),
+        spooled.path
+    );
+    let mut file = tokio::fs::File::create(&spooled.path)
+        .await
+        .context("创建临时文件失败")?;
+    let mut hasher = Sha256::new();
+    hasher.update(&received);
+    let mut md5 = etag::Md5::new();
+    md5.update(&received);
+    file.write_all(&received)
+        .await
+        .context("写入临时文件失败")?;
+    drop(received);
+    while let Some(item) = body.next().await {
+        let item = body_item(item)?;
+        hasher.update(&item);
+        md5.update(&item);
+        file.write_all(&item).await.context("写入临时文件失败")?;
+        spooled.size += item.len() as u64;
+    }
+    file.flush().await.context("写入临时文件失败")?;
+    spooled.sha256 = hasher.finalize().encode_hex_upper();
+    spooled.md5 = md5.finish();
+    Ok(spooled)
+}
+
+// 按分片保存落盘的请求体，全部分片保存后再写入元数据，对象在此之前不可见。
+// 返回写入元数据的日志序号。给出 cipher 时保存加密后的分片
+pub(crate) async fn upload(
+    state: &App,
+    file_path: String,
+    spooled: &SpoolFile,
+    attrs: ObjectAttrs,
+    cipher: Option<&ObjectCipher>,
+) -> anyhow::Result<u64> {
+    // 直通存储需要完整的原文件，仍一次性写入
+    let object_path = fs::object_path_from_meta(&file_path).context("解析对象路径失败")?;
+    if config::get().backend_for(&object_path) == Backend::Passthrough {
+        let body = spooled.read().await?;
+        let res = state
+            .client_write(Request::UploadFileV2 {
+                file_path,
+                body,
+                attrs,
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+        conditional::check_applied(res.data.value.as_deref())?;
+        if let Some(err) = res.data.value {
+            anyhow::bail!("保存对象失败: {}", err);
+        }
+        return Ok(res.log_id.index);
+    }
+    let mut file = tokio::fs::File::open(&spooled.path)
+        .await
+        .context("打开临时文件失败")?;
+    let mut saved = SavedChunks {
+        sha256: spooled.sha256.clone(),
+        md5: spooled.md5.clone(),
+        ..SavedChunks::new(cipher, attrs.codec)
+    };
+    let chunker = config::get().chunker();
+    let mut buf = fs::ChunkBuffer::new(chunker);
+    loop {
+        let mut block = Vec::with_capacity(chunker.max_chunk_size());
+        (&mut file)
+            .take(chunker.max_chunk_size() as u64)
+            .read_to_end(&mut block)
+            .await
+            .context("读取临时文件失败")?;
+        let eof = block.is_empty();
+        buf.extend(&block);
+        while let Some(chunk) = buf.next_chunk(eof) {
+            saved.push(state, chunk).await?;
+        }
+        if eof {
+            break;
+        }
+    }
+    commit(state, file_path, saved, attrs).await
+}
+
+// 写入已保存分片的对象的元数据，返回写入元数据的日志序号
+pub(crate) async fn commit(
+    state: &App,
+    file_path: String,
+    saved: SavedChunks,
+    mut attrs: ObjectAttrs,
+) -> anyhow::Result<u64> {
+    if attrs.content_type.is_none() {
+        let file_name = PathBuf::from(file_path.trim_end_matches(".meta"))
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        attrs.content_type = Some(util::file::detect_content_type(&file_name, &saved.head));
+    }
+    let res = state
+        .client_write(Request::CommitChunkedFileV2 {
+            file_path,
+            size: saved.size,
+            chunks: saved.chunks,
+            chunk_sizes: saved.chunk_sizes,
+            attrs,
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    conditional::check_applied(res.data.value.as_deref())?;
+    Ok(res.log_id.index)
+}
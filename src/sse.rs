@@ -0,0 +1,339 @@
+use crate::config;
+use crate::err::AppError;
+use crate::fs::{Encryption, SseAlgorithm};
+use crate::kms;
+use crate::util::cry;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crypto_hash::{digest, Algorithm};
+use futures::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use ntex::http::{HeaderMap, StatusCode};
+use ntex::util::Bytes;
+use openssl::symm::{Cipher, Crypter, Mode};
+use rand::Rng;
+use sha2::Sha256;
+use std::io;
+
+// --- 服务端加密：上传时带 x-amz-server-side-encryption: AES256（SSE-S3）的对象用随机生成的
+// 数据密钥加密分片内容，数据密钥保存在元数据中（元数据由桶密钥和主密钥加密，raft 日志中的
+// 数据密钥用主密钥加密）；带 x-amz-server-side-encryption-customer-*（SSE-C）的对象用客户提供的
+// 密钥加密，服务端只保存密钥的 HMAC 和 MD5，读取时必须提供相同的密钥。内容用 AES-256-CTR
+// 加密，密文与明文等长，范围读取按偏移解密；分片按密文计算哈希，加密的对象不与其他对象去重。
+// 普通上传的所有分片组成一段连续的密文，分片上传的每个分片以分片号为段号单独加密。加密的
+// 对象不协商压缩，直通存储不支持加密。--encrypted-bucket 中的桶，未要求加密的上传默认按 SSE-S3
+// 加密，删除桶即销毁桶密钥，其中对象的数据密钥随之无法解密
+
+pub const AES256: &str = "AES256";
+
+const SSE_HEADER: &str = "x-amz-server-side-encryption";
+const CUSTOMER_ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
+const CUSTOMER_KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
+const CUSTOMER_KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-md5";
+// 拷贝请求中源对象的 SSE-C 请求头，如 x-amz-copy-source-server-side-encryption-customer-key
+const COPY_SOURCE_PREFIX: &str = "x-amz-copy-source-";
+
+// 计数器前缀（nonce）的字节数，之后 4 字节为段号、4 字节为块计数
+const NONCE_LEN: usize = 8;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 请求提供的 SSE-C 客户密钥
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomerKey {
+    pub key: Vec<u8>,
+    // 密钥的 MD5（base64）
+    pub key_md5: String,
+}
+
+// 上传请求要求的加密方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum SseRequest {
+    S3,
+    Customer(CustomerKey),
+}
+
+fn invalid_argument(message: &str) -> AppError {
+    AppError::s3(StatusCode::BAD_REQUEST, "InvalidArgument", message)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .map(|value| value.to_str().unwrap_or_default().trim())
+}
+
+// 读取 SSE-C 请求头，请求头名称开头的 x-amz- 替换为 prefix
+fn parse_customer_key(headers: &HeaderMap, prefix: &str) -> Result<Option<CustomerKey>, AppError> {
+    let get = |name: &str| header(headers, &name.replacen("x-amz-", prefix, 1));
+    let algorithm = get(CUSTOMER_ALGORITHM_HEADER);
+    let key = get(CUSTOMER_KEY_HEADER);
+    let key_md5 = get(CUSTOMER_KEY_MD5_HEADER);
+    if algorithm.is_none() && key.is_none() && key_md5.is_none() {
+        return Ok(None);
+    }
+    let (Some(algorithm), Some(key), Some(key_md5)) = (algorithm, key, key_md5) else {
+        return Err(invalid_argument(
+            "Requests specifying Server Side Encryption with Customer provided keys must provide the algorithm, the secret key and its MD5",
+        ));
+    };
+    if algorithm != AES256 {
+        return Err(invalid_argument(
+            "The requested encryption algorithm is not valid, expected AES256",
+        ));
+    }
+    let key = STANDARD
+        .decode(key)
+        .ok()
+        .filter(|key| key.len() == 32)
+        .ok_or_else(|| {
+            invalid_argument("The secret key was invalid for the specified algorithm")
+        })?;
+    if STANDARD.encode(digest(Algorithm::MD5, &key)) != key_md5 {
+        return Err(invalid_argument(
+            "The calculated MD5 hash of the key did not match the hash that was provided",
+        ));
+    }
+    Ok(Some(CustomerKey {
+        key,
+        key_md5: key_md5.to_string(),
+    }))
+}
+
+// 上传请求（PUT、创建分片上传、拷贝的目标）要求的加密方式
+pub fn parse_request(headers: &HeaderMap) -> Result<Option<SseRequest>, AppError> {
+    let customer = parse_customer_key(headers, "x-amz-")?;
+    match header(headers, SSE_HEADER) {
+        None => Ok(customer.map(SseRequest::Customer)),
+        Some(_) if customer.is_some() => Err(invalid_argument(
+            "Server Side Encryption with Customer provided key is incompatible with the encryption method specified",
+        )),
+        Some(AES256) => Ok(Some(SseRequest::S3)),
+        Some(_) => Err(invalid_argument(
+            "The encryption method specified is not supported",
+        )),
+    }
+}
+
+// 读取、上传分片时提供的 SSE-C 密钥
+pub fn customer_key(headers: &HeaderMap) -> Result<Option<CustomerKey>, AppError> {
+    parse_customer_key(headers, "x-amz-")
+}
+
+// 拷贝请求中源对象的 SSE-C 密钥
+pub fn copy_source_key(headers: &HeaderMap) -> Result<Option<CustomerKey>, AppError> {
+    parse_customer_key(headers, COPY_SOURCE_PREFIX)
+}
+
+fn key_hmac(nonce: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(nonce).expect("HMAC 接受任意长度的密钥");
+    mac.update(key);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 为新对象生成加密参数
+pub fn new_encryption(request: &SseRequest) -> Encryption {
+    let mut nonce = vec![0u8; NONCE_LEN];
+    rand::thread_rng().fill(&mut nonce[..]);
+    match request {
+        SseRequest::S3 => Encryption {
+            algorithm: SseAlgorithm::S3,
+            key: cry::gen_data_key(),
+            nonce,
+            key_hmac: vec![],
+            customer_key_md5: None,
+            parts: vec![],
+            part_offsets: vec![],
+        },
+        SseRequest::Customer(customer) => Encryption {
+            algorithm: SseAlgorithm::Customer,
+            key: vec![],
+            key_hmac: key_hmac(&nonce, &customer.key),
+            nonce,
+            customer_key_md5: Some(customer.key_md5.clone()),
+            parts: vec![],
+            part_offsets: vec![],
+        },
+    }
+}
+
+// 上传到 bucket 的请求要求的加密方式，请求未要求加密时按桶的默认加密（--encrypted-bucket）
+pub fn parse_upload_request(
+    headers: &HeaderMap,
+    bucket: &str,
+) -> Result<Option<SseRequest>, AppError> {
+    let request = parse_request(headers)?;
+    Ok(request.or_else(|| {
+        config::get()
+            .is_encrypted_bucket(bucket)
+            .then_some(SseRequest::S3)
+    }))
+}
+
+// 上传到 bucket 的请求的加密参数和加密内容的密钥，不加密时为空
+pub fn for_upload(
+    headers: &HeaderMap,
+    bucket: &str,
+) -> Result<Option<(Encryption, ObjectCipher)>, AppError> {
+    Ok(parse_upload_request(headers, bucket)?.map(|request| new_cipher(&request)))
+}
+
+// 不经请求头的写入（如展开归档）按桶的默认加密生成加密参数，不加密时为空
+pub fn for_bucket(bucket: &str) -> Option<(Encryption, ObjectCipher)> {
+    config::get()
+        .is_encrypted_bucket(bucket)
+        .then(|| new_cipher(&SseRequest::S3))
+}
+
+fn new_cipher(request: &SseRequest) -> (Encryption, ObjectCipher) {
+    let encryption = new_encryption(request);
+    let key = match request {
+        SseRequest::S3 => encryption.key.clone(),
+        SseRequest::Customer(customer) => customer.key.clone(),
+    };
+    let cipher = ObjectCipher::new(&encryption, key);
+    (encryption, cipher)
+}
+
+// 写入 raft 日志前用主密钥加密数据密钥
+pub(crate) fn seal(mut encryption: Encryption) -> anyhow::Result<Encryption> {
+    if !encryption.key.is_empty() {
+        encryption.key = kms::encrypt(&encryption.key)?;
+    }
+    Ok(encryption)
+}
+
+// 应用日志时解密数据密钥，写入元数据
+pub(crate) fn unseal(encryption: Option<Encryption>) -> anyhow::Result<Option<Encryption>> {
+    let Some(mut encryption) = encryption else {
+        return Ok(None);
+    };
+    if !encryption.key.is_empty() {
+        encryption.key = kms::decrypt(&encryption.key)?;
+    }
+    Ok(Some(encryption))
+}
+
+// 读取对象所需的解密参数：未加密时为空，SSE-S3 用元数据中的数据密钥，SSE-C 需要请求提供
+// 加密时的客户密钥
+pub fn read_cipher(
+    encryption: Option<&Encryption>,
+    customer: Option<&CustomerKey>,
+) -> Result<Option<ObjectCipher>, AppError> {
+    let invalid_request =
+        |message: &str| AppError::s3(StatusCode::BAD_REQUEST, "InvalidRequest", message);
+    match (encryption, customer) {
+        (None, None) => Ok(None),
+        (Some(encryption), None) if encryption.algorithm == SseAlgorithm::S3 => {
+            Ok(Some(ObjectCipher::new(encryption, encryption.key.clone())))
+        }
+        (Some(encryption), Some(customer)) if encryption.algorithm == SseAlgorithm::Customer => {
+            if key_hmac(&encryption.nonce, &customer.key) != encryption.key_hmac {
+                return Err(AppError::s3(
+                    StatusCode::FORBIDDEN,
+                    "AccessDenied",
+                    "The provided encryption key does not match the key used to encrypt the object",
+                ));
+            }
+            Ok(Some(ObjectCipher::new(encryption, customer.key.clone())))
+        }
+        (Some(_), None) => Err(invalid_request(
+            "The object was stored using a form of Server Side Encryption. The correct parameters must be provided to retrieve the object",
+        )),
+        (_, Some(_)) => Err(invalid_request(
+            "The encryption parameters are not applicable to this object",
+        )),
+    }
+}
+
+// 加密对象的响应头
+pub fn response_headers(encryption: &Encryption) -> Vec<(&'static str, String)> {
+    match encryption.algorithm {
+        SseAlgorithm::S3 => vec![(SSE_HEADER, AES256.to_string())],
+        SseAlgorithm::Customer => vec![
+            (CUSTOMER_ALGORITHM_HEADER, AES256.to_string()),
+            (
+                CUSTOMER_KEY_MD5_HEADER,
+                encryption.customer_key_md5.clone().unwrap_or_default(),
+            ),
+        ],
+    }
+}
+
+// 加解密对象内容的密钥和计数器
+#[derive(Clone)]
+pub struct ObjectCipher {
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    parts: Vec<u32>,
+    part_offsets: Vec<u64>,
+}
+
+impl ObjectCipher {
+    pub fn new(encryption: &Encryption, key: Vec<u8>) -> Self {
+        ObjectCipher {
+            key,
+            nonce: encryption.nonce.clone(),
+            parts: encryption.parts.clone(),
+            part_offsets: encryption.part_offsets.clone(),
+        }
+    }
+
+    // 段 segment 中从 offset 开始的内容与密钥流异或，加密和解密相同。计数器按 128 位整数递增，
+    // 与从段首连续加密的结果一致
+    pub fn apply(&self, segment: u32, offset: u64, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut iv = [0u8; 16];
+        iv[..NONCE_LEN].copy_from_slice(&self.nonce[..NONCE_LEN]);
+        iv[NONCE_LEN..NONCE_LEN + 4].copy_from_slice(&segment.to_be_bytes());
+        let iv = u128::from_be_bytes(iv).wrapping_add((offset / 16) as u128);
+        let mut crypter = Crypter::new(
+            Cipher::aes_256_ctr(),
+            Mode::Encrypt,
+            &self.key,
+            Some(&iv.to_be_bytes()),
+        )
+        .map_err(io::Error::other)?;
+        // 跳过块内 offset 之前的密钥流
+        let skip = (offset % 16) as usize;
+        let mut out = vec![0u8; data.len().max(skip) + 16];
+        if skip > 0 {
+            crypter
+                .update(&[0u8; 16][..skip], &mut out)
+                .map_err(io::Error::other)?;
+        }
+        let n = crypter.update(data, &mut out).map_err(io::Error::other)?;
+        out.truncate(n);
+        Ok(out)
+    }
+
+    // 加解密第 index 个分片，start 为分片在对象中的起始偏移（分片上传的对象不使用）
+    pub fn apply_chunk(&self, index: usize, start: u64, data: &[u8]) -> io::Result<Vec<u8>> {
+        if self.parts.is_empty() {
+            return self.apply(0, start, data);
+        }
+        let part = self
+            .parts
+            .get(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "加密参数中缺少分片号"))?;
+        let offset = self.part_offsets.get(index).copied().unwrap_or(0);
+        self.apply(*part, offset, data)
+    }
+}
+
+// 解密从第 first 个分片开始、每项为一个分片的内容流，start 为该分片在对象中的起始偏移
+pub(crate) fn decrypt_chunks(
+    chunks: impl Stream<Item = io::Result<Bytes>> + Unpin,
+    cipher: ObjectCipher,
+    first: usize,
+    start: u64,
+) -> impl Stream<Item = io::Result<Bytes>> + Unpin {
+    let mut index = first;
+    let mut offset = start;
+    chunks.map(move |chunk| {
+        let chunk = chunk?;
+        let plain = cipher.apply_chunk(index, offset, &chunk)?;
+        index += 1;
+        offset += chunk.len() as u64;
+        Ok(Bytes::from(plain))
+    })
+}
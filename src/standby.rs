@@ -0,0 +1,316 @@
+use crate::api::DATA_DIR;
+use crate::client::S3Client;
+use crate::config;
+use crate::durability;
+use crate::raft::app::App;
+use crate::raft::store::Request;
+use crate::HandlerResponse;
+use anyhow::{anyhow, bail, Context};
+use log::{error, info, warn};
+use ntex::web;
+use ntex::web::HttpResponse;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// --- 主备模式：备用节点持续从主节点拉取已提交的日志条目并在本地应用，主节点故障时
+// 手动或按健康检查提升为主节点。日志条目中带有主节点的绝对路径，备用节点需使用相同的 --fs-root
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/wal", web::get().to(wal))
+        .route("/admin/standby", web::get().to(status))
+        .route("/admin/standby/promote", web::post().to(promote_handler));
+}
+
+// 单次返回的日志条目数上限
+const WAL_BATCH_ENTRIES: u64 = 256;
+// 单次返回的日志条目大小上限（超过后不再追加条目）
+const WAL_BATCH_BYTES: usize = 64 << 20;
+// 没有新条目时主节点最多等待的时间（长轮询）
+const WAL_LONG_POLL: Duration = Duration::from_secs(10);
+// 备用节点拉取失败后的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+// 备用节点已应用到的主节点日志位置
+const POSITION_FILE: &str = ".standby-position";
+// 提升为主节点后写入，重启后不再作为备用节点拉取日志
+const PROMOTED_FILE: &str = ".standby-promoted";
+
+// 拉取接口返回的日志条目，非业务条目（空条目、成员变更）的 request 为 None
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct WalEntry {
+    pub index: u64,
+    pub request: Option<Request>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalQuery {
+    from: u64,
+}
+
+// 返回主节点从 from 开始已应用的日志条目，暂无新条目时等待；from 之前的日志已被清理时返回 410
+pub async fn wal(
+    query: web::types::Query<WalQuery>,
+    state: web::types::State<App>,
+) -> HandlerResponse {
+    let from = query.from.max(1);
+    let deadline = Instant::now() + WAL_LONG_POLL;
+    let mut metrics = state.raft.metrics();
+    let applied = loop {
+        let applied = metrics.borrow().last_applied.map_or(0, |id| id.index);
+        let left = deadline.saturating_duration_since(Instant::now());
+        if applied >= from || left.is_zero() {
+            break applied;
+        }
+        let _ = tokio::time::timeout(left, metrics.changed()).await;
+    };
+    let log_store = state.log_store.clone();
+    let entries = tokio::task::spawn_blocking(move || {
+        log_store.read_entries(from, applied, WAL_BATCH_ENTRIES, WAL_BATCH_BYTES)
+    })
+    .await
+    .context("读取日志失败")??;
+    let Some(entries) = entries else {
+        return Ok(HttpResponse::Gone().body(format!("日志 {} 已被清理", from)));
+    };
+    let entries: Vec<WalEntry> = entries
+        .into_iter()
+        .map(|(index, request)| WalEntry { index, request })
+        .collect();
+    let body = postcard::to_stdvec(&entries).context("序列化日志失败")?;
+    Ok(HttpResponse::Ok()
+        .header("x-wal-applied", applied.to_string())
+        .content_type("application/octet-stream")
+        .body(body))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Primary,
+    Standby,
+    Promoted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StandbyStatus {
+    pub role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+    // 已应用到的主节点日志位置
+    pub position: u64,
+    // 最近一次拉取时主节点已应用的日志位置
+    pub primary_applied: u64,
+    pub lag_entries: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_last_contact_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+struct State {
+    role: Role,
+    position: u64,
+    primary_applied: u64,
+    last_contact: Option<Instant>,
+    last_error: Option<String>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    role: Role::Primary,
+    position: 0,
+    primary_applied: 0,
+    last_contact: None,
+    last_error: None,
+});
+// 备用节点在提升前只读
+static PASSIVE: AtomicBool = AtomicBool::new(false);
+
+// 当前节点是否为尚未提升的备用节点
+pub(crate) fn is_passive() -> bool {
+    PASSIVE.load(Ordering::Relaxed)
+}
+
+fn state_file(name: &str) -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(name)
+}
+
+fn load_position() -> u64 {
+    std::fs::read_to_string(state_file(POSITION_FILE))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+fn save_position(position: u64) -> anyhow::Result<()> {
+    let path = state_file(POSITION_FILE);
+    std::fs::create_dir_all(path.parent().unwrap())
+        .and_then(|_| std::fs::write(&path, position.to_string()))
+        .context("保存同步位置失败")
+}
+
+// 将备用节点提升为主节点：停止拉取日志并开放写入
+fn promote(reason: &str) -> bool {
+    if !PASSIVE.swap(false, Ordering::Relaxed) {
+        return false;
+    }
+    STATE.lock().unwrap().role = Role::Promoted;
+    if let Err(err) = std::fs::write(state_file(PROMOTED_FILE), reason) {
+        warn!("写入提升标记失败: {}", err);
+    }
+    warn!("备用节点已提升为主节点: {}", reason);
+    true
+}
+
+pub async fn promote_handler() -> HandlerResponse {
+    if promote("手动提升") {
+        Ok(HttpResponse::Ok().json(&current_status()))
+    } else {
+        Ok(HttpResponse::Conflict().body("当前节点不是备用节点"))
+    }
+}
+
+fn current_status() -> StandbyStatus {
+    let state = STATE.lock().unwrap();
+    StandbyStatus {
+        role: state.role,
+        primary: config::get().standby_of.clone(),
+        position: state.position,
+        primary_applied: state.primary_applied,
+        lag_entries: state.primary_applied.saturating_sub(state.position),
+        since_last_contact_ms: state.last_contact.map(|t| t.elapsed().as_millis() as u64),
+        last_error: state.last_error.clone(),
+    }
+}
+
+pub async fn status() -> HandlerResponse {
+    Ok(HttpResponse::Ok().json(&current_status()))
+}
+
+// 配置了 --standby-of 时启动日志拉取任务；之前已提升过的节点直接作为主节点运行
+pub(crate) fn spawn(app: App, access_key: String, secret_key: String) {
+    let Some(primary) = config::get().standby_of.clone() else {
+        return;
+    };
+    if state_file(PROMOTED_FILE).exists() {
+        warn!(
+            "节点已提升为主节点，不再从 {} 同步；删除 {:?} 后可重新作为备用节点",
+            primary,
+            state_file(PROMOTED_FILE)
+        );
+        STATE.lock().unwrap().role = Role::Promoted;
+        return;
+    }
+    PASSIVE.store(true, Ordering::Relaxed);
+    {
+        let mut state = STATE.lock().unwrap();
+        state.role = Role::Standby;
+        state.position = load_position();
+    }
+    info!("作为备用节点从 {} 同步日志", primary);
+    let client = S3Client::new(&primary, &access_key, &secret_key);
+    tokio::spawn(async move { stream_loop(app, client).await });
+}
+
+enum FetchError {
+    // 需要的日志已被主节点清理，只能重新复制数据目录
+    Gone(String),
+    Other(anyhow::Error),
+}
+
+async fn fetch(client: &S3Client, from: u64) -> Result<(Vec<WalEntry>, u64), FetchError> {
+    let from = from.to_string();
+    let query = [("from", from.as_str())];
+    let send = client.send(Method::GET, "admin/wal", &query, &[], vec![]);
+    let resp = tokio::time::timeout(WAL_LONG_POLL * 2, send)
+        .await
+        .map_err(|_| FetchError::Other(anyhow!("拉取日志超时")))?
+        .map_err(FetchError::Other)?;
+    let status = resp.status();
+    let applied = resp
+        .headers()
+        .get("x-wal-applied")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|err| FetchError::Other(err.into()))?;
+    if status == StatusCode::GONE {
+        return Err(FetchError::Gone(
+            String::from_utf8_lossy(&body).into_owned(),
+        ));
+    }
+    if !status.is_success() {
+        return Err(FetchError::Other(anyhow!("主节点返回 {}", status)));
+    }
+    let entries = postcard::from_bytes(&body).map_err(|err| FetchError::Other(err.into()))?;
+    Ok((entries, applied))
+}
+
+async fn apply(app: &App, request: Request) -> anyhow::Result<()> {
+    if !is_passive() {
+        bail!("已提升为主节点");
+    }
+    app.raft
+        .client_write(request)
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?;
+    durability::flush().await
+}
+
+async fn stream_loop(app: App, client: S3Client) {
+    let promote_after = Duration::from_secs(config::get().standby_promote_after_secs);
+    let mut last_contact = Instant::now();
+    while is_passive() {
+        let position = STATE.lock().unwrap().position;
+        let res = match fetch(&client, position + 1).await {
+            Ok((entries, applied)) => {
+                last_contact = Instant::now();
+                {
+                    let mut state = STATE.lock().unwrap();
+                    state.last_contact = Some(last_contact);
+                    state.primary_applied = applied;
+                }
+                apply_batch(&app, entries).await
+            }
+            Err(FetchError::Gone(message)) => {
+                last_contact = Instant::now();
+                error!("无法继续同步: {}，需要从主节点重新复制数据目录", message);
+                Err(anyhow!(message))
+            }
+            Err(FetchError::Other(err)) => {
+                if !promote_after.is_zero() && last_contact.elapsed() >= promote_after {
+                    promote(&format!(
+                        "主节点已有 {} 秒无法访问: {}",
+                        last_contact.elapsed().as_secs(),
+                        err
+                    ));
+                    return;
+                }
+                Err(err)
+            }
+        };
+        if let Err(err) = res {
+            STATE.lock().unwrap().last_error = Some(err.to_string());
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        } else {
+            STATE.lock().unwrap().last_error = None;
+        }
+    }
+}
+
+// 依次应用并记录位置，失败时停在失败的条目，下次从该条目重试
+async fn apply_batch(app: &App, entries: Vec<WalEntry>) -> anyhow::Result<()> {
+    for entry in entries {
+        if let Some(request) = entry.request {
+            apply(app, request).await?;
+        }
+        save_position(entry.index)?;
+        STATE.lock().unwrap().position = entry.index;
+    }
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use crate::config::{self, MasterKeySource, Scanner, ServerConfig};
+use crate::tls;
+use crate::HandlerResponse;
+use log::info;
+use ntex::web;
+use ntex::web::HttpResponse;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// --- 启动信息：节点就绪后在日志中打印生效的配置（监听地址、数据目录、认证方式、分片和压缩
+// 设置、功能开关），GET /admin/config 返回同样的内容。密钥只显示是否已设置，URL 中的用户名
+// 和密码被替换，命令行形式的配置（扫描命令、主密钥命令）只显示类型
+
+// 替换密钥的占位符
+pub const REDACTED: &str = "<redacted>";
+
+static EFFECTIVE: OnceLock<Value> = OnceLock::new();
+
+pub fn rest(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/config", web::get().to(effective));
+}
+
+// 不在服务配置中的启动参数
+#[derive(Debug, Clone, Default)]
+pub struct NodeInfo {
+    pub node_id: u64,
+    pub http_addr: String,
+    // 启用 HTTPS 时实际监听的地址
+    pub tls_addr: Option<String>,
+    // 启用 HTTP/3 时实际监听的 UDP 地址
+    pub http3_addr: Option<String>,
+    pub rpc_addr: String,
+    pub fs_root: String,
+    pub data_dir: String,
+    pub access_key: String,
+    pub leader_http_addr: Option<String>,
+    // 当前主密钥的编号
+    pub master_key_id: String,
+}
+
+// 去掉 URL 中的用户名和密码
+pub fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..authority_end].rsplit_once('@') {
+        Some((_, host)) => format!(
+            "{}://{}@{}{}",
+            scheme,
+            REDACTED,
+            host,
+            &rest[authority_end..]
+        ),
+        None => url.to_string(),
+    }
+}
+
+fn path_str(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+// 生效的配置
+pub fn effective_config(cfg: &ServerConfig, node: &NodeInfo) -> Value {
+    let chunk_roots: Vec<String> = if cfg.chunk_roots.is_empty() {
+        vec![path_str(&Path::new(&node.data_dir).join("file"))]
+    } else {
+        cfg.chunk_roots.iter().map(|p| path_str(p)).collect()
+    };
+    let master_key = match &cfg.master_key {
+        MasterKeySource::Builtin => "builtin".to_string(),
+        MasterKeySource::Inline(_) => "inline".to_string(),
+        MasterKeySource::File(path) => format!("file:{}", path_str(path)),
+        MasterKeySource::Command(_) => "command".to_string(),
+    };
+    let scanner = cfg.scanner.as_ref().map(|scanner| match scanner {
+        Scanner::Command(_) => "command".to_string(),
+        Scanner::Icap(url) => redact_url(url),
+    });
+    let jwt = &cfg.jwt;
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "node_id": node.node_id,
+        "listeners": {
+            "http_addr": node.http_addr,
+            "tls_addr": node.tls_addr,
+            "http3_addr": node.http3_addr,
+            "rpc_addr": node.rpc_addr,
+            "leader_http_addr": node.leader_http_addr,
+            "cdc_addr": cfg.cdc_addr,
+            "vhost_domain": cfg.vhost_domain,
+            "dns_addr": cfg.dns_addr,
+            "http_workers": cfg.http_workers,
+            "blocking_threads": cfg.blocking_threads,
+            "pin_cpus": cfg.pin_cpus,
+        },
+        "storage": {
+            "fs_root": node.fs_root,
+            "data_dir": node.data_dir,
+            "chunk_roots": chunk_roots,
+            "chunk_replicas": cfg.chunk_replicas,
+            "erasure": cfg.erasure().map(|(k, m)| format!("{}+{}", k, m)),
+            "storage_routes": cfg
+                .storage_routes
+                .iter()
+                .map(|route| format!("{}={:?}", route.prefix, route.backend))
+                .collect::<Vec<_>>(),
+            "spool_threshold": cfg.spool_threshold,
+            "spool_dir": cfg.spool_dir.as_deref().map(path_str),
+            "fsync": cfg.fsync,
+            "fsync_window_ms": cfg.fsync_window_ms,
+            "disk_watermarks": format!("{},{}", cfg.disk_watermarks.warning, cfg.disk_watermarks.critical),
+        },
+        "auth": {
+            "access_key": node.access_key,
+            "secret_key": REDACTED,
+            "master_key": master_key,
+            "master_key_id": node.master_key_id,
+            "secure": cfg.secure,
+            "plain_metadata": cfg.plain_metadata,
+            "jwt": jwt.enabled().then(|| json!({
+                "oidc_issuer": jwt.oidc_issuer,
+                "secret": jwt.secret.as_ref().map(|_| REDACTED),
+                "public_keys": jwt.public_keys.iter().map(|p| path_str(p)).collect::<Vec<_>>(),
+                "audience": jwt.audience,
+            })),
+            "tls_client_ca": cfg.tls.as_ref().and_then(|tls| tls.client_ca.as_deref().map(path_str)),
+            "tls_require_client_cert": cfg.tls.as_ref().map(|tls| tls.require_client_cert),
+            "tls_cert_identities": cfg
+                .tls
+                .iter()
+                .flat_map(|tls| &tls.cert_identities)
+                .map(|identity| format!("{}={}", tls::subject_string(&identity.subject), identity.access_key))
+                .collect::<Vec<_>>(),
+            "auth_webhook": cfg.auth_webhook.as_deref().map(redact_url),
+            "auth_cache_secs": cfg.auth_cache_secs,
+            "public_prefixes": cfg.public_prefixes,
+            "encrypted_buckets": cfg.encrypted_buckets,
+            "bucket_restrictions": cfg
+                .bucket_restrictions
+                .iter()
+                .map(|r| format!("{}={:?}", r.bucket, r.denied))
+                .collect::<Vec<_>>(),
+            "disabled_apis": cfg.disabled_apis.iter().map(|group| group.name()).collect::<Vec<_>>(),
+        },
+        "chunking": {
+            "chunk_size": cfg.chunk_bytes(),
+            "chunking": format!("{:?}", cfg.chunking),
+            "dedup_verify": format!("{:?}", cfg.dedup_verify),
+            "verify_writes": cfg.verify_writes,
+            "read_ahead_chunks": cfg.read_ahead_chunks,
+        },
+        "compression": {
+            "codec": format!("{:?}", cfg.compression.codec),
+            "level": cfg.compression.level,
+            "adaptive": cfg.compression.adaptive,
+            "min_level": cfg.compression.min_level,
+            "recompress_after_days": cfg.compression.recompress_after_days,
+            "recompress_level": cfg.compression.recompress_level,
+        },
+        "features": {
+            "cluster_ring": cfg.cluster_ring.then_some(cfg.ring_replicas),
+            "standby_of": cfg.standby_of.as_deref().map(redact_url),
+            "debug_endpoints": cfg.debug_endpoints,
+            "debug_headers": cfg.debug_headers,
+            "bucket_usage_headers": cfg.bucket_usage_headers,
+            "access_tracking": cfg.access_tracking,
+            "unread_expirations": cfg.unread_expirations.len(),
+            "scanner": scanner,
+            "scan_action": format!("{:?}", cfg.scan_action),
+            "plugins": cfg.plugins.iter().map(|p| path_str(&p.path)).collect::<Vec<_>>(),
+            "scripts": cfg.scripts.iter().map(|s| path_str(&s.path)).collect::<Vec<_>>(),
+            "statsd": cfg.statsd.addr,
+            "slow_request_ms": cfg.slow_request_threshold.map(|t| t.as_millis() as u64),
+            "gc_interval_hours": cfg.gc_interval_hours,
+            "scrub_interval_hours": cfg.scrub_interval_hours,
+            "lifecycle_interval_secs": cfg.lifecycle_interval_secs,
+            "lifecycle_day_secs": cfg.lifecycle_day_secs,
+            "idempotency_window_secs": cfg.idempotency_window_secs,
+            "request_limits": {
+                "max_header_count": cfg.request_limits.max_header_count,
+                "max_header_size": cfg.request_limits.max_header_size,
+                "max_url_length": cfg.request_limits.max_url_length,
+                "max_key_length": cfg.request_limits.max_key_length,
+                "max_metadata_size": cfg.request_limits.max_metadata_size,
+            },
+        },
+    })
+}
+
+// 启动横幅：首行为版本和节点，之后每个分组一行 `分组: 键=值 ...`，省略为空的值
+pub fn banner(effective: &Value) -> String {
+    let mut out = format!(
+        "rs-s3-local {} (node {})",
+        effective["version"].as_str().unwrap_or_default(),
+        effective["node_id"]
+    );
+    let Some(sections) = effective.as_object() else {
+        return out;
+    };
+    for (section, fields) in sections {
+        let Some(fields) = fields.as_object() else {
+            continue;
+        };
+        out.push_str(&format!("\n  {}:", section));
+        for (name, value) in fields {
+            match value {
+                Value::Null => {}
+                Value::Array(items) if items.is_empty() => {}
+                Value::String(s) => out.push_str(&format!(" {}={}", name, s)),
+                value => out.push_str(&format!(" {}={}", name, value)),
+            }
+        }
+    }
+    out
+}
+
+// 节点就绪后记录并打印生效的配置
+pub(crate) fn report(node: NodeInfo) {
+    let effective = effective_config(config::get(), &node);
+    info!("{}", banner(&effective));
+    let _ = EFFECTIVE.set(effective);
+}
+
+pub async fn effective() -> HandlerResponse {
+    Ok(HttpResponse::Ok().json(EFFECTIVE.get().unwrap_or(&Value::Null)))
+}
@@ -0,0 +1,310 @@
+use crate::config;
+use crate::identity::Operation;
+use log::info;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web;
+use std::collections::BTreeSet;
+use std::net::UdpSocket;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// --- statsd 指标推送：按请求累计计数与耗时，定期打包成 UDP 报文发送到 --statsd-addr；
+// 开启 --statsd-tags 时使用 dogstatsd 的标签格式，否则把标签拼进指标名
+
+// 推送间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// 单个 UDP 报文的大小上限，避免在常见 MTU 下分片
+const MAX_PACKET_BYTES: usize = 1432;
+// 两次推送之间最多缓存的指标行数，超过后丢弃新的指标
+const MAX_PENDING_LINES: usize = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricKind {
+    Counter,
+    Timing,
+}
+
+// 一行 statsd 指标，tags 为 (名称, 值)
+pub fn format_metric(
+    prefix: &str,
+    name: &str,
+    value: u64,
+    kind: MetricKind,
+    tags: &[(&str, &str)],
+    dogstatsd: bool,
+) -> String {
+    let mut line = String::new();
+    if !prefix.is_empty() {
+        line.push_str(prefix.trim_end_matches('.'));
+        line.push('.');
+    }
+    line.push_str(name);
+    if !dogstatsd {
+        for (_, value) in tags {
+            line.push('.');
+            line.push_str(value);
+        }
+    }
+    let kind = match kind {
+        MetricKind::Counter => "c",
+        MetricKind::Timing => "ms",
+    };
+    line.push_str(&format!(":{}|{}", value, kind));
+    if dogstatsd && !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+        line.push_str("|#");
+        line.push_str(&tags.join(","));
+    }
+    line
+}
+
+// 把指标行拼成不超过 MAX_PACKET_BYTES 的报文，行之间以换行分隔
+pub fn pack_lines(lines: &[String]) -> Vec<String> {
+    let mut packets = vec![];
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+static SOCKET: OnceLock<UdpSocket> = OnceLock::new();
+static PENDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(name: &str, value: u64, kind: MetricKind, tags: &[(&str, &str)]) {
+    if SOCKET.get().is_none() {
+        return;
+    }
+    let cfg = &config::get().statsd;
+    let line = format_metric(&cfg.prefix, name, value, kind, tags, cfg.tags);
+    let mut pending = PENDING.lock().unwrap();
+    if pending.len() < MAX_PENDING_LINES {
+        pending.push(line);
+    }
+}
+
+fn flush() {
+    let Some(socket) = SOCKET.get() else {
+        return;
+    };
+    let lines = std::mem::take(&mut *PENDING.lock().unwrap());
+    for packet in pack_lines(&lines) {
+        // UDP 发送失败（如接收端未启动）不影响服务
+        let _ = socket.send(packet.as_bytes());
+    }
+}
+
+// 配置了 --statsd-addr 时启动推送任务
+pub(crate) fn spawn() -> std::io::Result<()> {
+    let Some(addr) = config::get().statsd.addr.clone() else {
+        return Ok(());
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(&addr)?;
+    socket.set_nonblocking(true)?;
+    let _ = SOCKET.set(socket);
+    info!("statsd 指标推送到 {}", addr);
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            flush();
+        }
+    });
+    Ok(())
+}
+
+// 请求调用的 S3 接口，非 S3 请求返回 None
+pub fn api_of(method: &str, path: &str, query: &str, copy: bool) -> Option<&'static str> {
+    let rest = path.strip_prefix("/api")?.trim_start_matches('/');
+    let has = |name: &str| {
+        query
+            .split('&')
+            .any(|param| param.split('=').next() == Some(name))
+    };
+    if rest.is_empty() {
+        return Some(if method == "GET" {
+            "ListBuckets"
+        } else {
+            "Other"
+        });
+    }
+    let api = if !rest.trim_end_matches('/').contains('/') {
+        match method {
+            "GET" if has("website") => "GetBucketWebsite",
+            "GET" if has("policy") => "GetBucketPolicy",
+            "GET" if has("lifecycle") => "GetBucketLifecycle",
+            "GET" if has("versioning") => "GetBucketVersioning",
+            "GET" if has("versions") => "ListObjectVersions",
+            "GET" if has("list-type") => "ListObjectsV2",
+            "GET" => "ListObjects",
+            "HEAD" => "HeadBucket",
+            "PUT" if has("website") => "PutBucketWebsite",
+            "PUT" if has("policy") => "PutBucketPolicy",
+            "PUT" if has("lifecycle") => "PutBucketLifecycle",
+            "PUT" if has("versioning") => "PutBucketVersioning",
+            "PUT" => "CreateBucket",
+            "DELETE" if has("website") => "DeleteBucketWebsite",
+            "DELETE" if has("policy") => "DeleteBucketPolicy",
+            "DELETE" if has("lifecycle") => "DeleteBucketLifecycle",
+            "DELETE" => "DeleteBucket",
+            "POST" if has("delete") => "DeleteObjects",
+            _ => "Other",
+        }
+    } else {
+        match method {
+            "GET" if has("tagging") => "GetObjectTagging",
+            "GET" if has("uploadId") => "ListParts",
+            "GET" => "GetObject",
+            "HEAD" => "HeadObject",
+            "PUT" if has("tagging") => "PutObjectTagging",
+            "PUT" if has("uploadId") && copy => "UploadPartCopy",
+            "PUT" if has("uploadId") => "UploadPart",
+            "PUT" if copy => "CopyObject",
+            "PUT" => "PutObject",
+            "POST" if has("uploads") => "CreateMultipartUpload",
+            "POST" if has("uploadId") => "CompleteMultipartUpload",
+            "DELETE" if has("tagging") => "DeleteObjectTagging",
+            "DELETE" if has("uploadId") => "AbortMultipartUpload",
+            "DELETE" => "DeleteObject",
+            _ => "Other",
+        }
+    };
+    Some(api)
+}
+
+// 已作为标签出现过的桶
+static BUCKETS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+// 桶的标签值：最多区分 max 个桶，之后出现的桶归入 _other。只有请求成功时才登记新的桶，
+// 避免访问不存在的桶占满名额；标签值中指标格式的保留字符替换为 '_'
+pub fn bucket_label(seen: &mut BTreeSet<String>, bucket: &str, max: usize, admit: bool) -> String {
+    let label: String = bucket
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if seen.contains(&label) {
+        return label;
+    }
+    if admit && seen.len() < max {
+        seen.insert(label.clone());
+        return label;
+    }
+    "_other".to_string()
+}
+
+// 请求的指标类别：S3 请求按操作类别，另有管理接口和其他接口
+fn operation_of(method: &str, path: &str) -> &'static str {
+    if path.starts_with("/api") {
+        match Operation::of(method, path) {
+            Operation::Read => "read",
+            Operation::List => "list",
+            Operation::Write => "write",
+            Operation::Delete => "delete",
+        }
+    } else if path.starts_with("/admin") {
+        "admin"
+    } else {
+        "other"
+    }
+}
+
+// 记录每个请求的计数（requests）与耗时（request_time），标签为操作类别和状态码类别，
+// 按配置再加上 S3 接口和桶
+pub struct Metrics;
+
+impl<S> Middleware<S> for Metrics {
+    type Service = MetricsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        MetricsMiddleware { service }
+    }
+}
+
+pub struct MetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, Err> Service<web::WebRequest<Err>> for MetricsMiddleware<S>
+where
+    S: Service<web::WebRequest<Err>, Response = web::WebResponse, Error = web::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = web::WebResponse;
+    type Error = web::Error;
+
+    ntex::forward_poll_ready!(service);
+
+    async fn call(
+        &self,
+        req: web::WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if SOCKET.get().is_none() {
+            return ctx.call(&self.service, req).await;
+        }
+        let cfg = &config::get().statsd;
+        let operation = operation_of(req.method().as_str(), req.path());
+        let api = if cfg.api_labels {
+            api_of(
+                req.method().as_str(),
+                req.path(),
+                req.query_string(),
+                req.headers().contains_key("x-amz-copy-source"),
+            )
+        } else {
+            None
+        };
+        let bucket = req
+            .path()
+            .strip_prefix("/api/")
+            .and_then(|rest| rest.split('/').next())
+            .filter(|bucket| cfg.max_buckets > 0 && !bucket.is_empty())
+            .map(|bucket| bucket.to_string());
+        let start = Instant::now();
+        let res = ctx.call(&self.service, req).await;
+        let status = match &res {
+            Ok(res) => res.status().as_u16(),
+            Err(_) => 500,
+        };
+        let bucket = bucket.map(|bucket| {
+            bucket_label(
+                &mut BUCKETS.lock().unwrap(),
+                &bucket,
+                cfg.max_buckets,
+                status < 400,
+            )
+        });
+        let status = format!("{}xx", status / 100);
+        let mut tags = vec![("operation", operation), ("status", status.as_str())];
+        if let Some(api) = api {
+            tags.push(("api", api));
+        }
+        if let Some(bucket) = &bucket {
+            tags.push(("bucket", bucket.as_str()));
+        }
+        record("requests", 1, MetricKind::Counter, &tags);
+        record(
+            "request_time",
+            start.elapsed().as_millis() as u64,
+            MetricKind::Timing,
+            &tags,
+        );
+        res
+    }
+}
@@ -0,0 +1,127 @@
+use crate::copy::MetadataDirective;
+use crate::fs::Tag;
+use ntex::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+// --- 对象标签：PUT/GET/DELETE /api/{bucket}/{key}?tagging 读写对象的标签集合，上传和创建分片上传时
+// 也可以通过 x-amz-tagging 请求头（URL 编码的 "键=值&键=值"）设置。标签保存在对象元数据中，
+// GET/HEAD 返回 x-amz-tagging-count，列表中返回 TagCount（扩展）。拷贝时按 x-amz-tagging-directive
+// （COPY 沿用源对象的标签，REPLACE 使用请求中的）处理
+
+// 与 S3 一致的限制
+const MAX_TAGS: usize = 10;
+const MAX_KEY_LEN: usize = 128;
+const MAX_VALUE_LEN: usize = 256;
+
+// PutObjectTagging 请求体和 GetObjectTagging 返回结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename = "Tagging")]
+pub struct Tagging {
+    #[serde(rename = "TagSet", default)]
+    pub tag_set: TagSet,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagSet {
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<TagEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagEntry {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Value", default)]
+    pub value: String,
+}
+
+// 校验标签的数量、长度和键是否重复
+pub fn validate(tags: &[Tag]) -> Result<(), String> {
+    if tags.len() > MAX_TAGS {
+        return Err(format!("Object tags cannot be greater than {}", MAX_TAGS));
+    }
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.key.is_empty() || tag.key.chars().count() > MAX_KEY_LEN {
+            return Err("The TagKey you have provided is invalid".to_string());
+        }
+        if tag.value.chars().count() > MAX_VALUE_LEN {
+            return Err("The TagValue you have provided is invalid".to_string());
+        }
+        if tags[..i].iter().any(|other| other.key == tag.key) {
+            return Err("Cannot provide multiple Tags with the same key".to_string());
+        }
+    }
+    Ok(())
+}
+
+// 解析 x-amz-tagging 请求头
+pub fn parse_header(value: &str) -> Result<Vec<Tag>, String> {
+    let tags: Vec<Tag> = url::form_urlencoded::parse(value.as_bytes())
+        .map(|(key, value)| Tag {
+            key: key.into_owned(),
+            value: value.into_owned(),
+        })
+        .collect();
+    validate(&tags)?;
+    Ok(tags)
+}
+
+// 解析 PutObjectTagging 请求体，XML 格式错误时返回 Err(None)，标签无效时返回 Err(Some(原因))
+pub fn parse_xml(xml: &str) -> Result<Vec<Tag>, Option<String>> {
+    let tagging: Tagging = quick_xml::de::from_str(xml).map_err(|_| None)?;
+    let tags: Vec<Tag> = tagging
+        .tag_set
+        .tags
+        .into_iter()
+        .map(|entry| Tag {
+            key: entry.key,
+            value: entry.value,
+        })
+        .collect();
+    validate(&tags).map_err(Some)?;
+    Ok(tags)
+}
+
+pub fn tag_set(tags: &[Tag]) -> TagSet {
+    TagSet {
+        tags: tags
+            .iter()
+            .map(|tag| TagEntry {
+                key: tag.key.clone(),
+                value: tag.value.clone(),
+            })
+            .collect(),
+    }
+}
+
+// GetObjectTagging 返回结果
+pub fn to_xml(tags: &[Tag]) -> anyhow::Result<String> {
+    let tagging = Tagging {
+        tag_set: tag_set(tags),
+    };
+    Ok(quick_xml::se::to_string(&tagging)?)
+}
+
+// 请求中 x-amz-tagging 设置的标签，未设置时为空
+pub(crate) fn from_headers(headers: &HeaderMap) -> Result<Vec<Tag>, String> {
+    match headers.get("x-amz-tagging") {
+        Some(value) => parse_header(value.to_str().map_err(|err| err.to_string())?),
+        None => Ok(vec![]),
+    }
+}
+
+// 拷贝后新对象的标签
+pub(crate) fn for_copy(headers: &HeaderMap, source: &[Tag]) -> Result<Vec<Tag>, String> {
+    let directive = match headers.get("x-amz-tagging-directive") {
+        Some(value) => value
+            .to_str()
+            .map_err(|err| err.to_string())?
+            .parse::<MetadataDirective>()
+            .map_err(|_| "Unknown tagging directive".to_string())?,
+        None => MetadataDirective::Copy,
+    };
+    match directive {
+        MetadataDirective::Copy => Ok(source.to_vec()),
+        MetadataDirective::Replace => from_headers(headers),
+    }
+}
@@ -0,0 +1,87 @@
+use crate::config::{self, CertIdentity, TlsConfig};
+use anyhow::Context;
+use log::info;
+use ntex::tls::openssl::PeerCert;
+use ntex::web;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::x509::{X509Name, X509NameRef};
+
+// --- HTTPS 监听：--tls-addr 上用 --tls-cert、--tls-key 提供 HTTPS，路由和认证与 --http-addr
+// 上的 HTTP 相同，集群内部的请求仍走 HTTP。配置 --tls-client-ca 时请求客户端证书并用该 CA
+// 校验，校验失败的连接在握手时断开，--tls-require-client-cert 时没有证书的连接也断开。通过
+// 校验的证书主题按 --tls-cert-identity 对应到访问密钥，没有签名的请求以该访问密钥的身份和
+// 权限处理；带签名的请求仍按签名认证
+
+// 按配置创建 TLS 监听的参数
+pub(crate) fn acceptor(tls: &TlsConfig) -> anyhow::Result<SslAcceptorBuilder> {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+    builder
+        .set_certificate_chain_file(&tls.cert)
+        .with_context(|| format!("读取证书 {:?} 失败", tls.cert))?;
+    builder
+        .set_private_key_file(&tls.key, SslFiletype::PEM)
+        .with_context(|| format!("读取私钥 {:?} 失败", tls.key))?;
+    builder.check_private_key().context("私钥与证书不匹配")?;
+    if let Some(ca) = &tls.client_ca {
+        builder
+            .set_ca_file(ca)
+            .with_context(|| format!("读取客户端 CA 证书 {:?} 失败", ca))?;
+        builder.set_client_ca_list(X509Name::load_client_ca_file(ca)?);
+        let mut mode = SslVerifyMode::PEER;
+        if tls.require_client_cert {
+            mode |= SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+        }
+        builder.set_verify(mode);
+        // 校验客户端证书时恢复会话需要会话上下文
+        builder.set_session_id_context(b"rs-s3-local")?;
+    }
+    Ok(builder)
+}
+
+// 证书主题的属性，属性名为短名称（如 CN、O、OU）
+pub fn subject_attributes(name: &X509NameRef) -> Vec<(String, String)> {
+    name.entries()
+        .filter_map(|entry| {
+            let name = entry.object().nid().short_name().ok()?;
+            let value = entry.data().as_utf8().ok()?;
+            Some((name.to_ascii_uppercase(), value.to_string()))
+        })
+        .collect()
+}
+
+// `CN=a,O=b` 形式的证书主题，用于日志
+pub fn subject_string(subject: &[(String, String)]) -> String {
+    subject
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// 第一个匹配证书主题的访问密钥
+pub fn access_key_for<'a>(
+    identities: &'a [CertIdentity],
+    subject: &[(String, String)],
+) -> Option<&'a str> {
+    identities
+        .iter()
+        .find(|identity| identity.subject.iter().all(|attr| subject.contains(attr)))
+        .map(|identity| identity.access_key.as_str())
+}
+
+// 连接的客户端证书对应的访问密钥，不是 TLS 连接或没有证书时为空
+pub(crate) fn peer_access_key(
+    request: &web::WebRequest<impl web::ErrorRenderer>,
+) -> Option<String> {
+    let tls = config::get().tls.as_ref()?;
+    if tls.cert_identities.is_empty() {
+        return None;
+    }
+    let cert = request.io()?.query::<PeerCert>();
+    let subject = subject_attributes(cert.as_ref()?.0.subject_name());
+    let access_key = access_key_for(&tls.cert_identities, &subject);
+    if access_key.is_none() {
+        info!("客户端证书 {} 没有对应的访问密钥", subject_string(&subject));
+    }
+    access_key.map(str::to_string)
+}
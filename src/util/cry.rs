@@ -5,12 +5,10 @@ use crypto_hash::{hex_digest, Algorithm};
 use hmac::{Hmac, Mac};
 use ntex::util::BytesMut;
 use rand::seq::IndexedRandom;
+use rand::Rng;
 use sha2::Sha256;
 use zstd::zstd_safe::WriteBuf;
 
-// 定义一个默认的密钥常量。
-const DEFAULT_KEY: &str = "000102030405060708090A0B0C0D0E0F";
-
 // 使用 MD5 算法对字符串进行哈希加密的函数。
 pub fn encrypt_by_md5(s: &str) -> String {
     let digest = hex_digest(Algorithm::MD5, s.as_bytes());
@@ -35,23 +33,33 @@ fn gen_ascii_chars(size: usize) -> String {
     .unwrap()
 }
 
-// 使用 AES-256-CBC 加密算法加密数据的函数。
-pub fn aes_256_cbc_encrypt(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+// 使用指定的 32 字节密钥进行 AES-256-CBC 加密。
+pub fn aes_256_cbc_encrypt_with_key(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
     let iv_str = gen_ascii_chars(16);
     let iv = iv_str.as_bytes();
-    let cipher = AesCbc::new_from_slices(DEFAULT_KEY.as_bytes(), iv)?;
+    let cipher = AesCbc::new_from_slices(key, iv)?;
     let ciphertext = cipher.encrypt_vec(data);
     let mut buffer = BytesMut::from(iv);
     buffer.extend_from_slice(&ciphertext);
     Ok(Vec::from(buffer.as_slice()))
 }
 
-// 使用 AES-256-CBC 解密算法解密数据的函数。
-pub fn aes_256_cbc_decrypt(data: &[u8]) -> anyhow::Result<Vec<u8>> {
-    let cipher = AesCbc::new_from_slices(DEFAULT_KEY.as_bytes(), &data[0..16])?;
+// 使用指定的 32 字节密钥进行 AES-256-CBC 解密。
+pub fn aes_256_cbc_decrypt_with_key(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.len() < 16 {
+        anyhow::bail!("密文长度不足");
+    }
+    let cipher = AesCbc::new_from_slices(key, &data[0..16])?;
     Ok(cipher.decrypt_vec(&data[16..])?)
 }
 
+// 生成随机的 AES-256 数据密钥。
+pub fn gen_data_key() -> Vec<u8> {
+    let mut key = vec![0u8; 32];
+    rand::thread_rng().fill(&mut key[..]);
+    key
+}
+
 // 定义 HmacSha256 类型为使用 SHA256 哈希函数的 HMAC。
 type HmacSha256 = Hmac<Sha256>;
 // 对数据进行 SHA256 哈希的函数，返回十六进制字符串。
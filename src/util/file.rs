@@ -16,3 +16,76 @@ pub fn file_type_from_meta_info(file_path: &str) -> anyhow::Result<String> {
     let metadata = fs::load_metadata(&metainfo_path)?;
     Ok(metadata.file_type)
 }
+
+// 常见文件格式的魔数签名。
+const MAGIC_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"\x00asm", "application/wasm"),
+    (b"OggS", "audio/ogg"),
+    (b"ID3", "audio/mpeg"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+];
+
+// 根据文件内容的魔数推断文件类型。
+pub fn sniff_content_type(body: &[u8]) -> Option<&'static str> {
+    if body.len() >= 12 && &body[0..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if body.len() >= 12 && &body[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+    if let Some((_, mime)) = MAGIC_SIGNATURES
+        .iter()
+        .find(|(sig, _)| body.starts_with(sig))
+    {
+        return Some(mime);
+    }
+    let head = &body[..body.len().min(512)];
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start().to_ascii_lowercase();
+    if text.starts_with("<!doctype html") || text.starts_with("<html") {
+        return Some("text/html");
+    }
+    if text.starts_with("<?xml") {
+        return Some("application/xml");
+    }
+    None
+}
+
+// 是否为值得在传输时压缩的文本类文件类型。
+pub fn is_compressible(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime.as_str(),
+            "application/json"
+                | "application/javascript"
+                | "application/xml"
+                | "application/x-ndjson"
+                | "application/wasm"
+                | "image/svg+xml"
+        )
+}
+
+// 推断对象的文件类型：优先按扩展名，其次按内容魔数，都无法识别时为 application/octet-stream。
+pub fn detect_content_type(file_name: &str, body: &[u8]) -> String {
+    mime_guess::from_path(file_name)
+        .first()
+        .map(|mime| mime.to_string())
+        .or_else(|| sniff_content_type(body).map(str::to_string))
+        .unwrap_or_else(|| "application/octet-stream".to_string())
+}
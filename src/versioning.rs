@@ -0,0 +1,420 @@
+use crate::access;
+use crate::api::DATA_DIR;
+use crate::fs::{self, Backend, Metadata};
+use anyhow::Context;
+use chrono::Utc;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// --- 多版本：桶开启版本控制（PutBucketVersioning）后，覆盖和删除对象时保留原有版本。当前版本
+// 仍是桶目录下的元数据文件，列表和读取不受影响；历史版本和删除标记保存在
+// versions/<桶>/<键>/<版本号>.meta，按 versionId 读取、删除，或由 ListObjectVersions 列出。
+// 版本号由写入对象的日志序号生成，各节点一致且按写入顺序递增。暂停版本控制后写入的版本和删除
+// 标记为 null 版本，替换之前的 null 版本。直通存储的对象、暂存上传和重命名不保留历史版本
+
+// 桶版本控制状态文件名，内容为 Enabled 或 Suspended
+const VERSIONING_CONFIG_FILE: &str = ".versioning";
+// 数据目录下保存历史版本的目录
+const VERSIONS_PATH_SUFFIX: &str = "versions";
+// 未开启版本控制时写入的对象的版本号
+pub const NULL_VERSION_ID: &str = "null";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersioningStatus {
+    Enabled,
+    Suspended,
+}
+
+impl VersioningStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersioningStatus::Enabled => "Enabled",
+            VersioningStatus::Suspended => "Suspended",
+        }
+    }
+}
+
+impl FromStr for VersioningStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Enabled" => Ok(VersioningStatus::Enabled),
+            "Suspended" => Ok(VersioningStatus::Suspended),
+            _ => Err(format!("unknown versioning status `{}`", s)),
+        }
+    }
+}
+
+// PutBucketVersioning 请求体和 GetBucketVersioning 返回结果，从未开启过时 Status 为空
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename = "VersioningConfiguration")]
+pub struct VersioningConfiguration {
+    #[serde(rename = "Status", default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+// 解析 PutBucketVersioning 请求体
+pub fn parse_config(xml: &str) -> Result<VersioningStatus, String> {
+    let conf: VersioningConfiguration =
+        quick_xml::de::from_str(xml).map_err(|err| err.to_string())?;
+    conf.status
+        .ok_or_else(|| "Status is required".to_string())?
+        .parse()
+}
+
+// 版本号：日志序号和日志内序号（批量删除时每个对象一个）的十六进制，按写入顺序递增
+pub fn version_id(log_index: u64, seq: usize) -> String {
+    format!("{:016x}{:04x}", log_index, seq)
+}
+
+// 列出版本时从 key-marker 和 version-id-marker 之后开始的位置，entries 为已排序的（键，版本号）
+pub fn start_index(
+    entries: &[(String, String)],
+    key_marker: Option<&str>,
+    version_id_marker: Option<&str>,
+) -> usize {
+    let Some(key_marker) = key_marker.filter(|marker| !marker.is_empty()) else {
+        return 0;
+    };
+    if let Some(version_id_marker) = version_id_marker.filter(|marker| !marker.is_empty()) {
+        if let Some(pos) = entries
+            .iter()
+            .position(|(key, vid)| key == key_marker && vid == version_id_marker)
+        {
+            return pos + 1;
+        }
+    }
+    entries
+        .iter()
+        .position(|(key, _)| key.as_str() > key_marker)
+        .unwrap_or(entries.len())
+}
+
+pub(crate) fn config_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, VERSIONING_CONFIG_FILE)
+}
+
+// 桶的版本控制状态，从未开启过时为空
+pub(crate) fn status(bucket: &str) -> Option<VersioningStatus> {
+    std::fs::read_to_string(config_path(bucket))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+// 元数据所属桶的版本控制状态
+fn status_of(meta_file_path: &str) -> Option<VersioningStatus> {
+    let object_path = fs::object_path_from_meta(meta_file_path)?;
+    status(object_path.split('/').next()?)
+}
+
+// 桶的历史版本目录
+pub(crate) fn bucket_versions_dir(bucket: &str) -> PathBuf {
+    versions_root().join(bucket)
+}
+
+pub(crate) fn versions_root() -> PathBuf {
+    PathBuf::from(DATA_DIR.get().unwrap()).join(VERSIONS_PATH_SUFFIX)
+}
+
+// 对象（当前版本的元数据路径）的历史版本目录
+fn versions_dir_of(meta_file_path: &str) -> Option<PathBuf> {
+    Some(versions_root().join(fs::object_path_from_meta(meta_file_path)?))
+}
+
+fn version_path(meta_file_path: &str, version_id: &str) -> anyhow::Result<PathBuf> {
+    let dir = versions_dir_of(meta_file_path).context("解析对象路径失败")?;
+    Ok(dir.join(format!("{}.meta", version_id)))
+}
+
+// 桶中是否还有历史版本或删除标记
+pub(crate) fn has_versions(bucket: &str) -> bool {
+    let mut meta_files = vec![];
+    let dir = bucket_versions_dir(bucket);
+    dir.is_dir() && fs::walk_meta_files(&dir, &mut meta_files).is_ok() && !meta_files.is_empty()
+}
+
+fn version_of(metadata: &Metadata) -> &str {
+    metadata.version_id.as_deref().unwrap_or(NULL_VERSION_ID)
+}
+
+fn load_current(meta_file_path: &str) -> anyhow::Result<Option<Metadata>> {
+    if !Path::new(meta_file_path).is_file() {
+        return Ok(None);
+    }
+    fs::load_metadata(meta_file_path).map(Some)
+}
+
+// 把当前版本复制为历史版本，返回复制出的文件；暂停版本控制时 null 版本会被替换，不保留。
+// applying 为正在应用的日志对应的版本号
+fn archive_current(
+    meta_file_path: &str,
+    status: VersioningStatus,
+    applying: &str,
+) -> anyhow::Result<Option<PathBuf>> {
+    let Some(current) = load_current(meta_file_path)? else {
+        return Ok(None);
+    };
+    // 直通存储的原文件会被覆盖，无法保留
+    if current.backend == Backend::Passthrough {
+        return Ok(None);
+    }
+    if current.version_id.is_none() && status == VersioningStatus::Suspended {
+        return Ok(None);
+    }
+    // 重启后回放日志时当前版本可能比正在应用的日志更新，之后的日志会重新写入它，不复制
+    if current
+        .version_id
+        .as_deref()
+        .is_some_and(|id| id >= applying)
+    {
+        return Ok(None);
+    }
+    let path = version_path(meta_file_path, version_of(&current))?;
+    fs::save_metadata(&path, &current)?;
+    Ok(Some(path))
+}
+
+fn remove_version_file(path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err).context("删除历史版本失败"),
+        _ => {
+            // 键的最后一个历史版本被删除后删除空目录
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::remove_dir(dir);
+            }
+            Ok(())
+        }
+    }
+}
+
+// 写入对象的当前版本；桶开启过版本控制时原有的当前版本转为历史版本
+pub(crate) fn save_current(
+    meta_file_path: &str,
+    metadata: &mut Metadata,
+    log_index: u64,
+) -> anyhow::Result<()> {
+    let status = status_of(meta_file_path).filter(|_| metadata.backend == Backend::Dedup);
+    let Some(status) = status else {
+        metadata.version_id = None;
+        return fs::save_metadata(meta_file_path, metadata);
+    };
+    metadata.version_id = match status {
+        VersioningStatus::Enabled => Some(version_id(log_index, 0)),
+        VersioningStatus::Suspended => None,
+    };
+    metadata.delete_marker = false;
+    let archived = archive_current(meta_file_path, status, &version_id(log_index, 0))?;
+    if let Err(err) = fs::save_metadata(meta_file_path, metadata) {
+        if let Some(archived) = archived {
+            let _ = std::fs::remove_file(archived);
+        }
+        return Err(err);
+    }
+    if status == VersioningStatus::Suspended {
+        remove_version_file(&version_path(meta_file_path, NULL_VERSION_ID)?)?;
+    }
+    Ok(())
+}
+
+// 对象的全部历史版本，从新到旧
+fn noncurrent_versions(meta_file_path: &str) -> anyhow::Result<Vec<(PathBuf, Metadata)>> {
+    let mut versions = vec![];
+    let Some(dir) = versions_dir_of(meta_file_path) else {
+        return Ok(versions);
+    };
+    for entry in std::fs::read_dir(&dir).into_iter().flatten() {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "meta") {
+            let metadata = fs::load_metadata(&path)?;
+            versions.push((path, metadata));
+        }
+    }
+    versions.sort_by_key(|(_, metadata)| std::cmp::Reverse(order_key(metadata)));
+    Ok(versions)
+}
+
+fn order_key(metadata: &Metadata) -> (i64, String) {
+    (
+        metadata.time.timestamp_micros(),
+        version_of(metadata).to_string(),
+    )
+}
+
+// 当前版本被删除后，最新的历史版本不是删除标记时恢复为当前版本
+fn promote_latest(meta_file_path: &str) -> anyhow::Result<()> {
+    if Path::new(meta_file_path).exists() {
+        return Ok(());
+    }
+    let Some((path, latest)) = noncurrent_versions(meta_file_path)?.into_iter().next() else {
+        return Ok(());
+    };
+    if latest.delete_marker {
+        return Ok(());
+    }
+    fs::save_metadata(meta_file_path, &latest)?;
+    remove_version_file(&path)
+}
+
+// 删除对象：未指定版本时写入删除标记，原有的当前版本转为历史版本；指定版本时永久删除该版本。
+// 桶从未开启过版本控制或对象保存在直通存储时返回 false，由调用方直接删除
+pub(crate) fn delete(
+    meta_file_path: &str,
+    version_id: Option<&str>,
+    marker_id: String,
+) -> anyhow::Result<bool> {
+    let Some(status) = status_of(meta_file_path) else {
+        return Ok(false);
+    };
+    let current = load_current(meta_file_path)?;
+    if current
+        .as_ref()
+        .is_some_and(|current| current.backend == Backend::Passthrough)
+    {
+        return Ok(false);
+    }
+    let remove_current = || -> anyhow::Result<()> {
+        std::fs::remove_file(meta_file_path).context("删除文件失败")?;
+        if let Err(err) = access::forget(meta_file_path) {
+            info!("删除访问记录失败: {}", err);
+        }
+        Ok(())
+    };
+    match version_id {
+        None => {
+            archive_current(meta_file_path, status, &marker_id)?;
+            let marker_id = match status {
+                VersioningStatus::Enabled => marker_id,
+                VersioningStatus::Suspended => NULL_VERSION_ID.to_string(),
+            };
+            let name = Path::new(meta_file_path)
+                .file_stem()
+                .context("解析文件名失败")?
+                .to_string_lossy()
+                .to_string();
+            let marker = Metadata {
+                name,
+                size: 0,
+                file_type: String::new(),
+                time: Utc::now(),
+                chunks: vec![],
+                chunk_sizes: vec![],
+                backend: Backend::Dedup,
+                website_redirect: None,
+                headers: vec![],
+                scan: None,
+                etag: String::new(),
+                checksum_sha256: None,
+                version_id: (marker_id != NULL_VERSION_ID).then(|| marker_id.clone()),
+                delete_marker: true,
+                encryption: None,
+                tags: vec![],
+            };
+            fs::save_metadata(version_path(meta_file_path, &marker_id)?, &marker)?;
+            if current.is_some() {
+                remove_current()?;
+            }
+        }
+        Some(version_id) => {
+            if current
+                .as_ref()
+                .is_some_and(|current| version_of(current) == version_id)
+            {
+                remove_current()?;
+            } else {
+                remove_version_file(&version_path(meta_file_path, version_id)?)?;
+            }
+            promote_latest(meta_file_path)?;
+        }
+    }
+    Ok(true)
+}
+
+// 按版本号找到元数据文件：当前版本或历史版本
+pub(crate) fn resolve(meta_file_path: &str, version_id: &str) -> Option<PathBuf> {
+    if let Ok(Some(current)) = load_current(meta_file_path) {
+        if version_of(&current) == version_id {
+            return Some(PathBuf::from(meta_file_path));
+        }
+    }
+    let path = version_path(meta_file_path, version_id).ok()?;
+    path.is_file().then_some(path)
+}
+
+// ListObjectVersions 中的一个版本
+pub(crate) struct ObjectVersion {
+    pub key: String,
+    pub version_id: String,
+    pub is_latest: bool,
+    pub metadata: Metadata,
+}
+
+// 桶中键以 prefix 开头的全部版本，按键排序，同一个键内从新到旧；无法读取的元数据跳过
+pub(crate) fn list(
+    bucket: &str,
+    bucket_path: &Path,
+    prefix: &str,
+) -> anyhow::Result<Vec<ObjectVersion>> {
+    let mut keys: BTreeMap<String, (Option<Metadata>, Vec<Metadata>)> = BTreeMap::new();
+    let mut meta_files = vec![];
+    fs::walk_meta_files(bucket_path, &mut meta_files).context("遍历桶目录失败")?;
+    for meta_file in meta_files {
+        let Some(key) = relative_key(bucket_path, &meta_file, true) else {
+            continue;
+        };
+        if !key.starts_with(prefix) {
+            continue;
+        }
+        match fs::load_metadata(&meta_file) {
+            Ok(metadata) => keys.entry(key).or_default().0 = Some(metadata),
+            Err(err) => info!("跳过无法读取的元数据 {:?}: {}", meta_file, err),
+        }
+    }
+    let versions_dir = bucket_versions_dir(bucket);
+    let mut version_files = vec![];
+    if versions_dir.is_dir() {
+        fs::walk_meta_files(&versions_dir, &mut version_files).context("遍历历史版本失败")?;
+    }
+    for version_file in version_files {
+        let Some(key) = relative_key(&versions_dir, &version_file, false) else {
+            continue;
+        };
+        if !key.starts_with(prefix) {
+            continue;
+        }
+        match fs::load_metadata(&version_file) {
+            Ok(metadata) => keys.entry(key).or_default().1.push(metadata),
+            Err(err) => info!("跳过无法读取的元数据 {:?}: {}", version_file, err),
+        }
+    }
+    let mut versions = vec![];
+    for (key, (current, mut noncurrent)) in keys {
+        noncurrent.sort_by_key(|metadata| std::cmp::Reverse(order_key(metadata)));
+        for (i, metadata) in current.into_iter().chain(noncurrent).enumerate() {
+            versions.push(ObjectVersion {
+                key: key.clone(),
+                version_id: version_of(&metadata).to_string(),
+                is_latest: i == 0,
+                metadata,
+            });
+        }
+    }
+    Ok(versions)
+}
+
+// 元数据文件对应的键：当前版本为去掉 .meta 的相对路径，历史版本为所在目录的相对路径
+fn relative_key(root: &Path, meta_file: &Path, current: bool) -> Option<String> {
+    let relative = meta_file.strip_prefix(root).ok()?;
+    if current {
+        let relative = relative.to_string_lossy();
+        Some(relative.strip_suffix(".meta")?.to_string())
+    } else {
+        Some(relative.parent()?.to_string_lossy().to_string())
+    }
+}
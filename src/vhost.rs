@@ -0,0 +1,106 @@
+use crate::config;
+use ntex::http::header;
+use ntex::http::Uri;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web;
+
+// --- 虚拟主机风格寻址：配置 --vhost-domain（如 s3.localhost）后，Host 为 `<桶>.<域名>` 的请求
+// 改写为路径风格，`http://photos.s3.localhost:9000/api/cat.jpg` 按 `/api/photos/cat.jpg` 处理，
+// 路径仍需以 /api 开头。签名按客户端发送的原始路径计算，改写前的路径保存在请求扩展中供认证使用。
+// 配合 --dns-addr 的 DNS 应答（见 dns.rs）无需在 /etc/hosts 中逐个添加桶名
+
+// 改写前的请求路径
+#[derive(Debug, Clone)]
+pub struct OriginalPath(pub String);
+
+// Host（可带端口）为 domain 的子域名时返回桶名，忽略大小写
+pub fn bucket_of_host<'a>(host: &'a str, domain: &str) -> Option<&'a str> {
+    let host = host.split(':').next().unwrap_or(host).trim_end_matches('.');
+    let domain = domain.trim_end_matches('.');
+    let split = host.len().checked_sub(domain.len() + 1)?;
+    let (bucket, suffix) = host.split_at_checked(split)?;
+    match suffix.strip_prefix('.') {
+        Some(suffix) if suffix.eq_ignore_ascii_case(domain) && !bucket.is_empty() => Some(bucket),
+        _ => None,
+    }
+}
+
+// 把虚拟主机风格的路径改写为路径风格，不以 /api 开头的路径不改写
+pub fn rewrite_path(path: &str, bucket: &str) -> Option<String> {
+    let rest = path.strip_prefix("/api")?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(format!("/api/{}{}", bucket, rest))
+}
+
+// 参与签名计算的请求路径
+pub(crate) fn signed_path(request: &web::WebRequest<impl web::ErrorRenderer>) -> String {
+    match request.extensions().get::<OriginalPath>() {
+        Some(original) => original.0.clone(),
+        None => request.uri().path().to_owned(),
+    }
+}
+
+fn rewrite<Err>(req: &mut web::WebRequest<Err>, domain: &str) {
+    let host = match req.headers().get(header::HOST) {
+        Some(value) => value.to_str().ok().map(str::to_owned),
+        None => req.uri().host().map(str::to_owned),
+    };
+    let Some(host) = host else {
+        return;
+    };
+    let Some(bucket) = bucket_of_host(&host, domain) else {
+        return;
+    };
+    let original = req.uri().path().to_owned();
+    let Some(path) = rewrite_path(&original, bucket) else {
+        return;
+    };
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    let Ok(uri) = path_and_query.parse::<Uri>() else {
+        return;
+    };
+    req.head_mut().uri = uri.clone();
+    req.match_info_mut().set(uri);
+    req.extensions_mut().insert(OriginalPath(original));
+}
+
+pub struct VirtualHost;
+
+impl<S> Middleware<S> for VirtualHost {
+    type Service = VirtualHostMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        VirtualHostMiddleware { service }
+    }
+}
+
+pub struct VirtualHostMiddleware<S> {
+    service: S,
+}
+
+impl<S, Err> Service<web::WebRequest<Err>> for VirtualHostMiddleware<S>
+where
+    S: Service<web::WebRequest<Err>, Response = web::WebResponse, Error = web::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = web::WebResponse;
+    type Error = web::Error;
+
+    ntex::forward_poll_ready!(service);
+
+    async fn call(
+        &self,
+        mut req: web::WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if let Some(domain) = &config::get().vhost_domain {
+            rewrite(&mut req, domain);
+        }
+        ctx.call(&self.service, req).await
+    }
+}
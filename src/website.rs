@@ -0,0 +1,177 @@
+use crate::fs;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// 桶静态网站配置文件名
+const WEBSITE_CONFIG_FILE: &str = ".website.xml";
+
+// 静态网站配置（PutBucketWebsite 请求体），目前只使用其中的重定向规则
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename = "WebsiteConfiguration")]
+pub struct WebsiteConfiguration {
+    #[serde(
+        rename = "RoutingRules",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub routing_rules: Option<RoutingRules>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingRules {
+    #[serde(rename = "RoutingRule", default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+// 单条重定向规则，条件为空时匹配所有请求
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingRule {
+    #[serde(rename = "Condition", default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition>,
+    #[serde(rename = "Redirect")]
+    pub redirect: Redirect,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Condition {
+    #[serde(
+        rename = "KeyPrefixEquals",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub key_prefix_equals: Option<String>,
+    #[serde(
+        rename = "HttpErrorCodeReturnedEquals",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub http_error_code_returned_equals: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Redirect {
+    #[serde(rename = "HostName", default, skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+    #[serde(
+        rename = "HttpRedirectCode",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub http_redirect_code: Option<u16>,
+    #[serde(rename = "Protocol", default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(
+        rename = "ReplaceKeyPrefixWith",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replace_key_prefix_with: Option<String>,
+    #[serde(
+        rename = "ReplaceKeyWith",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replace_key_with: Option<String>,
+}
+
+impl WebsiteConfiguration {
+    // 校验配置：重定向状态码必须是 3xx，协议只能是 http/https，两种替换方式不能同时出现
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in self.rules() {
+            let redirect = &rule.redirect;
+            if let Some(code) = redirect.http_redirect_code {
+                if !(300..400).contains(&code) {
+                    return Err(format!("invalid HttpRedirectCode {}", code));
+                }
+            }
+            if let Some(protocol) = &redirect.protocol {
+                if protocol != "http" && protocol != "https" {
+                    return Err(format!("invalid Protocol {}", protocol));
+                }
+            }
+            if redirect.replace_key_with.is_some() && redirect.replace_key_prefix_with.is_some() {
+                return Err("ReplaceKeyWith and ReplaceKeyPrefixWith are exclusive".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn rules(&self) -> &[RoutingRule] {
+        self.routing_rules
+            .as_ref()
+            .map(|r| r.rules.as_slice())
+            .unwrap_or_default()
+    }
+
+    // 按配置顺序查找第一条命中的规则，返回重定向状态码和 Location；
+    // error_code 为读取对象时返回的错误码，为 None 时只匹配不带错误码条件的规则
+    pub fn redirect_for(
+        &self,
+        bucket: &str,
+        key: &str,
+        error_code: Option<u16>,
+    ) -> Option<(u16, String)> {
+        self.rules().iter().find_map(|rule| {
+            let (prefix, code) = match &rule.condition {
+                Some(c) => (
+                    c.key_prefix_equals.as_deref(),
+                    c.http_error_code_returned_equals,
+                ),
+                None => (None, None),
+            };
+            if code.is_some() && code != error_code {
+                return None;
+            }
+            let prefix = prefix.unwrap_or_default();
+            let rest = key.strip_prefix(prefix)?;
+            let redirect = &rule.redirect;
+            let new_key = match (
+                &redirect.replace_key_with,
+                &redirect.replace_key_prefix_with,
+            ) {
+                (Some(k), _) => k.clone(),
+                (None, Some(p)) => format!("{}{}", p, rest),
+                (None, None) => key.to_string(),
+            };
+            let location = match &redirect.host_name {
+                Some(host) => format!(
+                    "{}://{}/{}",
+                    redirect.protocol.as_deref().unwrap_or("http"),
+                    host,
+                    new_key
+                ),
+                None => format!("/api/{}/{}", bucket, new_key),
+            };
+            Some((redirect.http_redirect_code.unwrap_or(301), location))
+        })
+    }
+}
+
+// 对象的 x-amz-website-redirect-location 只允许站内绝对路径或 http(s) 地址
+pub fn is_valid_redirect_location(location: &str) -> bool {
+    location.starts_with('/') || location.starts_with("http://") || location.starts_with("https://")
+}
+
+// 将对象上保存的重定向地址转换成 Location，站内路径相对于桶的根目录
+pub fn resolve_redirect_location(bucket: &str, location: &str) -> String {
+    if location.starts_with('/') {
+        format!("/api/{}{}", bucket, location)
+    } else {
+        location.to_string()
+    }
+}
+
+// 桶静态网站配置文件路径
+pub(crate) fn config_path(bucket: &str) -> PathBuf {
+    fs::bucket_config_path(bucket, WEBSITE_CONFIG_FILE)
+}
+
+// 读取桶的静态网站配置原文
+pub(crate) fn load_raw(bucket: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(bucket)).ok()
+}
+
+// 读取并解析桶的静态网站配置，不存在或无法解析时返回 None
+pub(crate) fn load(bucket: &str) -> Option<WebsiteConfiguration> {
+    quick_xml::de::from_str(&load_raw(bucket)?).ok()
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+    use rs_s3_local::access::{is_unread_for, merge, AccessRecord, AccessRecords};
+
+    #[test]
+    fn test1() {
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let mut records = AccessRecords::new();
+        records.insert(
+            "a.txt".to_string(),
+            AccessRecord {
+                count: 3,
+                last_access: t0 + Duration::hours(2),
+            },
+        );
+        merge(
+            &mut records,
+            vec![
+                (
+                    "a.txt".to_string(),
+                    AccessRecord {
+                        count: 2,
+                        last_access: t0 + Duration::hours(1),
+                    },
+                ),
+                (
+                    "b.txt".to_string(),
+                    AccessRecord {
+                        count: 1,
+                        last_access: t0,
+                    },
+                ),
+            ],
+        );
+        assert_eq!(records["a.txt"].count, 5);
+        assert_eq!(records["a.txt"].last_access, t0 + Duration::hours(2));
+        assert_eq!(records["b.txt"].count, 1);
+    }
+
+    #[test]
+    fn test2() {
+        let modified = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let now = modified + Duration::days(40);
+        assert!(is_unread_for(None, modified, 30, now));
+        assert!(!is_unread_for(None, modified, 60, now));
+        assert!(!is_unread_for(
+            Some(now - Duration::days(3)),
+            modified,
+            30,
+            now
+        ));
+        // 重新上传后以上传时间重新计算
+        let old_read = modified - Duration::days(100);
+        assert!(!is_unread_for(
+            Some(old_read),
+            now - Duration::days(1),
+            30,
+            now
+        ));
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::admin::{du_groups, ratio, DedupStats};
+
+    #[test]
+    fn test1() {
+        assert_eq!(
+            du_groups("logs/2024/01/a.gz", "", 2),
+            vec!["logs/", "logs/2024/"]
+        );
+        assert_eq!(
+            du_groups("logs/2024/01/a.gz", "logs/", 5),
+            vec!["logs/2024/", "logs/2024/01/"]
+        );
+        assert!(du_groups("top.txt", "", 2).is_empty());
+        assert!(du_groups("img/a.png", "logs/", 2).is_empty());
+        assert!(du_groups("logs/a", "logs/", 0).is_empty());
+    }
+
+    #[test]
+    fn test2() {
+        let mut stats = DedupStats::default();
+        let chunks = vec!["a".to_string(), "b".to_string()];
+        let stored = |hash: &str| if hash == "a" { 10 } else { 20 };
+        stats.add_chunks(300, &chunks, &[100, 200], stored);
+        stats.add_chunks(300, &chunks, &[100, 200], stored);
+        // 旧版本写入的单分片对象没有分片大小
+        stats.add_chunks(50, &["c".to_string()], &[], |_| 5);
+        stats.add_file(40, 40);
+        assert_eq!(stats.objects, 4);
+        assert_eq!(stats.logical_size, 690);
+        assert_eq!(stats.deduplicated_size, 390);
+        assert_eq!(stats.physical_size, 75);
+        assert_eq!(stats.chunks, 3);
+        assert_eq!(stats.chunk_references, 5);
+        assert_eq!(ratio(690, 390), 1.77);
+        assert_eq!(ratio(0, 0), 1.0);
+    }
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod test {
     use quick_xml::se::to_string;
-    use rs_s3_local::model::{Bucket, BucketWrapper, ListBucketResp, Owner};
+    use rs_s3_local::api::is_valid_bucket_name;
+    use rs_s3_local::model::{
+        Bucket, BucketWrapper, Delete, DeleteError, DeleteResult, DeletedObject, ListBucketResp,
+        Owner,
+    };
     use serde::{Deserialize, Serialize};
 
     #[test]
@@ -9,17 +13,20 @@ mod test {
         let mut buckets = Vec::new();
         buckets.push(Bucket {
             name: "xx".to_string(),
-            creation_date: "111".to_string(),
+            creation_date: "2023-05-29T08:00:00Z".parse().unwrap(),
         });
         let list_res = ListBucketResp {
-            id: "20230529".to_string(),
             owner: Owner {
+                id: "20230529".to_string(),
                 display_name: "minioadmin".to_string(),
             },
             buckets: BucketWrapper { bucket: buckets },
         };
         let xml = to_string(&list_res);
         assert!(xml.is_ok(), "序列化错误");
+        let xml = xml.unwrap();
+        assert!(xml.starts_with("<ListAllMyBucketsResult>"));
+        assert!(xml.contains("<CreationDate>2023-05-29T08:00:00Z</CreationDate>"));
     }
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -60,4 +67,49 @@ mod test {
         let xml = to_string(&person);
         assert!(xml.is_ok(), "序列化失败");
     }
+
+    #[test]
+    fn test3() {
+        let xml = r#"<Delete xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Object><Key>a.txt</Key></Object><Object><Key>b/c.txt</Key><VersionId>null</VersionId></Object><Quiet>true</Quiet></Delete>"#;
+        let delete: Delete = quick_xml::de::from_str(xml).unwrap();
+        let keys: Vec<&str> = delete.objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, ["a.txt", "b/c.txt"]);
+        assert!(delete.quiet);
+
+        let res = DeleteResult {
+            deleted: vec![DeletedObject {
+                key: "a.txt".to_string(),
+                version_id: None,
+            }],
+            errors: vec![DeleteError {
+                key: "../x".to_string(),
+                code: "InvalidArgument".to_string(),
+                message: "Invalid object key".to_string(),
+            }],
+        };
+        assert_eq!(
+            to_string(&res).unwrap(),
+            "<DeleteResult><Deleted><Key>a.txt</Key></Deleted><Error><Key>../x</Key>\
+             <Code>InvalidArgument</Code><Message>Invalid object key</Message></Error></DeleteResult>"
+        );
+    }
+
+    #[test]
+    fn test4() {
+        for name in ["abc", "my-bucket.2024", "a".repeat(63).as_str()] {
+            assert!(is_valid_bucket_name(name), "{}", name);
+        }
+        for name in [
+            "ab",
+            "a".repeat(64).as_str(),
+            "MyBucket",
+            "my_bucket",
+            "-bucket",
+            "bucket.",
+            "my..bucket",
+            "192.168.1.1",
+        ] {
+            assert!(!is_valid_bucket_name(name), "{}", name);
+        }
+    }
 }
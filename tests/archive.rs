@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::archive::{
+        entry_key, padding, read_entry, tar_entries, tar_header, zip_entries, ArchiveFormat,
+    };
+    use std::io::Cursor;
+
+    fn checksum_ok(block: &[u8]) -> bool {
+        let stored = std::str::from_utf8(&block[148..154]).unwrap();
+        let expected: u32 = block
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    *b as u32
+                }
+            })
+            .sum();
+        u32::from_str_radix(stored, 8).unwrap() == expected
+    }
+
+    #[test]
+    fn test1() {
+        let header = tar_header("a/b.txt", 1000, 0);
+        assert_eq!(header.len(), 512);
+        assert_eq!(&header[..7], b"a/b.txt");
+        assert_eq!(&header[124..135], b"00000001750");
+        assert_eq!(&header[257..262], b"ustar");
+        assert!(checksum_ok(&header));
+        assert_eq!(padding(1000), 24);
+        assert_eq!(padding(1024), 0);
+    }
+
+    #[test]
+    fn test2() {
+        // 超过 100 字节的路径拆到 prefix 字段
+        let path = format!("{}/{}", "d".repeat(120), "f".repeat(50));
+        let header = tar_header(&path, 1, 0);
+        assert_eq!(header.len(), 512);
+        assert_eq!(&header[..50], "f".repeat(50).as_bytes());
+        assert_eq!(&header[345..465], "d".repeat(120).as_bytes());
+        // 无法拆分的长路径使用 GNU 长文件名条目
+        let path = "x".repeat(300);
+        let header = tar_header(&path, 1, 0);
+        assert_eq!(header.len(), 512 * 3);
+        assert_eq!(header[156], b'L');
+        assert_eq!(&header[512..812], path.as_bytes());
+        assert_eq!(header[1024 + 156], b'0');
+        assert!(checksum_ok(&header[1024..]));
+    }
+
+    #[test]
+    fn test3() {
+        // 生成的 tar 可以重新解析
+        let long = format!("{}/{}", "d".repeat(120), "f".repeat(120));
+        let mut tar = Vec::new();
+        for (path, data) in [("a.txt", &b"hello"[..]), (long.as_str(), &b"world!"[..])] {
+            tar.extend(tar_header(path, data.len() as u64, 0));
+            tar.extend_from_slice(data);
+            tar.resize(tar.len() + padding(data.len() as u64), 0);
+        }
+        tar.resize(tar.len() + 1024, 0);
+        assert_eq!(ArchiveFormat::detect(&tar), Some(ArchiveFormat::Tar));
+        let mut reader = Cursor::new(tar.as_slice());
+        let entries = tar_entries(&mut reader).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].path, long);
+        assert_eq!(read_entry(&mut reader, &entries[0]).unwrap(), b"hello");
+        assert_eq!(read_entry(&mut reader, &entries[1]).unwrap(), b"world!");
+    }
+
+    #[test]
+    fn test4() {
+        // 只含一个 stored 条目的 zip
+        let (name, data, crc) = (b"dir/a.txt", b"hello", 0x3610a686u32);
+        let mut zip = b"PK\x03\x04\x0a\0\0\0\0\0\0\0\0\0".to_vec();
+        zip.extend(crc.to_le_bytes());
+        zip.extend(5u32.to_le_bytes());
+        zip.extend(5u32.to_le_bytes());
+        zip.extend([name.len() as u8, 0, 0, 0]);
+        zip.extend(name);
+        zip.extend(data);
+        let directory = zip.len() as u32;
+        zip.extend(b"PK\x01\x02\x14\0\x0a\0\0\0\0\0\0\0\0\0");
+        zip.extend(crc.to_le_bytes());
+        zip.extend(5u32.to_le_bytes());
+        zip.extend(5u32.to_le_bytes());
+        zip.extend([
+            name.len() as u8,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]);
+        zip.extend(name);
+        let directory_size = zip.len() as u32 - directory;
+        zip.extend(b"PK\x05\x06\0\0\0\0\x01\0\x01\0");
+        zip.extend(directory_size.to_le_bytes());
+        zip.extend(directory.to_le_bytes());
+        zip.extend([0, 0]);
+        assert_eq!(ArchiveFormat::detect(&zip), Some(ArchiveFormat::Zip));
+        let mut reader = Cursor::new(zip.as_slice());
+        let entries = zip_entries(&mut reader).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "dir/a.txt");
+        assert_eq!(read_entry(&mut reader, &entries[0]).unwrap(), b"hello");
+        // CRC 不符时报错
+        zip[30 + name.len()] = b'j';
+        assert!(read_entry(&mut Cursor::new(zip.as_slice()), &entries[0]).is_err());
+    }
+
+    #[test]
+    fn test5() {
+        assert_eq!(
+            entry_key("seed/", "./a/b.txt").as_deref(),
+            Some("seed/a/b.txt")
+        );
+        assert_eq!(entry_key("", "/a//./b").as_deref(), Some("a/b"));
+        assert_eq!(entry_key("", "a/../../etc/passwd"), None);
+        assert_eq!(entry_key("", "./"), None);
+    }
+}
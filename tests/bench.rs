@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::bench::{parse_size, percentile};
+    use std::time::Duration;
+
+    #[test]
+    fn test1() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4KiB"), Ok(4096));
+        assert_eq!(parse_size("1.5MiB"), Ok(1572864));
+        assert_eq!(parse_size("2MB"), Ok(2_000_000));
+        assert!(parse_size("4XB").is_err());
+        assert!(parse_size("KiB").is_err());
+    }
+
+    #[test]
+    fn test2() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&samples[..1], 0.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::capacity::{FsStats, VolumeUsage, WatermarkState};
+    use rs_s3_local::config::DiskWatermarks;
+
+    #[test]
+    fn test1() {
+        let watermarks = DiskWatermarks::default();
+        let stats = FsStats {
+            block_size: 4096,
+            blocks: 1000,
+            blocks_free: 150,
+            blocks_avail: 100,
+            files: 100,
+            files_free: 90,
+            files_avail: 90,
+        };
+        let usage = VolumeUsage::new(vec!["/data".to_string()], &stats, &watermarks);
+        assert_eq!(usage.total_bytes, 4096 * 1000);
+        assert_eq!(usage.used_bytes, 4096 * 850);
+        assert_eq!(usage.free_bytes, 4096 * 100);
+        assert_eq!(usage.used_percent, 89.47);
+        assert_eq!(usage.inode_used_percent, 10.0);
+        assert_eq!(usage.state, WatermarkState::Warning);
+
+        let stats = FsStats {
+            files_free: 2,
+            files_avail: 2,
+            ..stats
+        };
+        let usage = VolumeUsage::new(vec![], &stats, &watermarks);
+        assert_eq!(usage.state, WatermarkState::Critical);
+        let usage = VolumeUsage::new(vec![], &FsStats::default(), &watermarks);
+        assert_eq!(usage.state, WatermarkState::Ok);
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use rs_s3_local::cdc::{object_events, ObjectState, Op};
+    use std::collections::BTreeMap;
+
+    fn state(etag: &str) -> ObjectState {
+        ObjectState {
+            etag: etag.to_string(),
+            version_id: "null".to_string(),
+            size: 1,
+        }
+    }
+
+    #[test]
+    fn test1() {
+        let before = BTreeMap::from([
+            ("b/kept".to_string(), state("a")),
+            ("b/changed".to_string(), state("a")),
+            ("b/dir/removed".to_string(), state("a")),
+        ]);
+        let after = BTreeMap::from([
+            ("b/kept".to_string(), state("a")),
+            ("b/changed".to_string(), state("b")),
+            ("b/added".to_string(), state("c")),
+        ]);
+        let events = object_events(7, Utc::now(), &before, &after);
+        let summary: Vec<_> = events
+            .iter()
+            .map(|e| {
+                (
+                    e.op,
+                    e.key.as_deref().unwrap(),
+                    e.before.is_some(),
+                    e.after.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Op::Put, "added", false, true),
+                (Op::Put, "changed", true, true),
+                (Op::Delete, "dir/removed", true, false),
+            ]
+        );
+        assert!(events.iter().all(|e| e.seq == 7 && e.bucket == "b"));
+    }
+}
@@ -0,0 +1,29 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::checksum::{
+        checksum_type, composite_sha256, is_mode_enabled, sha256_base64, sha256_of,
+    };
+
+    const HELLO: &str = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+    const WORLD: &str = "486EA46224D1BB4FB680F34F7C9AD96A8F24EC88BE73EA8E5A6C65260E9CB8A7";
+
+    #[test]
+    fn test1() {
+        let full = "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+        assert_eq!(sha256_of(b"hello"), full);
+        assert_eq!(sha256_base64(HELLO).as_deref(), Some(full));
+        assert_eq!(sha256_base64("ABCD"), None);
+        assert_eq!(checksum_type(full), "FULL_OBJECT");
+    }
+
+    #[test]
+    fn test2() {
+        let composite = composite_sha256(&[HELLO, WORLD]).unwrap();
+        assert_eq!(composite, "cwXbmyq8zXBsJW2z2X5f9I1nfP5NOlkEr7faDjlQ4eI=-2");
+        assert_eq!(checksum_type(&composite), "COMPOSITE");
+        assert_eq!(composite_sha256(&[HELLO, "xyz"]), None);
+        assert!(is_mode_enabled(Some("ENABLED")));
+        assert!(!is_mode_enabled(Some("DISABLED")));
+        assert!(!is_mode_enabled(None));
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::chunkstats::{histogram_bound, ChunkCensus, HistogramBucket};
+
+    #[test]
+    fn test1() {
+        assert_eq!(histogram_bound(0), 1);
+        assert_eq!(histogram_bound(1), 1);
+        assert_eq!(histogram_bound(3), 4);
+        assert_eq!(histogram_bound(4096), 4096);
+        assert_eq!(histogram_bound(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test2() {
+        let chunks = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let mut census = ChunkCensus::default();
+        census.add_object(300, &chunks(&["a", "b", "a"]), &[100, 100, 100]);
+        census.add_object(100, &chunks(&["a"]), &[]);
+        census.add_object(5, &chunks(&["c"]), &[]);
+        // 没有分片大小的多分片对象，大小记为 0
+        census.add_object(9, &chunks(&["d", "b"]), &[]);
+        let report = census.report(1, |hash| if hash == "a" { 42 } else { 0 });
+
+        assert_eq!(report.chunks, 4);
+        assert_eq!(report.references, 7);
+        assert_eq!(report.shared_chunks, 2);
+        assert_eq!(report.unique_size, 205);
+        assert_eq!(report.referenced_size, 505);
+        assert_eq!(report.average_chunk_size, 51);
+        assert_eq!(report.max_chunk_size, 100);
+        assert_eq!(report.dedup_ratio, 2.46);
+        assert_eq!(
+            report.size_histogram,
+            [
+                HistogramBucket {
+                    le: 1,
+                    chunks: 1,
+                    bytes: 0
+                },
+                HistogramBucket {
+                    le: 8,
+                    chunks: 1,
+                    bytes: 5
+                },
+                HistogramBucket {
+                    le: 128,
+                    chunks: 2,
+                    bytes: 200
+                },
+            ]
+        );
+        assert_eq!(
+            report.reference_histogram,
+            [
+                HistogramBucket {
+                    le: 1,
+                    chunks: 2,
+                    bytes: 5
+                },
+                HistogramBucket {
+                    le: 2,
+                    chunks: 1,
+                    bytes: 100
+                },
+                HistogramBucket {
+                    le: 4,
+                    chunks: 1,
+                    bytes: 100
+                },
+            ]
+        );
+        assert_eq!(report.top_shared.len(), 1);
+        assert_eq!(report.top_shared[0].hash, "a");
+        assert_eq!(report.top_shared[0].references, 3);
+        assert_eq!(report.top_shared[0].stored_size, 42);
+    }
+}
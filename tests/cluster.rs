@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::cluster::Ring;
+    use std::collections::BTreeMap;
+
+    fn ring(ids: &[u64]) -> Ring {
+        Ring::new(
+            ids.iter()
+                .map(|id| (*id, format!("127.0.0.1:900{}", id)))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test1() {
+        let three = ring(&[1, 2, 3]);
+        let two = ring(&[1, 2]);
+        for i in 0..200u32 {
+            let hash = format!("{:08X}{:056X}", i.wrapping_mul(0x9E37_79B9), i);
+            let owners = three.owners(&hash, 2);
+            assert_eq!(owners.len(), 2);
+            assert_ne!(owners[0], owners[1]);
+            // 移除节点 3 后，原本不归它的分片归属不变
+            let primary = three.owners(&hash, 1)[0];
+            if primary != 3 {
+                assert_eq!(two.owners(&hash, 1), vec![primary]);
+            }
+        }
+        assert_eq!(three.owners("00", 5).len(), 3);
+    }
+}
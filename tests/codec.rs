@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::codec::{decode, encode, is_zstd, Codec};
+
+    #[test]
+    fn test1() {
+        assert_eq!("zstd".parse(), Ok(Codec::Zstd(0)));
+        assert_eq!("zstd:19".parse(), Ok(Codec::Zstd(19)));
+        assert_eq!("zstd:-5".parse(), Ok(Codec::Zstd(-5)));
+        assert_eq!("lz4".parse(), Ok(Codec::Lz4));
+        assert_eq!("none".parse(), Ok(Codec::Uncompressed));
+        assert!("zstd:99".parse::<Codec>().is_err());
+        assert!("gzip".parse::<Codec>().is_err());
+        assert_eq!(Codec::default(), Codec::Zstd(0));
+    }
+
+    #[test]
+    fn test2() {
+        let data = b"mixed codecs coexist ".repeat(1000);
+        for codec in [
+            Codec::Zstd(0),
+            Codec::Zstd(19),
+            Codec::Lz4,
+            Codec::Uncompressed,
+        ] {
+            let chunk = encode(&data, codec).unwrap();
+            assert_eq!(decode(&chunk).unwrap(), data);
+            assert_eq!(is_zstd(&chunk), matches!(codec, Codec::Zstd(_)));
+        }
+        assert!(encode(&data, Codec::Lz4).unwrap().len() < data.len() / 10);
+        // 旧版本写入的分片是不带头部的 zstd 帧
+        let legacy = zstd::stream::encode_all(&data[..], 3).unwrap();
+        assert_eq!(decode(&legacy).unwrap(), data);
+        assert!(decode(b"\0RSC\x07").is_err());
+        // lz4 头部的原始大小超过分片大小上限时直接拒绝，不按其分配内存
+        let mut forged = b"\0RSC\x04".to_vec();
+        forged.extend_from_slice(&u64::MAX.to_le_bytes());
+        forged.extend_from_slice(&[0x10, b'a']);
+        let err = decode(&forged).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::compat::xml_values;
+
+    #[test]
+    fn test1() {
+        let xml = "<ListBucketResult><Contents><Key>a</Key></Contents><Contents><Key>b/c</Key></Contents>\
+            <CommonPrefixes><Prefix>d/</Prefix></CommonPrefixes><KeyCount>2</KeyCount></ListBucketResult>";
+        assert_eq!(xml_values(xml, "Key"), vec!["a", "b/c"]);
+        assert_eq!(xml_values(xml, "KeyCount"), vec!["2"]);
+        let prefixes = xml_values(xml, "CommonPrefixes");
+        assert_eq!(xml_values(&prefixes[0], "Prefix"), vec!["d/"]);
+        assert!(xml_values(xml, "UploadId").is_empty());
+        assert!(xml_values("<Key>unterminated", "Key").is_empty());
+    }
+}
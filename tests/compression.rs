@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::compression::{Controller, CpuTimes, Load};
+
+    #[test]
+    fn test1() {
+        let busy = Load {
+            cpu: 0.95,
+            queued: 0,
+            threads: 4,
+        };
+        let queued = Load {
+            cpu: 0.6,
+            queued: 4,
+            threads: 4,
+        };
+        let idle = Load {
+            cpu: 0.1,
+            queued: 0,
+            threads: 4,
+        };
+        let mut controller = Controller::new(2, -2);
+        assert_eq!(controller.update(&busy), -1);
+        let mut controller = Controller::new(0, -1);
+        assert_eq!(controller.current(), 3);
+        assert_eq!(controller.update(&busy), 1);
+        assert_eq!(controller.update(&queued), -1);
+        assert_eq!(controller.update(&busy), -1);
+        // 介于繁忙和空闲之间时保持不变
+        let moderate = Load { cpu: 0.6, ..idle };
+        assert_eq!(controller.update(&moderate), -1);
+        assert_eq!(controller.update(&idle), 1);
+        for _ in 0..5 {
+            controller.update(&idle);
+        }
+        assert_eq!(controller.current(), 3);
+    }
+
+    #[test]
+    fn test2() {
+        let earlier = CpuTimes::parse("cpu  100 0 100 700 100 0 0 0 0 0").unwrap();
+        assert_eq!(
+            earlier,
+            CpuTimes {
+                busy: 200,
+                total: 1000
+            }
+        );
+        let now = CpuTimes::parse("cpu  300 0 200 900 100 0 0 0 0 0").unwrap();
+        assert_eq!(now.usage_since(&earlier), 0.6);
+        assert_eq!(CpuTimes::parse("cpu0 1 2 3 4"), None);
+    }
+}
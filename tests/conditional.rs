@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use ntex::http::header::{HeaderName, HeaderValue};
+    use ntex::http::HeaderMap;
+    use rs_s3_local::conditional::{
+        create_only, etag_matches, evaluate, if_match, if_range_matches, write_allowed, Outcome,
+    };
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                HeaderName::from_static(name),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test1() {
+        assert!(etag_matches("\"abc\"", "abc"));
+        assert!(etag_matches("\"x\", W/\"abc\"", "abc"));
+        assert!(etag_matches("*", "abc"));
+        assert!(!etag_matches("\"abd\"", "abc"));
+
+        let etag = "abc";
+        let modified = Utc.with_ymd_and_hms(2024, 3, 5, 8, 0, 0).unwrap();
+        let same = "Tue, 5 Mar 2024 08:00:00 GMT";
+        let before = "Mon, 04 Mar 2024 08:00:00 GMT";
+        let check = |pairs: &[(&'static str, &str)]| evaluate(&headers(pairs), etag, modified);
+        assert_eq!(check(&[]), Outcome::Proceed);
+        assert_eq!(check(&[("if-none-match", "\"abc\"")]), Outcome::NotModified);
+        assert_eq!(check(&[("if-match", "\"x\"")]), Outcome::PreconditionFailed);
+        assert_eq!(check(&[("if-modified-since", same)]), Outcome::NotModified);
+        assert_eq!(check(&[("if-modified-since", before)]), Outcome::Proceed);
+        assert_eq!(
+            check(&[("if-unmodified-since", before)]),
+            Outcome::PreconditionFailed
+        );
+        assert_eq!(
+            check(&[("if-modified-since", "yesterday")]),
+            Outcome::Proceed
+        );
+        // If-Match 满足时忽略 If-Unmodified-Since，带 If-None-Match 时忽略 If-Modified-Since
+        assert_eq!(
+            check(&[("if-match", "\"abc\""), ("if-unmodified-since", before)]),
+            Outcome::Proceed
+        );
+        assert_eq!(
+            check(&[("if-none-match", "\"x\""), ("if-modified-since", same)]),
+            Outcome::Proceed
+        );
+    }
+
+    #[test]
+    fn test2() {
+        assert!(matches!(create_only(&headers(&[])), Ok(false)));
+        assert!(matches!(
+            create_only(&headers(&[("if-none-match", "*")])),
+            Ok(true)
+        ));
+        assert!(create_only(&headers(&[("if-none-match", "\"abc\"")])).is_err());
+        assert_eq!(
+            if_match(&headers(&[("if-match", " \"abc\" ")])).as_deref(),
+            Some("\"abc\"")
+        );
+
+        assert!(write_allowed(None, false, None));
+        assert!(write_allowed(Some("\"abc\""), false, Some("abc")));
+        assert!(write_allowed(Some("*"), false, Some("abc")));
+        assert!(!write_allowed(Some("\"abd\""), false, Some("abc")));
+        assert!(!write_allowed(Some("*"), false, None));
+        assert!(write_allowed(None, true, None));
+        assert!(!write_allowed(None, true, Some("abc")));
+    }
+
+    #[test]
+    fn test3() {
+        let modified = Utc.with_ymd_and_hms(2024, 3, 5, 8, 0, 0).unwrap();
+        let check =
+            |value: &str| if_range_matches(&headers(&[("if-range", value)]), "abc", modified);
+        assert!(if_range_matches(&headers(&[]), "abc", modified));
+        assert!(check("\"abc\""));
+        assert!(!check("\"abd\""));
+        assert!(!check("W/\"abc\""));
+        assert!(check("Tue, 5 Mar 2024 08:00:00 GMT"));
+        assert!(!check("Mon, 04 Mar 2024 08:00:00 GMT"));
+        assert!(!check("yesterday"));
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::config::{
+        parse_config_file, ApiGroup, BucketRestriction, Chunking, DedupVerify, DiskWatermarks,
+        PluginConfig, PluginHook, RestrictedOperation, ScanAction, ScriptConfig, ScriptEvent,
+        ServerConfig, StorageRoute, UnreadExpiration,
+    };
+    use rs_s3_local::fs::Backend;
+
+    #[test]
+    fn test1() {
+        let config = ServerConfig {
+            storage_routes: vec![
+                "logs/=passthrough".parse().unwrap(),
+                "logs/keep/=dedup".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.backend_for("logs/a.txt"), Backend::Passthrough);
+        assert_eq!(config.backend_for("logs/keep/a.txt"), Backend::Dedup);
+        assert_eq!(config.backend_for("data/a.txt"), Backend::Dedup);
+    }
+
+    #[test]
+    fn test2() {
+        assert!("logs/=tape".parse::<StorageRoute>().is_err());
+        assert!("logs/".parse::<StorageRoute>().is_err());
+    }
+
+    #[test]
+    fn test3() {
+        let config = ServerConfig {
+            public_prefixes: vec!["site".to_string(), "data/pub/".to_string()],
+            ..Default::default()
+        };
+        assert!(config.is_public("site/index.html"));
+        assert!(config.is_public("site"));
+        assert!(!config.is_public("site2/index.html"));
+        assert!(config.is_public("data/pub/a.csv"));
+        assert!(!config.is_public("data/private/a.csv"));
+    }
+
+    #[test]
+    fn test4() {
+        let config = ServerConfig {
+            chunk_roots: vec!["d1".into(), "d2".into(), "d3".into()],
+            chunk_replicas: 2,
+            ..Default::default()
+        };
+        // 0x0000000A % 3 == 1
+        let roots = config.chunk_roots_for("0000000AFF");
+        assert_eq!(
+            roots,
+            vec![std::path::Path::new("d2"), std::path::Path::new("d3")]
+        );
+        let roots = config.chunk_roots_for("00000002FF");
+        assert_eq!(
+            roots,
+            vec![std::path::Path::new("d3"), std::path::Path::new("d1")]
+        );
+        assert!(ServerConfig::default()
+            .chunk_roots_for("00000002FF")
+            .is_empty());
+    }
+
+    #[test]
+    fn test5() {
+        let restriction: BucketRestriction = "golden=delete, overwrite".parse().unwrap();
+        assert_eq!(restriction.bucket, "golden");
+        assert!("golden=rename".parse::<BucketRestriction>().is_err());
+        assert!("golden".parse::<BucketRestriction>().is_err());
+        let config = ServerConfig {
+            bucket_restrictions: vec![restriction],
+            ..Default::default()
+        };
+        assert!(config.is_denied("golden", RestrictedOperation::Delete));
+        assert!(config.is_denied("golden", RestrictedOperation::Overwrite));
+        assert!(!config.is_denied("golden", RestrictedOperation::List));
+        assert!(!config.is_denied("other", RestrictedOperation::Delete));
+    }
+
+    #[test]
+    fn test6() {
+        let plugin: PluginConfig = "pre-put,post-get=/plugins/redact.wasm@reports/2024/"
+            .parse()
+            .unwrap();
+        assert_eq!(plugin.hooks, vec![PluginHook::PrePut, PluginHook::PostGet]);
+        assert_eq!(plugin.path.to_str(), Some("/plugins/redact.wasm"));
+        assert!(plugin.applies_to("reports/2024/q1.csv"));
+        assert!(!plugin.applies_to("reports/2023/q1.csv"));
+        let plugin: PluginConfig = "event=audit.wasm".parse().unwrap();
+        assert!(plugin.applies_to("any/key"));
+        assert!("pre-get=a.wasm".parse::<PluginConfig>().is_err());
+        assert!("a.wasm".parse::<PluginConfig>().is_err());
+    }
+
+    #[test]
+    fn test7() {
+        let script: ScriptConfig = "put=/scripts/manifest.rhai@media/uploads/".parse().unwrap();
+        assert_eq!(script.events, vec![ScriptEvent::Put]);
+        assert!(script.applies_to(ScriptEvent::Put, "media/uploads/a.jpg"));
+        assert!(!script.applies_to(ScriptEvent::Delete, "media/uploads/a.jpg"));
+        assert!(!script.applies_to(ScriptEvent::Put, "media/other/a.jpg"));
+        assert!("create=a.rhai".parse::<ScriptConfig>().is_err());
+    }
+
+    #[test]
+    fn test8() {
+        assert_eq!(ScanAction::default(), ScanAction::Reject);
+        assert_eq!(
+            "quarantine".parse::<ScanAction>().unwrap(),
+            ScanAction::Quarantine
+        );
+        assert!("delete".parse::<ScanAction>().is_err());
+    }
+
+    #[test]
+    fn test9() {
+        let rule: UnreadExpiration = "logs/=30".parse().unwrap();
+        assert_eq!(rule.prefix, "logs/");
+        assert_eq!(rule.days, 30);
+        assert!("logs=0".parse::<UnreadExpiration>().is_err());
+        assert!("=30".parse::<UnreadExpiration>().is_err());
+        assert!("logs".parse::<UnreadExpiration>().is_err());
+    }
+
+    #[test]
+    fn test10() {
+        let watermarks: DiskWatermarks = "80, 90".parse().unwrap();
+        assert_eq!((watermarks.warning, watermarks.critical), (80, 90));
+        assert!("90,80".parse::<DiskWatermarks>().is_err());
+        assert!("80,101".parse::<DiskWatermarks>().is_err());
+        assert!("80".parse::<DiskWatermarks>().is_err());
+    }
+
+    #[test]
+    fn test11() {
+        assert_eq!("never".parse(), Ok(DedupVerify::Never));
+        assert_eq!("always".parse(), Ok(DedupVerify::Always));
+        assert_eq!("sampled".parse(), Ok(DedupVerify::Sampled(100)));
+        assert_eq!("sampled:10".parse(), Ok(DedupVerify::Sampled(10)));
+        assert!("sampled:0".parse::<DedupVerify>().is_err());
+        assert!("sometimes".parse::<DedupVerify>().is_err());
+    }
+
+    #[test]
+    fn test12() {
+        let text = r#"
+            http_addr = "0.0.0.0:9000"
+            zstd-level = -3
+            fsync = true
+            chunk-root = ["/disk1", "/disk2"]
+        "#;
+        let mut settings = parse_config_file(text).unwrap();
+        settings.sort();
+        assert_eq!(
+            settings,
+            vec![
+                (
+                    "chunk-root".to_string(),
+                    vec!["/disk1".to_string(), "/disk2".to_string()]
+                ),
+                ("fsync".to_string(), vec!["true".to_string()]),
+                ("http-addr".to_string(), vec!["0.0.0.0:9000".to_string()]),
+                ("zstd-level".to_string(), vec!["-3".to_string()]),
+            ]
+        );
+        assert!(parse_config_file("[jwt]\nsecret = \"x\"").is_err());
+        assert!(parse_config_file("port = ").is_err());
+        assert_eq!(ServerConfig::default().chunk_bytes(), 8 << 20);
+    }
+
+    #[test]
+    fn test13() {
+        let config = ServerConfig {
+            disabled_apis: vec!["delete".parse().unwrap(), "multipart".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!("policy".parse::<ApiGroup>().is_err());
+        let disabled = |method, path, query, copy| {
+            config.disabled_api(&ApiGroup::of(method, path, query, copy))
+        };
+        assert_eq!(
+            disabled("DELETE", "/api/b/k", "", false),
+            Some(ApiGroup::Delete)
+        );
+        assert_eq!(
+            disabled("POST", "/api/b", "delete", false),
+            Some(ApiGroup::Delete)
+        );
+        assert_eq!(
+            disabled("DELETE", "/api/b/k", "uploadId=1", false),
+            Some(ApiGroup::Multipart)
+        );
+        assert_eq!(
+            disabled("PUT", "/api/b/k", "partNumber=1&uploadId=1", true),
+            Some(ApiGroup::Multipart)
+        );
+        assert_eq!(disabled("DELETE", "/api/b", "", false), None);
+        assert_eq!(disabled("PUT", "/api/b/k", "", false), None);
+        assert_eq!(
+            ApiGroup::of("PUT", "/api/b/k", "", true),
+            vec![ApiGroup::Copy]
+        );
+        assert_eq!(
+            ApiGroup::of("DELETE", "/api/b/", "website", false),
+            vec![ApiGroup::BucketConfig]
+        );
+        assert_eq!(
+            ApiGroup::of("PUT", "/api/b", "", false),
+            vec![ApiGroup::CreateBucket]
+        );
+        assert_eq!(
+            ApiGroup::of("GET", "/admin/config", "", false),
+            vec![ApiGroup::Admin]
+        );
+        assert!(ApiGroup::of("GET", "/api/b", "list-type=2", false).is_empty());
+        assert!(ApiGroup::of("POST", "/cluster/init", "", false).is_empty());
+    }
+
+    #[test]
+    fn test14() {
+        assert_eq!("fixed".parse(), Ok(Chunking::Fixed));
+        assert_eq!("cdc".parse(), Ok(Chunking::ContentDefined));
+        assert!("fastcdc".parse::<Chunking>().is_err());
+        assert_eq!(ServerConfig::default().chunking, Chunking::Fixed);
+    }
+
+    #[test]
+    fn test15() {
+        let config = ServerConfig {
+            encrypted_buckets: vec!["tenant-a".to_string()],
+            ..Default::default()
+        };
+        assert!(config.is_encrypted_bucket("tenant-a"));
+        assert!(!config.is_encrypted_bucket("tenant-ab"));
+        assert!(!ServerConfig::default().is_encrypted_bucket("tenant-a"));
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::copy::{parse_source, CopySource, MetadataDirective};
+
+    #[test]
+    fn test1() {
+        let source = |bucket: &str, key: &str| {
+            Some(CopySource {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+        };
+        assert_eq!(parse_source("bucket/a/b.txt"), source("bucket", "a/b.txt"));
+        assert_eq!(parse_source("/bucket/a.txt"), source("bucket", "a.txt"));
+        assert_eq!(
+            parse_source("bucket/a%20b%E4%B8%AD.txt?versionId=1"),
+            source("bucket", "a b中.txt")
+        );
+        assert_eq!(
+            parse_source("bucket/a b+c.txt").unwrap().stored_path(),
+            "bucket/a%20b%2Bc.txt"
+        );
+        assert_eq!(parse_source("bucket"), None);
+        assert_eq!(parse_source("bucket/"), None);
+        assert_eq!(parse_source("/bucket//a.txt"), None);
+        assert_eq!(parse_source("bucket/a/../../b.txt"), None);
+        assert_eq!(parse_source("../a.txt"), None);
+        assert_eq!(parse_source("bucket/a/./b.txt"), None);
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!("COPY".parse(), Ok(MetadataDirective::Copy));
+        assert_eq!("REPLACE".parse(), Ok(MetadataDirective::Replace));
+        assert!("replace".parse::<MetadataDirective>().is_err());
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod test {
-    use rs_s3_local::util::cry::{aes_256_cbc_decrypt, aes_256_cbc_encrypt, do_hmac_sha256};
+    use rs_s3_local::util::cry::{
+        aes_256_cbc_decrypt_with_key, aes_256_cbc_encrypt_with_key, do_hmac_sha256, gen_data_key,
+    };
     #[test]
     fn test1() {
         let code = do_hmac_sha256(b"my secret and secure key", "input message").unwrap();
@@ -13,9 +15,25 @@ mod test {
     #[test]
     fn test2() {
         let s = "xxxxxx";
-        let en = aes_256_cbc_encrypt(s.as_bytes()).unwrap();
+        let key = gen_data_key();
+        let en = aes_256_cbc_encrypt_with_key(&key, s.as_bytes()).unwrap();
         //let en = general_purpose::STANDARD.encode(&en);
-        let de = String::from_utf8(aes_256_cbc_decrypt(&en).unwrap()).unwrap();
+        let de = String::from_utf8(aes_256_cbc_decrypt_with_key(&key, &en).unwrap()).unwrap();
         assert_eq!(s, &de);
     }
+
+    #[test]
+    fn test3() {
+        let key = gen_data_key();
+        let other = gen_data_key();
+        let en = aes_256_cbc_encrypt_with_key(&key, b"bucket metadata").unwrap();
+        assert_eq!(
+            aes_256_cbc_decrypt_with_key(&key, &en).unwrap(),
+            b"bucket metadata"
+        );
+        assert_ne!(
+            aes_256_cbc_decrypt_with_key(&other, &en).ok().as_deref(),
+            Some(&b"bucket metadata"[..])
+        );
+    }
 }
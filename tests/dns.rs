@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::dns::{default_answer, in_domain, respond};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.push(0);
+        query.extend_from_slice(&qtype.to_be_bytes());
+        query.extend_from_slice(&1u16.to_be_bytes());
+        query
+    }
+
+    #[test]
+    fn test1() {
+        let answer = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7));
+        let q = query("Photos.s3.localhost", 1);
+        let resp = respond(&q, "s3.localhost", answer).unwrap();
+        assert_eq!(&resp[..2], &[0x12, 0x34]);
+        // 响应、权威应答、期望递归，NOERROR，一个问题一个回答
+        assert_eq!(&resp[2..8], &[0x85, 0x00, 0, 1, 0, 1]);
+        assert_eq!(&resp[12..q.len()], &q[12..]);
+        assert_eq!(&resp[resp.len() - 4..], &[10, 0, 0, 7]);
+
+        // AAAA 查询没有回答，其他域名 NXDOMAIN
+        let resp = respond(&query("a.s3.localhost", 28), "s3.localhost", answer).unwrap();
+        assert_eq!(&resp[2..8], &[0x85, 0x00, 0, 1, 0, 0]);
+        let resp = respond(&query("example.com", 1), "s3.localhost", answer).unwrap();
+        assert_eq!(&resp[2..8], &[0x85, 0x03, 0, 1, 0, 0]);
+        assert!(respond(&resp, "s3.localhost", answer).is_none());
+        assert!(respond(&[0x12], "s3.localhost", answer).is_none());
+
+        assert!(in_domain("s3.localhost", "S3.localhost."));
+        assert!(!in_domain("xs3.localhost", "s3.localhost"));
+        assert_eq!(
+            default_answer("10.1.2.3:9000"),
+            IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))
+        );
+        assert_eq!(
+            default_answer("0.0.0.0:9000"),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(default_answer("[::]:9000"), IpAddr::V6(Ipv6Addr::LOCALHOST));
+    }
+}
@@ -0,0 +1,20 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::erasure::{decode_shards, encode_shards};
+
+    #[test]
+    fn test1() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let shards = encode_shards(&data, 4, 2).unwrap();
+        assert_eq!(shards.len(), 6);
+        let mut shards: Vec<Option<Vec<u8>>> = shards.into_iter().map(Some).collect();
+        shards[0] = None;
+        shards[3] = None;
+        assert_eq!(
+            decode_shards(shards.clone(), 4, 2, data.len()).unwrap(),
+            data
+        );
+        shards[5] = None;
+        assert!(decode_shards(shards, 4, 2, data.len()).is_err());
+    }
+}
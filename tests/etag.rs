@@ -0,0 +1,16 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::etag::{md5_hex, multipart_etag, quote};
+
+    #[test]
+    fn test1() {
+        assert_eq!(md5_hex(b"hello"), "5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(quote(&md5_hex(b"")), "\"d41d8cd98f00b204e9800998ecf8427e\"");
+    }
+
+    #[test]
+    fn test2() {
+        let parts = [md5_hex(b"hello"), quote(&md5_hex(b"world"))];
+        assert_eq!(multipart_etag(&parts), "065947336a2f2a95ba8899f3675c3be6-2");
+    }
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod test {
     use rkyv::{Deserialize, Infallible};
-    use rs_s3_local::fs::Metadata;
+    use rs_s3_local::config::Chunking;
+    use rs_s3_local::fs::{
+        ChunkBuffer, Chunker, Encryption, Metadata, ResponseHeader, ScanStatus, ScanVerdict,
+        SseAlgorithm, Tag,
+    };
 
     #[test]
     fn test1() {
@@ -11,6 +15,35 @@ mod test {
             file_type: "xxxxx".to_string(),
             time: Default::default(),
             chunks: vec![],
+            chunk_sizes: vec![],
+            backend: Default::default(),
+            website_redirect: Some("/index.html".to_string()),
+            headers: vec![ResponseHeader {
+                name: "Cache-Control".to_string(),
+                value: "no-cache".to_string(),
+            }],
+            scan: Some(ScanStatus {
+                verdict: ScanVerdict::Infected,
+                signature: Some("Eicar-Test-Signature".to_string()),
+                time: Default::default(),
+            }),
+            etag: "5d41402abc4b2a76b9719d911017c592".to_string(),
+            checksum_sha256: Some("LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=".to_string()),
+            version_id: None,
+            delete_marker: false,
+            encryption: Some(Encryption {
+                algorithm: SseAlgorithm::Customer,
+                key: vec![],
+                nonce: vec![7; 8],
+                key_hmac: vec![1; 32],
+                customer_key_md5: Some("XrY7u+Ae7tCTyyK7j1rNww==".to_string()),
+                parts: vec![1, 2],
+                part_offsets: vec![0, 0],
+            }),
+            tags: vec![Tag {
+                key: "env".to_string(),
+                value: "dev".to_string(),
+            }],
         };
 
         let bytes = rkyv::to_bytes::<_, 256>(&m).unwrap();
@@ -22,4 +55,120 @@ mod test {
 
     #[test]
     fn test2() {}
+
+    #[test]
+    fn test3() {
+        use rs_s3_local::util::file::detect_content_type;
+        assert_eq!(detect_content_type("index.html", b""), "text/html");
+        assert_eq!(detect_content_type("photo.PNG", b""), "image/png");
+        assert_eq!(
+            detect_content_type("blob", b"\x89PNG\r\n\x1a\n...."),
+            "image/png"
+        );
+        assert_eq!(
+            detect_content_type("page", b"  <!DOCTYPE html><html></html>"),
+            "text/html"
+        );
+        assert_eq!(
+            detect_content_type("data.bin.unknownext", b"\x00\x01"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test4() {
+        let fixed = Chunker::new(4, Chunking::Fixed);
+        assert_eq!(fixed.split(b"abcdefghij"), [0..4, 4..8, 8..10]);
+        assert_eq!(fixed.next_len(b"abc", false), None);
+        assert!(fixed.split(b"").is_empty());
+
+        // 伪随机内容（xorshift）
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let data: Vec<u8> = (0..256 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let cdc = Chunker::new(4096, Chunking::ContentDefined);
+        let ranges = cdc.split(&data);
+        assert_eq!(ranges.last().unwrap().end, data.len());
+        assert!(ranges[..ranges.len() - 1]
+            .iter()
+            .all(|r| r.len() >= 1024 && r.len() <= 16384));
+        let average = data.len() / ranges.len();
+        assert!(average > 2048 && average < 8192, "{}", average);
+
+        // 开头插入内容后，后面的分片基本不变
+        let chunks = |data: &[u8]| -> Vec<Vec<u8>> {
+            cdc.split(data)
+                .into_iter()
+                .map(|r| data[r].to_vec())
+                .collect()
+        };
+        let before = chunks(&data);
+        let mut shifted = b"inserted".to_vec();
+        shifted.extend_from_slice(&data);
+        let after = chunks(&shifted);
+        let shared = after.iter().filter(|c| before.contains(c)).count();
+        assert!(shared + 2 >= before.len(), "{} of {}", shared, before.len());
+
+        // 边接收边切分与整体切分的结果相同
+        let mut buf = ChunkBuffer::new(cdc);
+        let mut streamed = vec![];
+        for block in data.chunks(1000) {
+            buf.extend(block);
+            while let Some(chunk) = buf.next_chunk(false) {
+                streamed.push(chunk);
+            }
+        }
+        while let Some(chunk) = buf.next_chunk(true) {
+            streamed.push(chunk);
+        }
+        assert_eq!(streamed, before);
+    }
+
+    // 最初版本的元数据格式
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct V0Metadata {
+        name: String,
+        size: u64,
+        file_type: String,
+        time: chrono::DateTime<chrono::Utc>,
+        chunks: Vec<String>,
+    }
+
+    #[test]
+    fn test5() {
+        use rs_s3_local::fs::{deserialize_metadata, serialize_metadata, Backend};
+        let v0 = V0Metadata {
+            name: "report.pdf".to_string(),
+            size: 42,
+            file_type: "application/pdf".to_string(),
+            time: Default::default(),
+            chunks: vec!["ab".repeat(32), "cd".repeat(32)],
+        };
+        let bytes = rkyv::to_bytes::<_, 256>(&v0).unwrap();
+        let m = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(m.name, "report.pdf");
+        assert_eq!(m.size, 42);
+        assert_eq!(m.chunks, v0.chunks);
+        assert!(m.chunk_sizes.is_empty());
+        assert_eq!(m.backend, Backend::Dedup);
+        assert_eq!(m.etag.len(), 32);
+        assert_eq!(deserialize_metadata(&bytes).unwrap().etag, m.etag);
+
+        // 当前格式带版本头部，往返不变
+        let current = serialize_metadata(&m).unwrap();
+        assert_eq!(deserialize_metadata(&current).unwrap(), m);
+
+        // 损坏的内容和未知的版本返回错误，不会 panic
+        assert!(deserialize_metadata(&current[..current.len() / 2]).is_err());
+        assert!(deserialize_metadata(b"garbage").is_err());
+        let mut unknown = current.clone();
+        unknown[4] = 99;
+        assert!(deserialize_metadata(&unknown).is_err());
+    }
 }
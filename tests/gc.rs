@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::gc::{chunk_hash_from_path, is_past_grace};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test1() {
+        let hash = "AB".repeat(32);
+        let path = format!("{}/{}/{}", &hash[..1], &hash[1..3], &hash[3..]);
+        assert_eq!(chunk_hash_from_path(Path::new(&path)), Some(hash.clone()));
+        let shard = format!("{}.ec", path);
+        assert_eq!(chunk_hash_from_path(Path::new(&shard)), Some(hash.clone()));
+        // 临时文件和不符合目录结构的文件不是分片
+        let tmp = format!("{}.recompress.tmp", path);
+        assert_eq!(chunk_hash_from_path(Path::new(&tmp)), None);
+        assert_eq!(chunk_hash_from_path(Path::new(&hash)), None);
+        assert_eq!(chunk_hash_from_path(Path::new("A/BC/DEF")), None);
+    }
+
+    #[test]
+    fn test2() {
+        let now = SystemTime::now();
+        let grace = Duration::from_secs(3600);
+        assert!(is_past_grace(Some(now - grace * 2), now, grace));
+        assert!(!is_past_grace(Some(now - grace / 2), now, grace));
+        // 修改时间晚于当前时间或读取不到时保留
+        assert!(!is_past_grace(Some(now + grace), now, grace));
+        assert!(!is_past_grace(None, now, grace));
+    }
+}
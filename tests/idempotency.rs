@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod test {
+    use ntex::http::{HeaderMap, StatusCode};
+    use rs_s3_local::idempotency::{fingerprint, Begin, Cache, CachedBody, CachedResponse};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test1() {
+        let mut cache = Cache::default();
+        let window = Duration::from_secs(60);
+        let now = Instant::now();
+        let put = fingerprint("PUT", "/api/b/k", "", Some("UNSIGNED-PAYLOAD"));
+        assert!(matches!(
+            cache.begin("k1", &put, window, now),
+            Begin::Execute
+        ));
+        assert!(matches!(
+            cache.begin("k1", &put, window, now),
+            Begin::InFlight
+        ));
+        cache.finish(
+            "k1",
+            Some(CachedResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: CachedBody::Empty,
+            }),
+        );
+        match cache.begin("k1", &put, window, now + Duration::from_secs(1)) {
+            Begin::Replay(cached) => assert_eq!(cached.status, StatusCode::OK),
+            other => panic!("{:?}", other),
+        }
+        let delete = fingerprint("DELETE", "/api/b/k", "", None);
+        assert!(matches!(
+            cache.begin("k1", &delete, window, now),
+            Begin::Mismatch
+        ));
+        // 超过时间窗口后重新执行
+        assert!(matches!(
+            cache.begin("k1", &delete, window, now + window),
+            Begin::Execute
+        ));
+    }
+
+    #[test]
+    fn test2() {
+        let mut cache = Cache::default();
+        let window = Duration::from_secs(60);
+        let now = Instant::now();
+        let put = fingerprint("PUT", "/api/b/k", "", None);
+        assert!(matches!(
+            cache.begin("k", &put, window, now),
+            Begin::Execute
+        ));
+        cache.finish(
+            "k",
+            Some(CachedResponse {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                headers: HeaderMap::new(),
+                body: CachedBody::None,
+            }),
+        );
+        assert!(matches!(
+            cache.begin("k", &put, window, now),
+            Begin::Execute
+        ));
+        cache.finish("k", None);
+        assert!(matches!(
+            cache.begin("k", &put, window, now),
+            Begin::Execute
+        ));
+    }
+}
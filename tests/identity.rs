@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::identity::{Identity, Operation, Permissions};
+
+    #[test]
+    fn test1() {
+        let scoped = Permissions {
+            prefixes: vec!["logs".to_string(), "b/pub/".to_string()],
+            ..Default::default()
+        };
+        assert!(scoped.allows(Operation::Write, Some("logs/2024/a.gz"), false));
+        assert!(scoped.allows(Operation::Read, Some("b/pub/x"), false));
+        assert!(!scoped.allows(Operation::Read, Some("b/private"), false));
+        assert!(!scoped.allows(Operation::Read, Some("logs-archive/a"), false));
+        assert!(!scoped.allows(Operation::Read, Some("logs/../secret/x.txt"), false));
+        assert!(!scoped.allows(Operation::List, Some("logs/./x/"), false));
+        assert!(scoped.allows(Operation::List, None, false));
+        assert!(!scoped.allows(Operation::Read, None, true));
+
+        let read_only = Permissions {
+            read_only: true,
+            ..Default::default()
+        };
+        assert!(read_only.allows(Operation::Read, Some("any/key"), false));
+        assert!(!read_only.allows(Operation::Delete, Some("any/key"), false));
+        assert!(Permissions::full().allows(Operation::Write, None, true));
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(Operation::of("GET", "/api/"), Operation::List);
+        assert_eq!(Operation::of("GET", "/api/b/"), Operation::List);
+        assert_eq!(Operation::of("HEAD", "/api/b"), Operation::Read);
+        assert_eq!(Operation::of("GET", "/api/b/k/x"), Operation::Read);
+        assert_eq!(Operation::of("PUT", "/api/b"), Operation::Write);
+        assert_eq!(Operation::of("POST", "/api/b/k"), Operation::Write);
+        assert_eq!(Operation::of("DELETE", "/api/b/k"), Operation::Delete);
+
+        // 服务账号的权限不超出父密钥
+        let account = Identity {
+            access_key: "svc".to_string(),
+            secret_key: "s".to_string(),
+            previous_secret_key: None,
+            permissions: Permissions {
+                operations: vec![Operation::Read, Operation::Write],
+                ..Default::default()
+            },
+            inherited: Some(Permissions {
+                prefixes: vec!["b".to_string()],
+                ..Default::default()
+            }),
+        };
+        assert!(account.allows(Operation::Write, Some("b/k"), false));
+        assert!(!account.allows(Operation::Write, Some("c/k"), false));
+        assert!(!account.allows(Operation::Delete, Some("b/k"), false));
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::config::JwtConfig;
+    use rs_s3_local::jwt::{role_from_claims, AdminRole};
+    use serde_json::json;
+
+    #[test]
+    fn test1() {
+        let cfg = JwtConfig {
+            role_claim: "realm_access.roles".to_string(),
+            admin_role: "admin".to_string(),
+            readonly_role: "read-only".to_string(),
+            ..Default::default()
+        };
+        let claims = |roles| json!({ "sub": "u", "realm_access": { "roles": roles } });
+        assert_eq!(
+            role_from_claims(&claims(json!(["read-only", "admin"])), &cfg),
+            Some(AdminRole::Admin)
+        );
+        assert_eq!(
+            role_from_claims(&claims(json!(["read-only"])), &cfg),
+            Some(AdminRole::ReadOnly)
+        );
+        assert_eq!(role_from_claims(&claims(json!(["other"])), &cfg), None);
+        assert_eq!(role_from_claims(&json!({ "sub": "u" }), &cfg), None);
+        // 以空格分隔的字符串声明（如 scope）
+        let cfg = JwtConfig {
+            role_claim: "scope".to_string(),
+            ..cfg
+        };
+        assert_eq!(
+            role_from_claims(&json!({ "scope": "openid read-only" }), &cfg),
+            Some(AdminRole::ReadOnly)
+        );
+        assert!(!AdminRole::ReadOnly.allows(&ntex::http::Method::POST));
+        assert!(AdminRole::Admin.allows(&ntex::http::Method::DELETE));
+    }
+}
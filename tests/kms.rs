@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::kms::{key_id, Keyring, BUILTIN_KEY_ID};
+
+    const KEY_A: &str = "a1:000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const KEY_B: &str = "b2:1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+
+    #[test]
+    fn test1() {
+        assert!(Keyring::parse("").is_err());
+        assert!(Keyring::parse("a1:00").is_err());
+        assert!(Keyring::parse(&format!("{},{}", KEY_A, KEY_A)).is_err());
+        assert!(Keyring::parse(&KEY_A.replace("a1", BUILTIN_KEY_ID)).is_err());
+        let keyring = Keyring::parse(&format!("# 旧密钥\n{}\n\n{}\n", KEY_A, KEY_B)).unwrap();
+        assert_eq!(keyring.active_id(), "b2");
+    }
+
+    #[test]
+    fn test2() {
+        // 内置密钥按旧格式加密，不带头部
+        let builtin = Keyring::builtin();
+        let legacy = builtin.seal(b"metadata").unwrap();
+        assert_eq!(key_id(&legacy), None);
+        assert_eq!(builtin.open(&legacy).unwrap(), b"metadata");
+
+        let old = Keyring::parse(KEY_A).unwrap();
+        let sealed_a = old.seal(b"metadata").unwrap();
+        assert_eq!(key_id(&sealed_a), Some("a1"));
+
+        // 轮换后仍能解密旧密钥和内置密钥加密的数据，新数据使用新密钥
+        let rotated = Keyring::parse(&format!("{},{}", KEY_A, KEY_B)).unwrap();
+        assert_eq!(rotated.open(&sealed_a).unwrap(), b"metadata");
+        assert_eq!(rotated.open(&legacy).unwrap(), b"metadata");
+        let sealed_b = rotated.seal(b"metadata").unwrap();
+        assert_eq!(key_id(&sealed_b), Some("b2"));
+        assert!(old.open(&sealed_b).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration, Utc};
+    use rs_s3_local::fs::Tag;
+    use rs_s3_local::lifecycle::{split_upload_meta_name, LifecycleConfiguration};
+
+    const XMLNS: &str = r#"xmlns="http://s3.amazonaws.com/doc/2006-03-01/""#;
+
+    #[test]
+    fn test1() {
+        let rules = |rules: &str| {
+            format!(
+                "<LifecycleConfiguration {}>{}</LifecycleConfiguration>",
+                XMLNS, rules
+            )
+        };
+        assert!(LifecycleConfiguration::parse(&rules("")).is_err());
+        let no_action = "<Rule><ID>a</ID><Status>Enabled</Status><Filter><Prefix>logs/</Prefix></Filter></Rule>";
+        assert!(LifecycleConfiguration::parse(&rules(no_action)).is_err());
+        let zero_days =
+            "<Rule><Status>Enabled</Status><Filter/><Expiration><Days>0</Days></Expiration></Rule>";
+        assert!(LifecycleConfiguration::parse(&rules(zero_days)).is_err());
+        let transition =
+            "<Rule><Status>Enabled</Status><Filter/><Expiration><Days>1</Days></Expiration>\
+            <Transition><Days>1</Days><StorageClass>GLACIER</StorageClass></Transition></Rule>";
+        assert!(LifecycleConfiguration::parse(&rules(transition)).is_err());
+        let size_filter = "<Rule><Status>Enabled</Status><Filter><ObjectSizeGreaterThan>1</ObjectSizeGreaterThan>\
+            </Filter><Expiration><Days>1</Days></Expiration></Rule>";
+        assert!(LifecycleConfiguration::parse(&rules(size_filter)).is_err());
+        let abort_with_tag =
+            "<Rule><Status>Enabled</Status><Filter><Tag><Key>k</Key><Value>v</Value></Tag>\
+            </Filter><AbortIncompleteMultipartUpload><DaysAfterInitiation>1</DaysAfterInitiation>\
+            </AbortIncompleteMultipartUpload></Rule>";
+        assert!(LifecycleConfiguration::parse(&rules(abort_with_tag)).is_err());
+
+        let marker = |expiration: &str, filter: &str| {
+            format!(
+                "<Rule><Status>Enabled</Status><Filter>{}</Filter><Expiration>{}\
+                <ExpiredObjectDeleteMarker>true</ExpiredObjectDeleteMarker></Expiration></Rule>",
+                filter, expiration
+            )
+        };
+        assert!(LifecycleConfiguration::parse(&rules(&marker("<Days>1</Days>", ""))).is_err());
+        let tag = "<Tag><Key>k</Key><Value>v</Value></Tag>";
+        assert!(LifecycleConfiguration::parse(&rules(&marker("", tag))).is_err());
+        let conf =
+            LifecycleConfiguration::parse(&rules(&marker("", "<Prefix>logs/</Prefix>"))).unwrap();
+        assert!(conf.rules[0].removes_expired_marker("logs/a"));
+        assert!(!conf.rules[0].removes_expired_marker("data/a"));
+        let now: DateTime<Utc> = "2024-06-10T00:00:00Z".parse().unwrap();
+        let long_ago = now - Duration::days(9000);
+        assert!(!conf.rules[0].expires("logs/a", &[], long_ago, now, Duration::days(1)));
+
+        let ok = "<Rule><ID>logs</ID><Status>Enabled</Status><Filter><And><Prefix>logs/</Prefix>\
+            <Tag><Key>tier</Key><Value>tmp</Value></Tag></And></Filter><Expiration><Days>7</Days></Expiration></Rule>\
+            <Rule><ID>uploads</ID><Prefix>big/</Prefix><Status>Enabled</Status><AbortIncompleteMultipartUpload>\
+            <DaysAfterInitiation>2</DaysAfterInitiation></AbortIncompleteMultipartUpload></Rule>\
+            <Rule><ID>old</ID><Status>Disabled</Status><Filter></Filter>\
+            <Expiration><Date>2020-01-01T00:00:00.000Z</Date></Expiration></Rule>";
+        let conf = LifecycleConfiguration::parse(&rules(ok)).unwrap();
+        assert_eq!(conf.rules.len(), 3);
+        assert_eq!(conf.rules[0].prefix(), "logs/");
+        assert_eq!(conf.rules[1].prefix(), "big/");
+        let duplicate = format!(
+            "{}{}",
+            no_action.replace("</Rule>", "<Expiration><Days>1</Days></Expiration></Rule>"),
+            no_action
+        );
+        assert!(LifecycleConfiguration::parse(&rules(&duplicate)).is_err());
+
+        let now: DateTime<Utc> = "2024-06-10T00:00:00Z".parse().unwrap();
+        let day = Duration::days(1);
+        let tmp = [Tag {
+            key: "tier".to_string(),
+            value: "tmp".to_string(),
+        }];
+        let logs = &conf.rules[0];
+        assert!(logs.expires("logs/a", &tmp, now - Duration::days(7), now, day));
+        assert!(!logs.expires("logs/a", &tmp, now - Duration::days(6), now, day));
+        assert!(!logs.expires("logs/a", &[], now - Duration::days(30), now, day));
+        assert!(!logs.expires("data/a", &tmp, now - Duration::days(30), now, day));
+        // 一天按 60 秒计算
+        assert!(logs.expires(
+            "logs/a",
+            &tmp,
+            now - Duration::minutes(7),
+            now,
+            Duration::seconds(60)
+        ));
+
+        let uploads = &conf.rules[1];
+        assert!(uploads.aborts("big/a", now - Duration::days(2), now, day));
+        assert!(!uploads.aborts("big/a", now - Duration::days(1), now, day));
+        assert!(!uploads.aborts("small/a", now - Duration::days(9), now, day));
+        assert!(!uploads.expires("big/a", &[], now - Duration::days(9), now, day));
+        // 停用的规则不生效
+        assert!(!conf.rules[2].expires("any", &[], now, now, day));
+    }
+
+    #[test]
+    fn test2() {
+        let upload_id = "6f1c1e5e-3c8f-4a5a-9d2e-0e4b8e1f2a3b";
+        let name = format!("dir/a.txt.meta.{}", upload_id);
+        assert_eq!(
+            split_upload_meta_name(&name),
+            Some(("dir/a.txt", upload_id))
+        );
+        assert_eq!(split_upload_meta_name("dir/a.txt.meta"), None);
+        assert_eq!(split_upload_meta_name("a.meta.v1"), None);
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod test {
+    use ntex::http::header::{HeaderName, HeaderValue};
+    use ntex::http::HeaderMap;
+    use rs_s3_local::config::RequestLimits;
+    use rs_s3_local::limits::{check, Violation};
+
+    fn headers(n: usize, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for i in 0..n {
+            headers.insert(
+                HeaderName::try_from(format!("x-amz-meta-h{}", i)).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test1() {
+        let limits = RequestLimits::default();
+        assert_eq!(check(&limits, &headers(10, "v"), "/api/b/k", ""), None);
+        assert_eq!(
+            check(&limits, &headers(91, "v"), "/api/b/k", ""),
+            Some(Violation::HeaderCount)
+        );
+        assert_eq!(
+            check(&limits, &headers(3, &"v".repeat(3000)), "/api/b/k", ""),
+            Some(Violation::HeaderSize)
+        );
+        let query = format!("a={}", "x".repeat(8190));
+        assert_eq!(
+            check(&limits, &headers(1, "v"), "/api/b/k", &query),
+            Some(Violation::UrlLength)
+        );
+    }
+
+    #[test]
+    fn test2() {
+        let limits = RequestLimits::default();
+        let key = "k".repeat(1024);
+        let path = format!("/api/bucket/{}", key);
+        assert_eq!(check(&limits, &HeaderMap::new(), &path, ""), None);
+        // 按解码后的字节数计算
+        let path = format!("/api/bucket/{}%2F", key);
+        assert_eq!(
+            check(&limits, &HeaderMap::new(), &path, ""),
+            Some(Violation::KeyLength)
+        );
+        let path = format!("/admin/{}", "k".repeat(2000));
+        assert_eq!(check(&limits, &HeaderMap::new(), &path, ""), None);
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::listing::{decode_token, encode_token, filtered, paginate};
+
+    fn keys() -> std::iter::Peekable<std::vec::IntoIter<String>> {
+        [
+            "a.txt",
+            "docs/a.md",
+            "docs/img/1.png",
+            "docs/img/2.png",
+            "docs/z.md",
+            "z.txt",
+        ]
+        .iter()
+        .map(|k| k.to_string())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .peekable()
+    }
+
+    #[test]
+    fn test1() {
+        let page = paginate(&mut keys(), "", Some("/"), None, 1000);
+        assert_eq!(page.keys, vec!["a.txt", "z.txt"]);
+        assert_eq!(page.common_prefixes, vec!["docs/"]);
+        assert!(!page.is_truncated);
+        let page = paginate(&mut keys(), "docs/", Some("/"), None, 1000);
+        assert_eq!(page.keys, vec!["docs/a.md", "docs/z.md"]);
+        assert_eq!(page.common_prefixes, vec!["docs/img/"]);
+    }
+
+    #[test]
+    fn test2() {
+        let page = paginate(&mut keys(), "", Some("/"), None, 2);
+        assert_eq!(page.keys, vec!["a.txt"]);
+        assert_eq!(page.common_prefixes, vec!["docs/"]);
+        assert!(page.is_truncated);
+        assert_eq!(page.next_marker.as_deref(), Some("docs/"));
+        let token = encode_token(page.next_marker.as_deref().unwrap());
+        let after = decode_token(&token).unwrap();
+        let page = paginate(&mut keys(), "", Some("/"), Some(&after), 2);
+        assert_eq!(page.keys, vec!["z.txt"]);
+        assert!(page.common_prefixes.is_empty());
+        assert!(!page.is_truncated);
+        let page = paginate(&mut keys(), "docs/", None, Some("docs/img/1.png"), 1000);
+        assert_eq!(page.keys, vec!["docs/img/2.png", "docs/z.md"]);
+    }
+
+    #[test]
+    fn test3() {
+        let png = |key: &str| key.ends_with(".png");
+        let page = paginate(&mut filtered(keys(), png), "", Some("/"), None, 1000);
+        assert!(page.keys.is_empty());
+        assert_eq!(page.common_prefixes, vec!["docs/"]);
+        let md = |key: &str| key.ends_with(".md");
+        let page = paginate(&mut filtered(keys(), md), "docs/", Some("/"), None, 1);
+        assert_eq!(page.keys, vec!["docs/a.md"]);
+        assert!(page.common_prefixes.is_empty());
+        assert!(page.is_truncated);
+        let page = paginate(&mut filtered(keys(), |_: &str| false), "", None, None, 1000);
+        assert_eq!(page, Default::default());
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use log::Level;
+    use rs_s3_local::logging::{format_line, LogFormat, LogRotation};
+
+    #[test]
+    fn test1() {
+        let time = Utc.with_ymd_and_hms(2024, 3, 1, 8, 30, 0).unwrap();
+        assert_eq!(
+            format_line(LogFormat::Plain, time, Level::Info, "audit", "key rotated"),
+            "[2024-03-01T08:30:00.000Z INFO  audit] key rotated"
+        );
+        assert_eq!(
+            format_line(LogFormat::Logfmt, time, Level::Warn, "s3", "say \"hi\""),
+            "time=2024-03-01T08:30:00.000Z level=warn target=s3 msg=\"say \\\"hi\\\"\""
+        );
+        let json: serde_json::Value = serde_json::from_str(&format_line(
+            LogFormat::Json,
+            time,
+            Level::Error,
+            "s3",
+            "a\nb",
+        ))
+        .unwrap();
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["msg"], "a\nb");
+        assert_eq!(json["time"], "2024-03-01T08:30:00.000Z");
+        let later = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        assert_ne!(
+            LogRotation::Hourly.period(time),
+            LogRotation::Hourly.period(later)
+        );
+        assert_eq!(
+            LogRotation::Daily.period(time),
+            LogRotation::Daily.period(later)
+        );
+        assert_eq!(LogRotation::Never.period(time), None);
+    }
+}
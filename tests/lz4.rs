@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::lz4::{compress, decompress};
+
+    #[test]
+    fn test1() {
+        // 字面量 "a"、偏移 1 长度 14 的重叠匹配，最后 5 个字面量
+        let block = [0x1A, b'a', 1, 0, 0x50, b'a', b'a', b'a', b'a', b'a'];
+        assert_eq!(decompress(&block, 20).unwrap(), b"a".repeat(20));
+        assert!(decompress(&block, 19).is_err());
+        assert!(decompress(&block[..3], 20).is_err());
+        // 偏移超出已解压的内容
+        assert!(decompress(&[0x10, b'a', 2, 0, 0x00], 6).is_err());
+    }
+
+    #[test]
+    fn test2() {
+        let mut state = 0x9e37_79b9u32;
+        let noise: Vec<u8> = (0..70_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let text = b"lz4 keeps repeating itself, ".repeat(5000);
+        let long_literals = [&noise[..300], &text[..], &noise[..]].concat();
+        for data in [&b""[..], b"short", &text, &noise, &long_literals] {
+            let compressed = compress(data);
+            assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+        }
+        assert!(compress(&text).len() < text.len() / 10);
+    }
+
+    #[test]
+    fn test3() {
+        // lz4 1.9.4 命令行（lz4 -1 -B4）压缩得到的块，包含超过 15 字节的字面量和匹配
+        let block = hex::decode(concat!(
+            "ff1e54686520717569636b2062726f776e20666f78206a756d7073206f76657220746865206c",
+            "617a7920646f672e202d00ffddf615303132333435363738396162636465666768696a6b6c6d",
+            "6e6f707172737475767778797a1d020b0a0050646f672e20",
+        ))
+        .unwrap();
+        let data = [
+            &b"The quick brown fox jumps over the lazy dog. ".repeat(12)[..],
+            b"0123456789abcdefghijklmnopqrstuvwxyz",
+            &b"lazy dog. ".repeat(3),
+        ]
+        .concat();
+        assert_eq!(decompress(&block, data.len()).unwrap(), data);
+        assert_eq!(decompress(&compress(&data), data.len()).unwrap(), data);
+    }
+}
@@ -1,6 +1,53 @@
 #![allow(clippy::uninlined_format_args)]
 
+mod access;
+mod admin;
 mod api;
+mod archive;
+mod bench;
+mod capacity;
+mod cdc;
+mod checksum;
+mod chunkstats;
+mod cluster;
+mod codec;
+mod compat;
+mod compression;
+mod conditional;
+mod config;
+mod copy;
 mod crypto;
 mod date;
+mod dns;
+mod erasure;
+mod etag;
 mod fs;
+mod gc;
+mod idempotency;
+mod identity;
+mod jwt;
+mod kms;
+mod lifecycle;
+mod limits;
+mod listing;
+mod logging;
+mod lz4;
+mod middleware;
+mod multipart;
+mod policy;
+mod presign;
+mod range;
+mod recompress;
+mod rollback;
+mod scan;
+mod scrub;
+mod slowlog;
+mod snapshot;
+mod sse;
+mod startup;
+mod statsd;
+mod tagging;
+mod tls;
+mod versioning;
+mod vhost;
+mod website;
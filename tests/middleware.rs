@@ -0,0 +1,228 @@
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+    use ntex::http::{Method, StatusCode};
+    use ntex::web::{self, test, App, HttpResponse};
+    use rs_s3_local::identity::{Identity, Operation, Permissions};
+    use rs_s3_local::middleware::{
+        check_signing_time, decode_api_path, is_valid_api_path, required_permissions,
+        CredentialsV4, SignatureError,
+    };
+
+    #[test]
+    fn test1() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            check_signing_time("20240501T115500Z", "20240501", None, now),
+            Ok(())
+        );
+        assert_eq!(
+            check_signing_time("20240501T113000Z", "20240501", None, now),
+            Err(SignatureError::RequestTimeTooSkewed)
+        );
+        assert_eq!(
+            check_signing_time("20240501T123000Z", "20240501", None, now),
+            Err(SignatureError::RequestTimeTooSkewed)
+        );
+        assert!(matches!(
+            check_signing_time("20240501T115500Z", "20240430", None, now),
+            Err(SignatureError::Malformed(_))
+        ));
+        assert!(matches!(
+            check_signing_time("2024-05-01", "20240501", None, now),
+            Err(SignatureError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test2() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let later = now + Duration::hours(2);
+        assert_eq!(
+            check_signing_time("20240501T110000Z", "20240501", Some(3600), now),
+            Ok(())
+        );
+        assert_eq!(
+            check_signing_time("20240501T110000Z", "20240501", Some(3600), later),
+            Err(SignatureError::Expired)
+        );
+        assert_eq!(
+            check_signing_time("20240501T130000Z", "20240501", Some(3600), now),
+            Err(SignatureError::NotYetValid)
+        );
+        assert!(matches!(
+            check_signing_time("20240501T110000Z", "20240501", Some(604801), now),
+            Err(SignatureError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(
+            decode_api_path("/api/b/a%20b.txt").as_deref(),
+            Some("b/a b.txt")
+        );
+        assert_eq!(decode_api_path("/admin/config"), None);
+        for path in ["", "b", "b/", "b/a.txt", "b/dir/", "b/a..b/.c"] {
+            assert!(is_valid_api_path(path), "{}", path);
+        }
+        for path in ["..", "b/../c/x", "b/./x", "b//x", "/b/x", "b/x/.."] {
+            assert!(!is_valid_api_path(path), "{}", path);
+        }
+    }
+
+    #[ntex::test]
+    async fn test4() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CredentialsV4::new("root".into(), "secret".into()))
+                .route(
+                    "/api/{path}*",
+                    web::get().to(|| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+        // 未签名的请求在判断公开前缀之前就因路径段被拒绝
+        for uri in [
+            "/api/site/../secret/x.txt",
+            "/api/site/%2e%2e/secret/x.txt",
+            "/api/site/./x.txt",
+        ] {
+            let req = test::TestRequest::with_uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[ntex::test]
+    async fn test5() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CredentialsV4::new("root".into(), "secret".into()))
+                .route("/api/{path}*", web::to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        // 签名的请求同样在按访问密钥的前缀范围判断之前被拒绝
+        let authorization =
+            "AWS4-HMAC-SHA256 Credential=scoped/20240501/us-east-1/s3/aws4_request, \
+                             SignedHeaders=host;x-amz-date, Signature=00";
+        for (method, uri) in [
+            (Method::GET, "/api/site/../secret/x.txt"),
+            (Method::PUT, "/api/site/a/%2E%2E/%2E%2E/secret/x.txt"),
+            (Method::DELETE, "/api/site/./x.txt"),
+        ] {
+            let req = test::TestRequest::with_uri(uri)
+                .method(method)
+                .header("Authorization", authorization)
+                .header("x-amz-date", "20240501T120000Z")
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[ntex::test]
+    async fn test6() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CredentialsV4::new("root".into(), "secret".into()))
+                .route("/api/{path}*", web::to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        // 桶的操作限制不能借其他桶的路径绕过
+        for (method, uri) in [
+            (Method::DELETE, "/api/open/../locked/x.txt"),
+            (Method::PUT, "/api/open/%2E%2E/locked/x.txt"),
+            (Method::POST, "/api/open/..?delete"),
+            (Method::GET, "/api/open/../locked/"),
+        ] {
+            let req = test::TestRequest::with_uri(uri).method(method).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    fn scoped(prefix: &str) -> Identity {
+        Identity {
+            access_key: "scoped".to_string(),
+            secret_key: "secret".to_string(),
+            previous_secret_key: None,
+            permissions: Permissions {
+                prefixes: vec![prefix.to_string()],
+                ..Default::default()
+            },
+            inherited: None,
+        }
+    }
+
+    fn allows(identity: &Identity, method: Method, uri: &str) -> bool {
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let api_path = decode_api_path(path);
+        required_permissions(&method, path, api_path.as_deref(), query)
+            .iter()
+            .all(|(operation, object_path)| {
+                identity.allows(*operation, object_path.as_deref(), false)
+            })
+    }
+
+    #[test]
+    fn test7() {
+        let identity = scoped("bkt/tmp/");
+        // prefix 参数只在列出对象时作为键前缀
+        for (method, uri) in [
+            (Method::GET, "/api/bkt?prefix=tmp/"),
+            (Method::GET, "/api/bkt?list-type=2&prefix=tmp/a"),
+            (Method::GET, "/api/bkt/tmp/x.txt"),
+            (Method::PUT, "/api/bkt/tmp/x.txt"),
+            (Method::POST, "/api/bkt?extract&prefix=tmp/"),
+            (Method::POST, "/api/bkt?rename&source=tmp/a&target=tmp/b"),
+        ] {
+            assert!(allows(&identity, method, uri), "{}", uri);
+        }
+        // 写入桶配置、删除桶等桶级写操作需要整个桶的权限，不能借 prefix 参数绕过
+        for (method, uri) in [
+            (Method::PUT, "/api/bkt?policy&prefix=tmp/"),
+            (Method::PUT, "/api/bkt?lifecycle&prefix=tmp/"),
+            (Method::PUT, "/api/bkt?versioning&prefix=tmp/"),
+            (Method::GET, "/api/bkt?policy&prefix=tmp/"),
+            (Method::DELETE, "/api/bkt?prefix=tmp/"),
+            (Method::POST, "/api/bkt?commit=1&prefix=tmp/"),
+            (Method::GET, "/api/bkt"),
+            (
+                Method::POST,
+                "/api/bkt?rename&source=other/a&target=tmp/a&prefix=tmp/",
+            ),
+            (
+                Method::POST,
+                "/api/bkt?rename&source=tmp/a&target=other/a&prefix=tmp/",
+            ),
+            (Method::POST, "/api/bkt?rename&prefix=tmp/"),
+        ] {
+            assert!(!allows(&identity, method, uri), "{}", uri);
+        }
+        let whole = scoped("bkt");
+        for (method, uri) in [
+            (Method::PUT, "/api/bkt?policy"),
+            (Method::DELETE, "/api/bkt"),
+            (Method::POST, "/api/bkt?rename&source=a&target=b"),
+        ] {
+            assert!(allows(&whole, method, uri), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test8() {
+        // 批量删除的键在请求体中，中间件只校验操作类别，由处理函数逐个校验键
+        let path = "/api/bkt";
+        assert_eq!(
+            required_permissions(&Method::POST, path, Some("bkt"), "delete&prefix=tmp/"),
+            vec![(Operation::Delete, None)]
+        );
+        let identity = scoped("bkt/tmp/");
+        assert!(identity.allows(Operation::Delete, Some("bkt/tmp/a"), false));
+        assert!(!identity.allows(Operation::Delete, Some("bkt/other/a"), false));
+        let mut read_only = scoped("bkt");
+        read_only.permissions.read_only = true;
+        assert!(!allows(&read_only, Method::POST, "/api/bkt?delete"));
+    }
+}
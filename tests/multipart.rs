@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use rs_s3_local::model::PartETag;
+    use rs_s3_local::multipart::{
+        assemble, check_completion, format_part_record, normalize_etag, page_parts,
+        parse_part_chunks, parse_part_number, parse_part_record, CompletionError, UploadedPart,
+    };
+    use std::collections::BTreeMap;
+
+    fn uploaded(numbers: &[u32]) -> BTreeMap<u32, UploadedPart> {
+        numbers
+            .iter()
+            .map(|&n| {
+                let part = UploadedPart {
+                    part_number: n,
+                    hash: format!("HASH{}", n),
+                    etag: format!("HASH{}", n),
+                    size: 5 << 20,
+                    last_modified: Utc::now(),
+                    chunks: vec![(format!("HASH{}", n), 5 << 20)],
+                };
+                (n, part)
+            })
+            .collect()
+    }
+
+    fn requested(parts: &[(i32, &str)]) -> Vec<PartETag> {
+        parts
+            .iter()
+            .map(|(n, etag)| PartETag {
+                part_number: *n,
+                etag: normalize_etag(etag),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test1() {
+        assert_eq!(parse_part_number("1"), Some(1));
+        assert_eq!(parse_part_number("10000"), Some(10000));
+        assert_eq!(parse_part_number("0"), None);
+        assert_eq!(parse_part_number("../1"), None);
+        assert_eq!(
+            parse_part_record("4\nABCD\n"),
+            Some((4, "ABCD".to_string(), "ABCD".to_string()))
+        );
+        assert_eq!(
+            parse_part_record("4\nABCD\nabcd"),
+            Some((4, "ABCD".to_string(), "abcd".to_string()))
+        );
+        assert_eq!(parse_part_record("4"), None);
+        assert_eq!(normalize_etag("\"abcd\""), "ABCD");
+    }
+
+    #[test]
+    fn test2() {
+        let uploaded = uploaded(&[1, 2, 3]);
+        assert_eq!(
+            check_completion(&requested(&[(1, "\"hash1\""), (3, "HASH3")]), &uploaded),
+            Ok(())
+        );
+        assert_eq!(
+            check_completion(&requested(&[(2, "HASH2"), (1, "HASH1")]), &uploaded),
+            Err(CompletionError::InvalidPartOrder)
+        );
+        assert_eq!(
+            check_completion(&requested(&[(1, "HASH1"), (2, "HASH3")]), &uploaded),
+            Err(CompletionError::InvalidPart(2))
+        );
+        assert_eq!(
+            check_completion(&requested(&[(4, "HASH4")]), &uploaded),
+            Err(CompletionError::InvalidPart(4))
+        );
+        assert_eq!(
+            check_completion(&[], &uploaded),
+            Err(CompletionError::NoParts)
+        );
+    }
+
+    #[test]
+    fn test3() {
+        let uploaded = uploaded(&[1, 2, 5]);
+        let (page, truncated) = page_parts(&uploaded, 0, 2);
+        assert_eq!(
+            page.iter().map(|p| p.part_number).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(truncated);
+        let (page, truncated) = page_parts(&uploaded, 2, 2);
+        assert_eq!(page.iter().map(|p| p.part_number).collect::<Vec<_>>(), [5]);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test4() {
+        let record = format_part_record(4, "ABCD", "abcd", &[("ABCD".to_string(), 4)]);
+        assert_eq!(record, "4\nABCD\nabcd");
+        assert_eq!(
+            parse_part_chunks(&record),
+            Some(vec![("ABCD".to_string(), 4)])
+        );
+        let chunks = vec![("C1".to_string(), 3), ("C2".to_string(), 1)];
+        let record = format_part_record(4, "ABCD", "abcd", &chunks);
+        assert_eq!(
+            parse_part_record(&record),
+            Some((4, "ABCD".to_string(), "abcd".to_string()))
+        );
+        assert_eq!(parse_part_chunks(&record), Some(chunks.clone()));
+        assert_eq!(parse_part_chunks("4\nABCD\nabcd\nC1:x"), None);
+
+        let mut uploaded = uploaded(&[1, 2]);
+        let part = uploaded.get_mut(&2).unwrap();
+        part.size = 4;
+        part.chunks = chunks;
+        let assembled = assemble(&requested(&[(1, "HASH1"), (2, "HASH2")]), &uploaded);
+        assert_eq!(assembled.chunks, ["HASH1", "C1", "C2"]);
+        assert_eq!(assembled.chunk_sizes, [5 << 20, 3, 1]);
+        assert_eq!(assembled.chunk_parts, [1, 2, 2]);
+        assert_eq!(assembled.chunk_offsets, [0, 0, 3]);
+        assert_eq!(assembled.part_hashes, ["HASH1", "HASH2"]);
+        assert_eq!(assembled.size, (5 << 20) + 4);
+    }
+}
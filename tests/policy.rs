@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod test {
+    use ntex::http::{Method, StatusCode};
+    use ntex::web::{self, test, App, HttpResponse};
+    use rs_s3_local::middleware::CredentialsV4;
+    use rs_s3_local::policy::{action_of, wildcard_match, BucketPolicy, Decision, Resource};
+
+    #[test]
+    fn test1() {
+        assert!(wildcard_match("photos/*", "photos/a/b.jpg"));
+        assert!(wildcard_match("photos/*.jpg", "photos/a/b.jpg"));
+        assert!(wildcard_match("photos/?.jpg", "photos/a.jpg"));
+        assert!(!wildcard_match("photos/?.jpg", "photos/ab.jpg"));
+        assert!(!wildcard_match("photos/*", "photos"));
+        assert!(wildcard_match("*", ""));
+
+        assert!(BucketPolicy::parse("{}", "photos").is_err());
+        assert!(BucketPolicy::parse(r#"{"Statement": []}"#, "photos").is_err());
+        let statement = |principal: &str, action: &str, resource: &str| {
+            format!(
+                r#"{{"Statement": {{"Effect": "Allow", "Principal": {}, "Action": "{}", "Resource": "{}"}}}}"#,
+                principal, action, resource
+            )
+        };
+        let ok = statement(r#""*""#, "s3:GetObject", "arn:aws:s3:::photos/*");
+        assert!(BucketPolicy::parse(&ok, "photos").is_ok());
+        let outside = statement(r#""*""#, "s3:GetObject", "arn:aws:s3:::photos2/*");
+        assert!(BucketPolicy::parse(&outside, "photos").is_err());
+        let principal = statement(r#""alice""#, "s3:GetObject", "arn:aws:s3:::photos");
+        assert!(BucketPolicy::parse(&principal, "photos").is_err());
+        let action = statement(r#""*""#, "iam:PassRole", "arn:aws:s3:::photos");
+        assert!(BucketPolicy::parse(&action, "photos").is_err());
+        let condition = r#"{"Statement": {"Effect": "Allow", "Principal": "*", "Action": "*",
+            "Resource": "arn:aws:s3:::photos", "Condition": {}}}"#;
+        assert!(BucketPolicy::parse(condition, "photos").is_err());
+    }
+
+    #[test]
+    fn test2() {
+        let json = r#"{
+            "Version": "2012-10-17",
+            "Statement": [
+                {"Effect": "Allow", "Principal": {"AWS": ["alice", "bob"]},
+                 "Action": ["s3:GetObject", "s3:PutObject"], "Resource": "arn:aws:s3:::photos/shared/*"},
+                {"Effect": "Deny", "Principal": {"AWS": "*"},
+                 "Action": "s3:*", "Resource": "arn:aws:s3:::photos/shared/private/*"},
+                {"Effect": "Allow", "Principal": "*", "Action": "s3:ListBucket", "Resource": "arn:aws:s3:::photos"}
+            ]
+        }"#;
+        let policy = BucketPolicy::parse(json, "photos").unwrap();
+        let object = |key| Resource::Object("photos", key);
+        assert_eq!(
+            policy.evaluate(Some("alice"), "s3:PutObject", object("shared/a.jpg")),
+            Decision::Allow
+        );
+        assert_eq!(
+            policy.evaluate(Some("carol"), "s3:PutObject", object("shared/a.jpg")),
+            Decision::NoMatch
+        );
+        assert_eq!(
+            policy.evaluate(
+                Some("alice"),
+                "s3:GetObject",
+                object("shared/private/a.jpg")
+            ),
+            Decision::Deny
+        );
+        assert_eq!(
+            policy.evaluate(Some("alice"), "s3:DeleteObject", object("shared/a.jpg")),
+            Decision::NoMatch
+        );
+        assert_eq!(
+            policy.evaluate(None, "s3:ListBucket", Resource::Bucket("photos")),
+            Decision::Allow
+        );
+        // 批量删除：对象级 Deny 总是拒绝，Allow 需覆盖整个桶
+        assert_eq!(
+            policy.evaluate(
+                Some("alice"),
+                "s3:DeleteObject",
+                Resource::AnyObject("photos")
+            ),
+            Decision::Deny
+        );
+        let prefix_only = r#"{"Statement": {"Effect": "Allow", "Principal": "*",
+            "Action": "s3:DeleteObject", "Resource": "arn:aws:s3:::photos/shared/*"}}"#;
+        let policy = BucketPolicy::parse(prefix_only, "photos").unwrap();
+        assert_eq!(
+            policy.evaluate(None, "s3:DeleteObject", Resource::AnyObject("photos")),
+            Decision::NoMatch
+        );
+
+        assert_eq!(
+            action_of("GET", "/api/photos/a.jpg", "", false),
+            "s3:GetObject"
+        );
+        assert_eq!(
+            action_of("PUT", "/api/photos/a.jpg", "", true),
+            "s3:PutObject"
+        );
+        assert_eq!(
+            action_of("GET", "/api/photos", "list-type=2", false),
+            "s3:ListBucket"
+        );
+        assert_eq!(
+            action_of("PUT", "/api/photos", "policy", false),
+            "s3:PutBucketPolicy"
+        );
+        assert_eq!(
+            action_of("POST", "/api/photos", "delete", false),
+            "s3:DeleteObject"
+        );
+    }
+
+    #[ntex::test]
+    async fn test3() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CredentialsV4::new("root".into(), "secret".into()))
+                .route("/api/{path}*", web::to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        // 不能借其他桶的路径绕过 secret 桶的 Deny，或把公开桶的 Allow 延伸到其他桶
+        for (method, uri) in [
+            (Method::DELETE, "/api/other/../secret/x.txt"),
+            (Method::GET, "/api/public/..%2Fprivate/x.txt"),
+        ] {
+            let req = test::TestRequest::with_uri(uri).method(method).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::range::{locate, parse, ByteRange, Unsatisfiable};
+
+    #[test]
+    fn test1() {
+        let range = |start, end| Ok(Some(ByteRange { start, end }));
+        assert_eq!(parse("bytes=0-9", 100), range(0, 9));
+        assert_eq!(parse("bytes=90-", 100), range(90, 99));
+        assert_eq!(parse("bytes=95-200", 100), range(95, 99));
+        assert_eq!(parse("bytes=-10", 100), range(90, 99));
+        assert_eq!(parse("bytes=-500", 100), range(0, 99));
+        assert_eq!(parse("bytes=100-", 100), Err(Unsatisfiable));
+        assert_eq!(parse("bytes=-0", 100), Err(Unsatisfiable));
+        assert_eq!(parse("bytes=0-1,5-6", 100), Ok(None));
+        assert_eq!(parse("bytes=9-1", 100), Ok(None));
+        assert_eq!(parse("items=0-9", 100), Ok(None));
+        assert_eq!(
+            range(90, 99).unwrap().unwrap().content_range(100),
+            "bytes 90-99/100"
+        );
+    }
+
+    #[test]
+    fn test2() {
+        let sizes = [8, 8, 4];
+        assert_eq!(locate(&sizes, 0), (0, 0));
+        assert_eq!(locate(&sizes, 8), (1, 0));
+        assert_eq!(locate(&sizes, 19), (2, 3));
+        assert_eq!(locate(&[], 19), (0, 19));
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::codec::{encode, Codec};
+    use rs_s3_local::recompress::{marked_level, marker, recompress, MARKER_LEN};
+
+    #[test]
+    fn test1() {
+        let data = b"cold chunk data ".repeat(4096);
+        let fast = zstd::stream::encode_all(&data[..], -5).unwrap();
+        assert_eq!(marked_level(&fast), None);
+        let out = recompress(&fast, &data, 19).unwrap();
+        assert!(out.len() < fast.len());
+        assert_eq!(marked_level(&out), Some(19));
+        // 标记是可跳过帧，不影响解压
+        assert_eq!(zstd::stream::decode_all(&out[..]).unwrap(), data);
+    }
+
+    #[test]
+    fn test2() {
+        // 压缩不了的数据保留原有内容，只追加标记
+        let data: Vec<u8> = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let stored = zstd::stream::encode_all(&data[..], 19).unwrap();
+        let out = recompress(&stored, &data, 19).unwrap();
+        assert_eq!(out.len(), stored.len() + MARKER_LEN);
+        assert_eq!(&out[..stored.len()], &stored[..]);
+        assert_eq!(zstd::stream::decode_all(&out[..]).unwrap(), data);
+        assert_eq!(marked_level(&marker(-3)), Some(-3));
+        assert_eq!(marked_level(&[0u8; 8]), None);
+    }
+
+    #[test]
+    fn test3() {
+        // lz4 分片总是改为带标记的 zstd 帧
+        let data = b"fast lz4 chunk ".repeat(4096);
+        let fast = encode(&data, Codec::Lz4).unwrap();
+        let out = recompress(&fast, &data, 19).unwrap();
+        assert_eq!(marked_level(&out), Some(19));
+        assert_eq!(zstd::stream::decode_all(&out[..]).unwrap(), data);
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::rollback::{check, inject, upload, FailPoint, WrittenFiles};
+    use std::path::Path;
+
+    #[test]
+    fn test1() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("existing");
+        let written = dir.path().join("written");
+        let missing = dir.path().join("missing");
+        std::fs::write(&existing, b"old").unwrap();
+
+        let mut files = WrittenFiles::new();
+        assert!(!files.track(&existing));
+        assert!(files.track(&written));
+        assert!(files.track(&missing));
+        assert_eq!(files.paths(), &[written.clone(), missing.clone()]);
+
+        // 只写入了一部分时失败，未写入的文件不影响回滚
+        std::fs::write(&written, b"partial").unwrap();
+        assert_eq!(files.rollback(), 1);
+        assert!(existing.exists());
+        assert!(!written.exists());
+    }
+
+    #[test]
+    fn test2() {
+        for point in [
+            FailPoint::ChunkWrite,
+            FailPoint::ChunkVerify,
+            FailPoint::Metadata,
+        ] {
+            inject(Some(point));
+            for step in [
+                FailPoint::ChunkWrite,
+                FailPoint::ChunkVerify,
+                FailPoint::Metadata,
+            ] {
+                assert_eq!(check(step).is_err(), step == point);
+            }
+        }
+        inject(None);
+        assert!(check(FailPoint::Metadata).is_ok());
+    }
+
+    // 数据目录下的所有文件
+    fn files_under(dir: &Path) -> Vec<std::path::PathBuf> {
+        let mut files = vec![];
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(files_under(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test3() {
+        let dir = tempfile::tempdir().unwrap();
+        let chunk_dir = dir.path().join("file");
+        let meta_file = dir.path().join("buckets/b/a.txt.meta");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let put = |key: &str, body: &[u8], point| {
+            inject(point);
+            let res = runtime.block_on(upload(dir.path(), key, body.to_vec()));
+            inject(None);
+            res
+        };
+
+        // 写分片或写元数据失败时，不留下本次写入的分片和元数据
+        for point in [FailPoint::ChunkWrite, FailPoint::Metadata] {
+            assert!(put("b/a.txt", b"first", Some(point)).is_err());
+            assert!(files_under(&chunk_dir).is_empty());
+            assert!(files_under(&dir.path().join("buckets")).is_empty());
+        }
+
+        put("b/a.txt", b"first", None).unwrap();
+        let chunks = files_under(&chunk_dir);
+        assert_eq!(chunks.len(), 1);
+        let meta = std::fs::read(&meta_file).unwrap();
+
+        // 覆盖失败时删除新分片，原有分片和元数据保持不变
+        assert!(put("b/a.txt", b"second", Some(FailPoint::Metadata)).is_err());
+        assert_eq!(files_under(&chunk_dir), chunks);
+        assert_eq!(std::fs::read(&meta_file).unwrap(), meta);
+
+        // 去重命中的分片不是本次写入的，失败时不删除
+        assert!(put("b/c.txt", b"first", Some(FailPoint::Metadata)).is_err());
+        assert_eq!(files_under(&chunk_dir), chunks);
+        assert!(!dir.path().join("buckets/b/c.txt.meta").exists());
+    }
+}
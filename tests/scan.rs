@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::fs::ScanVerdict;
+    use rs_s3_local::scan::{
+        command_signature, icap_request_head, parse_icap_response, parse_icap_url,
+    };
+
+    #[test]
+    fn test1() {
+        assert_eq!(
+            command_signature("stdin: Eicar-Test-Signature FOUND\n\n---- SCAN SUMMARY ----\n"),
+            Some("Eicar-Test-Signature".to_string())
+        );
+        assert_eq!(
+            command_signature("blocked: macro\n"),
+            Some("blocked: macro".to_string())
+        );
+        assert_eq!(command_signature(""), None);
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(
+            parse_icap_url("icap://127.0.0.1/avscan"),
+            Some(("127.0.0.1".to_string(), 1344, "/avscan".to_string()))
+        );
+        assert_eq!(
+            parse_icap_url("icap://av:11344"),
+            Some(("av".to_string(), 11344, "/".to_string()))
+        );
+        assert_eq!(parse_icap_url("http://av/avscan"), None);
+        let head = icap_request_head("av", 1344, "/avscan", 5);
+        assert!(head.starts_with("RESPMOD icap://av:1344/avscan ICAP/1.0\r\n"));
+        assert!(head.contains("Encapsulated: res-hdr=0, res-body=78\r\n"));
+    }
+
+    #[test]
+    fn test3() {
+        let (verdict, _) = parse_icap_response("ICAP/1.0 204 No Content\r\n\r\n").unwrap();
+        assert_eq!(verdict, ScanVerdict::Clean);
+        let (verdict, signature) = parse_icap_response(
+            "ICAP/1.0 200 OK\r\nX-Infection-Found: Type=0; Resolution=2; Threat=Eicar-Test-Signature;\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(verdict, ScanVerdict::Infected);
+        assert_eq!(signature.as_deref(), Some("Eicar-Test-Signature"));
+        let (verdict, _) = parse_icap_response(
+            "ICAP/1.0 200 OK\r\nEncapsulated: res-hdr=0, res-body=40\r\n\r\nHTTP/1.1 403 Forbidden\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(verdict, ScanVerdict::Infected);
+        assert!(parse_icap_response("ICAP/1.0 500 Server Error\r\n\r\n").is_err());
+    }
+}
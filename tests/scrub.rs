@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::scrub::is_intact_file;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test1() {
+        let data = b"hello scrub".repeat(100);
+        let hash = hex::encode_upper(Sha256::digest(&data));
+        let chunk = zstd::encode_all(&data[..], 3).unwrap();
+        assert!(is_intact_file(&hash, false, chunk.clone()));
+        // 解压后内容与哈希不一致
+        let other = zstd::encode_all(&b"other"[..], 3).unwrap();
+        assert!(!is_intact_file(&hash, false, other));
+        // 无法解压
+        let mut flipped = chunk.clone();
+        let last = flipped.len() / 2;
+        flipped[last] ^= 0x01;
+        assert!(!is_intact_file(&hash, false, flipped));
+        // 不是纠删码块
+        assert!(!is_intact_file(&hash, true, chunk));
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::slowlog::{format_entry, server_timing, Phase, SlowRequest, Timings};
+    use std::time::Duration;
+
+    #[test]
+    fn test1() {
+        let mut timings = Timings::default();
+        timings.add(Phase::Hash, Duration::from_millis(3));
+        let mut applied = Timings::default();
+        applied.add(Phase::Hash, Duration::from_millis(4));
+        applied.add(Phase::Disk, Duration::from_millis(120));
+        timings.merge(&applied);
+        assert_eq!(timings.get(Phase::Hash), Duration::from_millis(7));
+        let line = format_entry(&SlowRequest {
+            method: "PUT",
+            path: "/api/b/k.bin",
+            api: Some("PutObject"),
+            status: 200,
+            duration: Duration::from_millis(1500),
+            request_bytes: Some(1024),
+            response_bytes: 0,
+            timings: &timings,
+        });
+        assert_eq!(
+            line,
+            "method=PUT path=/api/b/k.bin api=PutObject status=200 duration_ms=1500 \
+             request_bytes=1024 response_bytes=0 hash_ms=7 compress_ms=0 decompress_ms=0 \
+             disk_ms=120 raft_ms=0 auth_ms=0 metadata_ms=0"
+        );
+    }
+
+    #[test]
+    fn test2() {
+        let mut timings = Timings::default();
+        timings.add(Phase::Auth, Duration::from_micros(250));
+        timings.add(Phase::Metadata, Duration::from_micros(1500));
+        let mut applied = Timings::default();
+        applied.dedup_hits = 2;
+        applied.dedup_misses = 1;
+        applied.add(Phase::Raft, Duration::from_millis(3));
+        timings.merge(&applied);
+        assert_eq!((timings.dedup_hits, timings.dedup_misses), (2, 1));
+        assert_eq!(
+            server_timing(&timings, Duration::from_millis(5)),
+            "hash;dur=0.000, compress;dur=0.000, decompress;dur=0.000, disk;dur=0.000, \
+             raft;dur=3.000, auth;dur=0.250, metadata;dur=1.500, total;dur=5.000"
+        );
+    }
+}
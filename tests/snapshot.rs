@@ -0,0 +1,15 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::snapshot::is_valid_snapshot_name;
+
+    #[test]
+    fn test1() {
+        assert!(is_valid_snapshot_name("fixture-1"));
+        assert!(is_valid_snapshot_name("ci_2024.06.10"));
+        assert!(!is_valid_snapshot_name(""));
+        assert!(!is_valid_snapshot_name(".snapshot.json"));
+        assert!(!is_valid_snapshot_name("../other"));
+        assert!(!is_valid_snapshot_name("a/b"));
+        assert!(!is_valid_snapshot_name(&"a".repeat(65)));
+    }
+}
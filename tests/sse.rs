@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod test {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use ntex::http::header::{HeaderName, HeaderValue};
+    use ntex::http::HeaderMap;
+    use rs_s3_local::sse::{
+        copy_source_key, new_encryption, parse_request, read_cipher, CustomerKey, ObjectCipher,
+        SseRequest,
+    };
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                HeaderName::try_from(*name).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn customer_headers(prefix: &str, key: &str, md5: &str) -> HeaderMap {
+        let name = |suffix: &str| format!("{}server-side-encryption-customer-{}", prefix, suffix);
+        headers(&[
+            (&name("algorithm"), "AES256"),
+            (&name("key"), key),
+            (&name("key-MD5"), md5),
+        ])
+    }
+
+    #[test]
+    fn test1() {
+        assert!(matches!(parse_request(&headers(&[])), Ok(None)));
+        assert!(matches!(
+            parse_request(&headers(&[("x-amz-server-side-encryption", "AES256")])),
+            Ok(Some(SseRequest::S3))
+        ));
+        assert!(parse_request(&headers(&[("x-amz-server-side-encryption", "aws:kms")])).is_err());
+
+        let key = STANDARD.encode([7u8; 32]);
+        let md5 = STANDARD.encode(crypto_hash::digest(crypto_hash::Algorithm::MD5, &[7u8; 32]));
+        let sse_c = customer_headers("x-amz-", &key, &md5);
+        let Ok(Some(SseRequest::Customer(customer))) = parse_request(&sse_c) else {
+            panic!("SSE-C 请求头解析失败");
+        };
+        assert_eq!(customer.key, vec![7u8; 32]);
+        assert_eq!(customer.key_md5, md5);
+        // 同时指定 SSE-S3 和 SSE-C、MD5 不匹配、缺少请求头均无效
+        let mut both = sse_c.clone();
+        both.insert(
+            HeaderName::from_static("x-amz-server-side-encryption"),
+            HeaderValue::from_static("AES256"),
+        );
+        assert!(parse_request(&both).is_err());
+        let wrong_md5 = STANDARD.encode([0u8; 16]);
+        assert!(parse_request(&customer_headers("x-amz-", &key, &wrong_md5)).is_err());
+        let mut missing = sse_c.clone();
+        missing.remove("x-amz-server-side-encryption-customer-key-md5");
+        assert!(parse_request(&missing).is_err());
+
+        let copy = customer_headers("x-amz-copy-source-", &key, &md5);
+        assert!(matches!(copy_source_key(&copy), Ok(Some(_))));
+        assert!(matches!(copy_source_key(&sse_c), Ok(None)));
+    }
+
+    #[test]
+    fn test2() {
+        let customer = CustomerKey {
+            key: vec![9u8; 32],
+            key_md5: "md5".to_string(),
+        };
+        let encryption = new_encryption(&SseRequest::Customer(customer.clone()));
+        let cipher = ObjectCipher::new(&encryption, customer.key.clone());
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let sealed = cipher.apply(0, 0, &data).unwrap();
+        assert_eq!(sealed.len(), data.len());
+        assert_ne!(sealed, data);
+        // 从任意偏移开始解密与整体解密一致
+        assert_eq!(cipher.apply(0, 37, &sealed[37..]).unwrap(), &data[37..]);
+        assert_eq!(
+            cipher.apply_chunk(1, 500, &sealed[500..]).unwrap(),
+            &data[500..]
+        );
+        // 不同的段使用不同的密钥流
+        assert_ne!(cipher.apply(1, 0, &data).unwrap(), sealed);
+
+        assert!(read_cipher(Some(&encryption), Some(&customer)).is_ok());
+        let other = CustomerKey {
+            key: vec![8u8; 32],
+            ..customer
+        };
+        assert!(read_cipher(Some(&encryption), Some(&other)).is_err());
+        assert!(read_cipher(Some(&encryption), None).is_err());
+        assert!(read_cipher(None, Some(&other)).is_err());
+        assert!(matches!(read_cipher(None, None), Ok(None)));
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::statsd::{api_of, bucket_label, format_metric, pack_lines, MetricKind};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test1() {
+        let tags = [("operation", "read"), ("status", "2xx")];
+        assert_eq!(
+            format_metric("s3", "requests", 1, MetricKind::Counter, &tags, false),
+            "s3.requests.read.2xx:1|c"
+        );
+        assert_eq!(
+            format_metric("s3.", "request_time", 12, MetricKind::Timing, &tags, true),
+            "s3.request_time:12|ms|#operation:read,status:2xx"
+        );
+        assert_eq!(
+            format_metric("", "requests", 1, MetricKind::Counter, &[], true),
+            "requests:1|c"
+        );
+        let lines: Vec<String> = (0..100).map(|i| format!("s3.requests:{}|c", i)).collect();
+        let packets = pack_lines(&lines);
+        assert!(packets.len() > 1);
+        assert!(packets.iter().all(|p| p.len() <= 1432));
+        assert_eq!(packets.join("\n").split('\n').count(), 100);
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(api_of("GET", "/api/b/k.txt", "", false), Some("GetObject"));
+        assert_eq!(
+            api_of("GET", "/api/b", "list-type=2&prefix=a", false),
+            Some("ListObjectsV2")
+        );
+        assert_eq!(
+            api_of("PUT", "/api/b/k", "partNumber=1&uploadId=x", false),
+            Some("UploadPart")
+        );
+        assert_eq!(api_of("PUT", "/api/b/k", "", true), Some("CopyObject"));
+        assert_eq!(
+            api_of("POST", "/api/b/k", "uploads", false),
+            Some("CreateMultipartUpload")
+        );
+        assert_eq!(api_of("GET", "/api/", "", false), Some("ListBuckets"));
+        assert_eq!(api_of("GET", "/admin/buckets", "", false), None);
+    }
+
+    #[test]
+    fn test3() {
+        let mut seen = BTreeSet::new();
+        assert_eq!(bucket_label(&mut seen, "logs.2024", 2, true), "logs_2024");
+        assert_eq!(bucket_label(&mut seen, "missing", 2, false), "_other");
+        assert_eq!(bucket_label(&mut seen, "media", 2, true), "media");
+        assert_eq!(bucket_label(&mut seen, "third", 2, true), "_other");
+        assert_eq!(bucket_label(&mut seen, "logs.2024", 2, true), "logs_2024");
+    }
+}
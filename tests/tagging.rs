@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::fs::Tag;
+    use rs_s3_local::model::{Content, UserMetadata, UserMetadataEntry};
+    use rs_s3_local::tagging::{parse_header, parse_xml, tag_set, to_xml};
+
+    #[test]
+    fn test1() {
+        let tag = |key: &str, value: &str| Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            parse_header("env=dev&team=a%20b&empty=").unwrap(),
+            vec![tag("env", "dev"), tag("team", "a b"), tag("empty", "")]
+        );
+        assert!(parse_header("").unwrap().is_empty());
+        assert!(parse_header("a=1&a=2").is_err());
+        assert!(parse_header("=1").is_err());
+        assert!(parse_header(&"k".repeat(129)).is_err());
+        let many: Vec<String> = (0..11).map(|i| format!("k{}=v", i)).collect();
+        assert!(parse_header(&many.join("&")).is_err());
+
+        let xml = r#"<Tagging xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><TagSet>
+            <Tag><Key>env</Key><Value>dev</Value></Tag><Tag><Key>a&amp;b</Key><Value></Value></Tag>
+        </TagSet></Tagging>"#;
+        let tags = parse_xml(xml).unwrap();
+        assert_eq!(tags, vec![tag("env", "dev"), tag("a&b", "")]);
+        assert_eq!(parse_xml(&to_xml(&tags).unwrap()).unwrap(), tags);
+        assert!(parse_xml("<Tagging><TagSet></TagSet></Tagging>")
+            .unwrap()
+            .is_empty());
+        assert_eq!(parse_xml("not xml"), Err(None));
+        let duplicate = "<Tagging><TagSet><Tag><Key>a</Key><Value>1</Value></Tag>\
+            <Tag><Key>a</Key><Value>2</Value></Tag></TagSet></Tagging>";
+        assert!(matches!(parse_xml(duplicate), Err(Some(_))));
+    }
+
+    #[test]
+    fn test2() {
+        let tags = vec![Tag {
+            key: "env".to_string(),
+            value: "dev".to_string(),
+        }];
+        let content = Content {
+            key: "a.txt".to_string(),
+            last_modified: "2024-01-01T00:00:00Z".parse().unwrap(),
+            etag: "\"e\"".to_string(),
+            size: 1,
+            tag_count: Some(1),
+            user_metadata: Some(UserMetadata {
+                entries: vec![UserMetadataEntry {
+                    key: "owner".to_string(),
+                    value: "ops".to_string(),
+                }],
+            }),
+            tag_set: Some(tag_set(&tags)),
+        };
+        let xml = quick_xml::se::to_string_with_root("Contents", &content).unwrap();
+        assert!(xml.contains(
+            "<UserMetadata><Entry><Key>owner</Key><Value>ops</Value></Entry></UserMetadata>"
+        ));
+        assert!(xml.contains("<TagSet><Tag><Key>env</Key><Value>dev</Value></Tag></TagSet>"));
+        let plain = Content {
+            user_metadata: None,
+            tag_set: None,
+            ..content
+        };
+        let xml = quick_xml::se::to_string_with_root("Contents", &plain).unwrap();
+        assert!(!xml.contains("UserMetadata") && !xml.contains("TagSet"));
+    }
+}
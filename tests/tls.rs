@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod test {
+    use openssl::x509::X509Name;
+    use rs_s3_local::config::CertIdentity;
+    use rs_s3_local::tls::{access_key_for, subject_attributes, subject_string};
+
+    fn attrs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test1() {
+        let identity: CertIdentity = "cn=billing, O=Acme=billing-key".parse().unwrap();
+        assert_eq!(identity.subject, attrs(&[("CN", "billing"), ("O", "Acme")]));
+        assert_eq!(identity.access_key, "billing-key");
+        assert!("CN=billing".parse::<CertIdentity>().is_err());
+        assert!("CN=billing=".parse::<CertIdentity>().is_err());
+        assert!("billing=key".parse::<CertIdentity>().is_err());
+    }
+
+    #[test]
+    fn test2() {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_text("O", "Acme").unwrap();
+        name.append_entry_by_text("OU", "finance").unwrap();
+        name.append_entry_by_text("CN", "billing").unwrap();
+        let subject = subject_attributes(&name.build());
+        assert_eq!(subject_string(&subject), "O=Acme,OU=finance,CN=billing");
+
+        let identities: Vec<CertIdentity> =
+            ["CN=reports=reports-key", "CN=billing,O=Acme=billing-key"]
+                .iter()
+                .map(|s| s.parse().unwrap())
+                .collect();
+        assert_eq!(access_key_for(&identities, &subject), Some("billing-key"));
+        let other = attrs(&[("O", "Other"), ("CN", "billing")]);
+        assert_eq!(access_key_for(&identities, &other), None);
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::versioning::{parse_config, start_index, version_id, VersioningStatus};
+
+    #[test]
+    fn test1() {
+        assert_eq!(
+            parse_config(
+                "<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+            ),
+            Ok(VersioningStatus::Enabled)
+        );
+        assert_eq!(
+            parse_config(
+                "<VersioningConfiguration><Status>Suspended</Status></VersioningConfiguration>"
+            ),
+            Ok(VersioningStatus::Suspended)
+        );
+        assert!(parse_config("<VersioningConfiguration></VersioningConfiguration>").is_err());
+        assert!(parse_config(
+            "<VersioningConfiguration><Status>On</Status></VersioningConfiguration>"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test2() {
+        // 版本号按日志序号和日志内序号递增
+        assert_eq!(version_id(1, 0), "00000000000000010000");
+        assert!(version_id(9, 0) < version_id(10, 0));
+        assert!(version_id(10, 1) < version_id(11, 0));
+        assert!(version_id(15, 0) < version_id(15, 2));
+    }
+
+    #[test]
+    fn test3() {
+        let entries: Vec<(String, String)> = [("a", "v2"), ("a", "v1"), ("b", "v3"), ("c", "null")]
+            .iter()
+            .map(|(key, vid)| (key.to_string(), vid.to_string()))
+            .collect();
+        assert_eq!(start_index(&entries, None, None), 0);
+        assert_eq!(start_index(&entries, Some("a"), None), 2);
+        assert_eq!(start_index(&entries, Some("a"), Some("v2")), 1);
+        assert_eq!(start_index(&entries, Some("b"), Some("v3")), 3);
+        // 版本已被删除时从下一个键开始
+        assert_eq!(start_index(&entries, Some("a"), Some("v0")), 2);
+        assert_eq!(start_index(&entries, Some("c"), None), 4);
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::vhost::{bucket_of_host, rewrite_path};
+
+    #[test]
+    fn test1() {
+        let domain = "s3.localhost";
+        assert_eq!(
+            bucket_of_host("photos.s3.localhost:9000", domain),
+            Some("photos")
+        );
+        assert_eq!(
+            bucket_of_host("Photos.S3.Localhost.", domain),
+            Some("Photos")
+        );
+        assert_eq!(bucket_of_host("a.b.s3.localhost", domain), Some("a.b"));
+        assert_eq!(bucket_of_host("s3.localhost:9000", domain), None);
+        assert_eq!(bucket_of_host(".s3.localhost", domain), None);
+        assert_eq!(bucket_of_host("photoss3.localhost", domain), None);
+        assert_eq!(bucket_of_host("127.0.0.1:9000", domain), None);
+
+        assert_eq!(
+            rewrite_path("/api/dir/cat.jpg", "photos").as_deref(),
+            Some("/api/photos/dir/cat.jpg")
+        );
+        assert_eq!(
+            rewrite_path("/api/", "photos").as_deref(),
+            Some("/api/photos/")
+        );
+        assert_eq!(
+            rewrite_path("/api", "photos").as_deref(),
+            Some("/api/photos")
+        );
+        assert_eq!(rewrite_path("/apix/a", "photos"), None);
+        assert_eq!(rewrite_path("/admin/config", "photos"), None);
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod test {
+    use rs_s3_local::website::WebsiteConfiguration;
+
+    const CONFIG: &str = r#"<WebsiteConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+    <IndexDocument><Suffix>index.html</Suffix></IndexDocument>
+    <RoutingRules>
+        <RoutingRule>
+            <Condition><KeyPrefixEquals>docs/</KeyPrefixEquals></Condition>
+            <Redirect><ReplaceKeyPrefixWith>documents/</ReplaceKeyPrefixWith></Redirect>
+        </RoutingRule>
+        <RoutingRule>
+            <Condition><HttpErrorCodeReturnedEquals>404</HttpErrorCodeReturnedEquals></Condition>
+            <Redirect>
+                <HostName>example.com</HostName>
+                <Protocol>https</Protocol>
+                <HttpRedirectCode>302</HttpRedirectCode>
+                <ReplaceKeyWith>index.html</ReplaceKeyWith>
+            </Redirect>
+        </RoutingRule>
+    </RoutingRules>
+</WebsiteConfiguration>"#;
+
+    #[test]
+    fn test1() {
+        let conf: WebsiteConfiguration = quick_xml::de::from_str(CONFIG).unwrap();
+        assert!(conf.validate().is_ok());
+        assert_eq!(
+            conf.redirect_for("site", "docs/a.html", None),
+            Some((301, "/api/site/documents/a.html".to_string()))
+        );
+        assert_eq!(conf.redirect_for("site", "app/route", None), None);
+        assert_eq!(
+            conf.redirect_for("site", "app/route", Some(404)),
+            Some((302, "https://example.com/index.html".to_string()))
+        );
+    }
+
+    #[test]
+    fn test2() {
+        let conf: WebsiteConfiguration = quick_xml::de::from_str(
+            "<WebsiteConfiguration><RoutingRules><RoutingRule><Redirect><HttpRedirectCode>200</HttpRedirectCode></Redirect></RoutingRule></RoutingRules></WebsiteConfiguration>",
+        )
+        .unwrap();
+        assert!(conf.validate().is_err());
+    }
+}